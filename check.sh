@@ -0,0 +1,8 @@
+#!/bin/env sh
+set -e
+
+# The allocation counting replaces the global allocator on wasm, which the native tests never build
+cargo check -p crownfi-cw-common --target wasm32-unknown-unknown --features alloc-profiling
+
+# Makes sure the crate stays usable without the sei feature, see tests/chain_agnostic.rs
+cargo test -p crownfi-cw-common --no-default-features
@@ -0,0 +1,208 @@
+//! Allocation counting, for finding out how much the storage layer allocates.
+//!
+//! With the `alloc-profiling` feature enabled, wasm builds use a [CountingAllocator] as the global allocator, and
+//! contracts can expose the counts with [query_allocator_stats]. Native builds don't replace the global allocator, tests
+//! which want to measure allocations should install one themselves:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator<std::alloc::System> = CountingAllocator::new(std::alloc::System);
+//! ```
+//!
+//! The counts are per-thread, so tests running in parallel don't affect each other's counts.
+use std::{
+	alloc::{GlobalAlloc, Layout},
+	cell::Cell,
+};
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{to_json_binary, Binary, StdResult};
+
+thread_local! {
+	static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+	static DEALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+	static CURRENT_BYTES: Cell<u64> = const { Cell::new(0) };
+	static PEAK_BYTES: Cell<u64> = const { Cell::new(0) };
+}
+
+fn record_alloc(size: usize) {
+	// try_with as the thread-locals may already be gone while the thread is being torn down
+	let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+	let _ = CURRENT_BYTES.try_with(|current| {
+		let bytes = current.get() + size as u64;
+		current.set(bytes);
+		let _ = PEAK_BYTES.try_with(|peak| peak.set(peak.get().max(bytes)));
+	});
+}
+
+fn record_dealloc(size: usize) {
+	let _ = DEALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+	let _ = CURRENT_BYTES.try_with(|current| current.set(current.get().saturating_sub(size as u64)));
+}
+
+/// Wraps another allocator, counting the allocations made with it. Reallocations count as both an allocation and a
+/// deallocation.
+pub struct CountingAllocator<A> {
+	inner: A,
+}
+impl<A> CountingAllocator<A> {
+	pub const fn new(inner: A) -> Self {
+		Self { inner }
+	}
+}
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		let ptr = self.inner.alloc(layout);
+		if !ptr.is_null() {
+			record_alloc(layout.size());
+		}
+		ptr
+	}
+	unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+		let ptr = self.inner.alloc_zeroed(layout);
+		if !ptr.is_null() {
+			record_alloc(layout.size());
+		}
+		ptr
+	}
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		self.inner.dealloc(ptr, layout);
+		record_dealloc(layout.size());
+	}
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		let new_ptr = self.inner.realloc(ptr, layout, new_size);
+		if !new_ptr.is_null() {
+			record_dealloc(layout.size());
+			record_alloc(new_size);
+		}
+		new_ptr
+	}
+}
+
+#[cfg(target_arch = "wasm32")]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator<std::alloc::System> = CountingAllocator::new(std::alloc::System);
+
+/// Allocation counts since the start of the thread, or since [reset_allocator_stats] was last called.
+#[cw_serde]
+#[derive(Copy, Default, Eq)]
+pub struct AllocatorStats {
+	pub allocations: u64,
+	pub deallocations: u64,
+	/// The most bytes which were allocated at once.
+	pub peak_bytes: u64,
+}
+
+/// Returns the `(allocations, deallocations, peak bytes)` counted by the [CountingAllocator].
+pub fn allocator_stats() -> (u64, u64, u64) {
+	(
+		ALLOCATIONS.with(Cell::get),
+		DEALLOCATIONS.with(Cell::get),
+		PEAK_BYTES.with(Cell::get),
+	)
+}
+
+/// Resets the counts, the peak becomes the amount of bytes which are currently allocated.
+pub fn reset_allocator_stats() {
+	ALLOCATIONS.with(|count| count.set(0));
+	DEALLOCATIONS.with(|count| count.set(0));
+	PEAK_BYTES.with(|peak| peak.set(CURRENT_BYTES.with(Cell::get)));
+}
+
+/// Runs `f` and returns the allocations it made. This resets the counts.
+pub fn measure_allocations<T>(f: impl FnOnce() -> T) -> (T, AllocatorStats) {
+	reset_allocator_stats();
+	let result = f();
+	let (allocations, deallocations, peak_bytes) = allocator_stats();
+	(
+		result,
+		AllocatorStats {
+			allocations,
+			deallocations,
+			peak_bytes,
+		},
+	)
+}
+
+/// Returns the [AllocatorStats] as a query response, for contracts to add to their debug queries.
+pub fn query_allocator_stats() -> StdResult<Binary> {
+	let (allocations, deallocations, peak_bytes) = allocator_stats();
+	to_json_binary(&AllocatorStats {
+		allocations,
+		deallocations,
+		peak_bytes,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use bytemuck::{Pod, Zeroable};
+	use cosmwasm_std::{from_json, StdError};
+
+	use super::*;
+	use crate::{
+		impl_serializable_as_ref,
+		storage::{item::StoredItem, map::StoredMap, testing_common::*, SerializableItem},
+	};
+
+	#[global_allocator]
+	static ALLOCATOR: CountingAllocator<std::alloc::System> = CountingAllocator::new(std::alloc::System);
+
+	#[derive(Debug, Clone, Copy, PartialEq, Eq, Zeroable, Pod)]
+	#[repr(C)]
+	struct PodValue {
+		a: u64,
+		b: u64,
+	}
+	impl_serializable_as_ref!(PodValue);
+	impl StoredItem for PodValue {
+		fn namespace() -> &'static [u8] {
+			b"item"
+		}
+	}
+
+	#[test]
+	fn counts_allocations() {
+		let (_, stats) = measure_allocations(|| ());
+		assert_eq!(stats.allocations, 0);
+		let (vec, stats) = measure_allocations(|| vec![0u8; 1000]);
+		assert_eq!(stats.allocations, 1);
+		assert_eq!(stats.deallocations, 0);
+		assert!(stats.peak_bytes >= 1000);
+		let (_, stats) = measure_allocations(|| drop(vec));
+		assert_eq!((stats.allocations, stats.deallocations), (0, 1));
+
+		let response: AllocatorStats = from_json(query_allocator_stats().unwrap()).unwrap();
+		assert_eq!(response.deallocations, 1);
+	}
+
+	#[test]
+	fn storage_allocation_budgets() -> TestingResult {
+		let _storage = isolated()?;
+		let map = StoredMap::<u64, PodValue>::new(NAMESPACE);
+		let value = PodValue { a: 1, b: 2 };
+		map.set(&1, &value)?;
+		value.save()?;
+
+		let (result, stats) = measure_allocations(|| map.get(&1));
+		assert_eq!(result?.as_deref(), Some(&value));
+		assert!(stats.allocations <= 2, "StoredMap::get: {stats:?}");
+
+		let (result, stats) = measure_allocations(|| map.set(&2, &value));
+		result?;
+		assert!(stats.allocations <= 2, "StoredMap::set: {stats:?}");
+
+		let (result, stats) = measure_allocations(PodValue::load);
+		assert_eq!(result?.as_deref(), Some(&value));
+		assert!(stats.allocations <= 2, "StoredItem::load: {stats:?}");
+
+		let mut buf = Vec::with_capacity(64);
+		let (result, stats) = measure_allocations(|| map.get_with_buf(&1, &mut buf));
+		assert_eq!(result?.as_deref(), Some(&value));
+		assert!(stats.allocations <= 1, "StoredMap::get_with_buf: {stats:?}");
+
+		let (_, stats) = measure_allocations(|| map.remove(&2));
+		assert!(stats.allocations <= 1, "StoredMap::remove: {stats:?}");
+		Ok(())
+	}
+}
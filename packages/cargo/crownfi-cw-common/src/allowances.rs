@@ -0,0 +1,397 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Env, OverflowError, OverflowOperation, StdError, StdResult, Timestamp, Uint128};
+use cw20::Expiration;
+use thiserror::Error;
+
+use crate::{
+	data_types::{
+		canonical_addr::SeiCanonicalAddr,
+		responses::{paginate, Cursor, PageResponse},
+	},
+	storage::{
+		concat_byte_array_pairs,
+		map::{StoredMap, StoredMapIter},
+		set::StoredSet,
+		OZeroCopy, SerializableItem,
+	},
+	utils::lexicographic_next,
+};
+
+/// The most entries the allowance queries return at once, larger limits are lowered to this.
+pub const ALLOWANCE_PAGE_MAX_LIMIT: u32 = 100;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum AllowanceError {
+	#[error("No allowance was given to the spender")]
+	NoAllowance,
+	#[error("The allowance has expired")]
+	Expired,
+	#[error("Insufficient allowance, {available} is available but {requested} was requested")]
+	Insufficient { available: Uint128, requested: Uint128 },
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<AllowanceError> for StdError {
+	fn from(value: AllowanceError) -> Self {
+		match value {
+			AllowanceError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// How much a spender may spend on behalf of an owner, and until when.
+#[cw_serde]
+pub struct Allowance {
+	pub limit: Uint128,
+	pub expires: Expiration,
+}
+
+const ALLOWANCE_ITEM_LEN: usize = 16 + 1 + 8;
+
+/// Stored as the limit (u128 LE), a tag for the kind of expiration, and its height or time (u64 LE).
+impl SerializableItem for Allowance {
+	fn serialize_to_owned(&self) -> StdResult<Vec<u8>> {
+		let (tag, value) = match self.expires {
+			Expiration::Never {} => (0u8, 0u64),
+			Expiration::AtHeight(height) => (1, height),
+			Expiration::AtTime(time) => (2, time.nanos()),
+		};
+		let mut result = Vec::with_capacity(ALLOWANCE_ITEM_LEN);
+		result.extend_from_slice(&self.limit.u128().to_le_bytes());
+		result.push(tag);
+		result.extend_from_slice(&value.to_le_bytes());
+		Ok(result)
+	}
+	fn deserialize_to_owned(data: &[u8]) -> StdResult<Self> {
+		if data.len() != ALLOWANCE_ITEM_LEN {
+			return Err(StdError::invalid_data_size(ALLOWANCE_ITEM_LEN, data.len()));
+		}
+		let limit = u128::from_le_bytes(data[..16].try_into().unwrap()).into();
+		let value = u64::from_le_bytes(data[17..].try_into().unwrap());
+		let expires = match data[16] {
+			0 => Expiration::Never {},
+			1 => Expiration::AtHeight(value),
+			2 => Expiration::AtTime(Timestamp::from_nanos(value)),
+			tag => {
+				return Err(StdError::parse_err(
+					"Allowance",
+					format!("unknown expiration kind {tag}"),
+				))
+			}
+		};
+		Ok(Self { limit, expires })
+	}
+}
+
+/// cw20-style allowances, which let spenders spend an owner's balance up to a limit. What the balance is (e.g. an
+/// internal balance map of any asset) is up to the contract, this only keeps track of how much may be spent.
+///
+/// Allowances are keyed by `(owner, spender)`. A reverse index keyed by `(spender, owner)` is kept in a separate
+/// namespace, so that the allowances given to a spender can be listed as well. Allowances with a limit of 0 are
+/// removed rather than stored.
+pub struct AllowanceMap {
+	allowances: StoredMap<(SeiCanonicalAddr, SeiCanonicalAddr), Allowance>,
+	spender_index: StoredSet<(SeiCanonicalAddr, SeiCanonicalAddr)>,
+}
+
+impl AllowanceMap {
+	pub fn new(namespace: &'static [u8], spender_index_namespace: &'static [u8]) -> Self {
+		Self {
+			allowances: StoredMap::new(namespace),
+			spender_index: StoredSet::new(spender_index_namespace),
+		}
+	}
+
+	/// Returns the allowance, regardless of whether or not it has expired.
+	pub fn get(&self, owner: &SeiCanonicalAddr, spender: &SeiCanonicalAddr) -> StdResult<Option<Allowance>> {
+		Ok(self.allowances.get(&(*owner, *spender))?.map(OZeroCopy::into_inner))
+	}
+
+	/// Replaces the allowance, a limit of 0 removes it.
+	pub fn set_allowance(
+		&self,
+		owner: &SeiCanonicalAddr,
+		spender: &SeiCanonicalAddr,
+		allowance: &Allowance,
+	) -> StdResult<()> {
+		if allowance.limit.is_zero() {
+			self.remove_allowance(owner, spender);
+			return Ok(());
+		}
+		self.allowances.set(&(*owner, *spender), allowance)?;
+		self.spender_index.add(&(*spender, *owner))
+	}
+
+	pub fn remove_allowance(&self, owner: &SeiCanonicalAddr, spender: &SeiCanonicalAddr) {
+		self.allowances.remove(&(*owner, *spender));
+		self.spender_index.remove(&(*spender, *owner));
+	}
+
+	/// Adds to the allowance, erroring if the limit would overflow. The expiration is only changed if `expires` is
+	/// set, new allowances never expire otherwise.
+	pub fn increase_allowance(
+		&self,
+		owner: &SeiCanonicalAddr,
+		spender: &SeiCanonicalAddr,
+		amount: Uint128,
+		expires: Option<Expiration>,
+	) -> StdResult<Allowance> {
+		let mut allowance = self.get(owner, spender)?.unwrap_or(Allowance {
+			limit: Uint128::zero(),
+			expires: Expiration::Never {},
+		});
+		allowance.limit = allowance
+			.limit
+			.checked_add(amount)
+			.map_err(|_| OverflowError::new(OverflowOperation::Add, allowance.limit, amount))?;
+		if let Some(expires) = expires {
+			allowance.expires = expires;
+		}
+		self.set_allowance(owner, spender, &allowance)?;
+		Ok(allowance)
+	}
+
+	/// Subtracts from the allowance, the limit is clamped at 0 which removes it.
+	pub fn decrease_allowance(
+		&self,
+		owner: &SeiCanonicalAddr,
+		spender: &SeiCanonicalAddr,
+		amount: Uint128,
+		expires: Option<Expiration>,
+	) -> StdResult<Option<Allowance>> {
+		let Some(mut allowance) = self.get(owner, spender)? else {
+			return Ok(None);
+		};
+		allowance.limit = allowance.limit.saturating_sub(amount);
+		if let Some(expires) = expires {
+			allowance.expires = expires;
+		}
+		self.set_allowance(owner, spender, &allowance)?;
+		Ok(Some(allowance).filter(|allowance| !allowance.limit.is_zero()))
+	}
+
+	/// Deducts `amount` from the allowance, returning an error and leaving it unchanged if it has expired or isn't
+	/// large enough.
+	pub fn spend(
+		&self,
+		owner: &SeiCanonicalAddr,
+		spender: &SeiCanonicalAddr,
+		amount: Uint128,
+		env: &Env,
+	) -> Result<(), AllowanceError> {
+		let Some(mut allowance) = self.get(owner, spender)? else {
+			return Err(AllowanceError::NoAllowance);
+		};
+		if allowance.expires.is_expired(&env.block) {
+			return Err(AllowanceError::Expired);
+		}
+		allowance.limit = allowance
+			.limit
+			.checked_sub(amount)
+			.map_err(|_| AllowanceError::Insufficient {
+				available: allowance.limit,
+				requested: amount,
+			})?;
+		self.set_allowance(owner, spender, &allowance)?;
+		Ok(())
+	}
+
+	/// Lists the allowances the owner has given, by spender. Expired allowances are included.
+	pub fn allowances_by_owner(
+		&self,
+		owner: &SeiCanonicalAddr,
+		start_after: Option<Cursor>,
+		limit: u32,
+	) -> StdResult<PageResponse<(SeiCanonicalAddr, Allowance)>> {
+		let (start, end, prefix_len) = page_bounds(self.allowances.namespace(), owner, start_after)?;
+		let iter = StoredMapIter::<SeiCanonicalAddr, Allowance>::new_raw(&start, &end, prefix_len);
+		paginate_allowances(
+			iter.map(|(spender, allowance)| Ok((spender, allowance.into_inner()))),
+			limit,
+		)
+	}
+
+	/// Lists the allowances given to the spender, by owner. Expired allowances are included.
+	pub fn allowances_by_spender(
+		&self,
+		spender: &SeiCanonicalAddr,
+		start_after: Option<Cursor>,
+		limit: u32,
+	) -> StdResult<PageResponse<(SeiCanonicalAddr, Allowance)>> {
+		let (start, end, prefix_len) = page_bounds(self.spender_index.namespace(), spender, start_after)?;
+		let iter = StoredMapIter::<SeiCanonicalAddr, u8>::new_raw(&start, &end, prefix_len);
+		paginate_allowances(
+			iter.map(|(owner, _)| {
+				let allowance = self
+					.get(&owner, spender)?
+					.ok_or_else(|| StdError::not_found("Allowance in spender index"))?;
+				Ok((owner, allowance))
+			}),
+			limit,
+		)
+	}
+}
+
+/// Returns the storage key range of the entries whose key is `(addr, _)` and come after the cursor, along with the
+/// length of the key prefix.
+fn page_bounds(
+	namespace: &[u8],
+	addr: &SeiCanonicalAddr,
+	start_after: Option<Cursor>,
+) -> StdResult<(Vec<u8>, Vec<u8>, usize)> {
+	let prefix = concat_byte_array_pairs(namespace, &addr.storage_key());
+	let start = match start_after {
+		Some(cursor) => {
+			let start_after = SeiCanonicalAddr::try_from(cursor.0.as_slice())?;
+			// Appending a 0 results in the smallest storage key which comes after start_after's
+			[&prefix[..], &start_after.storage_key(), &[0]].concat()
+		}
+		None => prefix.clone(),
+	};
+	Ok((start, lexicographic_next(&prefix), prefix.len()))
+}
+
+fn paginate_allowances(
+	iter: impl Iterator<Item = StdResult<(SeiCanonicalAddr, Allowance)>>,
+	limit: u32,
+) -> StdResult<PageResponse<(SeiCanonicalAddr, Allowance)>> {
+	Ok(paginate(iter, limit, ALLOWANCE_PAGE_MAX_LIMIT, |(addr, _)| {
+		Cursor::from(addr.storage_key().to_vec())
+	})?
+	.into())
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::testing::mock_env;
+
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	fn allowances() -> AllowanceMap {
+		AllowanceMap::new(NAMESPACE, b"spenders")
+	}
+
+	fn addr(n: u8) -> SeiCanonicalAddr {
+		[n; 20].into()
+	}
+
+	fn never(limit: u128) -> Allowance {
+		Allowance {
+			limit: limit.into(),
+			expires: Expiration::Never {},
+		}
+	}
+
+	#[test]
+	fn spend_up_to_limit() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = allowances();
+		let env = mock_env();
+		let (owner, spender) = (addr(1), addr(2));
+		assert_eq!(
+			map.spend(&owner, &spender, Uint128::one(), &env),
+			Err(AllowanceError::NoAllowance)
+		);
+		map.set_allowance(&owner, &spender, &never(100))?;
+		map.spend(&owner, &spender, Uint128::new(60), &env)?;
+		assert_eq!(
+			map.spend(&owner, &spender, Uint128::new(41), &env),
+			Err(AllowanceError::Insufficient {
+				available: Uint128::new(40),
+				requested: Uint128::new(41)
+			})
+		);
+		map.spend(&owner, &spender, Uint128::new(40), &env)?;
+		assert_eq!(map.get(&owner, &spender)?, None);
+		assert_eq!(
+			map.spend(&owner, &spender, Uint128::one(), &env),
+			Err(AllowanceError::NoAllowance)
+		);
+		// Allowances are directional
+		map.set_allowance(&owner, &spender, &never(1))?;
+		assert!(map.spend(&spender, &owner, Uint128::one(), &env).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn expired_allowances() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = allowances();
+		let env = mock_env();
+		let (owner, spender) = (addr(1), addr(2));
+		map.increase_allowance(
+			&owner,
+			&spender,
+			Uint128::new(10),
+			Some(Expiration::AtHeight(env.block.height)),
+		)?;
+		assert_eq!(
+			map.spend(&owner, &spender, Uint128::one(), &env),
+			Err(AllowanceError::Expired)
+		);
+		let expires = Expiration::AtTime(env.block.time.plus_seconds(1));
+		map.increase_allowance(&owner, &spender, Uint128::new(5), Some(expires))?;
+		map.spend(&owner, &spender, Uint128::new(15), &env)?;
+		// The expiration is kept through storage
+		map.set_allowance(
+			&owner,
+			&spender,
+			&Allowance {
+				limit: Uint128::one(),
+				expires,
+			},
+		)?;
+		assert_eq!(map.get(&owner, &spender)?.unwrap().expires, expires);
+		Ok(())
+	}
+
+	#[test]
+	fn increase_and_decrease() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = allowances();
+		let (owner, spender) = (addr(1), addr(2));
+		assert_eq!(map.decrease_allowance(&owner, &spender, Uint128::one(), None)?, None);
+		map.increase_allowance(&owner, &spender, Uint128::new(10), None)?;
+		assert!(map.increase_allowance(&owner, &spender, Uint128::MAX, None).is_err());
+		assert_eq!(
+			map.decrease_allowance(&owner, &spender, Uint128::new(4), None)?,
+			Some(never(6))
+		);
+		assert_eq!(map.decrease_allowance(&owner, &spender, Uint128::new(7), None)?, None);
+		assert_eq!(map.get(&owner, &spender)?, None);
+		assert!(map.allowances_by_spender(&spender, None, 10)?.entries.is_empty());
+		Ok(())
+	}
+
+	#[test]
+	fn queries_by_owner_and_spender() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = allowances();
+		for owner in 1..=3 {
+			for spender in 4..=6 {
+				map.set_allowance(
+					&addr(owner),
+					&addr(spender),
+					&never(owner as u128 * 10 + spender as u128),
+				)?;
+			}
+		}
+		let page = map.allowances_by_owner(&addr(2), None, 2)?;
+		assert_eq!(page.entries, [(addr(4), never(24)), (addr(5), never(25))]);
+		let page = map.allowances_by_owner(&addr(2), page.next_cursor, 2)?;
+		assert_eq!(page.entries, [(addr(6), never(26))]);
+		assert_eq!(page.next_cursor, None);
+
+		map.remove_allowance(&addr(2), &addr(5));
+		map.decrease_allowance(&addr(3), &addr(5), Uint128::new(35), None)?;
+		let page = map.allowances_by_spender(&addr(5), None, 10)?;
+		assert_eq!(page.entries, [(addr(1), never(15))]);
+		let page = map.allowances_by_spender(&addr(4), None, 2)?;
+		assert_eq!(page.entries, [(addr(1), never(14)), (addr(2), never(24))]);
+		let page = map.allowances_by_spender(&addr(4), page.next_cursor, 2)?;
+		assert_eq!(page.entries, [(addr(3), never(34))]);
+		Ok(())
+	}
+}
@@ -0,0 +1,173 @@
+//! Typed clients for calling other contracts, see [contract_client](crate::contract_client).
+
+#[macro_export]
+/// Defines a client struct for another contract, from the variants of its execute and query messages.
+///
+/// ```ignore
+/// contract_client!(pub PoolClient {
+///     execute: PoolExecuteMsg {
+///         build_swap_ix => Swap { slippage_tolerance: Option<Decimal>, receiver: Option<Addr> },
+///         build_withdraw_ix => Withdraw {},
+///     },
+///     query: PoolQueryMsg {
+///         query_config => Config {} -> PoolConfigResponse,
+///         query_share_value => ShareValue { amount: Uint128 } -> ShareValueResponse,
+///     },
+/// });
+/// ```
+///
+/// `PoolClient::new(pool_addr)` then has a `build_swap_ix(&self, slippage_tolerance, receiver, funds)` method which
+/// returns the `WasmMsg::Execute` for `PoolExecuteMsg::Swap { .. }`, and a
+/// `query_share_value(&self, querier, amount)` method which returns the `ShareValueResponse`. Either section may be
+/// omitted, and only struct variants (as generated by `cw_serde`) are supported.
+macro_rules! contract_client {
+	(
+		$vis:vis $client:ident {
+			$(execute: $execute_msg:ident {
+				$($execute_method:ident => $execute_variant:ident { $($execute_arg:ident: $execute_arg_type:ty),* $(,)? }),*
+				$(,)?
+			} $(,)?)?
+			$(query: $query_msg:ident {
+				$($query_method:ident => $query_variant:ident { $($query_arg:ident: $query_arg_type:ty),* $(,)? }
+					-> $response:ty),*
+				$(,)?
+			} $(,)?)?
+		}
+	) => {
+		#[derive(Debug, Clone, PartialEq, Eq)]
+		$vis struct $client {
+			pub address: cosmwasm_std::Addr,
+		}
+		impl $client {
+			pub fn new(address: cosmwasm_std::Addr) -> Self {
+				Self { address }
+			}
+			$($(
+				pub fn $execute_method(
+					&self,
+					$($execute_arg: $execute_arg_type,)*
+					funds: Vec<cosmwasm_std::Coin>,
+				) -> cosmwasm_std::StdResult<cosmwasm_std::WasmMsg> {
+					Ok(cosmwasm_std::WasmMsg::Execute {
+						contract_addr: self.address.to_string(),
+						msg: cosmwasm_std::to_json_binary(&$execute_msg::$execute_variant { $($execute_arg),* })?,
+						funds,
+					})
+				}
+			)*)?
+			$($(
+				pub fn $query_method<C: cosmwasm_std::CustomQuery>(
+					&self,
+					querier: &cosmwasm_std::QuerierWrapper<C>,
+					$($query_arg: $query_arg_type),*
+				) -> cosmwasm_std::StdResult<$response> {
+					querier.query_wasm_smart(self.address.as_str(), &$query_msg::$query_variant { $($query_arg),* })
+				}
+			)*)?
+		}
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_schema::cw_serde;
+	use cosmwasm_std::{
+		coins, from_json, testing::MockQuerier, to_json_binary, Addr, ContractResult, Decimal, QuerierWrapper,
+		StdResult, SystemResult, Uint128, WasmMsg, WasmQuery,
+	};
+
+	#[cw_serde]
+	enum PoolExecuteMsg {
+		Swap {
+			slippage_tolerance: Option<Decimal>,
+			receiver: Option<Addr>,
+		},
+		Withdraw {},
+	}
+
+	#[cw_serde]
+	enum PoolQueryMsg {
+		Config {},
+		ShareValue { amount: Uint128 },
+	}
+
+	#[cw_serde]
+	struct PoolConfigResponse {
+		admin: Addr,
+		paused: bool,
+	}
+
+	contract_client!(PoolClient {
+		execute: PoolExecuteMsg {
+			build_swap_ix => Swap { slippage_tolerance: Option<Decimal>, receiver: Option<Addr> },
+			build_withdraw_ix => Withdraw {},
+		},
+		query: PoolQueryMsg {
+			query_config => Config {} -> PoolConfigResponse,
+			query_share_value => ShareValue { amount: Uint128 } -> Vec<Uint128>,
+		},
+	});
+
+	contract_client!(pub(crate) ExecuteOnlyClient {
+		execute: PoolExecuteMsg { build_withdraw_ix => Withdraw {} }
+	});
+
+	#[test]
+	fn execute_msgs_match_hand_written() -> StdResult<()> {
+		let client = PoolClient::new(Addr::unchecked("pool"));
+		let msg = client.build_swap_ix(Some(Decimal::percent(1)), None, coins(100, "usei"))?;
+		assert_eq!(
+			msg,
+			WasmMsg::Execute {
+				contract_addr: "pool".to_string(),
+				msg: to_json_binary(&PoolExecuteMsg::Swap {
+					slippage_tolerance: Some(Decimal::percent(1)),
+					receiver: None
+				})?,
+				funds: coins(100, "usei"),
+			}
+		);
+		let WasmMsg::Execute { msg, .. } = msg else { unreachable!() };
+		assert_eq!(
+			msg.as_slice(),
+			br#"{"swap":{"slippage_tolerance":"0.01","receiver":null}}"#
+		);
+		assert_eq!(
+			ExecuteOnlyClient::new(Addr::unchecked("pool")).build_withdraw_ix(vec![])?,
+			client.build_withdraw_ix(vec![])?
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn queries_deserialize_responses() -> StdResult<()> {
+		let mut mock_querier = MockQuerier::default();
+		mock_querier.update_wasm(|query| {
+			let WasmQuery::Smart { contract_addr, msg } = query else {
+				panic!("unexpected query");
+			};
+			assert_eq!(contract_addr, "pool");
+			let response = match from_json::<PoolQueryMsg>(msg).unwrap() {
+				PoolQueryMsg::Config {} => br#"{"admin":"admin","paused":true}"#.to_vec(),
+				PoolQueryMsg::ShareValue { amount } => {
+					format!(r#"["{}","{}"]"#, amount, amount * Uint128::new(2)).into()
+				}
+			};
+			SystemResult::Ok(ContractResult::Ok(response.into()))
+		});
+		let querier = QuerierWrapper::<cosmwasm_std::Empty>::new(&mock_querier);
+		let client = PoolClient::new(Addr::unchecked("pool"));
+		assert_eq!(
+			client.query_config(&querier)?,
+			PoolConfigResponse {
+				admin: Addr::unchecked("admin"),
+				paused: true
+			}
+		);
+		assert_eq!(
+			client.query_share_value(&querier, Uint128::new(5))?,
+			[Uint128::new(5), Uint128::new(10)]
+		);
+		Ok(())
+	}
+}
@@ -0,0 +1,242 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, StdError, StdResult};
+use thiserror::Error;
+
+use crate::{
+	data_types::{
+		canonical_addr::SeiCanonicalAddr,
+		responses::{paginate, Cursor, PageResponse},
+	},
+	storage::map::StoredMap,
+};
+
+/// The most entries [ContractRegistry::entries] returns at once, larger limits are lowered to this.
+pub const REGISTRY_PAGE_MAX_LIMIT: u32 = 100;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RegistryError {
+	#[error("No contract is registered as \"{0}\"")]
+	NotRegistered(String),
+	#[error("{0} may not change the contract registry")]
+	Unauthorized(Addr),
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<RegistryError> for StdError {
+	fn from(value: RegistryError) -> Self {
+		match value {
+			RegistryError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// The name of a well-known contract in a [ContractRegistry], see [registry_keys](crate::registry_keys).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RegistryKey(pub &'static str);
+impl RegistryKey {
+	pub const fn name(&self) -> &'static str {
+		self.0
+	}
+}
+impl AsRef<str> for RegistryKey {
+	fn as_ref(&self) -> &str {
+		self.0
+	}
+}
+
+#[macro_export]
+/// `registry_keys!(FACTORY, FEE_COLLECTOR = "fee_collector", ...)`
+///
+/// Defines a [RegistryKey](crate::contract_registry::RegistryKey) constant for each name, so that contracts are looked
+/// up in a [ContractRegistry](crate::contract_registry::ContractRegistry) with e.g. `registry.get(FACTORY)` rather than
+/// a string which may contain a typo. The stored name is the constant's name, unless specified otherwise.
+macro_rules! registry_keys {
+	($($key:ident $(= $name:literal)?),+ $(,)?) => {
+		$(
+			pub const $key: $crate::contract_registry::RegistryKey =
+				$crate::contract_registry::RegistryKey($crate::registry_keys!(@name $key $($name)?));
+		)+
+	};
+	(@name $key:ident) => {
+		stringify!($key)
+	};
+	(@name $key:ident $name:literal) => {
+		$name
+	};
+}
+
+/// A named entry of a [ContractRegistry], as returned by [ContractRegistry::entries].
+#[cw_serde]
+pub struct RegistryEntry {
+	pub name: String,
+	pub address: Addr,
+}
+
+/// An address book of the other contracts a contract interacts with, e.g. a router's factory and fee collector.
+///
+/// Addresses are validated and canonicalized once when they're set, rather than every time they're used.
+pub struct ContractRegistry {
+	contracts: StoredMap<String, SeiCanonicalAddr>,
+}
+impl ContractRegistry {
+	pub fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			contracts: StoredMap::new(namespace),
+		}
+	}
+
+	/// Registers `addr` as `name`, replacing the contract which was registered as it, if any.
+	pub fn set(&self, name: impl AsRef<str>, addr: &Addr) -> StdResult<()> {
+		self.contracts
+			.set(&name.as_ref().to_string(), &SeiCanonicalAddr::try_from(addr)?)
+	}
+
+	/// Same as [set](Self::set), but fails with [RegistryError::Unauthorized] unless `is_authorized` accepts the
+	/// sender, which would typically check that it's the contract's owner or admin.
+	pub fn set_authorized(
+		&self,
+		sender: &Addr,
+		is_authorized: impl FnOnce(&Addr) -> StdResult<bool>,
+		name: impl AsRef<str>,
+		addr: &Addr,
+	) -> Result<(), RegistryError> {
+		if !is_authorized(sender)? {
+			return Err(RegistryError::Unauthorized(sender.clone()));
+		}
+		self.set(name, addr)?;
+		Ok(())
+	}
+
+	pub fn remove(&self, name: impl AsRef<str>) {
+		self.contracts.remove(&name.as_ref().to_string());
+	}
+
+	pub fn has(&self, name: impl AsRef<str>) -> bool {
+		self.contracts.has(&name.as_ref().to_string())
+	}
+
+	/// Returns the contract registered as `name`, if any.
+	pub fn may_get(&self, name: impl AsRef<str>) -> StdResult<Option<SeiCanonicalAddr>> {
+		Ok(self
+			.contracts
+			.get(&name.as_ref().to_string())?
+			.map(|addr| addr.into_inner()))
+	}
+
+	/// Returns the contract registered as `name`, or [RegistryError::NotRegistered].
+	pub fn get(&self, name: impl AsRef<str>) -> Result<SeiCanonicalAddr, RegistryError> {
+		let name = name.as_ref();
+		self.may_get(name)?
+			.ok_or_else(|| RegistryError::NotRegistered(name.to_string()))
+	}
+
+	/// Same as [get](Self::get), but returns the contract's bech32 address.
+	pub fn get_addr(&self, name: impl AsRef<str>) -> Result<Addr, RegistryError> {
+		Ok(Addr::try_from(self.get(name)?)?)
+	}
+
+	/// Lists the registered contracts, for the frontend to query.
+	pub fn entries(&self, start_after: Option<Cursor>, limit: u32) -> StdResult<PageResponse<RegistryEntry>> {
+		let start_after = start_after
+			.map(|cursor| String::from_utf8(cursor.0.into()))
+			.transpose()
+			.map_err(|_| StdError::generic_err("Invalid contract registry cursor"))?;
+		// The range includes start_after itself
+		let iter = self
+			.contracts
+			.iter_range(start_after.clone(), None)?
+			.skip_while(|(name, _)| Some(name) == start_after.as_ref())
+			.map(|(name, addr)| {
+				Ok(RegistryEntry {
+					name,
+					address: Addr::try_from(&*addr)?,
+				})
+			});
+		Ok(paginate(iter, limit, REGISTRY_PAGE_MAX_LIMIT, |entry| {
+			Cursor::from(entry.name.clone().into_bytes())
+		})?
+		.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	registry_keys!(FACTORY, FEE_COLLECTOR = "fee_collector");
+
+	fn addr(n: u8) -> Addr {
+		Addr::try_from(SeiCanonicalAddr::from([n; 32])).unwrap()
+	}
+
+	#[test]
+	fn register_and_overwrite() -> TestingResult {
+		let _storage = isolated()?;
+		let registry = ContractRegistry::new(NAMESPACE);
+		assert_eq!(
+			registry.get(FACTORY),
+			Err(RegistryError::NotRegistered("FACTORY".to_string()))
+		);
+		assert_eq!(registry.may_get("fee_collector")?, None);
+
+		registry.set(FACTORY, &addr(1))?;
+		registry.set(FEE_COLLECTOR, &addr(2))?;
+		assert_eq!(registry.get(FACTORY)?, SeiCanonicalAddr::from([1; 32]));
+		assert_eq!(registry.get_addr("fee_collector")?, addr(2));
+		assert!(registry.set("router", &Addr::unchecked("not an address")).is_err());
+		assert!(!registry.has("router"));
+
+		registry.set(FACTORY, &addr(3))?;
+		assert_eq!(registry.get_addr(FACTORY)?, addr(3));
+		registry.remove(FEE_COLLECTOR);
+		assert!(registry.get(FEE_COLLECTOR).is_err());
+		assert_eq!(FEE_COLLECTOR.name(), "fee_collector");
+		Ok(())
+	}
+
+	#[test]
+	fn authorized_updates() -> TestingResult {
+		let _storage = isolated()?;
+		let registry = ContractRegistry::new(NAMESPACE);
+		let owner = addr(9);
+		let is_owner = |sender: &Addr| Ok(*sender == owner);
+		assert_eq!(
+			registry.set_authorized(&addr(8), is_owner, FACTORY, &addr(1)),
+			Err(RegistryError::Unauthorized(addr(8)))
+		);
+		assert!(!registry.has(FACTORY));
+		registry.set_authorized(&owner, is_owner, FACTORY, &addr(1))?;
+		assert_eq!(registry.get_addr(FACTORY)?, addr(1));
+		Ok(())
+	}
+
+	#[test]
+	fn paginated_entries() -> TestingResult {
+		let _storage = isolated()?;
+		let registry = ContractRegistry::new(NAMESPACE);
+		for (i, name) in ["a", "b", "c"].into_iter().enumerate() {
+			registry.set(name, &addr(i as u8))?;
+		}
+		let page = registry.entries(None, 2)?;
+		assert_eq!(
+			page.entries,
+			[
+				RegistryEntry {
+					name: "a".to_string(),
+					address: addr(0)
+				},
+				RegistryEntry {
+					name: "b".to_string(),
+					address: addr(1)
+				}
+			]
+		);
+		let page = registry.entries(page.next_cursor, 2)?;
+		assert_eq!(page.entries.len(), 1);
+		assert_eq!(page.entries[0].name, "c");
+		assert_eq!(page.next_cursor, None);
+		Ok(())
+	}
+}
@@ -0,0 +1,166 @@
+use cosmwasm_std::{Addr, Api, Binary, StdError};
+use ripemd::Ripemd160;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::{data_types::canonical_addr::SeiCanonicalAddr, utils::to_canonical_json};
+
+pub(crate) fn keccak256(data: &[&[u8]]) -> [u8; 32] {
+	let mut hasher = Keccak::v256();
+	for data in data {
+		hasher.update(data);
+	}
+	let mut result = [0u8; 32];
+	hasher.finalize(&mut result);
+	result
+}
+
+/// Returns the digest an EVM wallet signs when asked to `personal_sign` the message. (EIP-191 version 0x45)
+pub fn eip191_digest(message: &[u8]) -> [u8; 32] {
+	keccak256(&[
+		b"\x19Ethereum Signed Message:\n",
+		message.len().to_string().as_bytes(),
+		message,
+	])
+}
+
+/// Verifies that the message was signed by the specified EVM address using `personal_sign`.
+///
+/// The signature is expected to be in the usual `r || s || v` form, where `v` is either 27/28 or 0/1. Returns an
+/// error if the signature is malformed.
+pub fn verify_eip191(
+	message: &[u8],
+	signature: &[u8; 65],
+	expected_evm: &[u8; 20],
+	api: &dyn Api,
+) -> Result<bool, StdError> {
+	let recovery_param = match signature[64] {
+		0 | 27 => 0,
+		1 | 28 => 1,
+		v => {
+			return Err(StdError::parse_err(
+				"EIP-191 signature",
+				format!("invalid recovery id {v}"),
+			))
+		}
+	};
+	let pubkey = api.secp256k1_recover_pubkey(&eip191_digest(message), &signature[..64], recovery_param)?;
+	// Uncompressed pubkeys are 0x04 || x || y, the address is the last 20 bytes of the hash of x || y
+	let Some(pubkey_xy) = pubkey.strip_prefix(&[0x04]) else {
+		return Err(StdError::generic_err(
+			"secp256k1_recover_pubkey did not return an uncompressed pubkey",
+		));
+	};
+	Ok(keccak256(&[pubkey_xy])[12..] == *expected_evm)
+}
+
+/// Returns the amino JSON sign doc a cosmos wallet signs when asked to sign arbitrary data. (ADR-36)
+pub fn adr36_sign_doc(signer: &Addr, message_b64: &str) -> String {
+	let sign_doc = json!({
+		"account_number": "0",
+		"chain_id": "",
+		"fee": {"amount": [], "gas": "0"},
+		"memo": "",
+		"msgs": [{
+			"type": "sign/MsgSignData",
+			"value": {"data": message_b64, "signer": signer},
+		}],
+		"sequence": "0",
+	});
+	String::from_utf8(to_canonical_json(&sign_doc).expect("the sign doc only contains strings"))
+		.expect("canonical JSON is UTF-8")
+}
+
+/// Verifies that the base64 encoded message was signed by `signer` using ADR-36.
+///
+/// `pubkey` must be the signer's 33 byte compressed secp256k1 pubkey, which is checked against the signer's address,
+/// and `signature` is the 64 byte `r || s` signature. Returns an error if any of the inputs are malformed.
+pub fn verify_adr36(
+	signer: &Addr,
+	message_b64: &str,
+	pubkey: &[u8],
+	signature: &[u8],
+	api: &dyn Api,
+) -> Result<bool, StdError> {
+	// The signature would fail to verify anyway, but this gives a clearer error
+	Binary::from_base64(message_b64)?;
+	let signer_canonical = SeiCanonicalAddr::try_from(signer)?;
+	if pubkey.len() != 33 {
+		return Err(StdError::parse_err(
+			"ADR-36 pubkey",
+			"expected a 33 byte compressed secp256k1 pubkey",
+		));
+	}
+	let pubkey_hash: [u8; 20] = Ripemd160::digest(Sha256::digest(pubkey)).into();
+	if SeiCanonicalAddr::from(pubkey_hash) != signer_canonical {
+		return Ok(false);
+	}
+	let sign_doc_hash = Sha256::digest(adr36_sign_doc(signer, message_b64).as_bytes());
+	Ok(api.secp256k1_verify(&sign_doc_hash, signature, pubkey)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::testing::MockApi;
+	use hex::FromHex;
+
+	use super::*;
+
+	// Signed with the private key used in the web3.py documentation:
+	// 0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318
+	// Wallets use deterministic (RFC 6979) nonces, so these are the exact signatures a wallet produces for this key.
+	const EVM_ADDRESS: &str = "2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+	const EIP191_MESSAGE: &str = "I♥SF";
+	const EIP191_SIGNATURE: &str = "87f6856afaa7684bc6ecccef96246c155da984e7889dbdda1ae59940bdab8e676cd3055bd38ac073cd5710e3040f1ed08073ca026266caeb097213d52665c4c01b";
+
+	// Signed by the same private key as above
+	const SEI_ADDRESS: &str = "sei1nduq8yy8h4nr7g9vuuglzklqatmaquq9xw6h5x";
+	const SEI_PUBKEY: &str = "024e3b81af9c2234cad09d679ce6035ed1392347ce64ce405f5dcd36228a25de6e";
+	// "Sign in to CrownFi"
+	const ADR36_MESSAGE_B64: &str = "U2lnbiBpbiB0byBDcm93bkZp";
+	const ADR36_SIGNATURE: &str = "61f32c6068b3033e9067cc8b143305c4a3da062808ddf7f0062421821b387e9e7089654dc53245d16702d52426a39ebae0b4122f80d8ce9c4670b1af303b2ceb";
+
+	#[test]
+	fn eip191() {
+		let api = MockApi::default();
+		let expected_evm = <[u8; 20]>::from_hex(EVM_ADDRESS).unwrap();
+		let mut signature = <[u8; 65]>::from_hex(EIP191_SIGNATURE).unwrap();
+
+		assert_eq!(
+			hex::encode(eip191_digest(EIP191_MESSAGE.as_bytes())),
+			"1476abb745d423bf09273f1afd887d951181d25adc66c4834a70491911b7f750"
+		);
+		assert!(verify_eip191(EIP191_MESSAGE.as_bytes(), &signature, &expected_evm, &api).unwrap());
+		assert!(!verify_eip191(b"I<3SF", &signature, &expected_evm, &api).unwrap());
+		assert!(!verify_eip191(EIP191_MESSAGE.as_bytes(), &signature, &[1; 20], &api).unwrap());
+
+		// 0/1 recovery ids are accepted too
+		signature[64] = 0;
+		assert!(verify_eip191(EIP191_MESSAGE.as_bytes(), &signature, &expected_evm, &api).unwrap());
+		signature[64] = 29;
+		assert!(verify_eip191(EIP191_MESSAGE.as_bytes(), &signature, &expected_evm, &api).is_err());
+	}
+
+	#[test]
+	fn adr36() {
+		let api = MockApi::default();
+		let signer = Addr::unchecked(SEI_ADDRESS);
+		let pubkey = Vec::from_hex(SEI_PUBKEY).unwrap();
+		let signature = Vec::from_hex(ADR36_SIGNATURE).unwrap();
+
+		assert!(verify_adr36(&signer, ADR36_MESSAGE_B64, &pubkey, &signature, &api).unwrap());
+		// Different message
+		assert!(!verify_adr36(&signer, "U2lnbiBpbiB0byBDcm93bkZpIQ==", &pubkey, &signature, &api).unwrap());
+		// The pubkey doesn't belong to the signer
+		assert!(!verify_adr36(
+			&Addr::unchecked("sei19rl4cm2hmr8afy4kldpxz3fka4jguq0a3vute5"),
+			ADR36_MESSAGE_B64,
+			&pubkey,
+			&signature,
+			&api
+		)
+		.unwrap());
+		assert!(verify_adr36(&signer, "not base64!", &pubkey, &signature, &api).is_err());
+	}
+}
@@ -1,2 +1,9 @@
 pub mod asset;
+pub mod batch_query;
+pub mod bps;
 pub mod canonical_addr;
+pub mod clearable;
+#[cfg(feature = "sei")]
+pub mod nft;
+pub mod responses;
+pub mod vesting;
@@ -4,19 +4,21 @@ use cosmwasm_schema::{
 	schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema},
 };
 use cosmwasm_std::{
-	to_json_binary, Addr, BankMsg, Binary, Coin, ConversionOverflowError, CosmosMsg, QuerierWrapper, StdError, Uint128,
-	Uint256, WasmMsg,
+	to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, CustomQuery, QuerierWrapper, StdError, Uint128, WasmMsg,
 };
 use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20Coin, Cw20CoinVerified, Cw20ExecuteMsg, Cw20QueryMsg};
 use hex::FromHex;
+#[cfg(feature = "sei")]
 use sei_cosmwasm::{SeiMsg, SeiQuerier, SeiQueryWrapper};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::fmt;
+use std::{collections::BTreeMap, fmt};
 
 use super::canonical_addr::SeiCanonicalAddr;
+#[cfg(feature = "sei")]
+use crate::utils::{evm_word_to_uint128, uint128_to_evm_word};
 use crate::{
 	impl_serializable_borsh,
-	storage::SerializableItem,
+	storage::{concat_byte_array_pairs, SerializableItem},
 	utils::{bytes_to_ethereum_address, parse_ethereum_address},
 };
 
@@ -47,6 +49,73 @@ impl FungibleAssetKind {
 			_ => false,
 		}
 	}
+	/// A byte representation intended for use as a map key. This will not change between versions.
+	///
+	/// It is a variant tag (0 for native, 1 for CW20, 2 for ERC20) followed by the denom's UTF-8 bytes, the 32 byte
+	/// canonical address, or the 20 byte EVM address respectively.
+	pub fn canonical_bytes(&self) -> Vec<u8> {
+		match self {
+			FungibleAssetKind::Native(denom) => concat_byte_array_pairs(&[0], denom.as_bytes()),
+			FungibleAssetKind::CW20(addr) => concat_byte_array_pairs(&[1], bytemuck::bytes_of(addr)),
+			FungibleAssetKind::ERC20(addr) => concat_byte_array_pairs(&[2], addr),
+		}
+	}
+	/// Parses the output of [canonical_bytes](Self::canonical_bytes).
+	pub fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, StdError> {
+		let parse_err = |msg: &str| StdError::parse_err("FungibleAssetKind", msg);
+		match bytes.split_first() {
+			Some((0, denom)) => Ok(FungibleAssetKind::Native(
+				String::from_utf8(denom.to_vec()).map_err(|_| parse_err("denom isn't valid UTF-8"))?,
+			)),
+			Some((1, addr)) if addr.len() == 32 => Ok(FungibleAssetKind::CW20(addr.try_into()?)),
+			Some((2, addr)) if addr.len() == 20 => Ok(FungibleAssetKind::ERC20(addr.try_into().unwrap())),
+			_ => Err(parse_err("invalid variant tag or length")),
+		}
+	}
+	/// Checks if this refers to the same asset as the string form without converting `self` into a string. At most one
+	/// address canonicalization is done.
+	///
+	/// If `api_free` is true, sei1\* addresses are decoded within the contract rather than by using the
+	/// `addr_canonicalize` API.
+	pub fn matches_string(&self, other: &FungibleAssetKindString, api_free: bool) -> Result<bool, StdError> {
+		self.matches_string_with(other, |addr| {
+			if api_free {
+				SeiCanonicalAddr::from_bech32(addr)
+			} else {
+				SeiCanonicalAddr::try_from(addr)
+			}
+		})
+	}
+	fn matches_string_with(
+		&self,
+		other: &FungibleAssetKindString,
+		canonicalize: impl FnOnce(&str) -> Result<SeiCanonicalAddr, StdError>,
+	) -> Result<bool, StdError> {
+		match (self, other) {
+			(FungibleAssetKind::Native(denom), FungibleAssetKindString::Native(other_denom)) => {
+				Ok(denom == other_denom)
+			}
+			(FungibleAssetKind::CW20(addr), FungibleAssetKindString::CW20(other_addr)) => {
+				Ok(*addr == canonicalize(other_addr)?)
+			}
+			(FungibleAssetKind::ERC20(addr), FungibleAssetKindString::ERC20(other_addr)) => {
+				Ok(*addr == parse_ethereum_address(other_addr)?)
+			}
+			_ => Ok(false),
+		}
+	}
+}
+/// Invalid addresses are considered unequal
+impl PartialEq<FungibleAssetKindString> for FungibleAssetKind {
+	fn eq(&self, other: &FungibleAssetKindString) -> bool {
+		self.matches_string(other, false).unwrap_or(false)
+	}
+}
+/// Invalid addresses are considered unequal
+impl PartialEq<FungibleAssetKind> for FungibleAssetKindString {
+	fn eq(&self, other: &FungibleAssetKind) -> bool {
+		other.matches_string(self, false).unwrap_or(false)
+	}
 }
 
 impl TryFrom<FungibleAssetKindString> for FungibleAssetKind {
@@ -117,16 +186,30 @@ impl FungibleAssetKindString {
 			}),
 		}
 	}
+	/// Queries the balance of the specified holder of a native or CW20 asset, which works on any chain. ERC20 balances
+	/// can only be queried through Sei's EVM, so an error is returned for them, see [query_balance](Self::query_balance).
+	pub fn query_balance_without_evm<C: CustomQuery>(
+		&self,
+		querier: &QuerierWrapper<C>,
+		holder: &Addr,
+	) -> Result<Uint128, StdError> {
+		match self {
+			FungibleAssetKindString::Native(denom) => Ok(querier.query_balance(holder, denom)?.amount),
+			FungibleAssetKindString::CW20(address) => Ok(querier
+				.query_wasm_smart::<Cw20BalanceResponse>(address, &Cw20QueryMsg::Balance { address: holder.into() })?
+				.balance),
+			FungibleAssetKindString::ERC20(_) => Err(StdError::generic_err(
+				"ERC20 balances can only be queried with the SeiQueryWrapper querier",
+			)),
+		}
+	}
 	/// Queries the balance of the specified holder.
 	///
 	/// Note that in the case of ERC20 assets, a 0x\* addremss may be provided, and sei1* addresses will be attempted to
 	/// be converted to 0x\* addresses. If the conversion attempt fails, this will return 0.
+	#[cfg(feature = "sei")]
 	pub fn query_balance(&self, querier: &QuerierWrapper<SeiQueryWrapper>, holder: &Addr) -> Result<Uint128, StdError> {
 		match self {
-			FungibleAssetKindString::Native(denom) => Ok(querier.query_balance(holder, denom)?.amount),
-			FungibleAssetKindString::CW20(address) => Ok(querier
-				.query_wasm_smart::<Cw20BalanceResponse>(address, &Cw20QueryMsg::Balance { address: holder.into() })?
-				.balance),
 			FungibleAssetKindString::ERC20(address) => {
 				let querier = SeiQuerier::new(querier);
 				let mut evm_payload = Vec::<u8>::with_capacity(36);
@@ -160,24 +243,9 @@ impl FungibleAssetKindString {
 						)?
 						.encoded_data,
 				)?;
-				if evm_result.len() != 32 {
-					return Err(StdError::parse_err(
-						"Uint256",
-						"balanceOf(address) EVM call did not return a 32 byte long result",
-					));
-				}
-				if evm_result[0..16] != [0; 16] {
-					return Err(ConversionOverflowError::new(
-						"Uint256",
-						"Uint128",
-						Uint256::from_be_bytes(evm_result.0.try_into().unwrap()),
-					)
-					.into());
-				}
-				Ok(Uint128::from(<u128>::from_be_bytes(
-					evm_result.0[16..].try_into().unwrap(),
-				)))
+				evm_word_to_uint128(&evm_result)
 			}
+			_ => self.query_balance_without_evm(querier, holder),
 		}
 	}
 }
@@ -342,6 +410,29 @@ impl FungibleAsset {
 			}
 		}
 	}
+	/// Generates a transfer message for a native or CW20 asset, which works on any chain. ERC20 transfers require a
+	/// `SeiMsg`, so an error is returned for them, see [transfer_to_msg](Self::transfer_to_msg).
+	pub fn transfer_to_msg_without_evm<M>(&self, to: &Addr) -> Result<CosmosMsg<M>, StdError> {
+		match self {
+			FungibleAsset::Native(coin) => Ok(BankMsg::Send {
+				to_address: to.to_string(),
+				amount: vec![coin.clone()],
+			}
+			.into()),
+			FungibleAsset::CW20(coin) => Ok(WasmMsg::Execute {
+				contract_addr: coin.address.clone(),
+				msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+					recipient: to.to_string(),
+					amount: coin.amount,
+				})?,
+				funds: vec![],
+			}
+			.into()),
+			FungibleAsset::ERC20(_) => Err(StdError::generic_err(
+				"ERC20 assets can only be transferred with SeiMsg::CallEvm",
+			)),
+		}
+	}
 	/// Generates a transfer message for this asset
 	///
 	/// Note that in the case of ERC20, you should provide a 0x\* address, as this function encodes sei1\* addresses
@@ -351,23 +442,9 @@ impl FungibleAsset {
 	///
 	/// **FIXME:** Replace with falliable varient which can also take the querier to do proper sei1\* <> 0x\* address
 	/// conversion.
+	#[cfg(feature = "sei")]
 	pub fn transfer_to_msg(&self, to: &Addr) -> CosmosMsg<SeiMsg> {
 		match self {
-			FungibleAsset::Native(coin) => BankMsg::Send {
-				to_address: to.to_string(),
-				amount: vec![coin.clone()],
-			}
-			.into(),
-			FungibleAsset::CW20(coin) => WasmMsg::Execute {
-				contract_addr: coin.address.clone(),
-				msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
-					recipient: to.to_string(),
-					amount: coin.amount,
-				})
-				.expect("serialization shouldn't fail"),
-				funds: vec![],
-			}
-			.into(),
 			FungibleAsset::ERC20(coin) => SeiMsg::CallEvm {
 				value: Uint128::zero(),
 				to: coin.address.clone(),
@@ -392,12 +469,14 @@ impl FungibleAsset {
 							buff.extend_from_slice(&canon_addr.as_slice()[12..]);
 						}
 					}
-					buff.extend_from_slice(&[0; 16]);
-					buff.extend_from_slice(&coin.amount.to_be_bytes());
+					buff.extend_from_slice(&uint128_to_evm_word(coin.amount));
 					Binary::from(buff).to_base64()
 				},
 			}
 			.into(),
+			_ => self
+				.transfer_to_msg_without_evm(to)
+				.expect("serialization shouldn't fail"),
 		}
 	}
 
@@ -462,3 +541,367 @@ impl fmt::Display for FungibleAsset {
 		}
 	}
 }
+
+/// A collection of [FungibleAsset]s of any kind, like [cosmwasm_std::Coins] but not limited to native denoms.
+///
+/// There is at most one entry per asset kind, entries are kept in canonical order (native, then CW20, then ERC20,
+/// each sorted by their denom or address) and zero amounts are never stored. ERC20 addresses are lowercased so that
+/// differently checksummed addresses count as the same asset.
+///
+/// Serialized as a list of [FungibleAsset]s for messages, and using borsh for storage.
+#[derive(Debug, Clone, Default, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct FungibleAssets(BTreeMap<FungibleAssetKindString, u128>);
+impl_serializable_borsh!(FungibleAssets);
+
+impl FungibleAssets {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Merges assets of the same kind, returning an error if their sum overflows.
+	pub fn from_assets(assets: Vec<FungibleAsset>) -> Result<Self, StdError> {
+		let mut result = Self::new();
+		for asset in assets {
+			result.add(asset)?;
+		}
+		Ok(result)
+	}
+
+	/// Merges coins of the same denom, returning an error if their sum overflows.
+	pub fn from_coins(coins: &[Coin]) -> Result<Self, StdError> {
+		Self::from_assets(coins.iter().cloned().map(FungibleAsset::Native).collect())
+	}
+
+	fn normalize_kind(kind: FungibleAssetKindString) -> FungibleAssetKindString {
+		match kind {
+			FungibleAssetKindString::ERC20(address) => FungibleAssetKindString::ERC20(address.to_ascii_lowercase()),
+			kind => kind,
+		}
+	}
+
+	pub fn add(&mut self, asset: FungibleAsset) -> Result<(), StdError> {
+		let (kind, amount) = asset.into_asset_kind_string_and_amount();
+		if amount == 0 {
+			return Ok(());
+		}
+		let entry = self.0.entry(Self::normalize_kind(kind)).or_default();
+		*entry = Uint128::new(*entry).checked_add(Uint128::new(amount))?.u128();
+		Ok(())
+	}
+
+	/// Returns an error if there isn't enough of the asset, in which case nothing is changed. Entries reaching zero
+	/// are removed.
+	pub fn checked_sub(&mut self, asset: FungibleAsset) -> Result<(), StdError> {
+		let (kind, amount) = asset.into_asset_kind_string_and_amount();
+		if amount == 0 {
+			return Ok(());
+		}
+		let kind = Self::normalize_kind(kind);
+		let balance = self.0.get(&kind).copied().unwrap_or_default();
+		let remaining = Uint128::new(balance).checked_sub(Uint128::new(amount))?.u128();
+		if remaining == 0 {
+			self.0.remove(&kind);
+		} else {
+			self.0.insert(kind, remaining);
+		}
+		Ok(())
+	}
+
+	/// Returns zero if there is none of the asset.
+	pub fn get(&self, kind: &FungibleAssetKindString) -> Uint128 {
+		let amount = match kind {
+			FungibleAssetKindString::ERC20(address) => self
+				.0
+				.get(&FungibleAssetKindString::ERC20(address.to_ascii_lowercase())),
+			kind => self.0.get(kind),
+		};
+		Uint128::new(amount.copied().unwrap_or_default())
+	}
+
+	/// Iterates over the assets in canonical order.
+	pub fn iter(&self) -> impl Iterator<Item = (&FungibleAssetKindString, Uint128)> + '_ {
+		self.0.iter().map(|(kind, amount)| (kind, Uint128::new(*amount)))
+	}
+
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	pub fn into_vec(self) -> Vec<FungibleAsset> {
+		self.0
+			.into_iter()
+			.filter(|(_, amount)| *amount != 0)
+			.map(|(kind, amount)| kind.into_asset(amount))
+			.collect()
+	}
+
+	/// Returns an error if any of the assets aren't native.
+	pub fn try_into_native_coins(self) -> Result<Vec<Coin>, StdError> {
+		self.into_vec()
+			.into_iter()
+			.map(|asset| match asset {
+				FungibleAsset::Native(coin) => Ok(coin),
+				asset => Err(StdError::generic_err(format!(
+					"{} is not a native asset",
+					asset.identifier()
+				))),
+			})
+			.collect()
+	}
+}
+impl From<FungibleAssets> for Vec<FungibleAsset> {
+	fn from(value: FungibleAssets) -> Self {
+		value.into_vec()
+	}
+}
+impl TryFrom<Vec<FungibleAsset>> for FungibleAssets {
+	type Error = StdError;
+	fn try_from(value: Vec<FungibleAsset>) -> Result<Self, Self::Error> {
+		Self::from_assets(value)
+	}
+}
+impl TryFrom<&[Coin]> for FungibleAssets {
+	type Error = StdError;
+	fn try_from(value: &[Coin]) -> Result<Self, Self::Error> {
+		Self::from_coins(value)
+	}
+}
+impl Serialize for FungibleAssets {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.collect_seq(self.0.iter().map(|(kind, amount)| kind.clone().into_asset(*amount)))
+	}
+}
+impl<'de> Deserialize<'de> for FungibleAssets {
+	fn deserialize<D>(deserializer: D) -> Result<FungibleAssets, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let assets = <Vec<FungibleAsset> as Deserialize>::deserialize(deserializer)?;
+		Self::from_assets(assets).map_err(serde::de::Error::custom)
+	}
+}
+impl JsonSchema for FungibleAssets {
+	fn schema_name() -> String {
+		String::from("FungibleAssets")
+	}
+	fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+		Vec::<FungibleAsset>::json_schema(gen)
+	}
+}
+
+/// Encodes a cw20 hook message the same way the generated TypeScript SDK's `build*Cw20Ix` methods do, as JSON which
+/// becomes the base64 `msg` of a [Cw20ExecuteMsg::Send].
+pub fn encode_cw20_hook<T: Serialize>(hook_msg: &T) -> Result<Binary, StdError> {
+	to_json_binary(hook_msg)
+}
+
+/// Builds the [Cw20ExecuteMsg::Send] which sends `amount` of a cw20 token to `contract` along with the hook message,
+/// identical to the one built by the generated TypeScript SDK.
+pub fn cw20_hook_send_msg<T: Serialize>(
+	contract: impl Into<String>,
+	amount: Uint128,
+	hook_msg: &T,
+) -> Result<Cw20ExecuteMsg, StdError> {
+	Ok(Cw20ExecuteMsg::Send {
+		contract: contract.into(),
+		amount,
+		msg: encode_cw20_hook(hook_msg)?,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use cosmwasm_std::{coin, from_json, to_json_vec};
+
+	const CW20_ADDR: &str = "sei19rl4cm2hmr8afy4kldpxz3fka4jguq0a3vute5";
+	/// A hand-written sample of what the generated TypeScript SDK builds for a cw20 hook, see the description in the
+	/// file.
+	const CW20_HOOK_SAMPLE: &str = include_str!("../../test_data/cw20_hook_send.json");
+	const ERC20_ADDR: &str = "0x3894085Ef7Ff0f0aeDf52E2A2704928d1Ec074F1";
+
+	fn kinds() -> [FungibleAssetKind; 3] {
+		[
+			FungibleAssetKind::Native("usei".into()),
+			FungibleAssetKind::CW20(SeiCanonicalAddr::try_from(CW20_ADDR).unwrap()),
+			FungibleAssetKind::ERC20(parse_ethereum_address(ERC20_ADDR).unwrap()),
+		]
+	}
+
+	#[test]
+	fn matches_string() {
+		let kind_strings = [
+			FungibleAssetKindString::Native("usei".into()),
+			FungibleAssetKindString::CW20(CW20_ADDR.into()),
+			// ERC20 addresses are compared regardless of checksum casing
+			FungibleAssetKindString::ERC20(ERC20_ADDR.to_lowercase()),
+		];
+		for (i, kind) in kinds().iter().enumerate() {
+			for (ii, kind_string) in kind_strings.iter().enumerate() {
+				let mut canonicalizations = 0;
+				let result = kind
+					.matches_string_with(kind_string, |addr| {
+						canonicalizations += 1;
+						SeiCanonicalAddr::from_bech32(addr)
+					})
+					.unwrap();
+				assert_eq!(result, i == ii);
+				assert!(canonicalizations <= 1);
+				assert_eq!(kind.matches_string(kind_string, true).unwrap(), i == ii);
+				assert_eq!(kind == kind_string, i == ii);
+				assert_eq!(kind_string == kind, i == ii);
+			}
+		}
+
+		let kind = &kinds()[1];
+		assert!(
+			*kind
+				!= FungibleAssetKindString::CW20(
+					"sei1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq".into()
+				)
+		);
+		assert!(kind
+			.matches_string(&FungibleAssetKindString::CW20("nonsense".into()), true)
+			.is_err());
+		assert!(*kind != FungibleAssetKindString::CW20("nonsense".into()));
+		assert!(kinds()[0] != FungibleAssetKindString::Native("uusdc".into()));
+	}
+
+	#[test]
+	fn canonical_bytes() {
+		let [native, cw20, erc20] = kinds();
+		assert_eq!(native.canonical_bytes(), b"\x00usei");
+		assert_eq!(cw20.canonical_bytes().len(), 33);
+		assert_eq!(cw20.canonical_bytes()[0], 1);
+		assert_eq!(erc20.canonical_bytes().len(), 21);
+		assert_eq!(
+			erc20.canonical_bytes()[1..],
+			parse_ethereum_address(ERC20_ADDR).unwrap()
+		);
+		for kind in [native, cw20, erc20] {
+			assert_eq!(
+				FungibleAssetKind::from_canonical_bytes(&kind.canonical_bytes()).unwrap(),
+				kind
+			);
+		}
+		assert!(FungibleAssetKind::from_canonical_bytes(&[1; 21]).is_err());
+		assert!(FungibleAssetKind::from_canonical_bytes(&[]).is_err());
+	}
+
+	#[test]
+	fn fungible_assets_deduplicate() -> Result<(), StdError> {
+		let assets = FungibleAssets::from_assets(vec![
+			FungibleAssetKindString::ERC20(ERC20_ADDR.into()).into_asset(1u128),
+			FungibleAssetKindString::Native("usei".into()).into_asset(2u128),
+			FungibleAssetKindString::CW20(CW20_ADDR.into()).into_asset(3u128),
+			FungibleAssetKindString::ERC20(ERC20_ADDR.to_lowercase()).into_asset(4u128),
+			FungibleAssetKindString::Native("usei".into()).into_asset(5u128),
+			FungibleAssetKindString::Native("uusdc".into()).into_asset(0u128),
+		])?;
+		assert_eq!(assets.len(), 3);
+		assert_eq!(
+			assets
+				.iter()
+				.map(|(kind, amount)| (kind.to_string(), amount.u128()))
+				.collect::<Vec<_>>(),
+			[
+				("usei".to_string(), 7),
+				(format!("cw20/{CW20_ADDR}"), 3),
+				(format!("erc20/{}", ERC20_ADDR.to_lowercase()), 5),
+			]
+		);
+		assert_eq!(assets.get(&FungibleAssetKindString::ERC20(ERC20_ADDR.into())).u128(), 5);
+		assert_eq!(assets.get(&"uusdc".into()).u128(), 0);
+
+		let coins = FungibleAssets::from_coins(&[coin(1, "uusdc"), coin(2, "usei"), coin(3, "uusdc")])?;
+		assert_eq!(coins.try_into_native_coins()?, [coin(2, "usei"), coin(4, "uusdc")]);
+		assert!(assets.try_into_native_coins().is_err());
+		assert!(FungibleAssets::from_coins(&[coin(u128::MAX, "usei"), coin(1, "usei")]).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn fungible_assets_sub_to_zero() -> Result<(), StdError> {
+		let mut assets = FungibleAssets::from_coins(&[coin(10, "usei"), coin(5, "uusdc")])?;
+		assets.checked_sub(coin(4, "usei").into())?;
+		assert_eq!(assets.get(&"usei".into()).u128(), 6);
+		assets.checked_sub(coin(5, "uusdc").into())?;
+		assert_eq!(assets.len(), 1);
+		assert_eq!(assets.clone().into_vec(), [FungibleAsset::Native(coin(6, "usei"))]);
+
+		// Insufficient or missing assets leave everything as is
+		assert!(assets.checked_sub(coin(7, "usei").into()).is_err());
+		assert!(assets.checked_sub(coin(1, "uusdc").into()).is_err());
+		assert_eq!(assets.get(&"usei".into()).u128(), 6);
+		assets.checked_sub(coin(6, "usei").into())?;
+		assert!(assets.is_empty());
+		Ok(())
+	}
+
+	#[test]
+	fn fungible_assets_round_trip() -> Result<(), StdError> {
+		let assets = FungibleAssets::from_assets(vec![
+			FungibleAssetKindString::ERC20(ERC20_ADDR.into()).into_asset(1u128),
+			FungibleAssetKindString::CW20(CW20_ADDR.into()).into_asset(2u128),
+			FungibleAssetKindString::Native("usei".into()).into_asset(u128::MAX),
+		])?;
+		let json = to_json_vec(&assets)?;
+		assert_eq!(
+			from_json::<Vec<FungibleAsset>>(&json)?,
+			Vec::<FungibleAsset>::from(assets.clone())
+		);
+		assert_eq!(from_json::<FungibleAssets>(&json)?, assets);
+		// Duplicates in messages are merged
+		assert_eq!(
+			from_json::<FungibleAssets>(
+				r#"[{"native":{"denom":"usei","amount":"1"}},{"native":{"denom":"usei","amount":"2"}}]"#
+			)?,
+			FungibleAssets::from_coins(&[coin(3, "usei")])?
+		);
+
+		let bytes = assets.serialize_to_owned()?;
+		assert_eq!(FungibleAssets::deserialize_to_owned(&bytes)?, assets);
+		Ok(())
+	}
+
+	#[test]
+	fn cw20_hook_matches_typescript() {
+		#[cw_serde]
+		enum SwapHook {
+			Swap {
+				min_out: Uint128,
+				recipient: Option<String>,
+			},
+		}
+		#[derive(Deserialize)]
+		struct Sample {
+			hook_msg_base64: String,
+			execute_msg_json: String,
+		}
+		let sample: Sample = from_json(CW20_HOOK_SAMPLE).unwrap();
+		let hook_msg = SwapHook::Swap {
+			min_out: Uint128::new(1000),
+			recipient: None,
+		};
+		assert_eq!(encode_cw20_hook(&hook_msg).unwrap().to_base64(), sample.hook_msg_base64);
+
+		let Cw20ExecuteMsg::Send { contract, .. } = from_json(&sample.execute_msg_json).unwrap() else {
+			panic!("sample isn't a send message");
+		};
+		let send_msg = cw20_hook_send_msg(contract, Uint128::new(5000), &hook_msg).unwrap();
+		assert_eq!(
+			String::from_utf8(to_json_vec(&send_msg).unwrap()).unwrap(),
+			sample.execute_msg_json
+		);
+		// Contracts decode the hook message from the received msg
+		let Cw20ExecuteMsg::Send { msg, .. } = send_msg else { unreachable!() };
+		assert_eq!(from_json::<SwapHook>(&msg).unwrap(), hook_msg);
+	}
+}
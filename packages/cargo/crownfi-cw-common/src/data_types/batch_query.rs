@@ -0,0 +1,85 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{from_json, to_json_binary, Binary, StdResult};
+use serde::de::DeserializeOwned;
+
+/// Multiple JSON encoded query messages to be executed in one call.
+///
+/// Contracts can expose this by adding a `Batch(BatchQueryMsg)` variant to their query message and handling it with
+/// [handle_batch_query].
+#[cw_serde]
+pub struct BatchQueryMsg {
+	pub queries: Vec<Binary>,
+}
+
+/// The response of each query in a [BatchQueryMsg], in the same order. A query which failed contains its error message.
+#[cw_serde]
+pub struct BatchQueryResponse {
+	pub responses: Vec<Result<Binary, String>>,
+}
+
+/// Runs each query within the batch through `f`, which would typically be the contract's own query entry point.
+///
+/// A query which fails to deserialize or returns an error doesn't fail the whole batch, its error is returned in its
+/// place instead.
+pub fn handle_batch_query<Q: DeserializeOwned>(
+	msg: BatchQueryMsg,
+	f: impl Fn(Q) -> StdResult<Binary>,
+) -> StdResult<Binary> {
+	to_json_binary(&BatchQueryResponse {
+		responses: msg
+			.queries
+			.iter()
+			.map(|query| from_json::<Q>(query).and_then(&f).map_err(|err| err.to_string()))
+			.collect(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::{to_json_vec, StdError};
+
+	use super::*;
+
+	#[cw_serde]
+	enum QueryMsg {
+		Echo { value: u32 },
+		Fail {},
+	}
+
+	fn query(msg: QueryMsg) -> StdResult<Binary> {
+		match msg {
+			QueryMsg::Echo { value } => to_json_binary(&value),
+			QueryMsg::Fail {} => Err(StdError::generic_err("this query always fails")),
+		}
+	}
+
+	#[test]
+	fn mixed_batch() -> StdResult<()> {
+		let msg = BatchQueryMsg {
+			queries: vec![
+				to_json_binary(&QueryMsg::Echo { value: 1 })?,
+				to_json_binary(&QueryMsg::Fail {})?,
+				Binary::from(b"{\"unknown\":{}}"),
+				to_json_binary(&QueryMsg::Echo { value: 2 })?,
+			],
+		};
+		let response: BatchQueryResponse = from_json(handle_batch_query(msg, query)?)?;
+		assert_eq!(response.responses.len(), 4);
+		assert_eq!(response.responses[0], Ok(to_json_binary(&1)?));
+		assert_eq!(
+			response.responses[1],
+			Err("Generic error: this query always fails".to_string())
+		);
+		assert!(response.responses[2].is_err());
+		assert_eq!(response.responses[3], Ok(to_json_binary(&2)?));
+
+		// The JSON shape the SDK autogen expects
+		assert_eq!(
+			String::from_utf8(to_json_vec(&BatchQueryResponse {
+				responses: vec![Ok(Binary::from(b"1")), Err("oops".into())]
+			})?)?,
+			"{\"responses\":[{\"Ok\":\"MQ==\"},{\"Err\":\"oops\"}]}"
+		);
+		Ok(())
+	}
+}
@@ -0,0 +1,262 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, StdError, Uint128, Uint256};
+use std::{fmt, ops::Mul};
+
+use crate::storage::SerializableItem;
+
+const BPS_PER_UNIT: u16 = 10_000;
+
+/// A fraction in basis points, where 1 bps is 0.01% and 10 000 bps is 100%.
+///
+/// The value is always validated to be at most 10 000, including when deserialized from JSON or storage. Fees are
+/// applied with an explicit rounding direction, and [split](Self::split) never loses or creates any of the amount.
+#[cw_serde]
+#[derive(Copy, Default, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "u16")]
+pub struct Bps(u16);
+
+impl Bps {
+	pub const ZERO: Self = Self(0);
+	/// 100%
+	pub const MAX: Self = Self(BPS_PER_UNIT);
+
+	pub fn new(bps: u16) -> Result<Self, StdError> {
+		if bps > BPS_PER_UNIT {
+			return Err(StdError::generic_err(format!(
+				"{bps} bps is more than 100% ({BPS_PER_UNIT} bps)"
+			)));
+		}
+		Ok(Self(bps))
+	}
+
+	/// Same as [new](Self::new), but usable in constants, in which case an invalid value fails to compile.
+	pub const fn new_const(bps: u16) -> Self {
+		assert!(bps <= BPS_PER_UNIT, "bps must be at most 10 000");
+		Self(bps)
+	}
+
+	/// Parses a percentage with up to 2 decimal places, e.g. `"0.3%"` is 30 bps. The `%` is required, so that a
+	/// percentage can't be mistaken for an amount of basis points or vice versa.
+	pub fn from_percent_str(value: &str) -> Result<Self, StdError> {
+		let parse_err = |msg: &str| StdError::parse_err("Bps", format!("\"{value}\" {msg}"));
+		let Some(percent) = value.strip_suffix('%') else {
+			return Err(parse_err("must end with %"));
+		};
+		let (whole, fraction) = percent.split_once('.').unwrap_or((percent, ""));
+		if whole.is_empty() || !whole.bytes().all(|c| c.is_ascii_digit()) {
+			return Err(parse_err("isn't a valid percentage"));
+		}
+		if (percent.contains('.') && fraction.is_empty()) || !fraction.bytes().all(|c| c.is_ascii_digit()) {
+			return Err(parse_err("isn't a valid percentage"));
+		}
+		let fraction = fraction.trim_end_matches('0');
+		if fraction.len() > 2 {
+			return Err(parse_err("is more precise than 0.01%"));
+		}
+		let whole = whole.trim_start_matches('0');
+		if whole.len() > 3 {
+			return Err(parse_err("is more than 100%"));
+		}
+		// Up to 999.99% which doesn't fit in a u16 as bps
+		let whole: u32 = if whole.is_empty() { 0 } else { whole.parse().unwrap() };
+		let fraction: u32 = format!("{fraction:0<2}").parse().unwrap();
+		u16::try_from(whole * 100 + fraction)
+			.ok()
+			.and_then(|bps| Self::new(bps).ok())
+			.ok_or_else(|| parse_err("is more than 100%"))
+	}
+
+	#[inline]
+	pub fn value(&self) -> u16 {
+		self.0
+	}
+
+	/// Returns `100% - self`.
+	#[inline]
+	pub fn complement(&self) -> Self {
+		Self(BPS_PER_UNIT - self.0)
+	}
+
+	/// Returns `amount * self` as an exact numerator over 10 000.
+	fn full_mul(&self, amount: Uint128) -> Uint256 {
+		amount.full_mul(self.0)
+	}
+
+	/// Returns `amount * self`, rounded down.
+	pub fn apply_floor(&self, amount: Uint128) -> Uint128 {
+		// Never more than `amount`, so this can't overflow
+		(self.full_mul(amount) / Uint256::from(BPS_PER_UNIT))
+			.try_into()
+			.unwrap()
+	}
+
+	/// Returns `amount * self`, rounded up.
+	pub fn apply_ceil(&self, amount: Uint128) -> Uint128 {
+		let floor = self.apply_floor(amount);
+		if (self.full_mul(amount) % Uint256::from(BPS_PER_UNIT)).is_zero() {
+			floor
+		} else {
+			floor + Uint128::one()
+		}
+	}
+
+	/// Splits `amount` into the fee and what's left over, where `fee + remainder == amount` always holds.
+	///
+	/// The fee is rounded up, so that an amount can't be split into pieces small enough to avoid paying any fees.
+	pub fn split(&self, amount: Uint128) -> (Uint128, Uint128) {
+		let fee = self.apply_ceil(amount);
+		(fee, amount - fee)
+	}
+
+	pub fn to_decimal(&self) -> Decimal {
+		Decimal::from(*self)
+	}
+}
+
+impl TryFrom<u16> for Bps {
+	type Error = StdError;
+	#[inline]
+	fn try_from(value: u16) -> Result<Self, Self::Error> {
+		Self::new(value)
+	}
+}
+
+impl From<Bps> for u16 {
+	#[inline]
+	fn from(value: Bps) -> Self {
+		value.0
+	}
+}
+
+impl From<Bps> for Decimal {
+	fn from(value: Bps) -> Self {
+		// Decimal has 18 decimal places, 1 bps is 10^-4
+		Decimal::raw(value.0 as u128 * 100_000_000_000_000)
+	}
+}
+
+/// Compounds two fractions, e.g. a protocol fee which is a fraction of the swap fee. The product is exact.
+impl Mul for Bps {
+	type Output = Decimal;
+	fn mul(self, rhs: Self) -> Self::Output {
+		// 10^-8 per unit
+		Decimal::raw(self.0 as u128 * rhs.0 as u128 * 10_000_000_000)
+	}
+}
+
+/// Formats as a percentage, e.g. `0.3%`, which [from_percent_str](Bps::from_percent_str) accepts.
+impl fmt::Display for Bps {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let (whole, fraction) = (self.0 / 100, self.0 % 100);
+		match fraction {
+			0 => write!(f, "{whole}%"),
+			_ if fraction % 10 == 0 => write!(f, "{whole}.{}%", fraction / 10),
+			_ => write!(f, "{whole}.{fraction:02}%"),
+		}
+	}
+}
+
+impl SerializableItem for Bps {
+	fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+		Ok(self.0.to_le_bytes().into())
+	}
+	fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> {
+		let bytes = data
+			.try_into()
+			.map_err(|_| StdError::parse_err("Bps", "expected 2 bytes"))?;
+		Self::new(u16::from_le_bytes(bytes))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::{from_json, to_json_vec};
+	use proptest::prelude::*;
+
+	use super::*;
+
+	proptest! {
+		#[test]
+		fn split_is_exact(bps in 0..=BPS_PER_UNIT, amount: u128) {
+			let bps = Bps::new(bps).unwrap();
+			let amount = Uint128::new(amount);
+			let (fee, remainder) = bps.split(amount);
+			prop_assert_eq!(fee.checked_add(remainder), Ok(amount));
+			prop_assert!(bps.apply_floor(amount) <= fee);
+			prop_assert!(fee - bps.apply_floor(amount) <= Uint128::one());
+			// The complement's floor is the remainder
+			prop_assert_eq!(bps.complement().apply_floor(amount), remainder);
+		}
+	}
+
+	#[test]
+	fn edge_cases() {
+		let amount = Uint128::new(12345);
+		assert_eq!(Bps::ZERO.split(amount), (Uint128::zero(), amount));
+		assert_eq!(Bps::MAX.split(amount), (amount, Uint128::zero()));
+		assert_eq!(Bps::MAX.apply_floor(Uint128::MAX), Uint128::MAX);
+		assert_eq!(Bps::ZERO.apply_ceil(Uint128::MAX), Uint128::zero());
+		assert_eq!(Bps::ZERO.complement(), Bps::MAX);
+		assert!(Bps::new(10_001).is_err());
+
+		let fee = Bps::new(30).unwrap();
+		assert_eq!(fee.apply_floor(Uint128::new(1000)), Uint128::new(3));
+		assert_eq!(fee.apply_floor(Uint128::new(999)), Uint128::new(2));
+		assert_eq!(fee.apply_ceil(Uint128::new(999)), Uint128::new(3));
+		assert_eq!(fee.apply_ceil(Uint128::new(1)), Uint128::new(1));
+		assert_eq!(fee * Bps::new(2500).unwrap(), Decimal::from_ratio(75u128, 100_000u128));
+		assert_eq!(Bps::MAX * Bps::MAX, Decimal::one());
+		assert_eq!(fee.to_decimal(), Decimal::permille(3));
+	}
+
+	#[test]
+	fn percent_strings() {
+		for (value, bps) in [
+			("0.3%", 30),
+			("0.30%", 30),
+			("0.01%", 1),
+			("1%", 100),
+			("025.5%", 2550),
+			("100%", 10_000),
+			("100.00%", 10_000),
+			("0%", 0),
+		] {
+			assert_eq!(Bps::from_percent_str(value).unwrap().value(), bps, "{value}");
+		}
+		for (value, reason) in [
+			("0.3", "must end with %"),
+			("30 bps", "must end with %"),
+			("%", "isn't a valid percentage"),
+			(".5%", "isn't a valid percentage"),
+			("5.%", "isn't a valid percentage"),
+			("-1%", "isn't a valid percentage"),
+			("1.2.3%", "isn't a valid percentage"),
+			(" 1%", "isn't a valid percentage"),
+			("0.005%", "is more precise than 0.01%"),
+			("100.01%", "is more than 100%"),
+			("655.35%", "is more than 100%"),
+			("700%", "is more than 100%"),
+			("999.99%", "is more than 100%"),
+			("99999999%", "is more than 100%"),
+		] {
+			let err = Bps::from_percent_str(value).unwrap_err().to_string();
+			assert!(err.contains(reason), "{value}: {err}");
+		}
+		for bps in [0, 1, 10, 30, 99, 100, 2550, 10_000] {
+			let bps = Bps::new(bps).unwrap();
+			assert_eq!(Bps::from_percent_str(&bps.to_string()).unwrap(), bps);
+		}
+	}
+
+	#[test]
+	fn deserialization_is_validated() {
+		assert_eq!(from_json::<Bps>(b"30").unwrap(), Bps::new(30).unwrap());
+		assert_eq!(to_json_vec(&Bps::new(30).unwrap()).unwrap(), b"30");
+		assert!(from_json::<Bps>(b"10001").is_err());
+		assert_eq!(
+			Bps::deserialize_to_owned(&Bps::MAX.serialize_to_owned().unwrap()).unwrap(),
+			Bps::MAX
+		);
+		assert!(Bps::deserialize_to_owned(&10_001u16.to_le_bytes()).is_err());
+	}
+}
@@ -1,11 +1,16 @@
+use bech32::FromBase32;
 #[cfg(not(target_arch = "wasm32"))]
-use bech32::{FromBase32, ToBase32};
+use bech32::ToBase32;
 use borsh::{BorshDeserialize, BorshSerialize};
 use bytemuck::{Pod, Zeroable};
 use cosmwasm_std::{Addr, Api, CanonicalAddr, StdError};
 use std::fmt::Display;
 
-use crate::{impl_serializable_as_ref, storage::SerializableItem};
+use crate::{
+	impl_serializable_as_ref,
+	storage::{concat_byte_array_pairs, SerializableItem},
+	utils::lexicographic_next,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, BorshDeserialize, BorshSerialize, Zeroable, Pod)]
 #[repr(C)]
@@ -26,6 +31,57 @@ impl SeiCanonicalAddr {
 			&self.bytes
 		}
 	}
+	/// The exact bytes [StoredMap](crate::storage::map::StoredMap) and other containers use when this address is (part
+	/// of) a key.
+	///
+	/// Unlike [as_slice](Self::as_slice), this is always 32 bytes long. EOAs are left-padded with zeros, so they sort
+	/// before all contract addresses.
+	#[inline]
+	pub fn storage_key(&self) -> [u8; 32] {
+		self.bytes
+	}
+	/// Returns the `[start, end)` storage key bounds of all entries whose key starts with this address, in the
+	/// container with the specified namespace. For use with [StoredMapIter::new_raw].
+	///
+	/// Usually it's simpler to pass the address as the `key_prefix` to [StoredMapIter::new], which results in the same
+	/// range:
+	///
+	/// ```no_run
+	/// # use crownfi_cw_common::{data_types::canonical_addr::SeiCanonicalAddr, storage::map::StoredMapIter};
+	/// # fn example(owner: SeiCanonicalAddr) -> cosmwasm_std::StdResult<()> {
+	/// // Entries of a `StoredMap<(SeiCanonicalAddr, u64), String>` stored under b"orders"
+	/// for (seq, order) in StoredMapIter::<u64, String>::new(b"orders", owner, None, None)? {
+	///     // ...
+	/// }
+	/// let (start, end) = owner.addr_range_bounds(b"orders");
+	/// for (seq, order) in StoredMapIter::<u64, String>::new_raw(&start, &end, start.len()) {
+	///     // Same entries as above
+	/// }
+	/// # Ok(())
+	/// # }
+	/// ```
+	///
+	/// [StoredMapIter::new]: crate::storage::map::StoredMapIter::new
+	/// [StoredMapIter::new_raw]: crate::storage::map::StoredMapIter::new_raw
+	pub fn addr_range_bounds(&self, namespace: &[u8]) -> (Vec<u8>, Vec<u8>) {
+		let start = concat_byte_array_pairs(namespace, &self.bytes);
+		let end = lexicographic_next(&start);
+		(start, end)
+	}
+	/// Decodes a sei1\* address without using the `addr_canonicalize` API, even when running in a contract.
+	pub fn from_bech32(value: &str) -> Result<Self, StdError> {
+		let (prefix, words, _) = bech32::decode(value)
+			.map_err(|err| StdError::parse_err("SeiCanonicalAddr", format!("bech32::decode error: {err}")))?;
+		if prefix.as_str() != "sei" {
+			return Err(StdError::parse_err(
+				"SeiCanonicalAddr",
+				format!("\"{value}\" wasn't prefixed with \"sei\""),
+			));
+		}
+		let bytes = Vec::<u8>::from_base32(&words)
+			.map_err(|err| StdError::parse_err("SeiCanonicalAddr", format!("base32 decode error error: {err}")))?;
+		Self::try_from(bytes.as_slice())
+	}
 	#[deprecated(note = "Please use `self != (&addr).try_into()?` instead.")]
 	pub fn is_eq_addr(&self, addr: &Addr, api: &dyn Api) -> Result<bool, StdError> {
 		Ok(self.as_slice() == api.addr_canonicalize(addr.as_str())?.as_slice())
@@ -92,18 +148,9 @@ impl From<SeiCanonicalAddr> for CanonicalAddr {
 #[cfg(not(target_arch = "wasm32"))]
 impl TryFrom<&str> for SeiCanonicalAddr {
 	type Error = StdError;
+	#[inline]
 	fn try_from(value: &str) -> Result<Self, Self::Error> {
-		let (prefix, words, _) = bech32::decode(&value)
-			.map_err(|err| StdError::parse_err("SeiCanonicalAddr", format!("bech32::decode error: {err}")))?;
-		if prefix.as_str() != "sei" {
-			return Err(StdError::parse_err(
-				"SeiCanonicalAddr",
-				format!("\"{value}\" wasn't prefixed with \"sei\""),
-			));
-		}
-		let bytes = Vec::<u8>::from_base32(&words)
-			.map_err(|err| StdError::parse_err("SeiCanonicalAddr", format!("base32 decode error error: {err}")))?;
-		Self::try_from(bytes.as_slice())
+		Self::from_bech32(value)
 	}
 }
 
@@ -189,4 +236,51 @@ mod test {
 		]);
 		assert!(canon_addr.to_string().as_str() == "sei19rl4cm2hmr8afy4kldpxz3fka4jguq0a3vute5");
 	}
+	#[test]
+	fn tuple_key_range() -> crate::storage::testing_common::TestingResult {
+		use crate::storage::{map::StoredMap, map::StoredMapIter, testing_common::*};
+		let _storage_lock = init()?;
+		let eoa = SeiCanonicalAddr::from([1u8; 20]);
+		let other_eoa = SeiCanonicalAddr::from([2u8; 20]);
+		let contract = SeiCanonicalAddr::from([1u8; 32]);
+		assert_eq!(eoa.storage_key()[..12], [0; 12]);
+		assert_eq!(eoa.storage_key()[12..], *eoa.as_slice());
+		assert!(eoa.storage_key() < contract.storage_key());
+
+		let map = StoredMap::<(SeiCanonicalAddr, u64), u64>::new(NAMESPACE);
+		for (addr, seq) in [
+			(eoa, 1),
+			(other_eoa, 1),
+			(eoa, 2),
+			(contract, 1),
+			(eoa, 3),
+			(contract, 2),
+		] {
+			map.set(&(addr, seq), &(seq * 10))?;
+		}
+		assert_eq!(
+			map.key(&(eoa, 5))[..NAMESPACE.len() + 32],
+			[NAMESPACE, &eoa.storage_key()[..]].concat()
+		);
+
+		let via_prefix: Vec<(u64, u64)> = StoredMapIter::<u64, u64>::new(NAMESPACE, eoa, None, None)?
+			.map(|(seq, value)| (seq, value.into_inner()))
+			.collect();
+		let (start, end) = eoa.addr_range_bounds(NAMESPACE);
+		let via_bounds: Vec<(u64, u64)> = StoredMapIter::<u64, u64>::new_raw(&start, &end, start.len())
+			.map(|(seq, value)| (seq, value.into_inner()))
+			.collect();
+		assert_eq!(via_prefix, vec![(1, 10), (2, 20), (3, 30)]);
+		assert_eq!(via_prefix, via_bounds);
+
+		let (start, end) = contract.addr_range_bounds(NAMESPACE);
+		assert_eq!(
+			StoredMapIter::<u64, u64>::new_raw(&start, &end, start.len())
+				.rev()
+				.map(|(seq, _)| seq)
+				.collect::<Vec<_>>(),
+			vec![2, 1]
+		);
+		Ok(())
+	}
 }
@@ -0,0 +1,157 @@
+use cosmwasm_schema::schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An update to an optional value, as used by [impl_partial_update!](crate::impl_partial_update).
+///
+/// In JSON, a missing field is [NoChange](Self::NoChange), `null` is [Clear](Self::Clear) and any other value is
+/// [Set](Self::Set). Missing fields require `#[serde(default)]`, and NoChange must be skipped with
+/// `#[serde(skip_serializing_if = "...")]` as it would otherwise be serialized as `null`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum Clearable<T> {
+	Set(T),
+	Clear,
+	#[default]
+	NoChange,
+}
+
+impl<T> Clearable<T> {
+	#[inline]
+	pub fn is_no_change(&self) -> bool {
+		matches!(self, Self::NoChange)
+	}
+
+	/// Applies the update to the value, returning true if it changed.
+	pub fn apply_to(self, value: &mut Option<T>) -> bool
+	where
+		T: PartialEq,
+	{
+		match self {
+			Self::Set(new_value) => {
+				if value.as_ref() == Some(&new_value) {
+					return false;
+				}
+				*value = Some(new_value);
+				true
+			}
+			Self::Clear => value.take().is_some(),
+			Self::NoChange => false,
+		}
+	}
+}
+
+impl<T: Serialize> Serialize for Clearable<T> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		match self {
+			Self::Set(value) => serializer.serialize_some(value),
+			Self::Clear | Self::NoChange => serializer.serialize_none(),
+		}
+	}
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Clearable<T> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		Ok(match Option::<T>::deserialize(deserializer)? {
+			Some(value) => Self::Set(value),
+			None => Self::Clear,
+		})
+	}
+}
+
+impl<T: JsonSchema> JsonSchema for Clearable<T> {
+	fn is_referenceable() -> bool {
+		false
+	}
+	fn schema_name() -> String {
+		Option::<T>::schema_name()
+	}
+	fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+		Option::<T>::json_schema(gen)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_schema::{cw_serde, schema_for};
+	use cosmwasm_std::{from_json, to_json_string, Addr};
+
+	use crate::impl_partial_update;
+
+	#[cw_serde]
+	struct Config {
+		fee_bps: u16,
+		paused: bool,
+		admin: Option<Addr>,
+		fee_receiver: Option<Addr>,
+	}
+
+	impl_partial_update!(Config => ConfigUpdate {
+		fee_bps: u16,
+		paused: bool,
+	} clearable {
+		admin: Addr,
+		fee_receiver: Addr,
+	});
+
+	fn config() -> Config {
+		Config {
+			fee_bps: 30,
+			paused: false,
+			admin: Some(Addr::unchecked("admin")),
+			fee_receiver: None,
+		}
+	}
+
+	#[test]
+	fn json_representation() {
+		let update: ConfigUpdate = from_json(r#"{"paused": true, "admin": null, "fee_receiver": "sei1fees"}"#).unwrap();
+		assert_eq!(
+			update,
+			ConfigUpdate {
+				paused: Some(true),
+				admin: super::Clearable::Clear,
+				fee_receiver: super::Clearable::Set(Addr::unchecked("sei1fees")),
+				..Default::default()
+			}
+		);
+		assert_eq!(
+			to_json_string(&update).unwrap(),
+			r#"{"paused":true,"admin":null,"fee_receiver":"sei1fees"}"#
+		);
+		assert_eq!(to_json_string(&ConfigUpdate::default()).unwrap(), "{}");
+		assert!(from_json::<ConfigUpdate>(r#"{"unknown": 1}"#).is_err());
+
+		let schema = schema_for!(ConfigUpdate).schema.object.unwrap();
+		assert!(schema.required.is_empty());
+		assert_eq!(schema.properties.len(), 4);
+	}
+
+	#[test]
+	fn apply_update() {
+		let mut config = config();
+		assert!(config.apply_update(ConfigUpdate::default()).is_empty());
+		// Setting values to what they already are doesn't change them
+		let changed = config.apply_update(from_json(r#"{"fee_bps": 30, "admin": "admin"}"#).unwrap());
+		assert!(changed.is_empty());
+		assert_eq!(config, self::config());
+
+		let changed =
+			config.apply_update(from_json(r#"{"fee_bps": 25, "admin": null, "fee_receiver": null}"#).unwrap());
+		assert_eq!(changed, ["fee_bps", "admin"]);
+		assert_eq!(
+			config,
+			Config {
+				fee_bps: 25,
+				admin: None,
+				..self::config()
+			}
+		);
+
+		let changed = config.apply_update(from_json(r#"{"paused": true, "fee_receiver": "sei1fees"}"#).unwrap());
+		assert_eq!(changed, ["paused", "fee_receiver"]);
+		assert!(config.paused);
+		assert_eq!(config.fee_receiver, Some(Addr::unchecked("sei1fees")));
+		// NoChange leaves the set value alone
+		assert!(config.apply_update(from_json(r#"{"fee_bps": 25}"#).unwrap()).is_empty());
+		assert_eq!(config.fee_receiver, Some(Addr::unchecked("sei1fees")));
+	}
+}
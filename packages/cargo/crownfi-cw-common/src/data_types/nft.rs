@@ -0,0 +1,513 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+	to_json_binary, Addr, Binary, CosmosMsg, QuerierWrapper, StdError, StdResult, Timestamp, Uint128, Uint256, WasmMsg,
+};
+use sei_cosmwasm::{SeiMsg, SeiQuerier, SeiQueryWrapper};
+use std::{fmt, str::FromStr};
+
+use super::canonical_addr::SeiCanonicalAddr;
+use crate::{
+	impl_serializable_borsh,
+	storage::SerializableItem,
+	utils::{bytes_to_ethereum_address, parse_ethereum_address},
+};
+
+const ERC721_SAFE_TRANSFER_FROM: [u8; 4] = [0x42, 0x84, 0x2e, 0x0e]; // safeTransferFrom(address,address,uint256)
+const ERC721_APPROVE: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3]; // approve(address,uint256)
+const ERC721_OWNER_OF: [u8; 4] = [0x63, 0x52, 0x21, 0x1e]; // ownerOf(uint256)
+
+#[cw_serde]
+#[derive(Copy, Eq, Hash, PartialOrd, Ord, BorshDeserialize, BorshSerialize)]
+pub enum NonFungibleAssetKind {
+	Cw721,
+	Erc721,
+}
+impl NonFungibleAssetKind {
+	pub fn prefix(&self) -> &'static str {
+		match self {
+			NonFungibleAssetKind::Cw721 => "cw721/",
+			NonFungibleAssetKind::Erc721 => "erc721/",
+		}
+	}
+}
+
+/// Represents a single token of a CW721 or ERC721 collection.
+///
+/// `contract` is a sei1\* address for CW721 tokens, and a 0x\* address for ERC721 tokens. ERC721 token IDs are the
+/// decimal representation of the uint256.
+#[cw_serde]
+#[derive(Eq, Hash, PartialOrd, Ord, BorshDeserialize, BorshSerialize)]
+pub struct NonFungibleAsset {
+	pub kind: NonFungibleAssetKind,
+	pub contract: String,
+	pub token_id: String,
+}
+impl_serializable_borsh!(NonFungibleAsset);
+
+impl NonFungibleAsset {
+	pub fn cw721(contract: impl Into<String>, token_id: impl Into<String>) -> Self {
+		Self {
+			kind: NonFungibleAssetKind::Cw721,
+			contract: contract.into(),
+			token_id: token_id.into(),
+		}
+	}
+	pub fn erc721(contract: &[u8; 20], token_id: impl Into<String>) -> StdResult<Self> {
+		Ok(Self {
+			kind: NonFungibleAssetKind::Erc721,
+			contract: bytes_to_ethereum_address(contract)?,
+			token_id: token_id.into(),
+		})
+	}
+	pub fn is_cw721(&self) -> bool {
+		self.kind == NonFungibleAssetKind::Cw721
+	}
+	pub fn is_erc721(&self) -> bool {
+		self.kind == NonFungibleAssetKind::Erc721
+	}
+	/// Returns "cw721/{contract}/{token_id}" or "erc721/{contract}/{token_id}"
+	pub fn identifier(&self) -> String {
+		self.to_string()
+	}
+
+	/// Generates a message which transfers this token from `from` (usually the current contract) to `to`.
+	///
+	/// `from` is ignored for CW721 tokens. In the case of ERC721, sei1\* addresses are only supported for contracts,
+	/// as EOAs require a querier to be converted to their 0x\* counterparts.
+	pub fn try_transfer_to_msg(&self, from: &Addr, to: &Addr) -> StdResult<CosmosMsg<SeiMsg>> {
+		match self.kind {
+			NonFungibleAssetKind::Cw721 => Ok(WasmMsg::Execute {
+				contract_addr: self.contract.clone(),
+				msg: to_json_binary(&Cw721ExecuteMsg::TransferNft {
+					recipient: to.to_string(),
+					token_id: self.token_id.clone(),
+				})?,
+				funds: vec![],
+			}
+			.into()),
+			NonFungibleAssetKind::Erc721 => {
+				let mut data = Vec::with_capacity(100);
+				data.extend_from_slice(&ERC721_SAFE_TRANSFER_FROM);
+				data.extend_from_slice(&evm_address_word(from)?);
+				data.extend_from_slice(&evm_address_word(to)?);
+				data.extend_from_slice(&self.erc721_token_id()?.to_be_bytes());
+				Ok(self.call_evm(data))
+			}
+		}
+	}
+
+	/// Same as `try_transfer_to_msg`, but panics on failure.
+	pub fn transfer_to_msg(&self, from: &Addr, to: &Addr) -> CosmosMsg<SeiMsg> {
+		self.try_transfer_to_msg(from, to)
+			.expect("NonFungibleAsset::transfer_to_msg: invalid address or token id")
+	}
+
+	pub fn approval_msg(&self, spender: &Addr) -> ApprovalMsg<'_> {
+		ApprovalMsg::new(self, spender)
+	}
+
+	/// Queries the current owner of this token.
+	///
+	/// The owner of an ERC721 token is returned as a 0x\* address.
+	pub fn query_owner(&self, querier: &QuerierWrapper<SeiQueryWrapper>) -> StdResult<String> {
+		match self.kind {
+			NonFungibleAssetKind::Cw721 => Ok(querier
+				.query_wasm_smart::<Cw721OwnerOfResponse>(
+					&self.contract,
+					&Cw721QueryMsg::OwnerOf {
+						token_id: self.token_id.clone(),
+						include_expired: None,
+					},
+				)?
+				.owner),
+			NonFungibleAssetKind::Erc721 => {
+				let mut evm_payload = Vec::with_capacity(36);
+				evm_payload.extend_from_slice(&ERC721_OWNER_OF);
+				evm_payload.extend_from_slice(&self.erc721_token_id()?.to_be_bytes());
+				let evm_result = Binary::from_base64(
+					&SeiQuerier::new(querier)
+						.static_call(
+							// We don't know who the caller is, but who cares?
+							"sei1llllllllllllllllllllllllllllllllllllllllllllllllllls09qcrc".into(),
+							self.contract.clone(),
+							Binary::from(evm_payload).to_base64(),
+						)?
+						.encoded_data,
+				)?;
+				decode_evm_address_result(&evm_result)
+			}
+		}
+	}
+
+	fn erc721_token_id(&self) -> StdResult<Uint256> {
+		Uint256::from_str(&self.token_id)
+	}
+
+	fn call_evm(&self, data: Vec<u8>) -> CosmosMsg<SeiMsg> {
+		SeiMsg::CallEvm {
+			value: Uint128::zero(),
+			to: self.contract.clone(),
+			data: Binary::from(data).to_base64(),
+		}
+		.into()
+	}
+}
+
+impl fmt::Display for NonFungibleAsset {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}{}/{}", self.kind.prefix(), self.contract, self.token_id)
+	}
+}
+impl FromStr for NonFungibleAsset {
+	type Err = StdError;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (kind, rest) = if let Some(rest) = s.strip_prefix(NonFungibleAssetKind::Cw721.prefix()) {
+			(NonFungibleAssetKind::Cw721, rest)
+		} else if let Some(rest) = s.strip_prefix(NonFungibleAssetKind::Erc721.prefix()) {
+			(NonFungibleAssetKind::Erc721, rest)
+		} else {
+			return Err(StdError::parse_err(
+				"NonFungibleAsset",
+				"identifier must start with \"cw721/\" or \"erc721/\"",
+			));
+		};
+		// Contract addresses never contain a "/", token IDs might.
+		let Some((contract, token_id)) = rest.split_once('/') else {
+			return Err(StdError::parse_err(
+				"NonFungibleAsset",
+				"identifier is missing a token ID",
+			));
+		};
+		if contract.is_empty() || token_id.is_empty() {
+			return Err(StdError::parse_err(
+				"NonFungibleAsset",
+				"identifier has an empty contract address or token ID",
+			));
+		}
+		if kind == NonFungibleAssetKind::Erc721 {
+			parse_ethereum_address(contract)?;
+			Uint256::from_str(token_id)?;
+		}
+		Ok(Self {
+			kind,
+			contract: contract.into(),
+			token_id: token_id.into(),
+		})
+	}
+}
+
+/// Builds an approve or revoke message for a `NonFungibleAsset`.
+pub struct ApprovalMsg<'a> {
+	asset: &'a NonFungibleAsset,
+	spender: Addr,
+	expires: Option<Cw721Expiration>,
+}
+impl<'a> ApprovalMsg<'a> {
+	pub fn new(asset: &'a NonFungibleAsset, spender: &Addr) -> Self {
+		Self {
+			asset,
+			spender: spender.clone(),
+			expires: None,
+		}
+	}
+	/// Only supported by CW721 tokens, ERC721 approvals never expire.
+	pub fn expires(mut self, expires: Cw721Expiration) -> Self {
+		self.expires = Some(expires);
+		self
+	}
+	pub fn try_into_approve_msg(self) -> StdResult<CosmosMsg<SeiMsg>> {
+		match self.asset.kind {
+			NonFungibleAssetKind::Cw721 => Ok(WasmMsg::Execute {
+				contract_addr: self.asset.contract.clone(),
+				msg: to_json_binary(&Cw721ExecuteMsg::Approve {
+					spender: self.spender.into_string(),
+					token_id: self.asset.token_id.clone(),
+					expires: self.expires,
+				})?,
+				funds: vec![],
+			}
+			.into()),
+			NonFungibleAssetKind::Erc721 => {
+				if self.expires.is_some() {
+					return Err(StdError::generic_err("ERC721 approvals cannot expire"));
+				}
+				self.erc721_approve(evm_address_word(&self.spender)?)
+			}
+		}
+	}
+	pub fn try_into_revoke_msg(self) -> StdResult<CosmosMsg<SeiMsg>> {
+		match self.asset.kind {
+			NonFungibleAssetKind::Cw721 => Ok(WasmMsg::Execute {
+				contract_addr: self.asset.contract.clone(),
+				msg: to_json_binary(&Cw721ExecuteMsg::Revoke {
+					spender: self.spender.into_string(),
+					token_id: self.asset.token_id.clone(),
+				})?,
+				funds: vec![],
+			}
+			.into()),
+			// ERC721 tokens only have one approved address, revoking is done by approving the zero address.
+			NonFungibleAssetKind::Erc721 => self.erc721_approve([0; 32]),
+		}
+	}
+	fn erc721_approve(&self, spender_word: [u8; 32]) -> StdResult<CosmosMsg<SeiMsg>> {
+		let mut data = Vec::with_capacity(68);
+		data.extend_from_slice(&ERC721_APPROVE);
+		data.extend_from_slice(&spender_word);
+		data.extend_from_slice(&self.asset.erc721_token_id()?.to_be_bytes());
+		Ok(self.asset.call_evm(data))
+	}
+}
+
+/// Mirrors `cw_utils::Expiration`
+#[cw_serde]
+pub enum Cw721Expiration {
+	AtHeight(u64),
+	AtTime(Timestamp),
+	Never {},
+}
+
+/// The subset of the cw721 execute messages used by `NonFungibleAsset`
+#[cw_serde]
+pub enum Cw721ExecuteMsg {
+	TransferNft {
+		recipient: String,
+		token_id: String,
+	},
+	Approve {
+		spender: String,
+		token_id: String,
+		expires: Option<Cw721Expiration>,
+	},
+	Revoke {
+		spender: String,
+		token_id: String,
+	},
+}
+
+/// The subset of the cw721 query messages used by `NonFungibleAsset`
+#[cw_serde]
+pub enum Cw721QueryMsg {
+	OwnerOf {
+		token_id: String,
+		include_expired: Option<bool>,
+	},
+}
+
+#[cw_serde]
+pub struct Cw721Approval {
+	pub spender: String,
+	pub expires: Cw721Expiration,
+}
+
+#[cw_serde]
+pub struct Cw721OwnerOfResponse {
+	pub owner: String,
+	pub approvals: Vec<Cw721Approval>,
+}
+
+/// Left-pads the EVM address to a 32-byte ABI word.
+pub(crate) fn evm_address_word(addr: &Addr) -> StdResult<[u8; 32]> {
+	let mut word = [0u8; 32];
+	if addr.as_str().starts_with("0x") {
+		word[12..].copy_from_slice(&parse_ethereum_address(addr.as_str())?);
+		return Ok(word);
+	}
+	let canon_addr = SeiCanonicalAddr::try_from(addr)?;
+	if canon_addr.is_externally_owned_address() {
+		return Err(StdError::generic_err(format!(
+			"{addr} is an EOA, its 0x* address must be looked up first"
+		)));
+	}
+	word[12..].copy_from_slice(&canon_addr.as_slice()[12..]);
+	Ok(word)
+}
+
+/// Decodes an ABI-encoded `address` return value as a 0x\* address
+fn decode_evm_address_result(result: &[u8]) -> StdResult<String> {
+	if result.len() != 32 {
+		return Err(StdError::parse_err(
+			"address",
+			"EVM call did not return a 32 byte long result",
+		));
+	}
+	if result[..12] != [0; 12] {
+		return Err(StdError::parse_err(
+			"address",
+			"EVM call returned a value which isn't an address",
+		));
+	}
+	bytes_to_ethereum_address(&result[12..])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use cosmwasm_std::{from_json, testing::MockQuerier, to_json_binary, ContractResult, SystemResult, WasmQuery};
+	use sei_cosmwasm::{SeiQuery, StaticCallResponse};
+	use tiny_keccak::{Hasher, Keccak};
+
+	const ERC721_CONTRACT: [u8; 20] = [0x11; 20];
+
+	fn selector(signature: &str) -> [u8; 4] {
+		let mut hash = [0u8; 32];
+		let mut hasher = Keccak::v256();
+		hasher.update(signature.as_bytes());
+		hasher.finalize(&mut hash);
+		hash[..4].try_into().unwrap()
+	}
+
+	#[test]
+	fn selectors() {
+		assert_eq!(
+			ERC721_SAFE_TRANSFER_FROM,
+			selector("safeTransferFrom(address,address,uint256)")
+		);
+		assert_eq!(ERC721_APPROVE, selector("approve(address,uint256)"));
+		assert_eq!(ERC721_OWNER_OF, selector("ownerOf(uint256)"));
+	}
+
+	#[test]
+	fn erc721_transfer_calldata() {
+		let asset = NonFungibleAsset::erc721(&ERC721_CONTRACT, "258").unwrap();
+		let from = Addr::unchecked(format!("0x{}", "aa".repeat(20)));
+		let to = Addr::unchecked(format!("0x{}", "bb".repeat(20)));
+		let CosmosMsg::Custom(SeiMsg::CallEvm {
+			value,
+			to: contract,
+			data,
+		}) = asset.transfer_to_msg(&from, &to)
+		else {
+			panic!("expected a CallEvm message");
+		};
+		assert_eq!(value, Uint128::zero());
+		assert_eq!(contract, asset.contract);
+
+		let data = Binary::from_base64(&data).unwrap();
+		assert_eq!(data.len(), 100);
+		assert_eq!(data[..4], ERC721_SAFE_TRANSFER_FROM);
+		assert_eq!(data[4..16], [0; 12]);
+		assert_eq!(data[16..36], [0xaa; 20]);
+		assert_eq!(data[36..48], [0; 12]);
+		assert_eq!(data[48..68], [0xbb; 20]);
+		assert_eq!(data[68..98], [0; 30]);
+		assert_eq!(data[98..], [1, 2]);
+
+		// Non-numeric token IDs and sei1* EOAs can't be encoded
+		let bad_asset = NonFungibleAsset::erc721(&ERC721_CONTRACT, "nope").unwrap();
+		assert!(bad_asset.try_transfer_to_msg(&from, &to).is_err());
+		let eoa = Addr::unchecked(SeiCanonicalAddr::from([0x22; 20]).to_string());
+		assert!(asset.try_transfer_to_msg(&from, &eoa).is_err());
+	}
+
+	#[test]
+	fn approvals() {
+		let spender = Addr::unchecked(format!("0x{}", "cc".repeat(20)));
+		let asset = NonFungibleAsset::erc721(&ERC721_CONTRACT, "1").unwrap();
+		let CosmosMsg::Custom(SeiMsg::CallEvm { data, .. }) =
+			asset.approval_msg(&spender).try_into_revoke_msg().unwrap()
+		else {
+			panic!("expected a CallEvm message");
+		};
+		let data = Binary::from_base64(&data).unwrap();
+		assert_eq!(data[..4], ERC721_APPROVE);
+		assert_eq!(data[4..36], [0; 32]);
+		assert_eq!(data[67], 1);
+		assert!(asset
+			.approval_msg(&spender)
+			.expires(Cw721Expiration::AtHeight(5))
+			.try_into_approve_msg()
+			.is_err());
+
+		let asset = NonFungibleAsset::cw721("sei1collection", "token/1");
+		let CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) = asset
+			.approval_msg(&Addr::unchecked("sei1spender"))
+			.expires(Cw721Expiration::AtHeight(5))
+			.try_into_approve_msg()
+			.unwrap()
+		else {
+			panic!("expected a WasmMsg::Execute message");
+		};
+		assert_eq!(contract_addr, "sei1collection");
+		assert_eq!(
+			String::from_utf8(msg.0).unwrap(),
+			r#"{"approve":{"spender":"sei1spender","token_id":"token/1","expires":{"at_height":5}}}"#
+		);
+	}
+
+	#[test]
+	fn identifier_round_trip() {
+		let cw721 = NonFungibleAsset::cw721("sei1collection", "token/with/slashes");
+		assert_eq!(cw721.identifier(), "cw721/sei1collection/token/with/slashes");
+		assert_eq!(cw721.identifier().parse::<NonFungibleAsset>().unwrap(), cw721);
+
+		let erc721 = NonFungibleAsset::erc721(&ERC721_CONTRACT, "1234").unwrap();
+		assert_eq!(
+			erc721.identifier(),
+			"erc721/0x1111111111111111111111111111111111111111/1234"
+		);
+		assert_eq!(erc721.identifier().parse::<NonFungibleAsset>().unwrap(), erc721);
+
+		assert_eq!(
+			NonFungibleAsset::deserialize_to_owned(&erc721.serialize_to_owned().unwrap()).unwrap(),
+			erc721
+		);
+
+		assert!("cw20/sei1contract/1".parse::<NonFungibleAsset>().is_err());
+		assert!("cw721/sei1collection".parse::<NonFungibleAsset>().is_err());
+		assert!("cw721/sei1collection/".parse::<NonFungibleAsset>().is_err());
+		assert!("erc721/sei1collection/1".parse::<NonFungibleAsset>().is_err());
+		assert!("erc721/0x1111111111111111111111111111111111111111/abc"
+			.parse::<NonFungibleAsset>()
+			.is_err());
+	}
+
+	#[test]
+	fn query_owner() {
+		let mut querier =
+			MockQuerier::<SeiQueryWrapper>::new(&[]).with_custom_handler(|query| match &query.query_data {
+				SeiQuery::StaticCall { data, .. } => {
+					let data = Binary::from_base64(data).unwrap();
+					assert_eq!(data[..4], ERC721_OWNER_OF);
+					let mut result = [0u8; 32];
+					result[12..].copy_from_slice(&[0xdd; 20]);
+					SystemResult::Ok(ContractResult::Ok(
+						to_json_binary(&StaticCallResponse {
+							encoded_data: Binary::from(result).to_base64(),
+						})
+						.unwrap(),
+					))
+				}
+				_ => panic!("unexpected query"),
+			});
+		querier.update_wasm(|query| {
+			let WasmQuery::Smart { contract_addr, msg } = query else {
+				panic!("unexpected query");
+			};
+			assert_eq!(contract_addr, "sei1collection");
+			let Cw721QueryMsg::OwnerOf { token_id, .. } = from_json(msg).unwrap();
+			assert_eq!(token_id, "7");
+			SystemResult::Ok(ContractResult::Ok(
+				br#"{"owner":"sei1owner","approvals":[{"spender":"sei1spender","expires":{"never":{}}}]}"#.into(),
+			))
+		});
+		let querier = QuerierWrapper::<SeiQueryWrapper>::new(&querier);
+
+		assert_eq!(
+			NonFungibleAsset::cw721("sei1collection", "7")
+				.query_owner(&querier)
+				.unwrap(),
+			"sei1owner"
+		);
+		assert_eq!(
+			NonFungibleAsset::erc721(&ERC721_CONTRACT, "7")
+				.unwrap()
+				.query_owner(&querier)
+				.unwrap(),
+			format!("0x{}", "dd".repeat(20))
+		);
+		assert!(decode_evm_address_result(&[0xff; 32]).is_err());
+		assert!(decode_evm_address_result(&[0; 20]).is_err());
+	}
+}
@@ -0,0 +1,260 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Binary, StdResult, Uint128};
+
+use super::asset::{FungibleAsset, FungibleAssetKindString};
+
+/// Opaque pagination cursor. Clients should pass this back verbatim to get the next page.
+#[cw_serde]
+pub struct Cursor(pub Binary);
+impl From<Binary> for Cursor {
+	fn from(value: Binary) -> Self {
+		Self(value)
+	}
+}
+impl From<Vec<u8>> for Cursor {
+	fn from(value: Vec<u8>) -> Self {
+		Self(value.into())
+	}
+}
+impl From<Cursor> for Binary {
+	fn from(value: Cursor) -> Self {
+		value.0
+	}
+}
+
+/// Standard pagination envelope for queries which return a list of things.
+///
+/// `next_cursor` is `None` when there are no more entries, `total` is only set when it's cheap to know.
+#[cw_serde]
+pub struct PageResponse<T> {
+	pub entries: Vec<T>,
+	pub next_cursor: Option<Cursor>,
+	pub total: Option<u64>,
+}
+impl<T> PageResponse<T> {
+	pub fn new(entries: Vec<T>, next_cursor: Option<Cursor>) -> Self {
+		Self {
+			entries,
+			next_cursor,
+			total: None,
+		}
+	}
+	pub fn with_total(mut self, total: u64) -> Self {
+		self.total = Some(total);
+		self
+	}
+	/// Converts each entry, keeping the cursor and total as-is
+	pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> PageResponse<U> {
+		PageResponse {
+			entries: self.entries.into_iter().map(f).collect(),
+			next_cursor: self.next_cursor,
+			total: self.total,
+		}
+	}
+}
+impl<T> From<(Vec<T>, Option<Cursor>)> for PageResponse<T> {
+	fn from((entries, next_cursor): (Vec<T>, Option<Cursor>)) -> Self {
+		Self::new(entries, next_cursor)
+	}
+}
+impl<T> From<Page<T>> for PageResponse<T> {
+	fn from(value: Page<T>) -> Self {
+		Self::new(value.entries, value.next_cursor)
+	}
+}
+
+/// A page of entries returned by [paginate].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+	pub entries: Vec<T>,
+	/// The cursor of the last entry, `None` if there are no more entries after it.
+	pub next_cursor: Option<Cursor>,
+}
+
+/// Takes up to `limit` entries from `iter`, which should start after the cursor the client passed in. The limit is
+/// clamped to `1..=max_limit`.
+///
+/// If there are more entries, `cursor` is called with the last entry taken to get the cursor of the next page. The
+/// iterator is advanced past the last entry to check this, so it must be lazy.
+pub fn paginate<T>(
+	iter: impl Iterator<Item = StdResult<T>>,
+	limit: u32,
+	max_limit: u32,
+	cursor: impl FnOnce(&T) -> Cursor,
+) -> StdResult<Page<T>> {
+	let limit = limit.clamp(1, max_limit.max(1)) as usize;
+	let mut iter = iter.peekable();
+	let entries = iter.by_ref().take(limit).collect::<StdResult<Vec<_>>>()?;
+	let next_cursor = match (iter.peek(), entries.last()) {
+		(Some(_), Some(last)) => Some(cursor(last)),
+		_ => None,
+	};
+	Ok(Page { entries, next_cursor })
+}
+
+/// An asset amount in the form frontends expect: the prefixed identifier and the amount as a string.
+#[cw_serde]
+pub struct AssetAmountResponse {
+	pub kind: FungibleAssetKindString,
+	pub amount: Uint128,
+}
+impl From<FungibleAsset> for AssetAmountResponse {
+	fn from(value: FungibleAsset) -> Self {
+		let (kind, amount) = value.into_asset_kind_string_and_amount();
+		Self {
+			kind,
+			amount: amount.into(),
+		}
+	}
+}
+impl From<AssetAmountResponse> for FungibleAsset {
+	fn from(value: AssetAmountResponse) -> Self {
+		value.kind.into_asset(value.amount)
+	}
+}
+
+#[cw_serde]
+pub struct BalanceListResponse {
+	pub balances: Vec<AssetAmountResponse>,
+}
+impl<I: Into<AssetAmountResponse>> FromIterator<I> for BalanceListResponse {
+	fn from_iter<T: IntoIterator<Item = I>>(iter: T) -> Self {
+		Self {
+			balances: iter.into_iter().map(Into::into).collect(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use cosmwasm_schema::{schemars::schema_for, QueryResponses};
+	use cosmwasm_std::{to_json_string, Coin, Empty};
+	use crownfi_sei_sdk_autogen::CrownfiSdkMaker;
+	use cw20::Cw20Coin;
+
+	#[test]
+	fn schema_names_are_stable() {
+		let schema = schema_for!(PageResponse<AssetAmountResponse>);
+		assert_eq!(
+			schema.schema.metadata.unwrap().title.as_deref(),
+			Some("PageResponse_for_AssetAmountResponse")
+		);
+		assert!(schema.definitions.contains_key("AssetAmountResponse"));
+		assert!(schema.definitions.contains_key("Cursor"));
+		assert!(schema.definitions.contains_key("FungibleAssetKindString"));
+
+		let schema = schema_for!(BalanceListResponse);
+		assert_eq!(
+			schema.schema.metadata.unwrap().title.as_deref(),
+			Some("BalanceListResponse")
+		);
+		assert!(schema.definitions.contains_key("AssetAmountResponse"));
+	}
+
+	#[cw_serde]
+	enum NoMsg {}
+
+	#[cw_serde]
+	enum NoopMsg {
+		Noop {},
+	}
+
+	#[cw_serde]
+	#[derive(QueryResponses)]
+	enum PoolQueryMsg {
+		#[returns(PageResponse<AssetAmountResponse>)]
+		Reserves {},
+	}
+
+	#[cw_serde]
+	#[derive(QueryResponses)]
+	enum VaultQueryMsg {
+		#[returns(PageResponse<AssetAmountResponse>)]
+		Deposits {},
+		#[returns(BalanceListResponse)]
+		Balances {},
+	}
+
+	#[test]
+	fn shared_definitions_merge_in_the_sdk() {
+		let mut sdk_maker = CrownfiSdkMaker::new();
+		sdk_maker
+			.add_contract::<Empty, NoopMsg, PoolQueryMsg, NoMsg, NoMsg, NoopMsg>("pool")
+			.unwrap()
+			.add_contract::<Empty, NoopMsg, VaultQueryMsg, NoMsg, NoMsg, NoopMsg>("vault")
+			.unwrap();
+		let sdk = sdk_maker.generate_in_memory().unwrap();
+		let types = sdk.get("types.ts").unwrap();
+		for name in [
+			"PageResponseFor_AssetAmountResponse",
+			"AssetAmountResponse",
+			"BalanceListResponse",
+			"Cursor",
+			"FungibleAssetKindString",
+		] {
+			let declarations = types.matches(&format!("export interface {name} {{")).count()
+				+ types.matches(&format!("export type {name} =")).count();
+			assert_eq!(declarations, 1, "{name} should be declared once");
+		}
+		for contract in ["pool.ts", "vault.ts"] {
+			assert!(sdk
+				.get(contract)
+				.unwrap()
+				.contains("Promise<PageResponseFor_AssetAmountResponse>"));
+		}
+	}
+
+	#[test]
+	fn paginate_pages() -> StdResult<()> {
+		let page_after = |start_after: Option<u8>, limit| {
+			let iter = (0..5u8)
+				.filter(move |n| start_after.is_none_or(|start_after| *n > start_after))
+				.map(Ok);
+			paginate(iter, limit, 3, |n| Cursor::from(vec![*n]))
+		};
+		let page = page_after(None, 2)?;
+		assert_eq!(page.entries, [0, 1]);
+		assert_eq!(page.next_cursor, Some(Cursor::from(vec![1])));
+
+		// The limit is clamped
+		let page = page_after(Some(1), 10)?;
+		assert_eq!(page.entries, [2, 3, 4]);
+		assert_eq!(page.next_cursor, None);
+		assert_eq!(page_after(None, 0)?.entries, [0]);
+
+		let response: PageResponse<u8> = page_after(Some(0), 3)?.into();
+		assert_eq!(response, PageResponse::new(vec![1, 2, 3], Some(Cursor::from(vec![3]))));
+
+		// Errors are returned as-is
+		let iter = [Ok(1), Err(cosmwasm_std::StdError::generic_err("oops"))].into_iter();
+		assert!(paginate(iter, 2, 3, |n| Cursor::from(vec![*n])).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn page_response_json() {
+		let page: PageResponse<AssetAmountResponse> = PageResponse::new(
+			vec![
+				FungibleAsset::Native(Coin::new(100, "usei")).into(),
+				FungibleAsset::CW20(Cw20Coin {
+					address: "sei1contract".into(),
+					amount: 5u128.into(),
+				})
+				.into(),
+			],
+			Some(Cursor::from(vec![1, 2, 3])),
+		)
+		.with_total(2);
+		assert_eq!(
+			to_json_string(&page).unwrap(),
+			r#"{"entries":[{"kind":"usei","amount":"100"},{"kind":"cw20/sei1contract","amount":"5"}],"next_cursor":"AQID","total":2}"#
+		);
+
+		let last_page: PageResponse<AssetAmountResponse> = (vec![], None).into();
+		assert_eq!(
+			to_json_string(&last_page).unwrap(),
+			r#"{"entries":[],"next_cursor":null,"total":null}"#
+		);
+	}
+}
@@ -0,0 +1,434 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{StdError, StdResult, Timestamp, Uint128};
+use serde::Deserialize;
+
+use super::canonical_addr::SeiCanonicalAddr;
+use crate::{
+	extentions::timestamp::TimestampExtentions,
+	impl_serializable_borsh,
+	storage::{map::StoredMap, SerializableItem},
+};
+
+/// When an amount becomes available, e.g. for team token lockups or LP incentives. All times are in milliseconds
+/// since epoch, see [TimestampExtentions].
+///
+/// Vested amounts are always rounded down, and the full amount is vested once the schedule has ended. Schedules are
+/// validated when constructed and when deserialized from JSON.
+#[cw_serde]
+#[derive(Copy, Eq, Hash, BorshSerialize, BorshDeserialize)]
+#[serde(try_from = "UncheckedVestingSchedule")]
+pub enum VestingSchedule {
+	/// Vests continuously from `start` to `end`.
+	Linear { start: u64, end: u64 },
+	/// Vests continuously from `start` to `end`, but nothing is available until `cliff`, when everything which vested
+	/// up to then becomes available at once.
+	CliffThenLinear { cliff: u64, start: u64, end: u64 },
+	/// Vests `1 / count` of the amount at the end of each of the `count` periods, the first one starting at `start`.
+	Periodic { start: u64, period: u64, count: u32 },
+}
+impl_serializable_borsh!(VestingSchedule);
+
+/// Same as [VestingSchedule], so that it can be validated when deserialized.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum UncheckedVestingSchedule {
+	Linear { start: u64, end: u64 },
+	CliffThenLinear { cliff: u64, start: u64, end: u64 },
+	Periodic { start: u64, period: u64, count: u32 },
+}
+
+impl TryFrom<UncheckedVestingSchedule> for VestingSchedule {
+	type Error = StdError;
+	fn try_from(value: UncheckedVestingSchedule) -> Result<Self, Self::Error> {
+		let schedule = match value {
+			UncheckedVestingSchedule::Linear { start, end } => VestingSchedule::Linear { start, end },
+			UncheckedVestingSchedule::CliffThenLinear { cliff, start, end } => {
+				VestingSchedule::CliffThenLinear { cliff, start, end }
+			}
+			UncheckedVestingSchedule::Periodic { start, period, count } => {
+				VestingSchedule::Periodic { start, period, count }
+			}
+		};
+		schedule.validate()?;
+		Ok(schedule)
+	}
+}
+
+impl VestingSchedule {
+	pub fn linear(start: Timestamp, end: Timestamp) -> StdResult<Self> {
+		let schedule = Self::Linear {
+			start: start.millis(),
+			end: end.millis(),
+		};
+		schedule.validate()?;
+		Ok(schedule)
+	}
+
+	pub fn cliff_then_linear(cliff: Timestamp, start: Timestamp, end: Timestamp) -> StdResult<Self> {
+		let schedule = Self::CliffThenLinear {
+			cliff: cliff.millis(),
+			start: start.millis(),
+			end: end.millis(),
+		};
+		schedule.validate()?;
+		Ok(schedule)
+	}
+
+	pub fn periodic(start: Timestamp, period_ms: u64, count: u32) -> StdResult<Self> {
+		let schedule = Self::Periodic {
+			start: start.millis(),
+			period: period_ms,
+			count,
+		};
+		schedule.validate()?;
+		Ok(schedule)
+	}
+
+	/// Checks that `end > start`, that the cliff is within them, and that periodic schedules have a non-zero period
+	/// and count and end before the end of time.
+	pub fn validate(&self) -> StdResult<()> {
+		match *self {
+			VestingSchedule::Linear { start, end } => {
+				if end <= start {
+					return Err(StdError::generic_err("Vesting schedule must end after it starts"));
+				}
+			}
+			VestingSchedule::CliffThenLinear { cliff, start, end } => {
+				if end <= start {
+					return Err(StdError::generic_err("Vesting schedule must end after it starts"));
+				}
+				if cliff < start || cliff > end {
+					return Err(StdError::generic_err(
+						"Vesting schedule's cliff must be between its start and end",
+					));
+				}
+			}
+			VestingSchedule::Periodic { period, count, .. } => {
+				if period == 0 || count == 0 {
+					return Err(zero_period_error());
+				}
+				self.end()?;
+			}
+		}
+		Ok(())
+	}
+
+	/// The time (in milliseconds) from which the full amount is vested. Returns an error if a periodic schedule ends
+	/// after `u64::MAX`.
+	pub fn end(&self) -> StdResult<u64> {
+		match *self {
+			VestingSchedule::Linear { end, .. } => Ok(end),
+			VestingSchedule::CliffThenLinear { end, .. } => Ok(end),
+			VestingSchedule::Periodic { start, period, count } => period
+				.checked_mul(count as u64)
+				.and_then(|duration| duration.checked_add(start))
+				.ok_or_else(|| StdError::generic_err("Vesting schedule ends too far in the future")),
+		}
+	}
+
+	/// Returns how much of `total` has vested at `now`, rounded down.
+	///
+	/// The variants can be constructed without being validated, so this returns an error for periodic schedules with
+	/// a period of 0 or which end after `u64::MAX`.
+	pub fn vested_amount(&self, total: Uint128, now: Timestamp) -> StdResult<Uint128> {
+		if let VestingSchedule::Periodic { period: 0, .. } | VestingSchedule::Periodic { count: 0, .. } = self {
+			return Err(zero_period_error());
+		}
+		let now = now.millis();
+		if now >= self.end()? {
+			return Ok(total);
+		}
+		Ok(match *self {
+			VestingSchedule::Linear { start, end } => linear_vested_amount(total, start, end, now),
+			VestingSchedule::CliffThenLinear { cliff, start, end } => {
+				if now < cliff {
+					return Ok(Uint128::zero());
+				}
+				linear_vested_amount(total, start, end, now)
+			}
+			VestingSchedule::Periodic { start, period, count } => {
+				if now < start {
+					return Ok(Uint128::zero());
+				}
+				let elapsed_periods = (now - start) / period;
+				total.multiply_ratio(elapsed_periods, count as u64)
+			}
+		})
+	}
+
+	/// Returns how much of `total` can be claimed at `now`, given that `already_claimed` has been claimed before.
+	pub fn claimable(&self, total: Uint128, already_claimed: Uint128, now: Timestamp) -> StdResult<Uint128> {
+		Ok(self.vested_amount(total, now)?.saturating_sub(already_claimed))
+	}
+}
+
+fn zero_period_error() -> StdError {
+	StdError::generic_err("Vesting schedule's period and period count must be non-zero")
+}
+
+/// Assumes `now < end`
+fn linear_vested_amount(total: Uint128, start: u64, end: u64, now: u64) -> Uint128 {
+	if now <= start {
+		return Uint128::zero();
+	}
+	total.multiply_ratio(now - start, end - start)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct VestingPosition {
+	pub schedule: VestingSchedule,
+	pub total: u128,
+	pub claimed: u128,
+}
+impl_serializable_borsh!(VestingPosition);
+
+impl VestingPosition {
+	pub fn claimable(&self, now: Timestamp) -> StdResult<Uint128> {
+		self.schedule.claimable(self.total.into(), self.claimed.into(), now)
+	}
+}
+
+/// The vesting position of each user, as `user => (schedule, total, claimed)`.
+pub struct VestingPositionMap {
+	map: StoredMap<SeiCanonicalAddr, VestingPosition>,
+}
+
+impl VestingPositionMap {
+	pub fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			map: StoredMap::new(namespace),
+		}
+	}
+
+	pub fn get(&self, user: &SeiCanonicalAddr) -> StdResult<Option<VestingPosition>> {
+		Ok(self.map.get(user)?.map(|position| position.into_inner()))
+	}
+
+	/// Gives the user a new position, returning an error if they already have one.
+	pub fn create(&self, user: &SeiCanonicalAddr, schedule: VestingSchedule, total: Uint128) -> StdResult<()> {
+		schedule.validate()?;
+		if self.map.has(user) {
+			return Err(StdError::generic_err(format!("{user} already has a vesting position")));
+		}
+		self.map.set(
+			user,
+			&VestingPosition {
+				schedule,
+				total: total.u128(),
+				claimed: 0,
+			},
+		)
+	}
+
+	/// Removes the user's position, returning it so that whatever hasn't been claimed can be returned.
+	pub fn remove(&self, user: &SeiCanonicalAddr) -> StdResult<Option<VestingPosition>> {
+		let position = self.get(user)?;
+		self.map.remove(user);
+		Ok(position)
+	}
+
+	pub fn claimable(&self, user: &SeiCanonicalAddr, now: Timestamp) -> StdResult<Uint128> {
+		self.get(user)?
+			.map_or(Ok(Uint128::zero()), |position| position.claimable(now))
+	}
+
+	/// Marks everything which has vested by `now` as claimed, returning the amount which wasn't claimed before.
+	pub fn claim(&self, user: &SeiCanonicalAddr, now: Timestamp) -> StdResult<Uint128> {
+		let mut position = self
+			.get(user)?
+			.ok_or_else(|| StdError::not_found(format!("Vesting position of {user}")))?;
+		let claimable = position.claimable(now)?;
+		if !claimable.is_zero() {
+			position.claimed += claimable.u128();
+			self.map.set(user, &position)?;
+		}
+		Ok(claimable)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::{from_json, to_json_string};
+	use proptest::prelude::*;
+
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	fn at(millis: u64) -> Timestamp {
+		Timestamp::from_millis(millis)
+	}
+
+	fn any_schedule() -> impl Strategy<Value = VestingSchedule> {
+		prop_oneof![
+			(0..1_000_000u64, 1..1_000_000u64).prop_map(|(start, duration)| VestingSchedule::Linear {
+				start,
+				end: start + duration,
+			}),
+			(0..1_000_000u64, 1..1_000_000u64, 0..=1_000_000u64).prop_map(|(start, duration, cliff)| {
+				VestingSchedule::CliffThenLinear {
+					cliff: start + cliff % (duration + 1),
+					start,
+					end: start + duration,
+				}
+			}),
+			(0..1_000_000u64, 1..10_000u64, 1..100u32).prop_map(|(start, period, count)| VestingSchedule::Periodic {
+				start,
+				period,
+				count
+			}),
+		]
+	}
+
+	proptest! {
+		#[test]
+		fn never_over_vests(schedule in any_schedule(), total: u128, now in 0..3_000_000u64, later in 0..1_000_000u64) {
+			let total = Uint128::new(total);
+			let vested = schedule.vested_amount(total, at(now)).unwrap();
+			prop_assert!(vested <= total);
+			// Vesting never goes backwards
+			prop_assert!(schedule.vested_amount(total, at(now + later)).unwrap() >= vested);
+			prop_assert_eq!(schedule.vested_amount(total, at(schedule.end().unwrap())).unwrap(), total);
+		}
+	}
+
+	#[test]
+	fn boundary_instants() {
+		let total = Uint128::new(1_000);
+		let linear = VestingSchedule::linear(at(1_000), at(4_000)).unwrap();
+		assert_eq!(linear.vested_amount(total, at(0)).unwrap(), Uint128::zero());
+		assert_eq!(linear.vested_amount(total, at(1_000)).unwrap(), Uint128::zero());
+		assert_eq!(linear.vested_amount(total, at(1_001)).unwrap(), Uint128::zero());
+		assert_eq!(linear.vested_amount(total, at(1_003)).unwrap(), Uint128::one());
+		// 333.33...
+		assert_eq!(linear.vested_amount(total, at(2_000)).unwrap(), Uint128::new(333));
+		assert_eq!(linear.vested_amount(total, at(3_999)).unwrap(), Uint128::new(999));
+		assert_eq!(linear.vested_amount(total, at(4_000)).unwrap(), total);
+		assert_eq!(linear.vested_amount(total, at(u64::MAX / 1_000_000)).unwrap(), total);
+		assert!(linear.vested_amount(Uint128::MAX, at(3_999)).unwrap() < Uint128::MAX);
+
+		let cliff = VestingSchedule::cliff_then_linear(at(2_000), at(1_000), at(5_000)).unwrap();
+		assert_eq!(cliff.vested_amount(total, at(1_500)).unwrap(), Uint128::zero());
+		assert_eq!(cliff.vested_amount(total, at(1_999)).unwrap(), Uint128::zero());
+		assert_eq!(cliff.vested_amount(total, at(2_000)).unwrap(), Uint128::new(250));
+		assert_eq!(cliff.vested_amount(total, at(4_999)).unwrap(), Uint128::new(999));
+		assert_eq!(cliff.vested_amount(total, at(5_000)).unwrap(), total);
+		// A cliff at the end vests everything at once
+		let cliff = VestingSchedule::cliff_then_linear(at(5_000), at(1_000), at(5_000)).unwrap();
+		assert_eq!(cliff.vested_amount(total, at(4_999)).unwrap(), Uint128::zero());
+		assert_eq!(cliff.vested_amount(total, at(5_000)).unwrap(), total);
+
+		// Sub-millisecond precision is ignored
+		assert_eq!(
+			linear
+				.vested_amount(total, Timestamp::from_nanos(1_002_999_999))
+				.unwrap(),
+			Uint128::zero()
+		);
+	}
+
+	#[test]
+	fn periodic_sums_to_total() {
+		let total = Uint128::new(1_000);
+		let periodic = VestingSchedule::periodic(at(1_000), 100, 3).unwrap();
+		assert_eq!(periodic.end().unwrap(), 1_300);
+		let vested_at = |millis| periodic.vested_amount(total, at(millis)).unwrap();
+		assert_eq!(vested_at(999), Uint128::zero());
+		assert_eq!(vested_at(1_000), Uint128::zero());
+		assert_eq!(vested_at(1_099), Uint128::zero());
+		assert_eq!(vested_at(1_100), Uint128::new(333));
+		assert_eq!(vested_at(1_200), Uint128::new(666));
+		assert_eq!(vested_at(1_299), Uint128::new(666));
+		assert_eq!(vested_at(1_300), total);
+
+		// Claiming after each period adds up to exactly the total
+		let mut claimed = Uint128::zero();
+		for millis in [1_100, 1_150, 1_200, 1_300, 1_400] {
+			claimed += periodic.claimable(total, claimed, at(millis)).unwrap();
+		}
+		assert_eq!(claimed, total);
+		assert_eq!(
+			VestingSchedule::periodic(at(0), 1, u32::MAX)
+				.unwrap()
+				.vested_amount(Uint128::MAX, at(u32::MAX as u64))
+				.unwrap(),
+			Uint128::MAX
+		);
+	}
+
+	#[test]
+	fn validation() {
+		assert!(VestingSchedule::linear(at(1_000), at(1_000)).is_err());
+		assert!(VestingSchedule::linear(at(1_000), at(999)).is_err());
+		assert!(VestingSchedule::cliff_then_linear(at(999), at(1_000), at(2_000)).is_err());
+		assert!(VestingSchedule::cliff_then_linear(at(2_001), at(1_000), at(2_000)).is_err());
+		assert!(VestingSchedule::cliff_then_linear(at(1_000), at(1_000), at(2_000)).is_ok());
+		assert!(VestingSchedule::periodic(at(1_000), 0, 3).is_err());
+		assert!(VestingSchedule::periodic(at(1_000), 100, 0).is_err());
+		assert!(VestingSchedule::Periodic {
+			start: 1,
+			period: u64::MAX,
+			count: 1
+		}
+		.validate()
+		.is_err());
+
+		// Unvalidated schedules are errors rather than panics
+		let zero_period = VestingSchedule::Periodic {
+			start: 1_000,
+			period: 0,
+			count: 3,
+		};
+		assert!(zero_period.vested_amount(Uint128::new(1_000), at(1_500)).is_err());
+		let past_the_end_of_time = VestingSchedule::Periodic {
+			start: u64::MAX - 1,
+			period: 2,
+			count: 1,
+		};
+		assert!(past_the_end_of_time.end().is_err());
+		assert!(past_the_end_of_time
+			.claimable(Uint128::new(1_000), Uint128::zero(), at(1_500))
+			.is_err());
+
+		let schedule = VestingSchedule::cliff_then_linear(at(1_500), at(1_000), at(2_000)).unwrap();
+		let json = to_json_string(&schedule).unwrap();
+		assert_eq!(json, r#"{"cliff_then_linear":{"cliff":1500,"start":1000,"end":2000}}"#);
+		assert_eq!(from_json::<VestingSchedule>(&json).unwrap(), schedule);
+		assert!(from_json::<VestingSchedule>(r#"{"linear":{"start":2000,"end":1000}}"#).is_err());
+		assert!(from_json::<VestingSchedule>(r#"{"periodic":{"start":0,"period":0,"count":1}}"#).is_err());
+
+		let stored = schedule.serialize_to_owned().unwrap();
+		// The variant index and 3 u64's
+		assert_eq!(stored.len(), 25);
+		assert_eq!(VestingSchedule::deserialize_to_owned(&stored).unwrap(), schedule);
+	}
+
+	#[test]
+	fn claim_positions() -> TestingResult {
+		let _storage = isolated()?;
+		let positions = VestingPositionMap::new(NAMESPACE);
+		let alice = SeiCanonicalAddr::from([1; 32]);
+		let bob = SeiCanonicalAddr::from([2; 32]);
+		let schedule = VestingSchedule::linear(at(1_000), at(2_000))?;
+		positions.create(&alice, schedule, Uint128::new(500))?;
+		assert!(positions.create(&alice, schedule, Uint128::new(1)).is_err());
+		assert!(positions.claim(&bob, at(1_500)).is_err());
+		assert_eq!(positions.claimable(&bob, at(1_500))?, Uint128::zero());
+
+		assert_eq!(positions.claim(&alice, at(1_000))?, Uint128::zero());
+		assert_eq!(positions.claimable(&alice, at(1_500))?, Uint128::new(250));
+		assert_eq!(positions.claim(&alice, at(1_500))?, Uint128::new(250));
+		// Claiming again at the same time gives nothing
+		assert_eq!(positions.claim(&alice, at(1_500))?, Uint128::zero());
+		assert_eq!(positions.get(&alice)?.unwrap().claimed, 250);
+		assert_eq!(positions.claim(&alice, at(1_501))?, Uint128::zero());
+		assert_eq!(positions.claim(&alice, at(1_999))?, Uint128::new(249));
+		assert_eq!(positions.claim(&alice, at(5_000))?, Uint128::new(1));
+		assert_eq!(positions.claim(&alice, at(5_000))?, Uint128::zero());
+
+		let position = positions.remove(&alice)?.unwrap();
+		assert_eq!((position.total, position.claimed), (500, 500));
+		assert_eq!(positions.get(&alice)?, None);
+		Ok(())
+	}
+}
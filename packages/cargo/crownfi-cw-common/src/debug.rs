@@ -0,0 +1,129 @@
+//! Support functions for [debug_log!](crate::debug_log) and
+//! [debug_assert_storage_invariant!](crate::debug_assert_storage_invariant).
+use std::fmt::{self, Display, Write};
+
+use crate::{
+	storage::StoragePairIterator,
+	utils::{escape_key_bytes, lexicographic_next},
+};
+
+/// The maximum amount of entries listed by [dump_namespace].
+pub const DUMP_ENTRY_LIMIT: usize = 64;
+
+/// Formats a log line as `[module] message key=value ...`.
+///
+/// Values which are empty or contain whitespace, `=` or `"` are quoted so the line remains unambiguous.
+pub fn format_log(module: &str, message: fmt::Arguments, fields: &[(&str, &dyn Display)]) -> String {
+	let mut line = format!("[{module}] {message}");
+	for (key, value) in fields {
+		let value = value.to_string();
+		if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '=' || c == '"') {
+			write!(line, " {key}={value:?}").unwrap();
+		} else {
+			write!(line, " {key}={value}").unwrap();
+		}
+	}
+	line
+}
+
+/// Outputs a log line, this is what [debug_log!](crate::debug_log) expands to when enabled.
+///
+/// Prints to stderr on native targets, and uses the `debug` host function on wasm.
+#[cfg(any(not(target_arch = "wasm32"), feature = "debug-print"))]
+pub fn log(module: &str, message: fmt::Arguments, fields: &[(&str, &dyn Display)]) {
+	let line = format_log(module, message, fields);
+	#[cfg(target_arch = "wasm32")]
+	crate::wasm_api::debug::debug(&line);
+	#[cfg(not(target_arch = "wasm32"))]
+	eprintln!("{line}");
+}
+
+/// Lists the storage entries under the specified namespace, with the namespace stripped from the keys. Keys are
+/// escaped with [escape_key_bytes] and values are hex.
+///
+/// Only the first [DUMP_ENTRY_LIMIT] entries are listed.
+pub fn dump_namespace(namespace: &[u8]) -> String {
+	let mut dump = format!("namespace {}:", escape_key_bytes(namespace));
+	let end = lexicographic_next(namespace);
+	let mut iter = StoragePairIterator::new(Some(namespace), Some(&end));
+	for (key, value) in iter.by_ref().take(DUMP_ENTRY_LIMIT) {
+		write!(
+			dump,
+			"\n  {} => {}",
+			escape_key_bytes(&key[namespace.len()..]),
+			hex::encode(value)
+		)
+		.unwrap();
+	}
+	if iter.next().is_some() {
+		write!(dump, "\n  ...").unwrap();
+	}
+	dump
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::Cell;
+
+	use super::*;
+	use crate::storage::{base::storage_write, testing_common::*};
+
+	#[test]
+	fn log_formatting() {
+		assert_eq!(
+			format_log("my_contract::pool", format_args!("swapped {} for {}", 5, 7), &[]),
+			"[my_contract::pool] swapped 5 for 7"
+		);
+		assert_eq!(
+			format_log(
+				"my_contract::pool",
+				format_args!("swapped"),
+				&[
+					("pool", &1),
+					("denom", &"usei"),
+					("memo", &"hello there"),
+					("empty", &"")
+				]
+			),
+			"[my_contract::pool] swapped pool=1 denom=usei memo=\"hello there\" empty=\"\""
+		);
+		// Smoke test of the macro itself, which prints to stderr
+		let amount = 5;
+		crate::debug_log!("swapped {}", amount; pool = 1, denom = "usei");
+		crate::debug_log!("no fields");
+	}
+
+	#[test]
+	fn disabled_log_evaluates_nothing() {
+		let evaluations = Cell::new(0);
+		let expensive = || {
+			evaluations.set(evaluations.get() + 1);
+			"expensive"
+		};
+		crate::__debug_log_disabled!("value {}", expensive(); field = expensive());
+		crate::__debug_assert_storage_invariant_disabled!(expensive().is_empty(), expensive().as_bytes(), "oh no");
+		assert_eq!(evaluations.get(), 0);
+		// The enabled version does evaluate them
+		crate::debug_log!("value {}", expensive(); field = expensive());
+		assert_eq!(evaluations.get(), 2);
+	}
+
+	#[test]
+	fn invariant_dumps_namespace() -> TestingResult {
+		let _storage_lock = init()?;
+		storage_write(b"testing\x01", b"\xaa");
+		storage_write(b"testing\x02", b"\xbb\xcc");
+		storage_write(b"testinh", b"\xff");
+		crate::debug_assert_storage_invariant!(true, NAMESPACE, "never fails");
+
+		let err = std::panic::catch_unwind(|| {
+			crate::debug_assert_storage_invariant!(1 + 1 == 3, NAMESPACE, "total is {}", 3);
+		})
+		.expect_err("expected a panic");
+		assert_eq!(
+			err.downcast_ref::<String>().unwrap(),
+			"storage invariant violated: total is 3\nnamespace testing:\n  %01 => aa\n  %02 => bbcc"
+		);
+		Ok(())
+	}
+}
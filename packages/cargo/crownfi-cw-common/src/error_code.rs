@@ -0,0 +1,175 @@
+//! Numeric codes for contract errors, so that frontends can tell errors apart without matching their messages.
+//!
+//! Codes are assigned to the variants of a contract's error type with [impl_coded_error](crate::impl_coded_error),
+//! and embedded in the error message as `[E1002] ...` by [CodedError::to_std_error]. The message survives being
+//! wrapped by the chain ("failed to execute message; ..."), and the code can be read back out of it with
+//! [extract_error_code]. The generated TypeScript SDK does the same given the error type's `ERROR_CODES`, see
+//! `CrownfiSdkMaker::add_contract_errors` in crownfi-sei-sdk-autogen.
+use std::fmt::Display;
+
+use cosmwasm_std::StdError;
+
+pub trait CodedError: Display {
+	/// The code of this error, which is unique among the variants of the error type.
+	fn code(&self) -> u32;
+	/// Which contract or module the error came from.
+	fn category(&self) -> &'static str;
+	/// Converts this into a [StdError] whose message starts with the code, see [format_coded_error].
+	fn to_std_error(&self) -> StdError {
+		StdError::generic_err(format_coded_error(self.code(), self))
+	}
+}
+
+/// Returns `"[E{code}] {msg}"`, the format [extract_error_code] parses.
+pub fn format_coded_error(code: u32, msg: impl Display) -> String {
+	format!("[E{code}] {msg}")
+}
+
+/// Returns the code of the first `[E{code}]` in the message, which may have been prefixed with other text.
+pub fn extract_error_code(msg: &str) -> Option<u32> {
+	msg.match_indices("[E").find_map(|(index, _)| {
+		let digits = &msg[index + 2..];
+		let digits = &digits[..digits.find(']')?];
+		if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+			return None;
+		}
+		digits.parse().ok()
+	})
+}
+
+/// Used by [impl_coded_error](crate::impl_coded_error) to check that no codes are used twice at compile time.
+#[doc(hidden)]
+pub const fn error_codes_are_distinct(codes: &[u32]) -> bool {
+	let mut i = 0;
+	while i < codes.len() {
+		let mut ii = i + 1;
+		while ii < codes.len() {
+			if codes[i] == codes[ii] {
+				return false;
+			}
+			ii += 1;
+		}
+		i += 1;
+	}
+	true
+}
+
+#[macro_export]
+/// `impl_coded_error!(YourErrorType { VariantA = 1001, VariantB = 1002, ... })`
+///
+/// Implements [CodedError](crate::error_code::CodedError) for `YourErrorType`, along with an
+/// `YourErrorType::ERROR_CODES` table of `(variant name, code)` for the SDK generator.
+///
+/// Every variant must be listed (the generated `match` is exhaustive), and codes must be distinct, which is checked at
+/// compile time. The category is the type's name, unless specified with `impl_coded_error!(YourErrorType as "pool" {
+/// ... })`.
+macro_rules! impl_coded_error {
+	($error_type:ident { $($variant:ident = $code:literal),+ $(,)? }) => {
+		$crate::impl_coded_error!(@impl $error_type, stringify!($error_type), $($variant = $code),+);
+	};
+	($error_type:ident as $category:literal { $($variant:ident = $code:literal),+ $(,)? }) => {
+		$crate::impl_coded_error!(@impl $error_type, $category, $($variant = $code),+);
+	};
+	(@impl $error_type:ident, $category:expr, $($variant:ident = $code:literal),+) => {
+		impl $crate::error_code::CodedError for $error_type {
+			fn code(&self) -> u32 {
+				match self {
+					$(Self::$variant { .. } => $code,)+
+				}
+			}
+			fn category(&self) -> &'static str {
+				$category
+			}
+		}
+		impl $error_type {
+			/// The `(variant name, error code)` of every variant.
+			pub const ERROR_CODES: &'static [(&'static str, u32)] = &[$((stringify!($variant), $code)),+];
+		}
+		const _: () = assert!(
+			$crate::error_code::error_codes_are_distinct(&[$($code),+]),
+			concat!("error codes of ", stringify!($error_type), " must be distinct")
+		);
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::Uint128;
+	use thiserror::Error;
+
+	use super::*;
+
+	#[derive(Error, Debug, PartialEq)]
+	enum PoolError {
+		#[error("Unauthorized")]
+		Unauthorized,
+		#[error("Insufficient funds: {available} is available but {requested} was requested")]
+		InsufficientFunds { available: Uint128, requested: Uint128 },
+		#[error("{0}")]
+		Std(#[from] StdError),
+	}
+	impl_coded_error!(PoolError as "pool" {
+		Unauthorized = 1001,
+		InsufficientFunds = 1002,
+		Std = 1,
+	});
+
+	#[derive(Error, Debug)]
+	enum OtherError {
+		#[error("Paused")]
+		Paused(u8),
+	}
+	impl_coded_error!(OtherError { Paused = 7 });
+
+	#[test]
+	fn round_trip() {
+		let err = PoolError::InsufficientFunds {
+			available: Uint128::new(5),
+			requested: Uint128::new(7),
+		};
+		assert_eq!((err.code(), err.category()), (1002, "pool"));
+		let std_err = err.to_std_error();
+		assert_eq!(
+			std_err.to_string(),
+			"Generic error: [E1002] Insufficient funds: 5 is available but 7 was requested"
+		);
+		assert_eq!(extract_error_code(&std_err.to_string()), Some(1002));
+		// As returned by a failed transaction
+		let chain_msg = format!("failed to execute message; message index: 0: {std_err}: execute wasm contract failed");
+		assert_eq!(extract_error_code(&chain_msg), Some(1002));
+
+		let std_err = PoolError::Std(StdError::not_found("Pool")).to_std_error();
+		assert_eq!(extract_error_code(&std_err.to_string()), Some(1));
+		assert_eq!(OtherError::Paused(3).code(), 7);
+		assert_eq!(OtherError::Paused(3).category(), "OtherError");
+	}
+
+	#[test]
+	fn extract_edge_cases() {
+		assert_eq!(extract_error_code("no code here"), None);
+		assert_eq!(extract_error_code("[E] [Ex1] [E12"), None);
+		assert_eq!(extract_error_code("[E99999999999] too big"), None);
+		assert_eq!(extract_error_code("[Eabc] [E42] first valid code"), Some(42));
+		assert_eq!(extract_error_code("[E4294967295]"), Some(u32::MAX));
+		assert_eq!(format_coded_error(42, "Oops"), "[E42] Oops");
+	}
+
+	#[test]
+	fn error_code_table() {
+		assert_eq!(
+			PoolError::ERROR_CODES,
+			[("Unauthorized", 1001), ("InsufficientFunds", 1002), ("Std", 1)]
+		);
+		assert_eq!(PoolError::Unauthorized.code(), 1001);
+		assert_eq!(OtherError::ERROR_CODES, [("Paused", 7)]);
+		assert!(!error_codes_are_distinct(&[1, 2, 1]));
+		assert!(error_codes_are_distinct(&[]));
+	}
+
+	#[test]
+	fn invalid_code_tables() {
+		let cases = trybuild::TestCases::new();
+		cases.compile_fail("tests/ui/coded_error_duplicate.rs");
+		cases.compile_fail("tests/ui/coded_error_missing_variant.rs");
+	}
+}
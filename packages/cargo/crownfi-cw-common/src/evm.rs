@@ -0,0 +1,240 @@
+use cosmwasm_std::{Binary, StdError, Uint256};
+
+use crate::{
+	crypto::keccak256,
+	utils::{evm_word_to_address, evm_word_to_uint256, uint256_to_evm_word},
+};
+
+/// `keccak256("Transfer(address,address,uint256)")`
+pub const ERC20_TRANSFER_EVENT: [u8; 32] = [
+	0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa, 0x95, 0x2b, 0xa7,
+	0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef,
+];
+/// `keccak256("Approval(address,address,uint256)")`
+pub const ERC20_APPROVAL_EVENT: [u8; 32] = [
+	0x8c, 0x5b, 0xe1, 0xe5, 0xeb, 0xec, 0x7d, 0x5b, 0xd1, 0x4f, 0x71, 0x42, 0x7d, 0x1e, 0x84, 0xf3, 0xdd, 0x03, 0x14,
+	0xc0, 0xf7, 0xb2, 0x29, 0x1e, 0x5b, 0x20, 0x0a, 0xc8, 0xc7, 0xc3, 0xb9, 0x25,
+];
+
+/// The ABI types which can be decoded from logs, all of which are a single 32-byte word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvmAbiType {
+	Address,
+	Uint256,
+	Bool,
+	Bytes32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvmAbiValue {
+	Address([u8; 20]),
+	Uint256(Uint256),
+	Bool(bool),
+	Bytes32([u8; 32]),
+}
+
+impl EvmAbiValue {
+	pub fn decode(ty: EvmAbiType, word: &[u8]) -> Result<Self, StdError> {
+		Ok(match ty {
+			EvmAbiType::Address => EvmAbiValue::Address(evm_word_to_address(word)?),
+			EvmAbiType::Uint256 => EvmAbiValue::Uint256(evm_word_to_uint256(word)?),
+			EvmAbiType::Bool => match evm_word_to_uint256(word)? {
+				value if value.is_zero() => EvmAbiValue::Bool(false),
+				value if value == Uint256::one() => EvmAbiValue::Bool(true),
+				_ => return Err(StdError::parse_err("bool", "EVM word is neither 0 nor 1")),
+			},
+			EvmAbiType::Bytes32 => EvmAbiValue::Bytes32(
+				word.try_into()
+					.map_err(|_| StdError::parse_err("[u8; 32]", "expected a 32 byte long EVM word"))?,
+			),
+		})
+	}
+
+	/// Returns the value as it's ABI-encoded, which is also how it appears as an indexed topic.
+	pub fn to_word(&self) -> [u8; 32] {
+		let mut word = [0u8; 32];
+		match self {
+			EvmAbiValue::Address(address) => word[12..].copy_from_slice(address),
+			EvmAbiValue::Uint256(value) => word = uint256_to_evm_word(*value),
+			EvmAbiValue::Bool(value) => word[31] = *value as u8,
+			EvmAbiValue::Bytes32(bytes) => word = *bytes,
+		}
+		word
+	}
+}
+
+/// Returns the topic0 of the logs emitted by the event, e.g. `event_signature_hash("Transfer(address,address,uint256)")`.
+///
+/// The signature must be in its canonical form, without parameter names or spaces.
+pub fn event_signature_hash(sig: &str) -> [u8; 32] {
+	keccak256(&[sig.as_bytes()])
+}
+
+/// Decodes the parameters of a non-anonymous event log.
+///
+/// `layout` lists the types of the indexed parameters first, which are read from `topics[1..]`, followed by the
+/// types of the remaining parameters, read from `data`. Only parameters which fit in a single word are supported.
+///
+/// topic0 isn't checked, compare it against [event_signature_hash] first.
+pub fn decode_log(topics: &[Binary], data: &Binary, layout: &[EvmAbiType]) -> Result<Vec<EvmAbiValue>, StdError> {
+	let Some((_, indexed)) = topics.split_first() else {
+		return Err(StdError::parse_err("EvmLog", "log has no topics"));
+	};
+	if !data.len().is_multiple_of(32) {
+		return Err(StdError::parse_err(
+			"EvmLog",
+			format!("log data length {} isn't a multiple of 32", data.len()),
+		));
+	}
+	let words = indexed
+		.iter()
+		.map(|topic| topic.as_slice())
+		.chain(data.chunks_exact(32));
+	if indexed.len() + data.len() / 32 != layout.len() {
+		return Err(StdError::parse_err(
+			"EvmLog",
+			format!(
+				"expected {} parameters, log has {} topics and {} data words",
+				layout.len(),
+				indexed.len(),
+				data.len() / 32
+			),
+		));
+	}
+	words
+		.zip(layout)
+		.map(|(word, ty)| EvmAbiValue::decode(*ty, word))
+		.collect()
+}
+
+/// Returns the topics of a log emitted by the event with the specified indexed parameters, for comparing against the
+/// topics of a received log.
+pub fn encode_log_topics(sig: &str, indexed: &[EvmAbiValue]) -> Vec<Binary> {
+	std::iter::once(event_signature_hash(sig))
+		.chain(indexed.iter().map(EvmAbiValue::to_word))
+		.map(|word| Binary::from(word.as_slice()))
+		.collect()
+}
+
+fn decode_erc20_event(
+	event: &[u8; 32],
+	name: &str,
+	topics: &[Binary],
+	data: &Binary,
+) -> Result<([u8; 20], [u8; 20], Uint256), StdError> {
+	if topics.first().map(|topic| topic.as_slice()) != Some(event.as_slice()) {
+		return Err(StdError::parse_err(name, "topic0 doesn't match the event signature"));
+	}
+	let values = decode_log(
+		topics,
+		data,
+		&[EvmAbiType::Address, EvmAbiType::Address, EvmAbiType::Uint256],
+	)?;
+	match values.as_slice() {
+		[EvmAbiValue::Address(a), EvmAbiValue::Address(b), EvmAbiValue::Uint256(amount)] => Ok((*a, *b, *amount)),
+		_ => unreachable!("values were decoded with the specified layout"),
+	}
+}
+
+/// Decodes an ERC20 `Transfer(address indexed from, address indexed to, uint256 value)` log into
+/// `(from, to, value)`, returning an error if it's a different event.
+pub fn decode_erc20_transfer(topics: &[Binary], data: &Binary) -> Result<([u8; 20], [u8; 20], Uint256), StdError> {
+	decode_erc20_event(&ERC20_TRANSFER_EVENT, "Transfer", topics, data)
+}
+
+/// Decodes an ERC20 `Approval(address indexed owner, address indexed spender, uint256 value)` log into
+/// `(owner, spender, value)`, returning an error if it's a different event.
+pub fn decode_erc20_approval(topics: &[Binary], data: &Binary) -> Result<([u8; 20], [u8; 20], Uint256), StdError> {
+	decode_erc20_event(&ERC20_APPROVAL_EVENT, "Approval", topics, data)
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::from_json;
+	use serde::Deserialize;
+
+	use super::*;
+	use crate::utils::parse_ethereum_address;
+
+	/// A hand-written ERC20 transfer log in the format of a transaction receipt's, see the description in the file.
+	const TRANSFER_LOG: &str = include_str!("../test_data/erc20_transfer_log.json");
+
+	#[derive(Deserialize)]
+	#[serde(rename_all = "camelCase")]
+	struct Log {
+		address: String,
+		topics: Vec<String>,
+		data: String,
+	}
+
+	fn hex_binary(hex: &str) -> Binary {
+		Binary::from(hex::decode(hex.trim_start_matches("0x")).unwrap())
+	}
+
+	fn transfer_log() -> (Vec<Binary>, Binary) {
+		let log: Log = from_json(TRANSFER_LOG).unwrap();
+		assert!(parse_ethereum_address(&log.address).is_ok());
+		(
+			log.topics.iter().map(|topic| hex_binary(topic)).collect(),
+			hex_binary(&log.data),
+		)
+	}
+
+	#[test]
+	fn event_signatures() {
+		assert_eq!(
+			event_signature_hash("Transfer(address,address,uint256)"),
+			ERC20_TRANSFER_EVENT
+		);
+		assert_eq!(
+			event_signature_hash("Approval(address,address,uint256)"),
+			ERC20_APPROVAL_EVENT
+		);
+	}
+
+	#[test]
+	fn decode_transfer() {
+		let (topics, data) = transfer_log();
+		let from = parse_ethereum_address("0x3894085Ef7Ff0f0aeDf52E2A2704928d1Ec074F1").unwrap();
+		let to = parse_ethereum_address("0xB8f8b4E4A2A7e8b1C1B9b1A1F2D0E3C4a5B6c7D8").unwrap();
+		assert_eq!(
+			decode_erc20_transfer(&topics, &data).unwrap(),
+			(from, to, Uint256::from(2_500_000u128))
+		);
+		assert_eq!(
+			encode_log_topics(
+				"Transfer(address,address,uint256)",
+				&[EvmAbiValue::Address(from), EvmAbiValue::Address(to)]
+			),
+			topics
+		);
+		assert_eq!(
+			decode_log(
+				&topics,
+				&data,
+				&[EvmAbiType::Address, EvmAbiType::Address, EvmAbiType::Uint256]
+			)
+			.unwrap(),
+			[
+				EvmAbiValue::Address(from),
+				EvmAbiValue::Address(to),
+				EvmAbiValue::Uint256(Uint256::from(2_500_000u128))
+			]
+		);
+		// A transfer isn't an approval, even though the parameters are the same
+		assert!(decode_erc20_approval(&topics, &data).is_err());
+	}
+
+	#[test]
+	fn reject_malformed_logs() {
+		let (mut topics, data) = transfer_log();
+		assert!(decode_log(&topics, &data, &[EvmAbiType::Address, EvmAbiType::Address]).is_err());
+		assert!(decode_log(&topics, &Binary::from(&data[..31]), &[EvmAbiType::Address; 3]).is_err());
+		assert!(decode_log(&[], &data, &[EvmAbiType::Uint256]).is_err());
+		// An address is neither 0 nor 1
+		assert!(decode_log(&topics[..2], &data, &[EvmAbiType::Bool, EvmAbiType::Uint256]).is_err());
+
+		topics[0] = Binary::from(event_signature_hash("Transfer(address,address,uint128)").as_slice());
+		assert!(decode_erc20_transfer(&topics, &data).is_err());
+	}
+}
@@ -0,0 +1,232 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use cosmwasm_std::{Binary, Env, QuerierWrapper, StdError, StdResult};
+use sei_cosmwasm::{SeiQuerier, SeiQueryWrapper};
+use sha2::{Digest, Sha256};
+
+use crate::{
+	impl_serializable_borsh,
+	storage::{map::StoredMap, SerializableItem},
+	utils::bytes_to_ethereum_address,
+};
+
+/// The caller of the static calls. We don't know who the caller is, but who cares?
+const STATIC_CALL_SENDER: &str = "sei1llllllllllllllllllllllllllllllllllllllllllllllllllls09qcrc";
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+struct CachedCall {
+	target: [u8; 20],
+	calldata_hash: [u8; 32],
+	block_height: u64,
+	result: Vec<u8>,
+}
+impl_serializable_borsh!(CachedCall);
+
+/// Remembers the results of EVM static calls (e.g. ERC20 `balanceOf` or `decimals`) for the rest of the block, as they
+/// can't change within it as far as we're concerned.
+///
+/// Calls are stored in a fixed amount of slots chosen by the hash of the target and calldata, so the cache never grows
+/// beyond `slots` entries. A call whose slot is taken by another one overwrites it.
+///
+/// Storage can't be written during queries, so [get_or_call](Self::get_or_call) is for the execute path, and queries
+/// should use [get_or_call_read_only](Self::get_or_call_read_only), which uses the cached results without adding to
+/// them.
+pub struct EvmCallCache {
+	calls: StoredMap<u32, CachedCall>,
+	slots: u32,
+}
+impl EvmCallCache {
+	/// `slots` is the most calls which can be cached at once, it must not be 0.
+	pub fn new(namespace: &'static [u8], slots: u32) -> Self {
+		assert!(slots > 0, "EvmCallCache: slots must not be 0");
+		Self {
+			calls: StoredMap::new(namespace),
+			slots,
+		}
+	}
+
+	fn slot(&self, target: &[u8; 20], calldata_hash: &[u8; 32]) -> u32 {
+		let hash: [u8; 32] = Sha256::new()
+			.chain_update(target)
+			.chain_update(calldata_hash)
+			.finalize()
+			.into();
+		(u64::from_le_bytes(hash[..8].try_into().unwrap()) % self.slots as u64) as u32
+	}
+
+	/// Returns the cached result of the call if it was made during the current block.
+	pub fn get(&self, env: &Env, target: &[u8; 20], calldata: &[u8]) -> StdResult<Option<Binary>> {
+		let calldata_hash: [u8; 32] = Sha256::digest(calldata).into();
+		self.get_by_hash(env, target, &calldata_hash)
+	}
+
+	fn get_by_hash(&self, env: &Env, target: &[u8; 20], calldata_hash: &[u8; 32]) -> StdResult<Option<Binary>> {
+		let Some(cached) = self.calls.get(&self.slot(target, calldata_hash))? else {
+			return Ok(None);
+		};
+		if cached.block_height != env.block.height || cached.target != *target || cached.calldata_hash != *calldata_hash
+		{
+			return Ok(None);
+		}
+		Ok(Some(cached.into_inner().result.into()))
+	}
+
+	/// Returns the result of the call, only making it if it wasn't already made during the current block. The result
+	/// is then cached, so this can't be used in queries.
+	pub fn get_or_call(
+		&self,
+		querier: &QuerierWrapper<SeiQueryWrapper>,
+		env: &Env,
+		target: &[u8; 20],
+		calldata: &[u8],
+	) -> StdResult<Binary> {
+		let calldata_hash: [u8; 32] = Sha256::digest(calldata).into();
+		if let Some(result) = self.get_by_hash(env, target, &calldata_hash)? {
+			return Ok(result);
+		}
+		let result = static_call(querier, target, calldata)?;
+		self.calls.set(
+			&self.slot(target, &calldata_hash),
+			&CachedCall {
+				target: *target,
+				calldata_hash,
+				block_height: env.block.height,
+				result: result.to_vec(),
+			},
+		)?;
+		Ok(result)
+	}
+
+	/// Same as [get_or_call](Self::get_or_call), but doesn't cache the result, so that it can be used in queries.
+	pub fn get_or_call_read_only(
+		&self,
+		querier: &QuerierWrapper<SeiQueryWrapper>,
+		env: &Env,
+		target: &[u8; 20],
+		calldata: &[u8],
+	) -> StdResult<Binary> {
+		match self.get(env, target, calldata)? {
+			Some(result) => Ok(result),
+			None => static_call(querier, target, calldata),
+		}
+	}
+}
+
+fn static_call(querier: &QuerierWrapper<SeiQueryWrapper>, target: &[u8; 20], calldata: &[u8]) -> StdResult<Binary> {
+	let response = SeiQuerier::new(querier).static_call(
+		STATIC_CALL_SENDER.into(),
+		bytes_to_ethereum_address(target)?,
+		Binary::from(calldata).to_base64(),
+	)?;
+	Binary::from_base64(&response.encoded_data)
+		.map_err(|err| StdError::parse_err("StaticCallResponse", format!("encoded_data isn't base64: {err}")))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicU32, Ordering};
+
+	use cosmwasm_std::{
+		testing::{mock_env, MockQuerier},
+		to_json_binary, ContractResult, SystemResult,
+	};
+	use sei_cosmwasm::{SeiQuery, StaticCallResponse};
+
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	const TOKEN: [u8; 20] = [0xaa; 20];
+	const OTHER_TOKEN: [u8; 20] = [0xbb; 20];
+	const DECIMALS: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+
+	/// Responds with the calldata followed by the amount of calls made so far.
+	fn counting_querier(calls: &'static AtomicU32) -> MockQuerier<SeiQueryWrapper> {
+		MockQuerier::<SeiQueryWrapper>::new(&[]).with_custom_handler(move |query| match &query.query_data {
+			SeiQuery::StaticCall { data, .. } => {
+				let mut result = Binary::from_base64(data).unwrap().to_vec();
+				result.push(calls.fetch_add(1, Ordering::SeqCst) as u8 + 1);
+				SystemResult::Ok(ContractResult::Ok(
+					to_json_binary(&StaticCallResponse {
+						encoded_data: Binary::from(result).to_base64(),
+					})
+					.unwrap(),
+				))
+			}
+			_ => panic!("unexpected query"),
+		})
+	}
+
+	#[test]
+	fn caches_within_a_block() -> TestingResult {
+		static CALLS: AtomicU32 = AtomicU32::new(0);
+		let _storage = isolated()?;
+		let querier = counting_querier(&CALLS);
+		let querier = QuerierWrapper::<SeiQueryWrapper>::new(&querier);
+		let cache = EvmCallCache::new(NAMESPACE, 16);
+		let mut env = mock_env();
+
+		assert_eq!(cache.get(&env, &TOKEN, &DECIMALS)?, None);
+		assert_eq!(
+			cache.get_or_call(&querier, &env, &TOKEN, &DECIMALS)?.as_slice(),
+			[0x31, 0x3c, 0xe5, 0x67, 1]
+		);
+		assert_eq!(
+			cache.get_or_call(&querier, &env, &TOKEN, &DECIMALS)?.as_slice(),
+			[0x31, 0x3c, 0xe5, 0x67, 1]
+		);
+		assert_eq!(
+			cache
+				.get_or_call_read_only(&querier, &env, &TOKEN, &DECIMALS)?
+				.as_slice()[4],
+			1
+		);
+		assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+		// A different target is a different call
+		assert_eq!(
+			cache.get_or_call(&querier, &env, &OTHER_TOKEN, &DECIMALS)?.as_slice()[4],
+			2
+		);
+
+		env.block.height += 1;
+		assert_eq!(cache.get(&env, &TOKEN, &DECIMALS)?, None);
+		// Read-only calls don't refresh the cache
+		assert_eq!(
+			cache
+				.get_or_call_read_only(&querier, &env, &TOKEN, &DECIMALS)?
+				.as_slice()[4],
+			3
+		);
+		assert_eq!(cache.get(&env, &TOKEN, &DECIMALS)?, None);
+		assert_eq!(cache.get_or_call(&querier, &env, &TOKEN, &DECIMALS)?.as_slice()[4], 4);
+		assert_eq!(cache.get_or_call(&querier, &env, &TOKEN, &DECIMALS)?.as_slice()[4], 4);
+		assert_eq!(CALLS.load(Ordering::SeqCst), 4);
+		Ok(())
+	}
+
+	#[test]
+	fn slots_are_overwritten() -> TestingResult {
+		static CALLS: AtomicU32 = AtomicU32::new(0);
+		let _storage = isolated()?;
+		let querier = counting_querier(&CALLS);
+		let querier = QuerierWrapper::<SeiQueryWrapper>::new(&querier);
+		let cache = EvmCallCache::new(NAMESPACE, 1);
+		let env = mock_env();
+		let balance_of = [0x70, 0xa0, 0x82, 0x31];
+
+		cache.get_or_call(&querier, &env, &TOKEN, &DECIMALS)?;
+		cache.get_or_call(&querier, &env, &TOKEN, &balance_of)?;
+		// With a single slot, the second call evicted the first
+		assert_eq!(cache.get(&env, &TOKEN, &DECIMALS)?, None);
+		assert!(cache.get(&env, &TOKEN, &balance_of)?.is_some());
+		assert_eq!(cache.get_or_call(&querier, &env, &TOKEN, &DECIMALS)?.as_slice()[4], 3);
+		assert_eq!(cache.calls.iter()?.count(), 1);
+
+		// With more slots, the cache still never grows beyond them
+		let cache = EvmCallCache::new(b"bigger", 4);
+		for selector in 0..32u8 {
+			cache.get_or_call(&querier, &env, &TOKEN, &[selector])?;
+			assert!(cache.get(&env, &TOKEN, &[selector])?.is_some());
+		}
+		assert!(cache.calls.iter()?.count() <= 4);
+		Ok(())
+	}
+}
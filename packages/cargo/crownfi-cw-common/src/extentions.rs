@@ -1,2 +1,5 @@
+pub mod int256;
 pub mod math;
+pub mod shares;
+pub mod std_types;
 pub mod timestamp;
@@ -0,0 +1,168 @@
+//! Storage support for [Int256], for signed amounts such as funding payments or realized PnL.
+//!
+//! cosmwasm's Int256 already provides the checked arithmetic, the overflow-checked conversions from and to
+//! [Uint128](cosmwasm_std::Uint128) and [Uint256](cosmwasm_std::Uint256), and serde as a decimal string. This adds:
+//!
+//! * A [SerializableItem] implementation, stored as 32 big-endian bytes with the sign bit flipped, so that the storage
+//!   order of the keys of a [StoredMap](crate::storage::map::StoredMap) is the numeric order.
+//! * [borsh_serialize] and [borsh_deserialize], for use in borsh structs:
+//!
+//! ```
+//! use borsh::{BorshDeserialize, BorshSerialize};
+//! use cosmwasm_std::Int256;
+//! use crownfi_cw_common::extentions::int256;
+//!
+//! #[derive(BorshSerialize, BorshDeserialize)]
+//! pub struct Position {
+//!     #[borsh(
+//!         serialize_with = "int256::borsh_serialize",
+//!         deserialize_with = "int256::borsh_deserialize"
+//!     )]
+//!     pub realized_pnl: Int256,
+//! }
+//! ```
+use std::io::{Read, Result as IoResult, Write};
+
+use cosmwasm_std::{Int256, StdError};
+
+use crate::storage::SerializableItem;
+
+const SIGN_BIT: u8 = 0x80;
+
+impl SerializableItem for Int256 {
+	fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+		let mut bytes = self.to_be_bytes();
+		bytes[0] ^= SIGN_BIT;
+		Ok(bytes.into())
+	}
+	fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> {
+		let mut bytes: [u8; 32] = data
+			.try_into()
+			.map_err(|_| StdError::invalid_data_size(32, data.len()))?;
+		bytes[0] ^= SIGN_BIT;
+		Ok(Int256::from_be_bytes(bytes))
+	}
+}
+
+/// Writes the value as 32 little-endian bytes, the same way borsh encodes the native integer types.
+pub fn borsh_serialize<W: Write>(value: &Int256, writer: &mut W) -> IoResult<()> {
+	writer.write_all(&value.to_le_bytes())
+}
+
+pub fn borsh_deserialize<R: Read>(reader: &mut R) -> IoResult<Int256> {
+	let mut bytes = [0u8; 32];
+	reader.read_exact(&mut bytes)?;
+	Ok(Int256::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+	use borsh::{BorshDeserialize, BorshSerialize};
+	use cosmwasm_std::{from_json, to_json_string, Uint128, Uint256};
+
+	use super::*;
+	use crate::storage::{map::StoredMap, testing_common::*};
+
+	#[test]
+	fn arithmetic() {
+		let funding = Int256::from(-1_500i128);
+		let pnl = Int256::from(1_000u128);
+		assert_eq!(funding.checked_add(pnl).unwrap(), Int256::from(-500i128));
+		assert_eq!(pnl.checked_sub(funding).unwrap(), Int256::from(2_500u128));
+		assert_eq!(
+			funding.checked_mul(Int256::from(-2i8)).unwrap(),
+			Int256::from(3_000u128)
+		);
+		// Division truncates towards zero
+		assert_eq!(
+			funding.checked_div(Int256::from(1_000u128)).unwrap(),
+			Int256::from(-1i8)
+		);
+		assert!(funding.checked_div(Int256::zero()).is_err());
+		assert_eq!(funding.checked_add(-funding).unwrap(), Int256::zero());
+		assert!(funding.is_negative() && !pnl.is_negative());
+		assert_eq!(funding.unsigned_abs(), Uint256::from(1_500u128));
+		assert_eq!(funding.to_string(), "-1500");
+
+		assert_eq!(Int256::from(Uint128::MAX).unsigned_abs(), Uint256::from(Uint128::MAX));
+		assert_eq!(Uint128::try_from(pnl).unwrap(), Uint128::new(1_000));
+		assert!(Uint128::try_from(funding).is_err());
+		assert!(Int256::try_from(Uint256::MAX).is_err());
+	}
+
+	#[test]
+	fn boundary_values() {
+		assert_eq!(Int256::MAX.checked_add(Int256::MIN).unwrap(), Int256::from(-1i8));
+		assert!(Int256::MAX.checked_add(Int256::one()).is_err());
+		assert!(Int256::MIN.checked_sub(Int256::one()).is_err());
+		// MIN can't be negated
+		assert!(Int256::MIN.checked_mul(Int256::from(-1i8)).is_err());
+		assert!(Int256::zero().checked_sub(Int256::MIN).is_err());
+		assert!(Int256::MIN.checked_div(Int256::from(-1i8)).is_err());
+		assert_eq!(Int256::MIN.unsigned_abs(), Uint256::from(1u8) << 255);
+
+		for value in [Int256::MIN, Int256::from(-1i8), Int256::zero(), Int256::MAX] {
+			let bytes = value.serialize_to_owned().unwrap();
+			assert_eq!(Int256::deserialize_to_owned(&bytes).unwrap(), value);
+		}
+		assert_eq!(Int256::MIN.serialize_to_owned().unwrap(), [0; 32]);
+		assert_eq!(Int256::MAX.serialize_to_owned().unwrap(), [0xff; 32]);
+		assert!(Int256::deserialize_to_owned(&[0; 31]).is_err());
+	}
+
+	#[test]
+	fn storage_order() -> TestingResult {
+		let _storage = isolated()?;
+		let map = StoredMap::<Int256, u8>::new(NAMESPACE);
+		let mut keys = [
+			Int256::from(256u128),
+			Int256::MIN,
+			Int256::from(-1i8),
+			Int256::MAX,
+			Int256::zero(),
+			Int256::from(-256i128),
+			Int256::one(),
+		];
+		for key in keys {
+			map.set(&key, &0)?;
+		}
+		keys.sort();
+		assert_eq!(map.iter_keys()?.collect::<Vec<_>>(), keys);
+		assert_eq!(
+			map.iter_range_keys(Some(Int256::from(-256i128)), Some(Int256::one()))?
+				.collect::<Vec<_>>(),
+			[Int256::from(-256i128), Int256::from(-1i8), Int256::zero()]
+		);
+		Ok(())
+	}
+
+	#[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+	struct Position {
+		#[borsh(serialize_with = "borsh_serialize", deserialize_with = "borsh_deserialize")]
+		realized_pnl: Int256,
+		opened_at: u64,
+	}
+
+	#[test]
+	fn serde_round_trips() {
+		for value in [Int256::MIN, Int256::from(-42i8), Int256::zero(), Int256::MAX] {
+			let json = to_json_string(&value).unwrap();
+			assert_eq!(json, format!("\"{value}\""));
+			assert_eq!(from_json::<Int256>(&json).unwrap(), value);
+
+			let position = Position {
+				realized_pnl: value,
+				opened_at: 7,
+			};
+			let bytes = borsh::to_vec(&position).unwrap();
+			assert_eq!(bytes.len(), 40);
+			assert_eq!(Position::try_from_slice(&bytes).unwrap(), position);
+		}
+		assert_eq!(
+			to_json_string(&Int256::from(-42i8)).unwrap(),
+			"\"-42\"",
+			"the sign is part of the string"
+		);
+		assert!(from_json::<Int256>("-42").is_err());
+	}
+}
@@ -0,0 +1,210 @@
+use cosmwasm_std::{StdError, StdResult, Uint128, Uint256};
+
+use super::math::UintMathExtensions;
+
+/// Which way to round when a conversion doesn't divide evenly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+	Floor,
+	Ceil,
+}
+
+/// Converts between a vault's deposited assets and the shares representing them.
+///
+/// All intermediate values are 256-bit, so the conversions only fail if the result doesn't fit in a [Uint128].
+///
+/// Conversions should always round in the vault's favour, i.e. against the user:
+/// * Deposits: shares minted are rounded down ([Rounding::Floor]).
+/// * Withdrawals: shares burned for a specific amount of assets are rounded up ([Rounding::Ceil]).
+/// * Redemptions: assets paid out for a specific amount of shares are rounded down ([Rounding::Floor]).
+///
+/// By default, an empty vault converts 1:1, which leaves it open to the first-depositor inflation attack: An attacker
+/// deposits a tiny amount, then donates a large amount of assets directly to the vault, making the next deposits round
+/// down to (next to) nothing. Enabling the [virtual offset](Self::with_virtual_offset) mitigates this the same way the
+/// OpenZeppelin ERC-4626 implementation does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShareCalculator {
+	pub total_shares: Uint128,
+	pub total_assets: Uint128,
+	/// See [with_virtual_offset](Self::with_virtual_offset)
+	pub decimals_offset: Option<u8>,
+}
+
+impl ShareCalculator {
+	pub fn new(total_shares: Uint128, total_assets: Uint128) -> Self {
+		Self {
+			total_shares,
+			total_assets,
+			decimals_offset: None,
+		}
+	}
+
+	/// Acts as if there were always `10 ^ decimals_offset` more shares and 1 more asset in the vault.
+	///
+	/// These virtual shares capture part of any donation, which makes the inflation attack unprofitable. A higher
+	/// offset makes the attack more expensive at the cost of shares having more decimals than the underlying asset.
+	pub fn with_virtual_offset(mut self, decimals_offset: u8) -> Self {
+		self.decimals_offset = Some(decimals_offset);
+		self
+	}
+
+	fn virtual_totals(&self) -> StdResult<(Uint256, Uint256)> {
+		let total_shares = Uint256::from(self.total_shares);
+		let total_assets = Uint256::from(self.total_assets);
+		let Some(decimals_offset) = self.decimals_offset else {
+			return Ok((total_shares, total_assets));
+		};
+		let virtual_shares = Uint256::from(10u8).checked_pow(decimals_offset.into())?;
+		Ok((total_shares.checked_add(virtual_shares)?, total_assets + Uint256::one()))
+	}
+
+	/// Returns the amount of shares the specified amount of assets is worth.
+	pub fn assets_to_shares(&self, assets: Uint128, rounding: Rounding) -> Result<Uint128, StdError> {
+		if self.decimals_offset.is_none() && self.total_shares.is_zero() {
+			return Ok(assets);
+		}
+		let (total_shares, total_assets) = self.virtual_totals()?;
+		mul_div(assets, total_shares, total_assets, rounding)
+	}
+
+	/// Returns the amount of assets the specified amount of shares is worth.
+	pub fn shares_to_assets(&self, shares: Uint128, rounding: Rounding) -> Result<Uint128, StdError> {
+		if self.decimals_offset.is_none() && self.total_shares.is_zero() {
+			return Ok(shares);
+		}
+		let (total_shares, total_assets) = self.virtual_totals()?;
+		mul_div(shares, total_assets, total_shares, rounding)
+	}
+
+	/// Returns the shares minted for depositing the specified amount of assets, along with the updated totals.
+	pub fn deposit(&self, assets: Uint128) -> Result<(Uint128, Self), StdError> {
+		let shares = self.assets_to_shares(assets, Rounding::Floor)?;
+		Ok((
+			shares,
+			Self {
+				total_shares: self.total_shares.checked_add(shares)?,
+				total_assets: self.total_assets.checked_add(assets)?,
+				..*self
+			},
+		))
+	}
+
+	/// Returns the shares which must be burned to withdraw the specified amount of assets, along with the updated
+	/// totals.
+	pub fn withdraw(&self, assets: Uint128) -> Result<(Uint128, Self), StdError> {
+		let shares = self.assets_to_shares(assets, Rounding::Ceil)?;
+		Ok((
+			shares,
+			Self {
+				total_shares: self.total_shares.checked_sub(shares)?,
+				total_assets: self.total_assets.checked_sub(assets)?,
+				..*self
+			},
+		))
+	}
+
+	/// Returns the assets paid out for burning the specified amount of shares, along with the updated totals.
+	pub fn redeem(&self, shares: Uint128) -> Result<(Uint128, Self), StdError> {
+		let assets = self.shares_to_assets(shares, Rounding::Floor)?;
+		Ok((
+			assets,
+			Self {
+				total_shares: self.total_shares.checked_sub(shares)?,
+				total_assets: self.total_assets.checked_sub(assets)?,
+				..*self
+			},
+		))
+	}
+}
+
+fn mul_div(value: Uint128, numerator: Uint256, denominator: Uint256, rounding: Rounding) -> StdResult<Uint128> {
+	let product = Uint256::from(value).checked_mul(numerator)?;
+	let result = match rounding {
+		Rounding::Floor => product.checked_div(denominator)?,
+		Rounding::Ceil => product.checked_div_ceil_int(denominator)?,
+	};
+	Ok(Uint128::try_from(result)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rounding_direction() -> StdResult<()> {
+		let vault = ShareCalculator::new(3u128.into(), 10u128.into());
+		assert_eq!(vault.assets_to_shares(5u128.into(), Rounding::Floor)?, Uint128::new(1));
+		assert_eq!(vault.assets_to_shares(5u128.into(), Rounding::Ceil)?, Uint128::new(2));
+		assert_eq!(vault.shares_to_assets(1u128.into(), Rounding::Floor)?, Uint128::new(3));
+		assert_eq!(vault.shares_to_assets(1u128.into(), Rounding::Ceil)?, Uint128::new(4));
+		// Even divisions are unaffected
+		assert_eq!(vault.shares_to_assets(3u128.into(), Rounding::Ceil)?, Uint128::new(10));
+
+		// Depositing then withdrawing the same amount never leaves the user with more shares than they started with
+		let (minted, vault) = vault.deposit(5u128.into())?;
+		let (burned, vault) = vault.withdraw(5u128.into())?;
+		assert!(burned >= minted);
+		assert_eq!(vault.total_assets, Uint128::new(10));
+		Ok(())
+	}
+
+	#[test]
+	fn empty_vault() -> StdResult<()> {
+		let vault = ShareCalculator::new(Uint128::zero(), Uint128::zero());
+		assert_eq!(vault.deposit(100u128.into())?.0, Uint128::new(100));
+		let vault = vault.with_virtual_offset(3);
+		assert_eq!(vault.deposit(100u128.into())?.0, Uint128::new(100_000));
+		Ok(())
+	}
+
+	/// Runs the first-depositor inflation attack, returning what the attacker and the victim get back.
+	fn inflation_attack(vault: ShareCalculator) -> StdResult<(Uint128, Uint128)> {
+		let (attacker_shares, mut vault) = vault.deposit(1u128.into())?;
+		// Donated directly to the vault, no shares are minted
+		vault.total_assets += Uint128::new(10_000);
+		let (victim_shares, vault) = vault.deposit(20_000u128.into())?;
+		let (attacker_assets, vault) = vault.redeem(attacker_shares)?;
+		let (victim_assets, _) = vault.redeem(victim_shares)?;
+		Ok((attacker_assets, victim_assets))
+	}
+
+	#[test]
+	fn inflation_attack_mitigation() -> StdResult<()> {
+		let attacker_spent = Uint128::new(10_001);
+
+		let (attacker_assets, victim_assets) = inflation_attack(ShareCalculator::new(0u128.into(), 0u128.into()))?;
+		// The victim's deposit got rounded down to a single share, half of it went to the attacker
+		assert_eq!(attacker_assets, Uint128::new(15_000));
+		assert_eq!(victim_assets, Uint128::new(15_001));
+		assert!(attacker_assets > attacker_spent);
+
+		let (attacker_assets, victim_assets) =
+			inflation_attack(ShareCalculator::new(0u128.into(), 0u128.into()).with_virtual_offset(3))?;
+		// Most of the donation is captured by the virtual shares instead
+		assert!(attacker_assets < attacker_spent);
+		assert!(Uint128::new(20_000) - victim_assets <= Uint128::new(10));
+		Ok(())
+	}
+
+	#[test]
+	fn overflow() -> StdResult<()> {
+		// Intermediate values exceeding 128 bits are fine
+		let vault = ShareCalculator::new(Uint128::MAX, Uint128::MAX);
+		assert_eq!(vault.assets_to_shares(Uint128::MAX, Rounding::Ceil)?, Uint128::MAX);
+		assert_eq!(vault.shares_to_assets(Uint128::MAX, Rounding::Floor)?, Uint128::MAX);
+
+		// Results which don't fit are errors
+		let vault = ShareCalculator::new(Uint128::MAX, 1u128.into());
+		assert!(vault.assets_to_shares(2u128.into(), Rounding::Floor).is_err());
+		assert!(vault.deposit(1u128.into()).is_err());
+		// Can't have 10 ^ 255 virtual shares
+		assert!(vault
+			.with_virtual_offset(255)
+			.assets_to_shares(1u128.into(), Rounding::Floor)
+			.is_err());
+		// A vault which has shares but lost all its assets
+		let vault = ShareCalculator::new(1u128.into(), Uint128::zero());
+		assert!(vault.assets_to_shares(1u128.into(), Rounding::Floor).is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,179 @@
+//! [SerializableItem] implementations for the cosmwasm types contracts commonly store, so that they don't need to be
+//! converted to primitive types at every storage call site.
+//!
+//! * [Uint64], [Uint128], [Uint256] and [Uint512] are stored as big-endian bytes of their full width, so that the
+//!   storage order of the keys of a [StoredMap](crate::storage::map::StoredMap) is the numeric order, like
+//!   [Int256](super::int256).
+//! * [Decimal] and [Decimal256] are stored as their atomics, which also makes them ordered.
+//! * [Timestamp] is stored as a big-endian u64 of nanoseconds, so that time-indexed keys are ordered as well.
+//! * [Addr] is stored as its borsh-encoded string, the same as a `String` would be.
+//!
+//! Note that none of these can be read without copying, as they would have to be stored in little-endian to do so.
+use cosmwasm_std::{Addr, Decimal, Decimal256, StdError, Timestamp, Uint128, Uint256, Uint512, Uint64};
+
+use crate::storage::SerializableItem;
+
+fn be_bytes<const N: usize>(data: &[u8]) -> Result<[u8; N], StdError> {
+	data.try_into().map_err(|_| StdError::invalid_data_size(N, data.len()))
+}
+
+impl SerializableItem for Uint64 {
+	fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+		Ok(self.to_be_bytes().into())
+	}
+	fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> {
+		Ok(u64::from_be_bytes(be_bytes(data)?).into())
+	}
+}
+
+impl SerializableItem for Uint128 {
+	fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+		Ok(self.to_be_bytes().into())
+	}
+	fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> {
+		Ok(u128::from_be_bytes(be_bytes(data)?).into())
+	}
+}
+
+impl SerializableItem for Uint256 {
+	fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+		Ok(self.to_be_bytes().into())
+	}
+	fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> {
+		Ok(Uint256::from_be_bytes(be_bytes(data)?))
+	}
+}
+
+impl SerializableItem for Uint512 {
+	fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+		Ok(self.to_be_bytes().into())
+	}
+	fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> {
+		Ok(Uint512::from_be_bytes(be_bytes(data)?))
+	}
+}
+
+impl SerializableItem for Decimal {
+	fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+		self.atomics().serialize_to_owned()
+	}
+	fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> {
+		Ok(Decimal::new(Uint128::deserialize_to_owned(data)?))
+	}
+}
+
+impl SerializableItem for Decimal256 {
+	fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+		self.atomics().serialize_to_owned()
+	}
+	fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> {
+		Ok(Decimal256::new(Uint256::deserialize_to_owned(data)?))
+	}
+}
+
+impl SerializableItem for Timestamp {
+	fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+		Ok(self.nanos().to_be_bytes().into())
+	}
+	fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> {
+		Ok(Timestamp::from_nanos(u64::from_be_bytes(be_bytes(data)?)))
+	}
+}
+
+impl SerializableItem for Addr {
+	fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+		self.as_str().to_string().serialize_to_owned()
+	}
+	fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> {
+		Ok(Addr::unchecked(String::deserialize_to_owned(data)?))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fmt::Debug;
+
+	use super::*;
+	use crate::storage::{map::StoredMap, testing_common::*, OZeroCopy};
+
+	fn round_trip<T: SerializableItem + PartialEq + Debug>(values: &[T], len: Option<usize>) {
+		for value in values {
+			let bytes = value.serialize_to_owned().unwrap();
+			if let Some(len) = len {
+				assert_eq!(bytes.len(), len);
+				assert!(T::deserialize_to_owned(&bytes[1..]).is_err());
+			}
+			assert_eq!(&T::deserialize_to_owned(&bytes).unwrap(), value);
+		}
+	}
+
+	#[test]
+	fn round_trips() {
+		round_trip(&[Uint64::zero(), Uint64::new(42), Uint64::MAX], Some(8));
+		round_trip(&[Uint128::zero(), Uint128::new(42), Uint128::MAX], Some(16));
+		round_trip(&[Uint256::zero(), Uint256::from(42u8), Uint256::MAX], Some(32));
+		round_trip(&[Uint512::zero(), Uint512::from(42u8), Uint512::MAX], Some(64));
+		round_trip(&[Decimal::zero(), Decimal::percent(150), Decimal::MAX], Some(16));
+		round_trip(
+			&[Decimal256::zero(), Decimal256::permille(3), Decimal256::MAX],
+			Some(32),
+		);
+		round_trip(
+			&[
+				Timestamp::from_nanos(0),
+				Timestamp::from_seconds(1_700_000_000),
+				Timestamp::from_nanos(u64::MAX),
+			],
+			Some(8),
+		);
+		round_trip(&[Addr::unchecked(""), Addr::unchecked("sei1recipient")], None);
+		assert_eq!(
+			Addr::unchecked("sei1recipient").serialize_to_owned().unwrap(),
+			"sei1recipient".to_string().serialize_to_owned().unwrap()
+		);
+	}
+
+	#[test]
+	fn storage_order() -> TestingResult {
+		let _storage = isolated()?;
+		let map = StoredMap::<Uint128, String>::new(NAMESPACE);
+		let mut keys = [
+			Uint128::new(256),
+			Uint128::MAX,
+			Uint128::one(),
+			Uint128::zero(),
+			Uint128::new(255),
+			Uint128::new(1 << 64),
+		];
+		for key in keys {
+			map.set(&key, &key.to_string())?;
+		}
+		keys.sort();
+		assert_eq!(
+			map.iter()?
+				.map(|(key, value)| (key, value.into_inner()))
+				.collect::<Vec<_>>(),
+			keys.iter().map(|key| (*key, key.to_string())).collect::<Vec<_>>()
+		);
+		assert_eq!(
+			map.iter_range_keys(Some(Uint128::new(255)), Some(Uint128::new(1 << 64)))?
+				.rev()
+				.collect::<Vec<_>>(),
+			[Uint128::new(256), Uint128::new(255)]
+		);
+
+		let map = StoredMap::<Timestamp, Decimal>::new(b"prices");
+		for seconds in [300, 1, 70_000, 256] {
+			map.set(&Timestamp::from_seconds(seconds), &Decimal::percent(seconds))?;
+		}
+		assert_eq!(
+			map.iter_keys()?.map(|time| time.seconds()).collect::<Vec<_>>(),
+			[1, 256, 300, 70_000]
+		);
+		assert_eq!(
+			map.last()?.map(|(_, price)| OZeroCopy::into_inner(price)),
+			Some(Decimal::percent(70_000))
+		);
+		Ok(())
+	}
+}
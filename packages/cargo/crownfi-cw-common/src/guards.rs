@@ -0,0 +1,351 @@
+//! Checks for the slippage and deadline parameters of swap-style executes.
+//!
+//! All limits are inclusive: receiving exactly the minimum, spending exactly the maximum, executing at exactly the
+//! deadline or trading at exactly the limit price passes.
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Env, StdError, Timestamp, Uint128};
+use thiserror::Error;
+
+use crate::{data_types::asset::FungibleAssetKindString, extentions::timestamp::TimestampExtentions};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum GuardError {
+	#[error("Would receive {actual} {kind}, which is less than the minimum of {minimum}")]
+	BelowMinimum {
+		kind: FungibleAssetKindString,
+		minimum: Uint128,
+		actual: Uint128,
+	},
+	#[error("Would spend {actual} {kind}, which is more than the maximum of {maximum}")]
+	AboveMaximum {
+		kind: FungibleAssetKindString,
+		maximum: Uint128,
+		actual: Uint128,
+	},
+	#[error("The deadline ({deadline}) has passed, the block time is {block_time}")]
+	DeadlinePassed { deadline: Timestamp, block_time: Timestamp },
+	#[error("Would {direction} at a price of {actual}, which is worse than the limit of {limit}")]
+	PriceBeyondLimit {
+		direction: BuyOrSell,
+		limit: Decimal,
+		actual: Decimal,
+	},
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<GuardError> for StdError {
+	fn from(value: GuardError) -> Self {
+		match value {
+			GuardError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// Whether the trade buys the base asset with the quote asset, or sells the base asset for it. Prices are always the
+/// amount of quote asset per base asset.
+#[cw_serde]
+#[derive(Copy, Eq)]
+pub enum BuyOrSell {
+	Buy,
+	Sell,
+}
+impl std::fmt::Display for BuyOrSell {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			BuyOrSell::Buy => "buy",
+			BuyOrSell::Sell => "sell",
+		})
+	}
+}
+
+/// Fails unless `actual >= min`.
+pub fn assert_min_receive(
+	expected_kind: &FungibleAssetKindString,
+	min: Uint128,
+	actual: Uint128,
+) -> Result<(), GuardError> {
+	if actual < min {
+		return Err(GuardError::BelowMinimum {
+			kind: expected_kind.clone(),
+			minimum: min,
+			actual,
+		});
+	}
+	Ok(())
+}
+
+/// Fails unless `actual <= max`.
+pub fn assert_max_input(input_kind: &FungibleAssetKindString, max: Uint128, actual: Uint128) -> Result<(), GuardError> {
+	if actual > max {
+		return Err(GuardError::AboveMaximum {
+			kind: input_kind.clone(),
+			maximum: max,
+			actual,
+		});
+	}
+	Ok(())
+}
+
+/// Fails if the block time is after the deadline, in milliseconds since epoch. A block at exactly the deadline passes.
+pub fn assert_deadline(env: &Env, deadline: Option<u64>) -> Result<(), GuardError> {
+	let Some(deadline) = deadline else {
+		return Ok(());
+	};
+	// Compared in nanoseconds, a deadline too large to be converted is never reached
+	let passed = deadline
+		.checked_mul(1_000_000)
+		.is_some_and(|deadline_nanos| env.block.time.nanos() > deadline_nanos);
+	if passed {
+		return Err(GuardError::DeadlinePassed {
+			deadline: Timestamp::from_millis(deadline),
+			block_time: env.block.time,
+		});
+	}
+	Ok(())
+}
+
+/// Fails unless `actual <= limit` when buying, or `actual >= limit` when selling.
+pub fn assert_price_within(actual: Decimal, limit: Decimal, direction: BuyOrSell) -> Result<(), GuardError> {
+	let within_limit = match direction {
+		BuyOrSell::Buy => actual <= limit,
+		BuyOrSell::Sell => actual >= limit,
+	};
+	if !within_limit {
+		return Err(GuardError::PriceBeyondLimit {
+			direction,
+			limit,
+			actual,
+		});
+	}
+	Ok(())
+}
+
+/// The price of a trade which spent `input` to receive `output`, as quote asset per base asset. Prices which don't
+/// fit in a [Decimal], including the infinite price of receiving nothing, are [Decimal::MAX].
+pub fn trade_price(direction: BuyOrSell, input: Uint128, output: Uint128) -> Decimal {
+	let (quote, base) = match direction {
+		BuyOrSell::Buy => (input, output),
+		BuyOrSell::Sell => (output, input),
+	};
+	Decimal::checked_from_ratio(quote, base).unwrap_or(Decimal::MAX)
+}
+
+/// The optional limits of a swap, for including in execute messages.
+#[cw_serde]
+#[derive(Default)]
+pub struct SwapGuards {
+	/// The least amount of the output asset to receive.
+	pub min_receive: Option<Uint128>,
+	/// The most amount of the input asset to spend.
+	pub max_input: Option<Uint128>,
+	/// The last block time at which the swap may execute, in milliseconds since epoch.
+	pub deadline: Option<u64>,
+	/// The worst price to trade at, see [trade_price].
+	pub price_limit: Option<Decimal>,
+}
+impl SwapGuards {
+	/// Checks all the set limits against the outcome of the swap.
+	pub fn check_all(
+		&self,
+		env: &Env,
+		direction: BuyOrSell,
+		input_kind: &FungibleAssetKindString,
+		input: Uint128,
+		output_kind: &FungibleAssetKindString,
+		output: Uint128,
+	) -> Result<(), GuardError> {
+		assert_deadline(env, self.deadline)?;
+		if let Some(max_input) = self.max_input {
+			assert_max_input(input_kind, max_input, input)?;
+		}
+		if let Some(min_receive) = self.min_receive {
+			assert_min_receive(output_kind, min_receive, output)?;
+		}
+		if let Some(price_limit) = self.price_limit {
+			assert_price_within(trade_price(direction, input, output), price_limit, direction)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::{from_json, testing::mock_env};
+
+	use super::*;
+
+	fn usei() -> FungibleAssetKindString {
+		FungibleAssetKindString::Native("usei".into())
+	}
+
+	#[test]
+	fn amount_limits_are_inclusive() {
+		let kind = usei();
+		assert_eq!(assert_min_receive(&kind, Uint128::new(100), Uint128::new(100)), Ok(()));
+		assert_eq!(assert_min_receive(&kind, Uint128::new(100), Uint128::new(101)), Ok(()));
+		assert_eq!(
+			assert_min_receive(&kind, Uint128::new(100), Uint128::new(99)),
+			Err(GuardError::BelowMinimum {
+				kind: kind.clone(),
+				minimum: Uint128::new(100),
+				actual: Uint128::new(99)
+			})
+		);
+		assert_eq!(assert_max_input(&kind, Uint128::new(100), Uint128::new(100)), Ok(()));
+		assert_eq!(assert_max_input(&kind, Uint128::new(100), Uint128::new(99)), Ok(()));
+		assert_eq!(
+			assert_max_input(&kind, Uint128::new(100), Uint128::new(101)),
+			Err(GuardError::AboveMaximum {
+				kind: kind.clone(),
+				maximum: Uint128::new(100),
+				actual: Uint128::new(101)
+			})
+		);
+		assert_eq!(
+			GuardError::BelowMinimum {
+				kind,
+				minimum: Uint128::new(100),
+				actual: Uint128::new(99)
+			}
+			.to_string(),
+			"Would receive 99 usei, which is less than the minimum of 100"
+		);
+	}
+
+	#[test]
+	fn deadline_at_block_time() {
+		let mut env = mock_env();
+		let now = env.block.time.millis();
+		env.block.time = Timestamp::from_millis(now);
+		assert_eq!(assert_deadline(&env, None), Ok(()));
+		assert_eq!(assert_deadline(&env, Some(now)), Ok(()));
+		assert_eq!(assert_deadline(&env, Some(now + 1)), Ok(()));
+		assert_eq!(assert_deadline(&env, Some(u64::MAX)), Ok(()));
+		assert!(matches!(
+			assert_deadline(&env, Some(now - 1)),
+			Err(GuardError::DeadlinePassed { .. })
+		));
+		// Sub-millisecond block times are after a deadline of the same millisecond
+		env.block.time = Timestamp::from_millis(now).plus_nanos(1);
+		assert_eq!(
+			assert_deadline(&env, Some(now)),
+			Err(GuardError::DeadlinePassed {
+				deadline: Timestamp::from_millis(now),
+				block_time: env.block.time
+			})
+		);
+	}
+
+	#[test]
+	fn price_limit_directions() {
+		let limit = Decimal::percent(150);
+		let (lower, higher) = (Decimal::percent(149), Decimal::percent(151));
+		assert_eq!(assert_price_within(limit, limit, BuyOrSell::Buy), Ok(()));
+		assert_eq!(assert_price_within(lower, limit, BuyOrSell::Buy), Ok(()));
+		assert!(assert_price_within(higher, limit, BuyOrSell::Buy).is_err());
+		assert_eq!(assert_price_within(limit, limit, BuyOrSell::Sell), Ok(()));
+		assert_eq!(assert_price_within(higher, limit, BuyOrSell::Sell), Ok(()));
+		assert_eq!(
+			assert_price_within(lower, limit, BuyOrSell::Sell),
+			Err(GuardError::PriceBeyondLimit {
+				direction: BuyOrSell::Sell,
+				limit,
+				actual: lower
+			})
+		);
+
+		assert_eq!(trade_price(BuyOrSell::Buy, Uint128::new(300), Uint128::new(200)), limit);
+		assert_eq!(
+			trade_price(BuyOrSell::Sell, Uint128::new(200), Uint128::new(300)),
+			limit
+		);
+		assert_eq!(
+			trade_price(BuyOrSell::Buy, Uint128::new(300), Uint128::zero()),
+			Decimal::MAX
+		);
+	}
+
+	#[test]
+	fn check_all() {
+		let env = mock_env();
+		let (input_kind, output_kind) = (usei(), FungibleAssetKindString::CW20("token".into()));
+		let guards: SwapGuards = from_json(b"{}").unwrap();
+		assert_eq!(guards, SwapGuards::default());
+		assert_eq!(
+			guards.check_all(
+				&env,
+				BuyOrSell::Buy,
+				&input_kind,
+				Uint128::MAX,
+				&output_kind,
+				Uint128::zero()
+			),
+			Ok(())
+		);
+
+		let guards: SwapGuards =
+			from_json(br#"{"min_receive": "200", "max_input": "300", "price_limit": "1.5"}"#).unwrap();
+		assert_eq!(
+			guards.check_all(
+				&env,
+				BuyOrSell::Buy,
+				&input_kind,
+				Uint128::new(300),
+				&output_kind,
+				Uint128::new(200)
+			),
+			Ok(())
+		);
+		assert!(matches!(
+			guards.check_all(
+				&env,
+				BuyOrSell::Buy,
+				&input_kind,
+				Uint128::new(301),
+				&output_kind,
+				Uint128::new(200)
+			),
+			Err(GuardError::AboveMaximum { .. })
+		));
+		assert!(matches!(
+			guards.check_all(
+				&env,
+				BuyOrSell::Buy,
+				&input_kind,
+				Uint128::new(299),
+				&output_kind,
+				Uint128::new(199)
+			),
+			Err(GuardError::BelowMinimum { .. })
+		));
+		// Within the amount limits, but selling 250 for 200 is a price of 0.8, which is worse than the 1.5 limit
+		assert!(matches!(
+			guards.check_all(
+				&env,
+				BuyOrSell::Sell,
+				&input_kind,
+				Uint128::new(250),
+				&output_kind,
+				Uint128::new(200)
+			),
+			Err(GuardError::PriceBeyondLimit { .. })
+		));
+
+		let guards = SwapGuards {
+			deadline: Some(env.block.time.millis() - 1),
+			..Default::default()
+		};
+		assert!(matches!(
+			guards.check_all(
+				&env,
+				BuyOrSell::Buy,
+				&input_kind,
+				Uint128::one(),
+				&output_kind,
+				Uint128::one()
+			),
+			Err(GuardError::DeadlinePassed { .. })
+		));
+	}
+}
@@ -0,0 +1,258 @@
+#[cfg(all(feature = "stargate", feature = "sei"))]
+use cosmwasm_std::{Coin, CosmosMsg, IbcMsg, IbcTimeout};
+use cosmwasm_std::{Reply, StdError, StdResult, SubMsgResult, Timestamp};
+#[cfg(all(feature = "stargate", feature = "sei"))]
+use sei_cosmwasm::SeiMsg;
+
+use crate::{
+	extentions::timestamp::TimestampExtentions,
+	storage::{expiring_map::ExpiringMap, OZeroCopy, SerializableItem},
+};
+
+/// Keeps track of data associated with IBC packets which have been sent but not acknowledged or timed out yet, e.g.
+/// who to refund if an IBC transfer fails.
+///
+/// Entries are keyed by the source channel and packet sequence, which are what the ack and timeout handlers receive.
+/// The time each entry was recorded is indexed the same way as in [ExpiringMap], so that entries which never got an
+/// ack or timeout (e.g. because the channel was closed) can be [swept](Self::sweep) without scanning every entry.
+pub struct PendingPacketMap<T: SerializableItem> {
+	entries: ExpiringMap<(String, u64), T>,
+}
+
+impl<T: SerializableItem> PendingPacketMap<T> {
+	pub const fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			entries: ExpiringMap::new(namespace),
+		}
+	}
+
+	/// Records the data for the packet, replacing any data which was already recorded for it.
+	pub fn record(&self, channel_id: &str, sequence: u64, data: &T, now: Timestamp) -> StdResult<()> {
+		// The "expiry" is the time the entry was recorded, so that purging removes the entries older than a cutoff
+		self.entries
+			.set_with_ttl(&(channel_id.to_string(), sequence), data, now, 0)
+	}
+
+	/// Returns and removes the data recorded for the packet, for use in the ack and timeout handlers.
+	pub fn take(&self, channel_id: &str, sequence: u64) -> StdResult<Option<OZeroCopy<T>>> {
+		Ok(self
+			.entries
+			.take(&(channel_id.to_string(), sequence))?
+			.map(|(_, data)| data))
+	}
+
+	/// Returns the data recorded for the packet along with the time it was recorded.
+	pub fn get(&self, channel_id: &str, sequence: u64) -> StdResult<Option<(Timestamp, OZeroCopy<T>)>> {
+		self.entries
+			.get_with_expiry(&(channel_id.to_string(), sequence), Timestamp::from_nanos(0))
+	}
+
+	/// Removes up to `limit` entries recorded before `cutoff`, oldest first.
+	///
+	/// Returns the amount of entries removed, and whether or not there are more entries older than `cutoff` left.
+	pub fn sweep(&self, cutoff: Timestamp, limit: u32) -> StdResult<(u32, bool)> {
+		if cutoff.millis() == 0 {
+			return Ok((0, false));
+		}
+		self.entries
+			.purge_expired(Timestamp::from_millis(cutoff.millis() - 1), limit)
+	}
+}
+
+fn read_varint(data: &mut &[u8]) -> StdResult<u64> {
+	let mut value = 0u64;
+	for shift in (0..64).step_by(7) {
+		let Some((&byte, rest)) = data.split_first() else {
+			break;
+		};
+		*data = rest;
+		value |= ((byte & 0x7f) as u64) << shift;
+		if byte & 0x80 == 0 {
+			return Ok(value);
+		}
+	}
+	Err(StdError::parse_err("MsgTransferResponse", "invalid varint"))
+}
+
+/// Returns the packet sequence from the protobuf encoded `MsgTransferResponse` an IBC transfer responds with.
+pub fn parse_transfer_response(mut data: &[u8]) -> StdResult<u64> {
+	let mut sequence = None;
+	while !data.is_empty() {
+		let tag = read_varint(&mut data)?;
+		let skip = match (tag >> 3, tag & 7) {
+			(1, 0) => {
+				sequence = Some(read_varint(&mut data)?);
+				0
+			}
+			(_, 0) => {
+				read_varint(&mut data)?;
+				0
+			}
+			(_, 1) => 8,
+			(_, 2) => read_varint(&mut data)? as usize,
+			(_, 5) => 4,
+			(_, wire_type) => {
+				return Err(StdError::parse_err(
+					"MsgTransferResponse",
+					format!("unsupported wire type {wire_type}"),
+				))
+			}
+		};
+		if skip > data.len() {
+			return Err(StdError::parse_err("MsgTransferResponse", "unexpected end of data"));
+		}
+		data = &data[skip..];
+	}
+	// Sequences start at 1, so 0 (which protobuf omits) is never valid
+	sequence
+		.filter(|sequence| *sequence != 0)
+		.ok_or_else(|| StdError::parse_err("MsgTransferResponse", "missing sequence"))
+}
+
+/// Returns the packet sequence of an IBC transfer which was sent as a submessage, for use in the reply handler.
+///
+/// The channel isn't part of the response, so it has to be kept track of separately (e.g. through the reply ID).
+pub fn transfer_sequence_from_reply(reply: &Reply) -> StdResult<u64> {
+	let data = match &reply.result {
+		SubMsgResult::Ok(response) => response.data.as_ref(),
+		SubMsgResult::Err(err) => return Err(StdError::generic_err(format!("IBC transfer failed: {err}"))),
+	};
+	let Some(data) = data else {
+		return Err(StdError::generic_err("IBC transfer reply has no data"));
+	};
+	parse_transfer_response(data)
+}
+
+/// Creates a message which sends `amount` to `to_address` through the specified IBC transfer channel. The transfer
+/// times out if it hasn't been received `timeout_ms` milliseconds after `now`.
+#[cfg(all(feature = "stargate", feature = "sei"))]
+pub fn build_ibc_transfer(
+	channel_id: impl Into<String>,
+	to_address: impl Into<String>,
+	amount: Coin,
+	now: Timestamp,
+	timeout_ms: u64,
+) -> CosmosMsg<SeiMsg> {
+	CosmosMsg::Ibc(IbcMsg::Transfer {
+		channel_id: channel_id.into(),
+		to_address: to_address.into(),
+		amount,
+		timeout: IbcTimeout::with_timestamp(Timestamp::from_millis(now.millis() + timeout_ms)),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::{Binary, SubMsgResponse};
+
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	fn refunds() -> PendingPacketMap<(String, u128)> {
+		PendingPacketMap::new(NAMESPACE)
+	}
+
+	fn refund(user: &str, amount: u128) -> (String, u128) {
+		(user.to_string(), amount)
+	}
+
+	#[test]
+	fn record_and_take() -> TestingResult {
+		let _storage = isolated()?;
+		let map = refunds();
+		map.record("channel-0", 1, &refund("alice", 100), Timestamp::from_seconds(10))?;
+		map.record("channel-0", 2, &refund("bob", 200), Timestamp::from_seconds(10))?;
+		map.record("channel-1", 1, &refund("carol", 300), Timestamp::from_seconds(10))?;
+		assert_eq!(
+			map.get("channel-1", 1)?.map(|(time, data)| (time, data.into_inner())),
+			Some((Timestamp::from_seconds(10), refund("carol", 300)))
+		);
+
+		assert_eq!(
+			map.take("channel-0", 2)?.map(OZeroCopy::into_inner),
+			Some(refund("bob", 200))
+		);
+		assert_eq!(map.take("channel-0", 2)?, None);
+		assert_eq!(map.take("channel-0", 3)?, None);
+		assert_eq!(
+			map.take("channel-1", 1)?.map(OZeroCopy::into_inner),
+			Some(refund("carol", 300))
+		);
+		assert_eq!(
+			map.take("channel-0", 1)?.map(OZeroCopy::into_inner),
+			Some(refund("alice", 100))
+		);
+		assert!(crate::storage::StorageKeyIterator::new(None, None).next().is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn sweep_cutoff() -> TestingResult {
+		let _storage = isolated()?;
+		let map = refunds();
+		for sequence in 1..=4u64 {
+			map.record(
+				"channel-0",
+				sequence,
+				&refund("alice", 1),
+				Timestamp::from_seconds(sequence),
+			)?;
+		}
+		assert_eq!(map.sweep(Timestamp::from_seconds(1), 10)?, (0, false));
+		assert_eq!(map.sweep(Timestamp::from_seconds(3), 1)?, (1, true));
+		assert_eq!(map.sweep(Timestamp::from_seconds(3), 10)?, (1, false));
+		assert_eq!(map.get("channel-0", 2)?, None);
+		// Recorded exactly at the cutoff, so it's not older than it
+		assert!(map.get("channel-0", 3)?.is_some());
+		assert!(map.take("channel-0", 4)?.is_some());
+		assert_eq!(map.sweep(Timestamp::from_seconds(100), 10)?, (1, false));
+		Ok(())
+	}
+
+	#[test]
+	fn transfer_responses() {
+		// sequence: 300
+		assert_eq!(parse_transfer_response(&[0x08, 0xac, 0x02]).unwrap(), 300);
+		// Unknown fields are skipped
+		assert_eq!(
+			parse_transfer_response(&[0x12, 0x02, 0xff, 0xff, 0x08, 0x07, 0x18, 0x01]).unwrap(),
+			7
+		);
+		assert!(parse_transfer_response(&[]).is_err());
+		assert!(parse_transfer_response(&[0x08, 0x00]).is_err());
+		assert!(parse_transfer_response(&[0x08, 0xac]).is_err());
+		assert!(parse_transfer_response(&[0x12, 0x05, 0x00]).is_err());
+
+		let reply = |result| Reply { id: 1, result };
+		assert_eq!(
+			transfer_sequence_from_reply(&reply(SubMsgResult::Ok(SubMsgResponse {
+				events: vec![],
+				data: Some(Binary::from([0x08, 0x2a])),
+			})))
+			.unwrap(),
+			42
+		);
+		assert!(transfer_sequence_from_reply(&reply(SubMsgResult::Ok(SubMsgResponse {
+			events: vec![],
+			data: None,
+		})))
+		.is_err());
+		assert!(transfer_sequence_from_reply(&reply(SubMsgResult::Err("out of funds".into()))).is_err());
+	}
+
+	#[cfg(all(feature = "stargate", feature = "sei"))]
+	#[test]
+	fn transfer_msg() {
+		let msg = build_ibc_transfer(
+			"channel-0",
+			"cosmos1alice",
+			cosmwasm_std::coin(5, "usei"),
+			Timestamp::from_nanos(1_500_000_123),
+			60_000,
+		);
+		let CosmosMsg::Ibc(IbcMsg::Transfer { timeout, .. }) = msg else {
+			panic!("expected an IBC transfer");
+		};
+		assert_eq!(timeout.timestamp(), Some(Timestamp::from_millis(61_500)));
+	}
+}
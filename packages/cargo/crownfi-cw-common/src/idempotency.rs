@@ -0,0 +1,248 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Binary, Env, StdError, StdResult, Timestamp};
+use thiserror::Error;
+
+use crate::{
+	data_types::canonical_addr::SeiCanonicalAddr,
+	storage::{expiring_map::ExpiringMap, OZeroCopy, SerializableItem},
+};
+
+/// The largest payload an [OperationOutcome] may have, it's meant to hold something like an order ID rather than a
+/// whole response.
+pub const IDEMPOTENCY_MAX_PAYLOAD_LEN: usize = 256;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum IdempotencyError {
+	#[error("Operation was already executed at height {}", .0.height)]
+	DuplicateOperation(OperationOutcome),
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<IdempotencyError> for StdError {
+	fn from(value: IdempotencyError) -> Self {
+		match value {
+			IdempotencyError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// What was recorded when an operation was executed.
+#[cw_serde]
+pub struct OperationOutcome {
+	pub success: bool,
+	pub payload: Binary,
+	pub height: u64,
+}
+
+const OUTCOME_HEADER_LEN: usize = 1 + 8;
+
+/// Stored as the success flag, the height (u64 LE), then the payload.
+impl SerializableItem for OperationOutcome {
+	fn serialize_to_owned(&self) -> StdResult<Vec<u8>> {
+		let mut result = Vec::with_capacity(OUTCOME_HEADER_LEN + self.payload.len());
+		result.push(self.success as u8);
+		result.extend_from_slice(&self.height.to_le_bytes());
+		result.extend_from_slice(&self.payload);
+		Ok(result)
+	}
+	fn deserialize_to_owned(data: &[u8]) -> StdResult<Self> {
+		if data.len() < OUTCOME_HEADER_LEN {
+			return Err(StdError::invalid_data_size(OUTCOME_HEADER_LEN, data.len()));
+		}
+		Ok(Self {
+			success: data[0] != 0,
+			height: u64::from_le_bytes(data[1..OUTCOME_HEADER_LEN].try_into().unwrap()),
+			payload: Binary::from(&data[OUTCOME_HEADER_LEN..]),
+		})
+	}
+}
+
+/// Records the outcome of operations identified by a client-supplied ID, so that an operation which is submitted twice
+/// (e.g. by a relayer retrying a transaction) is only applied once.
+///
+/// Records are keyed by the sender and the operation ID, so senders can't block each other's operations. They expire
+/// `ttl_ms` milliseconds after being committed, after which the operation may be executed again. Expired records are
+/// kept in storage until [prune](Self::prune) is called.
+pub struct IdempotencyMap {
+	records: ExpiringMap<(SeiCanonicalAddr, [u8; 32]), OperationOutcome>,
+	ttl_ms: u64,
+}
+
+impl IdempotencyMap {
+	pub const fn new(namespace: &'static [u8], ttl_ms: u64) -> Self {
+		Self {
+			records: ExpiringMap::new(namespace),
+			ttl_ms,
+		}
+	}
+
+	/// Returns the recorded outcome of the operation, if it was executed and its record hasn't expired.
+	pub fn get(
+		&self,
+		sender: &SeiCanonicalAddr,
+		op_id: &[u8; 32],
+		now: Timestamp,
+	) -> StdResult<Option<OperationOutcome>> {
+		Ok(self.records.get(&(*sender, *op_id), now)?.map(OZeroCopy::into_inner))
+	}
+
+	/// Starts executing the operation, returning [DuplicateOperation](IdempotencyError::DuplicateOperation) with the
+	/// recorded outcome if it was already executed.
+	///
+	/// Nothing is recorded until the returned guard is committed, so if the transaction fails the operation can be
+	/// retried.
+	pub fn begin(
+		&self,
+		sender: &SeiCanonicalAddr,
+		op_id: &[u8; 32],
+		env: &Env,
+	) -> Result<IdempotencyGuard<'_>, IdempotencyError> {
+		if let Some(outcome) = self.get(sender, op_id, env.block.time)? {
+			return Err(IdempotencyError::DuplicateOperation(outcome));
+		}
+		Ok(IdempotencyGuard {
+			map: self,
+			key: (*sender, *op_id),
+			height: env.block.height,
+			time: env.block.time,
+		})
+	}
+
+	/// Removes up to `limit` records which have expired at `now`, oldest first.
+	///
+	/// Returns the amount of records removed, and whether or not there are more expired records left to remove.
+	pub fn prune(&self, now: Timestamp, limit: u32) -> StdResult<(u32, bool)> {
+		self.records.purge_expired(now, limit)
+	}
+}
+
+/// An operation which is being executed, see [IdempotencyMap::begin]. Dropping this without committing it records
+/// nothing.
+#[must_use = "the operation isn't recorded unless the guard is committed"]
+pub struct IdempotencyGuard<'map> {
+	map: &'map IdempotencyMap,
+	key: (SeiCanonicalAddr, [u8; 32]),
+	height: u64,
+	time: Timestamp,
+}
+
+impl IdempotencyGuard<'_> {
+	/// Records that the operation succeeded, with a payload which is returned to duplicate executions.
+	pub fn commit(self, payload: impl Into<Binary>) -> StdResult<OperationOutcome> {
+		self.record(true, payload.into())
+	}
+
+	/// Records that the operation failed without reverting the transaction (e.g. a submessage which was allowed to
+	/// fail), so that it isn't retried either.
+	pub fn commit_failure(self, payload: impl Into<Binary>) -> StdResult<OperationOutcome> {
+		self.record(false, payload.into())
+	}
+
+	fn record(self, success: bool, payload: Binary) -> StdResult<OperationOutcome> {
+		if payload.len() > IDEMPOTENCY_MAX_PAYLOAD_LEN {
+			return Err(StdError::generic_err(format!(
+				"Idempotency payload is {} bytes, the most allowed is {IDEMPOTENCY_MAX_PAYLOAD_LEN}",
+				payload.len()
+			)));
+		}
+		let outcome = OperationOutcome {
+			success,
+			payload,
+			height: self.height,
+		};
+		self.map
+			.records
+			.set_with_ttl(&self.key, &outcome, self.time, self.map.ttl_ms)?;
+		Ok(outcome)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::testing::mock_env;
+
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	const HOUR: u64 = 3_600_000;
+
+	fn env_at(seconds: u64, height: u64) -> Env {
+		let mut env = mock_env();
+		env.block.time = Timestamp::from_seconds(seconds);
+		env.block.height = height;
+		env
+	}
+
+	fn map() -> IdempotencyMap {
+		IdempotencyMap::new(NAMESPACE, HOUR)
+	}
+
+	#[test]
+	fn duplicates_return_recorded_outcome() -> TestingResult {
+		let _storage = isolated()?;
+		let map = map();
+		let (alice, bob) = (SeiCanonicalAddr::from([1; 32]), SeiCanonicalAddr::from([2; 32]));
+		let env = env_at(100, 10);
+		map.begin(&alice, &[7; 32], &env)?.commit(b"order 1".as_slice())?;
+
+		let later = env_at(200, 11);
+		assert_eq!(
+			map.begin(&alice, &[7; 32], &later).err(),
+			Some(IdempotencyError::DuplicateOperation(OperationOutcome {
+				success: true,
+				payload: Binary::from(b"order 1"),
+				height: 10,
+			}))
+		);
+		// Other senders and operations aren't affected
+		map.begin(&bob, &[7; 32], &later)?.commit_failure(Binary::default())?;
+		assert!(map.begin(&alice, &[8; 32], &later).is_ok());
+		assert_eq!(
+			map.get(&bob, &[7; 32], later.block.time)?
+				.map(|outcome| outcome.success),
+			Some(false)
+		);
+
+		assert!(map
+			.begin(&alice, &[9; 32], &later)?
+			.commit(vec![0; IDEMPOTENCY_MAX_PAYLOAD_LEN + 1])
+			.is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn retry_after_failure() -> TestingResult {
+		let _storage = isolated()?;
+		let map = map();
+		let alice = SeiCanonicalAddr::from([1; 32]);
+		let env = env_at(100, 10);
+		{
+			// The handler errored out before committing
+			let _guard = map.begin(&alice, &[7; 32], &env)?;
+		}
+		assert_eq!(map.get(&alice, &[7; 32], env.block.time)?, None);
+		map.begin(&alice, &[7; 32], &env)?.commit(b"retried".as_slice())?;
+		assert!(map.begin(&alice, &[7; 32], &env).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn pruning_old_records() -> TestingResult {
+		let _storage = isolated()?;
+		let map = map();
+		let alice = SeiCanonicalAddr::from([1; 32]);
+		map.begin(&alice, &[1; 32], &env_at(0, 1))?.commit(b"old".as_slice())?;
+		map.begin(&alice, &[2; 32], &env_at(1800, 2))?
+			.commit(b"new".as_slice())?;
+
+		let now = Timestamp::from_seconds(3600);
+		// Expired records no longer count as duplicates, even before they're pruned
+		assert_eq!(map.get(&alice, &[1; 32], now)?, None);
+		assert_eq!(map.prune(now, 10)?, (1, false));
+		assert_eq!(map.prune(now, 10)?, (0, false));
+		assert!(map.get(&alice, &[2; 32], now)?.is_some());
+		assert!(map.begin(&alice, &[2; 32], &env_at(3600, 3)).is_err());
+		Ok(())
+	}
+}
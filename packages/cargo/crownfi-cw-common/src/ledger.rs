@@ -0,0 +1,324 @@
+use cosmwasm_schema::cw_serde;
+#[cfg(feature = "sei")]
+use cosmwasm_std::{Addr, QuerierWrapper};
+use cosmwasm_std::{StdError, StdResult, Uint128};
+#[cfg(feature = "sei")]
+use sei_cosmwasm::SeiQueryWrapper;
+use thiserror::Error;
+
+use crate::{
+	data_types::{
+		asset::{FungibleAssetKind, FungibleAssetKindString},
+		canonical_addr::SeiCanonicalAddr,
+	},
+	storage::map::StoredMap,
+};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum LedgerError {
+	#[error("Insufficient balance, {available} is available but {requested} was requested")]
+	InsufficientBalance { available: Uint128, requested: Uint128 },
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<LedgerError> for StdError {
+	fn from(value: LedgerError) -> Self {
+		match value {
+			LedgerError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// An asset whose total in the [Ledger] doesn't match the contract's actual balance.
+#[cw_serde]
+pub struct Discrepancy {
+	pub kind: FungibleAssetKindString,
+	/// The sum of all user balances
+	pub recorded: Uint128,
+	/// The balance held by the contract
+	pub actual: Uint128,
+}
+
+/// Per-user balances of the assets a contract holds on their behalf, along with the total of every asset.
+///
+/// Every mutation updates the user balances and the totals together, so the totals are always the sum of the user
+/// balances. [verify_against_bank](Self::verify_against_bank) compares them against what the contract actually
+/// holds, e.g. in a health-check or after every test.
+///
+/// User balances are stored as `(kind, user) => u128`, and totals as `kind => u128`.
+pub struct Ledger {
+	balances: StoredMap<(FungibleAssetKind, SeiCanonicalAddr), u128>,
+	totals: StoredMap<FungibleAssetKind, u128>,
+}
+
+impl Ledger {
+	pub fn new(balances_namespace: &'static [u8], totals_namespace: &'static [u8]) -> Self {
+		Self {
+			balances: StoredMap::new(balances_namespace),
+			totals: StoredMap::new(totals_namespace),
+		}
+	}
+
+	pub fn balance(&self, user: SeiCanonicalAddr, kind: &FungibleAssetKind) -> StdResult<Uint128> {
+		Ok(self
+			.balances
+			.get(&(kind.clone(), user))?
+			.map(|balance| Uint128::new(*balance))
+			.unwrap_or_default())
+	}
+
+	/// The sum of all user balances of the asset.
+	pub fn total(&self, kind: &FungibleAssetKind) -> StdResult<Uint128> {
+		Ok(self
+			.totals
+			.get(kind)?
+			.map(|total| Uint128::new(*total))
+			.unwrap_or_default())
+	}
+
+	/// Writes the balance, removing it if it's zero.
+	fn set_balance(&self, key: &(FungibleAssetKind, SeiCanonicalAddr), balance: Uint128) -> StdResult<()> {
+		if balance.is_zero() {
+			self.balances.remove(key);
+			return Ok(());
+		}
+		self.balances.set(key, &balance.u128())
+	}
+
+	fn set_total(&self, kind: &FungibleAssetKind, total: Uint128) -> StdResult<()> {
+		if total.is_zero() {
+			self.totals.remove(kind);
+			return Ok(());
+		}
+		self.totals.set(kind, &total.u128())
+	}
+
+	pub fn credit(&self, user: SeiCanonicalAddr, kind: &FungibleAssetKind, amount: Uint128) -> StdResult<()> {
+		// Both are checked before anything is written
+		let new_total = self.total(kind)?.checked_add(amount)?;
+		let new_balance = self.balance(user, kind)?.checked_add(amount)?;
+		self.set_balance(&(kind.clone(), user), new_balance)?;
+		self.set_total(kind, new_total)
+	}
+
+	/// Returns [LedgerError::InsufficientBalance] without changing anything if the user's balance is too low.
+	pub fn debit(&self, user: SeiCanonicalAddr, kind: &FungibleAssetKind, amount: Uint128) -> Result<(), LedgerError> {
+		let balance = self.balance(user, kind)?;
+		let new_balance = balance
+			.checked_sub(amount)
+			.map_err(|_| LedgerError::InsufficientBalance {
+				available: balance,
+				requested: amount,
+			})?;
+		// Can't underflow if the totals are in sync
+		let new_total = self.total(kind)?.checked_sub(amount).map_err(StdError::from)?;
+		self.set_balance(&(kind.clone(), user), new_balance)?;
+		Ok(self.set_total(kind, new_total)?)
+	}
+
+	/// Moves the amount between users, which doesn't change the total.
+	pub fn transfer_internal(
+		&self,
+		from: SeiCanonicalAddr,
+		to: SeiCanonicalAddr,
+		kind: &FungibleAssetKind,
+		amount: Uint128,
+	) -> Result<(), LedgerError> {
+		if from == to {
+			let available = self.balance(from, kind)?;
+			if available < amount {
+				return Err(LedgerError::InsufficientBalance {
+					available,
+					requested: amount,
+				});
+			}
+			return Ok(());
+		}
+		let from_balance = self.balance(from, kind)?;
+		let new_from_balance = from_balance
+			.checked_sub(amount)
+			.map_err(|_| LedgerError::InsufficientBalance {
+				available: from_balance,
+				requested: amount,
+			})?;
+		// Can't overflow if the totals are in sync
+		let new_to_balance = self.balance(to, kind)?.checked_add(amount).map_err(StdError::from)?;
+		self.set_balance(&(kind.clone(), from), new_from_balance)?;
+		Ok(self.set_balance(&(kind.clone(), to), new_to_balance)?)
+	}
+
+	/// Returns the assets whose total doesn't match the contract's balance as queried with
+	/// [FungibleAssetKindString::query_balance].
+	///
+	/// The contract holding more than the total is also reported, as it usually means a deposit wasn't credited.
+	#[cfg(feature = "sei")]
+	pub fn verify_against_bank(
+		&self,
+		querier: &QuerierWrapper<SeiQueryWrapper>,
+		contract_addr: &Addr,
+		kinds: &[FungibleAssetKind],
+	) -> StdResult<Vec<Discrepancy>> {
+		let mut discrepancies = Vec::new();
+		for kind in kinds {
+			let recorded = self.total(kind)?;
+			let kind = FungibleAssetKindString::try_from(kind.clone())?;
+			let actual = kind.query_balance(querier, contract_addr)?;
+			if recorded != actual {
+				discrepancies.push(Discrepancy { kind, recorded, actual });
+			}
+		}
+		Ok(discrepancies)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[cfg(feature = "sei")]
+	use cosmwasm_std::{coin, testing::MockQuerier};
+	use proptest::prelude::*;
+
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	fn ledger() -> Ledger {
+		Ledger::new(b"balances", b"totals")
+	}
+
+	fn user(id: u8) -> SeiCanonicalAddr {
+		[id; 32].into()
+	}
+
+	fn kind(id: u8) -> FungibleAssetKind {
+		FungibleAssetKind::Native(format!("denom{id}"))
+	}
+
+	#[derive(Debug, Clone)]
+	enum LedgerOp {
+		Credit(u8, u8, u64),
+		Debit(u8, u8, u64),
+		Transfer(u8, u8, u8, u64),
+	}
+
+	fn ledger_ops() -> impl Strategy<Value = Vec<LedgerOp>> {
+		let op = prop_oneof![
+			(0..4u8, 0..2u8, 0..1000u64).prop_map(|(user, kind, amount)| LedgerOp::Credit(user, kind, amount)),
+			(0..4u8, 0..2u8, 0..1000u64).prop_map(|(user, kind, amount)| LedgerOp::Debit(user, kind, amount)),
+			(0..4u8, 0..4u8, 0..2u8, 0..1000u64)
+				.prop_map(|(from, to, kind, amount)| LedgerOp::Transfer(from, to, kind, amount)),
+		];
+		prop::collection::vec(op, 0..64)
+	}
+
+	proptest! {
+		#[test]
+		fn totals_are_sums(ops in ledger_ops()) {
+			let _storage = isolated().unwrap();
+			let ledger = ledger();
+			// What the balances should be
+			let mut model = [[0u128; 2]; 4];
+			for op in ops {
+				match op {
+					LedgerOp::Credit(id, kind_id, amount) => {
+						ledger.credit(user(id), &kind(kind_id), amount.into())?;
+						model[id as usize][kind_id as usize] += amount as u128;
+					}
+					LedgerOp::Debit(id, kind_id, amount) => {
+						let result = ledger.debit(user(id), &kind(kind_id), amount.into());
+						let balance = &mut model[id as usize][kind_id as usize];
+						prop_assert_eq!(result.is_ok(), *balance >= amount as u128);
+						if result.is_ok() {
+							*balance -= amount as u128;
+						}
+					}
+					LedgerOp::Transfer(from, to, kind_id, amount) => {
+						let result = ledger.transfer_internal(user(from), user(to), &kind(kind_id), amount.into());
+						prop_assert_eq!(result.is_ok(), model[from as usize][kind_id as usize] >= amount as u128);
+						if result.is_ok() {
+							model[from as usize][kind_id as usize] -= amount as u128;
+							model[to as usize][kind_id as usize] += amount as u128;
+						}
+					}
+				}
+			}
+			for kind_id in 0..2u8 {
+				let mut sum = 0;
+				for id in 0..4u8 {
+					let balance = ledger.balance(user(id), &kind(kind_id))?.u128();
+					prop_assert_eq!(balance, model[id as usize][kind_id as usize]);
+					sum += balance;
+				}
+				prop_assert_eq!(ledger.total(&kind(kind_id))?.u128(), sum);
+			}
+		}
+	}
+
+	#[test]
+	fn insufficient_balance_changes_nothing() -> TestingResult {
+		let _storage = isolated()?;
+		let ledger = ledger();
+		let usei = kind(0);
+		ledger.credit(user(1), &usei, Uint128::new(100))?;
+		ledger.credit(user(2), &usei, Uint128::new(5))?;
+
+		let insufficient = || LedgerError::InsufficientBalance {
+			available: Uint128::new(5),
+			requested: Uint128::new(6),
+		};
+		assert_eq!(ledger.debit(user(2), &usei, Uint128::new(6)), Err(insufficient()));
+		assert_eq!(
+			ledger.transfer_internal(user(2), user(1), &usei, Uint128::new(6)),
+			Err(insufficient())
+		);
+		assert_eq!(
+			ledger.transfer_internal(user(2), user(2), &usei, Uint128::new(6)),
+			Err(insufficient())
+		);
+		assert!(ledger.debit(user(3), &usei, Uint128::new(1)).is_err());
+		assert_eq!(ledger.balance(user(1), &usei)?, Uint128::new(100));
+		assert_eq!(ledger.balance(user(2), &usei)?, Uint128::new(5));
+		assert_eq!(ledger.total(&usei)?, Uint128::new(105));
+
+		// Overflowing credits don't change anything either
+		assert!(ledger.credit(user(3), &usei, Uint128::MAX).is_err());
+		assert_eq!(ledger.balance(user(3), &usei)?, Uint128::zero());
+		assert_eq!(ledger.total(&usei)?, Uint128::new(105));
+		Ok(())
+	}
+
+	#[cfg(feature = "sei")]
+	#[test]
+	fn verify_against_bank() -> TestingResult {
+		let _storage = isolated()?;
+		let ledger = ledger();
+		let contract = Addr::unchecked("contract");
+		let (usei, uusdc, uatom) = (kind(0), kind(1), kind(2));
+		ledger.credit(user(1), &usei, Uint128::new(70))?;
+		ledger.credit(user(2), &usei, Uint128::new(30))?;
+		ledger.credit(user(1), &uusdc, Uint128::new(50))?;
+
+		// 10 uusdc went missing, and an uncredited deposit of uatom
+		let querier = MockQuerier::<SeiQueryWrapper>::new(&[(
+			contract.as_str(),
+			&[coin(100, "denom0"), coin(40, "denom1"), coin(1, "denom2")],
+		)]);
+		let querier = QuerierWrapper::new(&querier);
+		assert_eq!(
+			ledger.verify_against_bank(&querier, &contract, &[usei.clone(), uusdc, uatom])?,
+			[
+				Discrepancy {
+					kind: "denom1".into(),
+					recorded: Uint128::new(50),
+					actual: Uint128::new(40),
+				},
+				Discrepancy {
+					kind: "denom2".into(),
+					recorded: Uint128::zero(),
+					actual: Uint128::new(1),
+				},
+			]
+		);
+		assert_eq!(ledger.verify_against_bank(&querier, &contract, &[usei])?, []);
+		Ok(())
+	}
+}
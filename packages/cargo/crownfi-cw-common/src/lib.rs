@@ -1,8 +1,50 @@
+//! Common utilities for CosmWasm contracts, primarily storage containers and data types.
+//!
+//! # Feature flags
+//!
+//! | Feature | Default | Enables |
+//! |---|---|---|
+//! | `sei` | yes | Sei's custom messages and queries: [ERC20 transfers and balance queries](data_types::asset), [data_types::nft], [receive_asset], [evm_call_cache], [ledger::Ledger::verify_against_bank] and [test_helpers::msgs]. |
+//! | `stargate` | no | `ibc::build_ibc_transfer`, which also requires `sei`, as it returns a `CosmosMsg<SeiMsg>`. |
+//! | `cosmwasm_1_2`, `cosmwasm_1_4` | no | The same features of cosmwasm-std, and the host functions which require them. |
+//! | `persistent-storage` | no | `storage::file_backed`, for native simulations. |
+//! | `alloc-profiling` | no | `alloc_profiling`, which replaces the global allocator on wasm. |
+//! | `debug-print` | no | Printing with `debug_log!` on wasm. |
+//!
+//! Without `sei`, the storage layer, math extensions, [data_types::canonical_addr] and the other data types work on any
+//! CosmWasm chain. [FungibleAsset](data_types::asset::FungibleAsset) keeps its ERC20 variants so that stored and
+//! serialized values stay compatible, but only the `*_without_evm` functions are available, which return an error for
+//! ERC20 assets.
+//!
+//! Storage iteration requires cosmwasm-std's `iterator` feature, which is on by default, and is also the case for all
+//! of the above. Disabling it in a contract's own cosmwasm-std dependency has no effect, as features are unified.
+#[cfg(feature = "alloc-profiling")]
+pub mod alloc_profiling;
+pub mod allowances;
+pub mod contract_client;
+pub mod contract_registry;
+pub mod crypto;
 pub mod data_types;
+#[doc(hidden)]
+pub mod debug;
 pub mod env;
+pub mod error_code;
+pub mod evm;
+#[cfg(feature = "sei")]
+pub mod evm_call_cache;
 pub mod extentions;
+pub mod guards;
+pub mod ibc;
+pub mod idempotency;
+pub mod ledger;
 pub mod macros;
+pub mod params;
+#[cfg(feature = "sei")]
+pub mod receive_asset;
+pub mod reply_helpers;
 pub mod storage;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod test_helpers;
 pub mod utils;
 #[cfg(target_arch = "wasm32")]
 pub mod wasm_api;
@@ -79,3 +79,176 @@ macro_rules! impl_from_cosmwasm_std_error_common {
 		$crate::impl_from_cosmwasm_std_error!(cosmwasm_std::VerificationError, $to_type);
 	};
 }
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "debug-print"))]
+#[macro_export]
+/// `debug_log!("format string", args...; key = value, ...)`
+///
+/// Logs a message prefixed with the calling module's path, followed by optional `key=value` pairs, e.g.
+/// `debug_log!("swapped {}", amount; pool = pool_id)` outputs `[my_contract::pool] swapped 5 pool=1`.
+///
+/// On native targets this prints to stderr. On wasm this compiles to nothing and none of the arguments are evaluated,
+/// unless the `debug-print` feature is enabled, in which case the message is sent to the VM's `debug` host function.
+macro_rules! debug_log {
+	($fmt:literal $(, $arg:expr)* $(; $($key:ident = $value:expr),+)?) => {
+		$crate::debug::log(
+			module_path!(),
+			format_args!($fmt $(, $arg)*),
+			&[$($((stringify!($key), &$value as &dyn ::std::fmt::Display)),+)?],
+		)
+	};
+}
+
+#[cfg(all(target_arch = "wasm32", not(feature = "debug-print")))]
+#[macro_export]
+/// `debug_log!("format string", args...; key = value, ...)`
+///
+/// Logs a message prefixed with the calling module's path, followed by optional `key=value` pairs, e.g.
+/// `debug_log!("swapped {}", amount; pool = pool_id)` outputs `[my_contract::pool] swapped 5 pool=1`.
+///
+/// On native targets this prints to stderr. On wasm this compiles to nothing and none of the arguments are evaluated,
+/// unless the `debug-print` feature is enabled, in which case the message is sent to the VM's `debug` host function.
+macro_rules! debug_log {
+	($($tokens:tt)*) => {
+		$crate::__debug_log_disabled!($($tokens)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+/// What `debug_log!` expands to when disabled. The arguments are still type-checked, but never evaluated.
+macro_rules! __debug_log_disabled {
+	($fmt:literal $(, $arg:expr)* $(; $($key:ident = $value:expr),+)?) => {
+		if false {
+			let _ = format_args!($fmt $(, $arg)*);
+			$($(let _ = &$value as &dyn ::std::fmt::Display;)+)?
+		}
+	};
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[macro_export]
+/// `debug_assert_storage_invariant!(condition, namespace, "format string", args...)`
+///
+/// Like `debug_assert!`, but the panic message also lists the storage entries under `namespace`.
+///
+/// This is only checked on native targets with debug assertions enabled, e.g. in tests. On wasm, none of the arguments
+/// are evaluated.
+macro_rules! debug_assert_storage_invariant {
+	($cond:expr, $namespace:expr, $($msg:tt)+) => {
+		if cfg!(debug_assertions) && !$cond {
+			panic!(
+				"storage invariant violated: {}\n{}",
+				format_args!($($msg)+),
+				$crate::debug::dump_namespace($namespace)
+			);
+		}
+	};
+}
+
+#[cfg(target_arch = "wasm32")]
+#[macro_export]
+/// `debug_assert_storage_invariant!(condition, namespace, "format string", args...)`
+///
+/// Like `debug_assert!`, but the panic message also lists the storage entries under `namespace`.
+///
+/// This is only checked on native targets with debug assertions enabled, e.g. in tests. On wasm, none of the arguments
+/// are evaluated.
+macro_rules! debug_assert_storage_invariant {
+	($($tokens:tt)*) => {
+		$crate::__debug_assert_storage_invariant_disabled!($($tokens)*)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+/// What `debug_assert_storage_invariant!` expands to on wasm. The arguments are still type-checked, but never
+/// evaluated.
+macro_rules! __debug_assert_storage_invariant_disabled {
+	($cond:expr, $namespace:expr, $($msg:tt)+) => {
+		if false {
+			let _: bool = $cond;
+			let _: &[u8] = $namespace;
+			let _ = format_args!($($msg)+);
+		}
+	};
+}
+
+#[macro_export]
+/// `impl_partial_update!(Config => ConfigUpdate { field: Type, ... } clearable { optional_field: Type, ... })`
+///
+/// Generates a `cw_serde` struct for "update config" style messages, where every field is optional, and adds an
+/// `apply_update` method to `Config` which only overwrites the fields that were specified. It returns the names of
+/// the fields which actually changed, e.g. for event attributes.
+///
+/// Fields of `Config` which are `Option`s themselves go in the `clearable` section with their inner type. These
+/// become [Clearable](crate::data_types::clearable::Clearable)s, so they can be unset with a `null` in JSON, while a
+/// missing field leaves them as-is. `Config` must implement `PartialEq`.
+///
+/// ```
+/// # use cosmwasm_schema::cw_serde;
+/// # use cosmwasm_std::Addr;
+/// # use crownfi_cw_common::impl_partial_update;
+/// #[cw_serde]
+/// pub struct Config {
+///     pub fee_bps: u16,
+///     pub paused: bool,
+///     pub admin: Option<Addr>,
+/// }
+/// impl_partial_update!(Config => pub ConfigUpdate {
+///     fee_bps: u16,
+///     paused: bool,
+/// } clearable {
+///     admin: Addr,
+/// });
+/// ```
+macro_rules! impl_partial_update {
+	(
+		$target:ident => $(#[$meta:meta])* $vis:vis $update:ident {
+			$($field:ident: $field_type:ty),* $(,)?
+		} $(clearable {
+			$($clearable_field:ident: $clearable_type:ty),* $(,)?
+		})?
+	) => {
+		$(#[$meta])*
+		#[::cosmwasm_schema::cw_serde]
+		#[derive(Default)]
+		$vis struct $update {
+			$(
+				#[serde(default, skip_serializing_if = "Option::is_none")]
+				pub $field: Option<$field_type>,
+			)*
+			$($(
+				#[serde(default, skip_serializing_if = "Self::__is_no_change")]
+				pub $clearable_field: $crate::data_types::clearable::Clearable<$clearable_type>,
+			)*)?
+		}
+		impl $update {
+			#[doc(hidden)]
+			#[allow(dead_code)]
+			fn __is_no_change<T>(value: &$crate::data_types::clearable::Clearable<T>) -> bool {
+				value.is_no_change()
+			}
+		}
+		impl $target {
+			/// Overwrites the fields specified in the update, returning the names of the ones which changed.
+			pub fn apply_update(&mut self, update: $update) -> Vec<&'static str> {
+				let mut changed = Vec::new();
+				$(
+					if let Some(value) = update.$field {
+						if self.$field != value {
+							self.$field = value;
+							changed.push(stringify!($field));
+						}
+					}
+				)*
+				$($(
+					if update.$clearable_field.apply_to(&mut self.$clearable_field) {
+						changed.push(stringify!($clearable_field));
+					}
+				)*)?
+				changed
+			}
+		}
+	};
+}
@@ -0,0 +1,352 @@
+use std::ops::RangeInclusive;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{from_json, to_json_string, Event, StdError, StdResult};
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::storage::{map::StoredMap, SerializableItem};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ParamError {
+	#[error("{name} must be within {min} and {max}, but {value} was given")]
+	OutOfBounds {
+		name: String,
+		value: String,
+		min: String,
+		max: String,
+	},
+	#[error("There is no parameter called \"{0}\"")]
+	UnknownParam(String),
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<ParamError> for StdError {
+	fn from(value: ParamError) -> Self {
+		match value {
+			ParamError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// A typed key of a [ParamStore], these are defined with [define_params](crate::define_params).
+pub struct Param<T: 'static> {
+	pub name: &'static str,
+	pub default: T,
+	/// The inclusive range the value must be within, if any.
+	pub bounds: Option<RangeInclusive<T>>,
+}
+impl<T> Param<T> {
+	pub const fn new(name: &'static str, default: T, bounds: Option<RangeInclusive<T>>) -> Self {
+		Self { name, default, bounds }
+	}
+}
+
+/// A parameter change which has been validated but not written yet, see [ParamStore::prepare].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamChange {
+	pub name: String,
+	/// The JSON of the current value, which is the default if it's unset.
+	pub old: String,
+	/// The JSON of the new value.
+	pub new: String,
+	bytes: Vec<u8>,
+}
+impl ParamChange {
+	pub fn is_noop(&self) -> bool {
+		self.old == self.new
+	}
+	/// A `param_changed` event with the name, old and new value as attributes.
+	pub fn event(&self) -> Event {
+		Event::new("param_changed")
+			.add_attribute("name", &self.name)
+			.add_attribute("old", &self.old)
+			.add_attribute("new", &self.new)
+	}
+}
+
+/// A change to a parameter, as included in governance messages. The value is its JSON, e.g. `"30"` or `"true"`.
+#[cw_serde]
+pub struct ParamUpdate {
+	pub name: String,
+	pub value: String,
+}
+
+/// A parameter with its current and default values as JSON, as returned by [ParamStore::list].
+#[cw_serde]
+pub struct ParamInfo {
+	pub name: String,
+	pub value: String,
+	pub default: String,
+}
+
+/// A set of parameters defined with [define_params](crate::define_params), so that they can be changed and listed by
+/// name.
+pub trait ParamSet {
+	/// Validates the JSON value of the parameter called `name`.
+	fn prepare_json(store: &ParamStore, name: &str, value_json: &str) -> Result<ParamChange, ParamError>;
+	fn list(store: &ParamStore) -> StdResult<Vec<ParamInfo>>;
+}
+
+/// Tunable parameters such as fees, limits and durations, stored by name so that new ones can be added without a
+/// migration. Parameters which were never set have their default value.
+///
+/// ```ignore
+/// define_params! {
+///     pub struct PoolParams {
+///         fee_bps: Bps = Bps::new_const(30), within Bps::ZERO..=Bps::new_const(1000);
+///         max_orders: u32 = 100, within 1..=10_000;
+///         paused: bool = false;
+///     }
+/// }
+/// let params = ParamStore::new(b"params");
+/// let fee = params.get(&PoolParams::fee_bps)?;
+/// let events = params.set_many::<PoolParams>(&msg.updates)?;
+/// ```
+pub struct ParamStore {
+	values: StoredMap<String, Vec<u8>>,
+}
+impl ParamStore {
+	pub fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			values: StoredMap::new(namespace),
+		}
+	}
+
+	/// Returns the value of the parameter, or its default if it was never set.
+	pub fn get<T: SerializableItem + Clone>(&self, param: &Param<T>) -> StdResult<T> {
+		match self.values.get(&param.name.to_string())? {
+			Some(bytes) => T::deserialize_to_owned(&bytes),
+			None => Ok(param.default.clone()),
+		}
+	}
+
+	/// Checks that the value is within the parameter's bounds, and returns the change which setting it would make.
+	pub fn prepare<T>(&self, param: &Param<T>, value: T) -> Result<ParamChange, ParamError>
+	where
+		T: SerializableItem + Serialize + PartialOrd + Clone,
+	{
+		if let Some(bounds) = &param.bounds {
+			if !bounds.contains(&value) {
+				return Err(ParamError::OutOfBounds {
+					name: param.name.to_string(),
+					value: to_json_string(&value)?,
+					min: to_json_string(bounds.start())?,
+					max: to_json_string(bounds.end())?,
+				});
+			}
+		}
+		Ok(ParamChange {
+			name: param.name.to_string(),
+			old: to_json_string(&self.get(param)?)?,
+			new: to_json_string(&value)?,
+			bytes: value.serialize_to_owned()?,
+		})
+	}
+
+	/// Writes a change returned by [prepare](Self::prepare).
+	pub fn apply(&self, change: &ParamChange) -> StdResult<()> {
+		self.values.set(&change.name, &change.bytes)
+	}
+
+	/// Sets the value of the parameter, returns an error if it's out of bounds.
+	pub fn set<T>(&self, param: &Param<T>, value: T) -> Result<ParamChange, ParamError>
+	where
+		T: SerializableItem + Serialize + PartialOrd + Clone,
+	{
+		let change = self.prepare(param, value)?;
+		self.apply(&change)?;
+		Ok(change)
+	}
+
+	/// Applies all the updates, or none of them if any is invalid. Returns a `param_changed` event for each parameter
+	/// whose value changed.
+	pub fn set_many<P: ParamSet>(&self, updates: &[ParamUpdate]) -> Result<Vec<Event>, ParamError> {
+		let changes = updates
+			.iter()
+			.map(|update| P::prepare_json(self, &update.name, &update.value))
+			.collect::<Result<Vec<_>, _>>()?;
+		let mut events = Vec::new();
+		for change in changes {
+			self.apply(&change)?;
+			if !change.is_noop() {
+				events.push(change.event());
+			}
+		}
+		Ok(events)
+	}
+
+	/// Lists all the parameters with their current and default values.
+	pub fn list<P: ParamSet>(&self) -> StdResult<Vec<ParamInfo>> {
+		P::list(self)
+	}
+
+	/// Used by [define_params](crate::define_params).
+	#[doc(hidden)]
+	pub fn prepare_from_json<T>(&self, param: &Param<T>, value_json: &str) -> Result<ParamChange, ParamError>
+	where
+		T: SerializableItem + Serialize + DeserializeOwned + PartialOrd + Clone,
+	{
+		self.prepare(param, from_json(value_json)?)
+	}
+
+	/// Used by [define_params](crate::define_params).
+	#[doc(hidden)]
+	pub fn info<T: SerializableItem + Serialize + Clone>(&self, param: &Param<T>) -> StdResult<ParamInfo> {
+		Ok(ParamInfo {
+			name: param.name.to_string(),
+			value: to_json_string(&self.get(param)?)?,
+			default: to_json_string(&param.default)?,
+		})
+	}
+}
+
+#[macro_export]
+/// Defines a [ParamSet](crate::params::ParamSet) with a typed [Param](crate::params::Param) constant for each
+/// parameter, named after it. See [ParamStore](crate::params::ParamStore) for an example.
+///
+/// Each parameter is `name: Type = default;`, optionally with inclusive bounds: `name: Type = default, within min..=max;`.
+/// The types must implement `SerializableItem` for storage and serde for the governance messages and queries.
+macro_rules! define_params {
+	(
+		$vis:vis struct $set:ident {
+			$($param:ident: $param_type:ty = $default:expr $(, within $bounds:expr)?;)+
+		}
+	) => {
+		$vis struct $set;
+		#[allow(non_upper_case_globals)]
+		impl $set {
+			$(
+				pub const $param: $crate::params::Param<$param_type> = $crate::params::Param::new(
+					stringify!($param),
+					$default,
+					$crate::define_params!(@bounds $($bounds)?),
+				);
+			)+
+		}
+		impl $crate::params::ParamSet for $set {
+			fn prepare_json(
+				store: &$crate::params::ParamStore,
+				name: &str,
+				value_json: &str,
+			) -> Result<$crate::params::ParamChange, $crate::params::ParamError> {
+				match name {
+					$(stringify!($param) => store.prepare_from_json(&Self::$param, value_json),)+
+					_ => Err($crate::params::ParamError::UnknownParam(name.to_string())),
+				}
+			}
+			fn list(store: &$crate::params::ParamStore) -> cosmwasm_std::StdResult<Vec<$crate::params::ParamInfo>> {
+				Ok(vec![$(store.info(&Self::$param)?),+])
+			}
+		}
+	};
+	(@bounds) => {
+		None
+	};
+	(@bounds $bounds:expr) => {
+		Some($bounds)
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{data_types::bps::Bps, storage::testing_common::*};
+
+	define_params! {
+		pub struct PoolParams {
+			fee_bps: Bps = Bps::new_const(30), within Bps::ZERO..=Bps::new_const(1000);
+			max_orders: u32 = 100, within 1..=10_000;
+			paused: bool = false;
+		}
+	}
+
+	fn update(name: &str, value: &str) -> ParamUpdate {
+		ParamUpdate {
+			name: name.to_string(),
+			value: value.to_string(),
+		}
+	}
+
+	#[test]
+	fn defaults_and_bounds() -> TestingResult {
+		let _storage = isolated()?;
+		let params = ParamStore::new(NAMESPACE);
+		assert_eq!(params.get(&PoolParams::fee_bps)?, Bps::new(30)?);
+		assert_eq!(params.get(&PoolParams::max_orders)?, 100);
+		assert!(!params.get(&PoolParams::paused)?);
+
+		params.set(&PoolParams::max_orders, 10_000)?;
+		params.set(&PoolParams::paused, true)?;
+		assert_eq!(params.get(&PoolParams::max_orders)?, 10_000);
+		assert!(params.get(&PoolParams::paused)?);
+		assert_eq!(
+			params.set(&PoolParams::max_orders, 0),
+			Err(ParamError::OutOfBounds {
+				name: "max_orders".to_string(),
+				value: "0".to_string(),
+				min: "1".to_string(),
+				max: "10000".to_string()
+			})
+		);
+		assert!(params.set(&PoolParams::fee_bps, Bps::new(1001)?).is_err());
+		assert_eq!(params.get(&PoolParams::max_orders)?, 10_000);
+		Ok(())
+	}
+
+	#[test]
+	fn batch_updates_are_atomic() -> TestingResult {
+		let _storage = isolated()?;
+		let params = ParamStore::new(NAMESPACE);
+		for invalid in [
+			update("max_orders", "10001"),
+			update("max_orders", "\"many\""),
+			update("fee_bps", "20000"),
+			update("nonexistent", "1"),
+		] {
+			assert!(params
+				.set_many::<PoolParams>(&[update("paused", "true"), invalid])
+				.is_err());
+			assert!(!params.get(&PoolParams::paused)?);
+		}
+		assert_eq!(
+			params.set_many::<PoolParams>(&[update("nonexistent", "1")]),
+			Err(ParamError::UnknownParam("nonexistent".to_string()))
+		);
+
+		let events = params.set_many::<PoolParams>(&[
+			update("fee_bps", "50"),
+			update("paused", "false"),
+			update("max_orders", "1"),
+		])?;
+		// paused was already false
+		assert_eq!(
+			events,
+			[
+				Event::new("param_changed")
+					.add_attribute("name", "fee_bps")
+					.add_attribute("old", "30")
+					.add_attribute("new", "50"),
+				Event::new("param_changed")
+					.add_attribute("name", "max_orders")
+					.add_attribute("old", "100")
+					.add_attribute("new", "1"),
+			]
+		);
+		assert_eq!(params.get(&PoolParams::fee_bps)?, Bps::new(50)?);
+		Ok(())
+	}
+
+	#[test]
+	fn listing() -> TestingResult {
+		let _storage = isolated()?;
+		let params = ParamStore::new(NAMESPACE);
+		params.set(&PoolParams::paused, true)?;
+		assert_eq!(
+			to_json_string(&params.list::<PoolParams>()?)?,
+			r#"[{"name":"fee_bps","value":"30","default":"30"},{"name":"max_orders","value":"100","default":"100"},{"name":"paused","value":"true","default":"false"}]"#
+		);
+		Ok(())
+	}
+}
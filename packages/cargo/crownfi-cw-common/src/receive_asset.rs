@@ -0,0 +1,560 @@
+//! Checking that the contract has received the assets an execute message is paying with, or getting the messages
+//! which pull them from the payer.
+//!
+//! How an asset gets to the contract depends on its kind:
+//!
+//! * Native tokens can only be sent as `info.funds`, the exact amount must have been sent.
+//! * CW20 tokens have already been received if the message is a cw20 hook of that token. Otherwise, they're pulled
+//!   with a `TransferFrom`, which requires an allowance from the payer.
+//! * ERC20 tokens are always pulled with a `transferFrom` EVM call, which requires an allowance from the payer.
+//!
+//! Pulls are messages which have to be dispatched before the assets can be considered received. As an ERC20 token's
+//! `transferFrom` is arbitrary code, contracts which don't trust the token should dispatch them as submessages and
+//! check the result with [PendingReceive] in the reply handler.
+use borsh::{BorshDeserialize, BorshSerialize};
+use cosmwasm_std::{
+	to_json_binary, Addr, Binary, Coin, CosmosMsg, Env, MessageInfo, QuerierWrapper, StdError, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use sei_cosmwasm::{SeiMsg, SeiQueryWrapper};
+use thiserror::Error;
+
+use crate::{
+	data_types::{asset::FungibleAssetKindString, nft::evm_address_word},
+	impl_serializable_borsh,
+	storage::SerializableItem,
+	utils::{parse_ethereum_address, uint128_to_evm_word},
+};
+
+const ERC20_TRANSFER_FROM: [u8; 4] = [0x23, 0xb8, 0x72, 0xdd]; // transferFrom(address,address,uint256)
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ReceiveError {
+	#[error("Expected {expected} of {kind} to be sent, but {received} was sent")]
+	WrongAmount {
+		kind: FungibleAssetKindString,
+		expected: Uint128,
+		received: Uint128,
+	},
+	#[error("Unexpected funds of {0} were sent")]
+	UnexpectedFunds(String),
+	#[error("Native tokens can't be received through a cw20 hook, {0} must be sent as funds")]
+	NativeInCw20Hook(String),
+	#[error("{0} can't be sent as funds")]
+	NotSendableAsFunds(FungibleAssetKindString),
+	#[error("Only {received} of the {expected} {kind} which was pulled has been received")]
+	PullNotReceived {
+		kind: FungibleAssetKindString,
+		expected: Uint128,
+		received: Uint128,
+	},
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<ReceiveError> for StdError {
+	fn from(value: ReceiveError) -> Self {
+		match value {
+			ReceiveError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// The cw20 token a hook message was sent along with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cw20HookInfo<'a> {
+	/// The cw20 contract, i.e. `info.sender` of the hook
+	pub token: &'a Addr,
+	pub amount: Uint128,
+}
+
+/// Everything about the current execute message which determines whether an asset has been received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReceiveContext<'a> {
+	/// `info.funds`
+	pub funds: &'a [Coin],
+	/// Who assets which haven't been received are pulled from.
+	pub payer: &'a Addr,
+	/// The 0x\* address of the payer, required for pulling ERC20 tokens if the payer is a sei1\* EOA.
+	pub payer_evm_address: Option<&'a str>,
+	/// The current contract, which receives the pulled assets.
+	pub contract: &'a Addr,
+	pub cw20_hook: Option<Cw20HookInfo<'a>>,
+}
+
+impl<'a> ReceiveContext<'a> {
+	/// The context of a regular execute message, where `info.sender` pays.
+	pub fn new(info: &'a MessageInfo, env: &'a Env) -> Self {
+		Self {
+			funds: &info.funds,
+			payer: &info.sender,
+			payer_evm_address: None,
+			contract: &env.contract.address,
+			cw20_hook: None,
+		}
+	}
+
+	/// The context of a cw20 hook, where `info.sender` is the cw20 contract and `hook_sender` is the (validated)
+	/// `sender` of the `Cw20ReceiveMsg`, who pays for anything else.
+	pub fn cw20_hook(info: &'a MessageInfo, env: &'a Env, hook_sender: &'a Addr, amount: Uint128) -> Self {
+		Self {
+			funds: &info.funds,
+			payer: hook_sender,
+			payer_evm_address: None,
+			contract: &env.contract.address,
+			cw20_hook: Some(Cw20HookInfo {
+				token: &info.sender,
+				amount,
+			}),
+		}
+	}
+
+	pub fn with_payer_evm_address(mut self, payer_evm_address: &'a str) -> Self {
+		self.payer_evm_address = Some(payer_evm_address);
+		self
+	}
+
+	fn funds_of(&self, denom: &str) -> Uint128 {
+		self.funds
+			.iter()
+			.filter(|coin| coin.denom == denom)
+			.map(|coin| coin.amount)
+			.sum()
+	}
+
+	fn payer_evm_word(&self) -> Result<[u8; 32], StdError> {
+		let Some(payer_evm_address) = self.payer_evm_address else {
+			return evm_address_word(self.payer);
+		};
+		let mut word = [0u8; 32];
+		word[12..].copy_from_slice(&parse_ethereum_address(payer_evm_address)?);
+		Ok(word)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReceivePlan {
+	/// The contract already holds the asset, nothing else has to happen.
+	AlreadyReceived,
+	/// The asset has to be pulled from the payer by these messages, which must be dispatched before relying on it.
+	NeedsMsgs(Vec<CosmosMsg<SeiMsg>>),
+}
+
+impl ReceivePlan {
+	pub fn is_received(&self) -> bool {
+		matches!(self, ReceivePlan::AlreadyReceived)
+	}
+
+	/// The messages to dispatch, empty if the asset was already received.
+	pub fn into_msgs(self) -> Vec<CosmosMsg<SeiMsg>> {
+		match self {
+			ReceivePlan::AlreadyReceived => Vec::new(),
+			ReceivePlan::NeedsMsgs(msgs) => msgs,
+		}
+	}
+}
+
+/// Checks whether `amount` of the asset has been received, or returns the messages which pull it from the payer.
+///
+/// The only funds this looks at are those of `kind`, use [plan_receive_all] to also reject any other funds.
+pub fn plan_receive(
+	kind: &FungibleAssetKindString,
+	amount: Uint128,
+	ctx: &ReceiveContext,
+) -> Result<ReceivePlan, ReceiveError> {
+	if let Some(sent_as_funds) = ctx.funds.iter().find(|coin| {
+		!kind.is_native()
+			&& FungibleAssetKindString::from(coin.denom.as_str())
+				.to_string()
+				.to_lowercase()
+				== kind.to_string().to_lowercase()
+	}) {
+		return Err(ReceiveError::NotSendableAsFunds(sent_as_funds.denom.as_str().into()));
+	}
+	match kind {
+		FungibleAssetKindString::Native(denom) => {
+			if ctx.cw20_hook.is_some() && !amount.is_zero() {
+				return Err(ReceiveError::NativeInCw20Hook(denom.clone()));
+			}
+			let received = ctx.funds_of(denom);
+			if received != amount {
+				return Err(ReceiveError::WrongAmount {
+					kind: kind.clone(),
+					expected: amount,
+					received,
+				});
+			}
+			Ok(ReceivePlan::AlreadyReceived)
+		}
+		FungibleAssetKindString::CW20(address) => {
+			if let Some(hook) = ctx.cw20_hook.filter(|hook| hook.token.as_str() == address) {
+				if hook.amount != amount {
+					return Err(ReceiveError::WrongAmount {
+						kind: kind.clone(),
+						expected: amount,
+						received: hook.amount,
+					});
+				}
+				return Ok(ReceivePlan::AlreadyReceived);
+			}
+			if amount.is_zero() {
+				return Ok(ReceivePlan::AlreadyReceived);
+			}
+			Ok(ReceivePlan::NeedsMsgs(vec![WasmMsg::Execute {
+				contract_addr: address.clone(),
+				msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
+					owner: ctx.payer.to_string(),
+					recipient: ctx.contract.to_string(),
+					amount,
+				})?,
+				funds: vec![],
+			}
+			.into()]))
+		}
+		FungibleAssetKindString::ERC20(address) => {
+			if amount.is_zero() {
+				return Ok(ReceivePlan::AlreadyReceived);
+			}
+			let mut data = Vec::with_capacity(100);
+			data.extend_from_slice(&ERC20_TRANSFER_FROM);
+			data.extend_from_slice(&ctx.payer_evm_word()?);
+			data.extend_from_slice(&evm_address_word(ctx.contract)?);
+			data.extend_from_slice(&uint128_to_evm_word(amount));
+			Ok(ReceivePlan::NeedsMsgs(vec![SeiMsg::CallEvm {
+				value: Uint128::zero(),
+				to: address.clone(),
+				data: Binary::from(data).to_base64(),
+			}
+			.into()]))
+		}
+	}
+}
+
+/// Same as [plan_receive] for every asset, but also returns an error if funds were sent which aren't one of them.
+///
+/// Each kind should only be listed once.
+pub fn plan_receive_all(
+	assets: &[(FungibleAssetKindString, Uint128)],
+	ctx: &ReceiveContext,
+) -> Result<ReceivePlan, ReceiveError> {
+	if let Some(unexpected) = ctx.funds.iter().find(|coin| {
+		!coin.amount.is_zero()
+			&& !assets
+				.iter()
+				.any(|(kind, _)| matches!(kind, FungibleAssetKindString::Native(denom) if *denom == coin.denom))
+	}) {
+		return Err(ReceiveError::UnexpectedFunds(unexpected.denom.clone()));
+	}
+	let mut msgs = Vec::new();
+	for (kind, amount) in assets {
+		msgs.extend(plan_receive(kind, *amount, ctx)?.into_msgs());
+	}
+	if msgs.is_empty() {
+		return Ok(ReceivePlan::AlreadyReceived);
+	}
+	Ok(ReceivePlan::NeedsMsgs(msgs))
+}
+
+/// The contract's balance of an asset before it was pulled, which is compared against once the pull messages have
+/// been executed.
+///
+/// Stored between the execute call which dispatches the pulls as submessages and the reply handler, this catches
+/// tokens which don't transfer the full amount, such as ones which take a fee on transfer.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct PendingReceive {
+	pub kind: FungibleAssetKindString,
+	pub balance_before: u128,
+	pub amount: u128,
+}
+impl_serializable_borsh!(PendingReceive);
+
+impl PendingReceive {
+	/// Queries the contract's current balance of the asset.
+	pub fn new(
+		querier: &QuerierWrapper<SeiQueryWrapper>,
+		contract: &Addr,
+		kind: FungibleAssetKindString,
+		amount: Uint128,
+	) -> Result<Self, StdError> {
+		Ok(Self {
+			balance_before: kind.query_balance(querier, contract)?.u128(),
+			kind,
+			amount: amount.u128(),
+		})
+	}
+
+	/// Returns an error unless the contract's balance has increased by at least the pulled amount.
+	pub fn verify(&self, querier: &QuerierWrapper<SeiQueryWrapper>, contract: &Addr) -> Result<(), ReceiveError> {
+		let received = self
+			.kind
+			.query_balance(querier, contract)?
+			.u128()
+			.saturating_sub(self.balance_before);
+		if received < self.amount {
+			return Err(ReceiveError::PullNotReceived {
+				kind: self.kind.clone(),
+				expected: self.amount.into(),
+				received: received.into(),
+			});
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::{
+		coin, coins, from_json,
+		testing::{mock_env, mock_info, MockQuerier},
+		ContractResult, Env, SystemResult, Uint256, WasmQuery,
+	};
+	use cw20::{BalanceResponse, Cw20QueryMsg};
+
+	use super::*;
+	use crate::{
+		data_types::canonical_addr::SeiCanonicalAddr,
+		storage::testing_common::TestingResult,
+		test_helpers::msgs::{assert_wasm_execute, find_evm_calls, Erc20Call},
+		utils::bytes_to_ethereum_address,
+	};
+
+	const CW20_ADDR: &str = "sei19rl4cm2hmr8afy4kldpxz3fka4jguq0a3vute5";
+	const ERC20_ADDR: &str = "0x3894085Ef7Ff0f0aeDf52E2A2704928d1Ec074F1";
+	const PAYER_EVM_ADDR: &str = "0x000000000000000000000000000000000000dEaD";
+
+	fn canonical_to_addr(canonical_addr: SeiCanonicalAddr) -> Addr {
+		Addr::try_from(canonical_addr).unwrap()
+	}
+
+	/// The mock env's contract address isn't a valid sei1* address, which is required for receiving ERC20 tokens.
+	fn env() -> Env {
+		let mut env = mock_env();
+		env.contract.address = canonical_to_addr([7; 32].into());
+		env
+	}
+
+	fn other_cw20_addr() -> String {
+		canonical_to_addr([8; 32].into()).into_string()
+	}
+
+	fn native() -> FungibleAssetKindString {
+		FungibleAssetKindString::Native("usei".into())
+	}
+	fn cw20() -> FungibleAssetKindString {
+		FungibleAssetKindString::CW20(CW20_ADDR.into())
+	}
+	fn erc20() -> FungibleAssetKindString {
+		FungibleAssetKindString::ERC20(ERC20_ADDR.into())
+	}
+
+	fn wrong_amount(kind: FungibleAssetKindString, expected: u128, received: u128) -> ReceiveError {
+		ReceiveError::WrongAmount {
+			kind,
+			expected: expected.into(),
+			received: received.into(),
+		}
+	}
+
+	#[test]
+	fn native_funds() -> TestingResult {
+		let env = env();
+		let amount = Uint128::new(100);
+		let info = mock_info("payer", &[coin(100, "usei"), coin(5, "uusdc")]);
+		let ctx = ReceiveContext::new(&info, &env);
+		assert_eq!(plan_receive(&native(), amount, &ctx)?, ReceivePlan::AlreadyReceived);
+		// Other denoms are only rejected by plan_receive_all
+		assert_eq!(
+			plan_receive_all(&[(native(), amount)], &ctx),
+			Err(ReceiveError::UnexpectedFunds("uusdc".into()))
+		);
+
+		let info = mock_info("payer", &coins(150, "usei"));
+		let ctx = ReceiveContext::new(&info, &env);
+		assert_eq!(
+			plan_receive(&native(), amount, &ctx),
+			Err(wrong_amount(native(), 100, 150))
+		);
+		let info = mock_info("payer", &coins(99, "usei"));
+		let ctx = ReceiveContext::new(&info, &env);
+		assert_eq!(
+			plan_receive(&native(), amount, &ctx),
+			Err(wrong_amount(native(), 100, 99))
+		);
+		let info = mock_info("payer", &[]);
+		let ctx = ReceiveContext::new(&info, &env);
+		assert_eq!(
+			plan_receive(&native(), amount, &ctx),
+			Err(wrong_amount(native(), 100, 0))
+		);
+		assert_eq!(
+			plan_receive(&native(), Uint128::zero(), &ctx)?,
+			ReceivePlan::AlreadyReceived
+		);
+
+		// Native tokens can't come with a hook
+		let hook_info = mock_info(CW20_ADDR, &[]);
+		let hook_sender = Addr::unchecked("payer");
+		let ctx = ReceiveContext::cw20_hook(&hook_info, &env, &hook_sender, amount);
+		assert_eq!(
+			plan_receive(&native(), amount, &ctx),
+			Err(ReceiveError::NativeInCw20Hook("usei".into()))
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn cw20_hook_and_pull() -> TestingResult {
+		let env = env();
+		let amount = Uint128::new(100);
+		let hook_info = mock_info(CW20_ADDR, &[]);
+		let hook_sender = Addr::unchecked("payer");
+		let ctx = ReceiveContext::cw20_hook(&hook_info, &env, &hook_sender, amount);
+		assert_eq!(plan_receive(&cw20(), amount, &ctx)?, ReceivePlan::AlreadyReceived);
+		let ctx = ReceiveContext::cw20_hook(&hook_info, &env, &hook_sender, Uint128::new(60));
+		assert_eq!(plan_receive(&cw20(), amount, &ctx), Err(wrong_amount(cw20(), 100, 60)));
+
+		// Any other cw20 has to be pulled from the hook's sender, with or without a hook
+		let other_cw20 = FungibleAssetKindString::CW20(other_cw20_addr());
+		let info = mock_info("payer", &[]);
+		for ctx in [ctx, ReceiveContext::new(&info, &env)] {
+			let plan = plan_receive(&other_cw20, amount, &ctx)?;
+			assert!(!plan.is_received());
+			let msgs = plan.into_msgs();
+			assert_eq!(msgs.len(), 1);
+			assert_wasm_execute(
+				&msgs,
+				&other_cw20_addr(),
+				&Cw20ExecuteMsg::TransferFrom {
+					owner: "payer".into(),
+					recipient: env.contract.address.to_string(),
+					amount,
+				},
+			);
+		}
+		assert_eq!(
+			plan_receive(&cw20(), amount, &ReceiveContext::new(&info, &env))?
+				.into_msgs()
+				.len(),
+			1,
+			"the hook's token can be pulled as well if it wasn't sent with a hook"
+		);
+		assert_eq!(
+			plan_receive(&other_cw20, Uint128::zero(), &ReceiveContext::new(&info, &env))?,
+			ReceivePlan::AlreadyReceived
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn erc20_pull() -> TestingResult {
+		let env = env();
+		let amount = Uint128::new(100);
+		let info = mock_info(canonical_to_addr([9; 20].into()).as_str(), &[]);
+		let ctx = ReceiveContext::new(&info, &env);
+		// The payer is an EOA whose 0x* address wasn't given
+		assert!(matches!(
+			plan_receive(&erc20(), amount, &ctx),
+			Err(ReceiveError::Std(_))
+		));
+
+		let hook_info = mock_info(CW20_ADDR, &[]);
+		let hook_sender = Addr::unchecked("payer");
+		for ctx in [
+			ctx.with_payer_evm_address(PAYER_EVM_ADDR),
+			ReceiveContext::cw20_hook(&hook_info, &env, &hook_sender, amount).with_payer_evm_address(PAYER_EVM_ADDR),
+		] {
+			let msgs = plan_receive(&erc20(), amount, &ctx)?.into_msgs();
+			let calls = find_evm_calls(&msgs);
+			assert_eq!(calls.len(), 1);
+			assert_eq!(calls[0].to, ERC20_ADDR);
+			assert_eq!(
+				calls[0].erc20,
+				Some(Erc20Call::TransferFrom {
+					from: PAYER_EVM_ADDR.to_lowercase(),
+					// The contract's 0x* address is the end of its canonical address
+					to: bytes_to_ethereum_address(&[7; 20])?,
+					amount: Uint256::from(100u128),
+				})
+			);
+		}
+
+		// ERC20 and CW20 tokens can't be sent as funds, even if a denom claims to be one
+		let info = mock_info("payer", &coins(100, format!("erc20/{}", ERC20_ADDR.to_lowercase())));
+		let ctx = ReceiveContext::new(&info, &env).with_payer_evm_address(PAYER_EVM_ADDR);
+		assert!(matches!(
+			plan_receive(&erc20(), amount, &ctx),
+			Err(ReceiveError::NotSendableAsFunds(_))
+		));
+		let info = mock_info("payer", &coins(100, format!("cw20/{CW20_ADDR}")));
+		assert!(matches!(
+			plan_receive(&cw20(), amount, &ReceiveContext::new(&info, &env)),
+			Err(ReceiveError::NotSendableAsFunds(_))
+		));
+		Ok(())
+	}
+
+	#[test]
+	fn receive_all() -> TestingResult {
+		let env = env();
+		let info = mock_info("payer", &coins(100, "usei"));
+		let ctx = ReceiveContext::new(&info, &env).with_payer_evm_address(PAYER_EVM_ADDR);
+		let assets = [
+			(native(), Uint128::new(100)),
+			(cw20(), Uint128::new(200)),
+			(erc20(), Uint128::new(300)),
+		];
+		let msgs = plan_receive_all(&assets, &ctx)?.into_msgs();
+		assert_eq!(msgs.len(), 2);
+		assert_eq!(find_evm_calls(&msgs).len(), 1);
+		assert_eq!(plan_receive_all(&assets[..1], &ctx)?, ReceivePlan::AlreadyReceived);
+		// The native asset wasn't sent
+		assert_eq!(
+			plan_receive_all(&assets[1..], &ctx),
+			Err(ReceiveError::UnexpectedFunds("usei".into()))
+		);
+		let err = plan_receive_all(&[(native(), Uint128::new(50))], &ctx).unwrap_err();
+		assert_eq!(err, wrong_amount(native(), 50, 100));
+		assert!(StdError::from(err).to_string().contains("Expected 50 of usei"));
+		Ok(())
+	}
+
+	#[test]
+	fn verify_pull() -> TestingResult {
+		let contract = env().contract.address;
+		let cw20_balance = |balance: u128| {
+			move |query: &WasmQuery| match query {
+				WasmQuery::Smart { contract_addr, msg } if contract_addr == CW20_ADDR => {
+					let Cw20QueryMsg::Balance { .. } = from_json(msg).unwrap() else {
+						panic!("unexpected query");
+					};
+					SystemResult::Ok(ContractResult::Ok(
+						to_json_binary(&BalanceResponse {
+							balance: balance.into(),
+						})
+						.unwrap(),
+					))
+				}
+				_ => panic!("unexpected query"),
+			}
+		};
+		let mut querier = MockQuerier::<SeiQueryWrapper>::new(&[]);
+		querier.update_wasm(cw20_balance(1_000));
+		let pending = PendingReceive::new(&QuerierWrapper::new(&querier), &contract, cw20(), Uint128::new(100))?;
+		assert_eq!(pending.balance_before, 1_000);
+		let pending = PendingReceive::deserialize_to_owned(&pending.serialize_to_owned()?)?;
+
+		// A fee was taken
+		querier.update_wasm(cw20_balance(1_098));
+		assert_eq!(
+			pending.verify(&QuerierWrapper::new(&querier), &contract),
+			Err(ReceiveError::PullNotReceived {
+				kind: cw20(),
+				expected: Uint128::new(100),
+				received: Uint128::new(98),
+			})
+		);
+		querier.update_wasm(cw20_balance(1_100));
+		assert_eq!(pending.verify(&QuerierWrapper::new(&querier), &contract), Ok(()));
+		Ok(())
+	}
+}
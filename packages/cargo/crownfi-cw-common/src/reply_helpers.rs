@@ -0,0 +1,314 @@
+//! Passing structured data from a submessage back to the reply handler of the contract which dispatched it.
+//!
+//! The called contract uses [set_typed_data] on its response, and the caller's reply handler reads it with
+//! [parse_reply_data], or [parse_instantiate_reply] if the submessage instantiated a contract. This requires the
+//! submessage to be sent with `ReplyOn::Success` or `ReplyOn::Always`.
+//!
+//! The chain doesn't pass the called contract's data through as-is, it wraps it in the protobuf encoded
+//! `MsgExecuteContractResponse` or `MsgInstantiateContractResponse`, which is decoded with cw-utils. Newer cosmwasm
+//! versions additionally list the responses of every message executed, which isn't supported here as this crate
+//! depends on cosmwasm 1.5.
+use cosmwasm_std::{from_json, to_json_binary, Addr, Binary, Reply, Response, StdError, StdResult, SubMsgResult};
+use cw_utils::{parse_execute_response_data, parse_instantiate_response_data};
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ReplyError {
+	#[error("Submessage failed: {0}")]
+	SubcallFailed(String),
+	#[error("Submessage response has no data")]
+	MissingData,
+	#[error("Submessage response data couldn't be decoded: {0}")]
+	DecodeError(String),
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<ReplyError> for StdError {
+	fn from(value: ReplyError) -> Self {
+		match value {
+			ReplyError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// The result of a submessage which instantiated a contract, see [parse_instantiate_reply].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstantiateReply<T> {
+	pub contract_address: Addr,
+	/// The data set with [set_typed_data] by the instantiated contract, if any.
+	pub data: Option<T>,
+}
+
+/// Sets the response's data to the JSON encoding of `data`, for the caller to read with [parse_reply_data].
+pub fn set_typed_data<T: Serialize, M>(resp: Response<M>, data: &T) -> StdResult<Response<M>> {
+	Ok(resp.set_data(to_json_binary(data)?))
+}
+
+/// Returns the data of the successful submessage response, which is `None` for messages which don't set any.
+fn reply_data(reply: &Reply) -> Result<Option<&Binary>, ReplyError> {
+	match &reply.result {
+		SubMsgResult::Ok(response) => Ok(response.data.as_ref().filter(|data| !data.is_empty())),
+		SubMsgResult::Err(err) => Err(ReplyError::SubcallFailed(err.clone())),
+	}
+}
+
+fn decode_data<T: DeserializeOwned>(data: Option<Binary>) -> Result<Option<T>, ReplyError> {
+	// Contracts can't set empty data, but a message which isn't a contract call may result in it
+	let Some(data) = data.filter(|data| !data.is_empty()) else {
+		return Ok(None);
+	};
+	from_json(&data)
+		.map(Some)
+		.map_err(|err| ReplyError::DecodeError(err.to_string()))
+}
+
+/// Decodes the data set with [set_typed_data] by the contract the submessage executed.
+pub fn parse_reply_data<T: DeserializeOwned>(reply: &Reply) -> Result<T, ReplyError> {
+	let data = reply_data(reply)?.ok_or(ReplyError::MissingData)?;
+	let response = parse_execute_response_data(data).map_err(|err| ReplyError::DecodeError(err.to_string()))?;
+	decode_data(response.data)?.ok_or(ReplyError::MissingData)
+}
+
+/// Returns the address of the contract the submessage instantiated, along with the data it set with
+/// [set_typed_data], if any.
+pub fn parse_instantiate_reply<T: DeserializeOwned>(reply: &Reply) -> Result<InstantiateReply<T>, ReplyError> {
+	let data = reply_data(reply)?.ok_or(ReplyError::MissingData)?;
+	let response = parse_instantiate_response_data(data).map_err(|err| ReplyError::DecodeError(err.to_string()))?;
+	Ok(InstantiateReply {
+		contract_address: Addr::unchecked(response.contract_address),
+		data: decode_data(response.data)?,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_schema::cw_serde;
+	use cosmwasm_std::{Deps, DepsMut, Empty, Env, MessageInfo, SubMsg, SubMsgResponse, Uint128, WasmMsg};
+	use cw_multi_test::{App, ContractWrapper, Executor};
+
+	use super::*;
+
+	#[cw_serde]
+	struct SwapResult {
+		received: Uint128,
+		route: Vec<String>,
+	}
+
+	fn swap_result() -> SwapResult {
+		SwapResult {
+			received: Uint128::new(1_000),
+			route: vec!["usei".into(), "uusdc".into()],
+		}
+	}
+
+	/// The contract called through submessages, which sets its data unless `with_data` is false.
+	mod callee {
+		use super::*;
+
+		pub fn execute(_: DepsMut, _: Env, _: MessageInfo, with_data: bool) -> StdResult<Response> {
+			let response = Response::new().add_attribute("action", "swap");
+			if !with_data {
+				return Ok(response);
+			}
+			set_typed_data(response, &swap_result())
+		}
+
+		pub fn instantiate(deps: DepsMut, env: Env, info: MessageInfo, with_data: bool) -> StdResult<Response> {
+			execute(deps, env, info, with_data)
+		}
+
+		pub fn query(_: Deps, _: Env, _: Empty) -> StdResult<Binary> {
+			unimplemented!()
+		}
+	}
+
+	#[cw_serde]
+	enum CallerMsg {
+		Execute { callee: String, with_data: bool },
+		Instantiate { code_id: u64, with_data: bool },
+	}
+
+	const EXECUTE_REPLY_ID: u64 = 1;
+	const INSTANTIATE_REPLY_ID: u64 = 2;
+
+	/// Dispatches a submessage to the callee, and passes on what its reply handler parsed as its own data.
+	mod caller {
+		use super::*;
+
+		pub fn execute(_: DepsMut, _: Env, _: MessageInfo, msg: CallerMsg) -> StdResult<Response> {
+			let submsg = match msg {
+				CallerMsg::Execute { callee, with_data } => SubMsg::reply_on_success(
+					WasmMsg::Execute {
+						contract_addr: callee,
+						msg: to_json_binary(&with_data)?,
+						funds: vec![],
+					},
+					EXECUTE_REPLY_ID,
+				),
+				CallerMsg::Instantiate { code_id, with_data } => SubMsg::reply_on_success(
+					WasmMsg::Instantiate {
+						admin: None,
+						code_id,
+						msg: to_json_binary(&with_data)?,
+						funds: vec![],
+						label: "callee".into(),
+					},
+					INSTANTIATE_REPLY_ID,
+				),
+			};
+			Ok(Response::new().add_submessage(submsg))
+		}
+
+		pub fn instantiate(_: DepsMut, _: Env, _: MessageInfo, _: Empty) -> StdResult<Response> {
+			Ok(Response::new())
+		}
+
+		pub fn reply(_: DepsMut, _: Env, reply: Reply) -> StdResult<Response> {
+			match reply.id {
+				EXECUTE_REPLY_ID => set_typed_data(Response::new(), &parse_reply_data::<SwapResult>(&reply)?),
+				_ => {
+					let instantiated = parse_instantiate_reply::<SwapResult>(&reply)?;
+					set_typed_data(
+						Response::new().add_attribute("callee", instantiated.contract_address),
+						&instantiated.data,
+					)
+				}
+			}
+		}
+	}
+
+	struct Contracts {
+		app: App,
+		callee_code_id: u64,
+		caller: Addr,
+		callee: Addr,
+	}
+
+	fn contracts() -> Contracts {
+		let mut app = App::default();
+		let callee_code_id = app.store_code(Box::new(ContractWrapper::new(
+			callee::execute,
+			callee::instantiate,
+			callee::query,
+		)));
+		let caller_code_id = app.store_code(Box::new(
+			ContractWrapper::new(caller::execute, caller::instantiate, callee::query).with_reply(caller::reply),
+		));
+		let owner = Addr::unchecked("owner");
+		let caller = app
+			.instantiate_contract(caller_code_id, owner.clone(), &Empty {}, &[], "caller", None)
+			.unwrap();
+		let callee = app
+			.instantiate_contract(callee_code_id, owner, &false, &[], "callee", None)
+			.unwrap();
+		Contracts {
+			app,
+			callee_code_id,
+			caller,
+			callee,
+		}
+	}
+
+	fn call(contracts: &mut Contracts, msg: &CallerMsg) -> Result<Option<Binary>, String> {
+		contracts
+			.app
+			.execute_contract(Addr::unchecked("user"), contracts.caller.clone(), msg, &[])
+			.map(|response| response.data)
+			.map_err(|err| format!("{:#}", err))
+	}
+
+	#[test]
+	fn round_trip_through_execute() {
+		let mut contracts = contracts();
+		let msg = CallerMsg::Execute {
+			callee: contracts.callee.to_string(),
+			with_data: true,
+		};
+		let data = call(&mut contracts, &msg).unwrap().unwrap();
+		assert_eq!(from_json::<SwapResult>(&data).unwrap(), swap_result());
+
+		let msg = CallerMsg::Execute {
+			callee: contracts.callee.to_string(),
+			with_data: false,
+		};
+		let err = call(&mut contracts, &msg).unwrap_err();
+		assert!(err.contains("Submessage response has no data"), "{err}");
+	}
+
+	#[test]
+	fn round_trip_through_instantiate() {
+		let mut contracts = contracts();
+		let msg = CallerMsg::Instantiate {
+			code_id: contracts.callee_code_id,
+			with_data: true,
+		};
+		let response = contracts
+			.app
+			.execute_contract(Addr::unchecked("user"), contracts.caller.clone(), &msg, &[])
+			.unwrap();
+		let data = from_json::<Option<SwapResult>>(response.data.as_ref().unwrap()).unwrap();
+		assert_eq!(data, Some(swap_result()));
+		// The callee instantiated by the test comes first
+		assert!(response.has_event(&cosmwasm_std::Event::new("wasm").add_attribute("callee", "contract2")));
+
+		// Data is optional for instantiation
+		let msg = CallerMsg::Instantiate {
+			code_id: contracts.callee_code_id,
+			with_data: false,
+		};
+		let data = call(&mut contracts, &msg).unwrap().unwrap();
+		assert_eq!(from_json::<Option<SwapResult>>(&data).unwrap(), None);
+	}
+
+	fn reply_with_data(data: Option<Binary>) -> Reply {
+		Reply {
+			id: EXECUTE_REPLY_ID,
+			result: SubMsgResult::Ok(SubMsgResponse { events: vec![], data }),
+		}
+	}
+
+	/// Wraps the data in a `MsgExecuteContractResponse` the way the chain does, for data shorter than 128 bytes.
+	fn execute_response(data: &[u8]) -> Binary {
+		[&[0x0a, data.len() as u8], data].concat().into()
+	}
+
+	#[test]
+	fn error_branches() {
+		let failed = Reply {
+			id: EXECUTE_REPLY_ID,
+			result: SubMsgResult::Err("out of gas".into()),
+		};
+		assert_eq!(
+			parse_reply_data::<SwapResult>(&failed),
+			Err(ReplyError::SubcallFailed("out of gas".into()))
+		);
+		assert_eq!(
+			parse_instantiate_reply::<SwapResult>(&failed),
+			Err(ReplyError::SubcallFailed("out of gas".into()))
+		);
+		assert_eq!(
+			parse_reply_data::<SwapResult>(&reply_with_data(None)),
+			Err(ReplyError::MissingData)
+		);
+		assert_eq!(
+			parse_reply_data::<SwapResult>(&reply_with_data(Some(Binary::default()))),
+			Err(ReplyError::MissingData)
+		);
+		// The called contract didn't set any data
+		assert_eq!(
+			parse_reply_data::<SwapResult>(&reply_with_data(Some(execute_response(b"")))),
+			Err(ReplyError::MissingData)
+		);
+
+		// Not wrapped in a MsgExecuteContractResponse
+		let err =
+			parse_reply_data::<SwapResult>(&reply_with_data(Some(Binary::from(b"{\"received\":\"1\"}")))).unwrap_err();
+		assert!(matches!(err, ReplyError::DecodeError(_)), "{err:?}");
+		let err = parse_reply_data::<SwapResult>(&reply_with_data(Some(execute_response(b"{\"received\":\"1\"}"))))
+			.unwrap_err();
+		assert!(matches!(err, ReplyError::DecodeError(_)), "{err:?}");
+		assert!(StdError::from(err).to_string().contains("couldn't be decoded"));
+	}
+}
@@ -9,13 +9,51 @@ use std::{
 };
 
 use self::base::{storage_iter_new, storage_iter_next_key, storage_iter_next_pair};
+pub use self::renamed_map::rename_namespace;
 
+pub mod accounting;
 pub mod base;
+pub mod big_endian_key;
+pub mod book_side;
+pub mod compressed_map;
+pub mod dual_map;
+pub mod expiring_map;
+#[cfg(all(feature = "persistent-storage", not(target_arch = "wasm32")))]
+pub mod file_backed;
+pub mod graph;
+pub mod hashed_key_map;
+pub mod indexed_map;
 pub mod item;
+pub mod item_cache;
+pub mod json_export;
+pub mod key_buf;
+pub mod layout_guard;
+pub mod lazy_index;
+pub mod log;
 pub mod map;
+pub mod nonce_map;
+pub mod pod_layout;
+pub mod prefetch;
+#[cfg(test)]
+mod proptests;
 pub mod queue;
+pub mod range_compat;
+pub mod reentrancy;
+pub mod renamed_map;
+pub mod salted_map;
 pub mod set;
+pub mod small_vec;
+pub mod snapshot_map;
+pub mod stats;
+pub mod timelock;
+pub mod value_size;
 pub mod vec;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod verify;
+pub mod versioned_map;
+pub mod weighted_index;
+pub mod write_batch;
+pub mod write_once;
 
 #[derive(Copy, Clone, PartialEq, Eq)]
 #[repr(u32)]
@@ -92,6 +130,11 @@ impl<T: Sized + SerializableItem> OZeroCopy<T> {
 				.expect("deserialize_to_owned should succeed if deserialize_as_ref did before"),
 		}
 	}
+	/// Whether the value is read in place from the bytes it was created from, rather than from a deserialized copy.
+	/// Both behave the same way, so this is only useful for diagnostics such as gas profiling.
+	pub fn was_zero_copy(&self) -> bool {
+		matches!(self.0, OZeroCopyType::ZeroCopy(_))
+	}
 	pub fn try_into_bytes(self) -> Result<Vec<u8>, StdError> {
 		Ok(match self.0 {
 			OZeroCopyType::Copy(val) => val.serialize_to_owned()?,
@@ -207,13 +250,13 @@ macro_rules! impl_serializable_borsh {
 		impl SerializableItem for $data_type {
 			fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
 				let mut result = Vec::new();
-				self.serialize(&mut result).map_err(|err| {
+				BorshSerialize::serialize(self, &mut result).map_err(|err| {
 					StdError::serialize_err(stringify!($data_type), err)
 				})?;
 				Ok(result)
 			}
 			fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> where Self: Sized {
-				Self::try_from_slice(data).map_err(|err| {
+				<Self as BorshDeserialize>::try_from_slice(data).map_err(|err| {
 					StdError::parse_err(stringify!($data_type), err)
 				})
 			}
@@ -223,13 +266,13 @@ macro_rules! impl_serializable_borsh {
 		impl<$($generic),*> SerializableItem for $data_type where $($generic: BorshDeserialize + BorshSerialize),* {
 			fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
 				let mut result = Vec::new();
-				self.serialize(&mut result).map_err(|err| {
+				BorshSerialize::serialize(self, &mut result).map_err(|err| {
 					StdError::serialize_err(stringify!($data_type), err)
 				})?;
 				Ok(result)
 			}
 			fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> where Self: Sized {
-				Self::try_from_slice(data).map_err(|err| {
+				<Self as BorshDeserialize>::try_from_slice(data).map_err(|err| {
 					StdError::parse_err(stringify!($data_type), err)
 				})
 			}
@@ -410,7 +453,7 @@ impl DoubleEndedIterator for StoragePairIterator {
 	}
 	fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
 		self.0.advance_back_by(n).ok()?;
-		self.next()
+		self.next_back()
 	}
 	// TODO: Impl advance_back_by when stable
 }
@@ -448,7 +491,7 @@ impl DoubleEndedIterator for StorageKeyIterator {
 	}
 	fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
 		self.0.advance_back_by(n).ok()?;
-		self.next()
+		self.next_back()
 	}
 	// TODO: Impl advance_back_by when stable
 }
@@ -486,7 +529,7 @@ impl DoubleEndedIterator for StorageValueIterator {
 	}
 	fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
 		self.0.advance_back_by(n).ok()?;
-		self.next()
+		self.next_back()
 	}
 	// TODO: Impl advance_back_by when stable
 }
@@ -521,6 +564,7 @@ impl_serializable_borsh!(Vec<T>, T);
 
 // Bytemuck doesn't have blanket impls for tuples, but borsh does! Which allows us to be lazy when defining map keys
 impl_serializable_borsh!((T0, T1), T0, T1);
+impl_serializable_borsh!((T0, T1, T2), T0, T1, T2);
 impl_serializable_borsh!((T0, T1, T2, T3), T0, T1, T2, T3);
 impl_serializable_borsh!((T0, T1, T2, T3, T4), T0, T1, T2, T3, T4);
 impl_serializable_borsh!((T0, T1, T2, T3, T4, T5), T0, T1, T2, T3, T4, T5);
@@ -881,19 +925,139 @@ pub enum MaybeMutableStorageRef<'a> {
 
 #[cfg(test)]
 pub mod testing_common {
-	use cosmwasm_std::MemoryStorage;
+	use cosmwasm_std::{MemoryStorage, Order, Record, Storage};
+	use std::sync::{
+		atomic::{AtomicU32, Ordering},
+		Arc,
+	};
 
-	use super::base::set_global_storage;
+	use super::base::{set_global_storage, ThreadStorageGuard};
 
 	pub type TestingResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error>>;
 	pub const NAMESPACE: &[u8] = b"testing";
 
 	static MUTEX: std::sync::Mutex<()> = std::sync::Mutex::new(());
 
+	/// Resets the global storage and locks it for the duration of the test. Tests which replace the global storage
+	/// need this, others should prefer [isolated].
 	pub fn init<'a>() -> TestingResult<std::sync::MutexGuard<'a, ()>> {
-		let lock = MUTEX.lock()?;
+		// A panicking test shouldn't cause every other test to fail, the storage gets reset anyway.
+		let lock = MUTEX.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
 		set_global_storage(Box::new(MemoryStorage::new()));
 
 		Ok(lock)
 	}
+
+	/// Gives the test's thread its own empty storage until the guard is dropped. Nothing is locked, so tests using
+	/// this run in parallel with each other and with tests using [init].
+	pub fn isolated() -> TestingResult<ThreadStorageGuard> {
+		Ok(ThreadStorageGuard::new(Box::new(MemoryStorage::new())))
+	}
+
+	/// A MemoryStorage which counts how often it's accessed.
+	#[derive(Default)]
+	pub struct CountingStorage {
+		inner: MemoryStorage,
+		/// Amount of `get` calls
+		pub reads: Arc<AtomicU32>,
+		/// Amount of `range` calls, the global storage makes one of these to create an iterator and for each entry
+		/// iterated over.
+		pub ranges: Arc<AtomicU32>,
+		/// Amount of `set` and `remove` calls
+		pub writes: Arc<AtomicU32>,
+	}
+	impl Storage for CountingStorage {
+		fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+			self.reads.fetch_add(1, Ordering::SeqCst);
+			self.inner.get(key)
+		}
+		fn range<'a>(
+			&'a self,
+			start: Option<&[u8]>,
+			end: Option<&[u8]>,
+			order: Order,
+		) -> Box<dyn Iterator<Item = Record> + 'a> {
+			self.ranges.fetch_add(1, Ordering::SeqCst);
+			self.inner.range(start, end, order)
+		}
+		fn set(&mut self, key: &[u8], value: &[u8]) {
+			self.writes.fetch_add(1, Ordering::SeqCst);
+			self.inner.set(key, value)
+		}
+		fn remove(&mut self, key: &[u8]) {
+			self.writes.fetch_add(1, Ordering::SeqCst);
+			self.inner.remove(key)
+		}
+	}
+
+	/// Like [init], but uses a [CountingStorage] and returns its `reads` and `ranges` counters.
+	pub fn init_counting<'a>() -> TestingResult<(std::sync::MutexGuard<'a, ()>, Arc<AtomicU32>, Arc<AtomicU32>)> {
+		let lock = init()?;
+		let storage = CountingStorage::default();
+		let (reads, ranges) = (storage.reads.clone(), storage.ranges.clone());
+		set_global_storage(Box::new(storage));
+		Ok((lock, reads, ranges))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{stats::StoredStats, *};
+
+	#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+	struct BorshPosition {
+		owner: String,
+		amount: u64,
+	}
+	impl_serializable_borsh!(BorshPosition);
+
+	#[test]
+	fn ozerocopy_pod_round_trip() -> Result<(), StdError> {
+		let bytes = StoredStats::default().serialize_to_owned()?;
+		let bytes_ptr = bytes.as_ptr();
+		// Vec<u8> doesn't guarantee the alignment of u128, though allocators practically always provide it
+		let aligned = (bytes_ptr as usize).is_multiple_of(std::mem::align_of::<StoredStats>());
+		let mut stats = OZeroCopy::<StoredStats>::new(bytes)?;
+		assert_eq!(stats.was_zero_copy(), aligned);
+		stats.count += 1;
+		stats.sum += 5;
+		assert_eq!((stats.count, stats.sum), (1, 5));
+
+		let bytes = stats.try_into_bytes()?;
+		if aligned {
+			// The mutation happened in place, so the original buffer is handed back
+			assert_eq!(bytes.as_ptr(), bytes_ptr);
+		}
+		let stats = StoredStats::deserialize_to_owned(&bytes)?;
+		assert_eq!((stats.count, stats.sum), (1, 5));
+		assert!(OZeroCopy::<StoredStats>::new(bytes[1..].to_vec()).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn ozerocopy_borsh_round_trip() -> Result<(), StdError> {
+		let original = BorshPosition {
+			owner: "alice".to_string(),
+			amount: 5,
+		};
+		let mut position = OZeroCopy::<BorshPosition>::new(original.serialize_to_owned()?)?;
+		assert!(!position.was_zero_copy());
+		position.owner.push_str("bob");
+		position.amount += 1;
+
+		let expected = BorshPosition {
+			owner: "alicebob".to_string(),
+			amount: 6,
+		};
+		assert_eq!(
+			BorshPosition::deserialize_to_owned(&position.try_into_bytes()?)?,
+			expected
+		);
+		assert!(OZeroCopy::<BorshPosition>::new(vec![1, 2, 3]).is_err());
+
+		let stats = OZeroCopy::from_inner(StoredStats::default());
+		assert!(!stats.was_zero_copy());
+		assert_eq!(stats.try_into_bytes()?, StoredStats::default().serialize_to_owned()?);
+		Ok(())
+	}
 }
@@ -0,0 +1,317 @@
+use bytemuck::{Pod, Zeroable};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{StdError, StdResult};
+
+use crate::{assert_pod_layout, impl_serializable_as_ref};
+
+use super::{
+	base::{storage_read, storage_read_item, storage_write_item},
+	concat_byte_array_pairs,
+	write_batch::WriteBatch,
+	OZeroCopy, SerializableItem,
+};
+
+/// The usage of every accounted namespace lives under this prefix.
+pub const NAMESPACE_ACCOUNTING_NAMESPACE: &[u8] = b"\xffusage/";
+
+/// The amount of entries in a namespace and their total size, see [NamespaceAccounting].
+#[cw_serde]
+#[derive(Copy, Default, Eq, Zeroable, Pod)]
+#[repr(C)]
+#[allow(dead_code)]
+pub struct NamespaceUsage {
+	pub entries: u64,
+	/// The total length of the storage keys (including the namespace) and values.
+	pub bytes: u64,
+}
+impl_serializable_as_ref!(NamespaceUsage);
+assert_pod_layout!(NamespaceUsage, size = 16, align = 8, fields = [entries: 0, bytes: 8]);
+
+impl NamespaceUsage {
+	pub fn saturating_add(self, other: Self) -> Self {
+		Self {
+			entries: self.entries.saturating_add(other.entries),
+			bytes: self.bytes.saturating_add(other.bytes),
+		}
+	}
+
+	/// Returns the usage after the value of a key went from `old_len` to `new_len` bytes, see
+	/// [NamespaceAccounting::record]. Saturates rather than overflowing, as the usage is only informational.
+	fn with_change(mut self, key_len: usize, old_len: Option<usize>, new_len: Option<usize>) -> Self {
+		if let Some(old_len) = old_len {
+			self.entries = self.entries.saturating_sub(1);
+			self.bytes = self.bytes.saturating_sub((key_len + old_len) as u64);
+		}
+		if let Some(new_len) = new_len {
+			self.entries = self.entries.saturating_add(1);
+			self.bytes = self.bytes.saturating_add((key_len + new_len) as u64);
+		}
+		self
+	}
+}
+
+/// Keeps track of the [NamespaceUsage] of a container, e.g. for reporting (or bounding) a contract's storage
+/// footprint in case of state-size based fees.
+///
+/// Containers with accounting enabled through their `with_accounting` method read the old value of every key they
+/// set or remove, so that the usage is exact for all writes done through them, including `*_batched` writes. This
+/// costs an extra read and a write of the usage per modification, which is why it's opt-in. Writes which bypass the
+/// container, e.g. through autosaving values, aren't accounted for.
+///
+/// [StoredMap], [StoredSet], [StoredVec] and [StoredVecDeque] support accounting. Only their entries are counted, the
+/// length headers of the latter two aren't.
+///
+/// [StoredMap]: super::map::StoredMap
+/// [StoredSet]: super::set::StoredSet
+/// [StoredVec]: super::vec::StoredVec
+/// [StoredVecDeque]: super::queue::StoredVecDeque
+pub struct NamespaceAccounting {
+	usage_key: Vec<u8>,
+}
+
+impl NamespaceAccounting {
+	pub fn new(namespace: &[u8]) -> Self {
+		Self {
+			usage_key: concat_byte_array_pairs(NAMESPACE_ACCOUNTING_NAMESPACE, namespace),
+		}
+	}
+
+	pub fn usage(&self) -> StdResult<NamespaceUsage> {
+		Ok(storage_read_item(&self.usage_key)?.map_or_else(NamespaceUsage::default, OZeroCopy::into_inner))
+	}
+
+	/// Records that the value of `key` went from being `old_len` bytes long to `new_len` bytes long, `None` meaning
+	/// that it didn't exist.
+	pub fn record(&self, key_len: usize, old_len: Option<usize>, new_len: Option<usize>) -> StdResult<()> {
+		if old_len.is_none() && new_len.is_none() {
+			return Ok(());
+		}
+		let usage = self.usage()?.with_change(key_len, old_len, new_len);
+		storage_write_item(&self.usage_key, &usage)
+	}
+
+	/// Same as [record](Self::record), but reads the old value's length from storage.
+	pub(crate) fn record_write(&self, key: &[u8], new_len: Option<usize>) -> StdResult<()> {
+		self.record(key.len(), storage_read(key).map(|value| value.len()), new_len)
+	}
+
+	/// Same as [record_write](Self::record_write), but for a write added to `batch`. The old value's length and the
+	/// usage take the batch's pending writes into account, and the usage is written once the batch is committed.
+	pub(crate) fn record_batched(&self, batch: &mut WriteBatch, key: &[u8], new_len: Option<usize>) -> StdResult<()> {
+		let old_len = batch
+			.pending_len(key)
+			.unwrap_or_else(|| storage_read(key).map(|value| value.len()));
+		if old_len.is_none() && new_len.is_none() {
+			return Ok(());
+		}
+		let usage = match batch.header(&self.usage_key) {
+			Some(bytes) => NamespaceUsage::deserialize_to_owned(bytes)?,
+			None => self.usage()?,
+		};
+		let usage = usage.with_change(key.len(), old_len, new_len);
+		batch.set_header(
+			&self.usage_key,
+			usage
+				.serialize_as_ref()
+				.expect("NamespaceUsage is serializable as ref")
+				.to_vec(),
+		);
+		Ok(())
+	}
+}
+
+/// A container which may keep track of its [NamespaceUsage].
+pub trait Accounted {
+	/// Returns the container's usage, or an error if it doesn't have accounting enabled.
+	fn accounting(&self) -> StdResult<NamespaceUsage>;
+}
+
+/// Adds up the usage of the containers, e.g. for a query reporting the whole contract's storage footprint.
+pub fn sum_usage(containers: &[&dyn Accounted]) -> StdResult<NamespaceUsage> {
+	containers
+		.iter()
+		.try_fold(NamespaceUsage::default(), |total, container| {
+			Ok(total.saturating_add(container.accounting()?))
+		})
+}
+
+pub(crate) fn accounting_not_enabled() -> StdError {
+	StdError::generic_err("accounting isn't enabled for this container")
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::Ordering;
+
+	use super::*;
+	use crate::storage::{
+		base::{set_global_storage, storage_write},
+		map::StoredMap,
+		queue::StoredVecDeque,
+		set::StoredSet,
+		testing_common::*,
+		vec::StoredVec,
+		StorageKeyIterator,
+	};
+
+	#[test]
+	fn tracks_sets_and_removes() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = StoredMap::<u32, String>::new(NAMESPACE).with_accounting();
+		let key_len = NAMESPACE.len() + 4;
+		// Borsh strings have a 4 byte length prefix
+		let entry_len = |value: &str| (key_len + 4 + value.len()) as u64;
+
+		map.set(&1, &"a".to_string())?;
+		map.set(&2, &"bcd".to_string())?;
+		assert_eq!(
+			map.accounting()?,
+			NamespaceUsage {
+				entries: 2,
+				bytes: entry_len("a") + entry_len("bcd")
+			}
+		);
+		// Overwriting only changes the size
+		map.set(&1, &"efghij".to_string())?;
+		assert_eq!(
+			map.accounting()?,
+			NamespaceUsage {
+				entries: 2,
+				bytes: entry_len("efghij") + entry_len("bcd")
+			}
+		);
+		map.remove(&2);
+		map.remove(&3);
+		let mut buf = Vec::new();
+		map.set_with_buf(&4, &"k".to_string(), &mut buf)?;
+		map.remove_with_buf(&1, &mut buf);
+		assert_eq!(
+			map.accounting()?,
+			NamespaceUsage {
+				entries: 1,
+				bytes: entry_len("k")
+			}
+		);
+		map.remove(&4);
+		assert_eq!(map.accounting()?, NamespaceUsage::default());
+
+		let set = StoredSet::<u64>::new(b"set").with_accounting();
+		set.add(&5)?;
+		set.add(&5)?;
+		assert_eq!(set.accounting()?.entries, 1);
+		assert_eq!(
+			sum_usage(&[&map, &set])?,
+			NamespaceUsage {
+				entries: 1,
+				bytes: 3 + 8 + 1
+			}
+		);
+		assert!(StoredMap::<u32, u32>::new(b"other").accounting().is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn tracks_batched_writes() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = StoredMap::<u32, u32>::new(NAMESPACE).with_accounting();
+		let entry_len = (NAMESPACE.len() + 4 + 4) as u64;
+		map.set(&1, &1)?;
+
+		let mut batch = WriteBatch::new();
+		// Writes to the same key within the batch only count once
+		map.set_batched(&mut batch, &2, &2)?;
+		map.set_batched(&mut batch, &2, &3)?;
+		map.set_batched(&mut batch, &3, &3)?;
+		map.remove_batched(&mut batch, &3)?;
+		map.remove_batched(&mut batch, &1)?;
+		map.remove_batched(&mut batch, &4)?;
+		// Nothing changes until the batch is committed
+		assert_eq!(map.accounting()?.entries, 1);
+		batch.commit();
+		assert_eq!(
+			map.accounting()?,
+			NamespaceUsage {
+				entries: 1,
+				bytes: entry_len
+			}
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn tracks_vec_and_queue_elements() -> TestingResult {
+		let _storage_lock = init()?;
+		let entry_len = |namespace: &[u8]| (namespace.len() + 4 + 2) as u64;
+
+		let mut vec = StoredVec::<u16>::new(NAMESPACE).with_accounting();
+		vec.extend([1, 2, 3].into_iter())?;
+		vec.insert(0, &0)?;
+		vec.swap_remove(1)?;
+		vec.pop()?;
+		assert_eq!(
+			vec.accounting()?,
+			NamespaceUsage {
+				entries: 2,
+				bytes: 2 * entry_len(NAMESPACE)
+			}
+		);
+
+		let mut queue = StoredVecDeque::<u16>::new(b"queue").with_accounting();
+		queue.push_back(&1)?;
+		queue.push_front(&0)?;
+		queue.push_back(&2)?;
+		queue.pop_front()?;
+		assert_eq!(
+			queue.accounting()?,
+			NamespaceUsage {
+				entries: 2,
+				bytes: 2 * entry_len(b"queue")
+			}
+		);
+		queue.clear(false);
+		assert_eq!(queue.accounting()?, NamespaceUsage::default());
+		Ok(())
+	}
+
+	#[test]
+	fn corrupted_usage_is_an_error() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = StoredMap::<u32, u32>::new(NAMESPACE).with_accounting();
+		map.set(&1, &1)?;
+		storage_write(
+			&concat_byte_array_pairs(NAMESPACE_ACCOUNTING_NAMESPACE, NAMESPACE),
+			b"bad",
+		);
+		assert!(map.set(&2, &2).is_err());
+		assert!(map.try_remove(&1).is_err());
+		assert!(map.set_batched(&mut WriteBatch::new(), &2, &2).is_err());
+		assert_eq!(map.get(&1)?.map(OZeroCopy::into_inner), Some(1));
+		Ok(())
+	}
+
+	#[test]
+	fn no_overhead_when_disabled() -> TestingResult {
+		let _storage_lock = init()?;
+		let storage = CountingStorage::default();
+		let (reads, writes) = (storage.reads.clone(), storage.writes.clone());
+		set_global_storage(Box::new(storage));
+
+		let map = StoredMap::<u32, u32>::new(NAMESPACE);
+		map.set(&1, &1)?;
+		map.set(&1, &2)?;
+		map.remove(&1);
+		assert_eq!((reads.load(Ordering::SeqCst), writes.load(Ordering::SeqCst)), (0, 3));
+		assert!(StorageKeyIterator::new(
+			Some(NAMESPACE_ACCOUNTING_NAMESPACE),
+			Some(&crate::utils::lexicographic_next(NAMESPACE_ACCOUNTING_NAMESPACE))
+		)
+		.next()
+		.is_none());
+
+		// Each modification reads the old value and the usage, and writes the usage
+		let map = map.with_accounting();
+		map.set(&1, &1)?;
+		assert_eq!((reads.load(Ordering::SeqCst), writes.load(Ordering::SeqCst)), (2, 5));
+		Ok(())
+	}
+}
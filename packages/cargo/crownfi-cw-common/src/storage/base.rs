@@ -1,10 +1,17 @@
-use super::{IteratorDirection, OZeroCopy, SerializableItem, StorageIterId};
+use super::{
+	concat_byte_array_pairs,
+	item_cache::ItemCache,
+	value_size::{check_value_size, StorageWriteError},
+	IteratorDirection, OZeroCopy, SerializableItem, StorageIterId,
+};
+use crate::utils::lexicographic_next;
 use cosmwasm_std::{StdError, Storage};
 
 #[cfg(not(target_arch = "wasm32"))]
 use cosmwasm_std::MemoryStorage;
 #[cfg(not(target_arch = "wasm32"))]
 use std::{
+	cell::{Cell, RefCell},
 	collections::BTreeMap,
 	sync::{atomic::AtomicU32, OnceLock, RwLock},
 };
@@ -12,18 +19,74 @@ use std::{
 #[cfg(target_arch = "wasm32")]
 use super::super::wasm_api;
 
-pub fn storage_read_item<T: SerializableItem + Sized>(key: &[u8]) -> Result<Option<OZeroCopy<T>>, StdError> {
-	if let Some(bytes) = storage_read(key) {
-		Ok(Some(OZeroCopy::new(bytes)?))
-	} else {
-		Ok(None)
+/// Keys longer than this are truncated when included in error messages.
+const MAX_CONTEXT_KEY_LEN: usize = 64;
+
+/// Appends the storage location of a value to an error returned while reading it, so that errors from corrupted or
+/// incompatible state can be traced back to the container and entry.
+///
+/// The namespace is shown as (lossy) UTF-8 and the key as hex, truncated if it's very long. Parse and serialize
+/// errors keep their variant, other errors are turned into generic errors.
+pub fn with_storage_context(err: StdError, namespace: &[u8], key: &[u8]) -> StdError {
+	let mut context = format!("(namespace: {:?}", String::from_utf8_lossy(namespace));
+	if !key.is_empty() {
+		context.push_str(", key: 0x");
+		context.push_str(&hex::encode(&key[..key.len().min(MAX_CONTEXT_KEY_LEN)]));
+		if key.len() > MAX_CONTEXT_KEY_LEN {
+			context.push('…');
+		}
+	}
+	context.push(')');
+	match err {
+		StdError::ParseErr { target_type, msg, .. } => StdError::parse_err(target_type, format!("{msg} {context}")),
+		StdError::SerializeErr { source_type, msg, .. } => {
+			StdError::serialize_err(source_type, format!("{msg} {context}"))
+		}
+		err => StdError::generic_err(format!("{err} {context}")),
 	}
 }
+
+pub fn storage_read_item<T: SerializableItem + Sized>(key: &[u8]) -> Result<Option<OZeroCopy<T>>, StdError> {
+	storage_read_namespaced_item(key, key.len())
+}
+/// Like [storage_read_item], but errors report `key[..namespace_len]` as the namespace and the rest as the key.
+pub(crate) fn storage_read_namespaced_item<T: SerializableItem + Sized>(
+	key: &[u8],
+	namespace_len: usize,
+) -> Result<Option<OZeroCopy<T>>, StdError> {
+	let Some(bytes) = storage_read(key) else {
+		return Ok(None);
+	};
+	let (namespace, key) = key.split_at(namespace_len);
+	Ok(Some(
+		OZeroCopy::new(bytes).map_err(|err| with_storage_context(err, namespace, key))?,
+	))
+}
+/// Returns [ValueTooLarge](super::value_size::ValueTooLarge) instead of writing if the serialized value exceeds the
+/// limit set with [set_max_value_size](super::value_size::set_max_value_size).
 pub fn storage_write_item<T: SerializableItem>(key: &[u8], value: &T) -> Result<(), StdError> {
+	Ok(try_storage_write_item(key, value)?)
+}
+/// Same as [storage_write_item], but returns a [StorageWriteError] which can be matched on to tell a value which is
+/// too large apart from other errors.
+pub fn try_storage_write_item<T: SerializableItem>(key: &[u8], value: &T) -> Result<(), StorageWriteError> {
+	storage_write_limited_item(key, key.len(), value, None)
+}
+/// Like [storage_write_item], but `max_size` overrides the global limit and errors report `key[..namespace_len]` as
+/// the namespace.
+pub(crate) fn storage_write_limited_item<T: SerializableItem>(
+	key: &[u8],
+	namespace_len: usize,
+	value: &T,
+	max_size: Option<usize>,
+) -> Result<(), StorageWriteError> {
 	if let Some(bytes) = value.serialize_as_ref() {
+		check_value_size(&key[..namespace_len], bytes.len(), max_size)?;
 		storage_write(key, bytes);
 	} else {
-		storage_write(key, &value.serialize_to_owned()?);
+		let bytes = value.serialize_to_owned()?;
+		check_value_size(&key[..namespace_len], bytes.len(), max_size)?;
+		storage_write(key, &bytes);
 	}
 	Ok(())
 }
@@ -41,11 +104,13 @@ pub fn storage_read(key: &[u8]) -> Option<Vec<u8>> {
 #[cfg(target_arch = "wasm32")]
 #[inline]
 pub fn storage_write(key: &[u8], value: &[u8]) {
+	ItemCache::on_write(key, Some(value));
 	wasm_api::storage::storage_write(key, value)
 }
 #[cfg(target_arch = "wasm32")]
 #[inline]
 pub fn storage_remove(key: &[u8]) {
+	ItemCache::on_write(key, None);
 	wasm_api::storage::storage_remove(key)
 }
 
@@ -84,6 +149,21 @@ pub fn set_global_storage(storage: Box<dyn ThreadSafeStorage>) -> Box<dyn Thread
 #[cfg(not(target_arch = "wasm32"))]
 static STORAGE_SEQ: AtomicU32 = AtomicU32::new(0);
 
+#[cfg(not(target_arch = "wasm32"))]
+thread_local! {
+	static THREAD_STORAGE: RefCell<Option<Box<dyn ThreadSafeStorage>>> = const { RefCell::new(None) };
+	static THREAD_STORAGE_SEQ: Cell<u32> = const { Cell::new(0) };
+	static THREAD_ITER_STATES: RefCell<BTreeMap<StorageIterId, IterState>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Changes every time the storage used by the current thread is replaced, either the global one or its override.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn storage_seq() -> u32 {
+	STORAGE_SEQ
+		.load(std::sync::atomic::Ordering::SeqCst)
+		.wrapping_add(THREAD_STORAGE_SEQ.get())
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 fn global_storage() -> &'static RwLock<Box<dyn ThreadSafeStorage>> {
 	static STORAGE: OnceLock<RwLock<Box<dyn ThreadSafeStorage>>> = OnceLock::new();
@@ -100,17 +180,79 @@ pub fn set_global_storage(storage: Box<dyn ThreadSafeStorage>) -> Box<dyn Thread
 	let mut writable_ref = global_storage().write().unwrap();
 	std::mem::replace(&mut *writable_ref, storage)
 }
+
+/// In a non-wasm32 environment, this makes the current thread use the specified storage instead of the global one,
+/// `None` going back to the global storage. Returns the thread's previous override.
+///
+/// Unlike the global storage, threads with their own storage don't affect each other, so tests using it can run in
+/// parallel. Iterators are per-thread as well, iterators created before the override was changed end early.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_thread_storage(storage: Option<Box<dyn ThreadSafeStorage>>) -> Option<Box<dyn ThreadSafeStorage>> {
+	THREAD_STORAGE_SEQ.set(THREAD_STORAGE_SEQ.get().wrapping_add(1));
+	THREAD_ITER_STATES.with_borrow_mut(BTreeMap::clear);
+	THREAD_STORAGE.replace(storage)
+}
+
+/// Overrides the current thread's storage with [set_thread_storage] until dropped, after which the previous override
+/// (if any) is restored.
+#[cfg(not(target_arch = "wasm32"))]
+#[must_use = "the override is removed when the guard is dropped"]
+pub struct ThreadStorageGuard {
+	previous: Option<Option<Box<dyn ThreadSafeStorage>>>,
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl ThreadStorageGuard {
+	pub fn new(storage: Box<dyn ThreadSafeStorage>) -> Self {
+		Self {
+			previous: Some(set_thread_storage(Some(storage))),
+		}
+	}
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for ThreadStorageGuard {
+	fn drop(&mut self) {
+		if let Some(previous) = self.previous.take() {
+			set_thread_storage(previous);
+		}
+	}
+}
+
+/// Runs `f` with the current thread using the specified storage, see [set_thread_storage].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn with_thread_storage<R>(storage: Box<dyn ThreadSafeStorage>, f: impl FnOnce() -> R) -> R {
+	let _guard = ThreadStorageGuard::new(storage);
+	f()
+}
+
+/// Calls `f` with the current thread's storage override, or the global storage if it doesn't have one.
+#[cfg(not(target_arch = "wasm32"))]
+fn with_storage<R>(f: impl FnOnce(&dyn ThreadSafeStorage) -> R) -> R {
+	THREAD_STORAGE.with_borrow(|thread_storage| match thread_storage {
+		Some(storage) => f(storage.as_ref()),
+		None => f(global_storage().read().unwrap().as_ref()),
+	})
+}
+#[cfg(not(target_arch = "wasm32"))]
+fn with_storage_mut<R>(f: impl FnOnce(&mut dyn ThreadSafeStorage) -> R) -> R {
+	THREAD_STORAGE.with_borrow_mut(|thread_storage| match thread_storage {
+		Some(storage) => f(storage.as_mut()),
+		None => f(global_storage().write().unwrap().as_mut()),
+	})
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn storage_read(key: &[u8]) -> Option<Vec<u8>> {
-	global_storage().read().unwrap().get(key)
+	with_storage(|storage| storage.get(key))
 }
 #[cfg(not(target_arch = "wasm32"))]
 pub fn storage_write(key: &[u8], value: &[u8]) {
-	global_storage().write().unwrap().set(key, value)
+	ItemCache::on_write(key, Some(value));
+	with_storage_mut(|storage| storage.set(key, value))
 }
 #[cfg(not(target_arch = "wasm32"))]
 pub fn storage_remove(key: &[u8]) {
-	global_storage().write().unwrap().remove(key)
+	ItemCache::on_write(key, None);
+	with_storage_mut(|storage| storage.remove(key))
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -128,28 +270,43 @@ fn storage_iter_states() -> &'static RwLock<BTreeMap<StorageIterId, IterState>>
 	static STORAGE: OnceLock<RwLock<BTreeMap<StorageIterId, IterState>>> = OnceLock::new();
 	STORAGE.get_or_init(|| RwLock::new(BTreeMap::new()))
 }
+/// Calls `f` with the iterator states of the storage [with_storage] uses, along with the sequence number iterators
+/// created now would have.
+#[cfg(not(target_arch = "wasm32"))]
+fn with_iter_states<R>(f: impl FnOnce(&mut BTreeMap<StorageIterId, IterState>, u32) -> R) -> R {
+	use std::sync::atomic::Ordering;
+
+	if THREAD_STORAGE.with_borrow(Option::is_some) {
+		THREAD_ITER_STATES.with_borrow_mut(|iter_states| f(iter_states, THREAD_STORAGE_SEQ.get()))
+	} else {
+		f(
+			&mut storage_iter_states().write().unwrap(),
+			STORAGE_SEQ.load(Ordering::SeqCst),
+		)
+	}
+}
 #[cfg(not(target_arch = "wasm32"))]
 pub fn storage_iter_new(start: Option<&[u8]>, end: Option<&[u8]>, direction: IteratorDirection) -> StorageIterId {
 	use std::sync::atomic::Ordering;
 
 	let iter_id = StorageIterId(ITER_SEQ.fetch_add(1, Ordering::SeqCst));
-	let storage: std::sync::RwLockReadGuard<Box<dyn ThreadSafeStorage>> = global_storage().read().unwrap();
-	let first_record = storage.range(start, end, direction.into()).next();
+	let first_record = with_storage(|storage| storage.range(start, end, direction.into()).next());
 	if let Some(next_record) = first_record {
-		let mut iter_states = storage_iter_states().write().unwrap();
-		iter_states.insert(
-			iter_id,
-			IterState {
-				next_record,
-				end: match direction {
-					IteratorDirection::Ascending => end,
-					IteratorDirection::Descending => start,
-				}
-				.map(|bytes| Vec::from(bytes)),
-				direction,
-				storage_nonce: STORAGE_SEQ.load(Ordering::SeqCst),
-			},
-		);
+		with_iter_states(|iter_states, storage_nonce| {
+			iter_states.insert(
+				iter_id,
+				IterState {
+					next_record,
+					end: match direction {
+						IteratorDirection::Ascending => end,
+						IteratorDirection::Descending => start,
+					}
+					.map(Vec::from),
+					direction,
+					storage_nonce,
+				},
+			);
+		});
 		iter_id
 	} else {
 		// It will always return None
@@ -158,41 +315,37 @@ pub fn storage_iter_new(start: Option<&[u8]>, end: Option<&[u8]>, direction: Ite
 }
 #[cfg(not(target_arch = "wasm32"))]
 pub fn storage_iter_next_pair(iter: StorageIterId) -> Option<(Vec<u8>, Vec<u8>)> {
-	use std::sync::atomic::Ordering;
-
-	let mut iter_states = storage_iter_states().write().unwrap();
-	let Some(iter_state) = iter_states.get_mut(&iter) else {
-		return None;
-	};
-	if iter_state.storage_nonce < STORAGE_SEQ.load(Ordering::SeqCst) {
-		iter_states.remove(&iter);
-		return None;
-	}
+	with_iter_states(|iter_states, storage_nonce| {
+		let iter_state = iter_states.get_mut(&iter)?;
+		if iter_state.storage_nonce != storage_nonce {
+			iter_states.remove(&iter);
+			return None;
+		}
 
-	let storage: std::sync::RwLockReadGuard<Box<dyn ThreadSafeStorage>> = global_storage().read().unwrap();
-	let next_record = match iter_state.direction {
-		IteratorDirection::Ascending => {
-			let mut next_key = Vec::with_capacity(iter_state.next_record.0.len() + 1);
-			next_key.extend_from_slice(&iter_state.next_record.0);
-			next_key.push(0);
+		let next_record = with_storage(|storage| match iter_state.direction {
+			IteratorDirection::Ascending => {
+				let mut next_key = Vec::with_capacity(iter_state.next_record.0.len() + 1);
+				next_key.extend_from_slice(&iter_state.next_record.0);
+				next_key.push(0);
 
-			storage
-				.range(Some(&next_key), iter_state.end.as_deref(), iter_state.direction.into())
-				.next()
+				storage
+					.range(Some(&next_key), iter_state.end.as_deref(), iter_state.direction.into())
+					.next()
+			}
+			IteratorDirection::Descending => storage
+				.range(
+					iter_state.end.as_deref(),
+					Some(&iter_state.next_record.0),
+					iter_state.direction.into(),
+				)
+				.next(),
+		});
+		if let Some(next_record) = next_record {
+			Some(std::mem::replace(&mut iter_state.next_record, next_record))
+		} else {
+			Some(iter_states.remove(&iter).unwrap().next_record)
 		}
-		IteratorDirection::Descending => storage
-			.range(
-				iter_state.end.as_deref(),
-				Some(&iter_state.next_record.0),
-				iter_state.direction.into(),
-			)
-			.next(),
-	};
-	if let Some(next_record) = next_record {
-		Some(std::mem::replace(&mut iter_state.next_record, next_record))
-	} else {
-		Some(iter_states.remove(&iter).unwrap().next_record)
-	}
+	})
 }
 #[cfg(not(target_arch = "wasm32"))]
 pub fn storage_iter_next_key(iter: StorageIterId) -> Option<Vec<u8>> {
@@ -280,3 +433,226 @@ impl Storage for GlobalStorage {
 		storage_remove(key)
 	}
 }
+
+/// Implements cosmwasm_std::Storage over the keys of the global storage which start with `prefix`, with the prefix
+/// removed. This allows libraries which take a `&dyn Storage` to operate on a namespace of the contract's state
+/// without being able to see or overwrite anything outside of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixedGlobalStorage {
+	prefix: Vec<u8>,
+}
+impl PrefixedGlobalStorage {
+	pub fn new(prefix: impl Into<Vec<u8>>) -> Self {
+		Self { prefix: prefix.into() }
+	}
+	pub fn prefix(&self) -> &[u8] {
+		&self.prefix
+	}
+	fn prefixed_key(&self, key: &[u8]) -> Vec<u8> {
+		concat_byte_array_pairs(&self.prefix, key)
+	}
+	/// Returns the start and end of the range in the global storage.
+	fn prefixed_range(&self, start: Option<&[u8]>, end: Option<&[u8]>) -> (Vec<u8>, Option<Vec<u8>>) {
+		let start = self.prefixed_key(start.unwrap_or_default());
+		let end = match end {
+			Some(end) => Some(self.prefixed_key(end)),
+			// The first key which doesn't start with the prefix. Trailing 0xff bytes are removed first, as e.g. [1, 255, 0]
+			// would come after [2] otherwise. If nothing is left, the range goes to the end of the storage.
+			None => {
+				let trimmed_len = self
+					.prefix
+					.iter()
+					.rposition(|byte| *byte != u8::MAX)
+					.map_or(0, |index| index + 1);
+				(trimmed_len > 0).then(|| lexicographic_next(&self.prefix[..trimmed_len]))
+			}
+		};
+		(start, end)
+	}
+}
+impl Storage for PrefixedGlobalStorage {
+	fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+		storage_read(&self.prefixed_key(key))
+	}
+	fn range<'a>(
+		&'a self,
+		start: Option<&[u8]>,
+		end: Option<&[u8]>,
+		order: cosmwasm_std::Order,
+	) -> Box<dyn Iterator<Item = cosmwasm_std::Record> + 'a> {
+		let (start, end) = self.prefixed_range(start, end);
+		let prefix_len = self.prefix.len();
+		Box::new(
+			GlobalStoragePairIter::new(Some(&start), end.as_deref(), order)
+				.map(move |(key, value)| (key[prefix_len..].to_vec(), value)),
+		)
+	}
+	fn range_keys<'a>(
+		&'a self,
+		start: Option<&[u8]>,
+		end: Option<&[u8]>,
+		order: cosmwasm_std::Order,
+	) -> Box<dyn Iterator<Item = Vec<u8>> + 'a> {
+		let (start, end) = self.prefixed_range(start, end);
+		let prefix_len = self.prefix.len();
+		Box::new(
+			GlobalStorageIter::new(Some(&start), end.as_deref(), order, false)
+				.map(move |key| key[prefix_len..].to_vec()),
+		)
+	}
+	fn range_values<'a>(
+		&'a self,
+		start: Option<&[u8]>,
+		end: Option<&[u8]>,
+		order: cosmwasm_std::Order,
+	) -> Box<dyn Iterator<Item = Vec<u8>> + 'a> {
+		let (start, end) = self.prefixed_range(start, end);
+		Box::new(GlobalStorageIter::new(Some(&start), end.as_deref(), order, true))
+	}
+	fn set(&mut self, key: &[u8], value: &[u8]) {
+		storage_write(&self.prefixed_key(key), value)
+	}
+	fn remove(&mut self, key: &[u8]) {
+		storage_remove(&self.prefixed_key(key))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Barrier;
+
+	use cosmwasm_std::{Order, StdResult};
+	use cw_storage_plus::Map;
+
+	use super::*;
+	use crate::storage::{dual_map::cw_storage_plus_map_prefix, testing_common::*, StoragePairIterator};
+
+	#[test]
+	fn isolated_threads_do_not_share_storage() {
+		let barrier = Barrier::new(2);
+		std::thread::scope(|scope| {
+			for thread_byte in [1u8, 2] {
+				let barrier = &barrier;
+				scope.spawn(move || {
+					let _storage = isolated().unwrap();
+					let mut iter = None;
+					for round in 0..50u8 {
+						storage_write(b"shared", &[thread_byte, round]);
+						storage_write(&[b'k', round], &[thread_byte]);
+						if round == 0 {
+							iter = Some(StoragePairIterator::new(None, None));
+							barrier.wait();
+						}
+						assert_eq!(storage_read(b"shared"), Some(vec![thread_byte, round]));
+					}
+					barrier.wait();
+					// Iterators see the entries written since they were created, but only those of their thread
+					assert_eq!(iter.unwrap().count(), 51);
+					assert!(StoragePairIterator::new(Some(b"k".as_slice()), Some(b"l".as_slice()))
+						.all(|(_, value)| value == [thread_byte]));
+					storage_remove(b"shared");
+					assert_eq!(storage_read(b"shared"), None);
+				});
+			}
+		});
+	}
+
+	#[test]
+	fn thread_storage_overrides_global() -> TestingResult {
+		let _storage_lock = init()?;
+		storage_write(b"global", b"1");
+		let outer_iter = StoragePairIterator::new(None, None);
+		with_thread_storage(Box::new(MemoryStorage::new()), || {
+			assert_eq!(storage_read(b"global"), None);
+			storage_write(b"thread", b"2");
+			let _inner = isolated().unwrap();
+			assert_eq!(storage_read(b"thread"), None);
+		});
+		assert_eq!(storage_read(b"global"), Some(b"1".to_vec()));
+		assert_eq!(storage_read(b"thread"), None);
+		// Iterators over the global storage weren't affected
+		assert_eq!(outer_iter.count(), 1);
+		Ok(())
+	}
+
+	#[test]
+	fn prefixed_storage_with_cw_storage_plus() -> TestingResult {
+		let _storage = isolated()?;
+		const BALANCES: Map<&str, u64> = Map::new("balances");
+		storage_write(b"user", b"outside");
+		storage_write(b"user0balances", b"outside");
+		storage_write(b"user2", b"outside");
+		let mut user_storage = PrefixedGlobalStorage::new(format!("user{}", 1));
+		BALANCES.save(&mut user_storage, "alice", &5)?;
+		BALANCES.save(&mut user_storage, "bob", &7)?;
+		BALANCES.update(&mut user_storage, "alice", |balance| {
+			StdResult::Ok(balance.unwrap_or_default() + 1)
+		})?;
+
+		let mut raw_key = concat_byte_array_pairs(b"user1", &cw_storage_plus_map_prefix(b"balances"));
+		raw_key.extend_from_slice(b"alice");
+		assert_eq!(storage_read(&raw_key), Some(b"6".to_vec()));
+		storage_write(&raw_key, b"10");
+		assert_eq!(BALANCES.load(&user_storage, "alice")?, 10);
+
+		assert_eq!(
+			BALANCES
+				.range(&user_storage, None, None, Order::Descending)
+				.collect::<StdResult<Vec<_>>>()?,
+			[("bob".to_string(), 7), ("alice".to_string(), 10)]
+		);
+		BALANCES.remove(&mut user_storage, "bob");
+		assert_eq!(storage_read(b"user2"), Some(b"outside".to_vec()));
+		assert_eq!(StoragePairIterator::new(None, None).count(), 4);
+		Ok(())
+	}
+
+	#[test]
+	fn prefixed_storage_range_stays_within_prefix() -> TestingResult {
+		for prefix in [b"ab".to_vec(), vec![0x01, 0xff], vec![0xff, 0xff], vec![]] {
+			let _storage = isolated()?;
+			for key in [
+				b"".as_slice(),
+				b"a",
+				b"ab",
+				b"ab\x00",
+				b"abc",
+				b"ac",
+				b"\x01\xff\x01",
+				b"\x02",
+				b"\xff\xff\x02",
+			] {
+				storage_write(key, &[key, b"!"].concat());
+			}
+			let storage = PrefixedGlobalStorage::new(prefix.clone());
+			let expected = StoragePairIterator::new(None, None)
+				.filter(|(key, _)| key.starts_with(&prefix))
+				.map(|(key, value)| (key[prefix.len()..].to_vec(), value))
+				.collect::<Vec<_>>();
+			assert_eq!(
+				storage.range(None, None, Order::Ascending).collect::<Vec<_>>(),
+				expected
+			);
+			assert_eq!(
+				storage.range_keys(None, None, Order::Descending).collect::<Vec<_>>(),
+				expected.iter().rev().map(|(key, _)| key.clone()).collect::<Vec<_>>()
+			);
+			assert_eq!(
+				storage.range_values(None, None, Order::Ascending).collect::<Vec<_>>(),
+				expected.iter().map(|(_, value)| value.clone()).collect::<Vec<_>>()
+			);
+			let bounded = storage
+				.range_keys(Some(b"\x00"), Some(b"c"), Order::Ascending)
+				.collect::<Vec<_>>();
+			assert!(bounded
+				.iter()
+				.all(|key| key.as_slice() >= b"\x00".as_slice() && key.as_slice() < b"c".as_slice()));
+			if prefix == b"ab" {
+				assert_eq!(expected.len(), 3);
+				assert_eq!(bounded, [b"\x00".to_vec()]);
+				assert_eq!(storage.get(b"c"), Some(b"abc!".to_vec()));
+			}
+		}
+		Ok(())
+	}
+}
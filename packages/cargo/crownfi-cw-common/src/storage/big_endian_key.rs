@@ -0,0 +1,168 @@
+use std::{
+	io::{Read, Result as IoResult, Write},
+	ops::Deref,
+};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use cosmwasm_std::StdError;
+
+use super::SerializableItem;
+
+/// A map key wrapper for integers which is stored big-endian, with the sign bit flipped for signed types, so that the
+/// storage order of the keys is their numeric order.
+///
+/// The integers themselves are stored in native little-endian so that they can be read without copying, which means
+/// that e.g. a `StoredMap<u32, V>` iterates over 256 before 1. Use a `StoredMap<BigEndianKey<u32>, V>` where the
+/// order matters, such as for order books or time-indexed data. This also applies to tuple keys, as the wrapper
+/// encodes itself the same way with borsh.
+///
+/// [StoredVec](super::vec::StoredVec) and [StoredVecDeque](super::queue::StoredVecDeque) don't need this, as their
+/// indices are never iterated over in storage order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BigEndianKey<T>(pub T);
+
+impl<T> BigEndianKey<T> {
+	#[inline]
+	pub fn into_inner(self) -> T {
+		self.0
+	}
+}
+impl<T> Deref for BigEndianKey<T> {
+	type Target = T;
+	#[inline]
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+impl<T> From<T> for BigEndianKey<T> {
+	#[inline]
+	fn from(value: T) -> Self {
+		Self(value)
+	}
+}
+
+macro_rules! impl_big_endian_key {
+	( $int_type:ty, $flip:expr ) => {
+		impl From<BigEndianKey<$int_type>> for $int_type {
+			#[inline]
+			fn from(value: BigEndianKey<$int_type>) -> Self {
+				value.0
+			}
+		}
+		impl BigEndianKey<$int_type> {
+			const SIZE: usize = std::mem::size_of::<$int_type>();
+			fn to_key_bytes(self) -> [u8; Self::SIZE] {
+				let mut bytes = self.0.to_be_bytes();
+				bytes[0] ^= $flip;
+				bytes
+			}
+			fn from_key_bytes(mut bytes: [u8; Self::SIZE]) -> Self {
+				bytes[0] ^= $flip;
+				Self(<$int_type>::from_be_bytes(bytes))
+			}
+		}
+		impl SerializableItem for BigEndianKey<$int_type> {
+			fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+				Ok(self.to_key_bytes().into())
+			}
+			fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> {
+				let bytes = data
+					.try_into()
+					.map_err(|_| StdError::invalid_data_size(Self::SIZE, data.len()))?;
+				Ok(Self::from_key_bytes(bytes))
+			}
+		}
+		impl BorshSerialize for BigEndianKey<$int_type> {
+			fn serialize<W: Write>(&self, writer: &mut W) -> IoResult<()> {
+				writer.write_all(&self.to_key_bytes())
+			}
+		}
+		impl BorshDeserialize for BigEndianKey<$int_type> {
+			fn deserialize_reader<R: Read>(reader: &mut R) -> IoResult<Self> {
+				let mut bytes = [0u8; Self::SIZE];
+				reader.read_exact(&mut bytes)?;
+				Ok(Self::from_key_bytes(bytes))
+			}
+		}
+	};
+}
+
+impl_big_endian_key!(u8, 0);
+impl_big_endian_key!(u16, 0);
+impl_big_endian_key!(u32, 0);
+impl_big_endian_key!(u64, 0);
+impl_big_endian_key!(u128, 0);
+impl_big_endian_key!(i8, 0x80);
+impl_big_endian_key!(i16, 0x80);
+impl_big_endian_key!(i32, 0x80);
+impl_big_endian_key!(i64, 0x80);
+impl_big_endian_key!(i128, 0x80);
+
+#[cfg(test)]
+mod tests {
+	use std::fmt::Debug;
+
+	use super::*;
+	use crate::storage::{map::StoredMap, testing_common::*};
+
+	fn assert_storage_order<T>(mut keys: Vec<T>) -> TestingResult
+	where
+		T: Copy + Ord + Debug,
+		BigEndianKey<T>: SerializableItem + BorshSerialize + BorshDeserialize,
+	{
+		let _storage = isolated()?;
+		let map = StoredMap::<BigEndianKey<T>, u8>::new(NAMESPACE);
+		let tuple_map = StoredMap::<(BigEndianKey<T>, u8), u8>::new(b"tuples");
+		for key in keys.iter() {
+			map.set(&(*key).into(), &0)?;
+			tuple_map.set(&((*key).into(), 1), &0)?;
+			tuple_map.set(&((*key).into(), 0), &0)?;
+		}
+		keys.sort();
+		assert_eq!(map.iter_keys()?.map(BigEndianKey::into_inner).collect::<Vec<_>>(), keys);
+		assert_eq!(
+			map.iter_keys()?.rev().map(BigEndianKey::into_inner).collect::<Vec<_>>(),
+			keys.iter().rev().copied().collect::<Vec<_>>()
+		);
+		assert_eq!(
+			tuple_map
+				.iter_keys()?
+				.map(|(key, _)| key.into_inner())
+				.collect::<Vec<_>>(),
+			keys.iter().flat_map(|key| [*key, *key]).collect::<Vec<_>>()
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn unsigned_storage_order() -> TestingResult {
+		assert_storage_order((0..=u8::MAX).rev().collect())?;
+		assert_storage_order(vec![256u16, 1, u16::MAX, 0, 255])?;
+		assert_storage_order(vec![256u32, 1, u32::MAX, 0, 255, 1 << 24])?;
+		assert_storage_order(vec![256u64, 1, u64::MAX, 0, 255, 1 << 32])?;
+		assert_storage_order(vec![256u128, 1, u128::MAX, 0, 255, 1 << 64])
+	}
+
+	#[test]
+	fn signed_storage_order() -> TestingResult {
+		assert_storage_order((i8::MIN..=i8::MAX).rev().collect())?;
+		assert_storage_order(vec![256i16, -1, i16::MAX, 0, -256, i16::MIN, 1])?;
+		assert_storage_order(vec![256i32, -1, i32::MAX, 0, -256, i32::MIN, 1])?;
+		assert_storage_order(vec![256i64, -1, i64::MAX, 0, -256, i64::MIN, 1])?;
+		assert_storage_order(vec![256i128, -1, i128::MAX, 0, -256, i128::MIN, 1])
+	}
+
+	#[test]
+	fn round_trips() {
+		for value in [i64::MIN, -1, 0, 1, i64::MAX] {
+			let key = BigEndianKey::from(value);
+			let bytes = key.serialize_to_owned().unwrap();
+			assert_eq!(bytes, borsh::to_vec(&key).unwrap());
+			assert_eq!(BigEndianKey::<i64>::deserialize_to_owned(&bytes).unwrap(), key);
+			assert_eq!(i64::from(key), value);
+		}
+		assert_eq!(BigEndianKey(1u32).serialize_to_owned().unwrap(), [0, 0, 0, 1]);
+		assert_eq!(BigEndianKey(-1i16).serialize_to_owned().unwrap(), [0x7f, 0xff]);
+		assert!(BigEndianKey::<u32>::deserialize_to_owned(&[0; 3]).is_err());
+	}
+}
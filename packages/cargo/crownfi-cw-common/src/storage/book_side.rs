@@ -0,0 +1,275 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{StdError, StdResult};
+
+use super::{
+	base::{storage_read_item, storage_write_item},
+	concat_byte_array_pairs,
+	map::{StoredMap, StoredMapIter},
+	IteratorDirection, OZeroCopy, SerializableItem,
+};
+
+/// Key tag of the order count of each price level
+const LEVELS_TAG: u8 = 0;
+/// Key tag of the orders, keyed by price and sequence
+const ORDERS_TAG: u8 = 1;
+/// Key of the next sequence number
+const SEQUENCE_TAG: u8 = 2;
+
+/// Identifies an order in a [BookSide].
+#[cw_serde]
+#[derive(Copy, Eq, PartialOrd, Ord, Hash)]
+pub struct OrderRef {
+	pub price: u64,
+	/// Orders within a price level are filled in ascending sequence order. Sequences are never reused within a book,
+	/// so a stale ref can't refer to a newer order.
+	pub sequence: u64,
+}
+
+/// One side of an orderbook, i.e. the bids or the asks: price levels with a FIFO queue of orders each.
+///
+/// Everything is stored under a single namespace, the level counts, orders and sequence counter each have their own
+/// tag byte following it. Prices and sequences are stored big-endian, so that levels and the orders within them are
+/// in numeric order. Whether the lowest or highest price is the best one is up to the caller, e.g. ascending for asks
+/// and descending for bids.
+///
+/// Partial fills are done by reading the order with [peek_front](Self::peek_front) and then either replacing it with
+/// [set_order](Self::set_order) or removing it with [pop_front](Self::pop_front).
+pub struct BookSide<O: SerializableItem> {
+	levels: StoredMap<(u8, [u8; 8]), u32>,
+	orders: StoredMap<(u8, [u8; 8], [u8; 8]), O>,
+	sequence_key: Vec<u8>,
+}
+
+impl<O: SerializableItem> BookSide<O> {
+	pub fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			levels: StoredMap::new(namespace),
+			orders: StoredMap::new(namespace),
+			sequence_key: concat_byte_array_pairs(namespace, &[SEQUENCE_TAG]),
+		}
+	}
+
+	fn order_key(order_ref: &OrderRef) -> (u8, [u8; 8], [u8; 8]) {
+		(
+			ORDERS_TAG,
+			order_ref.price.to_be_bytes(),
+			order_ref.sequence.to_be_bytes(),
+		)
+	}
+
+	fn order_entry(((_, price, sequence), order): ((u8, [u8; 8], [u8; 8]), OZeroCopy<O>)) -> (OrderRef, OZeroCopy<O>) {
+		let order_ref = OrderRef {
+			price: u64::from_be_bytes(price),
+			sequence: u64::from_be_bytes(sequence),
+		};
+		(order_ref, order)
+	}
+
+	/// Returns the amount of orders at the price level.
+	pub fn level_len(&self, price: u64) -> StdResult<u32> {
+		Ok(self
+			.levels
+			.get(&(LEVELS_TAG, price.to_be_bytes()))?
+			.map_or(0, OZeroCopy::into_inner))
+	}
+
+	fn set_level_len(&self, price: u64, len: u32) -> StdResult<()> {
+		if len == 0 {
+			self.levels.remove(&(LEVELS_TAG, price.to_be_bytes()));
+			return Ok(());
+		}
+		self.levels.set(&(LEVELS_TAG, price.to_be_bytes()), &len)
+	}
+
+	/// Adds the order to the back of its price level's queue.
+	pub fn insert_order(&mut self, price: u64, order: &O) -> StdResult<OrderRef> {
+		let sequence = storage_read_item::<u64>(&self.sequence_key)?.map_or(0, OZeroCopy::into_inner);
+		storage_write_item(&self.sequence_key, &(sequence + 1))?;
+		let order_ref = OrderRef { price, sequence };
+		self.orders.set(&Self::order_key(&order_ref), order)?;
+		self.set_level_len(price, self.level_len(price)? + 1)?;
+		Ok(order_ref)
+	}
+
+	/// Returns the lowest (ascending) or highest (descending) price which has orders.
+	pub fn best_level(&self, direction: IteratorDirection) -> StdResult<Option<u64>> {
+		let level = match direction {
+			IteratorDirection::Ascending => self.levels.first_in_prefix(&LEVELS_TAG)?,
+			IteratorDirection::Descending => self.levels.last_in_prefix(&LEVELS_TAG)?,
+		};
+		Ok(level.map(|((_, price), _)| u64::from_be_bytes(price)))
+	}
+
+	/// Iterates over the prices which have orders and their amount of orders, best first.
+	pub fn iter_levels(&self, direction: IteratorDirection) -> StdResult<BookLevelIter> {
+		Ok(BookLevelIter {
+			inner: StoredMapIter::new(self.levels.namespace(), LEVELS_TAG, None, None)?,
+			direction,
+		})
+	}
+
+	pub fn get_order(&self, order_ref: &OrderRef) -> StdResult<Option<OZeroCopy<O>>> {
+		self.orders.get(&Self::order_key(order_ref))
+	}
+
+	/// Replaces an existing order without changing its place in the queue, e.g. after it was partially filled.
+	pub fn set_order(&self, order_ref: &OrderRef, order: &O) -> StdResult<()> {
+		let key = Self::order_key(order_ref);
+		if !self.orders.has(&key) {
+			return Err(StdError::not_found("BookSide order"));
+		}
+		self.orders.set(&key, order)
+	}
+
+	/// Returns the oldest order at the price level.
+	pub fn peek_front(&self, price: u64) -> StdResult<Option<(OrderRef, OZeroCopy<O>)>> {
+		Ok(self
+			.orders
+			.first_in_prefix(&(ORDERS_TAG, price.to_be_bytes()))?
+			.map(Self::order_entry))
+	}
+
+	/// Removes and returns the oldest order at the price level, the level is removed along with its last order.
+	pub fn pop_front(&mut self, price: u64) -> StdResult<Option<(OrderRef, OZeroCopy<O>)>> {
+		let Some((order_ref, order)) = self.peek_front(price)? else {
+			return Ok(None);
+		};
+		self.orders.remove(&Self::order_key(&order_ref));
+		self.set_level_len(price, self.level_len(price)?.saturating_sub(1))?;
+		Ok(Some((order_ref, order)))
+	}
+
+	/// Removes the order from anywhere in its level's queue, returning it if it existed.
+	pub fn cancel(&mut self, order_ref: &OrderRef) -> StdResult<Option<OZeroCopy<O>>> {
+		let key = Self::order_key(order_ref);
+		let Some(order) = self.orders.get(&key)? else {
+			return Ok(None);
+		};
+		self.orders.remove(&key);
+		self.set_level_len(order_ref.price, self.level_len(order_ref.price)?.saturating_sub(1))?;
+		Ok(Some(order))
+	}
+}
+
+/// Iterates over the price levels of a [BookSide] and their amount of orders, see [BookSide::iter_levels].
+pub struct BookLevelIter {
+	inner: StoredMapIter<[u8; 8], u32>,
+	direction: IteratorDirection,
+}
+impl Iterator for BookLevelIter {
+	type Item = (u64, u32);
+	fn next(&mut self) -> Option<Self::Item> {
+		let (price, len) = match self.direction {
+			IteratorDirection::Ascending => self.inner.next()?,
+			IteratorDirection::Descending => self.inner.next_back()?,
+		};
+		Some((u64::from_be_bytes(price), *len))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	/// (owner id, amount)
+	type Order = (u8, u128);
+
+	fn asks() -> StdResult<(BookSide<Order>, Vec<OrderRef>)> {
+		let mut book = BookSide::new(NAMESPACE);
+		let mut refs = Vec::new();
+		for (price, order) in [
+			(105, (1, 10)),
+			(100, (2, 5)),
+			(105, (3, 20)),
+			(300, (4, 1)),
+			(100, (5, 7)),
+		] {
+			refs.push(book.insert_order(price, &order)?);
+		}
+		Ok((book, refs))
+	}
+
+	#[test]
+	fn fill_through_levels() -> TestingResult {
+		let _storage_lock = init()?;
+		let (mut book, _) = asks()?;
+		assert_eq!(book.best_level(IteratorDirection::Ascending)?, Some(100));
+		assert_eq!(book.best_level(IteratorDirection::Descending)?, Some(300));
+		assert_eq!(
+			book.iter_levels(IteratorDirection::Descending)?.collect::<Vec<_>>(),
+			[(300, 1), (105, 2), (100, 2)]
+		);
+
+		// Market buy for 30, best price first and FIFO within each level
+		let mut remaining = 30;
+		let mut fills = Vec::new();
+		while remaining > 0 {
+			let Some(price) = book.best_level(IteratorDirection::Ascending)? else {
+				break;
+			};
+			let (order_ref, order) = book.peek_front(price)?.unwrap();
+			let (owner, amount) = order.into_inner();
+			let filled = amount.min(remaining);
+			remaining -= filled;
+			fills.push((price, owner, filled));
+			if filled == amount {
+				book.pop_front(price)?;
+			} else {
+				book.set_order(&order_ref, &(owner, amount - filled))?;
+			}
+		}
+		assert_eq!(fills, [(100, 2, 5), (100, 5, 7), (105, 1, 10), (105, 3, 8)]);
+		assert_eq!(
+			book.peek_front(105)?.map(|(_, order)| order.into_inner()),
+			Some((3, 12))
+		);
+		assert_eq!(
+			book.iter_levels(IteratorDirection::Ascending)?.collect::<Vec<_>>(),
+			[(105, 1), (300, 1)]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn cancel_within_level() -> TestingResult {
+		let _storage_lock = init()?;
+		let (mut book, refs) = asks()?;
+		let middle = book.insert_order(105, &(6, 2))?;
+		book.insert_order(105, &(7, 3))?;
+		assert_eq!(book.level_len(105)?, 4);
+
+		assert_eq!(book.cancel(&middle)?.map(OZeroCopy::into_inner), Some((6, 2)));
+		assert_eq!(book.cancel(&middle)?, None);
+		assert!(book.set_order(&middle, &(6, 1)).is_err());
+		assert_eq!(book.level_len(105)?, 3);
+		let owners: Vec<u8> = std::iter::from_fn(|| book.pop_front(105).unwrap())
+			.map(|(_, order)| order.into_inner().0)
+			.collect();
+		assert_eq!(owners, [1, 3, 7]);
+		// Cancelling the only order removes the level
+		book.cancel(&refs[3])?;
+		assert_eq!(book.level_len(300)?, 0);
+		assert_eq!(book.best_level(IteratorDirection::Descending)?, Some(100));
+		Ok(())
+	}
+
+	#[test]
+	fn levels_are_cleaned_up() -> TestingResult {
+		let _storage_lock = init()?;
+		let (mut book, refs) = asks()?;
+		for price in [100, 105, 300] {
+			while book.pop_front(price)?.is_some() {}
+			assert_eq!(book.level_len(price)?, 0);
+		}
+		assert_eq!(book.pop_front(100)?, None);
+		assert_eq!(book.best_level(IteratorDirection::Ascending)?, None);
+		assert_eq!(book.best_level(IteratorDirection::Descending)?, None);
+		assert_eq!(book.iter_levels(IteratorDirection::Ascending)?.next(), None);
+
+		// Only the sequence counter is left, and sequences aren't reused
+		let order_ref = book.insert_order(100, &(1, 1))?;
+		assert!(refs.iter().all(|old_ref| old_ref.sequence < order_ref.sequence));
+		Ok(())
+	}
+}
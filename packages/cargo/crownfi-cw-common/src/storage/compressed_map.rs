@@ -0,0 +1,254 @@
+use std::{marker::PhantomData, rc::Rc};
+
+use cosmwasm_std::{StdError, StdResult};
+use miniz_oxide::{deflate::compress_to_vec, inflate::decompress_to_vec_with_limit};
+
+use crate::utils::lexicographic_next;
+
+use super::{map::StoredMap, OZeroCopy, SerializableItem, StoragePairIterator};
+
+const HEADER_RAW: u8 = 0;
+const HEADER_DEFLATE: u8 = 1;
+
+/// Values smaller than this aren't worth the gas spent trying to compress them.
+const MIN_COMPRESSIBLE_LEN: usize = 64;
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// The default upper bound of a decompressed value's size.
+pub const DEFAULT_MAX_DECOMPRESSED_LEN: usize = 1024 * 1024;
+
+/// A [StoredMap] which deflate-compresses its values, intended for large blobs such as serialized route tables and
+/// metadata.
+///
+/// Each value is prefixed with a 1-byte header, `0` for raw and `1` for compressed. Values are only stored compressed
+/// if that actually makes them smaller, so small Pod values only ever cost the extra header byte.
+///
+/// Decompression is bounded by [with_max_decompressed_len](Self::with_max_decompressed_len), so corrupted state can't
+/// be used as a decompression bomb.
+pub struct CompressedMap<K: SerializableItem, V: SerializableItem> {
+	map: StoredMap<K, V>,
+	namespace: &'static [u8],
+	max_decompressed_len: usize,
+}
+
+impl<K: SerializableItem, V: SerializableItem> CompressedMap<K, V> {
+	pub fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			map: StoredMap::new(namespace),
+			namespace,
+			max_decompressed_len: DEFAULT_MAX_DECOMPRESSED_LEN,
+		}
+	}
+
+	/// Sets the upper bound of a decompressed value's size, reading a value which exceeds it results in an error.
+	pub fn with_max_decompressed_len(mut self, max_decompressed_len: usize) -> Self {
+		self.max_decompressed_len = max_decompressed_len;
+		self
+	}
+
+	#[inline]
+	pub fn key(&self, key: &K) -> Vec<u8> {
+		self.map.key(key)
+	}
+
+	pub fn get(&self, key: &K) -> StdResult<Option<OZeroCopy<V>>> {
+		self.map
+			.get_raw_bytes(key)
+			.map(|bytes| decode_value(bytes, self.max_decompressed_len))
+			.transpose()
+			.map_err(|err| self.map.with_storage_context(err, key))
+	}
+
+	pub fn has(&self, key: &K) -> bool {
+		self.map.has(key)
+	}
+
+	pub fn set(&self, key: &K, value: &V) -> StdResult<()> {
+		let bytes = match value.serialize_as_ref() {
+			Some(value_bytes) => encode_value(value_bytes),
+			None => encode_value(&value.serialize_to_owned()?),
+		};
+		self.map.set_raw_bytes(key, &bytes)?;
+		Ok(())
+	}
+
+	pub fn remove(&self, key: &K) {
+		self.map.remove(key)
+	}
+
+	/// Returns an iterator which iterates over all key/value pairs of the map, decompressing the values.
+	///
+	/// Like [StoredMap::iter], it iterates in an ascending order and `.rev()` can be used to switch to descending order.
+	pub fn iter(&self) -> CompressedMapIter<K, V> {
+		CompressedMapIter {
+			inner_iter: StoragePairIterator::new(Some(self.namespace), Some(&lexicographic_next(self.namespace))),
+			key_slicing: self.namespace.len(),
+			max_decompressed_len: self.max_decompressed_len,
+			key_type: PhantomData,
+			value_type: PhantomData,
+		}
+	}
+}
+
+fn encode_value(value_bytes: &[u8]) -> Vec<u8> {
+	if value_bytes.len() >= MIN_COMPRESSIBLE_LEN {
+		let compressed = compress_to_vec(value_bytes, COMPRESSION_LEVEL);
+		if compressed.len() < value_bytes.len() {
+			let mut bytes = Vec::with_capacity(compressed.len() + 1);
+			bytes.push(HEADER_DEFLATE);
+			bytes.extend_from_slice(&compressed);
+			return bytes;
+		}
+	}
+	let mut bytes = Vec::with_capacity(value_bytes.len() + 1);
+	bytes.push(HEADER_RAW);
+	bytes.extend_from_slice(value_bytes);
+	bytes
+}
+
+fn decode_value<V: SerializableItem>(mut bytes: Vec<u8>, max_decompressed_len: usize) -> StdResult<OZeroCopy<V>> {
+	match bytes.first() {
+		Some(&HEADER_RAW) => {
+			bytes.remove(0);
+			OZeroCopy::new(bytes)
+		}
+		Some(&HEADER_DEFLATE) => {
+			let decompressed = decompress_to_vec_with_limit(&bytes[1..], max_decompressed_len).map_err(|err| {
+				StdError::parse_err("CompressedMap value", format!("decompression failed: {:?}", err.status))
+			})?;
+			OZeroCopy::new(decompressed)
+		}
+		Some(header) => Err(StdError::parse_err(
+			"CompressedMap value",
+			format!("unknown compression header {header}"),
+		)),
+		None => Err(StdError::parse_err(
+			"CompressedMap value",
+			"value is missing its header",
+		)),
+	}
+}
+
+/// Iterates over a [CompressedMap]. Like [StoredMapIter](super::map::StoredMapIter), iteration stops at the first
+/// entry which can't be read.
+pub struct CompressedMapIter<K: SerializableItem, V: SerializableItem> {
+	inner_iter: StoragePairIterator,
+	key_slicing: usize,
+	max_decompressed_len: usize,
+	key_type: PhantomData<K>,
+	value_type: PhantomData<V>,
+}
+
+impl<K: SerializableItem, V: SerializableItem> CompressedMapIter<K, V> {
+	fn parse_pair(&self, (key_bytes, value_bytes): (Rc<[u8]>, Vec<u8>)) -> Option<(K, OZeroCopy<V>)> {
+		Some((
+			K::deserialize_to_owned(&key_bytes[self.key_slicing..]).ok()?,
+			decode_value(value_bytes, self.max_decompressed_len).ok()?,
+		))
+	}
+}
+impl<K: SerializableItem, V: SerializableItem> Iterator for CompressedMapIter<K, V> {
+	type Item = (K, OZeroCopy<V>);
+	fn next(&mut self) -> Option<Self::Item> {
+		let pair = self.inner_iter.next()?;
+		self.parse_pair(pair)
+	}
+}
+impl<K: SerializableItem, V: SerializableItem> DoubleEndedIterator for CompressedMapIter<K, V> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		let pair = self.inner_iter.next_back()?;
+		self.parse_pair(pair)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::{base::storage_read, testing_common::*};
+
+	fn route_table(len: usize) -> String {
+		(0..len)
+			.map(|i| format!("{{\"pool\":\"sei1pool{}\",\"fee_bps\":30}}", i % 10))
+			.collect::<Vec<_>>()
+			.join(",")
+	}
+
+	#[test]
+	fn small_values_stay_raw() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = CompressedMap::<u32, u64>::new(NAMESPACE);
+		map.set(&1, &1337)?;
+		assert_eq!(
+			storage_read(&map.key(&1)).unwrap(),
+			[&[HEADER_RAW][..], &1337u64.to_le_bytes()].concat()
+		);
+		assert_eq!(map.get(&1)?.map(OZeroCopy::into_inner), Some(1337));
+		Ok(())
+	}
+
+	#[test]
+	fn incompressible_values_stay_raw() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = CompressedMap::<u32, Vec<u8>>::new(NAMESPACE);
+		// xorshift noise doesn't compress
+		let mut state = 0x2545f491u32;
+		let noise: Vec<u8> = (0..512)
+			.map(|_| {
+				state ^= state << 13;
+				state ^= state >> 17;
+				state ^= state << 5;
+				state as u8
+			})
+			.collect();
+		map.set(&1, &noise)?;
+		let stored = storage_read(&map.key(&1)).unwrap();
+		assert_eq!(stored[0], HEADER_RAW);
+		assert_eq!(stored.len(), noise.serialize_to_owned()?.len() + 1);
+		assert_eq!(map.get(&1)?.map(OZeroCopy::into_inner), Some(noise));
+		Ok(())
+	}
+
+	#[test]
+	fn large_values_compress() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = CompressedMap::<u32, String>::new(NAMESPACE);
+		let value = route_table(100);
+		map.set(&1, &value)?;
+		let stored = storage_read(&map.key(&1)).unwrap();
+		assert_eq!(stored[0], HEADER_DEFLATE);
+		assert!(stored.len() * 3 < value.len());
+		assert_eq!(map.get(&1)?.map(OZeroCopy::into_inner), Some(value));
+		Ok(())
+	}
+
+	#[test]
+	fn iterator_decompresses() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = CompressedMap::<u32, String>::new(NAMESPACE);
+		map.set(&1, &route_table(50))?;
+		map.set(&2, &"tiny".to_string())?;
+		map.set(&3, &route_table(70))?;
+		assert_eq!(
+			map.iter()
+				.map(|(key, value)| (key, value.into_inner()))
+				.collect::<Vec<_>>(),
+			vec![(1, route_table(50)), (2, "tiny".to_string()), (3, route_table(70))]
+		);
+		assert_eq!(map.iter().next_back().map(|(key, _)| key), Some(3));
+		Ok(())
+	}
+
+	#[test]
+	fn decompression_bomb() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = CompressedMap::<u32, Vec<u8>>::new(NAMESPACE);
+		map.set(&1, &vec![0u8; 100_000])?;
+		assert!(storage_read(&map.key(&1)).unwrap().len() < 1000);
+		assert!(map.get(&1)?.is_some());
+
+		let map = map.with_max_decompressed_len(10_000);
+		assert!(map.get(&1).is_err());
+		assert_eq!(map.iter().count(), 0);
+		Ok(())
+	}
+}
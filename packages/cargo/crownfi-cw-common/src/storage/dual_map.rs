@@ -0,0 +1,322 @@
+use cosmwasm_std::{from_json, to_json_vec, StdResult};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::utils::lexicographic_next;
+
+use super::{
+	base::{storage_read, storage_remove, storage_write},
+	concat_byte_array_pairs,
+	map::StoredMap,
+	OZeroCopy, SerializableItem, StoragePairIterator,
+};
+
+/// Reconciliation progress of every DualMap lives under this prefix.
+pub const DUAL_MAP_STATE_NAMESPACE: &[u8] = b"\xffdualmap/";
+
+const RECONCILE_IN_PROGRESS: u8 = 0;
+const RECONCILE_DONE: u8 = 1;
+
+/// Returns the raw key prefix cw-storage-plus uses for all entries of a `Map` with the specified namespace.
+pub fn cw_storage_plus_map_prefix(namespace: &[u8]) -> Vec<u8> {
+	let namespace_len: u16 = namespace
+		.len()
+		.try_into()
+		.expect("cw-storage-plus namespaces can't be longer than u16::MAX");
+	concat_byte_array_pairs(&namespace_len.to_be_bytes(), namespace)
+}
+
+/// A map which keeps a legacy cw-storage-plus `Map` and a [StoredMap] in sync, intended to be used during the
+/// transition between the two.
+///
+/// * Writes and removals go to both layouts.
+/// * Reads prefer the new layout and fall back to the legacy one. With
+///   [backfill_on_read](DualMap::backfill_on_read), any value found in the legacy layout is copied to the new one.
+/// * [reconcile](DualMap::reconcile) copies the entries which only exist in the legacy layout in bounded batches. Once
+///   it has reported completion, reads no longer touch the legacy layout.
+///
+/// Legacy values are JSON encoded like cw-storage-plus does. Since the legacy key encoding depends on the key type,
+/// the raw key bytes following the cw-storage-plus namespace prefix are provided by the `legacy_key` and
+/// `legacy_key_parse` functions. For `String` keys, these are simply the string's bytes.
+pub struct DualMap<K: SerializableItem, V: SerializableItem + Serialize + DeserializeOwned> {
+	map: StoredMap<K, V>,
+	state_key: Vec<u8>,
+	legacy_prefix: Vec<u8>,
+	legacy_key: fn(&K) -> Vec<u8>,
+	legacy_key_parse: fn(&[u8]) -> StdResult<K>,
+	backfill_on_read: bool,
+}
+
+impl<K: SerializableItem, V: SerializableItem + Serialize + DeserializeOwned> DualMap<K, V> {
+	pub fn new(
+		namespace: &'static [u8],
+		legacy_namespace: &[u8],
+		legacy_key: fn(&K) -> Vec<u8>,
+		legacy_key_parse: fn(&[u8]) -> StdResult<K>,
+	) -> Self {
+		Self {
+			map: StoredMap::new(namespace),
+			state_key: concat_byte_array_pairs(DUAL_MAP_STATE_NAMESPACE, namespace),
+			legacy_prefix: cw_storage_plus_map_prefix(legacy_namespace),
+			legacy_key,
+			legacy_key_parse,
+			backfill_on_read: false,
+		}
+	}
+
+	/// Copies values found in the legacy layout to the new one when they're read.
+	///
+	/// Don't enable this for maps which are read during queries, as storage is read-only there.
+	pub fn backfill_on_read(mut self) -> Self {
+		self.backfill_on_read = true;
+		self
+	}
+
+	#[inline]
+	pub fn key(&self, key: &K) -> Vec<u8> {
+		self.map.key(key)
+	}
+
+	#[inline]
+	pub fn legacy_key(&self, key: &K) -> Vec<u8> {
+		concat_byte_array_pairs(&self.legacy_prefix, &(self.legacy_key)(key))
+	}
+
+	/// The new layout, for use once the legacy one is no longer needed.
+	#[inline]
+	pub fn new_layout(&self) -> &StoredMap<K, V> {
+		&self.map
+	}
+
+	/// Returns true once [reconcile](Self::reconcile) has copied every legacy entry.
+	pub fn is_reconciled(&self) -> bool {
+		storage_read(&self.state_key).is_some_and(|state| state.first() == Some(&RECONCILE_DONE))
+	}
+
+	pub fn get(&self, key: &K) -> StdResult<Option<OZeroCopy<V>>> {
+		if let Some(value) = self.map.get(key)? {
+			return Ok(Some(value));
+		}
+		if self.is_reconciled() {
+			return Ok(None);
+		}
+		let Some(legacy_bytes) = storage_read(&self.legacy_key(key)) else {
+			return Ok(None);
+		};
+		let value: V = from_json(legacy_bytes)?;
+		if self.backfill_on_read {
+			self.map.set(key, &value)?;
+		}
+		Ok(Some(OZeroCopy::from_inner(value)))
+	}
+
+	pub fn has(&self, key: &K) -> bool {
+		self.map.has(key) || (!self.is_reconciled() && storage_read(&self.legacy_key(key)).is_some())
+	}
+
+	pub fn set(&self, key: &K, value: &V) -> StdResult<()> {
+		self.map.set(key, value)?;
+		storage_write(&self.legacy_key(key), &to_json_vec(value)?);
+		Ok(())
+	}
+
+	pub fn remove(&self, key: &K) {
+		self.map.remove(key);
+		storage_remove(&self.legacy_key(key));
+	}
+
+	/// Copies up to `limit` legacy entries which don't exist in the new layout yet, continuing where the last call left
+	/// off.
+	///
+	/// Returns the amount of entries copied, and whether or not all legacy entries have been reconciled.
+	pub fn reconcile(&self, limit: u32) -> StdResult<(u32, bool)> {
+		let start_key = match storage_read(&self.state_key) {
+			Some(state) if state.first() == Some(&RECONCILE_DONE) => return Ok((0, true)),
+			Some(state) => {
+				let mut start_key = state[1..].to_vec();
+				// Start after the last processed key
+				start_key.push(0);
+				start_key
+			}
+			None => self.legacy_prefix.clone(),
+		};
+		let end_key = lexicographic_next(&self.legacy_prefix);
+		let mut legacy_entries = StoragePairIterator::new(Some(&start_key), Some(&end_key));
+
+		let mut copied = 0;
+		let mut scanned = 0;
+		let mut last_legacy_key = None;
+		while scanned < limit {
+			let Some((legacy_key, legacy_value)) = legacy_entries.next() else {
+				storage_write(&self.state_key, &[RECONCILE_DONE]);
+				return Ok((copied, true));
+			};
+			scanned += 1;
+			let key = (self.legacy_key_parse)(&legacy_key[self.legacy_prefix.len()..])?;
+			if !self.map.has(&key) {
+				self.map.set(&key, &from_json::<V>(&legacy_value)?)?;
+				copied += 1;
+			}
+			last_legacy_key = Some(legacy_key);
+		}
+		// The limit may have been reached right at the last entry
+		if legacy_entries.next().is_none() {
+			storage_write(&self.state_key, &[RECONCILE_DONE]);
+			return Ok((copied, true));
+		}
+		if let Some(last_legacy_key) = last_legacy_key {
+			storage_write(
+				&self.state_key,
+				&concat_byte_array_pairs(&[RECONCILE_IN_PROGRESS], &last_legacy_key),
+			);
+		}
+		Ok((copied, false))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	};
+
+	use cosmwasm_std::{MemoryStorage, Order, Record, StdError, Storage};
+
+	use super::*;
+	use crate::storage::{base::set_global_storage, testing_common::*};
+
+	const LEGACY_NAMESPACE: &[u8] = b"balances";
+
+	/// Counts the reads which touch the legacy layout
+	struct LegacyReadCountingStorage {
+		inner: MemoryStorage,
+		legacy_reads: Arc<AtomicUsize>,
+	}
+	impl Storage for LegacyReadCountingStorage {
+		fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+			if key.starts_with(&cw_storage_plus_map_prefix(LEGACY_NAMESPACE)) {
+				self.legacy_reads.fetch_add(1, Ordering::SeqCst);
+			}
+			self.inner.get(key)
+		}
+		fn range<'a>(
+			&'a self,
+			start: Option<&[u8]>,
+			end: Option<&[u8]>,
+			order: Order,
+		) -> Box<dyn Iterator<Item = Record> + 'a> {
+			self.inner.range(start, end, order)
+		}
+		fn set(&mut self, key: &[u8], value: &[u8]) {
+			self.inner.set(key, value)
+		}
+		fn remove(&mut self, key: &[u8]) {
+			self.inner.remove(key)
+		}
+	}
+
+	fn balances() -> DualMap<String, u128> {
+		DualMap::new(
+			NAMESPACE,
+			LEGACY_NAMESPACE,
+			|key| key.as_bytes().to_vec(),
+			|bytes| String::from_utf8(bytes.to_vec()).map_err(StdError::from),
+		)
+	}
+
+	fn write_legacy(key: &str, value: u128) -> StdResult<()> {
+		storage_write(
+			&concat_byte_array_pairs(&cw_storage_plus_map_prefix(LEGACY_NAMESPACE), key.as_bytes()),
+			&to_json_vec(&value)?,
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn cw_storage_plus_prefix() {
+		assert_eq!(cw_storage_plus_map_prefix(b"balances"), b"\x00\x08balances");
+	}
+
+	#[test]
+	fn read_fallback() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = balances();
+		write_legacy("alice", 100)?;
+
+		assert_eq!(map.get(&"alice".to_string())?.map(OZeroCopy::into_inner), Some(100));
+		assert!(map.has(&"alice".to_string()));
+		// Not back-filled by default
+		assert!(!map.new_layout().has(&"alice".to_string()));
+		assert_eq!(map.get(&"bob".to_string())?, None);
+
+		map.set(&"bob".to_string(), &5)?;
+		assert_eq!(
+			storage_read(&map.legacy_key(&"bob".to_string())),
+			Some(b"\"5\"".to_vec())
+		);
+		assert_eq!(
+			map.new_layout().get(&"bob".to_string())?.map(OZeroCopy::into_inner),
+			Some(5)
+		);
+
+		map.remove(&"alice".to_string());
+		assert_eq!(map.get(&"alice".to_string())?, None);
+		Ok(())
+	}
+
+	#[test]
+	fn read_backfill() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = balances().backfill_on_read();
+		write_legacy("alice", 100)?;
+
+		assert_eq!(map.get(&"alice".to_string())?.map(OZeroCopy::into_inner), Some(100));
+		assert_eq!(
+			map.new_layout().get(&"alice".to_string())?.map(OZeroCopy::into_inner),
+			Some(100)
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn reconcile_converges() -> TestingResult {
+		let _storage_lock = init()?;
+		let legacy_reads = Arc::new(AtomicUsize::new(0));
+		set_global_storage(Box::new(LegacyReadCountingStorage {
+			inner: MemoryStorage::new(),
+			legacy_reads: legacy_reads.clone(),
+		}));
+		let map = balances();
+		for i in 0..5 {
+			write_legacy(&format!("user{i}"), i)?;
+		}
+		// Already in the new layout with a newer value, must not be overwritten.
+		map.set(&"user1".to_string(), &1000)?;
+
+		assert_eq!(map.reconcile(2)?, (1, false));
+		assert_eq!(map.reconcile(2)?, (2, false));
+		assert_eq!(map.reconcile(2)?, (1, true));
+		assert!(map.is_reconciled());
+		assert_eq!(map.reconcile(2)?, (0, true));
+
+		legacy_reads.store(0, Ordering::SeqCst);
+		for i in 0..5 {
+			let expected = if i == 1 { 1000 } else { i };
+			assert_eq!(map.get(&format!("user{i}"))?.map(OZeroCopy::into_inner), Some(expected));
+		}
+		assert_eq!(map.get(&"user9".to_string())?, None);
+		assert!(!map.has(&"user9".to_string()));
+		assert_eq!(legacy_reads.load(Ordering::SeqCst), 0);
+		Ok(())
+	}
+
+	#[test]
+	fn reconcile_exact_limit() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = balances();
+		write_legacy("alice", 1)?;
+		write_legacy("bob", 2)?;
+		assert_eq!(map.reconcile(2)?, (2, true));
+		Ok(())
+	}
+}
@@ -0,0 +1,267 @@
+use std::marker::PhantomData;
+
+use cosmwasm_std::{StdError, StdResult, Timestamp};
+use sha2::{Digest, Sha256};
+
+use super::{
+	base::{storage_read, storage_remove, storage_write, with_storage_context},
+	OZeroCopy, SerializableItem, StoragePairIterator,
+};
+
+const ENTRY_PREFIX: u8 = 0;
+const INDEX_PREFIX: u8 = 1;
+const EXPIRY_PREFIX_LEN: usize = 8;
+
+fn millis(time: Timestamp) -> u64 {
+	time.nanos() / 1_000_000
+}
+
+/// A map whose entries expire after a time-to-live, intended for ephemeral data such as pending quotes.
+///
+/// Expired entries are treated as missing but aren't deleted when read, use [purge_expired](Self::purge_expired) to
+/// remove them. To make that cheap, a time-ordered index of the entries is maintained, so purging never has to look at
+/// entries which haven't expired yet.
+///
+/// Entries are stored under `namespace ++ 0 ++ key` as `expiry in milliseconds (u64 LE) || value bytes`, so Pod values
+/// are still zero-copy readable. The index is stored under `namespace ++ 1 ++ expiry (u64 BE) ++ sha256(key)`, with the
+/// key as its value.
+pub struct ExpiringMap<K: SerializableItem, V: SerializableItem> {
+	namespace: &'static [u8],
+	key_type: PhantomData<K>,
+	value_type: PhantomData<V>,
+}
+
+impl<K: SerializableItem, V: SerializableItem> ExpiringMap<K, V> {
+	pub const fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			namespace,
+			key_type: PhantomData,
+			value_type: PhantomData,
+		}
+	}
+
+	fn key_bytes(key: &K) -> StdResult<Vec<u8>> {
+		match key.serialize_as_ref() {
+			Some(key_bytes) => Ok(key_bytes.to_vec()),
+			None => key.serialize_to_owned(),
+		}
+	}
+
+	fn entry_key(&self, key_bytes: &[u8]) -> Vec<u8> {
+		let mut entry_key = Vec::with_capacity(self.namespace.len() + 1 + key_bytes.len());
+		entry_key.extend_from_slice(self.namespace);
+		entry_key.push(ENTRY_PREFIX);
+		entry_key.extend_from_slice(key_bytes);
+		entry_key
+	}
+
+	fn index_prefix(&self) -> Vec<u8> {
+		let mut index_prefix = Vec::with_capacity(self.namespace.len() + 1);
+		index_prefix.extend_from_slice(self.namespace);
+		index_prefix.push(INDEX_PREFIX);
+		index_prefix
+	}
+
+	fn index_key(&self, expiry: u64, key_bytes: &[u8]) -> Vec<u8> {
+		let mut index_key = self.index_prefix();
+		index_key.extend_from_slice(&expiry.to_be_bytes());
+		index_key.extend_from_slice(&Sha256::digest(key_bytes));
+		index_key
+	}
+
+	fn read_entry(&self, key_bytes: &[u8]) -> StdResult<Option<(u64, Vec<u8>)>> {
+		let Some(mut bytes) = storage_read(&self.entry_key(key_bytes)) else {
+			return Ok(None);
+		};
+		let expiry = read_expiry(&bytes).map_err(|err| with_storage_context(err, self.namespace, key_bytes))?;
+		bytes.drain(..EXPIRY_PREFIX_LEN);
+		Ok(Some((expiry, bytes)))
+	}
+
+	/// Returns the value along with its expiry time, if it hasn't expired at `now`.
+	pub fn get_with_expiry(&self, key: &K, now: Timestamp) -> StdResult<Option<(Timestamp, OZeroCopy<V>)>> {
+		let key_bytes = Self::key_bytes(key)?;
+		let Some((expiry, bytes)) = self.read_entry(&key_bytes)? else {
+			return Ok(None);
+		};
+		if expiry <= millis(now) {
+			return Ok(None);
+		}
+		let value = OZeroCopy::new(bytes).map_err(|err| with_storage_context(err, self.namespace, &key_bytes))?;
+		Ok(Some((Timestamp::from_nanos(expiry * 1_000_000), value)))
+	}
+
+	/// Returns the value if it hasn't expired at `now`. Expired entries aren't removed.
+	pub fn get(&self, key: &K, now: Timestamp) -> StdResult<Option<OZeroCopy<V>>> {
+		Ok(self.get_with_expiry(key, now)?.map(|(_, value)| value))
+	}
+
+	pub fn has(&self, key: &K, now: Timestamp) -> StdResult<bool> {
+		let Some((expiry, _)) = self.read_entry(&Self::key_bytes(key)?)? else {
+			return Ok(false);
+		};
+		Ok(expiry > millis(now))
+	}
+
+	/// Sets the value, which expires `ttl_ms` milliseconds after `now`. This replaces any existing entry along with its
+	/// expiry time.
+	pub fn set_with_ttl(&self, key: &K, value: &V, now: Timestamp, ttl_ms: u64) -> StdResult<()> {
+		let key_bytes = Self::key_bytes(key)?;
+		let expiry = millis(now)
+			.checked_add(ttl_ms)
+			.ok_or_else(|| StdError::generic_err("ExpiringMap: expiry time overflowed"))?;
+		if let Some((old_expiry, _)) = self.read_entry(&key_bytes)? {
+			storage_remove(&self.index_key(old_expiry, &key_bytes));
+		}
+		let mut bytes;
+		if let Some(value_bytes) = value.serialize_as_ref() {
+			bytes = Vec::with_capacity(EXPIRY_PREFIX_LEN + value_bytes.len());
+			bytes.extend_from_slice(&expiry.to_le_bytes());
+			bytes.extend_from_slice(value_bytes);
+		} else {
+			bytes = value.serialize_to_owned()?;
+			bytes.splice(0..0, expiry.to_le_bytes());
+		}
+		storage_write(&self.entry_key(&key_bytes), &bytes);
+		storage_write(&self.index_key(expiry, &key_bytes), &key_bytes);
+		Ok(())
+	}
+
+	/// Removes the entry regardless of whether or not it has expired.
+	pub fn remove(&self, key: &K) -> StdResult<()> {
+		let key_bytes = Self::key_bytes(key)?;
+		if let Some((expiry, _)) = self.read_entry(&key_bytes)? {
+			storage_remove(&self.index_key(expiry, &key_bytes));
+			storage_remove(&self.entry_key(&key_bytes));
+		}
+		Ok(())
+	}
+
+	/// Removes the entry regardless of whether or not it has expired, returning it along with its expiry time.
+	pub fn take(&self, key: &K) -> StdResult<Option<(Timestamp, OZeroCopy<V>)>> {
+		let key_bytes = Self::key_bytes(key)?;
+		let Some((expiry, bytes)) = self.read_entry(&key_bytes)? else {
+			return Ok(None);
+		};
+		let value = OZeroCopy::new(bytes).map_err(|err| with_storage_context(err, self.namespace, &key_bytes))?;
+		storage_remove(&self.index_key(expiry, &key_bytes));
+		storage_remove(&self.entry_key(&key_bytes));
+		Ok(Some((Timestamp::from_nanos(expiry * 1_000_000), value)))
+	}
+
+	/// Removes up to `limit` entries which have expired at `now`, oldest first.
+	///
+	/// Returns the amount of entries removed, and whether or not there are more expired entries left to remove.
+	pub fn purge_expired(&self, now: Timestamp, limit: u32) -> StdResult<(u32, bool)> {
+		let index_prefix = self.index_prefix();
+		let mut index_end = index_prefix.clone();
+		// Entries expiring at exactly `now` have expired
+		index_end.extend_from_slice(&millis(now).saturating_add(1).to_be_bytes());
+		let mut iter = StoragePairIterator::new(Some(&index_prefix), Some(&index_end));
+		let mut removed = 0;
+		while removed < limit {
+			let Some((index_key, key_bytes)) = iter.next() else {
+				return Ok((removed, false));
+			};
+			storage_remove(&index_key);
+			storage_remove(&self.entry_key(&key_bytes));
+			removed += 1;
+		}
+		Ok((removed, iter.next().is_some()))
+	}
+}
+
+fn read_expiry(bytes: &[u8]) -> StdResult<u64> {
+	let Some(expiry) = bytes.first_chunk::<EXPIRY_PREFIX_LEN>() else {
+		return Err(StdError::parse_err("ExpiringMap", "value is missing its expiry prefix"));
+	};
+	Ok(u64::from_le_bytes(*expiry))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	const MINUTE: u64 = 60_000;
+
+	fn at(seconds: u64) -> Timestamp {
+		Timestamp::from_seconds(seconds)
+	}
+
+	fn quotes() -> ExpiringMap<String, u64> {
+		ExpiringMap::new(NAMESPACE)
+	}
+
+	fn index_len(map: &ExpiringMap<String, u64>) -> usize {
+		let index_prefix = map.index_prefix();
+		StoragePairIterator::new(
+			Some(&index_prefix),
+			Some(&crate::utils::lexicographic_next(&index_prefix)),
+		)
+		.count()
+	}
+
+	#[test]
+	fn expired_reads() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = quotes();
+		let key = "quote".to_string();
+		map.set_with_ttl(&key, &100, at(1000), 5 * MINUTE)?;
+		assert_eq!(map.get(&key, at(1299))?.map(OZeroCopy::into_inner), Some(100));
+		assert_eq!(
+			map.get_with_expiry(&key, at(1000))?.map(|(expiry, _)| expiry),
+			Some(at(1300))
+		);
+		assert!(map.has(&key, at(1299))?);
+		assert_eq!(map.get(&key, at(1300))?, None);
+		assert!(!map.has(&key, at(1300))?);
+		// Reading doesn't delete anything
+		assert!(storage_read(&map.entry_key(&key.serialize_to_owned()?)).is_some());
+		Ok(())
+	}
+
+	#[test]
+	fn overwrite_updates_index() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = quotes();
+		let key = "quote".to_string();
+		map.set_with_ttl(&key, &100, at(1000), MINUTE)?;
+		map.set_with_ttl(&key, &200, at(1030), MINUTE)?;
+		assert_eq!(index_len(&map), 1);
+		// The entry is still alive at the old expiry time, so purging doesn't touch it
+		assert_eq!(map.purge_expired(at(1060), 10)?, (0, false));
+		assert_eq!(map.get(&key, at(1060))?.map(OZeroCopy::into_inner), Some(200));
+		assert_eq!(map.purge_expired(at(1090), 10)?, (1, false));
+		assert_eq!(index_len(&map), 0);
+
+		map.set_with_ttl(&key, &300, at(2000), MINUTE)?;
+		map.remove(&key)?;
+		assert_eq!(index_len(&map), 0);
+		Ok(())
+	}
+
+	#[test]
+	fn purge_with_limit() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = quotes();
+		for i in 0..5u64 {
+			map.set_with_ttl(&format!("expiring{i}"), &i, at(1000 + i), MINUTE)?;
+		}
+		map.set_with_ttl(&"alive".to_string(), &1337, at(1000), 10 * MINUTE)?;
+
+		assert_eq!(map.purge_expired(at(1059), 10)?, (0, false));
+		assert_eq!(map.purge_expired(at(1100), 2)?, (2, true));
+		// The oldest ones go first
+		assert!(!map.has(&"expiring1".to_string(), at(0))?);
+		assert!(map.has(&"expiring2".to_string(), at(0))?);
+		assert_eq!(map.purge_expired(at(1100), 3)?, (3, false));
+		assert_eq!(map.purge_expired(at(1100), 3)?, (0, false));
+		assert_eq!(
+			map.get(&"alive".to_string(), at(1599))?.map(OZeroCopy::into_inner),
+			Some(1337)
+		);
+		assert_eq!(index_len(&map), 1);
+		Ok(())
+	}
+}
@@ -0,0 +1,434 @@
+//! A native [Storage] which persists to a file, for long-running local simulations whose state should survive
+//! between runs. Enabled with the `persistent-storage` feature.
+//!
+//! The file is an append-only log of writes and removals, which is replayed into an in-memory index when opened.
+//! Reads and iteration are served from the index, so they're as fast as with a
+//! [MemoryStorage](cosmwasm_std::MemoryStorage), and the file is rewritten with only the live entries once most of its
+//! records have been superseded, see [compact](FileBackedStorage::compact).
+//!
+//! ```no_run
+//! use crownfi_cw_common::storage::{base::set_global_storage, file_backed::FileBackedStorage};
+//!
+//! set_global_storage(Box::new(FileBackedStorage::open("simulation.state").unwrap()));
+//! ```
+use std::{
+	collections::BTreeMap,
+	fs::{self, File, OpenOptions},
+	io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+	ops::Bound,
+	path::{Path, PathBuf},
+};
+
+use cosmwasm_std::{Order, Record, Storage};
+
+const FILE_MAGIC: &[u8; 8] = b"CFSTOR\0\x01";
+const RECORD_SET: u8 = 1;
+const RECORD_REMOVE: u8 = 0;
+/// Compaction happens automatically once there are more superseded records than live entries, and at least this many.
+pub const AUTO_COMPACT_MIN_STALE_RECORDS: u64 = 4096;
+
+/// See the [module docs](self).
+///
+/// As the [Storage] methods can't return errors, failing to write to the file panics. Writes are buffered, use
+/// [flush](Self::flush) to make sure they're on disk. They're also flushed when dropped, though errors are ignored then.
+pub struct FileBackedStorage {
+	path: PathBuf,
+	data: BTreeMap<Vec<u8>, Vec<u8>>,
+	writer: BufWriter<File>,
+	stale_records: u64,
+}
+
+impl FileBackedStorage {
+	/// Opens the storage file at `path`, creating it if it doesn't exist.
+	///
+	/// An incomplete record at the end of the file, e.g. from a process which was killed mid-write, is discarded.
+	pub fn open<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+		let path = path.into();
+		let mut file = OpenOptions::new().read(true).append(true).create(true).open(&path)?;
+		let mut data = BTreeMap::new();
+		let mut stale_records = 0;
+		if file.metadata()?.len() == 0 {
+			file.write_all(FILE_MAGIC)?;
+			file.sync_data()?;
+		} else {
+			let mut reader = BufReader::new(&mut file);
+			let mut magic = [0u8; 8];
+			reader.read_exact(&mut magic)?;
+			if &magic != FILE_MAGIC {
+				return Err(io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!("{} isn't a FileBackedStorage file", path.display()),
+				));
+			}
+			let mut valid_len = FILE_MAGIC.len() as u64;
+			while let Some(LogRecord {
+				encoded_len,
+				key,
+				value,
+			}) = read_record(&mut reader)?
+			{
+				let replaced = match value {
+					Some(value) => data.insert(key, value).is_some(),
+					None => {
+						// The removal record itself is stale as well
+						stale_records += 1;
+						data.remove(&key).is_some()
+					}
+				};
+				if replaced {
+					stale_records += 1;
+				}
+				valid_len += encoded_len;
+			}
+			if valid_len != file.metadata()?.len() {
+				file.set_len(valid_len)?;
+			}
+			file.seek(SeekFrom::End(0))?;
+		}
+		Ok(Self {
+			path,
+			data,
+			writer: BufWriter::new(file),
+			stale_records,
+		})
+	}
+
+	#[inline]
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// The amount of entries.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.data.len()
+	}
+
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.data.is_empty()
+	}
+
+	/// The amount of records in the file which have been superseded by later ones, and would be removed by compacting.
+	#[inline]
+	pub fn stale_records(&self) -> u64 {
+		self.stale_records
+	}
+
+	/// Writes any buffered records to the file, and waits for them to reach the disk.
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.writer.flush()?;
+		self.writer.get_ref().sync_data()
+	}
+
+	/// Rewrites the file with only the current entries.
+	///
+	/// The new file is written next to the old one and then renamed over it, so the file is intact if this fails.
+	pub fn compact(&mut self) -> io::Result<()> {
+		self.writer.flush()?;
+		let mut temp_path = self.path.clone().into_os_string();
+		temp_path.push(".compacting");
+		let temp_path = PathBuf::from(temp_path);
+		{
+			let mut temp_writer = BufWriter::new(File::create(&temp_path)?);
+			temp_writer.write_all(FILE_MAGIC)?;
+			for (key, value) in self.data.iter() {
+				write_record(&mut temp_writer, key, Some(value))?;
+			}
+			temp_writer
+				.into_inner()
+				.map_err(io::IntoInnerError::into_error)?
+				.sync_all()?;
+		}
+		fs::rename(&temp_path, &self.path)?;
+		self.writer = BufWriter::new(OpenOptions::new().append(true).open(&self.path)?);
+		self.stale_records = 0;
+		Ok(())
+	}
+
+	fn append_record(&mut self, key: &[u8], value: Option<&[u8]>) {
+		write_record(&mut self.writer, key, value)
+			.unwrap_or_else(|err| panic!("FileBackedStorage couldn't write to {}: {err}", self.path.display()));
+		if self.stale_records >= AUTO_COMPACT_MIN_STALE_RECORDS && self.stale_records > self.data.len() as u64 {
+			self.compact()
+				.unwrap_or_else(|err| panic!("FileBackedStorage couldn't compact {}: {err}", self.path.display()));
+		}
+	}
+}
+
+impl Drop for FileBackedStorage {
+	fn drop(&mut self) {
+		let _ = self.flush();
+	}
+}
+
+impl Storage for FileBackedStorage {
+	fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+		self.data.get(key).cloned()
+	}
+
+	fn range<'a>(
+		&'a self,
+		start: Option<&[u8]>,
+		end: Option<&[u8]>,
+		order: Order,
+	) -> Box<dyn Iterator<Item = Record> + 'a> {
+		// Same semantics as MemoryStorage, where a start after the end is an empty range rather than a panic.
+		if let (Some(start), Some(end)) = (start, end) {
+			if start > end {
+				return Box::new(std::iter::empty());
+			}
+		}
+		let bounds = (
+			start.map_or(Bound::Unbounded, |start| Bound::Included(start.to_vec())),
+			end.map_or(Bound::Unbounded, |end| Bound::Excluded(end.to_vec())),
+		);
+		let iter = self.data.range(bounds).map(|(key, value)| (key.clone(), value.clone()));
+		match order {
+			Order::Ascending => Box::new(iter),
+			Order::Descending => Box::new(iter.rev()),
+		}
+	}
+
+	fn set(&mut self, key: &[u8], value: &[u8]) {
+		// Same as MemoryStorage, as an empty value couldn't be told apart from a removal on wasm.
+		assert!(
+			!value.is_empty(),
+			"Value must not be empty in Storage::set, use Storage::remove instead"
+		);
+		if self.data.insert(key.to_vec(), value.to_vec()).is_some() {
+			self.stale_records += 1;
+		}
+		self.append_record(key, Some(value));
+	}
+
+	fn remove(&mut self, key: &[u8]) {
+		if self.data.remove(key).is_none() {
+			return;
+		}
+		self.stale_records += 2;
+		self.append_record(key, None);
+	}
+}
+
+/// `[RECORD_SET][key len: u32 LE][key][value len: u32 LE][value]` or `[RECORD_REMOVE][key len: u32 LE][key]`
+fn write_record(writer: &mut impl Write, key: &[u8], value: Option<&[u8]>) -> io::Result<()> {
+	let len_prefix = |bytes: &[u8]| {
+		u32::try_from(bytes.len())
+			.map(u32::to_le_bytes)
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "keys and values must be under 4 GiB"))
+	};
+	writer.write_all(&[if value.is_some() { RECORD_SET } else { RECORD_REMOVE }])?;
+	writer.write_all(&len_prefix(key)?)?;
+	writer.write_all(key)?;
+	if let Some(value) = value {
+		writer.write_all(&len_prefix(value)?)?;
+		writer.write_all(value)?;
+	}
+	Ok(())
+}
+
+/// A record as read by [read_record], a `None` value being a removal.
+struct LogRecord {
+	encoded_len: u64,
+	key: Vec<u8>,
+	value: Option<Vec<u8>>,
+}
+
+/// Returns the next record, or `None` if there isn't a complete one left.
+fn read_record(reader: &mut impl Read) -> io::Result<Option<LogRecord>> {
+	fn read_len_prefixed(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+		let mut len = [0u8; 4];
+		if !read_exact_or_eof(reader, &mut len)? {
+			return Ok(None);
+		}
+		let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+		Ok(read_exact_or_eof(reader, &mut bytes)?.then_some(bytes))
+	}
+	let mut kind = [0u8; 1];
+	if !read_exact_or_eof(reader, &mut kind)? {
+		return Ok(None);
+	}
+	let Some(key) = read_len_prefixed(reader)? else {
+		return Ok(None);
+	};
+	let value = match kind[0] {
+		RECORD_SET => match read_len_prefixed(reader)? {
+			Some(value) => Some(value),
+			None => return Ok(None),
+		},
+		RECORD_REMOVE => None,
+		kind => {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("unknown FileBackedStorage record kind {kind}"),
+			))
+		}
+	};
+	Ok(Some(LogRecord {
+		encoded_len: 1 + 4 + key.len() as u64 + value.as_ref().map_or(0, |value| 4 + value.len() as u64),
+		key,
+		value,
+	}))
+}
+
+/// Like [Read::read_exact], but returns false instead of an error if the reader ends first.
+fn read_exact_or_eof(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+	match reader.read_exact(buf) {
+		Ok(()) => Ok(true),
+		Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+		Err(err) => Err(err),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::MemoryStorage;
+
+	use super::*;
+	use crate::storage::{base::ThreadStorageGuard, map::StoredMap, testing_common::TestingResult};
+
+	/// Removes the file when dropped.
+	struct TempPath(PathBuf);
+	impl TempPath {
+		fn new(name: &str) -> Self {
+			let path = std::env::temp_dir().join(format!("crownfi-file-backed-{}-{name}", std::process::id()));
+			let _ = fs::remove_file(&path);
+			Self(path)
+		}
+	}
+	impl Drop for TempPath {
+		fn drop(&mut self) {
+			let _ = fs::remove_file(&self.0);
+		}
+	}
+
+	fn contents(storage: &dyn Storage) -> Vec<Record> {
+		storage.range(None, None, Order::Ascending).collect()
+	}
+
+	#[test]
+	fn survives_reopen() -> io::Result<()> {
+		let path = TempPath::new("reopen");
+		{
+			let mut storage = FileBackedStorage::open(&path.0)?;
+			storage.set(b"alice", b"5");
+			storage.set(b"bob", b"7");
+			storage.set(b"alice", b"6");
+			storage.remove(b"bob");
+			storage.set(b"carol", b"1");
+			// Dropping flushes
+		}
+		let mut storage = FileBackedStorage::open(&path.0)?;
+		assert_eq!(
+			contents(&storage),
+			[(b"alice".to_vec(), b"6".to_vec()), (b"carol".to_vec(), b"1".to_vec())]
+		);
+		// The overwritten alice, and bob's set and removal
+		assert_eq!(storage.stale_records(), 3);
+		storage.set(b"dave", b"2");
+		storage.flush()?;
+		drop(storage);
+
+		// A record cut short by a crash is dropped, and doesn't affect the following writes
+		let full_len = fs::metadata(&path.0)?.len();
+		OpenOptions::new()
+			.append(true)
+			.open(&path.0)?
+			.write_all(&[RECORD_SET, 9, 0])?;
+		let mut storage = FileBackedStorage::open(&path.0)?;
+		assert_eq!(fs::metadata(&path.0)?.len(), full_len);
+		assert_eq!(storage.get(b"dave"), Some(b"2".to_vec()));
+		storage.set(b"erin", b"3");
+		drop(storage);
+		assert_eq!(FileBackedStorage::open(&path.0)?.len(), 4);
+
+		let not_storage = TempPath::new("not_storage");
+		fs::write(&not_storage.0, b"something else")?;
+		assert_eq!(
+			FileBackedStorage::open(&not_storage.0).err().map(|err| err.kind()),
+			Some(io::ErrorKind::InvalidData)
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn range_matches_memory_storage() -> TestingResult {
+		let path = TempPath::new("range");
+		let mut storage = FileBackedStorage::open(&path.0)?;
+		let mut memory_storage = MemoryStorage::new();
+		for key in [&b"b"[..], b"a", b"ab", b"\xff", b"", b"b\x00", b"ba"] {
+			storage.set(key, b"v");
+			memory_storage.set(key, b"v");
+		}
+		let bounds = [
+			(None, None),
+			(Some(&b"ab"[..]), None),
+			(None, Some(&b"b\x00"[..])),
+			(Some(b"a"), Some(b"ba")),
+			(Some(b"b"), Some(b"b")),
+			(Some(b"c"), Some(b"a")),
+		];
+		for (start, end) in bounds {
+			for (order_name, order) in [("ascending", Order::Ascending), ("descending", Order::Descending)] {
+				assert_eq!(
+					storage.range(start, end, order).collect::<Vec<_>>(),
+					memory_storage.range(start, end, order).collect::<Vec<_>>(),
+					"{start:?}..{end:?} {order_name}"
+				);
+			}
+		}
+
+		// And it works as the contract storage
+		let _guard = ThreadStorageGuard::new(Box::new(storage));
+		let map = StoredMap::<u32, u64>::new(b"nums");
+		for i in [3, 1, 2] {
+			map.set(&i, &(i as u64 * 10))?;
+		}
+		assert_eq!(
+			map.iter()?.map(|(key, value)| (key, *value)).collect::<Vec<_>>(),
+			[(1, 10), (2, 20), (3, 30)]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn compaction_preserves_contents() -> io::Result<()> {
+		let path = TempPath::new("compact");
+		let mut storage = FileBackedStorage::open(&path.0)?;
+		for i in 0..100u32 {
+			storage.set(&(i % 10).to_be_bytes(), &i.to_le_bytes());
+		}
+		storage.remove(&3u32.to_be_bytes());
+		let expected = contents(&storage);
+		assert_eq!(storage.stale_records(), 92);
+		storage.flush()?;
+		let uncompacted_len = fs::metadata(&path.0)?.len();
+
+		storage.compact()?;
+		assert_eq!(storage.stale_records(), 0);
+		assert!(fs::metadata(&path.0)?.len() < uncompacted_len / 5);
+		assert_eq!(contents(&storage), expected);
+		// The file is still written to after being replaced
+		storage.set(b"after", b"compaction");
+		drop(storage);
+		let storage = FileBackedStorage::open(&path.0)?;
+		assert_eq!(storage.len(), expected.len() + 1);
+		assert_eq!(storage.get(b"after"), Some(b"compaction".to_vec()));
+
+		// Compacting automatically
+		let mut storage = FileBackedStorage::open(&path.0)?;
+		for i in 0..=AUTO_COMPACT_MIN_STALE_RECORDS {
+			storage.set(b"counter", &i.to_le_bytes());
+		}
+		assert!(storage.stale_records() < AUTO_COMPACT_MIN_STALE_RECORDS);
+		drop(storage);
+		let storage = FileBackedStorage::open(&path.0)?;
+		assert_eq!(
+			storage.get(b"counter"),
+			Some(AUTO_COMPACT_MIN_STALE_RECORDS.to_le_bytes().to_vec())
+		);
+		assert_eq!(storage.len(), expected.len() + 2);
+		Ok(())
+	}
+}
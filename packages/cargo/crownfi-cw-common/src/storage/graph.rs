@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use cosmwasm_std::{StdError, StdResult};
+
+use crate::{
+	data_types::{asset::FungibleAssetKind, canonical_addr::SeiCanonicalAddr},
+	utils::lexicographic_next,
+};
+
+use super::{
+	base::{storage_has, storage_read_item, storage_remove, storage_write},
+	OZeroCopy, SerializableItem, StoragePairIterator,
+};
+
+/// How the nodes of a [StoredGraph] are encoded in its storage keys.
+///
+/// By default this is the [SerializableItem] encoding. Types which have multiple representations of the same thing
+/// should use a canonical form instead, which is why [FungibleAssetKind] nodes are stored as their
+/// [canonical_bytes](FungibleAssetKind::canonical_bytes).
+pub trait GraphNode: SerializableItem + Sized {
+	fn node_bytes(&self) -> StdResult<Vec<u8>> {
+		self.serialize_to_owned()
+	}
+	fn from_node_bytes(bytes: &[u8]) -> StdResult<Self> {
+		Self::deserialize_to_owned(bytes)
+	}
+}
+impl GraphNode for FungibleAssetKind {
+	fn node_bytes(&self) -> StdResult<Vec<u8>> {
+		Ok(self.canonical_bytes())
+	}
+	fn from_node_bytes(bytes: &[u8]) -> StdResult<Self> {
+		Self::from_canonical_bytes(bytes)
+	}
+}
+impl GraphNode for SeiCanonicalAddr {}
+impl GraphNode for String {}
+impl GraphNode for u32 {}
+impl GraphNode for u64 {}
+
+/// Encoded node -> (encoded previous node, encoded edge leading to it), `None` for the starting node
+type PathParents = BTreeMap<Vec<u8>, Option<(Vec<u8>, Vec<u8>)>>;
+
+/// An undirected graph with values on its edges, e.g. which pools connect which tokens for route discovery.
+///
+/// Each edge is stored in both directions as `namespace || len(a) (u16 BE) || a || b`, so that the neighbors of a node
+/// can be iterated over with a single prefix. Both directions are always written and removed together.
+pub struct StoredGraph<N: GraphNode, E: SerializableItem> {
+	namespace: &'static [u8],
+	node_type: std::marker::PhantomData<N>,
+	edge_type: std::marker::PhantomData<E>,
+}
+
+impl<N: GraphNode, E: SerializableItem> StoredGraph<N, E> {
+	pub const fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			namespace,
+			node_type: std::marker::PhantomData,
+			edge_type: std::marker::PhantomData,
+		}
+	}
+
+	/// Returns the key prefix of all edges starting at the node.
+	fn node_prefix(&self, node_bytes: &[u8]) -> StdResult<Vec<u8>> {
+		let len: u16 = node_bytes
+			.len()
+			.try_into()
+			.map_err(|_| StdError::serialize_err("StoredGraph node", "node is longer than u16::MAX bytes"))?;
+		Ok([self.namespace, &len.to_be_bytes(), node_bytes].concat())
+	}
+
+	fn edge_keys(&self, a: &N, b: &N) -> StdResult<(Vec<u8>, Vec<u8>)> {
+		let (a, b) = (a.node_bytes()?, b.node_bytes()?);
+		let a_to_b = [&self.node_prefix(&a)?[..], &b].concat();
+		let b_to_a = [&self.node_prefix(&b)?[..], &a].concat();
+		Ok((a_to_b, b_to_a))
+	}
+
+	/// Adds or replaces the edge between `a` and `b`.
+	pub fn add_edge(&self, a: &N, b: &N, edge: &E) -> StdResult<()> {
+		// Everything which can fail is done before anything is written
+		let (a_to_b, b_to_a) = self.edge_keys(a, b)?;
+		let edge = match edge.serialize_as_ref() {
+			Some(edge) => edge.to_vec(),
+			None => edge.serialize_to_owned()?,
+		};
+		storage_write(&a_to_b, &edge);
+		storage_write(&b_to_a, &edge);
+		Ok(())
+	}
+
+	pub fn remove_edge(&self, a: &N, b: &N) -> StdResult<()> {
+		let (a_to_b, b_to_a) = self.edge_keys(a, b)?;
+		storage_remove(&a_to_b);
+		storage_remove(&b_to_a);
+		Ok(())
+	}
+
+	pub fn has_edge(&self, a: &N, b: &N) -> StdResult<bool> {
+		Ok(storage_has(&self.edge_keys(a, b)?.0))
+	}
+
+	pub fn get_edge(&self, a: &N, b: &N) -> StdResult<Option<OZeroCopy<E>>> {
+		storage_read_item(&self.edge_keys(a, b)?.0)
+	}
+
+	/// Iterates over the nodes connected to `node` and the edges connecting them, in storage key order.
+	pub fn neighbors(&self, node: &N) -> StdResult<impl Iterator<Item = (N, OZeroCopy<E>)>> {
+		Ok(self
+			.neighbors_raw(&node.node_bytes()?)?
+			.filter_map(|(neighbor, edge)| Some((N::from_node_bytes(&neighbor).ok()?, OZeroCopy::new(edge).ok()?))))
+	}
+
+	/// Iterates over the encoded neighbors of the encoded node.
+	fn neighbors_raw(&self, node_bytes: &[u8]) -> StdResult<impl Iterator<Item = (Vec<u8>, Vec<u8>)>> {
+		let prefix = self.node_prefix(node_bytes)?;
+		let prefix_len = prefix.len();
+		Ok(
+			StoragePairIterator::new(Some(&prefix), Some(&lexicographic_next(&prefix)))
+				.map(move |(key, edge)| (key[prefix_len..].to_vec(), edge)),
+		)
+	}
+
+	/// Finds a path with the fewest edges from `from` to `to` with a breadth-first search, returning each node after
+	/// `from` along with the edge leading to it. The path from a node to itself is empty.
+	///
+	/// At most `max_depth` edges are followed, and at most `max_visited` nodes have their neighbors read. If no path is
+	/// found within these limits, `None` is returned, even if a longer path exists. As every visited node is a prefix
+	/// iteration, `max_visited` bounds the gas used.
+	pub fn find_path(&self, from: &N, to: &N, max_depth: u32, max_visited: u32) -> StdResult<Option<Vec<(N, E)>>> {
+		let (from, to) = (from.node_bytes()?, to.node_bytes()?);
+		if from == to {
+			return Ok(Some(Vec::new()));
+		}
+		let mut parents = PathParents::new();
+		parents.insert(from.clone(), None);
+		let mut queue = VecDeque::from([(from, 0u32)]);
+		let mut visited = 0;
+		while let Some((node, depth)) = queue.pop_front() {
+			if depth >= max_depth || visited >= max_visited {
+				continue;
+			}
+			visited += 1;
+			for (neighbor, edge) in self.neighbors_raw(&node)? {
+				if parents.contains_key(&neighbor) {
+					continue;
+				}
+				parents.insert(neighbor.clone(), Some((node.clone(), edge)));
+				if neighbor == to {
+					return self.build_path(&parents, to).map(Some);
+				}
+				queue.push_back((neighbor, depth + 1));
+			}
+		}
+		Ok(None)
+	}
+
+	fn build_path(&self, parents: &PathParents, to: Vec<u8>) -> StdResult<Vec<(N, E)>> {
+		let mut path = Vec::new();
+		let mut node = to;
+		while let Some(Some((previous, edge))) = parents.get(&node) {
+			path.push((N::from_node_bytes(&node)?, E::deserialize_to_owned(edge)?));
+			node = previous.clone();
+		}
+		path.reverse();
+		Ok(path)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::{testing_common::*, StorageKeyIterator};
+
+	/// Pool IDs connecting assets
+	fn graph() -> StoredGraph<FungibleAssetKind, u32> {
+		StoredGraph::new(NAMESPACE)
+	}
+
+	fn native(denom: &str) -> FungibleAssetKind {
+		FungibleAssetKind::Native(denom.to_string())
+	}
+
+	#[test]
+	fn edges_are_bidirectional() -> TestingResult {
+		let _storage = isolated()?;
+		let graph = graph();
+		let (sei, usdc, atom) = (native("usei"), native("uusdc"), native("uatom"));
+		graph.add_edge(&sei, &usdc, &1)?;
+		graph.add_edge(&usdc, &atom, &2)?;
+		// A denom which starts with another one is still a different node
+		graph.add_edge(&native("usei2"), &atom, &3)?;
+		assert!(graph.has_edge(&usdc, &sei)?);
+		assert_eq!(graph.get_edge(&atom, &usdc)?.map(OZeroCopy::into_inner), Some(2));
+		assert!(!graph.has_edge(&sei, &atom)?);
+
+		let neighbors: Vec<_> = graph.neighbors(&usdc)?.map(|(node, edge)| (node, *edge)).collect();
+		assert_eq!(neighbors, [(atom.clone(), 2), (sei.clone(), 1)]);
+		assert_eq!(graph.neighbors(&sei)?.count(), 1);
+
+		graph.remove_edge(&usdc, &sei)?;
+		assert!(!graph.has_edge(&sei, &usdc)?);
+		assert!(!graph.has_edge(&usdc, &sei)?);
+		assert_eq!(graph.neighbors(&sei)?.count(), 0);
+		graph.remove_edge(&atom, &usdc)?;
+		graph.remove_edge(&atom, &native("usei2"))?;
+		assert!(StorageKeyIterator::new(None, None).next().is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn paths_in_diamond() -> TestingResult {
+		let _storage = isolated()?;
+		let graph = graph();
+		// a - b - d - e
+		//  \- c -/
+		let [a, b, c, d, e] = ["a", "b", "c", "d", "e"].map(native);
+		graph.add_edge(&a, &b, &1)?;
+		graph.add_edge(&a, &c, &2)?;
+		graph.add_edge(&b, &d, &3)?;
+		graph.add_edge(&c, &d, &4)?;
+		graph.add_edge(&d, &e, &5)?;
+
+		assert_eq!(graph.find_path(&a, &a, 0, 0)?, Some(vec![]));
+		assert_eq!(
+			graph.find_path(&a, &e, 3, 10)?,
+			Some(vec![(b.clone(), 1), (d.clone(), 3), (e.clone(), 5)])
+		);
+		assert_eq!(
+			graph.find_path(&e, &c, 3, 10)?,
+			Some(vec![(d.clone(), 5), (c.clone(), 4)])
+		);
+		// Too deep
+		assert_eq!(graph.find_path(&a, &e, 2, 10)?, None);
+		// a, b and c need to be visited before d's neighbors are read
+		assert_eq!(graph.find_path(&a, &e, 3, 3)?, None);
+		assert!(graph.find_path(&a, &e, 3, 4)?.is_some());
+
+		graph.remove_edge(&d, &e)?;
+		assert_eq!(graph.find_path(&a, &e, 10, 100)?, None);
+		Ok(())
+	}
+}
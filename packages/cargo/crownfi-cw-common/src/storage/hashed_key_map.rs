@@ -0,0 +1,260 @@
+use std::{marker::PhantomData, rc::Rc};
+
+use cosmwasm_std::{StdError, StdResult};
+use sha2::{Digest, Sha256};
+
+use crate::utils::lexicographic_next;
+
+use super::{
+	base::{storage_read, storage_remove, storage_write, with_storage_context},
+	concat_byte_array_pairs, OZeroCopy, SerializableItem, StoragePairIterator,
+};
+
+const SLOT_PREFIX: u8 = 0;
+const OVERFLOW_PREFIX: u8 = 1;
+const KEY_LEN_SIZE: usize = 4;
+
+fn sha256(bytes: &[u8]) -> [u8; 32] {
+	Sha256::digest(bytes).into()
+}
+
+/// A map which stores its entries under the hash of the key rather than the key itself, intended for maps keyed by
+/// arbitrary user-supplied strings which may be very long.
+///
+/// The original key is stored along with the value, so it can still be recovered when iterating. Since entries are
+/// ordered by hash, range queries aren't supported and iteration order is effectively random.
+///
+/// Entries are stored under `namespace ++ 0 ++ sha256(key)`. In the (astronomically unlikely) event of a hash
+/// collision, the colliding entry is stored in an overflow bucket under `namespace ++ 1 ++ sha256(key) ++ key`.
+pub struct HashedKeyMap<K: SerializableItem, V: SerializableItem> {
+	namespace: &'static [u8],
+	hasher: fn(&[u8]) -> [u8; 32],
+	key_type: PhantomData<K>,
+	value_type: PhantomData<V>,
+}
+
+impl<K: SerializableItem, V: SerializableItem> HashedKeyMap<K, V> {
+	pub const fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			namespace,
+			hasher: sha256,
+			key_type: PhantomData,
+			value_type: PhantomData,
+		}
+	}
+
+	#[cfg(test)]
+	pub(crate) const fn with_hasher(mut self, hasher: fn(&[u8]) -> [u8; 32]) -> Self {
+		self.hasher = hasher;
+		self
+	}
+
+	fn key_bytes(key: &K) -> StdResult<Vec<u8>> {
+		match key.serialize_as_ref() {
+			Some(key_bytes) => Ok(key_bytes.to_vec()),
+			None => key.serialize_to_owned(),
+		}
+	}
+
+	fn slot_key(&self, hash: &[u8; 32]) -> Vec<u8> {
+		let mut slot_key = Vec::with_capacity(self.namespace.len() + 1 + hash.len());
+		slot_key.extend_from_slice(self.namespace);
+		slot_key.push(SLOT_PREFIX);
+		slot_key.extend_from_slice(hash);
+		slot_key
+	}
+
+	fn overflow_prefix(&self, hash: &[u8; 32]) -> Vec<u8> {
+		let mut overflow_prefix = self.slot_key(hash);
+		overflow_prefix[self.namespace.len()] = OVERFLOW_PREFIX;
+		overflow_prefix
+	}
+
+	/// Returns the storage key the value for the specified key is currently stored under, if any.
+	fn find_storage_key(&self, key_bytes: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+		let hash = (self.hasher)(key_bytes);
+		let slot_key = self.slot_key(&hash);
+		let slot_envelope = storage_read(&slot_key)?;
+		if envelope_key(&slot_envelope) == Some(key_bytes) {
+			return Some((slot_key, slot_envelope));
+		}
+		let overflow_key = concat_byte_array_pairs(&self.overflow_prefix(&hash), key_bytes);
+		let overflow_envelope = storage_read(&overflow_key)?;
+		Some((overflow_key, overflow_envelope))
+	}
+
+	pub fn get(&self, key: &K) -> StdResult<Option<OZeroCopy<V>>> {
+		let Some((storage_key, envelope)) = self.find_storage_key(&Self::key_bytes(key)?) else {
+			return Ok(None);
+		};
+		let (_, value) = split_envelope(envelope)
+			.map_err(|err| with_storage_context(err, self.namespace, &storage_key[self.namespace.len()..]))?;
+		Ok(Some(value))
+	}
+
+	pub fn has(&self, key: &K) -> StdResult<bool> {
+		Ok(self.find_storage_key(&Self::key_bytes(key)?).is_some())
+	}
+
+	pub fn set(&self, key: &K, value: &V) -> StdResult<()> {
+		let key_bytes = Self::key_bytes(key)?;
+		let hash = (self.hasher)(&key_bytes);
+		let slot_key = self.slot_key(&hash);
+		let envelope = match value.serialize_as_ref() {
+			Some(value_bytes) => make_envelope(&key_bytes, value_bytes)?,
+			None => make_envelope(&key_bytes, &value.serialize_to_owned()?)?,
+		};
+		match storage_read(&slot_key) {
+			Some(slot_envelope) if envelope_key(&slot_envelope) != Some(key_bytes.as_slice()) => {
+				storage_write(
+					&concat_byte_array_pairs(&self.overflow_prefix(&hash), &key_bytes),
+					&envelope,
+				);
+			}
+			_ => storage_write(&slot_key, &envelope),
+		}
+		Ok(())
+	}
+
+	pub fn remove(&self, key: &K) -> StdResult<()> {
+		let key_bytes = Self::key_bytes(key)?;
+		let hash = (self.hasher)(&key_bytes);
+		let Some((storage_key, _)) = self.find_storage_key(&key_bytes) else {
+			return Ok(());
+		};
+		storage_remove(&storage_key);
+		if storage_key == self.slot_key(&hash) {
+			// Overflow entries are only looked up if the slot is occupied, so one of them takes the removed entry's place.
+			let overflow_prefix = self.overflow_prefix(&hash);
+			if let Some((overflow_key, overflow_envelope)) =
+				StoragePairIterator::new(Some(&overflow_prefix), Some(&lexicographic_next(&overflow_prefix))).next()
+			{
+				storage_remove(&overflow_key);
+				storage_write(&storage_key, &overflow_envelope);
+			}
+		}
+		Ok(())
+	}
+
+	/// Returns an iterator over all key/value pairs of the map, in an unspecified order.
+	pub fn iter(&self) -> HashedKeyMapIter<K, V> {
+		HashedKeyMapIter {
+			inner_iter: StoragePairIterator::new(Some(self.namespace), Some(&lexicographic_next(self.namespace))),
+			key_type: PhantomData,
+			value_type: PhantomData,
+		}
+	}
+}
+
+fn make_envelope(key_bytes: &[u8], value_bytes: &[u8]) -> StdResult<Vec<u8>> {
+	let key_len = u32::try_from(key_bytes.len())
+		.map_err(|_| StdError::serialize_err("HashedKeyMap key", "key is longer than u32::MAX bytes"))?;
+	let mut envelope = Vec::with_capacity(KEY_LEN_SIZE + key_bytes.len() + value_bytes.len());
+	envelope.extend_from_slice(&key_len.to_le_bytes());
+	envelope.extend_from_slice(key_bytes);
+	envelope.extend_from_slice(value_bytes);
+	Ok(envelope)
+}
+
+fn envelope_key(envelope: &[u8]) -> Option<&[u8]> {
+	let (key_len, rest) = envelope.split_first_chunk::<KEY_LEN_SIZE>()?;
+	rest.get(..u32::from_le_bytes(*key_len) as usize)
+}
+
+fn split_envelope<V: SerializableItem>(mut envelope: Vec<u8>) -> StdResult<(Vec<u8>, OZeroCopy<V>)> {
+	let Some(key_bytes) = envelope_key(&envelope).map(|key_bytes| key_bytes.to_vec()) else {
+		return Err(StdError::parse_err(
+			"HashedKeyMap entry",
+			"entry is shorter than its key length",
+		));
+	};
+	envelope.drain(..KEY_LEN_SIZE + key_bytes.len());
+	Ok((key_bytes, OZeroCopy::new(envelope)?))
+}
+
+/// Iterates over a [HashedKeyMap], recovering the original keys. Iteration stops at the first entry which can't be
+/// read.
+pub struct HashedKeyMapIter<K: SerializableItem, V: SerializableItem> {
+	inner_iter: StoragePairIterator,
+	key_type: PhantomData<K>,
+	value_type: PhantomData<V>,
+}
+
+impl<K: SerializableItem, V: SerializableItem> HashedKeyMapIter<K, V> {
+	fn parse_pair(&self, (_, envelope): (Rc<[u8]>, Vec<u8>)) -> Option<(K, OZeroCopy<V>)> {
+		let (key_bytes, value) = split_envelope(envelope).ok()?;
+		Some((K::deserialize_to_owned(&key_bytes).ok()?, value))
+	}
+}
+impl<K: SerializableItem, V: SerializableItem> Iterator for HashedKeyMapIter<K, V> {
+	type Item = (K, OZeroCopy<V>);
+	fn next(&mut self) -> Option<Self::Item> {
+		let pair = self.inner_iter.next()?;
+		self.parse_pair(pair)
+	}
+}
+impl<K: SerializableItem, V: SerializableItem> DoubleEndedIterator for HashedKeyMapIter<K, V> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		let pair = self.inner_iter.next_back()?;
+		self.parse_pair(pair)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	#[test]
+	fn long_keys() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = HashedKeyMap::<String, u64>::new(NAMESPACE);
+		let long_key = "memo ".repeat(1000);
+		map.set(&long_key, &1)?;
+		map.set(&"short".to_string(), &2)?;
+		assert_eq!(map.get(&long_key)?.map(OZeroCopy::into_inner), Some(1));
+		assert!(map.has(&"short".to_string())?);
+		assert!(!map.has(&"missing".to_string())?);
+		// Keys don't grow with the length of the original key
+		assert!(storage_read(&map.slot_key(&sha256(&long_key.serialize_to_owned()?))).is_some());
+
+		map.set(&long_key, &3)?;
+		let mut entries: Vec<(String, u64)> = map.iter().map(|(key, value)| (key, value.into_inner())).collect();
+		entries.sort();
+		assert_eq!(entries, vec![(long_key.clone(), 3), ("short".to_string(), 2)]);
+
+		map.remove(&long_key)?;
+		assert_eq!(map.get(&long_key)?, None);
+		assert_eq!(map.iter().count(), 1);
+		Ok(())
+	}
+
+	#[test]
+	fn forced_collision() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = HashedKeyMap::<String, u64>::new(NAMESPACE).with_hasher(|_| [7; 32]);
+		let [a, b, c] = ["a".to_string(), "b".to_string(), "c".to_string()];
+		map.set(&a, &1)?;
+		map.set(&b, &2)?;
+		map.set(&c, &3)?;
+		// Updating an overflowed entry doesn't create a duplicate
+		map.set(&b, &20)?;
+		assert_eq!(map.get(&a)?.map(OZeroCopy::into_inner), Some(1));
+		assert_eq!(map.get(&b)?.map(OZeroCopy::into_inner), Some(20));
+		assert_eq!(map.get(&c)?.map(OZeroCopy::into_inner), Some(3));
+		assert_eq!(map.get(&"d".to_string())?, None);
+		assert_eq!(map.iter().count(), 3);
+
+		// Removing the slot's entry promotes an overflowed one
+		map.remove(&a)?;
+		assert_eq!(map.get(&a)?, None);
+		assert_eq!(map.get(&b)?.map(OZeroCopy::into_inner), Some(20));
+		assert_eq!(map.get(&c)?.map(OZeroCopy::into_inner), Some(3));
+		assert_eq!(map.iter().count(), 2);
+
+		map.remove(&c)?;
+		map.remove(&b)?;
+		assert_eq!(map.iter().count(), 0);
+		Ok(())
+	}
+}
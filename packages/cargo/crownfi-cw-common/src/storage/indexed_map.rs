@@ -0,0 +1,368 @@
+use std::marker::PhantomData;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use cosmwasm_std::{StdError, StdResult};
+
+use super::{map::StoredMap, OZeroCopy, SerializableItem};
+
+/// The value of multi-index entries. Storage can't hold empty values, and the entries only need to exist.
+const MULTI_INDEX_ENTRY: u8 = 1;
+
+/// A secondary index which a [StoredIndexedMap] keeps up to date, implemented by [MultiIndex] and [UniqueIndex].
+pub trait StoredIndex<K, V> {
+	/// Returns an error if `value` can't be stored under `key`, this is called for every index before anything is
+	/// written.
+	fn check(&self, key: &K, value: &V) -> StdResult<()>;
+	/// Updates the index after the value stored under `key` changed from `old` to `new`, `None` meaning that there
+	/// was no value.
+	fn update(&self, key: &K, old: Option<&V>, new: Option<&V>) -> StdResult<()>;
+}
+
+/// A non-unique secondary index, where any amount of primary entries may have the same index key.
+///
+/// Entries are stored as `(IK, K)` keys in their own namespace, so the primary keys of an index key are ordered by
+/// their borsh encoding.
+pub struct MultiIndex<K, V, IK> {
+	namespace: &'static [u8],
+	extractor: fn(&K, &V) -> IK,
+	value_type: PhantomData<V>,
+}
+impl<K, V, IK> MultiIndex<K, V, IK> {
+	/// `extractor` returns the index key of a primary entry.
+	pub const fn new(namespace: &'static [u8], extractor: fn(&K, &V) -> IK) -> Self {
+		Self {
+			namespace,
+			extractor,
+			value_type: PhantomData,
+		}
+	}
+}
+impl<K, V, IK> MultiIndex<K, V, IK>
+where
+	K: BorshSerialize + BorshDeserialize,
+	IK: BorshSerialize + BorshDeserialize,
+{
+	#[inline]
+	pub fn entries(&self) -> StoredMap<(IK, K), u8> {
+		StoredMap::new(self.namespace)
+	}
+}
+impl<K, V, IK> StoredIndex<K, V> for MultiIndex<K, V, IK>
+where
+	K: BorshSerialize + BorshDeserialize,
+	IK: BorshSerialize + BorshDeserialize + PartialEq,
+{
+	fn check(&self, _key: &K, _value: &V) -> StdResult<()> {
+		Ok(())
+	}
+	fn update(&self, key: &K, old: Option<&V>, new: Option<&V>) -> StdResult<()> {
+		let old_index_key = old.map(|old| (self.extractor)(key, old));
+		let new_index_key = new.map(|new| (self.extractor)(key, new));
+		if old_index_key == new_index_key {
+			return Ok(());
+		}
+		let entries = self.entries();
+		// The entry keys own their components and K isn't necessarily Clone, so it's copied through its encoding
+		let key_bytes = borsh::to_vec(key).map_err(|err| StdError::serialize_err("index key", err))?;
+		let key = || K::try_from_slice(&key_bytes).map_err(|err| StdError::parse_err("index key", err));
+		if let Some(old_index_key) = old_index_key {
+			entries.remove(&(old_index_key, key()?));
+		}
+		if let Some(new_index_key) = new_index_key {
+			entries.set(&(new_index_key, key()?), &MULTI_INDEX_ENTRY)?;
+		}
+		Ok(())
+	}
+}
+
+/// A unique secondary index, where [StoredIndexedMap::set] fails if another primary entry has the same index key.
+///
+/// Entries are stored as `IK => K` in their own namespace.
+pub struct UniqueIndex<K, V, IK> {
+	namespace: &'static [u8],
+	extractor: fn(&K, &V) -> IK,
+	value_type: PhantomData<V>,
+}
+impl<K, V, IK> UniqueIndex<K, V, IK> {
+	/// `extractor` returns the index key of a primary entry.
+	pub const fn new(namespace: &'static [u8], extractor: fn(&K, &V) -> IK) -> Self {
+		Self {
+			namespace,
+			extractor,
+			value_type: PhantomData,
+		}
+	}
+}
+impl<K: SerializableItem, V, IK: SerializableItem> UniqueIndex<K, V, IK> {
+	#[inline]
+	pub fn entries(&self) -> StoredMap<IK, K> {
+		StoredMap::new(self.namespace)
+	}
+}
+impl<K, V, IK> StoredIndex<K, V> for UniqueIndex<K, V, IK>
+where
+	K: SerializableItem + PartialEq,
+	IK: SerializableItem + PartialEq,
+{
+	fn check(&self, key: &K, value: &V) -> StdResult<()> {
+		match self.entries().get(&(self.extractor)(key, value))? {
+			Some(existing_key) if *existing_key != *key => Err(StdError::generic_err(format!(
+				"Unique index {:?} already has an entry with the same index key",
+				String::from_utf8_lossy(self.namespace)
+			))),
+			_ => Ok(()),
+		}
+	}
+	fn update(&self, key: &K, old: Option<&V>, new: Option<&V>) -> StdResult<()> {
+		let old_index_key = old.map(|old| (self.extractor)(key, old));
+		let new_index_key = new.map(|new| (self.extractor)(key, new));
+		if old_index_key == new_index_key {
+			return Ok(());
+		}
+		let entries = self.entries();
+		if let Some(old_index_key) = old_index_key {
+			entries.remove(&old_index_key);
+		}
+		if let Some(new_index_key) = new_index_key {
+			entries.set(&new_index_key, key)?;
+		}
+		Ok(())
+	}
+}
+
+/// A [StoredMap] with secondary indexes, which are kept up to date by `set` and `remove`.
+///
+/// ```ignore
+/// const BY_OWNER: MultiIndex<u64, Order, SeiCanonicalAddr> = MultiIndex::new(b"by_owner", |_, order| order.owner);
+/// const BY_CLIENT_ID: UniqueIndex<u64, Order, String> =
+///     UniqueIndex::new(b"by_client_id", |_, order| order.client_id.clone());
+///
+/// let orders = StoredIndexedMap::new(b"orders").with_index(BY_OWNER).with_index(BY_CLIENT_ID);
+/// orders.set(&id, &order)?;
+/// for entry in orders.iter_by_index(&BY_OWNER, &owner)? {
+///     let (id, order) = entry?;
+/// }
+/// ```
+///
+/// Writes which bypass `set` and `remove`, e.g. through [primary](Self::primary), aren't indexed.
+pub struct StoredIndexedMap<K: SerializableItem, V: SerializableItem> {
+	primary: StoredMap<K, V>,
+	indexes: Vec<Box<dyn StoredIndex<K, V>>>,
+}
+
+impl<K: SerializableItem + 'static, V: SerializableItem + 'static> StoredIndexedMap<K, V> {
+	pub fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			primary: StoredMap::new(namespace),
+			indexes: Vec::new(),
+		}
+	}
+
+	pub fn with_index(mut self, index: impl StoredIndex<K, V> + 'static) -> Self {
+		self.indexes.push(Box::new(index));
+		self
+	}
+
+	#[inline]
+	pub fn primary(&self) -> &StoredMap<K, V> {
+		&self.primary
+	}
+
+	#[inline]
+	pub fn get(&self, key: &K) -> StdResult<Option<OZeroCopy<V>>> {
+		self.primary.get(key)
+	}
+
+	#[inline]
+	pub fn has(&self, key: &K) -> bool {
+		self.primary.has(key)
+	}
+
+	/// Sets the value and updates the indexes. Nothing is written if a [UniqueIndex] already has another entry with
+	/// the value's index key.
+	pub fn set(&self, key: &K, value: &V) -> StdResult<()> {
+		for index in self.indexes.iter() {
+			index.check(key, value)?;
+		}
+		let old_value = self.primary.get(key)?;
+		for index in self.indexes.iter() {
+			index.update(key, old_value.as_deref(), Some(value))?;
+		}
+		self.primary.set(key, value)
+	}
+
+	/// Removes the value and its index entries.
+	pub fn remove(&self, key: &K) -> StdResult<()> {
+		let Some(old_value) = self.primary.get(key)? else {
+			return Ok(());
+		};
+		for index in self.indexes.iter() {
+			index.update(key, Some(&old_value), None)?;
+		}
+		self.primary.remove(key);
+		Ok(())
+	}
+
+	/// Iterates over the primary entries with the specified index key, ordered by the borsh encoding of their primary
+	/// key. This is a double-ended iterator, so `.rev()` can be used for descending order.
+	///
+	/// The encoding of integers is little-endian, so integer primary keys aren't in numeric order (256 comes before
+	/// 1). Use [BigEndianKey](super::big_endian_key::BigEndianKey) primary keys where the order matters.
+	pub fn iter_by_index<'a, IK>(
+		&'a self,
+		index: &MultiIndex<K, V, IK>,
+		index_key: &IK,
+	) -> StdResult<impl DoubleEndedIterator<Item = StdResult<(K, OZeroCopy<V>)>> + 'a>
+	where
+		K: BorshSerialize + BorshDeserialize,
+		IK: BorshSerialize + BorshDeserialize,
+	{
+		Ok(index
+			.entries()
+			.iter_prefix(index_key)?
+			.map(|(key, _)| self.get_indexed(key)))
+	}
+
+	/// Returns the primary entry with the specified index key.
+	pub fn get_by_index<IK: SerializableItem>(
+		&self,
+		index: &UniqueIndex<K, V, IK>,
+		index_key: &IK,
+	) -> StdResult<Option<(K, OZeroCopy<V>)>> {
+		index
+			.entries()
+			.get(index_key)?
+			.map(|key| self.get_indexed(key.into_inner()))
+			.transpose()
+	}
+
+	fn get_indexed(&self, key: K) -> StdResult<(K, OZeroCopy<V>)> {
+		let value = self
+			.primary
+			.get(&key)?
+			.ok_or_else(|| StdError::not_found("Index entry points to a missing primary entry"))?;
+		Ok((key, value))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use borsh::{BorshDeserialize, BorshSerialize};
+
+	use super::*;
+	use crate::{impl_serializable_borsh, storage::testing_common::*};
+
+	#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+	struct Order {
+		owner: String,
+		client_id: String,
+		amount: u64,
+	}
+	impl_serializable_borsh!(Order);
+
+	const BY_OWNER: MultiIndex<u32, Order, String> = MultiIndex::new(b"by_owner", |_, order| order.owner.clone());
+	const BY_CLIENT_ID: UniqueIndex<u32, Order, String> =
+		UniqueIndex::new(b"by_client_id", |_, order| order.client_id.clone());
+
+	fn orders() -> StoredIndexedMap<u32, Order> {
+		StoredIndexedMap::new(NAMESPACE)
+			.with_index(BY_OWNER)
+			.with_index(BY_CLIENT_ID)
+	}
+
+	fn order(owner: &str, client_id: &str, amount: u64) -> Order {
+		Order {
+			owner: owner.to_string(),
+			client_id: client_id.to_string(),
+			amount,
+		}
+	}
+
+	fn ids_by_owner(orders: &StoredIndexedMap<u32, Order>, owner: &str) -> StdResult<Vec<u32>> {
+		orders
+			.iter_by_index(&BY_OWNER, &owner.to_string())?
+			.map(|entry| entry.map(|(id, _)| id))
+			.collect()
+	}
+
+	#[test]
+	fn multi_index() -> TestingResult {
+		let _storage = isolated()?;
+		let orders = orders();
+		orders.set(&3, &order("alice", "a", 30))?;
+		orders.set(&1, &order("alice", "b", 10))?;
+		orders.set(&2, &order("bob", "c", 20))?;
+		// "alicea" isn't within the "alice" prefix
+		orders.set(&4, &order("alicea", "d", 40))?;
+
+		assert_eq!(ids_by_owner(&orders, "alice")?, [1, 3]);
+		assert_eq!(ids_by_owner(&orders, "bob")?, [2]);
+		assert!(ids_by_owner(&orders, "carol")?.is_empty());
+		let descending: Vec<(u32, u64)> = orders
+			.iter_by_index(&BY_OWNER, &"alice".to_string())?
+			.rev()
+			.map(|entry| entry.map(|(id, order)| (id, order.amount)))
+			.collect::<StdResult<_>>()?;
+		assert_eq!(descending, [(3, 30), (1, 10)]);
+
+		// Overwriting moves the entry to its new index key
+		orders.set(&3, &order("bob", "a", 31))?;
+		assert_eq!(ids_by_owner(&orders, "alice")?, [1]);
+		assert_eq!(ids_by_owner(&orders, "bob")?, [2, 3]);
+		// Overwriting with the same index key doesn't duplicate it
+		orders.set(&3, &order("bob", "a", 32))?;
+		assert_eq!(ids_by_owner(&orders, "bob")?, [2, 3]);
+		assert_eq!(BY_OWNER.entries().iter()?.count(), 4);
+
+		orders.remove(&1)?;
+		orders.remove(&1)?;
+		assert!(ids_by_owner(&orders, "alice")?.is_empty());
+		assert_eq!(BY_OWNER.entries().iter()?.count(), 3);
+
+		// Ordered by the little-endian encoding of the ids
+		orders.set(&256, &order("bob", "e", 50))?;
+		assert_eq!(ids_by_owner(&orders, "bob")?, [256, 2, 3]);
+		Ok(())
+	}
+
+	#[test]
+	fn unique_index() -> TestingResult {
+		let _storage = isolated()?;
+		let orders = orders();
+		orders.set(&1, &order("alice", "a", 10))?;
+		orders.set(&2, &order("bob", "b", 20))?;
+		assert_eq!(
+			orders
+				.get_by_index(&BY_CLIENT_ID, &"b".to_string())?
+				.map(|(id, order)| (id, order.into_inner())),
+			Some((2, order("bob", "b", 20)))
+		);
+
+		// Another entry has the same client ID, so nothing is written
+		let err = orders.set(&3, &order("carol", "a", 30)).unwrap_err();
+		assert!(err.to_string().contains("by_client_id"), "{err}");
+		assert!(!orders.has(&3));
+		assert!(ids_by_owner(&orders, "carol")?.is_empty());
+		assert!(orders.set(&2, &order("bob", "a", 20)).is_err());
+		assert_eq!(
+			orders.get(&2)?.map(|order| order.client_id.clone()).as_deref(),
+			Some("b")
+		);
+
+		// The entry itself can keep or change its client ID
+		orders.set(&1, &order("alice", "a", 11))?;
+		orders.set(&1, &order("alice", "z", 12))?;
+		assert_eq!(orders.get_by_index(&BY_CLIENT_ID, &"a".to_string())?, None);
+		assert_eq!(
+			orders.get_by_index(&BY_CLIENT_ID, &"z".to_string())?.map(|(id, _)| id),
+			Some(1)
+		);
+		// Which frees the old one
+		orders.set(&3, &order("carol", "a", 30))?;
+
+		orders.remove(&3)?;
+		assert_eq!(orders.get_by_index(&BY_CLIENT_ID, &"a".to_string())?, None);
+		assert_eq!(BY_CLIENT_ID.entries().iter()?.count(), 2);
+		Ok(())
+	}
+}
@@ -1,4 +1,5 @@
-use super::base::{storage_read_item, storage_remove, storage_write, storage_write_item};
+use super::base::{storage_read_item, storage_remove, storage_write_item, with_storage_context};
+use super::item_cache::ItemCache;
 use super::{OZeroCopy, SerializableItem};
 use cosmwasm_std::{StdError, Storage};
 use std::ops::{Deref, DerefMut};
@@ -35,6 +36,19 @@ pub trait StoredItem: SerializableItem + Sized {
 		storage_read_item(Self::namespace())
 	}
 
+	/// Like [load](Self::load), but only reads from storage once per contract call, see [ItemCache] for details.
+	fn load_cached() -> Result<Option<OZeroCopy<Self>>, StdError>
+	where
+		Self: Sized,
+	{
+		let Some(bytes) = ItemCache::read_through(Self::namespace()) else {
+			return Ok(None);
+		};
+		Ok(Some(
+			OZeroCopy::new(bytes).map_err(|err| with_storage_context(err, Self::namespace(), &[]))?,
+		))
+	}
+
 	#[inline]
 	fn save(&self) -> Result<(), StdError> {
 		storage_write_item(Self::namespace(), self)
@@ -56,15 +70,28 @@ pub trait StoredItem: SerializableItem + Sized {
 	}
 }
 
+/// Writes a modified value back to storage. Values which were read in place were also mutated in place, so types
+/// providing `serialize_as_ref` are written from those same bytes, while anything else is reserialized.
+fn autosave<T: SerializableItem>(namespace: &[u8], value: &OZeroCopy<T>) {
+	storage_write_item(namespace, value.deref()).unwrap_or_else(|err| {
+		panic!(
+			"serialization error on autosave of {:?}: {err}",
+			String::from_utf8_lossy(namespace)
+		)
+	})
+}
+
+/// Writes the item back to storage when dropped, if it was mutably dereferenced.
 pub struct AutosavingStoredItem<T: StoredItem> {
 	value: OZeroCopy<T>,
+	dirty: bool,
 }
 impl<'a, T: StoredItem> AutosavingStoredItem<T> {
 	pub fn new() -> Result<Option<Self>, StdError> {
 		let Some(value) = T::load()? else {
 			return Ok(None);
 		};
-		Ok(Some(Self { value }))
+		Ok(Some(Self { value, dirty: false }))
 	}
 }
 impl<'a, T: StoredItem + Default> AutosavingStoredItem<T> {
@@ -72,9 +99,10 @@ impl<'a, T: StoredItem + Default> AutosavingStoredItem<T> {
 		let Some(value) = T::load()? else {
 			return Ok(Self {
 				value: OZeroCopy::from_inner(T::default()),
+				dirty: false,
 			});
 		};
-		Ok(Self { value })
+		Ok(Self { value, dirty: false })
 	}
 }
 impl<T: StoredItem> Deref for AutosavingStoredItem<T> {
@@ -87,6 +115,7 @@ impl<T: StoredItem> Deref for AutosavingStoredItem<T> {
 impl<T: StoredItem> DerefMut for AutosavingStoredItem<T> {
 	#[inline]
 	fn deref_mut(&mut self) -> &mut Self::Target {
+		self.dirty = true;
 		&mut self.value
 	}
 }
@@ -95,35 +124,37 @@ where
 	T: StoredItem,
 {
 	fn drop(&mut self) {
-		match &self.value.0 {
-			super::OZeroCopyType::Copy(val) => {
-				storage_write_item(T::namespace(), val).expect("serialization error on autosave")
-			}
-			super::OZeroCopyType::ZeroCopy(bytes) => storage_write(T::namespace(), bytes),
+		if !self.dirty {
+			return;
 		}
+		autosave(T::namespace(), &self.value);
 	}
 }
 
+/// Writes the value back to storage when dropped, if it was mutably dereferenced.
 pub struct AutosavingSerializableItem<T: SerializableItem> {
 	value: OZeroCopy<T>,
 	namespace: Vec<u8>,
+	dirty: bool,
 }
 impl<T: SerializableItem> AutosavingSerializableItem<T> {
 	pub fn new(namespace: Vec<u8>) -> Result<Option<Self>, StdError> {
 		let Some(value) = storage_read_item(&namespace)? else { return Ok(None) };
-		Ok(Some(Self { value, namespace }))
+		Ok(Some(Self {
+			value,
+			namespace,
+			dirty: false,
+		}))
 	}
 }
 impl<'a, T: SerializableItem + Default> AutosavingSerializableItem<T> {
 	pub fn new_or_default(namespace: Vec<u8>) -> Result<Self, StdError> {
-		if let Some(value) = storage_read_item(&namespace)? {
-			Ok(Self { value, namespace })
-		} else {
-			Ok(Self {
-				value: OZeroCopy::from_inner(T::default()),
-				namespace,
-			})
-		}
+		let value = storage_read_item(&namespace)?.unwrap_or_else(|| OZeroCopy::from_inner(T::default()));
+		Ok(Self {
+			value,
+			namespace,
+			dirty: false,
+		})
 	}
 }
 impl<T: SerializableItem> Deref for AutosavingSerializableItem<T> {
@@ -136,6 +167,7 @@ impl<T: SerializableItem> Deref for AutosavingSerializableItem<T> {
 impl<T: SerializableItem> DerefMut for AutosavingSerializableItem<T> {
 	#[inline]
 	fn deref_mut(&mut self) -> &mut Self::Target {
+		self.dirty = true;
 		&mut self.value
 	}
 }
@@ -144,22 +176,24 @@ where
 	T: SerializableItem,
 {
 	fn drop(&mut self) {
-		match &self.value.0 {
-			super::OZeroCopyType::Copy(val) => {
-				storage_write_item(&self.namespace, val).expect("serialization error on autosave")
-			}
-			super::OZeroCopyType::ZeroCopy(bytes) => storage_write(&self.namespace, bytes),
+		if !self.dirty {
+			return;
 		}
+		autosave(&self.namespace, &self.value);
 	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::impl_serializable_borsh;
 	use crate::storage::{
-		base::{storage_read, storage_remove},
+		base::{storage_has, storage_read, storage_remove, ThreadStorageGuard},
+		stats::StoredStats,
 		testing_common::*,
 	};
+	use borsh::{BorshDeserialize, BorshSerialize};
+	use std::sync::atomic::Ordering;
 
 	impl StoredItem for u8 {
 		fn namespace() -> &'static [u8] {
@@ -173,6 +207,18 @@ mod tests {
 		}
 	}
 
+	#[derive(Debug, Default, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+	struct Settings {
+		admin: String,
+		paused: bool,
+	}
+	impl_serializable_borsh!(Settings);
+	impl StoredItem for Settings {
+		fn namespace() -> &'static [u8] {
+			b"settings"
+		}
+	}
+
 	#[test]
 	fn autosaving_stored_item() -> TestingResult {
 		let _storage_lock = init()?;
@@ -231,4 +277,100 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn autosave_only_when_modified() -> TestingResult {
+		let storage = CountingStorage::default();
+		let writes = storage.writes.clone();
+		let _storage = ThreadStorageGuard::new(Box::new(storage));
+
+		// An untouched default isn't persisted
+		drop(AutosavingSerializableItem::<u32>::new_or_default(NAMESPACE.to_vec())?);
+		let item = AutosavingSerializableItem::<u32>::new_or_default(NAMESPACE.to_vec())?;
+		assert_eq!(*item, 0);
+		drop(item);
+		assert!(!storage_has(NAMESPACE));
+		assert_eq!(writes.load(Ordering::SeqCst), 0);
+
+		// A mutated default is
+		let mut item = AutosavingSerializableItem::<u32>::new_or_default(NAMESPACE.to_vec())?;
+		*item += 5;
+		drop(item);
+		assert_eq!(storage_read_item::<u32>(NAMESPACE)?.map(OZeroCopy::into_inner), Some(5));
+		assert_eq!(writes.load(Ordering::SeqCst), 1);
+
+		// A stored value which is only read isn't rewritten
+		let item = AutosavingSerializableItem::<u32>::new(NAMESPACE.to_vec())?.unwrap();
+		assert_eq!(*item, 5);
+		drop(item);
+		assert_eq!(writes.load(Ordering::SeqCst), 1);
+		Ok(())
+	}
+
+	#[test]
+	fn autosave_stored_item_only_when_modified() -> TestingResult {
+		let storage = CountingStorage::default();
+		let writes = storage.writes.clone();
+		let _storage = ThreadStorageGuard::new(Box::new(storage));
+
+		drop(<(u16, u16)>::load_with_autosave_or_default()?);
+		assert!(!storage_has(<(u16, u16)>::namespace()));
+
+		let mut item = <(u16, u16)>::load_with_autosave_or_default()?;
+		item.1 = 420;
+		drop(item);
+		assert_eq!(writes.load(Ordering::SeqCst), 1);
+
+		let item = <(u16, u16)>::load_with_autosave()?.unwrap();
+		assert_eq!(*item, (0, 420));
+		drop(item);
+		assert_eq!(writes.load(Ordering::SeqCst), 1);
+		Ok(())
+	}
+
+	#[test]
+	fn autosaving_pod_struct() -> TestingResult {
+		let _storage_lock = init()?;
+		let mut stats = AutosavingSerializableItem::<StoredStats>::new_or_default(NAMESPACE.to_vec())?;
+		stats.count = 1;
+		stats.sum = 10;
+		drop(stats);
+
+		// Read in place this time, so the mutation happens in the bytes which are written back
+		let mut stats = AutosavingSerializableItem::<StoredStats>::new(NAMESPACE.to_vec())?.unwrap();
+		stats.count += 1;
+		stats.sum += 5;
+		drop(stats);
+
+		let stats = storage_read_item::<StoredStats>(NAMESPACE)?.unwrap();
+		assert_eq!((stats.count, stats.sum), (2, 15));
+		assert_eq!(
+			storage_read(NAMESPACE).unwrap(),
+			stats.into_inner().serialize_to_owned()?
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn autosaving_borsh_struct() -> TestingResult {
+		let _storage_lock = init()?;
+		let mut settings = Settings::load_with_autosave_or_default()?;
+		settings.admin = "alice".to_string();
+		drop(settings);
+
+		let mut settings = Settings::load_with_autosave()?.unwrap();
+		assert_eq!(settings.admin, "alice");
+		settings.admin.push_str("bob");
+		settings.paused = true;
+		drop(settings);
+
+		assert_eq!(
+			Settings::load()?.map(OZeroCopy::into_inner),
+			Some(Settings {
+				admin: "alicebob".to_string(),
+				paused: true
+			})
+		);
+		Ok(())
+	}
 }
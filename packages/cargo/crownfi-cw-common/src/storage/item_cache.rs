@@ -0,0 +1,147 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use super::base::storage_read;
+
+/// Memoizes storage reads of [StoredItem](super::item::StoredItem)s within a single contract call, used by
+/// [StoredItem::load_cached](super::item::StoredItem::load_cached).
+///
+/// Every write or removal done through [storage_write](super::base::storage_write) or
+/// [storage_remove](super::base::storage_remove) (which everything in this crate uses) replaces the cached entry for
+/// that key, so cached values can't go stale because of a save. Writes made through `deps.storage` bypass this
+/// however, so call [clear_item_cache] after using it and at the start of each entry point.
+///
+/// On native targets the cache is per-thread and is also cleared when [set_global_storage] is called. On wasm, the
+/// contract is single-threaded and the cache is a plain static.
+///
+/// [set_global_storage]: super::base::set_global_storage
+#[derive(Debug, Default)]
+pub struct ItemCache {
+	#[cfg(not(target_arch = "wasm32"))]
+	storage_seq: u32,
+	/// `None` values are cached misses
+	entries: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+thread_local! {
+	static ITEM_CACHE: RefCell<ItemCache> = RefCell::default();
+}
+
+#[cfg(target_arch = "wasm32")]
+struct SingleThreaded(RefCell<ItemCache>);
+// SAFTY: wasm contracts don't have threads
+#[cfg(target_arch = "wasm32")]
+unsafe impl Sync for SingleThreaded {}
+#[cfg(target_arch = "wasm32")]
+static ITEM_CACHE: SingleThreaded = SingleThreaded(RefCell::new(ItemCache {
+	entries: BTreeMap::new(),
+}));
+
+impl ItemCache {
+	#[cfg(not(target_arch = "wasm32"))]
+	fn with<R>(f: impl FnOnce(&mut ItemCache) -> R) -> R {
+		ITEM_CACHE.with(|cache| {
+			let mut cache = cache.borrow_mut();
+			let storage_seq = super::base::storage_seq();
+			if cache.storage_seq != storage_seq {
+				cache.storage_seq = storage_seq;
+				cache.entries.clear();
+			}
+			f(&mut cache)
+		})
+	}
+	#[cfg(target_arch = "wasm32")]
+	fn with<R>(f: impl FnOnce(&mut ItemCache) -> R) -> R {
+		f(&mut ITEM_CACHE.0.borrow_mut())
+	}
+
+	/// Returns the cached value of the key, reading it from storage if it isn't cached yet.
+	pub(crate) fn read_through(key: &[u8]) -> Option<Vec<u8>> {
+		if let Some(cached) = Self::with(|cache| cache.entries.get(key).cloned()) {
+			return cached;
+		}
+		let value = storage_read(key);
+		Self::with(|cache| cache.entries.insert(key.to_vec(), value.clone()));
+		value
+	}
+
+	/// Updates the cached value of the key if it's cached, `None` meaning it was removed.
+	pub(crate) fn on_write(key: &[u8], value: Option<&[u8]>) {
+		Self::with(|cache| {
+			if let Some(cached) = cache.entries.get_mut(key) {
+				*cached = value.map(<[u8]>::to_vec);
+			}
+		})
+	}
+
+	/// Returns the amount of cached keys.
+	pub fn len() -> usize {
+		Self::with(|cache| cache.entries.len())
+	}
+}
+
+/// Empties the [ItemCache], entry points should call this before doing anything else.
+pub fn clear_item_cache() {
+	ItemCache::with(|cache| cache.entries.clear())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::Ordering;
+
+	use cosmwasm_std::MemoryStorage;
+
+	use super::*;
+	use crate::storage::{
+		base::{set_global_storage, storage_write},
+		item::StoredItem,
+		testing_common::*,
+		OZeroCopy,
+	};
+
+	impl StoredItem for u64 {
+		fn namespace() -> &'static [u8] {
+			b"config"
+		}
+	}
+
+	#[test]
+	fn second_load_does_not_read() -> TestingResult {
+		let (_storage_lock, reads, _) = init_counting()?;
+		5u64.save()?;
+		assert_eq!(u64::load_cached()?.map(OZeroCopy::into_inner), Some(5));
+		assert_eq!(reads.load(Ordering::SeqCst), 1);
+		assert_eq!(u64::load_cached()?.map(OZeroCopy::into_inner), Some(5));
+		assert_eq!(reads.load(Ordering::SeqCst), 1);
+
+		clear_item_cache();
+		assert_eq!(ItemCache::len(), 0);
+		assert_eq!(u64::load_cached()?.map(OZeroCopy::into_inner), Some(5));
+		assert_eq!(reads.load(Ordering::SeqCst), 2);
+		Ok(())
+	}
+
+	#[test]
+	fn writes_update_cache() -> TestingResult {
+		let (_storage_lock, reads, _) = init_counting()?;
+		assert_eq!(u64::load_cached()?, None);
+		6u64.save()?;
+		assert_eq!(u64::load_cached()?.map(OZeroCopy::into_inner), Some(6));
+		// Autosaving items and raw writes are seen as well
+		*u64::load_with_autosave()?.unwrap() += 1;
+		assert_eq!(u64::load_cached()?.map(OZeroCopy::into_inner), Some(7));
+		storage_write(u64::namespace(), &8u64.to_le_bytes());
+		assert_eq!(u64::load_cached()?.map(OZeroCopy::into_inner), Some(8));
+		u64::remove();
+		assert_eq!(u64::load_cached()?, None);
+		// Only the first load_cached and load_with_autosave read storage
+		assert_eq!(reads.load(Ordering::SeqCst), 2);
+
+		// Replacing the storage clears the cache
+		9u64.save()?;
+		assert_eq!(u64::load_cached()?.map(OZeroCopy::into_inner), Some(9));
+		set_global_storage(Box::new(MemoryStorage::new()));
+		assert_eq!(u64::load_cached()?, None);
+		Ok(())
+	}
+}
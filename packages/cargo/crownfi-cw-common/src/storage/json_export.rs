@@ -0,0 +1,215 @@
+use std::any::type_name;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Binary, StdError, StdResult};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::utils::{escape_key_bytes, lexicographic_next};
+
+use super::{map::StoredMap, set::StoredSet, vec::StoredVec, SerializableItem, StoragePairIterator};
+
+/// The most entries [JsonExportable::export_json] returns at once, larger limits are lowered to this.
+pub const JSON_EXPORT_MAX_LIMIT: u32 = 100;
+
+/// A page of entries exported by [JsonExportable::export_json].
+#[cw_serde]
+pub struct JsonExportPage {
+	pub entries: Vec<Value>,
+	/// Pass this as `start_after_raw` to get the next page, `None` if there are no more entries.
+	pub next: Option<Binary>,
+}
+
+/// Exports the contents of a storage container as human-readable JSON, e.g. for governance or audit queries.
+///
+/// Values are serialized with serde rather than borsh. Keys are rendered the same way, so strings and addresses
+/// become strings, integers become numbers and tuples become arrays. Keys which can't be represented in JSON (e.g.
+/// integers larger than `u64::MAX`) fall back to their storage key, escaped with
+/// [escape_key_bytes](crate::utils::escape_key_bytes).
+///
+/// Entries are returned in storage order, which isn't numeric order for integer keys as they're stored
+/// little-endian. The cursor is the raw storage key of the last entry, so paging never has to parse keys.
+pub trait JsonExportable {
+	/// Returns up to `limit` entries (at most [JSON_EXPORT_MAX_LIMIT]) which come after `start_after_raw`.
+	fn export_json(&self, limit: u32, start_after_raw: Option<Binary>) -> StdResult<JsonExportPage>;
+}
+
+fn to_json_value<T: Serialize>(value: &T) -> StdResult<Value> {
+	serde_json::to_value(value).map_err(|err| StdError::serialize_err(type_name::<T>(), err))
+}
+
+fn key_to_json_value<K: Serialize>(key: &K, raw_key: &[u8]) -> Value {
+	serde_json::to_value(key).unwrap_or_else(|_| Value::String(escape_key_bytes(raw_key)))
+}
+
+/// Converts each entry within the namespace to JSON with `f`, which gets the storage key with the namespace removed
+/// and may skip entries by returning `None`.
+fn export_page(
+	namespace: &[u8],
+	limit: u32,
+	start_after_raw: Option<Binary>,
+	mut f: impl FnMut(&[u8], &[u8]) -> StdResult<Option<Value>>,
+) -> StdResult<JsonExportPage> {
+	let limit = limit.clamp(1, JSON_EXPORT_MAX_LIMIT) as usize;
+	// Appending a 0 results in the smallest storage key which comes after start_after_raw's
+	let start = start_after_raw.map_or_else(|| namespace.to_vec(), |key| [namespace, &key, &[0]].concat());
+	let mut iter = StoragePairIterator::new(Some(&start), Some(&lexicographic_next(namespace))).peekable();
+	let mut entries = Vec::new();
+	let mut last_key = None;
+	while entries.len() < limit {
+		let Some((key, value)) = iter.next() else {
+			break;
+		};
+		let raw_key = &key[namespace.len()..];
+		if let Some(entry) = f(raw_key, &value)? {
+			entries.push(entry);
+		}
+		last_key = Some(raw_key.to_vec());
+	}
+	let next = if iter.peek().is_some() {
+		last_key.map(Binary::from)
+	} else {
+		None
+	};
+	Ok(JsonExportPage { entries, next })
+}
+
+/// Entries are objects with a `key` and `value`.
+impl<K, V> JsonExportable for StoredMap<K, V>
+where
+	K: SerializableItem + Serialize,
+	V: SerializableItem + Serialize,
+{
+	fn export_json(&self, limit: u32, start_after_raw: Option<Binary>) -> StdResult<JsonExportPage> {
+		export_page(self.namespace(), limit, start_after_raw, |raw_key, raw_value| {
+			let key = K::deserialize_to_owned(raw_key)?;
+			let value = V::deserialize_to_owned(raw_value)?;
+			Ok(Some(json!({
+				"key": key_to_json_value(&key, raw_key),
+				"value": to_json_value(&value)?,
+			})))
+		})
+	}
+}
+
+/// Entries are the values of the set.
+impl<V> JsonExportable for StoredSet<V>
+where
+	V: SerializableItem + Serialize,
+{
+	fn export_json(&self, limit: u32, start_after_raw: Option<Binary>) -> StdResult<JsonExportPage> {
+		export_page(self.namespace(), limit, start_after_raw, |raw_key, _| {
+			Ok(Some(key_to_json_value(&V::deserialize_to_owned(raw_key)?, raw_key)))
+		})
+	}
+}
+
+/// Entries are objects with an `index` and `value`, as they aren't returned in index order.
+impl<V> JsonExportable for StoredVec<V>
+where
+	V: SerializableItem + Serialize,
+{
+	fn export_json(&self, limit: u32, start_after_raw: Option<Binary>) -> StdResult<JsonExportPage> {
+		export_page(self.namespace(), limit, start_after_raw, |raw_key, raw_value| {
+			// The length header and any orphaned entries aren't part of the vec
+			let Ok(index) = raw_key.try_into().map(u32::from_le_bytes) else {
+				return Ok(None);
+			};
+			if index >= self.len() {
+				return Ok(None);
+			}
+			Ok(Some(json!({
+				"index": index,
+				"value": to_json_value(&V::deserialize_to_owned(raw_value)?)?,
+			})))
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	#[test]
+	fn tuple_keys_are_readable() -> TestingResult {
+		let _storage_lock = init()?;
+		let fees = StoredMap::<(String, u16), u128>::new(NAMESPACE);
+		fees.set(&("usei".to_string(), 30), &5)?;
+		fees.set(&("uatom".to_string(), 5), &1_000_000)?;
+		let page = fees.export_json(10, None)?;
+		assert_eq!(page.next, None);
+		// Borsh strings start with their length, so shorter ones come first
+		assert_eq!(
+			Value::Array(page.entries),
+			json!([
+				{"key": ["usei", 30], "value": 5},
+				{"key": ["uatom", 5], "value": 1_000_000},
+			])
+		);
+
+		let whitelist = StoredSet::<String>::new(b"whitelist");
+		whitelist.add(&"sei1alice".to_string())?;
+		assert_eq!(whitelist.export_json(10, None)?.entries, [json!("sei1alice")]);
+
+		let mut history = StoredVec::<(u64, bool)>::new(b"history");
+		history.push(&(7, true))?;
+		history.push(&(8, false))?;
+		assert_eq!(
+			history.export_json(10, None)?.entries,
+			[
+				json!({"index": 0, "value": [7, true]}),
+				json!({"index": 1, "value": [8, false]})
+			]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn pages_are_continuous_and_limited() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = StoredMap::<u32, String>::new(NAMESPACE);
+		for i in 0..250u32 {
+			map.set(&i, &format!("value {i}"))?;
+		}
+		let mut exported = Vec::new();
+		let mut start_after = None;
+		let mut pages = 0;
+		loop {
+			// Asking for more than the maximum only gets the maximum
+			let page = map.export_json(u32::MAX, start_after)?;
+			assert!(page.entries.len() <= JSON_EXPORT_MAX_LIMIT as usize);
+			exported.extend(page.entries);
+			pages += 1;
+			let Some(next) = page.next else {
+				break;
+			};
+			start_after = Some(next);
+		}
+		assert_eq!(pages, 3);
+		let mut keys: Vec<u64> = exported.iter().map(|entry| entry["key"].as_u64().unwrap()).collect();
+		keys.sort_unstable();
+		assert_eq!(keys, (0..250).collect::<Vec<u64>>());
+
+		assert_eq!(map.export_json(0, None)?.entries.len(), 1);
+		let page = map.export_json(2, None)?;
+		let next_page = map.export_json(2, page.next)?;
+		assert_eq!(
+			map.export_json(4, None)?.entries,
+			[page.entries, next_page.entries].concat()
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn unrepresentable_keys_fall_back_to_escaped_bytes() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = StoredMap::<u128, u8>::new(NAMESPACE);
+		map.set(&u128::MAX, &1)?;
+		assert_eq!(
+			map.export_json(1, None)?.entries,
+			[json!({"key": "%FF".repeat(16), "value": 1})]
+		);
+		Ok(())
+	}
+}
@@ -0,0 +1,120 @@
+use std::ops::Deref;
+
+/// Keys up to this length are stored inline by [KeyBuf].
+pub const KEY_BUF_INLINE_LEN: usize = 64;
+
+#[derive(Debug, Clone)]
+enum KeyBufInner {
+	Inline([u8; KEY_BUF_INLINE_LEN], usize),
+	Heap(Vec<u8>),
+}
+
+/// A byte buffer for building storage keys which only allocates once the key grows beyond [KEY_BUF_INLINE_LEN]
+/// bytes, so that accessing containers with short keys doesn't require any heap allocations.
+#[derive(Debug, Clone)]
+pub struct KeyBuf(KeyBufInner);
+
+impl KeyBuf {
+	#[inline]
+	pub const fn new() -> Self {
+		Self(KeyBufInner::Inline([0; KEY_BUF_INLINE_LEN], 0))
+	}
+
+	/// Same as [concat_byte_array_pairs](super::concat_byte_array_pairs), but using a `KeyBuf`.
+	#[inline]
+	pub fn from_parts(a: &[u8], b: &[u8]) -> Self {
+		let mut result = Self::new();
+		result.extend_from_slice(a);
+		result.extend_from_slice(b);
+		result
+	}
+
+	pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+		match &mut self.0 {
+			KeyBufInner::Inline(inline_bytes, len) => {
+				let new_len = *len + bytes.len();
+				if new_len <= KEY_BUF_INLINE_LEN {
+					inline_bytes[*len..new_len].copy_from_slice(bytes);
+					*len = new_len;
+				} else {
+					let mut heap_bytes = Vec::with_capacity(new_len);
+					heap_bytes.extend_from_slice(&inline_bytes[..*len]);
+					heap_bytes.extend_from_slice(bytes);
+					self.0 = KeyBufInner::Heap(heap_bytes);
+				}
+			}
+			KeyBufInner::Heap(heap_bytes) => heap_bytes.extend_from_slice(bytes),
+		}
+	}
+
+	/// Empties the buffer. Any heap allocation is kept so it can be re-used.
+	pub fn clear(&mut self) {
+		match &mut self.0 {
+			KeyBufInner::Inline(_, len) => *len = 0,
+			KeyBufInner::Heap(heap_bytes) => heap_bytes.clear(),
+		}
+	}
+
+	#[inline]
+	pub fn as_slice(&self) -> &[u8] {
+		match &self.0 {
+			KeyBufInner::Inline(inline_bytes, len) => &inline_bytes[..*len],
+			KeyBufInner::Heap(heap_bytes) => heap_bytes,
+		}
+	}
+
+	/// Returns true if the contents haven't been spilled to the heap.
+	#[inline]
+	pub fn is_inline(&self) -> bool {
+		matches!(self.0, KeyBufInner::Inline(..))
+	}
+}
+
+impl Default for KeyBuf {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Deref for KeyBuf {
+	type Target = [u8];
+	#[inline]
+	fn deref(&self) -> &Self::Target {
+		self.as_slice()
+	}
+}
+
+impl AsRef<[u8]> for KeyBuf {
+	#[inline]
+	fn as_ref(&self) -> &[u8] {
+		self.as_slice()
+	}
+}
+
+impl PartialEq for KeyBuf {
+	fn eq(&self, other: &Self) -> bool {
+		self.as_slice() == other.as_slice()
+	}
+}
+impl Eq for KeyBuf {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn spills_to_heap() {
+		let mut buf = KeyBuf::from_parts(b"namespace", &[1; 55]);
+		assert!(buf.is_inline());
+		assert_eq!(buf.len(), KEY_BUF_INLINE_LEN);
+		buf.extend_from_slice(&[2]);
+		assert!(!buf.is_inline());
+		assert_eq!(&buf[..9], b"namespace");
+		assert_eq!(&buf[9..], [&[1; 55][..], &[2]].concat());
+
+		buf.clear();
+		assert!(buf.is_empty());
+		buf.extend_from_slice(b"short");
+		assert_eq!(buf.as_slice(), b"short");
+	}
+}
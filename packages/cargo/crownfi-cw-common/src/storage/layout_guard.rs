@@ -0,0 +1,233 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use cosmwasm_std::StdError;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use super::{
+	base::{storage_read_item, storage_write_item},
+	pod_layout::{layout_fingerprint, PodLayout},
+	OZeroCopy,
+};
+
+/// The fingerprints of the last accepted storage layout live here.
+pub const LAYOUT_GUARD_NAMESPACE: &[u8] = b"\xfflayout";
+
+/// Describes a single storage container of a contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutEntry {
+	/// A stable name for the container, used to report what changed. Renaming this is reported as a removal and an
+	/// addition.
+	pub name: &'static str,
+	pub namespace: &'static [u8],
+	/// The kind of container, e.g. `"StoredMap"` or `"StoredVec"`.
+	pub kind: &'static str,
+	/// The name of the stored value type. This is given explicitly rather than using `std::any::type_name`, as the
+	/// latter isn't guaranteed to be stable between compiler versions.
+	pub value_type: &'static str,
+	/// The [layout_fingerprint] of the stored value type, if it's a zero-copy struct. See
+	/// [with_pod_layout](Self::with_pod_layout).
+	pub value_layout: Option<u64>,
+}
+
+impl LayoutEntry {
+	pub const fn new(
+		name: &'static str,
+		namespace: &'static [u8],
+		kind: &'static str,
+		value_type: &'static str,
+	) -> Self {
+		Self {
+			name,
+			namespace,
+			kind,
+			value_type,
+			value_layout: None,
+		}
+	}
+
+	/// Also reports the entry as changed if the layout declared with [assert_pod_layout](crate::assert_pod_layout)
+	/// for `T` changes.
+	pub const fn with_pod_layout<T: PodLayout>(mut self) -> Self {
+		self.value_layout = Some(layout_fingerprint::<T>());
+		self
+	}
+
+	pub fn fingerprint(&self) -> [u8; 32] {
+		// Each field is length-prefixed so different splits of the same bytes can't collide.
+		let mut hasher = Sha256::new();
+		for field in [self.namespace, self.kind.as_bytes(), self.value_type.as_bytes()] {
+			hasher.update((field.len() as u64).to_le_bytes());
+			hasher.update(field);
+		}
+		// Only hashed when set, so entries which don't use it keep their existing fingerprints.
+		if let Some(value_layout) = self.value_layout {
+			hasher.update(value_layout.to_le_bytes());
+		}
+		hasher.finalize().into()
+	}
+}
+
+/// The differences between the stored layout and the one declared by the code, by [LayoutEntry::name].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LayoutDiff {
+	pub added: Vec<String>,
+	pub removed: Vec<String>,
+	pub changed: Vec<String>,
+}
+
+impl LayoutDiff {
+	pub fn is_empty(&self) -> bool {
+		self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+	}
+}
+
+impl Display for LayoutDiff {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let mut first = true;
+		for (label, names) in [
+			("added", &self.added),
+			("removed", &self.removed),
+			("changed", &self.changed),
+		] {
+			if names.is_empty() {
+				continue;
+			}
+			if !first {
+				f.write_str("; ")?;
+			}
+			first = false;
+			write!(f, "{label}: {}", names.join(", "))?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum LayoutGuardError {
+	#[error("Storage layout has changed ({0}), call accept_layout_changes() if this is intended")]
+	LayoutChanged(LayoutDiff),
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<LayoutGuardError> for StdError {
+	fn from(value: LayoutGuardError) -> Self {
+		match value {
+			LayoutGuardError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+type StoredLayout = Vec<(String, [u8; 32])>;
+
+fn declared_layout(entries: &[LayoutEntry]) -> StoredLayout {
+	entries
+		.iter()
+		.map(|entry| (entry.name.to_string(), entry.fingerprint()))
+		.collect()
+}
+
+/// Compares the storage layout declared by the code against the one accepted previously.
+///
+/// This is intended to be called during every instantiation and migration. The first time it's called, the declared
+/// layout is accepted as-is. Afterwards, an error describing the differences is returned if the layout has changed.
+pub fn verify_layout(entries: &[LayoutEntry]) -> Result<(), LayoutGuardError> {
+	let Some(stored_layout) = storage_read_item::<StoredLayout>(LAYOUT_GUARD_NAMESPACE)? else {
+		return Ok(accept_layout_changes(entries)?);
+	};
+	let diff = layout_diff(&OZeroCopy::into_inner(stored_layout), &declared_layout(entries));
+	if !diff.is_empty() {
+		return Err(LayoutGuardError::LayoutChanged(diff));
+	}
+	Ok(())
+}
+
+/// Stores the declared layout as the accepted one, for use by migrations which intentionally change the layout.
+pub fn accept_layout_changes(entries: &[LayoutEntry]) -> Result<(), StdError> {
+	storage_write_item(LAYOUT_GUARD_NAMESPACE, &declared_layout(entries))
+}
+
+fn layout_diff(stored: &StoredLayout, declared: &StoredLayout) -> LayoutDiff {
+	let stored: BTreeMap<&str, &[u8; 32]> = stored.iter().map(|(name, hash)| (name.as_str(), hash)).collect();
+	let declared: BTreeMap<&str, &[u8; 32]> = declared.iter().map(|(name, hash)| (name.as_str(), hash)).collect();
+	let mut diff = LayoutDiff::default();
+	for (name, fingerprint) in declared.iter() {
+		match stored.get(name) {
+			None => diff.added.push(name.to_string()),
+			Some(stored_fingerprint) if stored_fingerprint != fingerprint => diff.changed.push(name.to_string()),
+			_ => {}
+		}
+	}
+	for name in stored.keys() {
+		if !declared.contains_key(name) {
+			diff.removed.push(name.to_string());
+		}
+	}
+	diff
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	const V1_LAYOUT: &[LayoutEntry] = &[
+		LayoutEntry::new("config", b"config", "StoredItem", "Config"),
+		LayoutEntry::new("balances", b"balances", "StoredMap", "u128"),
+		LayoutEntry::new("orders", b"orders", "StoredVec", "Order"),
+	];
+
+	#[test]
+	fn accidental_rename() -> TestingResult {
+		let _storage_lock = init()?;
+		verify_layout(V1_LAYOUT)?;
+		verify_layout(V1_LAYOUT)?;
+
+		let v2_layout = &[
+			LayoutEntry::new("config", b"config", "StoredItem", "Config"),
+			// Whoops
+			LayoutEntry::new("balances", b"balance", "StoredMap", "u128"),
+			LayoutEntry::new("orders", b"orders", "StoredVec", "Order"),
+		];
+		let err = verify_layout(v2_layout).unwrap_err();
+		assert_eq!(
+			err,
+			LayoutGuardError::LayoutChanged(LayoutDiff {
+				changed: vec!["balances".into()],
+				..Default::default()
+			})
+		);
+		assert_eq!(
+			err.to_string(),
+			"Storage layout has changed (changed: balances), call accept_layout_changes() if this is intended"
+		);
+		// Still broken, nothing was accepted
+		assert!(verify_layout(v2_layout).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn intentional_migration() -> TestingResult {
+		let _storage_lock = init()?;
+		verify_layout(V1_LAYOUT)?;
+
+		let v2_layout = &[
+			LayoutEntry::new("config", b"config", "StoredItem", "ConfigV2"),
+			LayoutEntry::new("balances", b"balances", "StoredMap", "u128"),
+			LayoutEntry::new("positions", b"positions", "StoredMap", "Position"),
+		];
+		assert_eq!(
+			verify_layout(v2_layout),
+			Err(LayoutGuardError::LayoutChanged(LayoutDiff {
+				added: vec!["positions".into()],
+				removed: vec!["orders".into()],
+				changed: vec!["config".into()],
+			}))
+		);
+		accept_layout_changes(v2_layout)?;
+		verify_layout(v2_layout)?;
+		assert!(verify_layout(V1_LAYOUT).is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,292 @@
+use cosmwasm_std::{StdError, StdResult};
+use thiserror::Error;
+
+use crate::utils::lexicographic_next;
+
+use super::{
+	base::{storage_read, storage_write},
+	concat_byte_array_pairs,
+	map::{StoredMap, StoredMapIter},
+	OZeroCopy, SerializableItem,
+};
+
+/// Backfill progress of every LazyIndex lives under this prefix.
+pub const LAZY_INDEX_STATE_NAMESPACE: &[u8] = b"\xfflazyindex/";
+
+/// The amount of primary entries [LazyIndex::get_via] looks at on an index miss, unless changed with
+/// [LazyIndex::with_scan_bound].
+pub const DEFAULT_LAZY_INDEX_SCAN_BOUND: u32 = 32;
+
+const BACKFILL_IN_PROGRESS: u8 = 0;
+const BACKFILL_DONE: u8 = 1;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum LazyIndexError {
+	#[error("Index is incomplete and the entry wasn't found within the first {0} unindexed entries")]
+	IndexIncomplete(u32),
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<LazyIndexError> for StdError {
+	fn from(value: LazyIndexError) -> Self {
+		match value {
+			LazyIndexError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// A unique secondary index over a [StoredMap] which can be used before it has been fully built, intended for adding
+/// an index to an existing contract.
+///
+/// * Writes and removals done through the LazyIndex keep the index up to date.
+/// * [backfill](Self::backfill) indexes the existing primary entries in bounded batches.
+/// * Until the backfill has reported completion, [get_via](Self::get_via) falls back to scanning the primary entries
+///   which haven't been backfilled yet on an index miss, up to the scan bound. Afterwards misses don't scan.
+///
+/// `extractor` returns the index key of a primary entry. Index entries are stored as `IK => K` in their own
+/// namespace.
+pub struct LazyIndex<K: SerializableItem, V: SerializableItem, IK: SerializableItem> {
+	primary: StoredMap<K, V>,
+	index: StoredMap<IK, K>,
+	state_key: Vec<u8>,
+	extractor: fn(&K, &V) -> IK,
+	scan_bound: u32,
+	read_repair: bool,
+}
+
+impl<K: SerializableItem, V: SerializableItem, IK: SerializableItem + PartialEq> LazyIndex<K, V, IK> {
+	pub fn new(primary_namespace: &'static [u8], index_namespace: &'static [u8], extractor: fn(&K, &V) -> IK) -> Self {
+		Self {
+			primary: StoredMap::new(primary_namespace),
+			index: StoredMap::new(index_namespace),
+			state_key: concat_byte_array_pairs(LAZY_INDEX_STATE_NAMESPACE, index_namespace),
+			extractor,
+			scan_bound: DEFAULT_LAZY_INDEX_SCAN_BOUND,
+			read_repair: false,
+		}
+	}
+
+	/// Sets the amount of primary entries `get_via` scans on an index miss before giving up.
+	pub fn with_scan_bound(mut self, scan_bound: u32) -> Self {
+		self.scan_bound = scan_bound;
+		self
+	}
+
+	/// Writes the index entry of primary entries found by scanning in `get_via`.
+	///
+	/// Don't enable this for indexes which are read during queries, as storage is read-only there.
+	pub fn with_read_repair(mut self) -> Self {
+		self.read_repair = true;
+		self
+	}
+
+	#[inline]
+	pub fn primary(&self) -> &StoredMap<K, V> {
+		&self.primary
+	}
+
+	#[inline]
+	pub fn index(&self) -> &StoredMap<IK, K> {
+		&self.index
+	}
+
+	/// Returns true once [backfill](Self::backfill) has indexed every primary entry.
+	pub fn is_complete(&self) -> bool {
+		storage_read(&self.state_key).is_some_and(|state| state.first() == Some(&BACKFILL_DONE))
+	}
+
+	/// Returns the storage key the next backfill or scan starts at, or `None` if the backfill is complete.
+	fn unindexed_start(&self) -> Option<Vec<u8>> {
+		match storage_read(&self.state_key) {
+			Some(state) if state.first() == Some(&BACKFILL_DONE) => None,
+			Some(state) => {
+				let mut start_key = state[1..].to_vec();
+				// Start after the last indexed key
+				start_key.push(0);
+				Some(start_key)
+			}
+			None => Some(self.primary.namespace().to_vec()),
+		}
+	}
+
+	fn unindexed_entries(&self, start_key: &[u8]) -> StoredMapIter<K, V> {
+		let namespace = self.primary.namespace();
+		StoredMapIter::new_raw(start_key, &lexicographic_next(namespace), namespace.len())
+	}
+
+	/// Returns the primary entry with the specified index key.
+	///
+	/// On an index miss while the backfill isn't complete, the entries which haven't been backfilled yet are scanned.
+	/// [LazyIndexError::IndexIncomplete] is returned if the entry wasn't found within the scan bound.
+	pub fn get_via(&self, index_key: &IK) -> Result<Option<(K, OZeroCopy<V>)>, LazyIndexError> {
+		if let Some(key) = self.index.get(index_key)? {
+			let key = key.into_inner();
+			let value = self
+				.primary
+				.get(&key)?
+				.ok_or_else(|| StdError::not_found("LazyIndex entry points to a missing primary entry"))?;
+			return Ok(Some((key, value)));
+		}
+		let Some(start_key) = self.unindexed_start() else {
+			return Ok(None);
+		};
+		for (scanned, (key, value)) in self.unindexed_entries(&start_key).enumerate() {
+			if scanned == self.scan_bound as usize {
+				return Err(LazyIndexError::IndexIncomplete(self.scan_bound));
+			}
+			if (self.extractor)(&key, &value) == *index_key {
+				if self.read_repair {
+					self.index.set(index_key, &key)?;
+				}
+				return Ok(Some((key, value)));
+			}
+		}
+		Ok(None)
+	}
+
+	pub fn set(&self, key: &K, value: &V) -> StdResult<()> {
+		let index_key = (self.extractor)(key, value);
+		if let Some(old_value) = self.primary.get(key)? {
+			let old_index_key = (self.extractor)(key, &old_value);
+			if old_index_key != index_key {
+				self.index.remove(&old_index_key);
+			}
+		}
+		self.primary.set(key, value)?;
+		self.index.set(&index_key, key)
+	}
+
+	pub fn remove(&self, key: &K) -> StdResult<()> {
+		if let Some(old_value) = self.primary.get(key)? {
+			self.index.remove(&(self.extractor)(key, &old_value));
+			self.primary.remove(key);
+		}
+		Ok(())
+	}
+
+	/// Indexes up to `limit` primary entries, continuing where the last call left off.
+	///
+	/// Returns the amount of entries indexed, and whether or not all primary entries have been indexed.
+	pub fn backfill(&self, limit: u32) -> StdResult<(u32, bool)> {
+		let Some(start_key) = self.unindexed_start() else {
+			return Ok((0, true));
+		};
+		let mut entries = self.unindexed_entries(&start_key);
+		let mut indexed = 0;
+		let mut last_key = None;
+		while indexed < limit {
+			let Some((key, value)) = entries.next() else {
+				storage_write(&self.state_key, &[BACKFILL_DONE]);
+				return Ok((indexed, true));
+			};
+			self.index.set(&(self.extractor)(&key, &value), &key)?;
+			indexed += 1;
+			last_key = Some(key);
+		}
+		// The limit may have been reached right at the last entry
+		if entries.next().is_none() {
+			storage_write(&self.state_key, &[BACKFILL_DONE]);
+			return Ok((indexed, true));
+		}
+		if let Some(last_key) = last_key {
+			storage_write(
+				&self.state_key,
+				&concat_byte_array_pairs(&[BACKFILL_IN_PROGRESS], &self.primary.key(&last_key)),
+			);
+		}
+		Ok((indexed, false))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::Ordering;
+
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	const INDEX_NAMESPACE: &[u8] = b"by_name";
+
+	/// Primary map of IDs to names, indexed by name
+	fn by_name() -> LazyIndex<u32, String, String> {
+		LazyIndex::new(NAMESPACE, INDEX_NAMESPACE, |_, name| name.clone())
+	}
+
+	/// Writes the entries before the index existed
+	fn populate(count: u32) -> StdResult<()> {
+		let primary = by_name().primary;
+		for id in 0..count {
+			primary.set(&id, &format!("name {id}"))?;
+		}
+		Ok(())
+	}
+
+	fn get_id(index: &LazyIndex<u32, String, String>, name: &str) -> Result<Option<u32>, LazyIndexError> {
+		Ok(index.get_via(&name.to_string())?.map(|(id, _)| id))
+	}
+
+	#[test]
+	fn read_repair() -> TestingResult {
+		let _storage = isolated()?;
+		populate(5)?;
+		let index = by_name();
+		assert_eq!(get_id(&index, "name 3")?, Some(3));
+		assert_eq!(index.index().get(&"name 3".to_string())?, None);
+
+		let index = by_name().with_read_repair();
+		assert_eq!(get_id(&index, "name 3")?, Some(3));
+		assert_eq!(
+			index.index().get(&"name 3".to_string())?.map(OZeroCopy::into_inner),
+			Some(3)
+		);
+		assert_eq!(get_id(&index, "nobody")?, None);
+
+		// Writes keep the index up to date
+		index.set(&3, &"renamed".to_string())?;
+		assert_eq!(index.index().get(&"name 3".to_string())?, None);
+		assert_eq!(get_id(&index, "renamed")?, Some(3));
+		index.remove(&3)?;
+		assert_eq!(get_id(&index, "renamed")?, None);
+		Ok(())
+	}
+
+	#[test]
+	fn scan_bound() -> TestingResult {
+		let _storage = isolated()?;
+		populate(10)?;
+		let index = by_name().with_scan_bound(4);
+		// u32 keys are little-endian, so 0..4 are the first 4 in storage order
+		assert_eq!(get_id(&index, "name 3")?, Some(3));
+		assert_eq!(get_id(&index, "name 4"), Err(LazyIndexError::IndexIncomplete(4)));
+		assert_eq!(get_id(&index, "nobody"), Err(LazyIndexError::IndexIncomplete(4)));
+
+		// Scanning only covers the entries which haven't been backfilled
+		assert_eq!(index.backfill(4)?, (4, false));
+		assert_eq!(get_id(&index, "name 7")?, Some(7));
+		assert_eq!(get_id(&index, "name 8"), Err(LazyIndexError::IndexIncomplete(4)));
+		assert_eq!(index.backfill(4)?, (4, false));
+		assert_eq!(get_id(&index, "nobody")?, None);
+		Ok(())
+	}
+
+	#[test]
+	fn backfilled_reads_never_scan() -> TestingResult {
+		let (_storage_lock, _, ranges) = init_counting()?;
+		populate(10)?;
+		let index = by_name().with_scan_bound(0);
+		assert_eq!(index.backfill(6)?, (6, false));
+		assert!(!index.is_complete());
+		assert_eq!(index.backfill(6)?, (4, true));
+		assert!(index.is_complete());
+		assert_eq!(index.backfill(6)?, (0, true));
+
+		let ranges_before = ranges.load(Ordering::SeqCst);
+		for id in 0..10 {
+			assert_eq!(get_id(&index, &format!("name {id}"))?, Some(id));
+		}
+		assert_eq!(get_id(&index, "nobody")?, None);
+		assert_eq!(ranges.load(Ordering::SeqCst), ranges_before);
+		Ok(())
+	}
+}
@@ -0,0 +1,273 @@
+use bytemuck::{Pod, Zeroable};
+use cosmwasm_std::{StdError, StdResult};
+
+use crate::{assert_pod_layout, impl_serializable_as_ref};
+
+use super::{
+	base::{storage_read_item, storage_write_item},
+	map::StoredMap,
+	OZeroCopy, SerializableItem,
+};
+
+/// The sequence numbers of the first entry which hasn't been pruned, and of the next entry to be appended.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Zeroable, Pod)]
+#[repr(C)]
+#[allow(dead_code)]
+pub struct LogEnds {
+	pub first: u64,
+	pub next: u64,
+}
+impl_serializable_as_ref!(LogEnds);
+assert_pod_layout!(LogEnds, size = 16, align = 8, fields = [first: 0, next: 8]);
+
+/// An append-only sequence of entries, each identified by a sequence number which is never reused, even once the
+/// entry has been pruned.
+///
+/// Unlike a [StoredVecDeque](super::queue::StoredVecDeque), entries aren't popped by whoever reads them, so several
+/// readers can each keep their own position, see [ConsumerOffsets].
+pub struct StoredLog<V: SerializableItem> {
+	namespace: &'static [u8],
+	map: StoredMap<u64, V>,
+	ends: LogEnds,
+}
+impl<V: SerializableItem> StoredLog<V> {
+	pub fn new(namespace: &'static [u8]) -> StdResult<Self> {
+		Ok(Self {
+			namespace,
+			map: StoredMap::new(namespace),
+			ends: storage_read_item(namespace)?.map(|ends| *ends).unwrap_or_default(),
+		})
+	}
+
+	#[inline]
+	pub fn ends(&self) -> LogEnds {
+		self.ends
+	}
+
+	#[inline]
+	fn set_ends(&mut self, value: LogEnds) {
+		self.ends = value;
+		storage_write_item(self.namespace, &value).expect("2 u64's should never fail to serialize");
+	}
+
+	/// The amount of entries which haven't been pruned.
+	pub fn len(&self) -> u64 {
+		self.ends.next - self.ends.first
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ends.first == self.ends.next
+	}
+
+	/// Returns `None` if the entry hasn't been appended yet, or has been pruned.
+	pub fn get(&self, seq: u64) -> StdResult<Option<OZeroCopy<V>>> {
+		if seq < self.ends.first || seq >= self.ends.next {
+			return Ok(None);
+		}
+		self.map.get(&seq)
+	}
+
+	/// Appends the entry, returning its sequence number.
+	pub fn append(&mut self, value: &V) -> StdResult<u64> {
+		let mut ends = self.ends;
+		let seq = ends.next;
+		self.map.set(&seq, value)?;
+		ends.next += 1;
+		self.set_ends(ends);
+		Ok(seq)
+	}
+
+	/// Removes every entry with a sequence number lower than `seq`, returning the amount removed.
+	///
+	/// Use [ConsumerOffsets::min_committed_offset] to only prune the entries every consumer has read.
+	pub fn prune_before(&mut self, seq: u64) -> u64 {
+		let mut ends = self.ends;
+		let seq = seq.min(ends.next);
+		if seq <= ends.first {
+			return 0;
+		}
+		for pruned in ends.first..seq {
+			self.map.remove(&pruned);
+		}
+		let pruned = seq - ends.first;
+		ends.first = seq;
+		self.set_ends(ends);
+		pruned
+	}
+}
+
+/// The read position of several independent consumers of a [StoredLog], stored as `consumer name => next sequence`.
+///
+/// A consumer which hasn't committed anything yet starts at the first entry which hasn't been pruned, and doesn't
+/// hold back [min_committed_offset](Self::min_committed_offset).
+pub struct ConsumerOffsets {
+	offsets: StoredMap<String, u64>,
+}
+impl ConsumerOffsets {
+	pub fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			offsets: StoredMap::new(namespace),
+		}
+	}
+
+	/// The sequence number of the next entry the consumer will read.
+	pub fn offset<V: SerializableItem>(&self, log: &StoredLog<V>, consumer: &str) -> StdResult<u64> {
+		let committed = self.offsets.get(&consumer.to_string())?.map(|offset| *offset);
+		// Entries may have been pruned before the consumer first committed.
+		Ok(committed.unwrap_or_default().max(log.ends().first))
+	}
+
+	/// Returns up to `limit` entries following the consumer's offset, without moving it.
+	pub fn peek_without_commit<V: SerializableItem>(
+		&self,
+		log: &StoredLog<V>,
+		consumer: &str,
+		limit: u32,
+	) -> StdResult<Vec<(u64, OZeroCopy<V>)>> {
+		let start = self.offset(log, consumer)?;
+		let end = start.saturating_add(limit as u64).min(log.ends().next);
+		(start..end)
+			.map(|seq| {
+				let value = log
+					.get(seq)?
+					.ok_or_else(|| StdError::not_found("StoredLog entry within its ends"))?;
+				Ok((seq, value))
+			})
+			.collect()
+	}
+
+	/// Same as `peek_without_commit`, but the consumer's offset is moved past the returned entries.
+	pub fn read_next<V: SerializableItem>(
+		&self,
+		log: &StoredLog<V>,
+		consumer: &str,
+		limit: u32,
+	) -> StdResult<Vec<(u64, OZeroCopy<V>)>> {
+		let entries = self.peek_without_commit(log, consumer, limit)?;
+		if let Some((last_seq, _)) = entries.last() {
+			self.commit_offset(consumer, last_seq + 1)?;
+		}
+		Ok(entries)
+	}
+
+	/// Sets the sequence number of the next entry the consumer will read, e.g. after processing the entries returned
+	/// by `peek_without_commit`.
+	///
+	/// Returns an error if the offset would move backwards, as the entries may have been pruned since.
+	pub fn commit_offset(&self, consumer: &str, next_seq: u64) -> StdResult<()> {
+		let consumer = consumer.to_string();
+		if let Some(committed) = self.offsets.get(&consumer)? {
+			if next_seq < *committed {
+				return Err(StdError::generic_err(format!(
+					"Offset of consumer {consumer} can't move back from {} to {next_seq}",
+					*committed
+				)));
+			}
+		}
+		self.offsets.set(&consumer, &next_seq)
+	}
+
+	/// Stops tracking the consumer, so that it no longer holds back pruning.
+	pub fn remove_consumer(&self, consumer: &str) {
+		self.offsets.remove(&consumer.to_string());
+	}
+
+	/// The lowest offset committed by any consumer, entries before it have been read by everyone and can be pruned
+	/// with [StoredLog::prune_before]. Returns `None` if no consumer has committed an offset.
+	pub fn min_committed_offset(&self) -> StdResult<Option<u64>> {
+		Ok(self.offsets.iter()?.map(|(_, offset)| *offset).min())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	const OFFSETS_NAMESPACE: &[u8] = b"offsets";
+
+	fn seqs(entries: &[(u64, OZeroCopy<String>)]) -> Vec<u64> {
+		entries.iter().map(|(seq, _)| *seq).collect()
+	}
+
+	fn populated_log(count: u64) -> StdResult<StoredLog<String>> {
+		let mut log = StoredLog::new(NAMESPACE)?;
+		for i in 0..count {
+			assert_eq!(log.append(&format!("event {i}"))?, i);
+		}
+		Ok(log)
+	}
+
+	#[test]
+	fn independent_consumers() -> TestingResult {
+		let _storage = isolated()?;
+		let log = populated_log(5)?;
+		let offsets = ConsumerOffsets::new(OFFSETS_NAMESPACE);
+
+		let rewards = offsets.read_next(&log, "rewards", 3)?;
+		assert_eq!(seqs(&rewards), [0, 1, 2]);
+		assert_eq!(*rewards[1].1, "event 1");
+		assert_eq!(seqs(&offsets.read_next(&log, "stats", 1)?), [0]);
+		assert_eq!(seqs(&offsets.read_next(&log, "rewards", 3)?), [3, 4]);
+		assert_eq!(offsets.read_next(&log, "rewards", 3)?, []);
+		assert_eq!(seqs(&offsets.read_next(&log, "stats", 10)?), [1, 2, 3, 4]);
+
+		// The state is all in storage
+		let mut log = StoredLog::<String>::new(NAMESPACE)?;
+		assert_eq!(log.ends(), LogEnds { first: 0, next: 5 });
+		log.append(&"event 5".to_string())?;
+		let offsets = ConsumerOffsets::new(OFFSETS_NAMESPACE);
+		assert_eq!(seqs(&offsets.read_next(&log, "rewards", 3)?), [5]);
+		assert_eq!(seqs(&offsets.read_next(&log, "stats", 3)?), [5]);
+		Ok(())
+	}
+
+	#[test]
+	fn prune_below_slowest_consumer() -> TestingResult {
+		let _storage = isolated()?;
+		let mut log = populated_log(10)?;
+		let offsets = ConsumerOffsets::new(OFFSETS_NAMESPACE);
+		assert_eq!(offsets.min_committed_offset()?, None);
+
+		offsets.read_next(&log, "rewards", 7)?;
+		offsets.read_next(&log, "stats", 4)?;
+		assert_eq!(offsets.min_committed_offset()?, Some(4));
+		assert_eq!(log.prune_before(offsets.min_committed_offset()?.unwrap()), 4);
+		assert_eq!(log.len(), 6);
+		assert_eq!(log.get(3)?, None);
+		// Nothing the slowest consumer still needs is gone
+		assert_eq!(seqs(&offsets.read_next(&log, "stats", 10)?), [4, 5, 6, 7, 8, 9]);
+		assert_eq!(offsets.min_committed_offset()?, Some(7));
+
+		// New consumers start at the first remaining entry
+		assert_eq!(seqs(&offsets.peek_without_commit(&log, "audit", 2)?), [4, 5]);
+		offsets.remove_consumer("rewards");
+		assert_eq!(offsets.min_committed_offset()?, Some(10));
+		assert_eq!(log.prune_before(100), 6);
+		assert!(log.is_empty());
+		assert_eq!(log.append(&"event 10".to_string())?, 10);
+		Ok(())
+	}
+
+	#[test]
+	fn peek_without_commit() -> TestingResult {
+		let _storage = isolated()?;
+		let log = populated_log(4)?;
+		let offsets = ConsumerOffsets::new(OFFSETS_NAMESPACE);
+
+		let peeked = offsets.peek_without_commit(&log, "rewards", 2)?;
+		assert_eq!(seqs(&peeked), [0, 1]);
+		assert_eq!(offsets.min_committed_offset()?, None);
+		// Processing failed, so the same entries are returned again
+		assert_eq!(offsets.peek_without_commit(&log, "rewards", 2)?, peeked);
+		assert_eq!(offsets.read_next(&log, "rewards", 2)?, peeked);
+
+		let peeked = offsets.peek_without_commit(&log, "rewards", 2)?;
+		assert_eq!(seqs(&peeked), [2, 3]);
+		offsets.commit_offset("rewards", 3)?;
+		assert_eq!(seqs(&offsets.peek_without_commit(&log, "rewards", 2)?), [3]);
+		assert!(offsets.commit_offset("rewards", 1).is_err());
+		assert_eq!(offsets.offset(&log, "rewards")?, 3);
+		Ok(())
+	}
+}
@@ -1,16 +1,27 @@
-use cosmwasm_std::StdResult;
-use std::{marker::PhantomData, num::NonZeroUsize};
+use borsh::{BorshDeserialize, BorshSerialize};
+use cosmwasm_std::{StdError, StdResult};
+use std::{any::type_name, marker::PhantomData, num::NonZeroUsize, ops::Bound};
 
 use crate::utils::lexicographic_next;
 
 use super::{
-	base::{storage_has, storage_read, storage_read_item, storage_remove, storage_write, storage_write_item},
+	accounting::{accounting_not_enabled, Accounted, NamespaceAccounting, NamespaceUsage},
+	base::{
+		storage_has, storage_iter_new, storage_iter_next_key, storage_iter_next_pair, storage_read,
+		storage_read_namespaced_item, storage_remove, storage_write, storage_write_limited_item, with_storage_context,
+	},
 	concat_byte_array_pairs,
 	item::AutosavingSerializableItem,
-	OZeroCopy, SerializableItem, StorageKeyIterator, StoragePairIterator,
+	key_buf::KeyBuf,
+	stats::StoredStats,
+	value_size::{check_value_size, StorageWriteError},
+	write_batch::WriteBatch,
+	IteratorDirection, OZeroCopy, SerializableItem, StorageKeyIterator, StoragePairIterator,
 };
 pub struct StoredMap<K: SerializableItem, V: SerializableItem> {
 	namespace: &'static [u8],
+	accounted: bool,
+	max_value_size: Option<usize>,
 	key_type: PhantomData<K>,
 	value_type: PhantomData<V>,
 }
@@ -19,11 +30,34 @@ impl<'exec, K: SerializableItem, V: SerializableItem> StoredMap<K, V> {
 	pub fn new(namespace: &'static [u8]) -> Self {
 		Self {
 			namespace,
+			accounted: false,
+			max_value_size: None,
 			key_type: PhantomData,
 			value_type: PhantomData,
 		}
 	}
 
+	/// Keeps track of the amount of entries and their size through [NamespaceAccounting], which costs an extra read
+	/// and write on every `set` and `remove`. Enabling this on a map which already has entries requires adding them
+	/// to the usage with [NamespaceAccounting::record] first.
+	pub fn with_accounting(mut self) -> Self {
+		self.accounted = true;
+		self
+	}
+
+	/// Values larger than `max_size` bytes are rejected with [ValueTooLarge](super::value_size::ValueTooLarge) by
+	/// `set` and its variants, regardless of the limit set with
+	/// [set_max_value_size](super::value_size::set_max_value_size). Use [try_set](Self::try_set) to match on it.
+	pub fn with_max_value_size(mut self, max_size: usize) -> Self {
+		self.max_value_size = Some(max_size);
+		self
+	}
+
+	#[inline]
+	pub fn namespace(&self) -> &'static [u8] {
+		self.namespace
+	}
+
 	#[inline]
 	pub fn key(&self, key: &K) -> Vec<u8> {
 		if let Some(key_bytes) = key.serialize_as_ref() {
@@ -40,18 +74,86 @@ impl<'exec, K: SerializableItem, V: SerializableItem> StoredMap<K, V> {
 		}
 	}
 
+	/// Same as `key`, but writes the storage key into `buf` (clearing it first) so its allocation can be re-used
+	/// across many accesses.
+	pub fn key_into(&self, key: &K, buf: &mut Vec<u8>) {
+		buf.clear();
+		buf.extend_from_slice(self.namespace);
+		if let Some(key_bytes) = key.serialize_as_ref() {
+			buf.extend_from_slice(key_bytes);
+		} else {
+			buf.extend_from_slice(&key.serialize_to_owned().expect("key serialization should never fail"));
+		}
+	}
+
+	/// Same as `key`, but doesn't allocate if the storage key is at most [KEY_BUF_INLINE_LEN](super::key_buf::KEY_BUF_INLINE_LEN) bytes long and the key
+	/// type can be serialized by reference.
+	#[inline]
+	pub fn key_buf(&self, key: &K) -> KeyBuf {
+		if let Some(key_bytes) = key.serialize_as_ref() {
+			KeyBuf::from_parts(self.namespace, key_bytes)
+		} else {
+			KeyBuf::from_parts(
+				self.namespace,
+				&key.serialize_to_owned().expect("key serialization should never fail"),
+			)
+		}
+	}
+
 	#[inline]
 	pub fn get_raw_bytes(&self, key: &K) -> Option<Vec<u8>> {
-		storage_read(&self.key(key))
+		storage_read(&self.key_buf(key))
 	}
 
 	#[inline]
-	pub(crate) fn set_raw_bytes(&self, key: &K, bytes: &[u8]) {
-		storage_write(&self.key(key), bytes)
+	pub(crate) fn set_raw_bytes(&self, key: &K, bytes: &[u8]) -> StdResult<()> {
+		let key = self.key_buf(key);
+		if self.accounted {
+			return self.set_raw_bytes_accounted(&key, bytes);
+		}
+		storage_write(&key, bytes);
+		Ok(())
+	}
+
+	/// Records a write to the storage key in the usage, only called if accounting is enabled.
+	fn account(&self, storage_key: &[u8], new_len: Option<usize>) -> StdResult<()> {
+		NamespaceAccounting::new(self.namespace).record_write(storage_key, new_len)
+	}
+
+	fn set_accounted(&self, storage_key: &[u8], value: &V) -> Result<(), StorageWriteError> {
+		match value.serialize_as_ref() {
+			Some(bytes) => {
+				check_value_size(self.namespace, bytes.len(), self.max_value_size)?;
+				self.set_raw_bytes_accounted(storage_key, bytes)?;
+			}
+			None => {
+				let bytes = value.serialize_to_owned()?;
+				check_value_size(self.namespace, bytes.len(), self.max_value_size)?;
+				self.set_raw_bytes_accounted(storage_key, &bytes)?;
+			}
+		}
+		Ok(())
+	}
+
+	fn set_raw_bytes_accounted(&self, storage_key: &[u8], bytes: &[u8]) -> StdResult<()> {
+		self.account(storage_key, Some(bytes.len()))?;
+		storage_write(storage_key, bytes);
+		Ok(())
+	}
+
+	/// Attaches this map's namespace and the specified key to an error returned while reading its value.
+	pub(crate) fn with_storage_context(&self, err: StdError, key: &K) -> StdError {
+		with_storage_context(err, self.namespace, &self.key_buf(key)[self.namespace.len()..])
 	}
 
 	pub fn get(&self, key: &K) -> StdResult<Option<OZeroCopy<V>>> {
-		storage_read_item(&self.key(key))
+		storage_read_namespaced_item(&self.key_buf(key), self.namespace.len())
+	}
+
+	/// Same as `get`, but uses `buf` for building the storage key. See `key_into`.
+	pub fn get_with_buf(&self, key: &K, buf: &mut Vec<u8>) -> StdResult<Option<OZeroCopy<V>>> {
+		self.key_into(key, buf);
+		storage_read_namespaced_item(buf, self.namespace.len())
 	}
 
 	pub fn get_autosaving(&self, key: &K) -> StdResult<Option<AutosavingSerializableItem<V>>> {
@@ -67,15 +169,137 @@ impl<'exec, K: SerializableItem, V: SerializableItem> StoredMap<K, V> {
 
 	/// At the time of writing, the cosmwasm API cannot actually facilitate this, you should probably match on get()
 	pub fn has(&self, key: &K) -> bool {
-		storage_has(&self.key(key))
+		storage_has(&self.key_buf(key))
 	}
 
 	pub fn set(&self, key: &K, value: &V) -> StdResult<()> {
-		storage_write_item(&self.key(key), value)
+		Ok(self.try_set(key, value)?)
+	}
+
+	/// Same as `set`, but returns a [StorageWriteError] which can be matched on to tell a value which is too large
+	/// apart from other errors.
+	pub fn try_set(&self, key: &K, value: &V) -> Result<(), StorageWriteError> {
+		if self.accounted {
+			return self.set_accounted(&self.key_buf(key), value);
+		}
+		storage_write_limited_item(&self.key_buf(key), self.namespace.len(), value, self.max_value_size)
 	}
 
+	/// Same as `set`, but uses `buf` for building the storage key. See `key_into`.
+	pub fn set_with_buf(&self, key: &K, value: &V, buf: &mut Vec<u8>) -> StdResult<()> {
+		self.key_into(key, buf);
+		if self.accounted {
+			return Ok(self.set_accounted(buf, value)?);
+		}
+		Ok(storage_write_limited_item(
+			buf,
+			self.namespace.len(),
+			value,
+			self.max_value_size,
+		)?)
+	}
+
+	/// Removes the entry.
+	///
+	/// # Panics
+	///
+	/// If accounting is enabled and the usage stored for the namespace is corrupted, use
+	/// [try_remove](Self::try_remove) to get an error instead.
 	pub fn remove(&self, key: &K) {
-		storage_remove(&self.key(key))
+		self.try_remove(key).expect("accounting should be readable");
+	}
+
+	/// Same as `remove`, but returns an error if accounting is enabled and the usage can't be read.
+	pub fn try_remove(&self, key: &K) -> StdResult<()> {
+		self.remove_storage_key(&self.key_buf(key))
+	}
+
+	/// Same as `remove`, but uses `buf` for building the storage key. See `key_into`.
+	pub fn remove_with_buf(&self, key: &K, buf: &mut Vec<u8>) {
+		self.key_into(key, buf);
+		self.remove_storage_key(buf).expect("accounting should be readable");
+	}
+
+	fn remove_storage_key(&self, storage_key: &[u8]) -> StdResult<()> {
+		if self.accounted {
+			self.account(storage_key, None)?;
+		}
+		storage_remove(storage_key);
+		Ok(())
+	}
+
+	/// Reads the value, passes it to `f`, and writes the value `f` returns, or removes the entry if it returns `None`.
+	/// Returns the new value.
+	///
+	/// The value is passed as read from storage, so `Pod` values can be modified in place without being copied.
+	/// Nothing is written if `f` returns an error.
+	pub fn update(
+		&self,
+		key: &K,
+		f: impl FnOnce(Option<OZeroCopy<V>>) -> StdResult<Option<OZeroCopy<V>>>,
+	) -> StdResult<Option<OZeroCopy<V>>> {
+		let new_value = f(self.get(key)?)?;
+		match &new_value {
+			Some(value) => self.set(key, value)?,
+			None => self.remove(key),
+		}
+		Ok(new_value)
+	}
+
+	/// Same as [update](Self::update), but `f` modifies the value, which is the default if there was none. The value is
+	/// always written.
+	pub fn update_or_default(&self, key: &K, f: impl FnOnce(&mut V) -> StdResult<()>) -> StdResult<OZeroCopy<V>>
+	where
+		V: Default,
+	{
+		let mut value = self.get(key)?.unwrap_or_default();
+		f(&mut value)?;
+		self.set(key, &value)?;
+		Ok(value)
+	}
+
+	/// Same as `set`, but the write is added to `batch` instead of being applied immediately.
+	pub fn set_batched(&self, batch: &mut WriteBatch, key: &K, value: &V) -> StdResult<()> {
+		let bytes = match value.serialize_as_ref() {
+			Some(bytes) => bytes.to_vec(),
+			None => value.serialize_to_owned()?,
+		};
+		check_value_size(self.namespace, bytes.len(), self.max_value_size)?;
+		let key = self.key(key);
+		if self.accounted {
+			NamespaceAccounting::new(self.namespace).record_batched(batch, &key, Some(bytes.len()))?;
+		}
+		batch.set_raw(key, bytes);
+		Ok(())
+	}
+
+	/// Same as `remove`, but the removal is added to `batch` instead of being applied immediately.
+	pub fn remove_batched(&self, batch: &mut WriteBatch, key: &K) -> StdResult<()> {
+		let key = self.key(key);
+		if self.accounted {
+			NamespaceAccounting::new(self.namespace).record_batched(batch, &key, None)?;
+		}
+		batch.remove_raw(key);
+		Ok(())
+	}
+
+	/// Same as `set`, but also records the insertion or update in `stats`. `f` returns the value to aggregate.
+	pub fn set_tracked(&self, key: &K, value: &V, stats: &mut StoredStats, f: impl Fn(&V) -> u128) -> StdResult<()> {
+		match self.get(key)? {
+			Some(old_value) => stats.record_update(f(&old_value), f(value))?,
+			None => stats.record_insert(f(value))?,
+		}
+		self.set(key, value)
+	}
+
+	/// Same as `remove`, but also records the removal in `stats` if the entry existed. `f` returns the value to
+	/// aggregate.
+	pub fn remove_tracked(&self, key: &K, stats: &mut StoredStats, f: impl Fn(&V) -> u128) -> StdResult<()> {
+		if let Some(old_value) = self.get(key)? {
+			stats.record_remove(f(&old_value))?;
+			self.remove(key);
+		}
+		Ok(())
 	}
 
 	/// Returns an iterator which iterates over all key/value pairs of the map
@@ -109,6 +333,295 @@ impl<'exec, K: SerializableItem, V: SerializableItem> StoredMap<K, V> {
 	pub fn iter_range_keys(&self, after: Option<K>, before: Option<K>) -> StdResult<StoredMapKeyIter<K>> {
 		StoredMapKeyIter::new(self.namespace, (), after, before)
 	}
+
+	/// Removes every entry of the map, returning how many there were.
+	///
+	/// Each entry costs a storage write, so large maps may need to be cleared over multiple calls using
+	/// [remove_range](Self::remove_range).
+	///
+	/// **Note:** this removes every storage key starting with the namespace, see [remove_range](Self::remove_range).
+	pub fn clear(&self) -> StdResult<u32> {
+		self.remove_range(None, None)
+	}
+
+	/// Removes the entries within the same range [iter_range](Self::iter_range) would iterate over, i.e. `after` is
+	/// inclusive while `before` is exclusive. Returns how many entries were removed.
+	///
+	/// The keys are never deserialized, so this also removes entries whose key isn't a valid `K`.
+	///
+	/// **Note:** the range is scanned by storage key, which also covers any other namespace starting with this map's
+	/// namespace, e.g. clearing a map at `b"pool"` removes everything stored under `b"pool_fee"` as well. Namespaces
+	/// must therefore never be prefixes of each other.
+	pub fn remove_range(&self, after: Option<K>, before: Option<K>) -> StdResult<u32> {
+		let (start, end, _) = prefixed_key_range_to_byte_prefixes(self.namespace, (), after, before)?;
+		// The keys are collected first, as the storage may not be modified while it's being iterated over.
+		let keys: Vec<_> = StorageKeyIterator::new(Some(&start), Some(&end)).collect();
+		for key in keys.iter() {
+			self.remove_storage_key(key)?;
+		}
+		Ok(keys.len() as u32)
+	}
+
+	/// Returns the `[start, end)` storage key range of the entries whose key starts with the prefix.
+	fn prefix_range<P: SerializableItem>(&self, prefix: &P) -> StdResult<(Vec<u8>, Vec<u8>)> {
+		let start = match prefix.serialize_as_ref() {
+			Some(prefix_bytes) => concat_byte_array_pairs(self.namespace, prefix_bytes),
+			None => concat_byte_array_pairs(self.namespace, &prefix.serialize_to_owned()?),
+		};
+		let end = lexicographic_next(&start);
+		Ok((start, end))
+	}
+
+	fn edge_entry<P: SerializableItem>(
+		&self,
+		prefix: &P,
+		direction: IteratorDirection,
+	) -> StdResult<Option<(K, OZeroCopy<V>)>> {
+		let (start, end) = self.prefix_range(prefix)?;
+		let Some((key_bytes, value_bytes)) =
+			storage_iter_next_pair(storage_iter_new(Some(&start), Some(&end), direction))
+		else {
+			return Ok(None);
+		};
+		let key_bytes = &key_bytes[self.namespace.len()..];
+		let parse = || Ok((K::deserialize_to_owned(key_bytes)?, OZeroCopy::new(value_bytes)?));
+		parse()
+			.map(Some)
+			.map_err(|err| with_storage_context(err, self.namespace, key_bytes))
+	}
+
+	fn edge_key<P: SerializableItem>(&self, prefix: &P, direction: IteratorDirection) -> StdResult<Option<K>> {
+		let (start, end) = self.prefix_range(prefix)?;
+		let Some(key_bytes) = storage_iter_next_key(storage_iter_new(Some(&start), Some(&end), direction)) else {
+			return Ok(None);
+		};
+		let key_bytes = &key_bytes[self.namespace.len()..];
+		K::deserialize_to_owned(key_bytes)
+			.map(Some)
+			.map_err(|err| with_storage_context(err, self.namespace, key_bytes))
+	}
+
+	/// Returns the entry with the smallest key. This only reads a single entry from storage.
+	pub fn first(&self) -> StdResult<Option<(K, OZeroCopy<V>)>> {
+		self.edge_entry(&(), IteratorDirection::Ascending)
+	}
+
+	/// Returns the entry with the largest key. This only reads a single entry from storage.
+	pub fn last(&self) -> StdResult<Option<(K, OZeroCopy<V>)>> {
+		self.edge_entry(&(), IteratorDirection::Descending)
+	}
+
+	/// Returns the smallest key without reading any values.
+	pub fn first_key(&self) -> StdResult<Option<K>> {
+		self.edge_key(&(), IteratorDirection::Ascending)
+	}
+
+	/// Returns the largest key without reading any values.
+	pub fn last_key(&self) -> StdResult<Option<K>> {
+		self.edge_key(&(), IteratorDirection::Descending)
+	}
+
+	/// Returns the entry with the smallest key which starts with the prefix, e.g. the first element of a tuple key.
+	pub fn first_in_prefix<P: SerializableItem>(&self, prefix: &P) -> StdResult<Option<(K, OZeroCopy<V>)>> {
+		self.edge_entry(prefix, IteratorDirection::Ascending)
+	}
+
+	/// Returns the entry with the largest key which starts with the prefix, e.g. the first element of a tuple key.
+	pub fn last_in_prefix<P: SerializableItem>(&self, prefix: &P) -> StdResult<Option<(K, OZeroCopy<V>)>> {
+		self.edge_entry(prefix, IteratorDirection::Descending)
+	}
+
+	/// Returns the first key/value pair (in ascending key order) which matches the predicate.
+	///
+	/// This is a linear scan over the entire map, only use this on maps which have a bounded size. Consider using
+	/// `find_bounded` to make that bound explicit.
+	pub fn find(&self, f: impl FnMut(&K, &V) -> bool) -> StdResult<Option<(K, OZeroCopy<V>)>> {
+		self.find_bounded(usize::MAX, f)
+	}
+
+	/// Same as `find`, but returns an error if more than `max_scanned` entries would have to be looked at in order to
+	/// determine the result.
+	pub fn find_bounded(
+		&self,
+		max_scanned: usize,
+		mut f: impl FnMut(&K, &V) -> bool,
+	) -> StdResult<Option<(K, OZeroCopy<V>)>> {
+		for (scanned, (key, value)) in self.iter()?.enumerate() {
+			if scanned == max_scanned {
+				return Err(StdError::generic_err(format!(
+					"StoredMap scan exceeded the limit of {} entries",
+					max_scanned
+				)));
+			}
+			if f(&key, &value) {
+				return Ok(Some((key, value)));
+			}
+		}
+		Ok(None)
+	}
+
+	/// Returns true if any key/value pair matches the predicate. This is a linear scan, see `find`.
+	pub fn any(&self, f: impl FnMut(&K, &V) -> bool) -> StdResult<bool> {
+		self.any_bounded(usize::MAX, f)
+	}
+
+	/// Same as `any`, but errors if more than `max_scanned` entries would have to be looked at.
+	pub fn any_bounded(&self, max_scanned: usize, f: impl FnMut(&K, &V) -> bool) -> StdResult<bool> {
+		Ok(self.find_bounded(max_scanned, f)?.is_some())
+	}
+
+	/// Returns true if all key/value pairs match the predicate, or if the map is empty. This is a linear scan, see
+	/// `find`.
+	pub fn all(&self, f: impl FnMut(&K, &V) -> bool) -> StdResult<bool> {
+		self.all_bounded(usize::MAX, f)
+	}
+
+	/// Same as `all`, but errors if more than `max_scanned` entries would have to be looked at.
+	pub fn all_bounded(&self, max_scanned: usize, mut f: impl FnMut(&K, &V) -> bool) -> StdResult<bool> {
+		Ok(self.find_bounded(max_scanned, |k, v| !f(k, v))?.is_none())
+	}
+
+	/// Returns true if any entry has the specified value. This is a linear scan, see `find`.
+	pub fn contains_value(&self, value: &V) -> StdResult<bool>
+	where
+		V: PartialEq,
+	{
+		self.any(|_, v| v == value)
+	}
+}
+
+impl<K: SerializableItem, V: SerializableItem> Accounted for StoredMap<K, V> {
+	fn accounting(&self) -> StdResult<NamespaceUsage> {
+		if !self.accounted {
+			return Err(accounting_not_enabled());
+		}
+		NamespaceAccounting::new(self.namespace).usage()
+	}
+}
+
+/// Range queries over the components of 2-tuple keys, e.g. `(price, seq)` keys of an orderbook.
+///
+/// Tuple keys are stored as the concatenated borsh encodings of their components, so the bounds are built from those.
+/// As with any other key, the components only sort meaningfully if their encoding does, e.g. big-endian byte arrays.
+impl<K1, K2, V> StoredMap<(K1, K2), V>
+where
+	K1: BorshSerialize + BorshDeserialize,
+	K2: BorshSerialize + BorshDeserialize,
+	V: SerializableItem,
+{
+	fn with_component<T: BorshSerialize>(prefix: &[u8], component: &T) -> StdResult<Vec<u8>> {
+		let mut bytes = prefix.to_vec();
+		component
+			.serialize(&mut bytes)
+			.map_err(|err| StdError::serialize_err(type_name::<T>(), err))?;
+		Ok(bytes)
+	}
+
+	/// Iterates over all entries whose first key component is within the bounds, ordered by the full key.
+	pub fn iter_first_component_range(&self, lo: Bound<K1>, hi: Bound<K1>) -> StdResult<StoredMapIter<(K1, K2), V>> {
+		let start = match &lo {
+			Bound::Included(first) => Self::with_component(self.namespace, first)?,
+			Bound::Excluded(first) => lexicographic_next(&Self::with_component(self.namespace, first)?),
+			Bound::Unbounded => self.namespace.to_vec(),
+		};
+		let end = match &hi {
+			Bound::Included(first) => lexicographic_next(&Self::with_component(self.namespace, first)?),
+			Bound::Excluded(first) => Self::with_component(self.namespace, first)?,
+			Bound::Unbounded => lexicographic_next(self.namespace),
+		};
+		Ok(StoredMapIter::new_raw(&start, &end, self.namespace.len()))
+	}
+
+	/// Iterates over the entries whose first key component is `first` and second key component is within the bounds.
+	pub fn iter_within(&self, first: &K1, lo: Bound<K2>, hi: Bound<K2>) -> StdResult<StoredMapIter<(K1, K2), V>> {
+		let prefix = Self::with_component(self.namespace, first)?;
+		let start = match &lo {
+			Bound::Included(second) => Self::with_component(&prefix, second)?,
+			// The smallest key which comes after `second`
+			Bound::Excluded(second) => concat_byte_array_pairs(&Self::with_component(&prefix, second)?, &[0]),
+			Bound::Unbounded => prefix.clone(),
+		};
+		let end = match &hi {
+			Bound::Included(second) => concat_byte_array_pairs(&Self::with_component(&prefix, second)?, &[0]),
+			Bound::Excluded(second) => Self::with_component(&prefix, second)?,
+			Bound::Unbounded => lexicographic_next(&prefix),
+		};
+		Ok(StoredMapIter::new_raw(&start, &end, self.namespace.len()))
+	}
+
+	/// Iterates over the entries whose first key component is `first`, yielding only the second component as the key.
+	///
+	/// The second component is parsed using its `SerializableItem` implementation, which must match its borsh encoding
+	/// as that's what the tuple key is stored as. This is the case for primitives, `String`, and tuples.
+	pub fn iter_prefix(&self, first: &K1) -> StdResult<StoredMapIter<K2, V>>
+	where
+		K2: SerializableItem,
+	{
+		self.iter_prefix_range(first, None, None)
+	}
+
+	/// Same as [iter_prefix](Self::iter_prefix), but only over the second key components within the same range
+	/// [iter_range](Self::iter_range) would iterate over, i.e. `after` is inclusive while `before` is exclusive.
+	pub fn iter_prefix_range(
+		&self,
+		first: &K1,
+		after: Option<K2>,
+		before: Option<K2>,
+	) -> StdResult<StoredMapIter<K2, V>>
+	where
+		K2: SerializableItem,
+	{
+		let prefix = Self::with_component(self.namespace, first)?;
+		let start = match &after {
+			Some(second) => Self::with_component(&prefix, second)?,
+			None => prefix.clone(),
+		};
+		let end = match &before {
+			Some(second) => Self::with_component(&prefix, second)?,
+			None => lexicographic_next(&prefix),
+		};
+		Ok(StoredMapIter::new_raw(&start, &end, prefix.len()))
+	}
+
+	/// Iterates over each distinct first key component once, in ascending order, e.g. the price levels of an
+	/// orderbook.
+	///
+	/// After each value found, this skips ahead to the next first component, so the amount of storage reads is
+	/// proportional to the amount of distinct values rather than the amount of entries.
+	pub fn iter_distinct_first(&self) -> StoredMapDistinctFirstIter<K1> {
+		StoredMapDistinctFirstIter {
+			namespace_len: self.namespace.len(),
+			start: self.namespace.to_vec(),
+			end: lexicographic_next(self.namespace),
+			key_type: PhantomData,
+		}
+	}
+}
+
+/// Iterates over the distinct first key components of a map with 2-tuple keys, see
+/// [StoredMap::iter_distinct_first].
+pub struct StoredMapDistinctFirstIter<K1: BorshDeserialize> {
+	namespace_len: usize,
+	start: Vec<u8>,
+	end: Vec<u8>,
+	key_type: PhantomData<K1>,
+}
+impl<K1: BorshDeserialize> Iterator for StoredMapDistinctFirstIter<K1> {
+	type Item = K1;
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.start >= self.end {
+			return None;
+		}
+		let key = storage_iter_next_key(storage_iter_new(
+			Some(&self.start),
+			Some(&self.end),
+			IteratorDirection::Ascending,
+		))?;
+		let mut remaining = &key[self.namespace_len..];
+		let first = K1::deserialize(&mut remaining).ok()?;
+		self.start = lexicographic_next(&key[..key.len() - remaining.len()]);
+		Some(first)
+	}
 }
 
 /// Allows you to iterate over a stored map.
@@ -139,6 +652,18 @@ impl<'a, K: SerializableItem, V: SerializableItem> StoredMapIter<K, V> {
 			value_type: PhantomData,
 		})
 	}
+	/// Iterates over the storage keys from `start` (inclusive) to `end` (exclusive), parsing `K` from the bytes after
+	/// the first `key_slicing` bytes of each storage key.
+	///
+	/// See [SeiCanonicalAddr::addr_range_bounds](crate::data_types::canonical_addr::SeiCanonicalAddr::addr_range_bounds)
+	pub fn new_raw(start: &[u8], end: &[u8], key_slicing: usize) -> Self {
+		Self {
+			inner_iter: StoragePairIterator::new(Some(start), Some(end)),
+			key_slicing,
+			key_type: PhantomData,
+			value_type: PhantomData,
+		}
+	}
 	fn advance_by(&mut self, n: usize) -> Result<(), NonZeroUsize> {
 		self.inner_iter.0.advance_by(n)
 	}
@@ -173,11 +698,131 @@ impl<'a, K: SerializableItem, V: SerializableItem> DoubleEndedIterator for Store
 	}
 	fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
 		self.advance_back_by(n).ok()?;
-		self.next()
+		self.next_back()
 	}
 	// TODO: impl advance_by when stable
 }
 
+impl<K: SerializableItem, V: SerializableItem> StoredMapIter<K, V> {
+	/// Looks up an entry of `other` for each entry of this iterator, using the key returned by `f`.
+	///
+	/// Yields `(key, value, other_value)`, where `other_value` is `None` if `other` doesn't have the entry. Like this
+	/// iterator, iteration stops at the first entry which can't be read.
+	pub fn join<K2, V2, F>(self, other: &StoredMap<K2, V2>, f: F) -> StoredMapJoin<'_, K, V, K2, V2, F>
+	where
+		K2: SerializableItem,
+		V2: SerializableItem,
+		F: Fn(&K, &V) -> K2,
+	{
+		StoredMapJoin {
+			left: self,
+			right: other,
+			key_fn: f,
+		}
+	}
+
+	/// Same as `join`, but skips the entries which don't have a matching entry in `other`.
+	pub fn join_filter<K2, V2, F>(self, other: &StoredMap<K2, V2>, f: F) -> StoredMapJoinFilter<'_, K, V, K2, V2, F>
+	where
+		K2: SerializableItem,
+		V2: SerializableItem,
+		F: Fn(&K, &V) -> K2,
+	{
+		StoredMapJoinFilter(self.join(other, f))
+	}
+}
+
+/// Iterator returned by [StoredMapIter::join]
+pub struct StoredMapJoin<'a, K: SerializableItem, V: SerializableItem, K2: SerializableItem, V2: SerializableItem, F> {
+	left: StoredMapIter<K, V>,
+	right: &'a StoredMap<K2, V2>,
+	key_fn: F,
+}
+impl<K, V, K2, V2, F> StoredMapJoin<'_, K, V, K2, V2, F>
+where
+	K: SerializableItem,
+	V: SerializableItem,
+	K2: SerializableItem,
+	V2: SerializableItem,
+	F: Fn(&K, &V) -> K2,
+{
+	#[allow(clippy::type_complexity)]
+	fn join_entry(&self, (key, value): (K, OZeroCopy<V>)) -> Option<(K, OZeroCopy<V>, Option<OZeroCopy<V2>>)> {
+		let right_value = self.right.get(&(self.key_fn)(&key, &value)).ok()?;
+		Some((key, value, right_value))
+	}
+}
+impl<K, V, K2, V2, F> Iterator for StoredMapJoin<'_, K, V, K2, V2, F>
+where
+	K: SerializableItem,
+	V: SerializableItem,
+	K2: SerializableItem,
+	V2: SerializableItem,
+	F: Fn(&K, &V) -> K2,
+{
+	type Item = (K, OZeroCopy<V>, Option<OZeroCopy<V2>>);
+	fn next(&mut self) -> Option<Self::Item> {
+		let entry = self.left.next()?;
+		self.join_entry(entry)
+	}
+}
+impl<K, V, K2, V2, F> DoubleEndedIterator for StoredMapJoin<'_, K, V, K2, V2, F>
+where
+	K: SerializableItem,
+	V: SerializableItem,
+	K2: SerializableItem,
+	V2: SerializableItem,
+	F: Fn(&K, &V) -> K2,
+{
+	fn next_back(&mut self) -> Option<Self::Item> {
+		let entry = self.left.next_back()?;
+		self.join_entry(entry)
+	}
+}
+
+/// Iterator returned by [StoredMapIter::join_filter]
+pub struct StoredMapJoinFilter<
+	'a,
+	K: SerializableItem,
+	V: SerializableItem,
+	K2: SerializableItem,
+	V2: SerializableItem,
+	F,
+>(StoredMapJoin<'a, K, V, K2, V2, F>);
+impl<K, V, K2, V2, F> Iterator for StoredMapJoinFilter<'_, K, V, K2, V2, F>
+where
+	K: SerializableItem,
+	V: SerializableItem,
+	K2: SerializableItem,
+	V2: SerializableItem,
+	F: Fn(&K, &V) -> K2,
+{
+	type Item = (K, OZeroCopy<V>, OZeroCopy<V2>);
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let (key, value, Some(right_value)) = self.0.next()? {
+				return Some((key, value, right_value));
+			}
+		}
+	}
+}
+impl<K, V, K2, V2, F> DoubleEndedIterator for StoredMapJoinFilter<'_, K, V, K2, V2, F>
+where
+	K: SerializableItem,
+	V: SerializableItem,
+	K2: SerializableItem,
+	V2: SerializableItem,
+	F: Fn(&K, &V) -> K2,
+{
+	fn next_back(&mut self) -> Option<Self::Item> {
+		loop {
+			if let (key, value, Some(right_value)) = self.0.next_back()? {
+				return Some((key, value, right_value));
+			}
+		}
+	}
+}
+
 /// Allows you to iterate the keys over a stored map.
 ///
 /// If your key type for your stored map is a tuple, i.e. `(T1, T2, T3)`, you can set `K` to `(T2, T3)` while providing
@@ -202,6 +847,14 @@ impl<'a, K: SerializableItem> StoredMapKeyIter<K> {
 			key_type: PhantomData,
 		})
 	}
+	/// Same as [StoredMapIter::new_raw], but only iterates over the keys.
+	pub fn new_raw(start: &[u8], end: &[u8], key_slicing: usize) -> Self {
+		Self {
+			inner_iter: StorageKeyIterator::new(Some(start), Some(end)),
+			key_slicing,
+			key_type: PhantomData,
+		}
+	}
 	fn advance_by(&mut self, n: usize) -> Result<(), NonZeroUsize> {
 		self.inner_iter.0.advance_by(n)
 	}
@@ -230,7 +883,7 @@ impl<'a, K: SerializableItem> DoubleEndedIterator for StoredMapKeyIter<K> {
 	}
 	fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
 		self.advance_back_by(n).ok()?;
-		self.next()
+		self.next_back()
 	}
 	// TODO: impl advance_by when stable
 }
@@ -273,7 +926,7 @@ where
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use crate::storage::testing_common::*;
+	use crate::storage::{key_buf::KEY_BUF_INLINE_LEN, testing_common::*};
 
 	#[test]
 	fn stored_empty_map_iter() {
@@ -457,7 +1110,7 @@ mod tests {
 		let key = b"key1";
 		let value = b"val1";
 
-		stored_map.set_raw_bytes(key, value);
+		stored_map.set_raw_bytes(key, value)?;
 		assert_eq!(
 			stored_map.get(key).unwrap(),
 			Some(OZeroCopy::from_inner(value.to_owned()))
@@ -467,6 +1120,82 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn iter_prefix() -> TestingResult {
+		let _storage = isolated()?;
+		let map = StoredMap::<(String, u32), u64>::new(NAMESPACE);
+		for (name, ids) in [("alice", [1, 2, 3]), ("alicea", [4, 5, 6]), ("bob", [1, 7, 8])] {
+			for id in ids {
+				map.set(&(name.to_string(), id), &(id as u64 * 100))?;
+			}
+		}
+		let alice = "alice".to_string();
+		let entries = |iter: &mut dyn Iterator<Item = (u32, OZeroCopy<u64>)>| {
+			iter.map(|(id, value)| (id, value.into_inner())).collect::<Vec<_>>()
+		};
+		// The strings are length-prefixed, so "alicea" doesn't start with "alice"
+		assert_eq!(entries(&mut map.iter_prefix(&alice)?), [(1, 100), (2, 200), (3, 300)]);
+		assert_eq!(
+			entries(&mut map.iter_prefix(&alice)?.rev()),
+			[(3, 300), (2, 200), (1, 100)]
+		);
+		assert_eq!(entries(&mut map.iter_prefix(&"bob".to_string())?.rev()).len(), 3);
+		assert_eq!(map.iter_prefix(&"carol".to_string())?.count(), 0);
+		assert_eq!(
+			map.iter_prefix_range(&alice, Some(2), None)?
+				.map(|(id, _)| id)
+				.collect::<Vec<_>>(),
+			[2, 3]
+		);
+		assert_eq!(
+			map.iter_prefix_range(&alice, None, Some(3))?
+				.rev()
+				.map(|(id, _)| id)
+				.collect::<Vec<_>>(),
+			[2, 1]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn clear() -> TestingResult {
+		let _storage = isolated()?;
+		let map = StoredMap::<String, u32>::new(NAMESPACE);
+		assert_eq!(map.clear()?, 0);
+
+		let other = StoredMap::<String, u32>::new(b"other");
+		for (i, key) in ["a", "b", "c"].into_iter().enumerate() {
+			map.set(&key.to_string(), &(i as u32))?;
+			other.set(&key.to_string(), &(i as u32))?;
+		}
+		assert_eq!(map.clear()?, 3);
+		assert_eq!(map.iter()?.count(), 0);
+		assert_eq!(map.clear()?, 0);
+		assert_eq!(other.iter()?.count(), 3);
+		Ok(())
+	}
+
+	#[test]
+	fn remove_range() -> TestingResult {
+		let _storage = isolated()?;
+		let map = StoredMap::<(u8, u8), u32>::new(NAMESPACE).with_accounting();
+		for first in 1..=3u8 {
+			for second in 1..=3u8 {
+				map.set(&(first, second), &(first as u32 * 10 + second as u32))?;
+			}
+		}
+		assert_eq!(map.remove_range(Some((1, 2)), Some((2, 2)))?, 3);
+		assert_eq!(
+			map.iter_keys()?.collect::<Vec<_>>(),
+			[(1, 1), (2, 2), (2, 3), (3, 1), (3, 2), (3, 3)]
+		);
+		assert_eq!(map.remove_range(Some((3, 1)), None)?, 3);
+		assert_eq!(map.remove_range(None, Some((2, 2)))?, 1);
+		assert_eq!(map.iter_keys()?.collect::<Vec<_>>(), [(2, 2), (2, 3)]);
+		assert_eq!(map.accounting()?.entries, 2);
+		Ok(())
+	}
+
 	#[test]
 	fn autosaving() -> TestingResult {
 		let _storage_lock = init()?;
@@ -489,12 +1218,358 @@ mod tests {
 		drop(v2);
 
 		assert!(storage_has(&stored_map.key(&key)));
-		// XXX: expected behavior?
-		assert!(storage_has(&stored_map.key(&fake_key)));
+		// The default was never modified, so it isn't written
+		assert!(!storage_has(&stored_map.key(&fake_key)));
+
+		let mut v3 = stored_map.get_or_default_autosaving(&fake_key).unwrap();
+		v3.push_str("modified");
+		drop(v3);
+		assert_eq!(
+			stored_map.get(&fake_key)?.as_deref().map(String::as_str),
+			Some("modified")
+		);
 
 		let v1 = stored_map.get(&key).unwrap().unwrap();
 		assert_eq!(*v1, String::from("banana2"));
 
 		Ok(())
 	}
+
+	#[test]
+	fn find() -> TestingResult {
+		let _storage_lock = init()?;
+		let stored_map = StoredMap::<u16, u16>::new(NAMESPACE);
+		for i in 0..10u16 {
+			stored_map.set(&i, &(i * 100))?;
+		}
+
+		assert_eq!(
+			stored_map
+				.find(|_, v| *v == 900)?
+				.map(|(key, value)| (key, value.into_inner())),
+			Some((9, 900))
+		);
+		assert_eq!(stored_map.find(|_, v| *v == 901)?, None);
+		assert!(stored_map.any(|k, _| *k == 3)?);
+		assert!(stored_map.all(|k, v| *k * 100 == *v)?);
+		assert!(!stored_map.all(|k, _| *k < 9)?);
+		assert!(stored_map.contains_value(&500)?);
+		assert!(!stored_map.contains_value(&501)?);
+
+		assert_eq!(
+			stored_map
+				.find_bounded(5, |k, _| *k == 4)?
+				.map(|(key, value)| (key, value.into_inner())),
+			Some((4, 400))
+		);
+		assert!(stored_map.find_bounded(5, |k, _| *k == 5).is_err());
+		assert!(stored_map.find_bounded(5, |_, _| false).is_err());
+		assert!(stored_map.any_bounded(5, |_, _| false).is_err());
+		assert!(stored_map.all_bounded(5, |_, _| true).is_err());
+		assert_eq!(stored_map.find_bounded(10, |_, _| false)?, None);
+		assert!(stored_map.all_bounded(10, |_, _| true)?);
+
+		Ok(())
+	}
+
+	fn join_maps() -> StdResult<(StoredMap<u16, String>, StoredMap<String, u64>)> {
+		let positions = StoredMap::<u16, String>::new(b"positions");
+		let accounts = StoredMap::<String, u64>::new(b"accounts");
+		positions.set(&1, &"alice".to_string())?;
+		positions.set(&2, &"bob".to_string())?;
+		positions.set(&3, &"alice".to_string())?;
+		positions.set(&4, &"carol".to_string())?;
+		accounts.set(&"alice".to_string(), &100)?;
+		accounts.set(&"carol".to_string(), &300)?;
+		Ok((positions, accounts))
+	}
+
+	#[test]
+	fn join() -> TestingResult {
+		let _storage_lock = init()?;
+		let (positions, accounts) = join_maps()?;
+		assert_eq!(
+			positions
+				.iter()?
+				.join(&accounts, |_, owner| owner.clone())
+				.map(|(id, owner, account)| (id, owner.into_inner(), account.map(OZeroCopy::into_inner)))
+				.collect::<Vec<_>>(),
+			vec![
+				(1, "alice".to_string(), Some(100)),
+				(2, "bob".to_string(), None),
+				(3, "alice".to_string(), Some(100)),
+				(4, "carol".to_string(), Some(300)),
+			]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn join_filter() -> TestingResult {
+		let _storage_lock = init()?;
+		let (positions, accounts) = join_maps()?;
+		assert_eq!(
+			positions
+				.iter()?
+				.join_filter(&accounts, |_, owner| owner.clone())
+				.map(|(id, _, account)| (id, account.into_inner()))
+				.collect::<Vec<_>>(),
+			vec![(1, 100), (3, 100), (4, 300)]
+		);
+		accounts.remove(&"carol".to_string());
+		assert_eq!(
+			positions
+				.iter()?
+				.join_filter(&accounts, |_, owner| owner.clone())
+				.rev()
+				.map(|(id, _, _)| id)
+				.collect::<Vec<_>>(),
+			vec![3, 1]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn join_rev() -> TestingResult {
+		let _storage_lock = init()?;
+		let (positions, accounts) = join_maps()?;
+		let mut joined = positions.iter()?.join(&accounts, |_, owner| owner.clone());
+		let (id, _, account) = joined.next_back().unwrap();
+		assert_eq!((id, account.map(OZeroCopy::into_inner)), (4, Some(300)));
+		let (id, _, account) = joined.next().unwrap();
+		assert_eq!((id, account.map(OZeroCopy::into_inner)), (1, Some(100)));
+		assert_eq!(
+			joined
+				.rev()
+				.map(|(id, _, account)| (id, account.is_some()))
+				.collect::<Vec<_>>(),
+			vec![(3, true), (2, false)]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn corrupted_entry_error_context() -> TestingResult {
+		let _storage_lock = init()?;
+		let stored_map = StoredMap::<String, u64>::new(NAMESPACE);
+		let key = "alice".to_string();
+		storage_write(&stored_map.key(&key), &[1, 2, 3]);
+		let err = stored_map.get(&key).unwrap_err().to_string();
+		assert!(
+			err.contains(&format!("{:?}", String::from_utf8_lossy(NAMESPACE))),
+			"{err}"
+		);
+		assert!(
+			err.contains(&format!("key: 0x{}", hex::encode(key.serialize_to_owned()?))),
+			"{err}"
+		);
+
+		let long_key = "a".repeat(100);
+		storage_write(&stored_map.key(&long_key), &[1, 2, 3]);
+		let err = stored_map.get(&long_key).unwrap_err().to_string();
+		assert!(err.contains(&format!("key: 0x64000000{}…)", "61".repeat(60))), "{err}");
+		Ok(())
+	}
+
+	#[test]
+	fn key_buffers() -> TestingResult {
+		let _storage_lock = init()?;
+		let stored_map = StoredMap::<u64, String>::new(NAMESPACE);
+		let mut buf = Vec::new();
+		stored_map.set_with_buf(&1, &"one".to_string(), &mut buf)?;
+		stored_map.set(&2, &"two".to_string())?;
+		assert_eq!(buf, stored_map.key(&1));
+		let capacity = buf.capacity();
+
+		assert_eq!(stored_map.get_with_buf(&2, &mut buf)?, stored_map.get(&2)?);
+		assert_eq!(
+			stored_map.get_with_buf(&1, &mut buf)?.map(OZeroCopy::into_inner),
+			Some("one".to_string())
+		);
+		assert_eq!(stored_map.get_with_buf(&3, &mut buf)?, None);
+		stored_map.remove_with_buf(&1, &mut buf);
+		assert_eq!(stored_map.get(&1)?, None);
+		assert_eq!(buf.capacity(), capacity);
+
+		// Short keys never leave the stack
+		let key_buf = stored_map.key_buf(&u64::MAX);
+		assert!(key_buf.is_inline());
+		assert_eq!(*key_buf, *stored_map.key(&u64::MAX));
+
+		let string_map = StoredMap::<String, u64>::new(NAMESPACE);
+		let long_key = "a".repeat(KEY_BUF_INLINE_LEN);
+		let key_buf = string_map.key_buf(&long_key);
+		assert!(!key_buf.is_inline());
+		assert_eq!(*key_buf, *string_map.key(&long_key));
+		string_map.set(&long_key, &5)?;
+		assert_eq!(
+			string_map.get_with_buf(&long_key, &mut buf)?.map(OZeroCopy::into_inner),
+			Some(5)
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn first_and_last() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = StoredMap::<(u8, u8), u16>::new(NAMESPACE);
+		// Something right after the namespace shouldn't be picked up
+		storage_write(b"testinh", b"x");
+		assert_eq!(map.first()?, None);
+		assert_eq!(map.last_key()?, None);
+		assert_eq!(map.first_in_prefix(&1u8)?, None);
+
+		map.set(&(1, 5), &15)?;
+		let single = Some(((1, 5), OZeroCopy::from_inner(15)));
+		assert_eq!(map.first()?, single);
+		assert_eq!(map.last()?, single);
+		assert_eq!(map.first_key()?, Some((1, 5)));
+		assert_eq!(map.last_key()?, Some((1, 5)));
+
+		map.set(&(0, 9), &9)?;
+		map.set(&(1, 2), &12)?;
+		map.set(&(2, 0), &20)?;
+		map.set(&(3, 1), &31)?;
+		assert_eq!(map.first_key()?, Some((0, 9)));
+		assert_eq!(map.last_key()?, Some((3, 1)));
+		assert_eq!(map.first()?.map(|(_, value)| value.into_inner()), Some(9));
+		// The global min and max are outside of the prefix
+		assert_eq!(
+			map.first_in_prefix(&1u8)?.map(|(key, value)| (key, value.into_inner())),
+			Some(((1, 2), 12))
+		);
+		assert_eq!(
+			map.last_in_prefix(&1u8)?.map(|(key, value)| (key, value.into_inner())),
+			Some(((1, 5), 15))
+		);
+		assert_eq!(map.first_in_prefix(&4u8)?, None);
+
+		let set = crate::storage::set::StoredSet::<u16>::new(b"set");
+		assert_eq!((set.min()?, set.max()?), (None, None));
+		for value in [300, 256, 70] {
+			set.add(&value)?;
+		}
+		// Keys are little-endian, so this isn't numeric order
+		assert_eq!((set.min()?, set.max()?), (Some(256), Some(70)));
+		Ok(())
+	}
+
+	type OrderKey = ([u8; 8], [u8; 8]);
+
+	/// Orders keyed by (price, seq), both big-endian so they're in numeric order.
+	fn orderbook() -> StdResult<StoredMap<OrderKey, u64>> {
+		let orders = StoredMap::new(NAMESPACE);
+		for (price, seqs) in [(100u64, 1..=10u64), (105, 11..=20), (250, 21..=30)] {
+			for seq in seqs {
+				orders.set(&(price.to_be_bytes(), seq.to_be_bytes()), &(price * seq))?;
+			}
+		}
+		Ok(orders)
+	}
+
+	fn order_keys(iter: impl Iterator<Item = (OrderKey, OZeroCopy<u64>)>) -> Vec<(u64, u64)> {
+		iter.map(|((price, seq), _)| (u64::from_be_bytes(price), u64::from_be_bytes(seq)))
+			.collect()
+	}
+
+	#[test]
+	fn first_component_ranges() -> TestingResult {
+		let _storage_lock = init()?;
+		let orders = orderbook()?;
+		let price = |price: u64| price.to_be_bytes();
+		let seq = |seq: u64| seq.to_be_bytes();
+
+		// All orders with price <= 105
+		let keys = order_keys(orders.iter_first_component_range(Bound::Unbounded, Bound::Included(price(105)))?);
+		assert_eq!(keys.len(), 20);
+		assert_eq!((keys[0], keys[19]), ((100, 1), (105, 20)));
+		let keys = order_keys(orders.iter_first_component_range(Bound::Excluded(price(100)), Bound::Unbounded)?);
+		assert_eq!(keys.len(), 20);
+		assert_eq!((keys[0], keys[19]), ((105, 11), (250, 30)));
+		let keys =
+			order_keys(orders.iter_first_component_range(Bound::Included(price(101)), Bound::Excluded(price(250)))?);
+		assert_eq!(keys.len(), 10);
+		assert!(orders
+			.iter_first_component_range(Bound::Included(price(250)), Bound::Excluded(price(100)))?
+			.next()
+			.is_none());
+
+		let keys = order_keys(orders.iter_within(&price(105), Bound::Excluded(seq(12)), Bound::Included(seq(14)))?);
+		assert_eq!(keys, [(105, 13), (105, 14)]);
+		let keys = order_keys(orders.iter_within(&price(105), Bound::Included(seq(19)), Bound::Unbounded)?);
+		assert_eq!(keys, [(105, 19), (105, 20)]);
+		let keys = order_keys(
+			orders
+				.iter_within(&price(250), Bound::Unbounded, Bound::Excluded(seq(22)))?
+				.rev(),
+		);
+		assert_eq!(keys, [(250, 21)]);
+		assert!(orders
+			.iter_within(&price(101), Bound::Unbounded, Bound::Unbounded)?
+			.next()
+			.is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn distinct_first_seeks() -> TestingResult {
+		let (_storage_lock, _, ranges) = init_counting()?;
+		let orders = orderbook()?;
+
+		let ranges_before = ranges.load(std::sync::atomic::Ordering::SeqCst);
+		let prices: Vec<u64> = orders.iter_distinct_first().map(u64::from_be_bytes).collect();
+		assert_eq!(prices, [100, 105, 250]);
+		// Creating and reading from an iterator for each price level, and a final one which is empty. Iterating over
+		// every order would take 31.
+		assert_eq!(
+			ranges.load(std::sync::atomic::Ordering::SeqCst) - ranges_before,
+			3 * 2 + 1
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn update() -> TestingResult {
+		let _storage = isolated()?;
+		let map = StoredMap::<u8, u64>::new(NAMESPACE);
+		// Inserting
+		let inserted = map.update(&1, |old| {
+			assert!(old.is_none());
+			Ok(Some(OZeroCopy::from_inner(10)))
+		})?;
+		assert_eq!(inserted.as_deref(), Some(&10));
+		// Modifying in place
+		map.update(&1, |old| {
+			let mut value = old.unwrap();
+			*value += 5;
+			Ok(Some(value))
+		})?;
+		assert_eq!(map.get(&1)?.as_deref(), Some(&15));
+		// Errors leave the value as it was
+		let err = map.update(&1, |_| Err(StdError::generic_err("nope"))).unwrap_err();
+		assert_eq!(err, StdError::generic_err("nope"));
+		assert_eq!(map.get(&1)?.as_deref(), Some(&15));
+		// Removing
+		assert_eq!(map.update(&1, |_| Ok(None))?, None);
+		assert!(!map.has(&1));
+
+		let names = StoredMap::<u8, String>::new(b"names");
+		assert_eq!(
+			*names.update_or_default(&1, |name| {
+				name.push_str("alice");
+				Ok(())
+			})?,
+			"alice"
+		);
+		names.update_or_default(&1, |name| {
+			name.push_str(" and bob");
+			Ok(())
+		})?;
+		assert_eq!(names.get(&1)?.as_deref().map(String::as_str), Some("alice and bob"));
+		assert!(names
+			.update_or_default(&2, |_| Err(StdError::generic_err("nope")))
+			.is_err());
+		assert!(!names.has(&2));
+		Ok(())
+	}
 }
@@ -0,0 +1,237 @@
+use cosmwasm_std::{OverflowError, OverflowOperation, StdError, StdResult};
+use thiserror::Error;
+
+use crate::data_types::canonical_addr::SeiCanonicalAddr;
+
+use super::{map::StoredMap, OZeroCopy, SerializableItem};
+
+const SEI_SIGNER_TAG: u8 = 0;
+const EVM_SIGNER_TAG: u8 = 1;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum NonceError {
+	#[error("Invalid nonce {provided}, expected {expected}")]
+	NotSequential { expected: u64, provided: u64 },
+	#[error("Invalid nonce {provided}, expected {expected} or greater")]
+	NotIncreasing { expected: u64, provided: u64 },
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<NonceError> for StdError {
+	fn from(value: NonceError) -> Self {
+		match value {
+			NonceError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// Which nonces [NonceMap::check_and_update] accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoncePolicy {
+	/// Only the nonce following the last used one.
+	Sequential,
+	/// Any nonce greater than the last used one, so that signed messages can be skipped (e.g. if they were never
+	/// submitted), but never replayed.
+	Increasing,
+}
+
+/// A signer of off-chain messages, Sei and EVM signers are tracked independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NonceSigner {
+	Sei(SeiCanonicalAddr),
+	Evm([u8; 20]),
+}
+impl From<SeiCanonicalAddr> for NonceSigner {
+	fn from(value: SeiCanonicalAddr) -> Self {
+		Self::Sei(value)
+	}
+}
+impl From<[u8; 20]> for NonceSigner {
+	fn from(value: [u8; 20]) -> Self {
+		Self::Evm(value)
+	}
+}
+
+/// Stored as a tag byte followed by the address bytes.
+impl SerializableItem for NonceSigner {
+	fn serialize_to_owned(&self) -> StdResult<Vec<u8>> {
+		Ok(match self {
+			Self::Sei(addr) => [&[SEI_SIGNER_TAG][..], &addr.storage_key()].concat(),
+			Self::Evm(addr) => [&[EVM_SIGNER_TAG][..], addr].concat(),
+		})
+	}
+	fn deserialize_to_owned(data: &[u8]) -> StdResult<Self> {
+		match data.split_first() {
+			Some((&SEI_SIGNER_TAG, addr)) if addr.len() == 32 => Ok(Self::Sei(addr.try_into()?)),
+			Some((&EVM_SIGNER_TAG, addr)) if addr.len() == 20 => Ok(Self::Evm(addr.try_into().unwrap())),
+			_ => Err(StdError::parse_err("NonceSigner", "invalid tag or length")),
+		}
+	}
+}
+
+/// Replay protection for signed off-chain messages, e.g. for gasless or meta-transactions, by keeping track of the last
+/// nonce used by each signer.
+///
+/// Nonces start at 1, as 0 is what [current](Self::current) returns for signers which haven't used any. Each nonce is
+/// stored as a plain u64 and is read without copying. Never clear or move the map's namespace (e.g. during a
+/// migration), as that allows every previously signed message to be replayed.
+///
+/// The nonce should be checked after the signature is verified, and must be part of the signed message:
+///
+/// ```no_run
+/// # use cosmwasm_std::{Api, StdError};
+/// # use crownfi_cw_common::{crypto::verify_eip191, storage::nonce_map::{NonceMap, NoncePolicy}};
+/// const NONCES: &[u8] = b"nonces";
+///
+/// fn execute_signed(api: &dyn Api, signer: [u8; 20], nonce: u64, signature: &[u8; 65]) -> Result<(), StdError> {
+///     let message = format!("Withdraw all, nonce {nonce}");
+///     if !verify_eip191(message.as_bytes(), signature, &signer, api)? {
+///         return Err(StdError::generic_err("invalid signature"));
+///     }
+///     NonceMap::new(NONCES).check_and_update(signer, nonce, NoncePolicy::Sequential)?;
+///     // ...
+///     Ok(())
+/// }
+/// ```
+pub struct NonceMap {
+	nonces: StoredMap<NonceSigner, u64>,
+}
+
+impl NonceMap {
+	pub fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			nonces: StoredMap::new(namespace),
+		}
+	}
+
+	/// Returns the last nonce the signer has used, or 0 if it hasn't used any.
+	pub fn current(&self, signer: impl Into<NonceSigner>) -> StdResult<u64> {
+		Ok(self.nonces.get(&signer.into())?.map_or(0, OZeroCopy::into_inner))
+	}
+
+	/// Same as [current](Self::current), for many signers at once.
+	pub fn current_many(&self, signers: &[NonceSigner]) -> StdResult<Vec<u64>> {
+		signers.iter().map(|signer| self.current(*signer)).collect()
+	}
+
+	/// Records that the signer has used the nonce, returning an error with the expected nonce and leaving the stored
+	/// one unchanged if the policy doesn't allow it.
+	pub fn check_and_update(
+		&self,
+		signer: impl Into<NonceSigner>,
+		provided_nonce: u64,
+		policy: NoncePolicy,
+	) -> Result<(), NonceError> {
+		let signer = signer.into();
+		let current = self.current(signer)?;
+		let expected = current
+			.checked_add(1)
+			.ok_or_else(|| StdError::from(OverflowError::new(OverflowOperation::Add, current, 1)))?;
+		match policy {
+			NoncePolicy::Sequential if provided_nonce != expected => {
+				return Err(NonceError::NotSequential {
+					expected,
+					provided: provided_nonce,
+				});
+			}
+			NoncePolicy::Increasing if provided_nonce < expected => {
+				return Err(NonceError::NotIncreasing {
+					expected,
+					provided: provided_nonce,
+				});
+			}
+			_ => {}
+		}
+		self.nonces.set(&signer, &provided_nonce)?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	const SEI_SIGNER: [u8; 20] = [1; 20];
+	const EVM_SIGNER: [u8; 20] = [1; 20];
+
+	fn sei_signer() -> NonceSigner {
+		SeiCanonicalAddr::from(SEI_SIGNER).into()
+	}
+
+	#[test]
+	fn sequential_nonces() -> TestingResult {
+		let _storage_lock = init()?;
+		let nonces = NonceMap::new(NAMESPACE);
+		assert_eq!(nonces.current(sei_signer())?, 0);
+		assert_eq!(
+			nonces.check_and_update(sei_signer(), 0, NoncePolicy::Sequential),
+			Err(NonceError::NotSequential {
+				expected: 1,
+				provided: 0
+			})
+		);
+		nonces.check_and_update(sei_signer(), 1, NoncePolicy::Sequential)?;
+		nonces.check_and_update(sei_signer(), 2, NoncePolicy::Sequential)?;
+		// Reuse
+		assert_eq!(
+			nonces.check_and_update(sei_signer(), 2, NoncePolicy::Sequential),
+			Err(NonceError::NotSequential {
+				expected: 3,
+				provided: 2
+			})
+		);
+		// Gap
+		assert!(nonces
+			.check_and_update(sei_signer(), 4, NoncePolicy::Sequential)
+			.is_err());
+		assert_eq!(nonces.current(sei_signer())?, 2);
+		Ok(())
+	}
+
+	#[test]
+	fn increasing_nonces() -> TestingResult {
+		let _storage_lock = init()?;
+		let nonces = NonceMap::new(NAMESPACE);
+		nonces.check_and_update(EVM_SIGNER, 5, NoncePolicy::Increasing)?;
+		nonces.check_and_update(EVM_SIGNER, 100, NoncePolicy::Increasing)?;
+		for nonce in [3, 100] {
+			assert_eq!(
+				nonces.check_and_update(EVM_SIGNER, nonce, NoncePolicy::Increasing),
+				Err(NonceError::NotIncreasing {
+					expected: 101,
+					provided: nonce
+				})
+			);
+		}
+		nonces.check_and_update(EVM_SIGNER, u64::MAX, NoncePolicy::Increasing)?;
+		// No nonce can follow the largest one
+		assert!(matches!(
+			nonces.check_and_update(EVM_SIGNER, u64::MAX, NoncePolicy::Increasing),
+			Err(NonceError::Std(_))
+		));
+		Ok(())
+	}
+
+	#[test]
+	fn signer_kinds_are_independent() -> TestingResult {
+		let _storage_lock = init()?;
+		let nonces = NonceMap::new(NAMESPACE);
+		// Same address bytes, but different kinds of signers
+		nonces.check_and_update(sei_signer(), 1, NoncePolicy::Sequential)?;
+		nonces.check_and_update(EVM_SIGNER, 7, NoncePolicy::Increasing)?;
+		nonces.check_and_update(sei_signer(), 2, NoncePolicy::Sequential)?;
+		assert_eq!(
+			nonces.current_many(&[sei_signer(), EVM_SIGNER.into(), [2; 20].into()])?,
+			[2, 7, 0]
+		);
+		for signer in [sei_signer(), EVM_SIGNER.into()] {
+			assert_eq!(
+				NonceSigner::deserialize_to_owned(&signer.serialize_to_owned()?)?,
+				signer
+			);
+		}
+		Ok(())
+	}
+}
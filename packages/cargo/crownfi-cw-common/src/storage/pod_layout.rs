@@ -0,0 +1,141 @@
+//! Compile-time checks that the layout of zero-copy stored structs doesn't change.
+//!
+//! Types stored with [impl_serializable_as_ref](crate::impl_serializable_as_ref) are read back by reinterpreting the
+//! stored bytes, so reordering their fields or changing a field's type silently corrupts existing state once the
+//! contract is upgraded. Every such struct should have an [assert_pod_layout](crate::assert_pod_layout) right next to
+//! it, which stops compilation if the layout no longer matches:
+//!
+//! ```
+//! use bytemuck::{Pod, Zeroable};
+//! use cosmwasm_std::StdError;
+//! use crownfi_cw_common::{assert_pod_layout, impl_serializable_as_ref, storage::SerializableItem};
+//!
+//! #[derive(Clone, Copy, Zeroable, Pod)]
+//! #[repr(C)]
+//! pub struct Position {
+//!     pub amount: u64,
+//!     pub opened_at: u32,
+//!     pub flags: u32,
+//! }
+//! impl_serializable_as_ref!(Position);
+//! assert_pod_layout!(Position, size = 16, align = 8, fields = [amount: 0, opened_at: 8, flags: 12]);
+//! ```
+//!
+//! Intentional layout changes require a migration of the stored values. To catch those done without one, pass the
+//! type to [LayoutEntry::with_pod_layout](super::layout_guard::LayoutEntry::with_pod_layout) so that
+//! [verify_layout](super::layout_guard::verify_layout) compares its [layout_fingerprint] on migrate.
+
+/// The layout declared with [assert_pod_layout](crate::assert_pod_layout), which also implements this trait.
+pub trait PodLayout {
+	const SIZE: usize;
+	const ALIGN: usize;
+	/// The name and offset of every field, in the order they were declared in the macro.
+	const FIELDS: &'static [(&'static str, usize)];
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+const fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+	let mut i = 0;
+	while i < bytes.len() {
+		hash ^= bytes[i] as u64;
+		hash = hash.wrapping_mul(FNV_PRIME);
+		i += 1;
+	}
+	hash
+}
+
+/// A hash of the size, alignment and field names and offsets declared for `T`, which stays the same across compiler
+/// versions. Renaming or reordering fields changes the fingerprint even if the offsets are the same.
+pub const fn layout_fingerprint<T: PodLayout>() -> u64 {
+	let mut hash = fnv1a(FNV_OFFSET_BASIS, &(T::SIZE as u64).to_le_bytes());
+	hash = fnv1a(hash, &(T::ALIGN as u64).to_le_bytes());
+	let mut i = 0;
+	while i < T::FIELDS.len() {
+		let (name, offset) = T::FIELDS[i];
+		// Length-prefixed so different splits of the same bytes can't collide.
+		hash = fnv1a(hash, &(name.len() as u64).to_le_bytes());
+		hash = fnv1a(hash, name.as_bytes());
+		hash = fnv1a(hash, &(offset as u64).to_le_bytes());
+		i += 1;
+	}
+	hash
+}
+
+/// Fails compilation unless the struct has the specified size, alignment and field offsets, and implements
+/// [PodLayout](crate::storage::pod_layout::PodLayout) with them. See the [module docs](crate::storage::pod_layout).
+///
+/// `assert_pod_layout!(QueueEnds, size = 8, align = 4, fields = [front: 0, back: 4]);`
+#[macro_export]
+macro_rules! assert_pod_layout {
+	($data_type:ty, size = $size:expr, align = $align:expr, fields = [$($field:ident: $offset:expr),* $(,)?]) => {
+		const _: () = {
+			assert!(
+				::core::mem::size_of::<$data_type>() == $size,
+				concat!("the size of ", stringify!($data_type), " has changed")
+			);
+			assert!(
+				::core::mem::align_of::<$data_type>() == $align,
+				concat!("the alignment of ", stringify!($data_type), " has changed")
+			);
+			$(
+				assert!(
+					::core::mem::offset_of!($data_type, $field) == $offset,
+					concat!("the offset of ", stringify!($data_type), "::", stringify!($field), " has changed")
+				);
+			)*
+		};
+		impl $crate::storage::pod_layout::PodLayout for $data_type {
+			const SIZE: usize = $size;
+			const ALIGN: usize = $align;
+			const FIELDS: &'static [(&'static str, usize)] = &[$((stringify!($field), $offset)),*];
+		}
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use bytemuck::{Pod, Zeroable};
+
+	use super::*;
+	use crate::storage::{accounting::NamespaceUsage, layout_guard::LayoutEntry, queue::QueueEnds};
+
+	#[derive(Clone, Copy, Zeroable, Pod)]
+	#[repr(C)]
+	struct Mixed {
+		a: u64,
+		b: u16,
+		c: u16,
+		d: [u8; 4],
+	}
+	assert_pod_layout!(Mixed, size = 16, align = 8, fields = [a: 0, b: 8, c: 10, d: 12]);
+
+	#[test]
+	fn declared_layout() {
+		assert_eq!(<Mixed as PodLayout>::FIELDS, [("a", 0), ("b", 8), ("c", 10), ("d", 12)]);
+		assert_ne!(layout_fingerprint::<Mixed>(), layout_fingerprint::<QueueEnds>());
+
+		let entry = LayoutEntry::new("queue", b"queue", "StoredVecDeque", "Order");
+		assert_ne!(entry.fingerprint(), entry.with_pod_layout::<Mixed>().fingerprint());
+		assert_ne!(
+			entry.with_pod_layout::<Mixed>().fingerprint(),
+			entry.with_pod_layout::<QueueEnds>().fingerprint()
+		);
+	}
+
+	#[test]
+	fn fingerprints_are_stable() {
+		// Changing these means the layout guard rejects existing contracts on migrate.
+		assert_eq!(layout_fingerprint::<QueueEnds>(), 0xcdeb58caabac2bfc);
+		assert_eq!(layout_fingerprint::<NamespaceUsage>(), 0x5d0860fbff2a0b7a);
+		const FINGERPRINT: u64 = layout_fingerprint::<QueueEnds>();
+		assert_eq!(FINGERPRINT, layout_fingerprint::<QueueEnds>());
+	}
+
+	#[test]
+	fn layout_changes_fail_to_compile() {
+		let cases = trybuild::TestCases::new();
+		cases.compile_fail("tests/ui/pod_layout_reordered.rs");
+	}
+}
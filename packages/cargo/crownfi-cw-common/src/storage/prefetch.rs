@@ -0,0 +1,154 @@
+//! Reading storage iterators ahead in chunks.
+//!
+//! CosmWasm's `db_next` host call only returns a single entry, so for now [Prefetch::prefetch_into] makes one host
+//! call per item, and [Chunked] only saves the Rust-side bookkeeping of pulling items one by one. If a batched host
+//! API becomes available, the storage iterators' implementations of [Prefetch] are where it would be used.
+use std::collections::VecDeque;
+
+use super::{
+	map::{StoredMapIter, StoredMapKeyIter},
+	vec::IndexedStoredItemIter,
+	SerializableItem, StorageKeyIterator, StoragePairIterator,
+};
+
+pub trait Prefetch: Iterator {
+	/// Appends up to `n` of the next items to `buf`. Returns `false` if the iterator ended before `n` items were read.
+	fn prefetch_into(&mut self, n: usize, buf: &mut VecDeque<Self::Item>) -> bool {
+		for _ in 0..n {
+			let Some(item) = self.next() else {
+				return false;
+			};
+			buf.push_back(item);
+		}
+		true
+	}
+
+	/// Returns an iterator which reads `chunk_size` items at a time. At most one chunk is read ahead of the items
+	/// which have been returned.
+	fn chunked(self, chunk_size: usize) -> Chunked<Self>
+	where
+		Self: Sized,
+	{
+		Chunked::new(self, chunk_size)
+	}
+}
+
+impl Prefetch for StoragePairIterator {}
+impl Prefetch for StorageKeyIterator {}
+impl<K: SerializableItem, V: SerializableItem> Prefetch for StoredMapIter<K, V> {}
+impl<K: SerializableItem> Prefetch for StoredMapKeyIter<K> {}
+impl<V: SerializableItem> Prefetch for IndexedStoredItemIter<V> {}
+
+/// See [Prefetch::chunked].
+pub struct Chunked<I: Prefetch> {
+	inner: I,
+	chunk_size: usize,
+	buf: VecDeque<I::Item>,
+	exhausted: bool,
+}
+impl<I: Prefetch> Chunked<I> {
+	pub fn new(inner: I, chunk_size: usize) -> Self {
+		let chunk_size = chunk_size.max(1);
+		Self {
+			inner,
+			chunk_size,
+			buf: VecDeque::with_capacity(chunk_size),
+			exhausted: false,
+		}
+	}
+	/// The amount of items which have been read but not returned yet.
+	pub fn buffered(&self) -> usize {
+		self.buf.len()
+	}
+}
+impl<I: Prefetch> Iterator for Chunked<I> {
+	type Item = I::Item;
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.buf.is_empty() && !self.exhausted {
+			self.exhausted = !self.inner.prefetch_into(self.chunk_size, &mut self.buf);
+		}
+		self.buf.pop_front()
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		if self.exhausted {
+			return (self.buf.len(), Some(self.buf.len()));
+		}
+		let (lower, upper) = self.inner.size_hint();
+		(
+			lower.saturating_add(self.buf.len()),
+			upper.and_then(|upper| upper.checked_add(self.buf.len())),
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::Ordering;
+
+	use cosmwasm_std::StdResult;
+
+	use super::*;
+	use crate::storage::{map::StoredMap, queue::StoredVecDeque, set::StoredSet, testing_common::*};
+
+	#[test]
+	fn chunked_matches_unchunked() -> TestingResult {
+		let _storage = isolated()?;
+		let map = StoredMap::<u16, u32>::new(NAMESPACE);
+		for i in 0..25u16 {
+			map.set(&(i * 3), &(i as u32 * 7))?;
+		}
+		let expected = map.iter()?.map(|(k, v)| (k, *v)).collect::<Vec<_>>();
+		for chunk_size in [0, 1, 4, 25, 100] {
+			let chunked = map
+				.iter()?
+				.chunked(chunk_size)
+				.map(|(k, v)| (k, *v))
+				.collect::<Vec<_>>();
+			assert_eq!(chunked, expected, "chunk size {chunk_size}");
+			assert_eq!(
+				map.iter_keys()?.chunked(chunk_size).collect::<Vec<_>>(),
+				map.iter_keys()?.collect::<Vec<_>>()
+			);
+		}
+
+		let set = StoredSet::<u8>::new(b"set");
+		for i in [5, 1, 9] {
+			set.add(&i)?;
+		}
+		assert_eq!(
+			set.iter()?.chunked(2).collect::<Vec<_>>(),
+			set.iter()?.collect::<Vec<_>>()
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn stops_within_one_chunk() -> TestingResult {
+		let (_storage_lock, reads, ranges) = init_counting()?;
+		let mut queue = StoredVecDeque::<u32>::new(NAMESPACE);
+		for i in 0..20 {
+			queue.push_back(&i)?;
+		}
+
+		reads.store(0, Ordering::SeqCst);
+		let mut iter = queue.iter().chunked(4).map(|value| value.map(|value| *value));
+		assert_eq!(iter.by_ref().take(3).collect::<StdResult<Vec<_>>>()?, [0, 1, 2]);
+		assert_eq!(reads.load(Ordering::SeqCst), 4);
+		assert_eq!(iter.next(), Some(Ok(3)));
+		assert_eq!(reads.load(Ordering::SeqCst), 4);
+		assert_eq!(iter.next(), Some(Ok(4)));
+		assert_eq!(reads.load(Ordering::SeqCst), 8);
+
+		let map = StoredMap::<u8, u8>::new(b"map");
+		for i in 0..20 {
+			map.set(&i, &i)?;
+		}
+		let ranges_before = ranges.load(Ordering::SeqCst);
+		let mut iter = map.iter_keys()?.chunked(5);
+		assert_eq!(iter.by_ref().take(2).collect::<Vec<_>>(), [0, 1]);
+		assert_eq!(iter.buffered(), 3);
+		// One range call to create the iterator, then one for each entry read
+		assert_eq!(ranges.load(Ordering::SeqCst) - ranges_before, 1 + 5);
+		Ok(())
+	}
+}
@@ -0,0 +1,662 @@
+//! Property tests for the serialization and container layer.
+//!
+//! The containers are driven by random operation sequences and compared against their `std` equivalents after every
+//! step. Only a handful of cases are ran by default to keep `cargo test` fast, set `PROPTEST_CASES` to run more, e.g.
+//! `PROPTEST_CASES=10000 cargo test proptests`.
+use std::{
+	collections::{BTreeMap, VecDeque},
+	fmt::Debug,
+};
+
+use proptest::{prelude::*, sample::Index};
+
+use super::{
+	base::storage_write_item,
+	map::StoredMap,
+	queue::{QueueEnds, StoredVecDeque},
+	set::StoredSet,
+	stats::StoredStats,
+	vec::StoredVec,
+	OZeroCopy, SerializableItem,
+};
+use crate::{
+	data_types::{asset::FungibleAssetKind, canonical_addr::SeiCanonicalAddr},
+	storage::testing_common::*,
+};
+
+const DEFAULT_CASES: u32 = 32;
+
+fn config() -> ProptestConfig {
+	ProptestConfig::with_cases(
+		std::env::var("PROPTEST_CASES")
+			.ok()
+			.and_then(|cases| cases.parse().ok())
+			.unwrap_or(DEFAULT_CASES),
+	)
+}
+
+fn key_bytes<K: SerializableItem>(key: &K) -> Vec<u8> {
+	key.serialize_to_owned().expect("test keys should serialize")
+}
+
+fn assert_round_trip<T: SerializableItem + PartialEq + Debug>(value: &T) -> Result<(), TestCaseError> {
+	let bytes = value.serialize_to_owned()?;
+	if let Some(ref_bytes) = value.serialize_as_ref() {
+		prop_assert_eq!(ref_bytes, bytes.as_slice());
+	}
+	if let Some(zero_copied) = T::deserialize_as_ref(&bytes) {
+		prop_assert_eq!(zero_copied, value);
+	}
+	prop_assert_eq!(&T::deserialize_to_owned(&bytes)?, value);
+	prop_assert_eq!(&*OZeroCopy::<T>::new(bytes.clone())?, value);
+	prop_assert_eq!(OZeroCopy::<T>::new(bytes.clone())?.try_into_bytes()?, bytes);
+	Ok(())
+}
+
+/// Pulls items off both ends of `iter` according to `pulls` (true = front) and checks them against `expected`.
+fn assert_double_ended<T, I>(mut iter: I, expected: &[T], pulls: &[bool]) -> Result<(), TestCaseError>
+where
+	T: PartialEq + Debug + Clone,
+	I: DoubleEndedIterator<Item = T>,
+{
+	let mut expected: VecDeque<T> = expected.iter().cloned().collect();
+	for &front in pulls {
+		if front {
+			prop_assert_eq!(iter.next(), expected.pop_front());
+		} else {
+			prop_assert_eq!(iter.next_back(), expected.pop_back());
+		}
+	}
+	Ok(())
+}
+
+/// Checks forward, reverse, nth, nth_back, and interleaved iteration
+fn assert_iteration<T, I, F>(make_iter: F, expected: &[T], nth: &Index, pulls: &[bool]) -> Result<(), TestCaseError>
+where
+	T: PartialEq + Debug + Clone,
+	I: DoubleEndedIterator<Item = T>,
+	F: Fn() -> I,
+{
+	prop_assert_eq!(make_iter().collect::<Vec<_>>(), expected);
+	prop_assert_eq!(
+		make_iter().rev().collect::<Vec<_>>(),
+		expected.iter().rev().cloned().collect::<Vec<_>>()
+	);
+	// Also check one past the end
+	let n = nth.index(expected.len() + 2);
+	prop_assert_eq!(make_iter().nth(n), expected.get(n).cloned());
+	prop_assert_eq!(make_iter().rev().nth(n), expected.iter().rev().nth(n).cloned());
+	let mut iter = make_iter();
+	prop_assert_eq!(iter.nth_back(n), expected.iter().rev().nth(n).cloned());
+	prop_assert_eq!(
+		iter.collect::<Vec<_>>(),
+		expected[..expected.len().saturating_sub(n + 1)].to_vec()
+	);
+	assert_double_ended(make_iter(), expected, pulls)
+}
+
+#[derive(Debug, Clone)]
+enum MapOp<K, V> {
+	Set(K, V),
+	Remove(K),
+	Get(K),
+}
+
+fn map_ops<K, V>(
+	key: impl Strategy<Value = K> + Clone + 'static,
+	value: impl Strategy<Value = V> + 'static,
+) -> impl Strategy<Value = Vec<MapOp<K, V>>>
+where
+	K: Debug + Clone + 'static,
+	V: Debug + Clone + 'static,
+{
+	prop::collection::vec(
+		prop_oneof![
+			3 => (key.clone(), value).prop_map(|(k, v)| MapOp::Set(k, v)),
+			1 => key.clone().prop_map(MapOp::Remove),
+			1 => key.prop_map(MapOp::Get),
+		],
+		0..48,
+	)
+}
+
+/// The model is keyed by the serialized key, as that's the order the storage iterates in.
+fn check_map_model<K, V>(
+	ops: Vec<MapOp<K, V>>,
+	range: (K, K),
+	nth: Index,
+	pulls: Vec<bool>,
+) -> Result<(), TestCaseError>
+where
+	K: SerializableItem + PartialEq + Debug + Clone,
+	V: SerializableItem + PartialEq + Debug + Clone,
+{
+	let _storage_lock = init().unwrap();
+	let map = StoredMap::<K, V>::new(NAMESPACE);
+	// Neighbouring namespaces which shouldn't show up. Note that a namespace which has "testing" as a prefix _would_
+	// show up, namespaces are expected to not be prefixes of each other.
+	let before_map = StoredMap::<K, V>::new(b"testinf");
+	let after_map = StoredMap::<K, V>::new(b"testinh");
+	let mut model = BTreeMap::<Vec<u8>, (K, V)>::new();
+
+	for op in ops {
+		match op {
+			MapOp::Set(k, v) => {
+				map.set(&k, &v)?;
+				before_map.set(&k, &v)?;
+				after_map.set(&k, &v)?;
+				model.insert(key_bytes(&k), (k, v));
+			}
+			MapOp::Remove(k) => {
+				map.remove(&k);
+				model.remove(&key_bytes(&k));
+			}
+			MapOp::Get(k) => {
+				prop_assert_eq!(map.has(&k), model.contains_key(&key_bytes(&k)));
+				prop_assert_eq!(
+					map.get(&k)?.map(OZeroCopy::into_inner),
+					model.get(&key_bytes(&k)).map(|(_, v)| v.clone())
+				);
+			}
+		}
+	}
+
+	let expected: Vec<(K, V)> = model.values().cloned().collect();
+	assert_iteration(
+		|| map.iter().unwrap().map(|(k, v)| (k, v.into_inner())),
+		&expected,
+		&nth,
+		&pulls,
+	)?;
+	let expected_keys: Vec<K> = model.values().map(|(k, _)| k.clone()).collect();
+	assert_iteration(|| map.iter_keys().unwrap(), &expected_keys, &nth, &pulls)?;
+
+	let (mut after, mut before) = range;
+	if key_bytes(&after) > key_bytes(&before) {
+		std::mem::swap(&mut after, &mut before);
+	}
+	let expected_range: Vec<(K, V)> = model
+		.range(key_bytes(&after)..key_bytes(&before))
+		.map(|(_, pair)| pair.clone())
+		.collect();
+	assert_iteration(
+		|| {
+			map.iter_range(Some(after.clone()), Some(before.clone()))
+				.unwrap()
+				.map(|(k, v)| (k, v.into_inner()))
+		},
+		&expected_range,
+		&nth,
+		&pulls,
+	)?;
+	let expected_range: Vec<(K, V)> = model.range(key_bytes(&after)..).map(|(_, pair)| pair.clone()).collect();
+	assert_iteration(
+		|| {
+			map.iter_range(Some(after.clone()), None)
+				.unwrap()
+				.map(|(k, v)| (k, v.into_inner()))
+		},
+		&expected_range,
+		&nth,
+		&pulls,
+	)?;
+	Ok(())
+}
+
+fn small_string() -> impl Strategy<Value = String> + Clone {
+	"[a-c]{0,3}"
+}
+
+fn small_u32() -> impl Strategy<Value = u32> + Clone {
+	prop_oneof![0u32..16, any::<u32>()]
+}
+
+#[derive(Debug, Clone)]
+enum SetOp<V> {
+	Add(V),
+	Remove(V),
+	Has(V),
+}
+
+#[derive(Debug, Clone)]
+enum VecOp {
+	Push(u16),
+	Pop,
+	Get(u32),
+	Set(u32, u16),
+	Remove(Index),
+	SwapRemove(Index),
+	Swap(Index, Index),
+	Truncate(Index, bool),
+	Clear(bool),
+	Extend(Vec<u16>),
+	Reload,
+}
+
+fn vec_ops() -> impl Strategy<Value = Vec<VecOp>> {
+	prop::collection::vec(
+		prop_oneof![
+			4 => any::<u16>().prop_map(VecOp::Push),
+			1 => Just(VecOp::Pop),
+			1 => (0u32..20).prop_map(VecOp::Get),
+			1 => (0u32..20, any::<u16>()).prop_map(|(i, v)| VecOp::Set(i, v)),
+			1 => any::<Index>().prop_map(VecOp::Remove),
+			1 => any::<Index>().prop_map(VecOp::SwapRemove),
+			1 => any::<(Index, Index)>().prop_map(|(a, b)| VecOp::Swap(a, b)),
+			1 => any::<(Index, bool)>().prop_map(|(i, dirty)| VecOp::Truncate(i, dirty)),
+			1 => any::<bool>().prop_map(VecOp::Clear),
+			1 => prop::collection::vec(any::<u16>(), 0..5).prop_map(VecOp::Extend),
+			1 => Just(VecOp::Reload),
+		],
+		0..48,
+	)
+}
+
+#[derive(Debug, Clone)]
+enum DequeOp {
+	PushFront(u16),
+	PushBack(u16),
+	PopFront,
+	PopBack,
+	Get(u32),
+	Set(Index, u16),
+	SetFront(u16),
+	SetBack(u16),
+	Swap(Index, Index),
+	Clear(bool),
+	Reload,
+}
+
+fn deque_ops() -> impl Strategy<Value = Vec<DequeOp>> {
+	prop::collection::vec(
+		prop_oneof![
+			3 => any::<u16>().prop_map(DequeOp::PushFront),
+			3 => any::<u16>().prop_map(DequeOp::PushBack),
+			1 => Just(DequeOp::PopFront),
+			1 => Just(DequeOp::PopBack),
+			1 => (0u32..20).prop_map(DequeOp::Get),
+			1 => any::<(Index, u16)>().prop_map(|(i, v)| DequeOp::Set(i, v)),
+			1 => any::<u16>().prop_map(DequeOp::SetFront),
+			1 => any::<u16>().prop_map(DequeOp::SetBack),
+			1 => any::<(Index, Index)>().prop_map(|(a, b)| DequeOp::Swap(a, b)),
+			1 => any::<bool>().prop_map(DequeOp::Clear),
+			1 => Just(DequeOp::Reload),
+		],
+		0..48,
+	)
+}
+
+/// Where the queue's front starts, biased towards the wrap-around point.
+fn deque_start() -> impl Strategy<Value = u32> {
+	prop_oneof![Just(0), Just(u32::MAX), (u32::MAX - 8)..=u32::MAX, any::<u32>()]
+}
+
+fn fungible_asset_kind() -> impl Strategy<Value = FungibleAssetKind> {
+	prop_oneof![
+		any::<String>().prop_map(FungibleAssetKind::Native),
+		any::<[u8; 32]>().prop_map(|bytes| FungibleAssetKind::CW20(bytes.into())),
+		any::<[u8; 20]>().prop_map(FungibleAssetKind::ERC20),
+	]
+}
+
+proptest! {
+	#![proptest_config(config())]
+
+	#[test]
+	fn round_trip_ints(
+		a: u8, b: i8, c: u16, d: i16, e: u32, f: i32, g: u64, h: i64, i: usize, j: isize, k: u128, l: i128
+	) {
+		assert_round_trip(&a)?;
+		assert_round_trip(&b)?;
+		assert_round_trip(&c)?;
+		assert_round_trip(&d)?;
+		assert_round_trip(&e)?;
+		assert_round_trip(&f)?;
+		assert_round_trip(&g)?;
+		assert_round_trip(&h)?;
+		assert_round_trip(&i)?;
+		assert_round_trip(&j)?;
+		assert_round_trip(&k)?;
+		assert_round_trip(&l)?;
+	}
+
+	#[test]
+	fn round_trip_floats(a: f32, b: f64) {
+		// NaN != NaN, so compare the bits instead
+		prop_assert_eq!(f32::deserialize_to_owned(&a.serialize_to_owned()?)?.to_bits(), a.to_bits());
+		prop_assert_eq!(f64::deserialize_to_owned(&b.serialize_to_owned()?)?.to_bits(), b.to_bits());
+		prop_assert_eq!(a.serialize_as_ref(), Some(&a.to_le_bytes()[..]));
+		prop_assert_eq!(b.serialize_as_ref(), Some(&b.to_le_bytes()[..]));
+	}
+
+	#[test]
+	fn round_trip_borsh(a: bool, b: String, c: Vec<u8>, d: Vec<String>, e: Vec<(u32, bool)>) {
+		assert_round_trip(&a)?;
+		assert_round_trip(&b)?;
+		assert_round_trip(&c)?;
+		assert_round_trip(&d)?;
+		assert_round_trip(&e)?;
+	}
+
+	#[test]
+	fn round_trip_tuples(
+		a: (u64, String),
+		b: (u8, String, u128, bool),
+		c: (u8, u16, u32, u64, String),
+		d: (u8, u8, u8, u8, u8, u8),
+		e: (bool, String, u8, u16, u32, u64, i8),
+		f: (u8, u8, u8, u8, u8, u8, u8, String),
+		g: (u8, u8, u8, u8, u8, u8, u8, u8, String),
+		h: (u8, u8, u8, u8, u8, u8, u8, u8, u8, String),
+	) {
+		assert_round_trip(&a)?;
+		assert_round_trip(&b)?;
+		assert_round_trip(&c)?;
+		assert_round_trip(&d)?;
+		assert_round_trip(&e)?;
+		assert_round_trip(&f)?;
+		assert_round_trip(&g)?;
+		assert_round_trip(&h)?;
+	}
+
+	#[test]
+	fn round_trip_pod_arrays(a: [u8; 20], b: [u8; 32], c: [u64; 4], d: [i16; 3], e: [u128; 2]) {
+		assert_round_trip(&a)?;
+		assert_round_trip(&b)?;
+		assert_round_trip(&c)?;
+		assert_round_trip(&d)?;
+		assert_round_trip(&e)?;
+		assert_round_trip(&[0u32; 0])?;
+	}
+
+	#[test]
+	fn round_trip_escaped_keys(bytes: Vec<u8>) {
+		let escaped = crate::utils::escape_key_bytes(&bytes);
+		prop_assert!(escaped.bytes().all(|byte| byte.is_ascii_graphic()));
+		prop_assert_eq!(crate::utils::unescape_key_bytes(&escaped).unwrap(), bytes);
+	}
+
+	#[test]
+	fn round_trip_crate_types(addr: [u8; 32], front: u32, back: u32, kind in fungible_asset_kind()) {
+		assert_round_trip(&())?;
+		assert_round_trip(&SeiCanonicalAddr::from(addr))?;
+		let ends = QueueEnds { front, back };
+		let ends_bytes = ends.serialize_to_owned()?;
+		let decoded = QueueEnds::deserialize_to_owned(&ends_bytes)?;
+		prop_assert_eq!((decoded.front, decoded.back), (front, back));
+		assert_round_trip(&kind)?;
+	}
+
+	#[test]
+	fn map_string_keys(
+		ops in map_ops(small_string(), any::<u64>()),
+		range in (small_string(), small_string()),
+		nth: Index,
+		pulls in prop::collection::vec(any::<bool>(), 0..16),
+	) {
+		check_map_model(ops, range, nth, pulls)?;
+	}
+
+	#[test]
+	fn map_tuple_keys(
+		ops in map_ops((0u8..4, small_string()), small_string()),
+		range in ((0u8..4, small_string()), (0u8..4, small_string())),
+		nth: Index,
+		pulls in prop::collection::vec(any::<bool>(), 0..16),
+	) {
+		check_map_model(ops, range, nth, pulls)?;
+	}
+
+	#[test]
+	fn map_int_keys(
+		ops in map_ops(small_u32(), prop::collection::vec(any::<u8>(), 0..4)),
+		range in (small_u32(), small_u32()),
+		nth: Index,
+		pulls in prop::collection::vec(any::<bool>(), 0..16),
+	) {
+		check_map_model(ops, range, nth, pulls)?;
+	}
+
+	#[test]
+	fn map_byte_keys(
+		ops in map_ops(any::<[u8; 2]>(), any::<u16>()),
+		range: ([u8; 2], [u8; 2]),
+		nth: Index,
+		pulls in prop::collection::vec(any::<bool>(), 0..16),
+	) {
+		check_map_model(ops, range, nth, pulls)?;
+	}
+
+	#[test]
+	fn stats_model(ops in map_ops(0u32..16, any::<u64>())) {
+		let _storage_lock = init().unwrap();
+		let map = StoredMap::<u32, u64>::new(NAMESPACE);
+		let mut stats = StoredStats::default();
+		let mut model = BTreeMap::<u32, u64>::new();
+		for op in ops {
+			match op {
+				MapOp::Set(k, v) => {
+					map.set_tracked(&k, &v, &mut stats, |v| *v as u128)?;
+					model.insert(k, v);
+				}
+				MapOp::Remove(k) => {
+					map.remove_tracked(&k, &mut stats, |v| *v as u128)?;
+					model.remove(&k);
+				}
+				MapOp::Get(_) => {}
+			}
+			prop_assert_eq!(stats.count as usize, model.len());
+			prop_assert_eq!(stats.sum, model.values().map(|v| *v as u128).sum::<u128>());
+			// min and max are only guaranteed to be bounds after updates and removals
+			if let (Some(min), Some(max)) = (model.values().min(), model.values().max()) {
+				prop_assert!(stats.min <= *min as u128);
+				prop_assert!(stats.max >= *max as u128);
+			}
+		}
+		let rebuilt = StoredStats::rebuild_from(&map, |_, v| *v as u128)?;
+		prop_assert_eq!(rebuilt.count, stats.count);
+		prop_assert_eq!(rebuilt.sum, stats.sum);
+		prop_assert_eq!(rebuilt.min, model.values().min().map_or(0, |v| *v as u128));
+		prop_assert_eq!(rebuilt.max, model.values().max().map_or(0, |v| *v as u128));
+	}
+
+	#[test]
+	fn set_model(
+		ops in prop::collection::vec(
+			prop_oneof![
+				2 => small_string().prop_map(SetOp::Add),
+				1 => small_string().prop_map(SetOp::Remove),
+				1 => small_string().prop_map(SetOp::Has),
+			],
+			0..48
+		),
+		nth: Index,
+		pulls in prop::collection::vec(any::<bool>(), 0..16),
+	) {
+		let _storage_lock = init().unwrap();
+		let set = StoredSet::<String>::new(NAMESPACE);
+		let mut model = BTreeMap::<Vec<u8>, String>::new();
+		for op in ops {
+			match op {
+				SetOp::Add(v) => {
+					set.add(&v)?;
+					model.insert(key_bytes(&v), v);
+				}
+				SetOp::Remove(v) => {
+					set.remove(&v);
+					model.remove(&key_bytes(&v));
+				}
+				SetOp::Has(v) => {
+					prop_assert_eq!(set.has(&v), model.contains_key(&key_bytes(&v)));
+				}
+			}
+		}
+		let expected: Vec<String> = model.into_values().collect();
+		assert_iteration(|| set.iter().unwrap(), &expected, &nth, &pulls)?;
+	}
+
+	// `insert` isn't covered here as it currently doesn't update the length.
+	#[test]
+	fn vec_model(ops in vec_ops(), nth: Index, pulls in prop::collection::vec(any::<bool>(), 0..16)) {
+		let _storage_lock = init().unwrap();
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		let mut model = Vec::<u16>::new();
+		for op in ops {
+			match op {
+				VecOp::Push(v) => {
+					vec.push(&v)?;
+					model.push(v);
+				}
+				VecOp::Pop => {
+					prop_assert_eq!(vec.pop()?.map(OZeroCopy::into_inner), model.pop());
+				}
+				VecOp::Get(i) => {
+					prop_assert_eq!(vec.get(i)?.map(OZeroCopy::into_inner), model.get(i as usize).copied());
+				}
+				VecOp::Set(i, v) => {
+					let result = vec.set(i, &v);
+					prop_assert_eq!(result.is_ok(), (i as usize) < model.len());
+					if let Some(slot) = model.get_mut(i as usize) {
+						*slot = v;
+					}
+				}
+				VecOp::Remove(i) if !model.is_empty() => {
+					let i = i.index(model.len());
+					prop_assert_eq!(vec.remove(i as u32)?.into_inner(), model.remove(i));
+				}
+				VecOp::SwapRemove(i) if !model.is_empty() => {
+					let i = i.index(model.len());
+					prop_assert_eq!(vec.swap_remove(i as u32)?.into_inner(), model.swap_remove(i));
+				}
+				VecOp::Swap(a, b) if !model.is_empty() => {
+					let (a, b) = (a.index(model.len()), b.index(model.len()));
+					vec.swap(a as u32, b as u32)?;
+					model.swap(a, b);
+				}
+				VecOp::Truncate(len, dirty) => {
+					let len = len.index(model.len() + 2);
+					vec.truncate(len as u32, dirty);
+					model.truncate(len);
+				}
+				VecOp::Clear(dirty) => {
+					vec.clear(dirty);
+					model.clear();
+				}
+				VecOp::Extend(values) => {
+					vec.extend(values.iter().copied())?;
+					model.extend(values);
+				}
+				VecOp::Reload => {
+					vec = StoredVec::new(NAMESPACE);
+				}
+				VecOp::Remove(_) | VecOp::SwapRemove(_) | VecOp::Swap(..) => {}
+			}
+			prop_assert_eq!(vec.len() as usize, model.len());
+		}
+		prop_assert_eq!(StoredVec::<u16>::new(NAMESPACE).len() as usize, model.len());
+		assert_iteration(
+			|| vec.iter().map(|item| item.unwrap().into_inner()),
+			&model,
+			&nth,
+			&pulls,
+		)?;
+	}
+
+	#[test]
+	fn deque_model(
+		start in deque_start(),
+		ops in deque_ops(),
+		nth: Index,
+		pulls in prop::collection::vec(any::<bool>(), 0..16),
+	) {
+		let _storage_lock = init().unwrap();
+		storage_write_item(NAMESPACE, &QueueEnds { front: start, back: start })?;
+		let mut queue = StoredVecDeque::<u16>::new(NAMESPACE);
+		let mut model = VecDeque::<u16>::new();
+		for op in ops {
+			match op {
+				DequeOp::PushFront(v) => {
+					queue.push_front(&v)?;
+					model.push_front(v);
+				}
+				DequeOp::PushBack(v) => {
+					queue.push_back(&v)?;
+					model.push_back(v);
+				}
+				DequeOp::PopFront => {
+					prop_assert_eq!(queue.pop_front()?.map(OZeroCopy::into_inner), model.pop_front());
+				}
+				DequeOp::PopBack => {
+					prop_assert_eq!(queue.pop_back()?.map(OZeroCopy::into_inner), model.pop_back());
+				}
+				DequeOp::Get(i) => {
+					prop_assert_eq!(queue.get(i)?.map(OZeroCopy::into_inner), model.get(i as usize).copied());
+				}
+				DequeOp::Set(i, v) if !model.is_empty() => {
+					let i = i.index(model.len());
+					queue.set(i as u32, &v)?;
+					model[i] = v;
+				}
+				DequeOp::SetFront(v) => {
+					prop_assert_eq!(queue.set_front(&v).is_ok(), !model.is_empty());
+					if let Some(front) = model.front_mut() {
+						*front = v;
+					}
+				}
+				DequeOp::SetBack(v) => {
+					prop_assert_eq!(queue.set_back(&v).is_ok(), !model.is_empty());
+					if let Some(back) = model.back_mut() {
+						*back = v;
+					}
+				}
+				DequeOp::Swap(a, b) if !model.is_empty() => {
+					let (a, b) = (a.index(model.len()), b.index(model.len()));
+					queue.swap(a as u32, b as u32)?;
+					model.swap(a, b);
+				}
+				DequeOp::Clear(dirty) => {
+					queue.clear(dirty);
+					model.clear();
+				}
+				DequeOp::Reload => {
+					queue = StoredVecDeque::new(NAMESPACE);
+				}
+				DequeOp::Set(..) | DequeOp::Swap(..) => {}
+			}
+			prop_assert_eq!(queue.len() as usize, model.len());
+			prop_assert_eq!(queue.is_empty(), model.is_empty());
+			prop_assert_eq!(queue.get_front()?.map(OZeroCopy::into_inner), model.front().copied());
+			prop_assert_eq!(queue.get_back()?.map(OZeroCopy::into_inner), model.back().copied());
+		}
+		let expected: Vec<u16> = model.into_iter().collect();
+		assert_iteration(
+			|| queue.iter().map(|item| item.unwrap().into_inner()),
+			&expected,
+			&nth,
+			&pulls,
+		)?;
+	}
+
+	#[test]
+	fn deque_swap_out_of_bounds(
+		start in deque_start(),
+		stale in prop::collection::vec(any::<u16>(), 1..8),
+		values in prop::collection::vec(any::<u16>(), 0..8),
+		offset in 0u32..8,
+	) {
+		let _storage_lock = init().unwrap();
+		storage_write_item(NAMESPACE, &QueueEnds { front: start, back: start })?;
+		let mut queue = StoredVecDeque::<u16>::new(NAMESPACE);
+		// A dirty clear leaves entries behind which shouldn't be reachable
+		for value in stale.iter() {
+			queue.push_back(value)?;
+		}
+		queue.clear(true);
+		for value in values.iter() {
+			queue.push_back(value)?;
+		}
+		let len = queue.len();
+		prop_assert!(queue.swap(len + offset, 0).is_err());
+		prop_assert!(queue.swap(0, len + offset).is_err());
+	}
+}
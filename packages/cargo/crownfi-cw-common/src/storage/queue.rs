@@ -1,12 +1,21 @@
+use std::{
+	marker::PhantomData,
+	ops::{Bound, RangeBounds},
+};
+
 use bytemuck::{Pod, Zeroable};
 use cosmwasm_std::{StdError, StdResult};
 
-use crate::impl_serializable_as_ref;
+use crate::{assert_pod_layout, debug_log, impl_serializable_as_ref, utils::hex_display};
 
 use super::{
-	base::{storage_read, storage_write_item},
+	accounting::{Accounted, NamespaceUsage},
+	base::{storage_read, storage_read_namespaced_item, storage_write_item},
+	concat_byte_array_pairs,
 	map::StoredMap,
+	value_size::StorageWriteError,
 	vec::IndexedStoredItemIter,
+	write_batch::WriteBatch,
 	OZeroCopy, SerializableItem,
 };
 
@@ -17,6 +26,7 @@ pub struct QueueEnds {
 	pub back: u32,
 }
 impl_serializable_as_ref!(QueueEnds);
+assert_pod_layout!(QueueEnds, size = 8, align = 4, fields = [front: 0, back: 4]);
 
 pub struct StoredVecDeque<V: SerializableItem> {
 	namespace: &'static [u8],
@@ -24,25 +34,54 @@ pub struct StoredVecDeque<V: SerializableItem> {
 	ends: QueueEnds,
 }
 impl<V: SerializableItem> StoredVecDeque<V> {
+	/// Loads the queue, treating a header which isn't 4 or 8 bytes long as an empty queue.
+	///
+	/// See [try_new](Self::try_new) for what the header lengths mean.
 	pub fn new(namespace: &'static [u8]) -> Self {
-		let ends = storage_read(namespace)
-			.map(|data| {
-				if data.len() == 4 {
-					// Vec that has been "upgraded" to a queue
-					return QueueEnds {
-						front: 0,
-						back: u32::from_le_bytes(data.try_into().unwrap()),
-					};
-				}
-				QueueEnds::deserialize_to_owned(&data).unwrap_or_default()
-			})
-			.unwrap_or_default();
-
-		Self {
+		Self::try_new(namespace).unwrap_or_else(|err| {
+			debug_log!("treating StoredVecDeque as empty: {}", err; namespace = hex_display(namespace));
+			Self {
+				namespace,
+				map: StoredMap::new(namespace),
+				ends: QueueEnds::default(),
+			}
+		})
+	}
+
+	/// Loads the queue, returning [StdError::InvalidDataSize] if the header exists but is neither a 4 byte
+	/// [StoredVec](super::vec::StoredVec) length nor 8 byte [QueueEnds].
+	///
+	/// A StoredVec's entries are stored the same way, so a vec is "upgraded" to a queue with the same elements by
+	/// loading it as one, its header is rewritten as QueueEnds once the queue is modified.
+	pub fn try_new(namespace: &'static [u8]) -> StdResult<Self> {
+		let ends = match storage_read(namespace) {
+			Some(data) => match data.len() {
+				4 => QueueEnds {
+					front: 0,
+					back: u32::from_le_bytes(data.try_into().unwrap()),
+				},
+				8 => QueueEnds::deserialize_to_owned(&data)?,
+				len => return Err(StdError::invalid_data_size(8, len)),
+			},
+			None => QueueEnds::default(),
+		};
+		Ok(Self {
 			namespace,
 			map: StoredMap::new(namespace),
 			ends,
-		}
+		})
+	}
+
+	/// Elements larger than `max_size` bytes are rejected by `set` and `push`, see [StoredMap::with_max_value_size].
+	pub fn with_max_value_size(mut self, max_size: usize) -> Self {
+		self.map = self.map.with_max_value_size(max_size);
+		self
+	}
+
+	/// Keeps track of the elements' usage, see [StoredMap::with_accounting]. The ends header isn't counted.
+	pub fn with_accounting(mut self) -> Self {
+		self.map = self.map.with_accounting();
+		self
 	}
 
 	#[inline]
@@ -51,22 +90,33 @@ impl<V: SerializableItem> StoredVecDeque<V> {
 		storage_write_item(self.namespace, &value).expect("2 u32's should never fail to serialize");
 	}
 
+	/// Updates the ends in memory, and in storage once the batch is committed.
+	fn set_ends_batched(&mut self, batch: &mut WriteBatch, value: QueueEnds) {
+		self.ends = value;
+		batch.set_header(
+			self.namespace,
+			value
+				.serialize_as_ref()
+				.expect("QueueEnds is serializable as ref")
+				.to_vec(),
+		);
+	}
+
 	#[inline]
 	pub fn ends(&self) -> QueueEnds {
 		self.ends
 	}
 
+	/// Converts an index counting from the front to the key the element is stored under. Both ends move freely around
+	/// the u32 range, so the keys of a queue may wrap from `u32::MAX` to 0.
 	fn to_raw_index(&self, index: u32) -> u32 {
 		index.wrapping_add(self.ends().front)
 	}
 
 	pub fn len(&self) -> u32 {
 		let ends = self.ends();
-		if ends.back >= ends.front {
-			ends.back - ends.front
-		} else {
-			u32::MAX - (ends.front - ends.back) + 1
-		}
+		// The queue is never full, so this can't wrap all the way around to 0
+		ends.back.wrapping_sub(ends.front)
 	}
 	pub fn get(&self, index: u32) -> StdResult<Option<OZeroCopy<V>>> {
 		if index >= self.len() {
@@ -76,13 +126,32 @@ impl<V: SerializableItem> StoredVecDeque<V> {
 		self.map.get(&self.to_raw_index(index))
 	}
 	pub fn set(&self, index: u32, value: &V) -> StdResult<()> {
+		Ok(self.try_set(index, value)?)
+	}
+
+	/// Same as `set`, but returns a [StorageWriteError] which can be matched on to tell a value which is too large
+	/// apart from other errors.
+	pub fn try_set(&self, index: u32, value: &V) -> Result<(), StorageWriteError> {
+		if index >= self.len() {
+			return Err(StdError::not_found("StoredVecDeque out of bounds").into());
+		}
+		self.map.try_set(&self.to_raw_index(index), value)
+	}
+
+	/// Same as `set`, but the write is added to `batch` instead of being applied immediately.
+	pub fn set_batched(&self, batch: &mut WriteBatch, index: u32, value: &V) -> StdResult<()> {
 		if index >= self.len() {
 			return Err(StdError::not_found("StoredVecDeque out of bounds"));
 		}
-		self.map.set(&self.to_raw_index(index), value)
+		self.map.set_batched(batch, &self.to_raw_index(index), value)
 	}
 
+	/// Swaps the elements at the two indices, which count from the front like the ones given to `get` and `set`.
 	pub fn swap(&self, index1: u32, index2: u32) -> StdResult<()> {
+		let len = self.len();
+		if index1 >= len || index2 >= len {
+			return Err(StdError::not_found("StoredVecDeque out of bounds"));
+		}
 		let index1 = self.to_raw_index(index1);
 		let index2 = self.to_raw_index(index2);
 		let tmp_value = self
@@ -95,8 +164,8 @@ impl<V: SerializableItem> StoredVecDeque<V> {
 				.map
 				.get_raw_bytes(&index2)
 				.ok_or(StdError::not_found("StoredVecDeque out of bounds"))?,
-		);
-		self.map.set_raw_bytes(&index2, &tmp_value);
+		)?;
+		self.map.set_raw_bytes(&index2, &tmp_value)?;
 		Ok(())
 	}
 
@@ -108,6 +177,36 @@ impl<V: SerializableItem> StoredVecDeque<V> {
 		IndexedStoredItemIter::new(self.namespace, ends.front, ends.back)
 	}
 
+	/// Iterates over the elements along with their index, counting from the front.
+	pub fn iter_enumerated(&self) -> StoredVecDequeEnumeratedIter<V> {
+		self.iter_range_logical(..)
+	}
+
+	/// Like [iter_enumerated](Self::iter_enumerated), but only over the elements within the range of indices. The
+	/// range is clamped to [len](Self::len).
+	pub fn iter_range_logical(&self, range: impl RangeBounds<u32>) -> StoredVecDequeEnumeratedIter<V> {
+		let len = self.len();
+		let end = match range.end_bound() {
+			Bound::Included(end) => end.saturating_add(1),
+			Bound::Excluded(end) => *end,
+			Bound::Unbounded => len,
+		}
+		.min(len);
+		let start = match range.start_bound() {
+			Bound::Included(start) => *start,
+			Bound::Excluded(start) => start.saturating_add(1),
+			Bound::Unbounded => 0,
+		}
+		.min(end);
+		StoredVecDequeEnumeratedIter {
+			namespace: self.namespace,
+			front: self.ends.front,
+			start,
+			end,
+			value_type: PhantomData,
+		}
+	}
+
 	#[inline]
 	pub fn is_empty(&self) -> bool {
 		self.ends.front == self.ends.back
@@ -142,21 +241,40 @@ impl<V: SerializableItem> StoredVecDeque<V> {
 		let mut ends = self.ends();
 		ends.back = self.ends.back.wrapping_sub(1);
 		let result = self.map.get(&ends.back)?;
-		self.map.remove(&ends.back);
+		self.map.try_remove(&ends.back)?;
 		self.set_ends(ends);
 		Ok(result)
 	}
 
 	pub fn push_back(&mut self, value: &V) -> StdResult<()> {
+		Ok(self.try_push_back(value)?)
+	}
+
+	/// Same as `push_back`, but returns a [StorageWriteError] which can be matched on to tell a value which is too
+	/// large apart from other errors.
+	pub fn try_push_back(&mut self, value: &V) -> Result<(), StorageWriteError> {
 		let mut ends = self.ends();
 		if ends.back.wrapping_add(1) == ends.front {
 			return Err(StdError::generic_err("StoredVecQueue is full"))?;
 		}
-		self.map.set(&ends.back, value)?;
+		self.map.try_set(&ends.back, value)?;
 		ends.back = ends.back.wrapping_add(1);
 		self.set_ends(ends);
 		Ok(())
 	}
+
+	/// Same as `push_back`, but the writes are added to `batch` instead of being applied immediately. See [WriteBatch]
+	/// for how the ends are handled.
+	pub fn push_back_batched(&mut self, batch: &mut WriteBatch, value: &V) -> StdResult<()> {
+		let mut ends = self.ends();
+		if ends.back.wrapping_add(1) == ends.front {
+			return Err(StdError::generic_err("StoredVecQueue is full"))?;
+		}
+		self.map.set_batched(batch, &ends.back, value)?;
+		ends.back = ends.back.wrapping_add(1);
+		self.set_ends_batched(batch, ends);
+		Ok(())
+	}
 	pub fn get_front(&self) -> StdResult<Option<OZeroCopy<V>>> {
 		if self.is_empty() {
 			return Ok(None);
@@ -176,22 +294,94 @@ impl<V: SerializableItem> StoredVecDeque<V> {
 		}
 		let mut ends = self.ends();
 		let result = self.map.get(&ends.front)?;
-		self.map.remove(&ends.front);
+		self.map.try_remove(&ends.front)?;
 		ends.front = ends.front.wrapping_add(1);
 		self.set_ends(ends);
 		Ok(result)
 	}
 
+	/// Removes up to `max` elements from the front, returning them in the order `pop_front` would. Unlike repeated
+	/// calls to `pop_front`, the ends are only written once.
+	pub fn drain_front(&mut self, max: u32) -> StdResult<Vec<OZeroCopy<V>>> {
+		let count = max.min(self.len());
+		if count == 0 {
+			return Ok(Vec::new());
+		}
+		let mut ends = self.ends();
+		let indices = (0..count).map(|i| ends.front.wrapping_add(i));
+		let result = self.drain_indices(indices)?;
+		ends.front = ends.front.wrapping_add(count);
+		self.set_ends(ends);
+		Ok(result)
+	}
+
+	/// Removes up to `max` elements from the back, returning them in the order `pop_back` would. Unlike repeated
+	/// calls to `pop_back`, the ends are only written once.
+	pub fn drain_back(&mut self, max: u32) -> StdResult<Vec<OZeroCopy<V>>> {
+		let count = max.min(self.len());
+		if count == 0 {
+			return Ok(Vec::new());
+		}
+		let mut ends = self.ends();
+		let indices = (1..=count).map(|i| ends.back.wrapping_sub(i));
+		let result = self.drain_indices(indices)?;
+		ends.back = ends.back.wrapping_sub(count);
+		self.set_ends(ends);
+		Ok(result)
+	}
+
+	/// Reads all the elements before removing any, so that nothing is removed if one can't be read.
+	fn drain_indices(&mut self, indices: impl Iterator<Item = u32> + Clone) -> StdResult<Vec<OZeroCopy<V>>> {
+		let result = indices
+			.clone()
+			.map(|index| {
+				self.map
+					.get(&index)?
+					.ok_or(StdError::not_found("StoredVecDeque element missing from storage"))
+			})
+			.collect::<StdResult<Vec<_>>>()?;
+		for index in indices {
+			self.map.try_remove(&index)?;
+		}
+		Ok(result)
+	}
+
 	pub fn push_front(&mut self, value: &V) -> StdResult<()> {
+		Ok(self.try_push_front(value)?)
+	}
+
+	/// Same as `push_front`, but returns a [StorageWriteError] which can be matched on to tell a value which is too
+	/// large apart from other errors.
+	pub fn try_push_front(&mut self, value: &V) -> Result<(), StorageWriteError> {
 		let mut ends = self.ends();
 		ends.front = ends.front.wrapping_sub(1);
 		if ends.front == ends.back {
 			return Err(StdError::generic_err("StoredVecQueue is full"))?;
 		}
-		self.map.set(&ends.front, value)?;
+		self.map.try_set(&ends.front, value)?;
 		self.set_ends(ends);
 		Ok(())
 	}
+
+	/// Same as `push_front`, but the writes are added to `batch` instead of being applied immediately. See
+	/// [WriteBatch] for how the ends are handled.
+	pub fn push_front_batched(&mut self, batch: &mut WriteBatch, value: &V) -> StdResult<()> {
+		let mut ends = self.ends();
+		ends.front = ends.front.wrapping_sub(1);
+		if ends.front == ends.back {
+			return Err(StdError::generic_err("StoredVecQueue is full"))?;
+		}
+		self.map.set_batched(batch, &ends.front, value)?;
+		self.set_ends_batched(batch, ends);
+		Ok(())
+	}
+}
+
+impl<V: SerializableItem> Accounted for StoredVecDeque<V> {
+	#[inline]
+	fn accounting(&self) -> StdResult<NamespaceUsage> {
+		self.map.accounting()
+	}
 }
 
 impl<V: SerializableItem> IntoIterator for StoredVecDeque<V> {
@@ -210,14 +400,74 @@ impl<V: SerializableItem> IntoIterator for &StoredVecDeque<V> {
 		IndexedStoredItemIter::new(self.namespace, ends.front, ends.back)
 	}
 }
+/// Iterates over the elements of a [StoredVecDeque] along with their index, see
+/// [iter_enumerated](StoredVecDeque::iter_enumerated).
+///
+/// An element which is missing from storage is returned as a [StdError::NotFound] rather than ending the iteration.
+pub struct StoredVecDequeEnumeratedIter<V: SerializableItem> {
+	namespace: &'static [u8],
+	front: u32,
+	start: u32,
+	end: u32,
+	value_type: PhantomData<V>,
+}
+impl<V: SerializableItem> StoredVecDequeEnumeratedIter<V> {
+	fn read(&self, index: u32) -> (u32, StdResult<OZeroCopy<V>>) {
+		let raw_index = index.wrapping_add(self.front);
+		let value = storage_read_namespaced_item(
+			&concat_byte_array_pairs(self.namespace, &raw_index.to_le_bytes()),
+			self.namespace.len(),
+		)
+		.and_then(|value| value.ok_or_else(|| StdError::not_found("StoredVecDeque element")));
+		(index, value)
+	}
+}
+impl<V: SerializableItem> Iterator for StoredVecDequeEnumeratedIter<V> {
+	type Item = (u32, StdResult<OZeroCopy<V>>);
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.start == self.end {
+			return None;
+		}
+		self.start += 1;
+		Some(self.read(self.start - 1))
+	}
+
+	fn nth(&mut self, n: usize) -> Option<Self::Item> {
+		self.start = u32::try_from(n).map_or(self.end, |n| self.start.saturating_add(n).min(self.end));
+		self.next()
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = (self.end - self.start) as usize;
+		(len, Some(len))
+	}
+}
+impl<V: SerializableItem> DoubleEndedIterator for StoredVecDequeEnumeratedIter<V> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		if self.start == self.end {
+			return None;
+		}
+		self.end -= 1;
+		Some(self.read(self.end))
+	}
+
+	fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+		self.end = u32::try_from(n).map_or(self.start, |n| self.end.saturating_sub(n).max(self.start));
+		self.next_back()
+	}
+}
+impl<V: SerializableItem> ExactSizeIterator for StoredVecDequeEnumeratedIter<V> {}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
 	use crate::storage::{
-		base::{storage_has, storage_remove},
+		base::{storage_has, storage_remove, ThreadStorageGuard},
 		testing_common::*,
 	};
 	use std::collections::VecDeque;
+	use std::ops::Bound;
+	use std::sync::atomic::Ordering;
 
 	type TestingResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error>>;
 	const NAMESPACE: &[u8] = b"testing";
@@ -232,7 +482,7 @@ mod tests {
 
 		let val = queue.get(3);
 
-		// assert_eq!(queue.len(), 3); XXX: won't work until StoredVecDeque::len is fixed
+		assert_eq!(queue.len(), 3);
 		assert_eq!(val, Ok(None));
 		assert_eq!(queue.pop_back()?.map(|ozc| { ozc.into_inner() }), Some(1));
 		assert_eq!(queue.len(), 2);
@@ -265,6 +515,139 @@ mod tests {
 		Ok(())
 	}
 
+	fn contents(queue: &StoredVecDeque<u16>) -> StdResult<Vec<u16>> {
+		queue.iter().map(|value| value.map(OZeroCopy::into_inner)).collect()
+	}
+
+	#[test]
+	fn wraps_around_u32_max() -> TestingResult {
+		let _storage = isolated()?;
+		let mut queue = StoredVecDeque::<u16>::new(NAMESPACE);
+		queue.set_ends(QueueEnds {
+			front: u32::MAX - 1,
+			back: u32::MAX - 1,
+		});
+		for value in 0..4 {
+			queue.push_back(&value)?;
+		}
+		assert_eq!(queue.ends().back, 2);
+		assert_eq!(queue.len(), 4);
+		assert_eq!(contents(&queue)?, [0, 1, 2, 3]);
+		for index in 0..4 {
+			assert_eq!(queue.get(index)?.map(OZeroCopy::into_inner), Some(index as u16));
+		}
+		assert_eq!(queue.get(4)?, None);
+		queue.set(2, &20)?;
+		assert!(queue.set(4, &40).is_err());
+		// Swaps across the wrap point
+		queue.swap(0, 3)?;
+		queue.swap(1, 2)?;
+		assert!(queue.swap(1, 4).is_err());
+		assert_eq!(contents(&StoredVecDeque::<u16>::new(NAMESPACE))?, [3, 20, 1, 0]);
+		Ok(())
+	}
+
+	#[test]
+	fn drain_writes_ends_once() -> TestingResult {
+		let storage = CountingStorage::default();
+		let writes = storage.writes.clone();
+		let _storage = ThreadStorageGuard::new(Box::new(storage));
+		let mut queue = StoredVecDeque::<u16>::new(NAMESPACE);
+		// Nothing is written when there's nothing to drain
+		assert!(queue.drain_front(5)?.is_empty());
+		assert!(queue.drain_back(5)?.is_empty());
+		assert_eq!(writes.load(Ordering::SeqCst), 0);
+		for value in 0..10 {
+			queue.push_back(&value)?;
+		}
+
+		let writes_before = writes.load(Ordering::SeqCst);
+		let drained: Vec<u16> = queue.drain_front(4)?.into_iter().map(OZeroCopy::into_inner).collect();
+		assert_eq!(drained, [0, 1, 2, 3]);
+		// One removal per element and a single write of the ends
+		assert_eq!(writes.load(Ordering::SeqCst) - writes_before, 4 + 1);
+
+		let writes_before = writes.load(Ordering::SeqCst);
+		let drained: Vec<u16> = queue.drain_back(2)?.into_iter().map(OZeroCopy::into_inner).collect();
+		assert_eq!(drained, [9, 8]);
+		assert_eq!(writes.load(Ordering::SeqCst) - writes_before, 2 + 1);
+
+		let writes_before = writes.load(Ordering::SeqCst);
+		assert!(queue.drain_front(0)?.is_empty());
+		assert_eq!(writes.load(Ordering::SeqCst), writes_before);
+
+		let queue = StoredVecDeque::<u16>::new(NAMESPACE);
+		assert_eq!(contents(&queue)?, [4, 5, 6, 7]);
+		Ok(())
+	}
+
+	#[test]
+	fn drain_past_len_and_wrapped() -> TestingResult {
+		let _storage = isolated()?;
+		let mut queue = StoredVecDeque::<u16>::new(NAMESPACE);
+		queue.set_ends(QueueEnds {
+			front: u32::MAX - 2,
+			back: u32::MAX - 2,
+		});
+		for value in 0..6 {
+			queue.push_back(&value)?;
+		}
+		assert_eq!(queue.ends().back, 3);
+		let drained: Vec<u16> = queue.drain_front(4)?.into_iter().map(OZeroCopy::into_inner).collect();
+		assert_eq!(drained, [0, 1, 2, 3]);
+		assert_eq!(queue.ends().front, 1);
+		assert_eq!(contents(&queue)?, [4, 5]);
+
+		queue.push_front(&3)?;
+		queue.push_front(&2)?;
+		assert_eq!(queue.ends().front, u32::MAX);
+		let drained: Vec<u16> = queue.drain_back(100)?.into_iter().map(OZeroCopy::into_inner).collect();
+		assert_eq!(drained, [5, 4, 3, 2]);
+		assert!(queue.is_empty());
+		assert_eq!(queue.drain_back(1)?.len(), 0);
+		let queue = StoredVecDeque::<u16>::new(NAMESPACE);
+		assert!(queue.is_empty());
+		for index in [u32::MAX - 2, u32::MAX - 1, u32::MAX, 0, 1, 2] {
+			assert!(!storage_has(&concat_byte_array_pairs(NAMESPACE, &index.to_le_bytes())));
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn mixed_pushes_and_pops() -> TestingResult {
+		let _storage = isolated()?;
+		let mut queue = StoredVecDeque::<u16>::new(NAMESPACE);
+		let mut expected = VecDeque::new();
+		for value in 0..20u16 {
+			if value % 3 == 0 {
+				queue.push_back(&value)?;
+				expected.push_back(value);
+			} else {
+				queue.push_front(&value)?;
+				expected.push_front(value);
+			}
+			assert_eq!(queue.len() as usize, expected.len());
+		}
+		assert_eq!(contents(&queue)?, Vec::from(expected.clone()));
+		for (index, value) in expected.iter().enumerate() {
+			assert_eq!(queue.get(index as u32)?.map(OZeroCopy::into_inner), Some(*value));
+		}
+
+		while !expected.is_empty() {
+			if expected.len() % 2 == 0 {
+				assert_eq!(queue.pop_front()?.map(OZeroCopy::into_inner), expected.pop_front());
+			} else {
+				assert_eq!(queue.pop_back()?.map(OZeroCopy::into_inner), expected.pop_back());
+			}
+			assert_eq!(queue.len() as usize, expected.len());
+		}
+		assert!(queue.is_empty());
+		assert_eq!(queue.get(0)?, None);
+		assert_eq!(queue.pop_front()?, None);
+		assert!(contents(&queue)?.is_empty());
+		Ok(())
+	}
+
 	#[test]
 	fn queue() -> TestingResult {
 		let _storage_lock = init()?;
@@ -282,25 +665,27 @@ mod tests {
 		assert_eq!(Some(OZeroCopy::from_inner(1234)), queue.get_front()?);
 		assert_eq!(Some(OZeroCopy::from_inner(1234)), queue.get(0)?);
 		assert_eq!(Some(OZeroCopy::from_inner(69)), queue.get_back()?);
-		assert_eq!(Some(69), queue.get(1)?.map(OZeroCopy::into_inner)); // XXX: BROKEN BECAUSE OF queue.len()
+		assert_eq!(Some(69), queue.get(1)?.map(OZeroCopy::into_inner));
 
 		queue.set(0, &69)?;
-		// queue.set(u32::MAX - 1, &420)?;
+		// Indices count from the front, not the raw keys the elements are stored under
+		assert!(queue.set(u32::MAX, &420).is_err());
 
 		assert_eq!(Some(OZeroCopy::from_inner(69)), queue.get(0)?);
-		// assert_eq!(Some(420), queue.get(u32::MAX - 1)?);
+		assert_eq!(None, queue.get(u32::MAX)?);
 
 		queue.set_front(&420)?;
 		queue.set_back(&69)?;
 		assert_eq!(Some(OZeroCopy::from_inner(420)), queue.get_front()?);
 		assert_eq!(Some(OZeroCopy::from_inner(69)), queue.get_back()?);
+		// The front was pushed below 0, so the queue's keys wrap around
+		assert!(queue.ends().front > queue.ends().back);
 		let ends = queue.ends();
-		dbg!(ends.clone());
-
-		// XXX: broken
 		assert!(queue.swap(ends.front, ends.back).is_err());
-		// assert_eq!(Some(69), queue.get_front()?);
-		// assert_eq!(Some(420), queue.get_back()?);
+		assert!(queue.swap(0, queue.len()).is_err());
+		queue.swap(0, queue.len() - 1)?;
+		assert_eq!(Some(OZeroCopy::from_inner(69)), queue.get_front()?);
+		assert_eq!(Some(OZeroCopy::from_inner(420)), queue.get_back()?);
 
 		queue.clear(true);
 		assert!(queue.set_front(&69).is_err());
@@ -389,6 +774,32 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn try_new_headers() -> TestingResult {
+		let _storage_lock = init()?;
+		let mut vec = crate::storage::vec::StoredVec::<u16>::new(NAMESPACE);
+		vec.push(&69)?;
+		vec.push(&420)?;
+
+		// The vec's 4 byte header is upgraded
+		let mut queue = StoredVecDeque::<u16>::try_new(NAMESPACE)?;
+		assert_eq!(queue.len(), 2);
+		queue.push_front(&1)?;
+		let queue = StoredVecDeque::<u16>::try_new(NAMESPACE)?;
+		assert_eq!((queue.ends().front, queue.ends().back), (u32::MAX, 2));
+		assert_eq!(queue.get(0)?.map(OZeroCopy::into_inner), Some(1));
+
+		for header in [&[1u8][..], &[1, 0, 0, 0, 0, 0], &[0; 12]] {
+			crate::storage::base::storage_write(NAMESPACE, header);
+			assert_eq!(
+				StoredVecDeque::<u16>::try_new(NAMESPACE).err(),
+				Some(StdError::invalid_data_size(8, header.len()))
+			);
+			assert_eq!(StoredVecDeque::<u16>::new(NAMESPACE).len(), 0);
+		}
+		Ok(())
+	}
+
 	#[test]
 	fn queue_length() -> TestingResult {
 		let _storage_lock = init()?;
@@ -471,6 +882,63 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn enumerated_iteration() -> TestingResult {
+		let _storage = isolated()?;
+		let mut queue = StoredVecDeque::<u16>::new(NAMESPACE);
+		for value in 10..15 {
+			queue.push_back(&value)?;
+		}
+		for value in (5..10).rev() {
+			queue.push_front(&value)?;
+		}
+		// The front has wrapped around to the end of the raw indices
+		assert!(queue.ends().front > queue.ends().back);
+
+		let enumerated: Vec<(u32, u16)> = queue
+			.iter_enumerated()
+			.map(|(index, value)| (index, value.unwrap().into_inner()))
+			.collect();
+		assert_eq!(enumerated.len(), 10);
+		for (index, value) in enumerated.iter() {
+			assert_eq!(queue.get(*index)?.map(OZeroCopy::into_inner), Some(*value));
+			assert_eq!(*value as u32, index + 5);
+		}
+		let reversed: Vec<u32> = queue.iter_enumerated().rev().map(|(index, _)| index).collect();
+		assert_eq!(reversed, (0..10).rev().collect::<Vec<_>>());
+
+		let in_range = |range: (Bound<u32>, Bound<u32>)| -> Vec<(u32, u16)> {
+			queue
+				.iter_range_logical(range)
+				.map(|(index, value)| (index, value.unwrap().into_inner()))
+				.collect()
+		};
+		assert_eq!(
+			in_range((Bound::Included(3), Bound::Excluded(6))),
+			[(3, 8), (4, 9), (5, 10)]
+		);
+		assert_eq!(in_range((Bound::Excluded(7), Bound::Unbounded)), [(8, 13), (9, 14)]);
+		assert_eq!(
+			in_range((Bound::Included(8), Bound::Included(u32::MAX))),
+			[(8, 13), (9, 14)]
+		);
+		assert!(in_range((Bound::Included(6), Bound::Excluded(2))).is_empty());
+		assert!(queue.iter_range_logical(20..).next().is_none());
+
+		let mut iter = queue.iter_range_logical(2..8);
+		assert_eq!(iter.len(), 6);
+		assert_eq!(iter.next_back().map(|(index, _)| index), Some(7));
+		assert_eq!(iter.nth(2).map(|(index, _)| index), Some(4));
+		assert_eq!(iter.nth_back(1).map(|(index, _)| index), Some(5));
+		assert!(iter.next().is_none());
+
+		// Missing elements are reported rather than silently ending the iteration
+		queue.map.remove(&queue.to_raw_index(1));
+		let results: Vec<bool> = queue.iter_range_logical(..3).map(|(_, value)| value.is_ok()).collect();
+		assert_eq!(results, [true, false, true]);
+		Ok(())
+	}
+
 	// #[test]
 	// fn queue_is_full() -> TestingResult {
 	// 	let _storage_lock = init()?;
@@ -0,0 +1,261 @@
+//! Range queries on [StoredMap] shaped like cw-storage-plus's, so that code using `Map::range` and `Map::keys` can be
+//! ported by replacing the map type and dropping the `storage` argument.
+//!
+//! As with any other [StoredMap] iteration, entries are ordered by their serialized keys, so these methods are only
+//! available for [OrderedKey] types, whose encoding sorts the same way as cw-storage-plus. Little-endian integers and
+//! borsh strings don't, use [BigEndianKey] for integer keys instead.
+use cosmwasm_std::{Decimal, Decimal256, Int256, Order, StdResult, Timestamp, Uint128, Uint256, Uint512, Uint64};
+
+use super::{
+	big_endian_key::BigEndianKey, concat_byte_array_pairs, map::StoredMap, SerializableItem, StorageKeyIterator,
+	StoragePairIterator,
+};
+use crate::{data_types::canonical_addr::SeiCanonicalAddr, utils::lexicographic_next};
+
+/// Marker for key types whose serialized bytes sort in the same order as the values themselves, which
+/// [StoredMap::range] and [StoredMap::keys] rely on for their bounds.
+pub trait OrderedKey: SerializableItem {}
+
+macro_rules! impl_ordered_key {
+	( $($key_type:ty),* ) => {
+		$(impl OrderedKey for $key_type {})*
+	};
+}
+impl_ordered_key!(
+	u8,
+	SeiCanonicalAddr,
+	Uint64,
+	Uint128,
+	Uint256,
+	Uint512,
+	Int256,
+	Decimal,
+	Decimal256,
+	Timestamp
+);
+impl_ordered_key!(
+	BigEndianKey<u8>,
+	BigEndianKey<u16>,
+	BigEndianKey<u32>,
+	BigEndianKey<u64>,
+	BigEndianKey<u128>,
+	BigEndianKey<i8>,
+	BigEndianKey<i16>,
+	BigEndianKey<i32>,
+	BigEndianKey<i64>,
+	BigEndianKey<i128>
+);
+impl<const N: usize> OrderedKey for [u8; N] {}
+
+/// The equivalent of `cw_storage_plus::Bound`, an inclusive or exclusive limit of a range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CwBound<K> {
+	Inclusive(K),
+	Exclusive(K),
+}
+impl<K> CwBound<K> {
+	pub fn inclusive<T: Into<K>>(limit: T) -> Self {
+		Self::Inclusive(limit.into())
+	}
+	pub fn exclusive<T: Into<K>>(limit: T) -> Self {
+		Self::Exclusive(limit.into())
+	}
+}
+
+/// What [StoredMap::range] and [StoredMap::keys] return, errors are returned per entry as with cw-storage-plus.
+pub type CwRangeIter<T> = Box<dyn Iterator<Item = StdResult<T>>>;
+
+impl<K: OrderedKey + 'static, V: SerializableItem + 'static> StoredMap<K, V> {
+	/// Returns the `[start, end)` storage key range between the bounds, or `None` if it's empty.
+	fn cw_bound_range(&self, min: Option<CwBound<K>>, max: Option<CwBound<K>>) -> Option<(Vec<u8>, Vec<u8>)> {
+		let start = match min {
+			Some(CwBound::Inclusive(key)) => self.key(&key),
+			// The smallest key which comes after `key`
+			Some(CwBound::Exclusive(key)) => concat_byte_array_pairs(&self.key(&key), &[0]),
+			None => self.namespace().to_vec(),
+		};
+		let end = match max {
+			Some(CwBound::Inclusive(key)) => concat_byte_array_pairs(&self.key(&key), &[0]),
+			Some(CwBound::Exclusive(key)) => self.key(&key),
+			None => lexicographic_next(self.namespace()),
+		};
+		(start < end).then_some((start, end))
+	}
+
+	/// The equivalent of `cw_storage_plus::Map::range`, returns the owned key/value pairs between the bounds.
+	///
+	/// Unlike [iter_range](Self::iter_range), entries which fail to deserialize are returned as errors rather than
+	/// being skipped.
+	pub fn range(
+		&self,
+		min: Option<CwBound<K>>,
+		max: Option<CwBound<K>>,
+		order: Order,
+	) -> StdResult<CwRangeIter<(K, V)>> {
+		let Some((start, end)) = self.cw_bound_range(min, max) else {
+			return Ok(Box::new(std::iter::empty()));
+		};
+		let key_slicing = self.namespace().len();
+		let iter = StoragePairIterator::new(Some(&start), Some(&end)).map(move |(key_bytes, value_bytes)| {
+			Ok((
+				K::deserialize_to_owned(&key_bytes[key_slicing..])?,
+				V::deserialize_to_owned(&value_bytes)?,
+			))
+		});
+		Ok(match order {
+			Order::Ascending => Box::new(iter),
+			Order::Descending => Box::new(iter.rev()),
+		})
+	}
+
+	/// The equivalent of `cw_storage_plus::Map::keys`, returns the keys between the bounds.
+	pub fn keys(&self, min: Option<CwBound<K>>, max: Option<CwBound<K>>, order: Order) -> StdResult<CwRangeIter<K>> {
+		let Some((start, end)) = self.cw_bound_range(min, max) else {
+			return Ok(Box::new(std::iter::empty()));
+		};
+		let key_slicing = self.namespace().len();
+		let iter = StorageKeyIterator::new(Some(&start), Some(&end))
+			.map(move |key_bytes| K::deserialize_to_owned(&key_bytes[key_slicing..]));
+		Ok(match order {
+			Order::Ascending => Box::new(iter),
+			Order::Descending => Box::new(iter.rev()),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::testing::MockStorage;
+	use cw_storage_plus::{Bound, Map};
+
+	use super::*;
+	use crate::{data_types::canonical_addr::SeiCanonicalAddr, storage::testing_common::*};
+
+	const REFERENCE: Map<u8, u32> = Map::new("reference");
+
+	type BoundPair<K> = (Option<CwBound<K>>, Option<CwBound<K>>);
+
+	fn to_reference_bound(bound: &Option<CwBound<u8>>) -> Option<Bound<'static, u8>> {
+		match bound {
+			Some(CwBound::Inclusive(key)) => Some(Bound::inclusive(*key)),
+			Some(CwBound::Exclusive(key)) => Some(Bound::exclusive(*key)),
+			None => None,
+		}
+	}
+
+	fn all_bounds(lo: u8, hi: u8) -> Vec<BoundPair<u8>> {
+		let mins = [None, Some(CwBound::inclusive(lo)), Some(CwBound::exclusive(lo))];
+		let maxes = [None, Some(CwBound::inclusive(hi)), Some(CwBound::exclusive(hi))];
+		mins.iter()
+			.flat_map(|min| maxes.iter().map(|max| (min.clone(), max.clone())))
+			.collect()
+	}
+
+	#[test]
+	fn matches_cw_storage_plus() -> TestingResult {
+		let _storage = isolated()?;
+		let map = StoredMap::<u8, u32>::new(NAMESPACE);
+		let mut reference_storage = MockStorage::new();
+		for key in (0..=40).step_by(4) {
+			map.set(&key, &(key as u32 * 100))?;
+			REFERENCE.save(&mut reference_storage, key, &(key as u32 * 100))?;
+		}
+		// Bounds on existing keys, between keys, outside of the range and crossed over
+		for (lo, hi) in [(8, 24), (9, 23), (0, 40), (0, 255), (24, 8), (12, 12)] {
+			for (min, max) in all_bounds(lo, hi) {
+				for order in [Order::Ascending, Order::Descending] {
+					let expected = REFERENCE
+						.range(
+							&reference_storage,
+							to_reference_bound(&min),
+							to_reference_bound(&max),
+							order,
+						)
+						.collect::<StdResult<Vec<_>>>()?;
+					let expected_keys = REFERENCE
+						.keys(
+							&reference_storage,
+							to_reference_bound(&min),
+							to_reference_bound(&max),
+							order,
+						)
+						.collect::<StdResult<Vec<_>>>()?;
+					let context = format!("{min:?} {max:?} {}", order == Order::Ascending);
+					assert_eq!(
+						map.range(min.clone(), max.clone(), order)?
+							.collect::<StdResult<Vec<_>>>()?,
+						expected,
+						"{context}"
+					);
+					assert_eq!(
+						map.keys(min.clone(), max.clone(), order)?
+							.collect::<StdResult<Vec<_>>>()?,
+						expected_keys,
+						"{context}"
+					);
+				}
+			}
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn big_endian_keys_past_one_byte() -> TestingResult {
+		const REFERENCE_U64: Map<u64, u32> = Map::new("reference");
+		let _storage = isolated()?;
+		let map = StoredMap::<BigEndianKey<u64>, u32>::new(NAMESPACE);
+		let mut reference_storage = MockStorage::new();
+		let keys = [1u64, 255, 256, 300, 65_536, 1 << 40, u64::MAX];
+		for (i, key) in keys.into_iter().enumerate() {
+			map.set(&key.into(), &(i as u32))?;
+			REFERENCE_U64.save(&mut reference_storage, key, &(i as u32))?;
+		}
+		for order in [Order::Ascending, Order::Descending] {
+			let expected = REFERENCE_U64
+				.range(
+					&reference_storage,
+					Some(Bound::exclusive(255u64)),
+					Some(Bound::inclusive(1u64 << 40)),
+					order,
+				)
+				.collect::<StdResult<Vec<_>>>()?;
+			let actual = map
+				.range(
+					Some(CwBound::exclusive(255u64)),
+					Some(CwBound::inclusive(1u64 << 40)),
+					order,
+				)?
+				.map(|entry| entry.map(|(key, value)| (key.into_inner(), value)))
+				.collect::<StdResult<Vec<_>>>()?;
+			assert_eq!(actual, expected);
+			assert_eq!(actual.len(), 4);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn address_keys_and_errors() -> TestingResult {
+		let _storage = isolated()?;
+		let map = StoredMap::<SeiCanonicalAddr, String>::new(NAMESPACE);
+		let addrs = [[1u8; 32], [2; 32], [3; 32]].map(SeiCanonicalAddr::from);
+		for addr in addrs.iter() {
+			map.set(addr, &addr.to_string())?;
+		}
+		let keys = map
+			.keys(
+				Some(CwBound::exclusive(addrs[0])),
+				Some(CwBound::inclusive(addrs[2])),
+				Order::Descending,
+			)?
+			.collect::<StdResult<Vec<_>>>()?;
+		assert_eq!(keys, [addrs[2], addrs[1]]);
+
+		// Values which can't be deserialized are surfaced rather than skipped
+		StoredMap::<SeiCanonicalAddr, u8>::new(NAMESPACE).set(&addrs[1], &255)?;
+		let entries = map.range(None, None, Order::Ascending)?.collect::<Vec<_>>();
+		assert_eq!(entries.len(), 3);
+		assert!(entries[0].is_ok());
+		assert!(entries[1].is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,154 @@
+use cosmwasm_std::StdError;
+use thiserror::Error;
+
+use super::{
+	base::{storage_has, storage_remove, storage_write},
+	concat_byte_array_pairs,
+};
+
+/// All reentrancy markers live under this prefix. The leading 0xff keeps them out of the way of any human-readable
+/// namespaces.
+pub const REENTRANCY_NAMESPACE: &[u8] = b"\xffreentrancy/";
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ReentrancyError {
+	#[error("Reentrancy detected for scope \"{0}\"")]
+	AlreadyEntered(String),
+}
+impl From<ReentrancyError> for StdError {
+	fn from(value: ReentrancyError) -> Self {
+		StdError::generic_err(value.to_string())
+	}
+}
+
+/// Guards against handler logic being re-entered while it's still running.
+///
+/// # Semantics
+///
+/// `enter` writes a marker to storage and returns a `ReentrancyToken`, any other `enter` call with the same scope will
+/// fail with `ReentrancyError::AlreadyEntered` until the marker is removed. The marker is removed when the token is
+/// dropped, or when `ReentrancyToken::exit` is called.
+///
+/// Storage writes are _not_ ephemeral, they persist across submessages within the same transaction, and across
+/// transactions if the transaction succeeds. Keep the following in mind:
+///
+/// * Submessages are only ran _after_ your entry point returns. A token which is dropped at the end of your handler
+///   does not protect the submessages it dispatches. If you need the scope to stay locked until a reply comes back,
+///   call `ReentrancyToken::persist` and then `ReentrancyGuard::release` in your reply handler.
+/// * If a persisted marker is never released (e.g. the reply handler is never invoked because you used
+///   `ReplyOn::Never`), the scope stays locked forever. Only persist when you have a guaranteed release path.
+/// * If your entry point returns an error, all storage writes (including the marker) are reverted by the chain, so a
+///   failed handler can never leave a marker behind.
+/// * A contract panic on wasm aborts without running destructors, which is fine for the same reason as above.
+pub struct ReentrancyGuard;
+impl ReentrancyGuard {
+	#[inline]
+	pub fn marker_key(scope: &str) -> Vec<u8> {
+		concat_byte_array_pairs(REENTRANCY_NAMESPACE, scope.as_bytes())
+	}
+	/// Locks the specified scope, returns an error if it's already locked.
+	pub fn enter(scope: &str) -> Result<ReentrancyToken, ReentrancyError> {
+		let key = Self::marker_key(scope);
+		if storage_has(&key) {
+			return Err(ReentrancyError::AlreadyEntered(scope.to_string()));
+		}
+		storage_write(&key, &[1]);
+		Ok(ReentrancyToken { key: Some(key) })
+	}
+	/// Returns true if the specified scope is currently locked.
+	pub fn is_entered(scope: &str) -> bool {
+		storage_has(&Self::marker_key(scope))
+	}
+	/// Unlocks a scope which was previously locked with `ReentrancyToken::persist`
+	pub fn release(scope: &str) {
+		storage_remove(&Self::marker_key(scope))
+	}
+	/// Runs the closure with the specified scope locked, the scope is unlocked afterwards regardless of whether or not
+	/// the closure returned an error.
+	pub fn with_guard<T, E, F>(scope: &str, f: F) -> Result<T, E>
+	where
+		E: From<ReentrancyError>,
+		F: FnOnce() -> Result<T, E>,
+	{
+		let token = Self::enter(scope)?;
+		let result = f();
+		token.exit();
+		result
+	}
+}
+
+/// Proof that a scope is locked, the scope is unlocked when this is dropped.
+#[must_use = "the scope is unlocked as soon as the token is dropped"]
+pub struct ReentrancyToken {
+	key: Option<Vec<u8>>,
+}
+impl ReentrancyToken {
+	/// Unlocks the scope. This is the same as dropping the token, but is more explicit.
+	pub fn exit(mut self) {
+		if let Some(key) = self.key.take() {
+			storage_remove(&key);
+		}
+	}
+	/// Consumes the token while leaving the scope locked. `ReentrancyGuard::release` must be called later.
+	pub fn persist(mut self) {
+		self.key = None;
+	}
+}
+impl Drop for ReentrancyToken {
+	fn drop(&mut self) {
+		if let Some(key) = self.key.take() {
+			storage_remove(&key);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	#[test]
+	fn nested_enter() -> TestingResult {
+		let _storage_lock = init()?;
+
+		let token = ReentrancyGuard::enter("swap")?;
+		assert!(ReentrancyGuard::is_entered("swap"));
+		assert_eq!(
+			ReentrancyGuard::enter("swap").err(),
+			Some(ReentrancyError::AlreadyEntered("swap".into()))
+		);
+		let other_token = ReentrancyGuard::enter("deposit")?;
+		drop(token);
+		assert!(!ReentrancyGuard::is_entered("swap"));
+		assert!(ReentrancyGuard::is_entered("deposit"));
+		other_token.exit();
+		assert!(!ReentrancyGuard::is_entered("deposit"));
+
+		let token = ReentrancyGuard::enter("swap")?;
+		token.persist();
+		assert!(ReentrancyGuard::enter("swap").is_err());
+		ReentrancyGuard::release("swap");
+		assert!(ReentrancyGuard::enter("swap").is_ok());
+
+		Ok(())
+	}
+
+	#[test]
+	fn with_guard_cleanup() -> TestingResult {
+		let _storage_lock = init()?;
+
+		let result = ReentrancyGuard::with_guard("swap", || -> Result<(), StdError> {
+			assert!(ReentrancyGuard::is_entered("swap"));
+			let nested = ReentrancyGuard::with_guard("swap", || Ok::<_, StdError>(()));
+			assert!(nested.is_err());
+			Err(StdError::generic_err("oh no"))
+		});
+		assert_eq!(result, Err(StdError::generic_err("oh no")));
+		assert!(!ReentrancyGuard::is_entered("swap"));
+
+		assert_eq!(ReentrancyGuard::with_guard("swap", || Ok::<_, StdError>(5)), Ok(5));
+		assert!(!ReentrancyGuard::is_entered("swap"));
+
+		Ok(())
+	}
+}
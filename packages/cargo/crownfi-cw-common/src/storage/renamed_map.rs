@@ -0,0 +1,230 @@
+use cosmwasm_std::{StdError, StdResult};
+
+use crate::utils::lexicographic_next;
+
+use super::{
+	base::{storage_has, storage_remove, storage_write},
+	concat_byte_array_pairs,
+	map::StoredMap,
+	OZeroCopy, SerializableItem, StorageKeyIterator, StoragePairIterator,
+};
+
+/// Moves up to `limit` entries from the `old` key prefix to the `new` one, keeping whatever follows the prefix.
+///
+/// Entries which already exist under the new prefix aren't overwritten, as they were written after the rename
+/// started (see [RenamedMap]), but the old entry is still removed. As moved entries are removed, each call continues
+/// where the last one left off.
+///
+/// Returns the amount of entries moved, and the key (without the prefix) of the next entry to move, which is `None`
+/// once nothing is left under the old prefix. Errors if either prefix starts with the other, as the renamed entries
+/// would then be moved again.
+///
+/// **Note:** Namespaces aren't length-prefixed, so _every_ key starting with `old` is moved, including those of
+/// unrelated namespaces which merely start with it, e.g. `b"pool_fee"` when renaming `b"pool"`. Use
+/// [RenamedMap::rename_batch] to only move the keys which belong to a map.
+pub fn rename_namespace(old: &[u8], new: &[u8], limit: u32) -> StdResult<(u32, Option<Vec<u8>>)> {
+	rename_matching(old, new, limit, |_| true)
+}
+
+/// Same as [rename_namespace], but only the entries whose key (without the prefix) is accepted by `filter` are moved.
+fn rename_matching(
+	old: &[u8],
+	new: &[u8],
+	limit: u32,
+	filter: impl Fn(&[u8]) -> bool,
+) -> StdResult<(u32, Option<Vec<u8>>)> {
+	if old.starts_with(new) || new.starts_with(old) {
+		return Err(StdError::generic_err(
+			"rename_namespace: the old and new namespaces can't be prefixes of each other",
+		));
+	}
+	let end = lexicographic_next(old);
+	let mut entries =
+		StoragePairIterator::new(Some(old), Some(&end)).filter(|(old_key, _)| filter(&old_key[old.len()..]));
+	let mut moved = 0;
+	while moved < limit {
+		let Some((old_key, value)) = entries.next() else {
+			return Ok((moved, None));
+		};
+		let new_key = concat_byte_array_pairs(new, &old_key[old.len()..]);
+		if !storage_has(&new_key) {
+			storage_write(&new_key, &value);
+		}
+		storage_remove(&old_key);
+		moved += 1;
+	}
+	// The iterator has prefetched the next entry, which is still under the old prefix.
+	Ok((moved, entries.next().map(|(old_key, _)| old_key[old.len()..].to_vec())))
+}
+
+/// A map whose namespace is being renamed with [rename_namespace], which can be used while entries exist under both
+/// namespaces.
+///
+/// * Writes only go to the new namespace, which is why [rename_namespace] doesn't overwrite entries there.
+/// * Removals go to both namespaces, so that a removed value can't reappear from the old one.
+/// * Reads prefer the new namespace and fall back to the old one.
+///
+/// Unlike [DualMap](super::dual_map::DualMap), both namespaces use the same layout.
+pub struct RenamedMap<K: SerializableItem, V: SerializableItem> {
+	old: StoredMap<K, V>,
+	new: StoredMap<K, V>,
+}
+
+impl<K: SerializableItem, V: SerializableItem> RenamedMap<K, V> {
+	pub fn new(old_namespace: &'static [u8], new_namespace: &'static [u8]) -> Self {
+		Self {
+			old: StoredMap::new(old_namespace),
+			new: StoredMap::new(new_namespace),
+		}
+	}
+
+	/// The map under the new namespace, for use once the rename is done.
+	#[inline]
+	pub fn new_layout(&self) -> &StoredMap<K, V> {
+		&self.new
+	}
+
+	/// Returns true once no key of this map is left under the old namespace. This reads a single entry from storage,
+	/// unless there are keys of other namespaces starting with the old one.
+	pub fn is_renamed(&self) -> bool {
+		let old_namespace = self.old.namespace();
+		!StorageKeyIterator::new(Some(old_namespace), Some(&lexicographic_next(old_namespace)))
+			.any(|key| K::deserialize_to_owned(&key[old_namespace.len()..]).is_ok())
+	}
+
+	pub fn get(&self, key: &K) -> StdResult<Option<OZeroCopy<V>>> {
+		if let Some(value) = self.new.get(key)? {
+			return Ok(Some(value));
+		}
+		self.old.get(key)
+	}
+
+	pub fn has(&self, key: &K) -> bool {
+		self.new.has(key) || self.old.has(key)
+	}
+
+	pub fn set(&self, key: &K, value: &V) -> StdResult<()> {
+		self.new.set(key, value)
+	}
+
+	pub fn remove(&self, key: &K) {
+		self.new.remove(key);
+		self.old.remove(key);
+	}
+
+	/// Moves up to `limit` entries to the new namespace, see [rename_namespace]. Unlike it, keys which don't
+	/// deserialize as `K` are left alone, as they belong to other namespaces starting with the old one.
+	pub fn rename_batch(&self, limit: u32) -> StdResult<(u32, Option<Vec<u8>>)> {
+		rename_matching(self.old.namespace(), self.new.namespace(), limit, |key| {
+			K::deserialize_to_owned(key).is_ok()
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	const OLD_NAMESPACE: &[u8] = b"oldname";
+	const NEW_NAMESPACE: &[u8] = b"newname";
+
+	fn balances() -> RenamedMap<String, u128> {
+		RenamedMap::new(OLD_NAMESPACE, NEW_NAMESPACE)
+	}
+
+	#[test]
+	fn rename_converges() -> TestingResult {
+		let _storage_lock = init()?;
+		let old = StoredMap::<String, u128>::new(OLD_NAMESPACE);
+		for i in 0..5 {
+			old.set(&format!("user{i}"), &i)?;
+		}
+		// Something right after the old prefix which must be left alone
+		storage_write(b"oldnamf", b"x");
+
+		let map = balances();
+		let mut batches = 0;
+		let mut total_moved = 0;
+		loop {
+			let (moved, next) = map.rename_batch(2)?;
+			batches += 1;
+			total_moved += moved;
+			let Some(next) = next else {
+				break;
+			};
+			assert_eq!(
+				next,
+				map.new_layout().key(&format!("user{total_moved}"))[NEW_NAMESPACE.len()..]
+			);
+		}
+		assert_eq!((batches, total_moved), (3, 5));
+		assert!(map.is_renamed());
+		assert_eq!(map.rename_batch(2)?, (0, None));
+		for i in 0..5 {
+			assert_eq!(
+				map.new_layout().get(&format!("user{i}"))?.map(OZeroCopy::into_inner),
+				Some(i)
+			);
+		}
+		assert!(storage_has(b"oldnamf"));
+		Ok(())
+	}
+
+	#[test]
+	fn reads_during_rename() -> TestingResult {
+		let _storage_lock = init()?;
+		let old = StoredMap::<String, u128>::new(OLD_NAMESPACE);
+		for name in ["ann", "bob", "cat"] {
+			old.set(&name.to_string(), &1)?;
+		}
+		let map = balances();
+		assert!(!map.is_renamed());
+		assert_eq!(map.rename_batch(1)?.0, 1);
+		// ann has been moved, bob and cat haven't
+		assert!(map.new_layout().has(&"ann".to_string()));
+		assert_eq!(map.get(&"bob".to_string())?.map(OZeroCopy::into_inner), Some(1));
+
+		// A newer value isn't overwritten by the rename, and a removed value doesn't come back
+		map.set(&"bob".to_string(), &2)?;
+		map.remove(&"cat".to_string());
+		assert!(!map.has(&"cat".to_string()));
+		assert_eq!(map.rename_batch(10)?, (1, None));
+		assert_eq!(map.get(&"bob".to_string())?.map(OZeroCopy::into_inner), Some(2));
+		assert_eq!(map.get(&"cat".to_string())?, None);
+		Ok(())
+	}
+
+	#[test]
+	fn rename_batch_skips_namespaces_sharing_the_prefix() -> TestingResult {
+		let _storage_lock = init()?;
+		let old = StoredMap::<u32, u128>::new(b"pool");
+		old.set(&1, &10)?;
+		old.set(&2, &20)?;
+		// Its keys are 12 bytes after the b"pool" prefix, so they don't deserialize as u32
+		let fees = StoredMap::<u64, u128>::new(b"pool_fee");
+		fees.set(&7, &70)?;
+
+		let map = RenamedMap::<u32, u128>::new(b"pool", b"vault");
+		assert_eq!(map.rename_batch(10)?, (2, None));
+		assert!(map.is_renamed());
+		assert_eq!(map.new_layout().get(&2)?.map(OZeroCopy::into_inner), Some(20));
+		assert_eq!(fees.get(&7)?.map(OZeroCopy::into_inner), Some(70));
+
+		// The untyped rename takes the sibling along
+		assert_eq!(rename_namespace(b"pool", b"tank", 10)?, (1, None));
+		assert_eq!(fees.get(&7)?, None);
+		Ok(())
+	}
+
+	#[test]
+	fn overlapping_namespaces_are_rejected() -> TestingResult {
+		let _storage_lock = init()?;
+		assert!(rename_namespace(b"fees", b"fees_v2", 10).is_err());
+		assert!(rename_namespace(b"fees_v2", b"fees", 10).is_err());
+		assert!(rename_namespace(b"fees", b"fees", 10).is_err());
+		assert!(rename_namespace(b"fees", b"", 10).is_err());
+		assert_eq!(rename_namespace(b"fees", b"tolls", 10)?, (0, None));
+		Ok(())
+	}
+}
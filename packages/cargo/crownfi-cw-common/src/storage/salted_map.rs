@@ -0,0 +1,211 @@
+use std::marker::PhantomData;
+
+use cosmwasm_std::{Env, StdError, StdResult};
+
+use crate::{crypto::keccak256, utils::hex_display};
+
+use super::{
+	base::{storage_has, storage_read, storage_remove, storage_write},
+	concat_byte_array_pairs, OZeroCopy, SerializableItem,
+};
+
+/// A map whose storage keys are `namespace ++ keccak256(salt ++ key)`, so that its keys can't be listed from raw state.
+///
+/// # Threat model
+///
+/// This is obfuscation, not secrecy. The salt is stored in the contract's state under `namespace`, so anyone can read
+/// it and check whether a key they *guess* is in the map, e.g. by hashing every address they know of. What it prevents
+/// is enumerating the keys from a raw state dump, as the original keys are never stored, and the per-map salt means
+/// the same key results in unrelated storage keys across namespaces and contracts. Values are stored as-is.
+///
+/// For the same reason, the map can't be iterated, and doesn't provide any methods which would require it.
+///
+/// The salt is created with [new](Self::new), which must first be called during instantiate or execute as it writes
+/// to storage. Queries should use [load](Self::load).
+pub struct SaltedMap<K: SerializableItem, V: SerializableItem> {
+	namespace: &'static [u8],
+	salt: [u8; 32],
+	key_type: PhantomData<K>,
+	value_type: PhantomData<V>,
+}
+
+impl<K: SerializableItem, V: SerializableItem> SaltedMap<K, V> {
+	/// Loads the map's salt, creating it if it doesn't exist yet.
+	///
+	/// The salt is derived from the contract address, the namespace and the current block and transaction, so it
+	/// can't be known before the map was created.
+	pub fn new(namespace: &'static [u8], env: &Env) -> StdResult<Self> {
+		if !storage_has(namespace) {
+			let salt = keccak256(&[
+				env.contract.address.as_bytes(),
+				namespace,
+				&env.block.height.to_le_bytes(),
+				&env.block.time.nanos().to_le_bytes(),
+				&env.transaction.as_ref().map_or(u32::MAX, |tx| tx.index).to_le_bytes(),
+			]);
+			storage_write(namespace, &salt);
+		}
+		Self::load(namespace)
+	}
+
+	/// Loads the map's salt, returning [StdError::NotFound] if it hasn't been created with [new](Self::new) yet.
+	pub fn load(namespace: &'static [u8]) -> StdResult<Self> {
+		let salt = storage_read(namespace)
+			.ok_or_else(|| StdError::not_found(format!("SaltedMap salt at {}", hex_display(namespace))))?;
+		Ok(Self {
+			namespace,
+			salt: salt
+				.try_into()
+				.map_err(|salt: Vec<u8>| StdError::invalid_data_size(32, salt.len()))?,
+			key_type: PhantomData,
+			value_type: PhantomData,
+		})
+	}
+
+	#[inline]
+	pub fn namespace(&self) -> &'static [u8] {
+		self.namespace
+	}
+
+	/// Returns the storage key the value for the specified key is stored under.
+	pub fn key(&self, key: &K) -> StdResult<Vec<u8>> {
+		let hash = match key.serialize_as_ref() {
+			Some(key_bytes) => keccak256(&[&self.salt, key_bytes]),
+			None => keccak256(&[&self.salt, &key.serialize_to_owned()?]),
+		};
+		Ok(concat_byte_array_pairs(self.namespace, &hash))
+	}
+
+	pub fn get(&self, key: &K) -> StdResult<Option<OZeroCopy<V>>> {
+		storage_read(&self.key(key)?).map(OZeroCopy::new).transpose()
+	}
+
+	pub fn has(&self, key: &K) -> StdResult<bool> {
+		Ok(storage_has(&self.key(key)?))
+	}
+
+	pub fn set(&self, key: &K, value: &V) -> StdResult<()> {
+		let storage_key = self.key(key)?;
+		match value.serialize_as_ref() {
+			Some(value_bytes) => storage_write(&storage_key, value_bytes),
+			None => storage_write(&storage_key, &value.serialize_to_owned()?),
+		}
+		Ok(())
+	}
+
+	pub fn remove(&self, key: &K) -> StdResult<()> {
+		storage_remove(&self.key(key)?);
+		Ok(())
+	}
+}
+
+/// A set of values which can't be listed from raw state, see [SaltedMap] for how, and what it protects against.
+///
+/// Intended for e.g. checking whether an address is on a list without publishing the list.
+pub struct SaltedSet<V: SerializableItem> {
+	inner_map: SaltedMap<V, u8>,
+}
+
+impl<V: SerializableItem> SaltedSet<V> {
+	/// See [SaltedMap::new].
+	pub fn new(namespace: &'static [u8], env: &Env) -> StdResult<Self> {
+		Ok(Self {
+			inner_map: SaltedMap::new(namespace, env)?,
+		})
+	}
+
+	/// See [SaltedMap::load].
+	pub fn load(namespace: &'static [u8]) -> StdResult<Self> {
+		Ok(Self {
+			inner_map: SaltedMap::load(namespace)?,
+		})
+	}
+
+	#[inline]
+	pub fn namespace(&self) -> &'static [u8] {
+		self.inner_map.namespace()
+	}
+
+	#[inline]
+	pub fn has(&self, value: &V) -> StdResult<bool> {
+		self.inner_map.has(value)
+	}
+
+	#[inline]
+	pub fn add(&self, value: &V) -> StdResult<()> {
+		// Same marker value as StoredSet
+		self.inner_map.set(value, &254)
+	}
+
+	#[inline]
+	pub fn remove(&self, value: &V) -> StdResult<()> {
+		self.inner_map.remove(value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::{testing::mock_env, Addr};
+
+	use super::*;
+	use crate::{data_types::canonical_addr::SeiCanonicalAddr, storage::testing_common::*};
+
+	const OTHER_NAMESPACE: &[u8] = b"other";
+
+	#[test]
+	fn slots_are_stable() -> TestingResult {
+		let _storage = isolated()?;
+		let mut env = mock_env();
+		let map = SaltedMap::<String, u64>::new(NAMESPACE, &env)?;
+		map.set(&"alice".to_string(), &5)?;
+		let alice_key = map.key(&"alice".to_string())?;
+		assert_eq!(alice_key.len(), NAMESPACE.len() + 32);
+		assert!(!alice_key.windows(5).any(|window| window == b"alice"));
+
+		// The salt doesn't change once created, even if the env does
+		env.block.height += 1;
+		let map = SaltedMap::<String, u64>::new(NAMESPACE, &env)?;
+		assert_eq!(map.key(&"alice".to_string())?, alice_key);
+		let map = SaltedMap::<String, u64>::load(NAMESPACE)?;
+		assert_eq!(map.get(&"alice".to_string())?.map(OZeroCopy::into_inner), Some(5));
+		assert!(map.has(&"alice".to_string())?);
+		map.remove(&"alice".to_string())?;
+		assert_eq!(map.get(&"alice".to_string())?, None);
+		assert!(SaltedMap::<String, u64>::load(OTHER_NAMESPACE).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn salts_differ_per_namespace() -> TestingResult {
+		let _storage = isolated()?;
+		let env = mock_env();
+		let listed: SeiCanonicalAddr = [7; 32].into();
+		let set = SaltedSet::new(NAMESPACE, &env)?;
+		let other_set = SaltedSet::new(OTHER_NAMESPACE, &env)?;
+		set.add(&listed)?;
+		assert!(set.has(&listed)?);
+		set.remove(&listed)?;
+		assert!(!set.has(&listed)?);
+		set.add(&listed)?;
+		assert!(!other_set.has(&listed)?);
+		assert!(!set.has(&[8; 32].into())?);
+		assert_ne!(
+			set.inner_map.key(&listed)?[NAMESPACE.len()..],
+			other_set.inner_map.key(&listed)?[OTHER_NAMESPACE.len()..]
+		);
+
+		// Nor are they the same for another contract
+		let _storage = isolated()?;
+		let mut env = mock_env();
+		env.contract.address = Addr::unchecked("other_contract");
+		let moved_set = SaltedSet::new(NAMESPACE, &env)?;
+		assert_ne!(moved_set.inner_map.key(&listed)?, set.inner_map.key(&listed)?);
+		Ok(())
+	}
+
+	#[test]
+	fn cannot_be_iterated() {
+		let cases = trybuild::TestCases::new();
+		cases.compile_fail("tests/ui/salted_map_iter.rs");
+	}
+}
@@ -1,6 +1,14 @@
-use cosmwasm_std::StdResult;
+use cosmwasm_std::{OverflowError, OverflowOperation, StdResult};
+
+use crate::{
+	debug_log,
+	utils::{hex_display, lexicographic_next},
+};
 
 use super::{
+	accounting::{Accounted, NamespaceUsage},
+	base::{storage_read, storage_write},
+	concat_byte_array_pairs,
 	map::{StoredMap, StoredMapKeyIter},
 	SerializableItem,
 };
@@ -21,6 +29,16 @@ impl<'exec, V: SerializableItem> StoredSet<V> {
 			inner_map: StoredMap::new(namespace),
 		}
 	}
+	/// See [StoredMap::with_accounting].
+	pub fn with_accounting(self) -> Self {
+		Self {
+			inner_map: self.inner_map.with_accounting(),
+		}
+	}
+	#[inline]
+	pub fn namespace(&self) -> &'static [u8] {
+		self.inner_map.namespace()
+	}
 	#[inline]
 	pub fn has(&self, value: &V) -> bool {
 		self.inner_map.has(value)
@@ -42,6 +60,18 @@ impl<'exec, V: SerializableItem> StoredSet<V> {
 		self.inner_map.iter_keys()
 	}
 
+	/// Returns the smallest value. This only reads a single entry from storage.
+	#[inline]
+	pub fn min(&self) -> StdResult<Option<V>> {
+		self.inner_map.first_key()
+	}
+
+	/// Returns the largest value. This only reads a single entry from storage.
+	#[inline]
+	pub fn max(&self) -> StdResult<Option<V>> {
+		self.inner_map.last_key()
+	}
+
 	/// Returns an iterator which iterates over all set values over a specified range
 	///
 	/// You can use `after` to skip items while in ascending order. Or `before` along with the `.rev()` method to skip
@@ -51,4 +81,161 @@ impl<'exec, V: SerializableItem> StoredSet<V> {
 	}
 }
 
+impl<V: SerializableItem> Accounted for StoredSet<V> {
+	#[inline]
+	fn accounting(&self) -> StdResult<NamespaceUsage> {
+		self.inner_map.accounting()
+	}
+}
+
+/// A [StoredSet] which keeps count of its values, so that e.g. a maximum amount of members can be enforced without
+/// iterating over them.
+///
+/// The count is stored as a 4 byte header under the namespace itself, the same way
+/// [StoredVec](super::vec::StoredVec) stores its length. A plain StoredSet over the same namespace would therefore
+/// fail to iterate, and values added or removed through one aren't counted. Values must not serialize to 0 bytes, as
+/// they would be stored under the header's key.
+pub struct CountedStoredSet<V: SerializableItem> {
+	set: StoredSet<V>,
+	len: u32,
+}
+
+impl<V: SerializableItem> CountedStoredSet<V> {
+	/// Loads the set, treating a count header which isn't 4 bytes long as 0.
+	pub fn new(namespace: &'static [u8]) -> Self {
+		let len = match storage_read(namespace) {
+			Some(data) => data.as_slice().try_into().map(u32::from_le_bytes).unwrap_or_else(|_| {
+				debug_log!("treating CountedStoredSet as empty"; namespace = hex_display(namespace));
+				0
+			}),
+			None => 0,
+		};
+		Self {
+			set: StoredSet::new(namespace),
+			len,
+		}
+	}
+
+	#[inline]
+	fn set_len(&mut self, value: u32) {
+		self.len = value;
+		storage_write(self.namespace(), &value.to_le_bytes());
+	}
+
+	#[inline]
+	pub fn namespace(&self) -> &'static [u8] {
+		self.set.namespace()
+	}
+	#[inline]
+	pub fn len(&self) -> u32 {
+		self.len
+	}
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	#[inline]
+	pub fn has(&self, value: &V) -> bool {
+		self.set.has(value)
+	}
+
+	/// Adds the value, returning whether it wasn't in the set already.
+	pub fn add(&mut self, value: &V) -> StdResult<bool> {
+		if self.has(value) {
+			return Ok(false);
+		}
+		let len = self
+			.len
+			.checked_add(1)
+			.ok_or(OverflowError::new(OverflowOperation::Add, self.len, 1))?;
+		self.set.add(value)?;
+		self.set_len(len);
+		Ok(true)
+	}
+
+	/// Removes the value, returning whether it was in the set.
+	pub fn remove(&mut self, value: &V) -> bool {
+		if !self.has(value) {
+			return false;
+		}
+		self.set.remove(value);
+		self.set_len(self.len.saturating_sub(1));
+		true
+	}
+
+	/// See [StoredSet::iter].
+	pub fn iter(&self) -> StdResult<StoredMapKeyIter<V>> {
+		self.iter_range(None, None)
+	}
+
+	/// See [StoredSet::iter_range].
+	pub fn iter_range(&self, after: Option<V>, before: Option<V>) -> StdResult<StoredMapKeyIter<V>> {
+		let namespace = self.namespace();
+		let start = match after {
+			Some(value) => concat_byte_array_pairs(namespace, &value.serialize_to_owned()?),
+			// The smallest key after the count header
+			None => concat_byte_array_pairs(namespace, &[0]),
+		};
+		let end = match before {
+			Some(value) => concat_byte_array_pairs(namespace, &value.serialize_to_owned()?),
+			None => lexicographic_next(namespace),
+		};
+		Ok(StoredMapKeyIter::new_raw(&start, &end, namespace.len()))
+	}
+
+	/// Returns the smallest value.
+	pub fn min(&self) -> StdResult<Option<V>> {
+		Ok(self.iter()?.next())
+	}
+
+	/// Returns the largest value.
+	pub fn max(&self) -> StdResult<Option<V>> {
+		Ok(self.iter()?.next_back())
+	}
+}
+
 // Depends on set which is proven to work
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	#[test]
+	fn counted_set() -> TestingResult {
+		let _storage = isolated()?;
+		let mut set = CountedStoredSet::<String>::new(NAMESPACE);
+		assert!(set.is_empty());
+		assert_eq!(set.min()?, None);
+		assert_eq!(set.max()?, None);
+		assert_eq!(set.iter()?.count(), 0);
+
+		assert!(set.add(&"bob".to_string())?);
+		assert!(set.add(&"alice".to_string())?);
+		assert!(!set.add(&"bob".to_string())?);
+		assert_eq!(set.len(), 2);
+		assert!(!set.remove(&"carol".to_string()));
+		assert_eq!(set.len(), 2);
+
+		// The count persists, and isn't seen as a value
+		let mut set = CountedStoredSet::<String>::new(NAMESPACE);
+		assert_eq!(set.len(), 2);
+		// Strings are length-prefixed, so shorter ones come first
+		assert_eq!(set.iter()?.collect::<Vec<_>>(), ["bob", "alice"]);
+		assert_eq!(set.iter()?.rev().collect::<Vec<_>>(), ["alice", "bob"]);
+		assert_eq!(
+			set.iter_range(None, Some("alice".to_string()))?.collect::<Vec<_>>(),
+			["bob"]
+		);
+		assert_eq!(set.min()?.as_deref(), Some("bob"));
+		assert_eq!(set.max()?.as_deref(), Some("alice"));
+
+		assert!(set.remove(&"alice".to_string()));
+		assert!(!set.remove(&"alice".to_string()));
+		assert!(set.add(&"carol".to_string())?);
+		let set = CountedStoredSet::<String>::new(NAMESPACE);
+		assert_eq!(set.len(), 2);
+		assert_eq!(set.iter()?.count() as u32, set.len());
+		Ok(())
+	}
+}
@@ -0,0 +1,556 @@
+use std::marker::PhantomData;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use cosmwasm_std::{StdError, StdResult};
+use thiserror::Error;
+
+use crate::data_types::canonical_addr::SeiCanonicalAddr;
+
+use super::{
+	base::{storage_read_item, storage_remove, storage_write_item},
+	item::AutosavingSerializableItem,
+	OZeroCopy, SerializableItem,
+};
+
+/// The length of [PodSmallVec]'s header, which aligns the elements for any Pod type which is at most 16-byte aligned.
+const POD_SMALL_VEC_HEADER_LEN: usize = 16;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SmallVecError {
+	#[error("The vec is full, its capacity is {0}")]
+	Full(usize),
+	#[error("Index {index} is out of bounds, the length is {len}")]
+	OutOfBounds { index: usize, len: usize },
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<SmallVecError> for StdError {
+	fn from(value: SmallVecError) -> Self {
+		match value {
+			SmallVecError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// Pod types which can be stored in a [PodSmallVec], which requires them to be at most 16-byte aligned so that the
+/// elements follow its header without any padding.
+///
+/// Implement this with [impl_small_vec_item](crate::impl_small_vec_item), which checks the alignment at compile time.
+///
+/// # Safety
+///
+/// `align_of::<Self>()` must be at most 16.
+pub unsafe trait SmallVecItem: Pod {}
+
+/// Implements [SmallVecItem] for the specified types, failing to compile if any of them is more than 16-byte aligned.
+#[macro_export]
+macro_rules! impl_small_vec_item {
+	( $( $item_type:ty ),+ $(,)? ) => {
+		$(
+			const _: () = assert!(
+				::std::mem::align_of::<$item_type>() <= 16,
+				concat!("PodSmallVec elements can be at most 16-byte aligned, which ", stringify!($item_type), " isn't")
+			);
+			// SAFETY: The alignment was asserted above
+			unsafe impl $crate::storage::small_vec::SmallVecItem for $item_type {}
+		)+
+	};
+}
+
+impl_small_vec_item!(
+	u8,
+	i8,
+	u16,
+	i16,
+	u32,
+	i32,
+	u64,
+	i64,
+	u128,
+	i128,
+	usize,
+	isize,
+	f32,
+	f64,
+	SeiCanonicalAddr
+);
+// SAFETY: Arrays have the same alignment as their elements
+unsafe impl<T: SmallVecItem, const N: usize> SmallVecItem for [T; N] {}
+
+/// Operations shared by [PodSmallVec] and [BorshSmallVec], so that [StoredSmallVecOf] can work with either.
+pub trait SmallVecValue: SerializableItem + Default {
+	type Item;
+	const CAPACITY: usize;
+	fn as_slice(&self) -> &[Self::Item];
+	fn push(&mut self, value: Self::Item) -> Result<(), SmallVecError>;
+	fn pop(&mut self) -> Option<Self::Item>;
+	fn set(&mut self, index: usize, value: Self::Item) -> Result<(), SmallVecError>;
+	fn clear(&mut self);
+}
+
+/// A vec of up to `N` Pod values which is stored as a single fixed-size value: A length byte, 15 zero bytes so that
+/// the elements are aligned, then `[V; N]`. This allows it to be zero-copy deserialized, which also makes
+/// [as_slice](Self::as_slice) zero-copy.
+///
+/// `N` may be at most 255, and `V` at most 16-byte aligned, which is guaranteed by [SmallVecItem].
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct PodSmallVec<V: SmallVecItem, const N: usize> {
+	len: u8,
+	_padding: [u8; POD_SMALL_VEC_HEADER_LEN - 1],
+	items: [V; N],
+}
+
+// SAFETY: repr(C), and all fields are Pod. There is no padding as the header is 16 bytes long and `V`'s alignment is
+// at most 16 (so also a divisor of 16), which `SmallVecItem` guarantees for every `V` this can be instantiated with.
+unsafe impl<V: SmallVecItem, const N: usize> Zeroable for PodSmallVec<V, N> {}
+unsafe impl<V: SmallVecItem, const N: usize> Pod for PodSmallVec<V, N> {}
+
+impl<V: SmallVecItem, const N: usize> PodSmallVec<V, N> {
+	const LAYOUT_CHECK: () = assert!(N <= u8::MAX as usize, "PodSmallVec can hold at most 255 elements");
+
+	pub fn new() -> Self {
+		#[allow(clippy::let_unit_value)]
+		let _ = Self::LAYOUT_CHECK;
+		Zeroable::zeroed()
+	}
+
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.len as usize
+	}
+
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	#[inline]
+	pub const fn capacity(&self) -> usize {
+		N
+	}
+
+	#[inline]
+	pub fn as_slice(&self) -> &[V] {
+		&self.items[..self.len()]
+	}
+
+	#[inline]
+	pub fn as_mut_slice(&mut self) -> &mut [V] {
+		let len = self.len();
+		&mut self.items[..len]
+	}
+
+	pub fn get(&self, index: usize) -> Option<&V> {
+		self.as_slice().get(index)
+	}
+
+	pub fn set(&mut self, index: usize, value: V) -> Result<(), SmallVecError> {
+		let len = self.len();
+		*self
+			.as_mut_slice()
+			.get_mut(index)
+			.ok_or(SmallVecError::OutOfBounds { index, len })? = value;
+		Ok(())
+	}
+
+	pub fn push(&mut self, value: V) -> Result<(), SmallVecError> {
+		let len = self.len();
+		if len == N {
+			return Err(SmallVecError::Full(N));
+		}
+		self.items[len] = value;
+		self.len += 1;
+		Ok(())
+	}
+
+	pub fn pop(&mut self) -> Option<V> {
+		if self.is_empty() {
+			return None;
+		}
+		self.len -= 1;
+		let value = self.items[self.len()];
+		// Keep unused slots zeroed, so that equal vecs are stored identically
+		self.items[self.len()] = V::zeroed();
+		Some(value)
+	}
+
+	pub fn clear(&mut self) {
+		self.len = 0;
+		self.items = [V::zeroed(); N];
+	}
+
+	pub fn iter(&self) -> std::slice::Iter<'_, V> {
+		self.as_slice().iter()
+	}
+}
+
+impl<V: SmallVecItem, const N: usize> Default for PodSmallVec<V, N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<V: SmallVecItem + PartialEq, const N: usize> PartialEq for PodSmallVec<V, N> {
+	fn eq(&self, other: &Self) -> bool {
+		self.as_slice() == other.as_slice()
+	}
+}
+
+impl<V: SmallVecItem, const N: usize> SerializableItem for PodSmallVec<V, N> {
+	fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+		Ok(bytemuck::bytes_of(std::hint::black_box(self)).into())
+	}
+	fn serialize_as_ref(&self) -> Option<&[u8]> {
+		Some(bytemuck::bytes_of(std::hint::black_box(self)))
+	}
+	fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> {
+		#[allow(clippy::let_unit_value)]
+		let _ = Self::LAYOUT_CHECK;
+		let result: Self = bytemuck::try_pod_read_unaligned(std::hint::black_box(data))
+			.map_err(|err| StdError::parse_err("PodSmallVec", err))?;
+		if result.len() > N {
+			return Err(StdError::parse_err("PodSmallVec", "length exceeds the capacity"));
+		}
+		Ok(result)
+	}
+	fn deserialize_as_ref(data: &[u8]) -> Option<&Self> {
+		#[allow(clippy::let_unit_value)]
+		let _ = Self::LAYOUT_CHECK;
+		bytemuck::try_from_bytes::<Self>(data)
+			.ok()
+			.filter(|value| value.len() <= N)
+	}
+	fn deserialize_as_ref_mut(data: &mut [u8]) -> Option<&mut Self> {
+		#[allow(clippy::let_unit_value)]
+		let _ = Self::LAYOUT_CHECK;
+		bytemuck::try_from_bytes_mut::<Self>(data)
+			.ok()
+			.filter(|value| value.len() <= N)
+	}
+}
+
+impl<V: SmallVecItem, const N: usize> SmallVecValue for PodSmallVec<V, N> {
+	type Item = V;
+	const CAPACITY: usize = N;
+	fn as_slice(&self) -> &[V] {
+		self.as_slice()
+	}
+	fn push(&mut self, value: V) -> Result<(), SmallVecError> {
+		self.push(value)
+	}
+	fn pop(&mut self) -> Option<V> {
+		self.pop()
+	}
+	fn set(&mut self, index: usize, value: V) -> Result<(), SmallVecError> {
+		self.set(index, value)
+	}
+	fn clear(&mut self) {
+		self.clear()
+	}
+}
+
+/// Like [PodSmallVec], but for values which aren't Pod. Stored as a borsh-serialized `Vec<V>`, so it can't be
+/// zero-copy deserialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BorshSmallVec<V, const N: usize> {
+	items: Vec<V>,
+}
+
+impl<V, const N: usize> BorshSmallVec<V, N> {
+	pub fn new() -> Self {
+		Self { items: Vec::new() }
+	}
+
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.items.len()
+	}
+
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.items.is_empty()
+	}
+
+	#[inline]
+	pub const fn capacity(&self) -> usize {
+		N
+	}
+
+	#[inline]
+	pub fn as_slice(&self) -> &[V] {
+		&self.items
+	}
+
+	#[inline]
+	pub fn as_mut_slice(&mut self) -> &mut [V] {
+		&mut self.items
+	}
+
+	pub fn get(&self, index: usize) -> Option<&V> {
+		self.items.get(index)
+	}
+
+	pub fn set(&mut self, index: usize, value: V) -> Result<(), SmallVecError> {
+		let len = self.len();
+		*self
+			.items
+			.get_mut(index)
+			.ok_or(SmallVecError::OutOfBounds { index, len })? = value;
+		Ok(())
+	}
+
+	pub fn push(&mut self, value: V) -> Result<(), SmallVecError> {
+		if self.len() == N {
+			return Err(SmallVecError::Full(N));
+		}
+		self.items.push(value);
+		Ok(())
+	}
+
+	pub fn pop(&mut self) -> Option<V> {
+		self.items.pop()
+	}
+
+	pub fn clear(&mut self) {
+		self.items.clear()
+	}
+
+	pub fn iter(&self) -> std::slice::Iter<'_, V> {
+		self.items.iter()
+	}
+}
+
+impl<V, const N: usize> Default for BorshSmallVec<V, N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<V: BorshSerialize + BorshDeserialize, const N: usize> SerializableItem for BorshSmallVec<V, N> {
+	fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+		borsh::to_vec(&self.items).map_err(|err| StdError::serialize_err("BorshSmallVec", err))
+	}
+	fn deserialize_to_owned(data: &[u8]) -> Result<Self, StdError> {
+		let items = Vec::<V>::try_from_slice(data).map_err(|err| StdError::parse_err("BorshSmallVec", err))?;
+		if items.len() > N {
+			return Err(StdError::parse_err("BorshSmallVec", "length exceeds the capacity"));
+		}
+		Ok(Self { items })
+	}
+}
+
+impl<V: BorshSerialize + BorshDeserialize, const N: usize> SmallVecValue for BorshSmallVec<V, N> {
+	type Item = V;
+	const CAPACITY: usize = N;
+	fn as_slice(&self) -> &[V] {
+		self.as_slice()
+	}
+	fn push(&mut self, value: V) -> Result<(), SmallVecError> {
+		self.push(value)
+	}
+	fn pop(&mut self) -> Option<V> {
+		self.pop()
+	}
+	fn set(&mut self, index: usize, value: V) -> Result<(), SmallVecError> {
+		self.set(index, value)
+	}
+	fn clear(&mut self) {
+		self.clear()
+	}
+}
+
+/// A small, fixed-capacity vec stored as a single value under the namespace, which is cheaper than a [StoredVec]
+/// (one key per element plus a length header) for collections such as fee tiers or route hops. Every operation reads
+/// and writes the whole vec, so it's always updated atomically.
+///
+/// Use [StoredSmallVec] for Pod values or [StoredBorshSmallVec] otherwise. To make multiple changes with a single
+/// write, use [load_autosaving](Self::load_autosaving).
+///
+/// [StoredVec]: super::vec::StoredVec
+pub struct StoredSmallVecOf<T: SmallVecValue> {
+	namespace: &'static [u8],
+	value_type: PhantomData<T>,
+}
+
+/// A [StoredSmallVecOf] for Pod values, which can be read without copying.
+pub type StoredSmallVec<V, const N: usize> = StoredSmallVecOf<PodSmallVec<V, N>>;
+/// A [StoredSmallVecOf] for borsh-serializable values.
+pub type StoredBorshSmallVec<V, const N: usize> = StoredSmallVecOf<BorshSmallVec<V, N>>;
+
+impl<T: SmallVecValue> StoredSmallVecOf<T> {
+	pub const fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			namespace,
+			value_type: PhantomData,
+		}
+	}
+
+	#[inline]
+	pub const fn capacity(&self) -> usize {
+		T::CAPACITY
+	}
+
+	/// Loads the vec, which is empty if it has never been saved.
+	pub fn load(&self) -> StdResult<OZeroCopy<T>> {
+		Ok(storage_read_item(self.namespace)?.unwrap_or_default())
+	}
+
+	/// Loads the vec, which is saved when the returned value is dropped if it was modified.
+	pub fn load_autosaving(&self) -> StdResult<AutosavingSerializableItem<T>> {
+		AutosavingSerializableItem::new_or_default(self.namespace.to_vec())
+	}
+
+	pub fn save(&self, value: &T) -> StdResult<()> {
+		storage_write_item(self.namespace, value)
+	}
+
+	pub fn len(&self) -> StdResult<usize> {
+		Ok(self.load()?.as_slice().len())
+	}
+
+	pub fn is_empty(&self) -> StdResult<bool> {
+		Ok(self.len()? == 0)
+	}
+
+	pub fn get(&self, index: usize) -> StdResult<Option<T::Item>>
+	where
+		T::Item: Clone,
+	{
+		Ok(self.load()?.as_slice().get(index).cloned())
+	}
+
+	pub fn set(&self, index: usize, value: T::Item) -> Result<(), SmallVecError> {
+		let mut vec = self.load()?;
+		vec.set(index, value)?;
+		Ok(self.save(&vec)?)
+	}
+
+	pub fn push(&self, value: T::Item) -> Result<(), SmallVecError> {
+		let mut vec = self.load()?;
+		vec.push(value)?;
+		Ok(self.save(&vec)?)
+	}
+
+	pub fn pop(&self) -> StdResult<Option<T::Item>> {
+		let mut vec = self.load()?;
+		let Some(value) = vec.pop() else {
+			return Ok(None);
+		};
+		self.save(&vec)?;
+		Ok(Some(value))
+	}
+
+	/// Removes all elements, along with the storage entry.
+	pub fn clear(&self) {
+		storage_remove(self.namespace)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::{base::storage_read, testing_common::*};
+
+	#[derive(Debug, Clone, Copy, PartialEq, Zeroable, Pod)]
+	#[repr(C)]
+	struct FeeTier {
+		max_volume: u128,
+		fee_bps: u16,
+		_padding: [u8; 14],
+	}
+	impl_small_vec_item!(FeeTier);
+
+	fn fee_tier(max_volume: u128, fee_bps: u16) -> FeeTier {
+		FeeTier {
+			max_volume,
+			fee_bps,
+			_padding: [0; 14],
+		}
+	}
+
+	#[test]
+	fn capacity_enforcement() -> TestingResult {
+		let _storage_lock = init()?;
+		let hops = StoredSmallVec::<u64, 3>::new(NAMESPACE);
+		for hop in 1..=3 {
+			hops.push(hop)?;
+		}
+		assert_eq!(hops.push(4), Err(SmallVecError::Full(3)));
+		assert_eq!(hops.set(3, 4), Err(SmallVecError::OutOfBounds { index: 3, len: 3 }));
+		assert_eq!(hops.load()?.as_slice(), [1, 2, 3]);
+
+		let names = StoredBorshSmallVec::<String, 2>::new(NAMESPACE);
+		names.clear();
+		names.push("a".into())?;
+		names.push("b".into())?;
+		assert_eq!(names.push("c".into()), Err(SmallVecError::Full(2)));
+		// Corrupted or oversized entries aren't accepted either
+		storage_write_item(NAMESPACE, &vec!["a".to_string(), "b".into(), "c".into()])?;
+		assert!(names.load().is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn zero_copy_autosaving() -> TestingResult {
+		let _storage_lock = init()?;
+		let tiers = StoredSmallVec::<FeeTier, 4>::new(NAMESPACE);
+		tiers.push(fee_tier(1000, 30))?;
+		tiers.push(fee_tier(u128::MAX, 10))?;
+		assert_eq!(
+			storage_read(NAMESPACE).map(|bytes| bytes.len()),
+			Some(16 + 4 * std::mem::size_of::<FeeTier>())
+		);
+
+		{
+			let mut tiers = tiers.load_autosaving()?;
+			for tier in tiers.as_mut_slice() {
+				tier.fee_bps += 1;
+			}
+			tiers.push(fee_tier(5, 5))?;
+		}
+		let loaded = tiers.load()?;
+		assert_eq!(
+			loaded.as_slice(),
+			[fee_tier(1000, 31), fee_tier(u128::MAX, 11), fee_tier(5, 5)]
+		);
+		assert_eq!(loaded.capacity(), 4);
+		Ok(())
+	}
+
+	#[test]
+	fn persistence() -> TestingResult {
+		let _storage_lock = init()?;
+		let hops = StoredSmallVec::<u32, 8>::new(NAMESPACE);
+		assert!(hops.is_empty()?);
+		assert_eq!(hops.pop()?, None);
+		hops.push(7)?;
+		hops.push(8)?;
+		hops.set(0, 6)?;
+		assert_eq!(hops.pop()?, Some(8));
+		assert_eq!(hops.len()?, 1);
+		assert_eq!(hops.get(0)?, Some(6));
+		assert_eq!(hops.get(1)?, None);
+		let mut expected = PodSmallVec::<u32, 8>::new();
+		expected.push(6)?;
+		// Popped slots are zeroed, so the bytes are identical to a vec which never had them
+		assert_eq!(storage_read(NAMESPACE), Some(bytemuck::bytes_of(&expected).to_vec()));
+		hops.clear();
+		assert_eq!(hops.len()?, 0);
+
+		let routes = StoredBorshSmallVec::<(String, u32), 4>::new(b"routes");
+		routes.push(("usei".into(), 1))?;
+		routes.push(("uatom".into(), 2))?;
+		assert_eq!(routes.pop()?, Some(("uatom".into(), 2)));
+		assert_eq!(routes.load()?.into_inner().as_slice(), [("usei".to_string(), 1)]);
+		Ok(())
+	}
+
+	#[test]
+	fn overaligned_items_fail_to_compile() {
+		let cases = trybuild::TestCases::new();
+		cases.compile_fail("tests/ui/small_vec_overaligned.rs");
+	}
+}
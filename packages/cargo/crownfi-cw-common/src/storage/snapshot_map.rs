@@ -0,0 +1,616 @@
+use std::{marker::PhantomData, num::NonZeroU64};
+
+use cosmwasm_std::{StdError, StdResult, Uint128};
+
+use crate::utils::lexicographic_next;
+
+use super::{
+	base::{storage_has, storage_read_item, storage_remove, storage_write, storage_write_item},
+	concat_byte_array_pairs,
+	map::StoredMap,
+	OZeroCopy, SerializableItem, StorageKeyIterator, StoragePairIterator,
+};
+
+const CHANGELOG_REMOVED: u8 = 0;
+const CHANGELOG_SET: u8 = 1;
+
+/// Returns `namespace ++ key length (u16 BE) ++ key`. The length prefix prevents keys which start with another key
+/// from being mixed up with its checkpoints.
+fn length_prefixed_key<K: SerializableItem>(namespace: &[u8], key: &K) -> StdResult<Vec<u8>> {
+	let owned_key_bytes;
+	let key_bytes = match key.serialize_as_ref() {
+		Some(key_bytes) => key_bytes,
+		None => {
+			owned_key_bytes = key.serialize_to_owned()?;
+			&owned_key_bytes
+		}
+	};
+	let key_len = u16::try_from(key_bytes.len())
+		.map_err(|_| StdError::serialize_err("SnapshotMap key", "key is longer than u16::MAX bytes"))?;
+	let mut prefix = Vec::with_capacity(namespace.len() + 2 + key_bytes.len());
+	prefix.extend_from_slice(namespace);
+	prefix.extend_from_slice(&key_len.to_be_bytes());
+	prefix.extend_from_slice(key_bytes);
+	Ok(prefix)
+}
+
+/// The checkpoints of a single value, stored as `prefix ++ height (u64 BE) -> value`.
+struct Checkpoints<'a> {
+	prefix: &'a [u8],
+}
+
+impl Checkpoints<'_> {
+	fn checkpoint_key(&self, height: u64) -> Vec<u8> {
+		concat_byte_array_pairs(self.prefix, &height.to_be_bytes())
+	}
+
+	fn parse_height(&self, key: &[u8]) -> StdResult<u64> {
+		let height = key[self.prefix.len()..]
+			.try_into()
+			.map_err(|_| StdError::parse_err("SnapshotMap checkpoint", "invalid height"))?;
+		Ok(u64::from_be_bytes(height))
+	}
+
+	fn end_key(&self, height: u64) -> Vec<u8> {
+		match height.checked_add(1) {
+			Some(end_height) => self.checkpoint_key(end_height),
+			None => lexicographic_next(self.prefix),
+		}
+	}
+
+	/// Returns the height and raw value of the latest checkpoint at or before the specified height.
+	fn latest_bytes_at(&self, height: u64) -> StdResult<Option<(u64, Vec<u8>)>> {
+		StoragePairIterator::new(Some(self.prefix), Some(&self.end_key(height)))
+			.next_back()
+			.map(|(key, value)| Ok((self.parse_height(&key)?, value)))
+			.transpose()
+	}
+
+	/// Returns the height of the last checkpoint, without reading its value.
+	fn last_height(&self) -> StdResult<Option<u64>> {
+		StorageKeyIterator::new(Some(self.prefix), Some(&self.end_key(u64::MAX)))
+			.next_back()
+			.map(|key| self.parse_height(&key))
+			.transpose()
+	}
+
+	/// Returns the latest checkpoint at or before the specified height, for checkpoints storing a u128 LE.
+	fn latest_at(&self, height: u64) -> StdResult<Option<(u64, Uint128)>> {
+		let Some((height, value)) = self.latest_bytes_at(height)? else {
+			return Ok(None);
+		};
+		let value = value
+			.try_into()
+			.map_err(|_| StdError::parse_err("SnapshotMap checkpoint", "invalid value"))?;
+		Ok(Some((height, Uint128::new(u128::from_le_bytes(value)))))
+	}
+
+	fn save(&self, value: Uint128, height: u64) -> StdResult<()> {
+		if let Some((last_height, last_value)) = self.latest_at(u64::MAX)? {
+			if last_height > height {
+				return Err(StdError::generic_err(format!(
+					"SnapshotMap: can't save a checkpoint at height {height}, one already exists at height {last_height}"
+				)));
+			}
+			if last_value == value {
+				return Ok(());
+			}
+		}
+		storage_write(&self.checkpoint_key(height), &value.u128().to_le_bytes());
+		Ok(())
+	}
+
+	fn prune_below(&self, height: u64) -> StdResult<u32> {
+		let Some((keep_height, _)) = self.latest_bytes_at(height)? else {
+			return Ok(0);
+		};
+		let pruned_keys: Vec<_> =
+			StorageKeyIterator::new(Some(self.prefix), Some(&self.checkpoint_key(keep_height))).collect();
+		for key in pruned_keys.iter() {
+			storage_remove(key);
+		}
+		Ok(pruned_keys.len() as u32)
+	}
+
+	/// Records the value, or its removal, in a changelog entry at the checkpoint height the strategy picks for the
+	/// specified height. This overwrites any entry already recorded at that checkpoint height.
+	fn record<V: SerializableItem>(&self, value: Option<&V>, height: u64, strategy: SnapshotStrategy) -> StdResult<()> {
+		let height = strategy.checkpoint_height(height);
+		if let Some(last_height) = self.last_height()? {
+			if last_height > height {
+				return Err(StdError::generic_err(format!(
+					"SnapshotStoredMap: can't record a change at height {height}, one already exists at height {last_height}"
+				)));
+			}
+		}
+		let entry = match value {
+			Some(value) => {
+				let mut entry = vec![CHANGELOG_SET];
+				match value.serialize_as_ref() {
+					Some(bytes) => entry.extend_from_slice(bytes),
+					None => entry.extend_from_slice(&value.serialize_to_owned()?),
+				}
+				entry
+			}
+			None => vec![CHANGELOG_REMOVED],
+		};
+		storage_write(&self.checkpoint_key(height), &entry);
+		Ok(())
+	}
+
+	/// Returns the value of the latest changelog entry at or before the specified height, if it isn't a removal.
+	fn value_at<V: SerializableItem>(&self, height: u64) -> StdResult<Option<OZeroCopy<V>>> {
+		let Some((_, mut entry)) = self.latest_bytes_at(height)? else {
+			return Ok(None);
+		};
+		match entry.first() {
+			Some(&CHANGELOG_SET) => {
+				entry.remove(0);
+				Ok(Some(OZeroCopy::new(entry)?))
+			}
+			Some(&CHANGELOG_REMOVED) => Ok(None),
+			_ => Err(StdError::parse_err("SnapshotStoredMap changelog", "invalid entry")),
+		}
+	}
+}
+
+/// Keeps track of the history of a [Uint128] value per key, such as per-address voting power, so that the value at
+/// any past block height can be queried.
+///
+/// A checkpoint is only written when the value changes. The checkpoints of each key are stored under
+/// `namespace ++ key length (u16 BE) ++ key ++ height (u64 BE)`.
+pub struct SnapshotMap<K: SerializableItem> {
+	namespace: &'static [u8],
+	key_type: PhantomData<K>,
+}
+
+impl<K: SerializableItem> SnapshotMap<K> {
+	pub const fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			namespace,
+			key_type: PhantomData,
+		}
+	}
+
+	fn key_prefix(&self, key: &K) -> StdResult<Vec<u8>> {
+		length_prefixed_key(self.namespace, key)
+	}
+
+	/// Records the key's value as of the specified height. Heights must be saved in non-decreasing order.
+	pub fn save(&self, key: &K, value: Uint128, height: u64) -> StdResult<()> {
+		Checkpoints {
+			prefix: &self.key_prefix(key)?,
+		}
+		.save(value, height)
+	}
+
+	/// Same as `save`, but also adjusts `total` by the difference between the key's new and old value.
+	pub fn save_with_total(&self, key: &K, value: Uint128, total: &SnapshotItem, height: u64) -> StdResult<()> {
+		let old_value = self.load_current(key)?;
+		let total_value = total.load_current()?;
+		let new_total = if value >= old_value {
+			total_value.checked_add(value - old_value)?
+		} else {
+			total_value.checked_sub(old_value - value)?
+		};
+		self.save(key, value, height)?;
+		total.save(new_total, height)
+	}
+
+	/// Returns the key's value as of the specified height, which is zero if it had no value at the time.
+	pub fn load_at(&self, key: &K, height: u64) -> StdResult<Uint128> {
+		Ok(Checkpoints {
+			prefix: &self.key_prefix(key)?,
+		}
+		.latest_at(height)?
+		.map(|(_, value)| value)
+		.unwrap_or_default())
+	}
+
+	pub fn load_current(&self, key: &K) -> StdResult<Uint128> {
+		self.load_at(key, u64::MAX)
+	}
+
+	/// Removes the key's checkpoints which aren't needed to answer queries at or after the specified height.
+	///
+	/// Returns the amount of checkpoints removed. Queries before `height` are no longer accurate afterwards.
+	pub fn prune_below(&self, key: &K, height: u64) -> StdResult<u32> {
+		Checkpoints {
+			prefix: &self.key_prefix(key)?,
+		}
+		.prune_below(height)
+	}
+}
+
+/// Like [SnapshotMap], but for a single value such as the total supply.
+pub struct SnapshotItem {
+	namespace: &'static [u8],
+}
+
+impl SnapshotItem {
+	pub const fn new(namespace: &'static [u8]) -> Self {
+		Self { namespace }
+	}
+
+	fn checkpoints(&self) -> Checkpoints<'static> {
+		Checkpoints { prefix: self.namespace }
+	}
+
+	/// Records the value as of the specified height. Heights must be saved in non-decreasing order.
+	pub fn save(&self, value: Uint128, height: u64) -> StdResult<()> {
+		self.checkpoints().save(value, height)
+	}
+
+	/// Returns the value as of the specified height, which is zero if it had no value at the time.
+	pub fn load_at(&self, height: u64) -> StdResult<Uint128> {
+		Ok(self
+			.checkpoints()
+			.latest_at(height)?
+			.map(|(_, value)| value)
+			.unwrap_or_default())
+	}
+
+	pub fn load_current(&self) -> StdResult<Uint128> {
+		self.load_at(u64::MAX)
+	}
+
+	/// Removes the checkpoints which aren't needed to answer queries at or after the specified height.
+	pub fn prune_below(&self, height: u64) -> StdResult<u32> {
+		self.checkpoints().prune_below(height)
+	}
+}
+
+/// How often [SnapshotStoredMap] and [SnapshotStoredItem] record changelog entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotStrategy {
+	/// Every write is recorded at its own height, so historical reads are exact.
+	EveryWrite,
+	/// Writes are recorded at the next height which is a multiple of N, where later writes before that height
+	/// overwrite the same entry. This writes at most one changelog entry per key every N heights, at the cost of
+	/// historical reads being exact only at multiples of N. Reads at other heights may return a value up to N - 1
+	/// heights old, but never one written after the requested height.
+	EveryNHeights(NonZeroU64),
+}
+
+impl SnapshotStrategy {
+	fn checkpoint_height(self, height: u64) -> u64 {
+		match self {
+			SnapshotStrategy::EveryWrite => height,
+			SnapshotStrategy::EveryNHeights(interval) => match height % interval {
+				0 => height,
+				remainder => height.saturating_add(interval.get() - remainder),
+			},
+		}
+	}
+}
+
+/// A [StoredMap] which also records the history of every key in a changelog, so that the value of a key at any past
+/// block height can be queried with [value_at](Self::value_at), such as for governance votes.
+///
+/// Unlike [SnapshotMap], values can be of any type. The current values are stored in a regular [StoredMap] under
+/// `namespace`, while the changelog entries are stored under
+/// `changelog_namespace ++ key length (u16 BE) ++ key ++ height (u64 BE)` as `1 ++ value bytes`, or `0` for a removal.
+///
+/// The height is usually `env.block.height`, and must not decrease between writes to the same key.
+pub struct SnapshotStoredMap<K: SerializableItem, V: SerializableItem> {
+	values: StoredMap<K, V>,
+	changelog_namespace: &'static [u8],
+	strategy: SnapshotStrategy,
+}
+
+impl<K: SerializableItem, V: SerializableItem> SnapshotStoredMap<K, V> {
+	pub fn new(namespace: &'static [u8], changelog_namespace: &'static [u8], strategy: SnapshotStrategy) -> Self {
+		Self {
+			values: StoredMap::new(namespace),
+			changelog_namespace,
+			strategy,
+		}
+	}
+
+	/// The map holding the current values, e.g. for iterating over them. Writing to it directly bypasses the
+	/// changelog.
+	pub fn current(&self) -> &StoredMap<K, V> {
+		&self.values
+	}
+
+	pub fn get(&self, key: &K) -> StdResult<Option<OZeroCopy<V>>> {
+		self.values.get(key)
+	}
+
+	pub fn has(&self, key: &K) -> bool {
+		self.values.has(key)
+	}
+
+	pub fn set(&self, key: &K, value: &V, height: u64) -> StdResult<()> {
+		Checkpoints {
+			prefix: &self.key_prefix(key)?,
+		}
+		.record(Some(value), height, self.strategy)?;
+		self.values.set(key, value)
+	}
+
+	/// Removes the key, recording that it had no value as of the specified height. Does nothing if it had no value.
+	pub fn remove(&self, key: &K, height: u64) -> StdResult<()> {
+		if !self.values.has(key) {
+			return Ok(());
+		}
+		Checkpoints {
+			prefix: &self.key_prefix(key)?,
+		}
+		.record::<V>(None, height, self.strategy)?;
+		self.values.remove(key);
+		Ok(())
+	}
+
+	/// Returns the key's value as of the specified height, or `None` if it had no value at the time.
+	pub fn value_at(&self, key: &K, height: u64) -> StdResult<Option<OZeroCopy<V>>> {
+		Checkpoints {
+			prefix: &self.key_prefix(key)?,
+		}
+		.value_at(height)
+	}
+
+	/// Removes the key's changelog entries which aren't needed to answer queries at or after the specified height.
+	///
+	/// Returns the amount of entries removed. Queries before `height` are no longer accurate afterwards.
+	pub fn prune_below(&self, key: &K, height: u64) -> StdResult<u32> {
+		Checkpoints {
+			prefix: &self.key_prefix(key)?,
+		}
+		.prune_below(height)
+	}
+
+	fn key_prefix(&self, key: &K) -> StdResult<Vec<u8>> {
+		length_prefixed_key(self.changelog_namespace, key)
+	}
+}
+
+/// Like [SnapshotStoredMap], but for a single value. The current value is stored under `namespace`, and the changelog
+/// entries under `changelog_namespace ++ height (u64 BE)`.
+pub struct SnapshotStoredItem<T: SerializableItem> {
+	namespace: &'static [u8],
+	changelog_namespace: &'static [u8],
+	strategy: SnapshotStrategy,
+	value_type: PhantomData<T>,
+}
+
+impl<T: SerializableItem> SnapshotStoredItem<T> {
+	pub const fn new(namespace: &'static [u8], changelog_namespace: &'static [u8], strategy: SnapshotStrategy) -> Self {
+		Self {
+			namespace,
+			changelog_namespace,
+			strategy,
+			value_type: PhantomData,
+		}
+	}
+
+	fn checkpoints(&self) -> Checkpoints<'static> {
+		Checkpoints {
+			prefix: self.changelog_namespace,
+		}
+	}
+
+	pub fn get(&self) -> StdResult<Option<OZeroCopy<T>>> {
+		storage_read_item(self.namespace)
+	}
+
+	pub fn set(&self, value: &T, height: u64) -> StdResult<()> {
+		self.checkpoints().record(Some(value), height, self.strategy)?;
+		storage_write_item(self.namespace, value)
+	}
+
+	/// Removes the value, recording that there was no value as of the specified height. Does nothing if there was no
+	/// value.
+	pub fn remove(&self, height: u64) -> StdResult<()> {
+		if !storage_has(self.namespace) {
+			return Ok(());
+		}
+		self.checkpoints().record::<T>(None, height, self.strategy)?;
+		storage_remove(self.namespace);
+		Ok(())
+	}
+
+	/// Returns the value as of the specified height, or `None` if there was no value at the time.
+	pub fn value_at(&self, height: u64) -> StdResult<Option<OZeroCopy<T>>> {
+		self.checkpoints().value_at(height)
+	}
+
+	/// Removes the changelog entries which aren't needed to answer queries at or after the specified height.
+	pub fn prune_below(&self, height: u64) -> StdResult<u32> {
+		self.checkpoints().prune_below(height)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	fn voting_power() -> SnapshotMap<String> {
+		SnapshotMap::new(NAMESPACE)
+	}
+
+	#[test]
+	fn checkpoints() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = voting_power();
+		let alice = "alice".to_string();
+		map.save(&alice, 100u128.into(), 10)?;
+		map.save(&alice, 100u128.into(), 15)?;
+		map.save(&alice, 250u128.into(), 20)?;
+		map.save(&alice, 50u128.into(), 30)?;
+
+		// Before any checkpoint
+		assert_eq!(map.load_at(&alice, 9)?, Uint128::zero());
+		// Exactly at a checkpoint
+		assert_eq!(map.load_at(&alice, 10)?, Uint128::new(100));
+		assert_eq!(map.load_at(&alice, 20)?, Uint128::new(250));
+		// Between checkpoints
+		assert_eq!(map.load_at(&alice, 15)?, Uint128::new(100));
+		assert_eq!(map.load_at(&alice, 29)?, Uint128::new(250));
+		assert_eq!(map.load_at(&alice, 1000)?, Uint128::new(50));
+		assert_eq!(map.load_current(&alice)?, Uint128::new(50));
+		assert_eq!(map.load_current(&"bob".to_string())?, Uint128::zero());
+
+		assert!(map.save(&alice, 1u128.into(), 29).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn unchanged_values_are_not_written() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = voting_power();
+		let alice = "alice".to_string();
+		map.save(&alice, 100u128.into(), 10)?;
+		map.save(&alice, 100u128.into(), 11)?;
+		let prefix = map.key_prefix(&alice)?;
+		assert_eq!(
+			StorageKeyIterator::new(Some(&prefix), Some(&lexicographic_next(&prefix))).count(),
+			1
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn prune() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = voting_power();
+		let alice = "alice".to_string();
+		let alice_2 = "alice2".to_string();
+		map.save(&alice, 1u128.into(), 10)?;
+		map.save(&alice, 2u128.into(), 20)?;
+		map.save(&alice, 3u128.into(), 30)?;
+		map.save(&alice_2, 7u128.into(), 5)?;
+
+		// The checkpoint at 20 is still needed for queries at 25
+		assert_eq!(map.prune_below(&alice, 25)?, 1);
+		assert_eq!(map.load_at(&alice, 25)?, Uint128::new(2));
+		assert_eq!(map.load_at(&alice, 30)?, Uint128::new(3));
+		assert_eq!(map.load_at(&alice, 10)?, Uint128::zero());
+		assert_eq!(map.prune_below(&alice, 25)?, 0);
+		// Other keys are unaffected
+		assert_eq!(map.load_at(&alice_2, 5)?, Uint128::new(7));
+		Ok(())
+	}
+
+	#[test]
+	fn total_supply() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = voting_power();
+		let total = SnapshotItem::new(b"total");
+		map.save_with_total(&"alice".to_string(), 100u128.into(), &total, 10)?;
+		map.save_with_total(&"bob".to_string(), 50u128.into(), &total, 12)?;
+		map.save_with_total(&"alice".to_string(), 30u128.into(), &total, 15)?;
+
+		assert_eq!(total.load_at(9)?, Uint128::zero());
+		assert_eq!(total.load_at(10)?, Uint128::new(100));
+		assert_eq!(total.load_at(12)?, Uint128::new(150));
+		assert_eq!(total.load_current()?, Uint128::new(80));
+		assert_eq!(total.prune_below(12)?, 1);
+		assert_eq!(total.load_at(13)?, Uint128::new(150));
+		Ok(())
+	}
+
+	fn values_at(map: &SnapshotStoredMap<String, u64>, key: &str, heights: &[u64]) -> StdResult<Vec<Option<u64>>> {
+		heights
+			.iter()
+			.map(|height| Ok(map.value_at(&key.to_string(), *height)?.map(OZeroCopy::into_inner)))
+			.collect()
+	}
+
+	#[test]
+	fn stored_map_history() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = SnapshotStoredMap::<String, u64>::new(NAMESPACE, b"changelog", SnapshotStrategy::EveryWrite);
+		let alice = "alice".to_string();
+		map.set(&alice, &100, 10)?;
+		map.set(&alice, &250, 20)?;
+		map.set(&alice, &50, 30)?;
+		// Only the last write within the same height is kept
+		map.set(&alice, &75, 30)?;
+		map.set(&"alice2".to_string(), &1, 5)?;
+
+		assert_eq!(
+			values_at(&map, "alice", &[0, 9, 10, 19, 20, 29, 30, u64::MAX])?,
+			[
+				None,
+				None,
+				Some(100),
+				Some(100),
+				Some(250),
+				Some(250),
+				Some(75),
+				Some(75)
+			]
+		);
+		assert_eq!(map.get(&alice)?.map(OZeroCopy::into_inner), Some(75));
+		assert_eq!(values_at(&map, "bob", &[0, 30])?, [None, None]);
+		assert!(map.set(&alice, &1, 29).is_err());
+
+		map.remove(&alice, 40)?;
+		map.set(&alice, &5, 50)?;
+		assert_eq!(
+			values_at(&map, "alice", &[30, 39, 40, 49, 50])?,
+			[Some(75), Some(75), None, None, Some(5)]
+		);
+		assert_eq!(values_at(&map, "alice2", &[4, 5, 50])?, [None, Some(1), Some(1)]);
+
+		// The entry at 30 is still needed for queries at 35
+		assert_eq!(map.prune_below(&alice, 35)?, 2);
+		assert_eq!(
+			values_at(&map, "alice", &[10, 35, 40, 50])?,
+			[None, Some(75), None, Some(5)]
+		);
+		assert_eq!(values_at(&map, "alice2", &[5])?, [Some(1)]);
+		Ok(())
+	}
+
+	#[test]
+	fn stored_map_every_n_heights() -> TestingResult {
+		let _storage_lock = init()?;
+		let strategy = SnapshotStrategy::EveryNHeights(NonZeroU64::new(10).unwrap());
+		let map = SnapshotStoredMap::<String, u64>::new(NAMESPACE, b"changelog", strategy);
+		let alice = "alice".to_string();
+		map.set(&alice, &1, 3)?;
+		map.set(&alice, &2, 7)?;
+		map.set(&alice, &3, 10)?;
+		map.set(&alice, &4, 12)?;
+
+		// Reads are exact at multiples of 10, and never return values written after the requested height
+		assert_eq!(
+			values_at(&map, "alice", &[3, 9, 10, 12, 19, 20])?,
+			[None, None, Some(3), Some(3), Some(3), Some(4)]
+		);
+		assert_eq!(map.get(&alice)?.map(OZeroCopy::into_inner), Some(4));
+		let changelog = length_prefixed_key(b"changelog", &alice)?;
+		assert_eq!(
+			StorageKeyIterator::new(Some(&changelog), Some(&lexicographic_next(&changelog))).count(),
+			2
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn stored_item_history() -> TestingResult {
+		let _storage_lock = init()?;
+		let item = SnapshotStoredItem::<String>::new(NAMESPACE, b"changelog", SnapshotStrategy::EveryWrite);
+		let value_at = |height| -> StdResult<Option<String>> { Ok(item.value_at(height)?.map(OZeroCopy::into_inner)) };
+		item.set(&"first".to_string(), 10)?;
+		item.set(&"second".to_string(), 20)?;
+		item.remove(30)?;
+		item.remove(31)?;
+
+		assert_eq!(value_at(9)?, None);
+		assert_eq!(value_at(10)?.as_deref(), Some("first"));
+		assert_eq!(value_at(25)?.as_deref(), Some("second"));
+		assert_eq!(value_at(30)?, None);
+		assert_eq!(item.get()?.map(OZeroCopy::into_inner), None);
+
+		item.set(&"third".to_string(), 40)?;
+		assert_eq!(item.get()?.map(OZeroCopy::into_inner).as_deref(), Some("third"));
+		assert_eq!(item.prune_below(25)?, 1);
+		assert_eq!(value_at(25)?.as_deref(), Some("second"));
+		assert_eq!(value_at(45)?.as_deref(), Some("third"));
+		Ok(())
+	}
+}
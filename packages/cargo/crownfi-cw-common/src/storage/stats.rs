@@ -0,0 +1,197 @@
+use bytemuck::{Pod, Zeroable};
+use cosmwasm_std::{OverflowError, OverflowOperation, StdError, StdResult};
+
+use crate::{assert_pod_layout, impl_serializable_as_ref, utils::lexicographic_next};
+
+use super::{
+	item::AutosavingSerializableItem,
+	map::{StoredMap, StoredMapIter},
+	SerializableItem,
+};
+
+/// Aggregate statistics over the values of a container, maintained incrementally so they can be queried without
+/// scanning the container.
+///
+/// `count` and `sum` are always exact. `min` and `max` are only exact as long as no values have been removed or
+/// updated, since the next smallest or largest value can't be known without a scan. Afterwards they're bounds: `min`
+/// is less than or equal to the actual minimum, and `max` is greater than or equal to the actual maximum. Use
+/// [rebuild_from](Self::rebuild_from) or [rebuild_batch](Self::rebuild_batch) to make them exact again.
+///
+/// `min` and `max` are 0 while `count` is 0.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Zeroable, Pod)]
+#[repr(C)]
+#[allow(dead_code)]
+pub struct StoredStats {
+	pub count: u64,
+	_padding: u64,
+	pub sum: u128,
+	pub min: u128,
+	pub max: u128,
+}
+impl_serializable_as_ref!(StoredStats);
+assert_pod_layout!(StoredStats, size = 64, align = 16, fields = [count: 0, _padding: 8, sum: 16, min: 32, max: 48]);
+
+impl StoredStats {
+	/// Loads the stats stored under the specified key, which are saved when the returned value is dropped if they were
+	/// modified.
+	pub fn load_autosaving(namespace: &[u8]) -> StdResult<AutosavingSerializableItem<Self>> {
+		AutosavingSerializableItem::new_or_default(namespace.to_vec())
+	}
+
+	pub fn record_insert(&mut self, value: u128) -> StdResult<()> {
+		self.sum = self
+			.sum
+			.checked_add(value)
+			.ok_or_else(|| OverflowError::new(OverflowOperation::Add, self.sum, value))?;
+		if self.count == 0 {
+			self.min = value;
+			self.max = value;
+		} else {
+			self.min = self.min.min(value);
+			self.max = self.max.max(value);
+		}
+		self.count += 1;
+		Ok(())
+	}
+
+	/// Records the removal of a value. Leaves `min` and `max` as-is unless this was the last value.
+	pub fn record_remove(&mut self, value: u128) -> StdResult<()> {
+		self.sum = self
+			.sum
+			.checked_sub(value)
+			.ok_or_else(|| OverflowError::new(OverflowOperation::Sub, self.sum, value))?;
+		self.count = self
+			.count
+			.checked_sub(1)
+			.ok_or_else(|| OverflowError::new(OverflowOperation::Sub, self.count, 1))?;
+		if self.count == 0 {
+			self.min = 0;
+			self.max = 0;
+		}
+		Ok(())
+	}
+
+	/// Records a value being replaced. `min` and `max` are only widened to include the new value.
+	pub fn record_update(&mut self, old_value: u128, new_value: u128) -> StdResult<()> {
+		let new_sum = self
+			.sum
+			.checked_sub(old_value)
+			.ok_or_else(|| OverflowError::new(OverflowOperation::Sub, self.sum, old_value))?;
+		self.sum = new_sum
+			.checked_add(new_value)
+			.ok_or_else(|| OverflowError::new(OverflowOperation::Add, new_sum, new_value))?;
+		self.min = self.min.min(new_value);
+		self.max = self.max.max(new_value);
+		Ok(())
+	}
+
+	/// Computes exact stats over all values of the map, `f` returns the value to aggregate for each entry.
+	///
+	/// This is a full scan, see [rebuild_batch](Self::rebuild_batch) for maps which may be too large to scan in one
+	/// transaction.
+	pub fn rebuild_from<K: SerializableItem, V: SerializableItem>(
+		map: &StoredMap<K, V>,
+		mut f: impl FnMut(&K, &V) -> u128,
+	) -> StdResult<Self> {
+		let mut stats = Self::default();
+		for (key, value) in map.iter()? {
+			stats.record_insert(f(&key, &value))?;
+		}
+		Ok(stats)
+	}
+
+	/// Adds up to `limit` entries of the map which come after `start_after` to these stats.
+	///
+	/// Returns the key to pass as `start_after` for the next batch, or `None` if the end of the map was reached.
+	/// Starting from [StoredStats::default], this results in exact stats once all batches have been processed. As the
+	/// map may change in between batches, the partial stats should be stored separately and only replace the
+	/// incrementally maintained ones once complete, with every mutation to an already-scanned key being recorded in
+	/// both.
+	pub fn rebuild_batch<K: SerializableItem, V: SerializableItem>(
+		&mut self,
+		map: &StoredMap<K, V>,
+		start_after: Option<K>,
+		limit: usize,
+		mut f: impl FnMut(&K, &V) -> u128,
+	) -> StdResult<Option<K>> {
+		let namespace = map.namespace();
+		let end = lexicographic_next(namespace);
+		// Appending a 0 results in the smallest storage key which comes after start_after's
+		let start = start_after.map_or_else(|| namespace.to_vec(), |key| [&map.key(&key)[..], &[0]].concat());
+		let mut iter = StoredMapIter::<K, V>::new_raw(&start, &end, namespace.len()).peekable();
+		let mut last_key = None;
+		for (key, value) in iter.by_ref().take(limit.max(1)) {
+			self.record_insert(f(&key, &value))?;
+			last_key = Some(key);
+		}
+		if iter.peek().is_none() {
+			return Ok(None);
+		}
+		Ok(last_key)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::{base::storage_read_item, testing_common::*, OZeroCopy};
+
+	#[test]
+	fn min_max_after_remove_are_bounds() -> TestingResult {
+		let _storage_lock = init()?;
+		let positions = StoredMap::<u32, u128>::new(NAMESPACE);
+		let mut stats = StoredStats::default();
+		for (key, value) in [(1, 50), (2, 10), (3, 1000)] {
+			positions.set_tracked(&key, &value, &mut stats, |value| *value)?;
+		}
+		assert_eq!((stats.count, stats.sum, stats.min, stats.max), (3, 1060, 10, 1000));
+
+		// The largest position is closed, max can't know what the next largest is
+		positions.remove_tracked(&3, &mut stats, |value| *value)?;
+		positions.set_tracked(&2, &20, &mut stats, |value| *value)?;
+		assert_eq!((stats.count, stats.sum, stats.min, stats.max), (2, 70, 10, 1000));
+		let rebuilt = StoredStats::rebuild_from(&positions, |_, value| *value)?;
+		assert_eq!((rebuilt.count, rebuilt.sum, rebuilt.min, rebuilt.max), (2, 70, 20, 50));
+
+		positions.remove_tracked(&1, &mut stats, |value| *value)?;
+		positions.remove_tracked(&2, &mut stats, |value| *value)?;
+		// Removing a missing entry doesn't affect the stats
+		positions.remove_tracked(&2, &mut stats, |value| *value)?;
+		assert_eq!(stats, StoredStats::default());
+		assert!(stats.record_remove(1).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn rebuild_in_batches() -> TestingResult {
+		let _storage_lock = init()?;
+		let orders = StoredMap::<u32, u64>::new(NAMESPACE);
+		for key in 0..10u32 {
+			orders.set(&key, &(key as u64 * 3))?;
+		}
+		let mut stats = StoredStats::default();
+		let mut cursor = None;
+		let mut batches = 0;
+		loop {
+			cursor = stats.rebuild_batch(&orders, cursor, 4, |_, value| *value as u128)?;
+			batches += 1;
+			if cursor.is_none() {
+				break;
+			}
+		}
+		assert_eq!(batches, 3);
+		assert_eq!(stats, StoredStats::rebuild_from(&orders, |_, value| *value as u128)?);
+		assert_eq!((stats.count, stats.sum, stats.min, stats.max), (10, 135, 0, 27));
+		Ok(())
+	}
+
+	#[test]
+	fn autosaving() -> TestingResult {
+		let _storage_lock = init()?;
+		StoredStats::load_autosaving(b"stats")?.record_insert(5)?;
+		StoredStats::load_autosaving(b"stats")?.record_insert(7)?;
+		let stats = storage_read_item::<StoredStats>(b"stats")?.map(OZeroCopy::into_inner);
+		assert_eq!(stats.map(|stats| (stats.count, stats.sum)), Some((2, 12)));
+		Ok(())
+	}
+}
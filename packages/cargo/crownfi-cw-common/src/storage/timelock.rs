@@ -0,0 +1,239 @@
+use std::marker::PhantomData;
+
+use cosmwasm_std::{StdError, StdResult, Timestamp};
+use thiserror::Error;
+
+use super::{
+	base::{storage_read, storage_read_item, storage_remove, storage_write, storage_write_item, with_storage_context},
+	concat_byte_array_pairs, OZeroCopy, SerializableItem,
+};
+
+const CURRENT_SUFFIX: &[u8] = &[0];
+const PENDING_SUFFIX: &[u8] = &[1];
+const TIMESTAMP_PREFIX_LEN: usize = 8;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TimelockError {
+	#[error("A change is already pending, taking effect at {0}")]
+	AlreadyPending(Timestamp),
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<TimelockError> for StdError {
+	fn from(value: TimelockError) -> Self {
+		match value {
+			TimelockError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// A single value whose changes only take effect after a delay, e.g. for parameter changes which users should be
+/// able to react to.
+///
+/// Reading is side-effect free: [current](Self::current) returns the pending value once its effective time has
+/// passed, even if it hasn't been promoted yet. Call [tick](Self::tick) from any execute handler to persist the
+/// promotion. [schedule](Self::schedule) does this itself, so that a matured change is never replaced.
+///
+/// The current value is stored under `namespace ++ 0`, and the pending value under
+/// `namespace ++ 1` as `effective time in nanoseconds (u64 LE) || value bytes`.
+pub struct TimelockedItem<T: SerializableItem> {
+	current_key: Vec<u8>,
+	pending_key: Vec<u8>,
+	namespace: &'static [u8],
+	value_type: PhantomData<T>,
+}
+
+impl<T: SerializableItem> TimelockedItem<T> {
+	pub fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			current_key: concat_byte_array_pairs(namespace, CURRENT_SUFFIX),
+			pending_key: concat_byte_array_pairs(namespace, PENDING_SUFFIX),
+			namespace,
+			value_type: PhantomData,
+		}
+	}
+
+	/// Sets the current value immediately, bypassing the timelock. Intended for instantiation.
+	pub fn set_immediately(&self, value: &T) -> StdResult<()> {
+		storage_write_item(&self.current_key, value)
+	}
+
+	/// Schedules `new_value` to take effect at `effective_at`.
+	///
+	/// A pending change which has taken effect by `now` is promoted first, see [tick](Self::tick). Returns an error if
+	/// a change is still pending after that, unless `overwrite` is true, in which case the pending change is replaced.
+	pub fn schedule(
+		&self,
+		new_value: &T,
+		effective_at: Timestamp,
+		now: Timestamp,
+		overwrite: bool,
+	) -> Result<(), TimelockError> {
+		self.tick(now)?;
+		if !overwrite {
+			if let Some(pending_at) = self.pending_effective_at()? {
+				return Err(TimelockError::AlreadyPending(pending_at));
+			}
+		}
+		let mut bytes;
+		if let Some(value_bytes) = new_value.serialize_as_ref() {
+			bytes = Vec::with_capacity(TIMESTAMP_PREFIX_LEN + value_bytes.len());
+			bytes.extend_from_slice(&effective_at.nanos().to_le_bytes());
+			bytes.extend_from_slice(value_bytes);
+		} else {
+			bytes = new_value.serialize_to_owned()?;
+			bytes.splice(0..0, effective_at.nanos().to_le_bytes());
+		}
+		storage_write(&self.pending_key, &bytes);
+		Ok(())
+	}
+
+	/// Returns the value which is in effect at `now`.
+	pub fn current(&self, now: Timestamp) -> StdResult<Option<OZeroCopy<T>>> {
+		if let Some((effective_at, value)) = self.pending()? {
+			if effective_at <= now {
+				return Ok(Some(value));
+			}
+		}
+		storage_read_item(&self.current_key)
+	}
+
+	/// Returns the pending value along with the time it takes effect, if any.
+	pub fn pending(&self) -> StdResult<Option<(Timestamp, OZeroCopy<T>)>> {
+		let Some(mut bytes) = storage_read(&self.pending_key) else {
+			return Ok(None);
+		};
+		let effective_at = read_effective_at(&bytes).map_err(|err| with_storage_context(err, self.namespace, &[]))?;
+		bytes.drain(..TIMESTAMP_PREFIX_LEN);
+		let value = OZeroCopy::new(bytes).map_err(|err| with_storage_context(err, self.namespace, &[]))?;
+		Ok(Some((effective_at, value)))
+	}
+
+	fn pending_effective_at(&self) -> StdResult<Option<Timestamp>> {
+		storage_read(&self.pending_key)
+			.map(|bytes| read_effective_at(&bytes))
+			.transpose()
+	}
+
+	/// Removes the pending change, if any.
+	pub fn cancel_pending(&self) {
+		storage_remove(&self.pending_key)
+	}
+
+	/// Makes the pending value the current one if its effective time has passed.
+	///
+	/// Returns true if a value was promoted.
+	pub fn tick(&self, now: Timestamp) -> StdResult<bool> {
+		let Some(effective_at) = self.pending_effective_at()? else {
+			return Ok(false);
+		};
+		if effective_at > now {
+			return Ok(false);
+		}
+		let mut bytes = storage_read(&self.pending_key).expect("pending_effective_at just read this");
+		bytes.drain(..TIMESTAMP_PREFIX_LEN);
+		storage_write(&self.current_key, &bytes);
+		storage_remove(&self.pending_key);
+		Ok(true)
+	}
+}
+
+fn read_effective_at(bytes: &[u8]) -> StdResult<Timestamp> {
+	let Some(nanos) = bytes.first_chunk::<TIMESTAMP_PREFIX_LEN>() else {
+		return Err(StdError::parse_err(
+			"TimelockedItem",
+			"pending value is missing its timestamp prefix",
+		));
+	};
+	Ok(Timestamp::from_nanos(u64::from_le_bytes(*nanos)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	fn fee_bps() -> TimelockedItem<u16> {
+		TimelockedItem::new(NAMESPACE)
+	}
+
+	fn current(item: &TimelockedItem<u16>, seconds: u64) -> StdResult<Option<u16>> {
+		Ok(item
+			.current(Timestamp::from_seconds(seconds))?
+			.map(OZeroCopy::into_inner))
+	}
+
+	#[test]
+	fn takes_effect_after_delay() -> TestingResult {
+		let _storage_lock = init()?;
+		let item = fee_bps();
+		assert_eq!(current(&item, 0)?, None);
+		item.set_immediately(&30)?;
+		item.schedule(&50, Timestamp::from_seconds(100), Timestamp::from_seconds(0), false)?;
+
+		assert_eq!(current(&item, 99)?, Some(30));
+		assert_eq!(current(&item, 100)?, Some(50));
+		assert_eq!(
+			item.pending()?.map(|(at, value)| (at, value.into_inner())),
+			Some((Timestamp::from_seconds(100), 50))
+		);
+		assert_eq!(
+			item.schedule(&60, Timestamp::from_seconds(200), Timestamp::from_seconds(0), false),
+			Err(TimelockError::AlreadyPending(Timestamp::from_seconds(100)))
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn cancel_and_overwrite() -> TestingResult {
+		let _storage_lock = init()?;
+		let item = fee_bps();
+		item.set_immediately(&30)?;
+		item.schedule(&50, Timestamp::from_seconds(100), Timestamp::from_seconds(0), false)?;
+		item.cancel_pending();
+		assert_eq!(item.pending()?, None);
+		assert_eq!(current(&item, 1000)?, Some(30));
+
+		item.schedule(&50, Timestamp::from_seconds(100), Timestamp::from_seconds(0), false)?;
+		item.schedule(&70, Timestamp::from_seconds(300), Timestamp::from_seconds(0), true)?;
+		assert_eq!(current(&item, 200)?, Some(30));
+		assert_eq!(current(&item, 300)?, Some(70));
+		Ok(())
+	}
+
+	#[test]
+	fn matured_change_is_kept_when_scheduling() -> TestingResult {
+		let _storage_lock = init()?;
+		let item = fee_bps();
+		item.set_immediately(&30)?;
+		item.schedule(&50, Timestamp::from_seconds(100), Timestamp::from_seconds(0), false)?;
+		// 50 has taken effect but hasn't been ticked
+		item.schedule(&70, Timestamp::from_seconds(300), Timestamp::from_seconds(150), true)?;
+		assert_eq!(current(&item, 200)?, Some(50));
+		assert_eq!(current(&item, 300)?, Some(70));
+
+		// Nothing is pending anymore once the change has taken effect
+		item.schedule(&90, Timestamp::from_seconds(400), Timestamp::from_seconds(300), false)?;
+		assert_eq!(current(&item, 350)?, Some(70));
+		Ok(())
+	}
+
+	#[test]
+	fn promotion_happens_once() -> TestingResult {
+		let _storage_lock = init()?;
+		let item = fee_bps();
+		item.set_immediately(&30)?;
+		item.schedule(&50, Timestamp::from_seconds(100), Timestamp::from_seconds(0), false)?;
+		assert!(!item.tick(Timestamp::from_seconds(99))?);
+		assert_eq!(storage_read(&item.current_key), Some(30u16.to_le_bytes().to_vec()));
+
+		assert!(item.tick(Timestamp::from_seconds(100))?);
+		assert!(!item.tick(Timestamp::from_seconds(101))?);
+		assert_eq!(item.pending()?, None);
+		assert_eq!(current(&item, 0)?, Some(50));
+		// A new change can be scheduled without overwriting
+		item.schedule(&10, Timestamp::from_seconds(500), Timestamp::from_seconds(101), false)?;
+		Ok(())
+	}
+}
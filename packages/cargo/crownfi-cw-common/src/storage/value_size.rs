@@ -0,0 +1,244 @@
+use cosmwasm_std::StdError;
+use thiserror::Error;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::cell::Cell;
+
+/// The limit on the size of a single storage value used until [set_max_value_size] is called, `None` meaning
+/// unlimited.
+///
+/// Taken from the `CROWNFI_MAX_VALUE_SIZE` environment variable at compile time if set, which is the only way to
+/// configure it without calling [set_max_value_size] first, e.g. `CROWNFI_MAX_VALUE_SIZE=65536 cargo build`.
+pub const DEFAULT_MAX_VALUE_SIZE: Option<usize> = match option_env!("CROWNFI_MAX_VALUE_SIZE") {
+	Some(max_size) => Some(parse_max_value_size(max_size)),
+	None => None,
+};
+
+/// Parses a decimal byte count, used for `CROWNFI_MAX_VALUE_SIZE`.
+const fn parse_max_value_size(max_size: &str) -> usize {
+	let digits = max_size.as_bytes();
+	assert!(!digits.is_empty(), "CROWNFI_MAX_VALUE_SIZE must not be empty");
+	let mut result: usize = 0;
+	let mut i = 0;
+	while i < digits.len() {
+		assert!(
+			digits[i].is_ascii_digit(),
+			"CROWNFI_MAX_VALUE_SIZE must be a decimal number of bytes"
+		);
+		result = match result.checked_mul(10) {
+			Some(result) => match result.checked_add((digits[i] - b'0') as usize) {
+				Some(result) => result,
+				None => panic!("CROWNFI_MAX_VALUE_SIZE is too large"),
+			},
+			None => panic!("CROWNFI_MAX_VALUE_SIZE is too large"),
+		};
+		i += 1;
+	}
+	result
+}
+
+/// Returned instead of writing a value which is larger than the configured limit.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error(
+	"Value of {size} bytes exceeds the limit of {max_size} bytes (namespace: {:?})",
+	String::from_utf8_lossy(namespace)
+)]
+pub struct ValueTooLarge {
+	pub namespace: Vec<u8>,
+	pub size: usize,
+	pub max_size: usize,
+}
+impl From<ValueTooLarge> for StdError {
+	fn from(value: ValueTooLarge) -> Self {
+		StdError::generic_err(value.to_string())
+	}
+}
+
+/// Returned by the writes which are subject to the value size limit, so that callers can tell [ValueTooLarge] apart
+/// from other errors. Converts into a [StdError] with `?` like the other error types of this crate.
+#[derive(Error, Debug, PartialEq)]
+pub enum StorageWriteError {
+	#[error("{0}")]
+	ValueTooLarge(#[from] ValueTooLarge),
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<StorageWriteError> for StdError {
+	fn from(value: StorageWriteError) -> Self {
+		match value {
+			StorageWriteError::ValueTooLarge(err) => err.into(),
+			StorageWriteError::Std(err) => err,
+		}
+	}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+thread_local! {
+	static MAX_VALUE_SIZE: Cell<Option<usize>> = const { Cell::new(DEFAULT_MAX_VALUE_SIZE) };
+}
+
+#[cfg(target_arch = "wasm32")]
+struct SingleThreaded(std::cell::Cell<Option<usize>>);
+// SAFTY: wasm contracts don't have threads
+#[cfg(target_arch = "wasm32")]
+unsafe impl Sync for SingleThreaded {}
+#[cfg(target_arch = "wasm32")]
+static MAX_VALUE_SIZE: SingleThreaded = SingleThreaded(std::cell::Cell::new(DEFAULT_MAX_VALUE_SIZE));
+
+/// Sets the limit on the size of values written through [storage_write_item](super::base::storage_write_item) and
+/// the containers' `set` and `push` methods, `None` meaning unlimited. Returns the previous limit.
+///
+/// Containers configured with `with_max_value_size` use their own limit instead. Reads are never limited, so values
+/// written before the limit was lowered can still be loaded.
+///
+/// On native targets the limit is per-thread, so parallel tests don't affect each other.
+pub fn set_max_value_size(max_size: Option<usize>) -> Option<usize> {
+	#[cfg(not(target_arch = "wasm32"))]
+	return MAX_VALUE_SIZE.replace(max_size);
+	#[cfg(target_arch = "wasm32")]
+	return MAX_VALUE_SIZE.0.replace(max_size);
+}
+
+/// Returns the limit set with [set_max_value_size].
+pub fn max_value_size() -> Option<usize> {
+	#[cfg(not(target_arch = "wasm32"))]
+	return MAX_VALUE_SIZE.get();
+	#[cfg(target_arch = "wasm32")]
+	return MAX_VALUE_SIZE.0.get();
+}
+
+/// Returns [ValueTooLarge] if `size` exceeds `max_size`, or the global limit if `max_size` is `None`.
+pub(crate) fn check_value_size(namespace: &[u8], size: usize, max_size: Option<usize>) -> Result<(), ValueTooLarge> {
+	match max_size.or_else(max_value_size) {
+		Some(max_size) if size > max_size => Err(ValueTooLarge {
+			namespace: namespace.to_vec(),
+			size,
+			max_size,
+		}),
+		_ => Ok(()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::{
+		base::{storage_read, storage_write_item, try_storage_write_item},
+		map::StoredMap,
+		queue::StoredVecDeque,
+		testing_common::*,
+		vec::StoredVec,
+	};
+
+	#[test]
+	fn oversized_writes_are_rejected() -> TestingResult {
+		let _storage = isolated()?;
+		set_max_value_size(Some(64));
+		// 4 byte length prefix + 100 u32's
+		assert_eq!(
+			try_storage_write_item(b"big", &vec![0u32; 100]).unwrap_err(),
+			StorageWriteError::ValueTooLarge(ValueTooLarge {
+				namespace: b"big".to_vec(),
+				size: 404,
+				max_size: 64,
+			})
+		);
+		assert_eq!(storage_read(b"big"), None);
+
+		// Only the namespace is reported for containers
+		let map = StoredMap::<u32, Vec<u8>>::new(NAMESPACE);
+		assert_eq!(
+			map.try_set(&1, &vec![1; 64]).unwrap_err(),
+			StorageWriteError::ValueTooLarge(ValueTooLarge {
+				namespace: NAMESPACE.to_vec(),
+				size: 68,
+				max_size: 64,
+			})
+		);
+		assert_eq!(map.get(&1)?, None);
+		map.set(&1, &vec![1; 60])?;
+
+		// Reads aren't limited
+		set_max_value_size(Some(16));
+		assert_eq!(map.get(&1)?.map(|value| value.len()), Some(60));
+		assert_eq!(set_max_value_size(None), Some(16));
+		Ok(())
+	}
+
+	#[test]
+	fn container_limit_overrides_global() -> TestingResult {
+		let _storage = isolated()?;
+		set_max_value_size(Some(16));
+		let mut vec = StoredVec::<Vec<u8>>::new(NAMESPACE).with_max_value_size(128);
+		vec.push(&vec![1; 100])?;
+
+		set_max_value_size(None);
+		let mut vec = StoredVec::<Vec<u8>>::new(NAMESPACE).with_max_value_size(8);
+		assert_eq!(
+			vec.try_push(&vec![1; 8]).unwrap_err(),
+			StorageWriteError::ValueTooLarge(ValueTooLarge {
+				namespace: NAMESPACE.to_vec(),
+				size: 12,
+				max_size: 8,
+			})
+		);
+		assert_eq!(vec.len(), 1);
+		assert!(matches!(
+			vec.try_set(0, &vec![1; 5]),
+			Err(StorageWriteError::ValueTooLarge(_))
+		));
+		// Other errors are passed on as-is
+		assert!(matches!(vec.try_set(1, &vec![1]), Err(StorageWriteError::Std(_))));
+
+		// The StdResult variants report it as a generic error
+		assert_eq!(
+			vec.push(&vec![1; 8]).unwrap_err(),
+			StdError::generic_err("Value of 12 bytes exceeds the limit of 8 bytes (namespace: \"testing\")")
+		);
+
+		let mut queue = StoredVecDeque::<Vec<u8>>::new(b"queue").with_max_value_size(8);
+		assert!(matches!(
+			queue.try_push_back(&vec![1; 8]),
+			Err(StorageWriteError::ValueTooLarge(_))
+		));
+		assert!(matches!(
+			queue.try_push_front(&vec![1; 8]),
+			Err(StorageWriteError::ValueTooLarge(_))
+		));
+		queue.try_push_back(&vec![1; 4])?;
+		assert!(matches!(
+			queue.try_set(0, &vec![1; 8]),
+			Err(StorageWriteError::ValueTooLarge(_))
+		));
+		assert_eq!(queue.len(), 1);
+		Ok(())
+	}
+
+	#[test]
+	fn parses_default_from_env() {
+		assert_eq!(parse_max_value_size("0"), 0);
+		assert_eq!(parse_max_value_size("65536"), 65536);
+	}
+
+	#[test]
+	fn pod_writes_under_limit() -> TestingResult {
+		let _storage = isolated()?;
+		set_max_value_size(Some(8));
+		let mut vec = StoredVec::<u64>::new(NAMESPACE);
+		vec.push(&1)?;
+		vec.push(&u64::MAX)?;
+		vec.set(0, &2)?;
+		assert_eq!(
+			vec.iter()
+				.map(|value| value.map(|value| *value))
+				.collect::<Result<Vec<_>, _>>()?,
+			[2, u64::MAX]
+		);
+		storage_write_item(b"item", &3u64)?;
+		assert!(matches!(
+			try_storage_write_item(b"item", &[3u64; 2]),
+			Err(StorageWriteError::ValueTooLarge(ValueTooLarge { size: 16, .. }))
+		));
+		Ok(())
+	}
+}
@@ -1,14 +1,37 @@
-use std::{marker::PhantomData, num::NonZeroUsize};
+use std::{
+	marker::PhantomData,
+	num::NonZeroUsize,
+	ops::{Bound, RangeBounds},
+};
+
+use cosmwasm_std::{OverflowError, StdError, StdResult};
 
-use cosmwasm_std::{OverflowError, StdError};
+use crate::{
+	debug_log,
+	utils::{hex_display, lexicographic_next},
+};
 
 use super::{
-	base::{storage_read, storage_read_item, storage_write},
+	accounting::{Accounted, NamespaceUsage},
+	base::{storage_read, storage_read_namespaced_item, storage_write},
 	concat_byte_array_pairs,
 	map::StoredMap,
-	OZeroCopy, SerializableItem,
+	value_size::StorageWriteError,
+	write_batch::WriteBatch,
+	OZeroCopy, SerializableItem, StorageKeyIterator,
 };
 
+/// Describes what `StoredVec::repair` has fixed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepairReport {
+	/// The length stored in the header before the repair
+	pub previous_len: u32,
+	/// The length of the contiguous entries starting from index 0, which is now stored in the header
+	pub len: u32,
+	/// The amount of entries removed which were at or past the contiguous length
+	pub orphans_removed: u32,
+}
+
 pub struct StoredVec<V: SerializableItem> {
 	namespace: &'static [u8],
 	map: StoredMap<u32, V>,
@@ -16,16 +39,54 @@ pub struct StoredVec<V: SerializableItem> {
 }
 
 impl<'exec, V: SerializableItem> StoredVec<V> {
+	/// Loads the vec, treating a length header which isn't 4 bytes long as an empty vec.
+	///
+	/// The length is stored under `namespace` and the elements under `namespace ++ index (u32 LE)`, so no other
+	/// storage may use a namespace which starts with this one, e.g. `b"pool_fee"` next to a vec at `b"pool"`. Such a
+	/// key is indistinguishable from an element, so [repair](Self::repair) would remove it.
+	///
+	/// Such a header means the namespace holds something else, e.g. the 8 byte header of a [StoredVecDeque], so
+	/// [try_new](Self::try_new) should be preferred if that could be the case.
+	///
+	/// [StoredVecDeque]: super::queue::StoredVecDeque
 	pub fn new(namespace: &'static [u8]) -> Self {
-		let len = storage_read(namespace)
-			.map(|data| u32::from_le_bytes(data.try_into().unwrap_or_default()))
-			.unwrap_or_default();
+		Self::try_new(namespace).unwrap_or_else(|err| {
+			debug_log!("treating StoredVec as empty: {}", err; namespace = hex_display(namespace));
+			Self {
+				namespace,
+				map: StoredMap::new(namespace),
+				len: 0,
+			}
+		})
+	}
 
-		Self {
+	/// Loads the vec, returning [StdError::InvalidDataSize] if the length header exists but isn't 4 bytes long.
+	pub fn try_new(namespace: &'static [u8]) -> StdResult<Self> {
+		let len = match storage_read(namespace) {
+			Some(data) => u32::from_le_bytes(
+				data.as_slice()
+					.try_into()
+					.map_err(|_| StdError::invalid_data_size(4, data.len()))?,
+			),
+			None => 0,
+		};
+		Ok(Self {
 			namespace,
 			map: StoredMap::new(namespace),
 			len,
-		}
+		})
+	}
+
+	/// Elements larger than `max_size` bytes are rejected by `set` and `push`, see [StoredMap::with_max_value_size].
+	pub fn with_max_value_size(mut self, max_size: usize) -> Self {
+		self.map = self.map.with_max_value_size(max_size);
+		self
+	}
+
+	/// Keeps track of the elements' usage, see [StoredMap::with_accounting]. The length header isn't counted.
+	pub fn with_accounting(mut self) -> Self {
+		self.map = self.map.with_accounting();
+		self
 	}
 
 	#[inline]
@@ -37,6 +98,11 @@ impl<'exec, V: SerializableItem> StoredVec<V> {
 	pub fn len(&self) -> u32 {
 		return self.len;
 	}
+
+	#[inline]
+	pub fn namespace(&self) -> &'static [u8] {
+		self.namespace
+	}
 	pub fn get(&self, index: u32) -> Result<Option<OZeroCopy<V>>, StdError> {
 		if index < self.len {
 			return self.map.get(&index);
@@ -45,11 +111,16 @@ impl<'exec, V: SerializableItem> StoredVec<V> {
 	}
 
 	pub fn set(&self, index: u32, value: &V) -> Result<(), StdError> {
+		Ok(self.try_set(index, value)?)
+	}
+
+	/// Same as `set`, but returns a [StorageWriteError] which can be matched on to tell a value which is too large
+	/// apart from other errors.
+	pub fn try_set(&self, index: u32, value: &V) -> Result<(), StorageWriteError> {
 		if index >= self.len() {
-			return Err(StdError::not_found("StoredVec out of bounds"));
+			return Err(StdError::not_found("StoredVec out of bounds").into());
 		}
-		self.map.set(&index, value)?;
-		Ok(())
+		self.map.try_set(&index, value)
 	}
 
 	#[inline]
@@ -57,16 +128,66 @@ impl<'exec, V: SerializableItem> StoredVec<V> {
 		u32::MAX
 	}
 
+	/// Removes all elements. If `dirty` is true, only the length is set to 0, leaving the elements in storage.
+	///
+	/// Only the indices below the length are removed, as anything past it could belong to another namespace sharing
+	/// this one's prefix, see [new](Self::new).
 	pub fn clear(&mut self, dirty: bool) {
 		if !dirty {
-			let len = self.len();
-			for i in 0..len {
+			// Only the entries which actually exist are removed, so a corrupted length doesn't cost a write per index.
+			for i in self.stored_indices() {
+				if i >= self.len {
+					break;
+				}
 				self.map.remove(&i);
 			}
 		}
 		self.set_len(0);
 	}
 
+	/// Returns the indices of all entries which exist in storage in ascending order, regardless of the stored length.
+	fn stored_indices(&self) -> Vec<u32> {
+		let index_key_len = self.namespace.len() + std::mem::size_of::<u32>();
+		let mut indices: Vec<u32> =
+			StorageKeyIterator::new(Some(self.namespace), Some(&lexicographic_next(self.namespace)))
+				.filter_map(|key| {
+					if key.len() != index_key_len {
+						// The length header, or something that doesn't belong to us.
+						return None;
+					}
+					Some(u32::from_le_bytes(
+						key[self.namespace.len()..].try_into().expect("length was just checked"),
+					))
+				})
+				.collect();
+		// Keys are ordered by their little-endian bytes
+		indices.sort_unstable();
+		indices
+	}
+
+	/// Brings the length header back in sync with the entries in storage.
+	///
+	/// The new length is the amount of contiguous entries starting from index 0, and all entries after the first
+	/// missing index are removed. This assumes no other namespace starts with this one, see [new](Self::new).
+	pub fn repair(&mut self) -> Result<RepairReport, StdError> {
+		let previous_len = self.len();
+		let indices = self.stored_indices();
+		let len = indices
+			.iter()
+			.zip(0..)
+			.take_while(|(index, expected_index)| **index == *expected_index)
+			.count() as u32;
+		for index in indices[len as usize..].iter() {
+			self.map.try_remove(index)?;
+		}
+		self.set_len(len);
+		Ok(RepairReport {
+			previous_len,
+			len,
+			orphans_removed: indices.len() as u32 - len,
+		})
+	}
+
 	pub fn extend<I: Iterator<Item = V>>(&mut self, iter: I) -> Result<(), StdError> {
 		let mut len = self.len();
 		for item in iter {
@@ -97,7 +218,7 @@ impl<'exec, V: SerializableItem> StoredVec<V> {
 			return Err(StdError::not_found("StoredVec out of bounds"));
 		}
 		for i in (index..len).rev() {
-			self.map.set_raw_bytes(&(i + 1), &self.map.get_raw_bytes(&i).unwrap());
+			self.map.set_raw_bytes(&(i + 1), &self.map.get_raw_bytes(&i).unwrap())?;
 		}
 		self.map.set(&index, element)
 	}
@@ -116,20 +237,50 @@ impl<'exec, V: SerializableItem> StoredVec<V> {
 		}
 		len -= 1;
 		let result = self.map.get(&len)?;
-		self.map.remove(&len);
+		self.map.try_remove(&len)?;
 		self.set_len(len);
 		Ok(result)
 	}
 
 	pub fn push(&mut self, element: &V) -> Result<(), StdError> {
+		Ok(self.try_push(element)?)
+	}
+
+	/// Same as `push`, but returns a [StorageWriteError] which can be matched on to tell a value which is too large
+	/// apart from other errors.
+	pub fn try_push(&mut self, element: &V) -> Result<(), StorageWriteError> {
 		let mut len = self.len();
-		self.map.set(&len, element)?;
-		len = len
+		self.map.try_set(&len, element)?;
+		len = len.checked_add(1).ok_or(StdError::from(OverflowError::new(
+			cosmwasm_std::OverflowOperation::Add,
+			len,
+			1,
+		)))?;
+		self.set_len(len);
+		Ok(())
+	}
+
+	/// Same as `push`, but the writes are added to `batch` instead of being applied immediately. See [WriteBatch] for
+	/// how the length is handled.
+	pub fn push_batched(&mut self, batch: &mut WriteBatch, element: &V) -> Result<(), StdError> {
+		let len = self.len();
+		let new_len = len
 			.checked_add(1)
 			.ok_or(OverflowError::new(cosmwasm_std::OverflowOperation::Add, len, 1))?;
-		self.set_len(len);
+		self.map.set_batched(batch, &len, element)?;
+		self.len = new_len;
+		batch.set_header(self.namespace, new_len.to_le_bytes().to_vec());
 		Ok(())
 	}
+
+	/// Same as `set`, but the write is added to `batch` instead of being applied immediately.
+	pub fn set_batched(&self, batch: &mut WriteBatch, index: u32, value: &V) -> Result<(), StdError> {
+		if index >= self.len() {
+			return Err(StdError::not_found("StoredVec out of bounds"));
+		}
+		self.map.set_batched(batch, &index, value)
+	}
+
 	pub fn remove(&mut self, index: u32) -> Result<OZeroCopy<V>, StdError> {
 		let new_len = self
 			.len()
@@ -140,9 +291,9 @@ impl<'exec, V: SerializableItem> StoredVec<V> {
 			.get(&index)?
 			.ok_or(StdError::not_found("StoredVec out of bounds"))?;
 		for i in index..new_len {
-			self.map.set_raw_bytes(&i, &self.map.get_raw_bytes(&(i + 1)).unwrap());
+			self.map.set_raw_bytes(&i, &self.map.get_raw_bytes(&(i + 1)).unwrap())?;
 		}
-		self.map.remove(&new_len);
+		self.map.try_remove(&new_len)?;
 		self.set_len(new_len);
 		Ok(result)
 	}
@@ -158,8 +309,8 @@ impl<'exec, V: SerializableItem> StoredVec<V> {
 				.map
 				.get_raw_bytes(&index2)
 				.ok_or(StdError::not_found("StoredVec out of bounds"))?,
-		);
-		self.map.set_raw_bytes(&index2, &tmp_value);
+		)?;
+		self.map.set_raw_bytes(&index2, &tmp_value)?;
 		Ok(())
 	}
 	pub fn swap_remove(&mut self, index: u32) -> Result<OZeroCopy<V>, StdError> {
@@ -172,24 +323,104 @@ impl<'exec, V: SerializableItem> StoredVec<V> {
 			.get(&index)?
 			.ok_or(StdError::not_found("StoredVec out of bounds"))?;
 		self.map
-			.set_raw_bytes(&index, &self.map.get_raw_bytes(&new_len).unwrap());
-		self.map.remove(&new_len);
+			.set_raw_bytes(&index, &self.map.get_raw_bytes(&new_len).unwrap())?;
+		self.map.try_remove(&new_len)?;
 		self.set_len(new_len);
 		Ok(result)
 	}
 
+	/// Returns an iterator over the elements within `range`, which are removed from the vec once the iterator is
+	/// dropped, even if they weren't all iterated over. The range is clamped to the length of the vec.
+	///
+	/// The elements after the range are moved into its place and the length is written once when the iterator is
+	/// dropped, rather than after every element as with repeated calls to `remove`. Use
+	/// [finish](StoredVecDrain::finish) instead of dropping it to find out about elements missing from storage.
+	pub fn drain(&mut self, range: impl RangeBounds<u32>) -> StoredVecDrain<'_, V> {
+		let len = self.len();
+		let start = match range.start_bound() {
+			Bound::Included(start) => *start,
+			Bound::Excluded(start) => start.saturating_add(1),
+			Bound::Unbounded => 0,
+		}
+		.min(len);
+		let end = match range.end_bound() {
+			Bound::Included(end) => end.saturating_add(1),
+			Bound::Excluded(end) => *end,
+			Bound::Unbounded => len,
+		}
+		.clamp(start, len);
+		StoredVecDrain {
+			vec: self,
+			start,
+			end,
+			front: start,
+			back: end,
+		}
+	}
+
+	/// Same as `drain`, but over the last `n` elements. Use `.rev()` on the returned iterator to get them in the
+	/// order `pop` would.
+	pub fn drain_back(&mut self, n: u32) -> StoredVecDrain<'_, V> {
+		let len = self.len();
+		self.drain(len.saturating_sub(n)..)
+	}
+
 	pub fn truncate(&mut self, len: u32, dirty: bool) {
 		let cur_len = self.len();
 		if cur_len <= len {
 			return;
 		}
 		if !dirty {
-			for i in len..cur_len {
-				self.map.remove(&i);
+			// Only the entries which actually exist are removed, so a corrupted length doesn't cost a write per index.
+			for i in self.stored_indices() {
+				if i >= cur_len {
+					break;
+				}
+				if i >= len {
+					self.map.remove(&i);
+				}
 			}
 		}
 		self.set_len(len);
 	}
+
+	/// Returns the index of the first element equal to `value`.
+	///
+	/// This is a linear scan over the entire vec, only use this on vecs which have a bounded size. Consider using
+	/// `position_by_value_bounded` to make that bound explicit.
+	pub fn position_by_value(&self, value: &V) -> Result<Option<u32>, StdError>
+	where
+		V: PartialEq,
+	{
+		self.position_by_value_bounded(u32::MAX, value)
+	}
+
+	/// Same as `position_by_value`, but returns an error if more than `max_scanned` elements would have to be looked
+	/// at in order to determine the result.
+	pub fn position_by_value_bounded(&self, max_scanned: u32, value: &V) -> Result<Option<u32>, StdError>
+	where
+		V: PartialEq,
+	{
+		for (index, item) in (0..self.len()).zip(self.iter()) {
+			if index == max_scanned {
+				return Err(StdError::generic_err(format!(
+					"StoredVec scan exceeded the limit of {} elements",
+					max_scanned
+				)));
+			}
+			if *item? == *value {
+				return Ok(Some(index));
+			}
+		}
+		Ok(None)
+	}
+}
+
+impl<V: SerializableItem> Accounted for StoredVec<V> {
+	#[inline]
+	fn accounting(&self) -> StdResult<NamespaceUsage> {
+		self.map.accounting()
+	}
 }
 
 impl<V: SerializableItem> IntoIterator for StoredVec<V> {
@@ -209,6 +440,98 @@ impl<V: SerializableItem> IntoIterator for &StoredVec<V> {
 	}
 }
 
+/// Iterator returned by [StoredVec::drain].
+pub struct StoredVecDrain<'a, V: SerializableItem> {
+	vec: &'a mut StoredVec<V>,
+	/// The range being removed
+	start: u32,
+	end: u32,
+	/// The range which hasn't been iterated over yet
+	front: u32,
+	back: u32,
+}
+impl<V: SerializableItem> StoredVecDrain<'_, V> {
+	fn read(&self, index: u32) -> Result<OZeroCopy<V>, StdError> {
+		self.vec
+			.map
+			.get(&index)?
+			.ok_or(StdError::not_found("StoredVec element missing from storage"))
+	}
+
+	/// Removes the drained range from the vec, which also happens when the iterator is dropped.
+	///
+	/// Returns [StdError::NotFound] if any of the elements after the range were missing from storage, which only
+	/// happens if the storage was corrupted. The drain is still completed, the missing elements leave a gap at their new
+	/// index instead, which [repair](StoredVec::repair) can deal with.
+	pub fn finish(mut self) -> StdResult<()> {
+		self.remove_drained()
+	}
+
+	fn remove_drained(&mut self) -> StdResult<()> {
+		let removed = self.end - self.start;
+		// Makes sure this only happens once, as it's also called on drop
+		self.start = self.end;
+		if removed == 0 {
+			return Ok(());
+		}
+		let len = self.vec.len();
+		let map = &self.vec.map;
+		let mut missing = 0u32;
+		for i in self.end..len {
+			match map.get_raw_bytes(&i) {
+				Some(bytes) => map.set_raw_bytes(&(i - removed), &bytes)?,
+				None => {
+					map.try_remove(&(i - removed))?;
+					missing += 1;
+				}
+			}
+		}
+		let new_len = len - removed;
+		for i in new_len..len {
+			map.try_remove(&i)?;
+		}
+		self.vec.set_len(new_len);
+		if missing > 0 {
+			return Err(StdError::not_found(format!(
+				"{missing} StoredVec elements missing from storage while draining"
+			)));
+		}
+		Ok(())
+	}
+}
+impl<V: SerializableItem> Iterator for StoredVecDrain<'_, V> {
+	type Item = Result<OZeroCopy<V>, StdError>;
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.front == self.back {
+			return None;
+		}
+		let result = self.read(self.front);
+		self.front += 1;
+		Some(result)
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = (self.back - self.front) as usize;
+		(len, Some(len))
+	}
+}
+impl<V: SerializableItem> DoubleEndedIterator for StoredVecDrain<'_, V> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		if self.front == self.back {
+			return None;
+		}
+		self.back -= 1;
+		Some(self.read(self.back))
+	}
+}
+impl<V: SerializableItem> ExactSizeIterator for StoredVecDrain<'_, V> {}
+impl<V: SerializableItem> Drop for StoredVecDrain<'_, V> {
+	fn drop(&mut self) {
+		if let Err(err) = self.remove_drained() {
+			debug_log!("StoredVec drain completed with gaps: {}", err; namespace = hex_display(self.vec.namespace));
+		}
+	}
+}
+
 /// Iterator for StoredVec and StoredVecDeque
 pub struct IndexedStoredItemIter<V: SerializableItem> {
 	namespace: &'static [u8],
@@ -303,7 +626,11 @@ impl<'exec, V: SerializableItem> Iterator for IndexedStoredItemIter<V> {
 		if self.start == self.end {
 			return None;
 		}
-		let result = storage_read_item(&concat_byte_array_pairs(self.namespace, &self.start.to_le_bytes())).transpose();
+		let result = storage_read_namespaced_item(
+			&concat_byte_array_pairs(self.namespace, &self.start.to_le_bytes()),
+			self.namespace.len(),
+		)
+		.transpose();
 		self.start = self.start.wrapping_add(1);
 		result
 	}
@@ -329,7 +656,11 @@ impl<'exec, V: SerializableItem> DoubleEndedIterator for IndexedStoredItemIter<V
 			return None;
 		}
 		self.end = self.end.wrapping_sub(1);
-		storage_read_item(&concat_byte_array_pairs(self.namespace, &self.end.to_le_bytes())).transpose()
+		storage_read_namespaced_item(
+			&concat_byte_array_pairs(self.namespace, &self.end.to_le_bytes()),
+			self.namespace.len(),
+		)
+		.transpose()
 	}
 
 	fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
@@ -345,7 +676,9 @@ impl<'exec, V: SerializableItem> ExactSizeIterator for IndexedStoredItemIter<V>
 mod tests {
 	use cosmwasm_std::MemoryStorage;
 
-	use crate::storage::base::set_global_storage;
+	use std::sync::atomic::Ordering;
+
+	use crate::storage::base::{set_global_storage, storage_remove, ThreadStorageGuard};
 
 	use super::*;
 	use crate::storage::testing_common::*;
@@ -395,6 +728,24 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn try_new_rejects_invalid_headers() -> TestingResult {
+		let _storage_lock = init()?;
+		assert_eq!(StoredVec::<u16>::try_new(NAMESPACE)?.len(), 0);
+		// e.g. a StoredVecDeque's header
+		for header in [&[1u8, 0, 0, 0, 3, 0, 0, 0][..], &[1, 0, 0], &[1, 0, 0, 0, 0]] {
+			storage_write(NAMESPACE, header);
+			assert_eq!(
+				StoredVec::<u16>::try_new(NAMESPACE).err(),
+				Some(StdError::invalid_data_size(4, header.len()))
+			);
+			assert_eq!(StoredVec::<u16>::new(NAMESPACE).len(), 0);
+		}
+		storage_write(NAMESPACE, &3u32.to_le_bytes());
+		assert_eq!(StoredVec::<u16>::try_new(NAMESPACE)?.len(), 3);
+		Ok(())
+	}
+
 	#[test]
 	fn extend() -> TestingResult {
 		let _storage_lock = init()?;
@@ -567,4 +918,234 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn position_by_value() -> TestingResult {
+		let _storage_lock = init()?;
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		vec.extend(0..10)?;
+
+		assert_eq!(vec.position_by_value(&9)?, Some(9));
+		assert_eq!(vec.position_by_value(&10)?, None);
+		assert_eq!(vec.position_by_value_bounded(5, &4)?, Some(4));
+		assert!(vec.position_by_value_bounded(5, &5).is_err());
+		assert!(vec.position_by_value_bounded(5, &10).is_err());
+		assert_eq!(vec.position_by_value_bounded(10, &10)?, None);
+
+		Ok(())
+	}
+
+	fn write_raw_entry(index: u32, value: u16) {
+		storage_write(
+			&concat_byte_array_pairs(NAMESPACE, &index.to_le_bytes()),
+			&value.to_le_bytes(),
+		);
+	}
+
+	fn stored_entries() -> Vec<u32> {
+		StoredVec::<u16>::new(NAMESPACE).stored_indices()
+	}
+
+	#[test]
+	fn repair_header_too_large() -> TestingResult {
+		let _storage_lock = init()?;
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		vec.extend([1, 2, 3].into_iter())?;
+		// Within the previous length, so it's known to belong to the vec
+		write_raw_entry(10, 4);
+		storage_write(NAMESPACE, &500u32.to_le_bytes());
+
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		assert_eq!(vec.len(), 500);
+		assert_eq!(
+			vec.repair()?,
+			RepairReport {
+				previous_len: 500,
+				len: 3,
+				orphans_removed: 1
+			}
+		);
+		assert_eq!(stored_entries(), vec![0, 1, 2]);
+		assert_eq!(StoredVec::<u16>::new(NAMESPACE).len(), 3);
+		let v: Vec<_> = vec
+			.iter()
+			.map(|item| item.map(OZeroCopy::into_inner))
+			.collect::<Result<_, _>>()?;
+		assert_eq!(v, vec![1, 2, 3]);
+
+		Ok(())
+	}
+
+	#[test]
+	fn repair_orphans() -> TestingResult {
+		let _storage_lock = init()?;
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		vec.extend([1, 2, 3].into_iter())?;
+		// Orphaned past the header, 256 is included as it sorts before 3 in storage.
+		write_raw_entry(3, 4);
+		write_raw_entry(256, 5);
+		// Index 5 is missing, so 6 isn't contiguous
+		write_raw_entry(4, 6);
+		write_raw_entry(6, 7);
+
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		assert_eq!(
+			vec.repair()?,
+			RepairReport {
+				previous_len: 3,
+				len: 5,
+				orphans_removed: 2
+			}
+		);
+		assert_eq!(stored_entries(), vec![0, 1, 2, 3, 4]);
+		let v: Vec<_> = vec
+			.iter()
+			.map(|item| item.map(OZeroCopy::into_inner))
+			.collect::<Result<_, _>>()?;
+		assert_eq!(v, vec![1, 2, 3, 4, 6]);
+
+		// Nothing left to repair
+		assert_eq!(
+			vec.repair()?,
+			RepairReport {
+				previous_len: 5,
+				len: 5,
+				orphans_removed: 0
+			}
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn clear_removes_orphans() -> TestingResult {
+		let _storage_lock = init()?;
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		vec.extend([1, 2, 3].into_iter())?;
+		write_raw_entry(10, 4);
+		storage_write(NAMESPACE, &500u32.to_le_bytes());
+
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		vec.clear(false);
+		assert!(vec.is_empty());
+		assert!(stored_entries().is_empty());
+
+		Ok(())
+	}
+
+	#[test]
+	fn clear_keeps_namespaces_sharing_the_prefix() -> TestingResult {
+		let _storage_lock = init()?;
+		// Exactly as long as an index key of the vec
+		let fee_key = b"testing_fee";
+		storage_write(fee_key, &[5]);
+
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		vec.extend([1, 2, 3].into_iter())?;
+		vec.clear(false);
+		assert_eq!(stored_entries(), [u32::from_le_bytes(*b"_fee")]);
+		assert_eq!(storage_read(fee_key), Some(vec![5]));
+		Ok(())
+	}
+
+	#[test]
+	fn truncate_only_removes_stored_entries() -> TestingResult {
+		let storage = CountingStorage::default();
+		let writes = storage.writes.clone();
+		let _storage = ThreadStorageGuard::new(Box::new(storage));
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		vec.extend([1, 2, 3, 4].into_iter())?;
+		write_raw_entry(600, 5);
+		storage_write(NAMESPACE, &500u32.to_le_bytes());
+
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		let writes_before = writes.load(Ordering::SeqCst);
+		vec.truncate(2, false);
+		// Indices 2 and 3, and the header. 600 is past the length, so it's left alone.
+		assert_eq!(writes.load(Ordering::SeqCst) - writes_before, 2 + 1);
+		assert_eq!(stored_entries(), [0, 1, 600]);
+		assert_eq!(contents(&vec)?, [1, 2]);
+		Ok(())
+	}
+
+	fn contents(vec: &StoredVec<u16>) -> TestingResult<Vec<u16>> {
+		Ok(vec
+			.iter()
+			.map(|item| item.map(OZeroCopy::into_inner))
+			.collect::<Result<_, _>>()?)
+	}
+
+	#[test]
+	fn drain() -> TestingResult {
+		let _storage_lock = init()?;
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		assert_eq!(vec.drain(..).count(), 0);
+		assert_eq!(vec.drain_back(3).count(), 0);
+		assert!(stored_entries().is_empty());
+
+		vec.extend(0..10)?;
+		let drained: Vec<u16> = vec
+			.drain(2..5)
+			.map(|item| item.map(OZeroCopy::into_inner))
+			.collect::<Result<_, _>>()?;
+		assert_eq!(drained, [2, 3, 4]);
+		assert_eq!(contents(&vec)?, [0, 1, 5, 6, 7, 8, 9]);
+		assert_eq!(stored_entries(), (0..7).collect::<Vec<_>>());
+
+		// Elements which weren't iterated over are removed as well
+		let mut drain = vec.drain(1..=4);
+		assert_eq!(drain.next_back().transpose()?.map(OZeroCopy::into_inner), Some(7));
+		assert_eq!(drain.next().transpose()?.map(OZeroCopy::into_inner), Some(1));
+		drop(drain);
+		assert_eq!(contents(&StoredVec::new(NAMESPACE))?, [0, 8, 9]);
+		assert_eq!(stored_entries(), [0, 1, 2]);
+
+		// Ranges past the end are clamped
+		assert_eq!(vec.drain(2..100).len(), 1);
+		assert_eq!(contents(&vec)?, [0, 8]);
+		assert_eq!(vec.drain(5..).count(), 0);
+		assert_eq!(vec.len(), 2);
+		Ok(())
+	}
+
+	#[test]
+	fn drain_with_missing_elements() -> TestingResult {
+		let _storage_lock = init()?;
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		vec.extend(0..6)?;
+		storage_remove(&concat_byte_array_pairs(NAMESPACE, &4u32.to_le_bytes()));
+
+		let err = vec.drain(0..1).finish().unwrap_err();
+		assert!(matches!(err, StdError::NotFound { .. }), "{err}");
+		assert_eq!(vec.len(), 5);
+		// The gap moved down along with the other elements
+		assert_eq!(stored_entries(), [0, 1, 2, 4]);
+
+		// Dropping doesn't panic either
+		drop(vec.drain(0..1));
+		assert_eq!(vec.len(), 4);
+		assert_eq!(stored_entries(), [0, 1, 3]);
+		assert_eq!(vec.repair()?.len, 2);
+		assert_eq!(contents(&vec)?, [2, 3]);
+		Ok(())
+	}
+
+	#[test]
+	fn drain_back() -> TestingResult {
+		let _storage_lock = init()?;
+		let mut vec = StoredVec::<u16>::new(NAMESPACE);
+		vec.extend(0..5)?;
+		let popped: Vec<u16> = vec
+			.drain_back(2)
+			.rev()
+			.map(|item| item.map(OZeroCopy::into_inner))
+			.collect::<Result<_, _>>()?;
+		assert_eq!(popped, [4, 3]);
+		assert_eq!(contents(&vec)?, [0, 1, 2]);
+		assert_eq!(vec.drain_back(10).count(), 3);
+		assert!(vec.is_empty());
+		assert_eq!(StoredVec::<u16>::new(NAMESPACE).len(), 0);
+		assert!(stored_entries().is_empty());
+		Ok(())
+	}
 }
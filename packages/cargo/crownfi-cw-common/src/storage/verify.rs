@@ -0,0 +1,216 @@
+//! Checks that every entry of a contract's state can be read with the current type definitions, e.g. as a CI step
+//! before migrating a deployed contract.
+//!
+//! Load the exported state of the contract into the native storage first (e.g. with a
+//! [ThreadStorageGuard](super::base::ThreadStorageGuard) over a [MemoryStorage](cosmwasm_std::MemoryStorage) filled
+//! with the entries), then call [verify_snapshot] with a decoder for every container of the new binary.
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{StdError, StdResult};
+
+use crate::utils::hex_display;
+
+use super::{SerializableItem, StoragePairIterator};
+
+/// The most failed entries reported per namespace, and the most entries with an unknown prefix reported.
+pub const MAX_REPORTED_FAILURES: usize = 10;
+
+/// Attempts to decode an entry, given its storage key with the registered prefix removed, and its value.
+pub type EntryDecoder = Box<dyn Fn(&[u8], &[u8]) -> StdResult<()>>;
+
+struct RegisteredDecoder {
+	name: &'static str,
+	prefix: Vec<u8>,
+	decode: EntryDecoder,
+}
+
+/// The decoders [verify_snapshot] uses, each registered for a storage key prefix.
+#[derive(Default)]
+pub struct StateDecoders {
+	decoders: Vec<RegisteredDecoder>,
+}
+
+impl StateDecoders {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Entries are attributed to the decoder with the longest prefix they start with.
+	pub fn with_decoder(mut self, name: &'static str, prefix: &[u8], decode: EntryDecoder) -> Self {
+		self.decoders.push(RegisteredDecoder {
+			name,
+			prefix: prefix.to_vec(),
+			decode,
+		});
+		self
+	}
+
+	/// A single value stored at `key`, such as a [StoredItem](super::item::StoredItem).
+	pub fn with_item<T: SerializableItem>(self, name: &'static str, key: &[u8]) -> Self {
+		self.with_decoder(
+			name,
+			key,
+			Box::new(|key_suffix, value| {
+				if !key_suffix.is_empty() {
+					return Err(StdError::generic_err("unexpected entry under an item's key"));
+				}
+				T::deserialize_to_owned(value).map(|_| ())
+			}),
+		)
+	}
+
+	/// The entries of a [StoredMap](super::map::StoredMap).
+	pub fn with_map<K: SerializableItem, V: SerializableItem>(self, name: &'static str, namespace: &[u8]) -> Self {
+		self.with_decoder(
+			name,
+			namespace,
+			Box::new(|key, value| {
+				K::deserialize_to_owned(key)?;
+				V::deserialize_to_owned(value).map(|_| ())
+			}),
+		)
+	}
+
+	/// The header and elements of a [StoredVec](super::vec::StoredVec) or
+	/// [StoredVecDeque](super::queue::StoredVecDeque).
+	pub fn with_vec<V: SerializableItem>(self, name: &'static str, namespace: &[u8]) -> Self {
+		self.with_decoder(
+			name,
+			namespace,
+			Box::new(|key, value| match key.len() {
+				// A StoredVec length or QueueEnds
+				0 if value.len() == 4 || value.len() == 8 => Ok(()),
+				0 => Err(StdError::invalid_data_size(8, value.len())),
+				4 => V::deserialize_to_owned(value).map(|_| ()),
+				len => Err(StdError::invalid_data_size(4, len)),
+			}),
+		)
+	}
+
+	fn find(&self, key: &[u8]) -> Option<&RegisteredDecoder> {
+		self.decoders
+			.iter()
+			.filter(|decoder| key.starts_with(&decoder.prefix))
+			.max_by_key(|decoder| decoder.prefix.len())
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeFailure {
+	/// The full storage key, as 0x-prefixed hex
+	pub key_hex: String,
+	pub error: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NamespaceReport {
+	pub ok: u64,
+	pub failed: u64,
+	/// The first [MAX_REPORTED_FAILURES] failures
+	pub failures: Vec<DecodeFailure>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+	/// Reports of every registered decoder, by name
+	pub namespaces: BTreeMap<&'static str, NamespaceReport>,
+	/// The amount of entries which didn't match any registered prefix
+	pub unknown_prefix: u64,
+	/// The storage keys of the first [MAX_REPORTED_FAILURES] entries with an unknown prefix, as 0x-prefixed hex
+	pub unknown_prefix_examples: Vec<String>,
+}
+
+impl VerifyReport {
+	/// Returns true if every entry was attributed to a decoder and decoded successfully.
+	pub fn is_ok(&self) -> bool {
+		self.unknown_prefix == 0 && self.namespaces.values().all(|namespace| namespace.failed == 0)
+	}
+}
+
+/// Attempts to decode every entry in storage, see the [module docs](self).
+pub fn verify_snapshot(decoders: &StateDecoders) -> VerifyReport {
+	let mut report = VerifyReport::default();
+	for decoder in decoders.decoders.iter() {
+		report.namespaces.entry(decoder.name).or_default();
+	}
+	for (key, value) in StoragePairIterator::new(None, None) {
+		let Some(decoder) = decoders.find(&key) else {
+			report.unknown_prefix += 1;
+			if report.unknown_prefix_examples.len() < MAX_REPORTED_FAILURES {
+				report.unknown_prefix_examples.push(hex_display(&key).to_string());
+			}
+			continue;
+		};
+		let namespace = report.namespaces.entry(decoder.name).or_default();
+		match (decoder.decode)(&key[decoder.prefix.len()..], &value) {
+			Ok(()) => namespace.ok += 1,
+			Err(err) => {
+				namespace.failed += 1;
+				if namespace.failures.len() < MAX_REPORTED_FAILURES {
+					namespace.failures.push(DecodeFailure {
+						key_hex: hex_display(&key).to_string(),
+						error: err.to_string(),
+					});
+				}
+			}
+		}
+	}
+	report
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::{
+		base::{storage_write, storage_write_item},
+		map::StoredMap,
+		testing_common::*,
+		vec::StoredVec,
+	};
+
+	const ORDERS_NAMESPACE: &[u8] = b"orders";
+	const ORDER_HISTORY_NAMESPACE: &[u8] = b"orders_history";
+	const EVENTS_NAMESPACE: &[u8] = b"events";
+
+	fn decoders() -> StateDecoders {
+		StateDecoders::new()
+			.with_item::<u64>("config", NAMESPACE)
+			.with_map::<u64, String>("orders", ORDERS_NAMESPACE)
+			.with_map::<u64, String>("order_history", ORDER_HISTORY_NAMESPACE)
+			.with_vec::<u32>("events", EVENTS_NAMESPACE)
+	}
+
+	#[test]
+	fn categorizes_entries() -> TestingResult {
+		let _storage = isolated()?;
+		storage_write_item(NAMESPACE, &7u64)?;
+		let orders = StoredMap::<u64, String>::new(ORDERS_NAMESPACE);
+		orders.set(&1, &"buy".to_string())?;
+		orders.set(&2, &"sell".to_string())?;
+		StoredMap::<u64, String>::new(ORDER_HISTORY_NAMESPACE).set(&1, &"filled".to_string())?;
+		let mut events = StoredVec::<u32>::new(EVENTS_NAMESPACE);
+		events.push(&1)?;
+		events.push(&2)?;
+		assert!(verify_snapshot(&decoders()).is_ok());
+
+		// A string whose length prefix is longer than the value
+		let corrupt_key = orders.key(&3);
+		storage_write(&corrupt_key, &[0xff, 0, 0, 0, b'x']);
+		storage_write(b"legacy/1", b"old");
+		let report = verify_snapshot(&decoders());
+		assert!(!report.is_ok());
+
+		let orders_report = &report.namespaces["orders"];
+		assert_eq!((orders_report.ok, orders_report.failed), (2, 1));
+		assert_eq!(orders_report.failures[0].key_hex, hex_display(&corrupt_key).to_string());
+		assert!(!orders_report.failures[0].error.is_empty());
+		// orders_history starts with "orders", but the longest prefix wins
+		assert_eq!(report.namespaces["order_history"].ok, 1);
+		assert_eq!(report.namespaces["config"].ok, 1);
+		// The header and 2 elements
+		assert_eq!(report.namespaces["events"].ok, 3);
+		assert_eq!(report.unknown_prefix, 1);
+		assert_eq!(report.unknown_prefix_examples, [hex_display(b"legacy/1").to_string()]);
+		Ok(())
+	}
+}
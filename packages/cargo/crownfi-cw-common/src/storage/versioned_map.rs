@@ -0,0 +1,287 @@
+use cosmwasm_std::{OverflowError, StdError, StdResult};
+use thiserror::Error;
+
+use super::{map::StoredMap, OZeroCopy, SerializableItem};
+
+const VERSION_PREFIX_LEN: usize = 4;
+/// The version followed by a tag byte telling whether a value follows.
+const HEADER_LEN: usize = VERSION_PREFIX_LEN + 1;
+const TAG_REMOVED: u8 = 0;
+const TAG_VALUE: u8 = 1;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CasError {
+	#[error("VersionedMap version mismatch: expected {expected}, actual {actual}")]
+	VersionMismatch { expected: u32, actual: u32 },
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<CasError> for StdError {
+	fn from(value: CasError) -> Self {
+		match value {
+			CasError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// A map where every value is stored alongside a version number which is incremented on each write, allowing for
+/// "compare-and-swap" style updates.
+///
+/// Values are stored as `version (u32 LE) || 1u8 || value bytes`, the prefix being removed in-place when reading, so
+/// Pod values are still zero-copy readable.
+///
+/// A key which never existed has a version of 0, so the first write to any key results in version 1. Removing a key
+/// also increments its version, leaving `version || 0u8` behind as a tombstone. This keeps versions increasing across
+/// a remove and re-insert, so a stale version can never match again. The tombstone costs a 5 byte storage entry per
+/// removed key. The tag byte keeps values which serialize to nothing, such as `()`, apart from tombstones.
+pub struct VersionedMap<K: SerializableItem, V: SerializableItem> {
+	map: StoredMap<K, V>,
+}
+
+impl<K: SerializableItem, V: SerializableItem> VersionedMap<K, V> {
+	pub fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			map: StoredMap::new(namespace),
+		}
+	}
+
+	#[inline]
+	pub fn key(&self, key: &K) -> Vec<u8> {
+		self.map.key(key)
+	}
+
+	/// Returns the current version of the specified key, 0 if it never existed.
+	pub fn version(&self, key: &K) -> StdResult<u32> {
+		let Some(bytes) = self.map.get_raw_bytes(key) else {
+			return Ok(0);
+		};
+		Ok(split_header(&bytes)
+			.map_err(|err| self.map.with_storage_context(err, key))?
+			.0)
+	}
+
+	pub fn get_versioned(&self, key: &K) -> StdResult<Option<(u32, OZeroCopy<V>)>> {
+		let Some(mut bytes) = self.map.get_raw_bytes(key) else {
+			return Ok(None);
+		};
+		let (version, removed, _) = split_header(&bytes).map_err(|err| self.map.with_storage_context(err, key))?;
+		if removed {
+			return Ok(None);
+		}
+		// This shifts the bytes in-place rather than allocating a new Vec, so the value stays zero-copy-able.
+		bytes.drain(..HEADER_LEN);
+		let value = OZeroCopy::new(bytes).map_err(|err| self.map.with_storage_context(err, key))?;
+		Ok(Some((version, value)))
+	}
+
+	pub fn get(&self, key: &K) -> StdResult<Option<OZeroCopy<V>>> {
+		Ok(self.get_versioned(key)?.map(|(_, value)| value))
+	}
+
+	pub fn has(&self, key: &K) -> bool {
+		self.map
+			.get_raw_bytes(key)
+			.is_some_and(|bytes| matches!(split_header(&bytes), Ok((_, false, _))))
+	}
+
+	/// Writes the value only if the current version matches `expected_version`, returning the new version.
+	///
+	/// An `expected_version` of 0 means "only create this entry if it never existed". A removed entry can be recreated
+	/// by passing the version it was removed at, as returned by [version](Self::version).
+	pub fn set_if_version(&self, key: &K, expected_version: u32, value: &V) -> Result<u32, CasError> {
+		let actual = self.version(key)?;
+		if actual != expected_version {
+			return Err(CasError::VersionMismatch {
+				expected: expected_version,
+				actual,
+			});
+		}
+		Ok(self.write_version(key, actual, value)?)
+	}
+
+	/// Writes the value regardless of its current version, returning the new version.
+	pub fn set(&self, key: &K, value: &V) -> StdResult<u32> {
+		let current = self.version(key)?;
+		self.write_version(key, current, value)
+	}
+
+	/// Removes the value, returning the version of the tombstone left in its place, or `None` if there was no value.
+	pub fn remove(&self, key: &K) -> StdResult<Option<u32>> {
+		let Some(bytes) = self.map.get_raw_bytes(key) else {
+			return Ok(None);
+		};
+		let (current, removed, _) = split_header(&bytes).map_err(|err| self.map.with_storage_context(err, key))?;
+		if removed {
+			return Ok(None);
+		}
+		let new_version = next_version(current)?;
+		self.map.set_raw_bytes(key, &header(new_version, TAG_REMOVED))?;
+		Ok(Some(new_version))
+	}
+
+	fn write_version(&self, key: &K, current: u32, value: &V) -> StdResult<u32> {
+		let new_version = next_version(current)?;
+		let mut bytes;
+		if let Some(value_bytes) = value.serialize_as_ref() {
+			bytes = Vec::with_capacity(HEADER_LEN + value_bytes.len());
+			bytes.extend_from_slice(&header(new_version, TAG_VALUE));
+			bytes.extend_from_slice(value_bytes);
+		} else {
+			bytes = header(new_version, TAG_VALUE).to_vec();
+			bytes.extend_from_slice(&value.serialize_to_owned()?);
+		}
+		self.map.set_raw_bytes(key, &bytes)?;
+		Ok(new_version)
+	}
+}
+
+fn next_version(current: u32) -> StdResult<u32> {
+	Ok(current
+		.checked_add(1)
+		.ok_or(OverflowError::new(cosmwasm_std::OverflowOperation::Add, current, 1))?)
+}
+
+fn header(version: u32, tag: u8) -> [u8; HEADER_LEN] {
+	let mut header = [tag; HEADER_LEN];
+	header[..VERSION_PREFIX_LEN].copy_from_slice(&version.to_le_bytes());
+	header
+}
+
+/// Returns the version, whether the entry is a tombstone, and the value bytes.
+fn split_header(bytes: &[u8]) -> StdResult<(u32, bool, &[u8])> {
+	let Some((header, value)) = bytes.split_first_chunk::<HEADER_LEN>() else {
+		return Err(StdError::parse_err(
+			"VersionedMap",
+			"value is missing its version prefix",
+		));
+	};
+	let (version, tag) = header.split_at(VERSION_PREFIX_LEN);
+	let version = u32::from_le_bytes(version.try_into().unwrap());
+	match tag[0] {
+		TAG_VALUE => Ok((version, false, value)),
+		TAG_REMOVED if value.is_empty() => Ok((version, true, value)),
+		_ => Err(StdError::parse_err("VersionedMap", "value has an invalid tag")),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	#[test]
+	fn cas_chain() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = VersionedMap::<String, u64>::new(NAMESPACE);
+		let key = "order1".to_string();
+
+		assert_eq!(map.version(&key)?, 0);
+		assert_eq!(map.set_if_version(&key, 0, &100)?, 1);
+		assert_eq!(map.set_if_version(&key, 1, &200)?, 2);
+		assert_eq!(map.set_if_version(&key, 2, &300)?, 3);
+
+		let (version, value) = map.get_versioned(&key)?.unwrap();
+		assert_eq!(version, 3);
+		assert_eq!(*value, 300);
+
+		assert_eq!(map.set(&key, &400)?, 4);
+		assert_eq!(map.get(&key)?.map(OZeroCopy::into_inner), Some(400));
+
+		Ok(())
+	}
+
+	#[test]
+	fn mismatch_reports_actual_version() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = VersionedMap::<String, String>::new(NAMESPACE);
+		let key = "order1".to_string();
+
+		map.set(&key, &"a".to_string())?;
+		map.set(&key, &"b".to_string())?;
+		assert_eq!(
+			map.set_if_version(&key, 1, &"c".to_string()),
+			Err(CasError::VersionMismatch { expected: 1, actual: 2 })
+		);
+		assert_eq!(map.get(&key)?.map(OZeroCopy::into_inner), Some("b".to_string()));
+
+		Ok(())
+	}
+
+	#[test]
+	fn absent_key() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = VersionedMap::<String, u32>::new(NAMESPACE);
+		let key = "order1".to_string();
+
+		assert_eq!(map.get_versioned(&key)?, None);
+		assert_eq!(
+			map.set_if_version(&key, 1, &5),
+			Err(CasError::VersionMismatch { expected: 1, actual: 0 })
+		);
+		assert!(!map.has(&key));
+
+		assert_eq!(map.set_if_version(&key, 0, &5)?, 1);
+		// Create-only fails once the key exists
+		assert_eq!(
+			map.set_if_version(&key, 0, &6),
+			Err(CasError::VersionMismatch { expected: 0, actual: 1 })
+		);
+
+		assert_eq!(map.remove(&key)?, Some(2));
+		assert_eq!(map.version(&key)?, 2);
+		assert_eq!(map.get_versioned(&key)?, None);
+		assert!(!map.has(&key));
+		assert_eq!(map.remove(&key)?, None);
+
+		Ok(())
+	}
+
+	#[test]
+	fn empty_values_are_not_tombstones() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = VersionedMap::<String, ()>::new(NAMESPACE);
+		let key = "order1".to_string();
+
+		assert_eq!(map.set(&key, &())?, 1);
+		assert!(map.has(&key));
+		assert_eq!(map.get_versioned(&key)?.map(|(version, _)| version), Some(1));
+		assert_eq!(map.remove(&key)?, Some(2));
+		assert!(!map.has(&key));
+		assert_eq!(map.get(&key)?, None);
+		assert_eq!(map.set_if_version(&key, 2, &())?, 3);
+		assert!(map.get(&key)?.is_some());
+		Ok(())
+	}
+
+	#[test]
+	fn versions_survive_removal() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = VersionedMap::<String, u64>::new(NAMESPACE);
+		let key = "order1".to_string();
+
+		map.set(&key, &100)?;
+		let stale_version = map.set(&key, &200)?;
+		map.remove(&key)?;
+		assert_eq!(map.set(&key, &300)?, 4);
+
+		// A client which read the value before it was removed and re-inserted can't overwrite it
+		assert_eq!(
+			map.set_if_version(&key, stale_version, &999),
+			Err(CasError::VersionMismatch {
+				expected: stale_version,
+				actual: 4
+			})
+		);
+		assert_eq!(
+			map.set_if_version(&key, 0, &999),
+			Err(CasError::VersionMismatch { expected: 0, actual: 4 })
+		);
+		assert_eq!(map.get(&key)?.map(OZeroCopy::into_inner), Some(300));
+
+		// Recreating through CAS takes the tombstone's version
+		let removed_version = map.remove(&key)?.unwrap();
+		assert_eq!(map.set_if_version(&key, removed_version, &400)?, 6);
+		Ok(())
+	}
+}
@@ -0,0 +1,209 @@
+use cosmwasm_std::{OverflowError, OverflowOperation, StdError, StdResult, Uint128, Uint256};
+use sha2::{Digest, Sha256};
+
+use super::{
+	base::{storage_read_item, storage_remove, storage_write_item},
+	concat_byte_array_pairs,
+	map::StoredMap,
+	OZeroCopy, SerializableItem,
+};
+
+/// The total weight of every WeightedIndex lives under this prefix.
+pub const WEIGHTED_INDEX_TOTAL_NAMESPACE: &[u8] = b"\xffweights/";
+
+/// Derives a uniformly distributed value below `total` from the entropy bytes.
+///
+/// The entropy is hashed and the 256-bit result is reduced modulo `total`, the resulting bias is at most
+/// `total / 2^256`, which is negligible.
+pub fn uniform_below(entropy: &[u8], total: u128) -> StdResult<u128> {
+	if entropy.is_empty() {
+		return Err(StdError::generic_err("uniform_below: no entropy was provided"));
+	}
+	if total == 0 {
+		return Err(StdError::generic_err("uniform_below: total must be greater than 0"));
+	}
+	let value = Uint256::from_be_bytes(Sha256::digest(entropy).into()) % Uint256::from(total);
+	Ok(Uint128::try_from(value).unwrap().u128())
+}
+
+/// A collection of keys with weights, which keys can be randomly selected from proportionally to their weight.
+///
+/// **This only does the math.** The entropy passed to [sample](Self::sample) must be unpredictable to everyone who
+/// could benefit from the outcome, e.g. the output of a VRF or the hash of a block which doesn't exist yet at the time
+/// of commitment. Block times, heights and transaction data are known to validators and (mostly) users in advance.
+///
+/// Implementors only need to be able to find the key at a point of the cumulative weight distribution. [WeightedIndex]
+/// does this by walking through all of the keys, a Fenwick tree based implementation could do so in logarithmic time.
+pub trait WeightedSampler<K: SerializableItem> {
+	fn total_weight(&self) -> StdResult<u128>;
+	fn weight(&self, key: &K) -> StdResult<u128>;
+	/// Sets the weight of the key, a weight of 0 removes it.
+	fn set_weight(&self, key: &K, weight: u128) -> StdResult<()>;
+	fn remove(&self, key: &K) -> StdResult<()> {
+		self.set_weight(key, 0)
+	}
+	/// Returns the key whose range of the cumulative weight distribution contains `point`, or `None` if `point` is
+	/// greater than or equal to the total weight. Keys with a larger weight have a proportionally larger range.
+	fn find_by_cumulative_weight(&self, point: u128) -> StdResult<Option<K>>;
+
+	/// Selects a key with a probability proportional to its weight, or returns `None` if there are no keys.
+	fn sample(&self, entropy: &[u8]) -> StdResult<Option<K>> {
+		let total = self.total_weight()?;
+		if total == 0 {
+			return Ok(None);
+		}
+		self.find_by_cumulative_weight(uniform_below(entropy, total)?)
+	}
+
+	/// Like [sample](Self::sample), but also removes the selected key, e.g. for drawing winners without replacement.
+	fn sample_and_remove(&self, entropy: &[u8]) -> StdResult<Option<K>> {
+		let Some(key) = self.sample(entropy)? else {
+			return Ok(None);
+		};
+		self.remove(&key)?;
+		Ok(Some(key))
+	}
+}
+
+/// A [WeightedSampler] which stores the weights in a [StoredMap] and keeps track of their total.
+///
+/// Sampling iterates through the keys in order until the selected one is found, so it's O(n) and only suitable for
+/// collections small enough to be iterated through in a single transaction.
+pub struct WeightedIndex<K: SerializableItem> {
+	weights: StoredMap<K, u128>,
+	total_key: Vec<u8>,
+}
+
+impl<K: SerializableItem> WeightedIndex<K> {
+	pub fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			weights: StoredMap::new(namespace),
+			total_key: concat_byte_array_pairs(WEIGHTED_INDEX_TOTAL_NAMESPACE, namespace),
+		}
+	}
+
+	#[inline]
+	pub fn weights(&self) -> &StoredMap<K, u128> {
+		&self.weights
+	}
+}
+
+impl<K: SerializableItem> WeightedSampler<K> for WeightedIndex<K> {
+	fn total_weight(&self) -> StdResult<u128> {
+		Ok(storage_read_item::<u128>(&self.total_key)?.map_or(0, OZeroCopy::into_inner))
+	}
+
+	fn weight(&self, key: &K) -> StdResult<u128> {
+		Ok(self.weights.get(key)?.map_or(0, OZeroCopy::into_inner))
+	}
+
+	fn set_weight(&self, key: &K, weight: u128) -> StdResult<()> {
+		let old_weight = self.weight(key)?;
+		let total = self.total_weight()? - old_weight;
+		let total = total
+			.checked_add(weight)
+			.ok_or_else(|| OverflowError::new(OverflowOperation::Add, total, weight))?;
+		if weight == 0 {
+			self.weights.remove(key);
+		} else {
+			self.weights.set(key, &weight)?;
+		}
+		if total == 0 {
+			storage_remove(&self.total_key);
+		} else {
+			storage_write_item(&self.total_key, &total)?;
+		}
+		Ok(())
+	}
+
+	fn find_by_cumulative_weight(&self, point: u128) -> StdResult<Option<K>> {
+		let mut cumulative_weight = 0u128;
+		for (key, weight) in self.weights.iter()? {
+			// Can't overflow as the total didn't
+			cumulative_weight += *weight;
+			if point < cumulative_weight {
+				return Ok(Some(key));
+			}
+		}
+		Ok(None)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+
+	#[test]
+	fn distribution() -> TestingResult {
+		let _storage_lock = init()?;
+		let index = WeightedIndex::<u8>::new(NAMESPACE);
+		let weights = [(1u8, 10u128), (2, 0), (3, 30), (4, 60), (5, 0)];
+		for (key, weight) in weights {
+			index.set_weight(&key, weight)?;
+		}
+		assert_eq!(index.total_weight()?, 100);
+
+		const SAMPLES: u32 = 10_000;
+		let mut counts = [0u32; 6];
+		for i in 0..SAMPLES {
+			let key = index.sample(&i.to_le_bytes())?.unwrap();
+			counts[key as usize] += 1;
+		}
+		assert_eq!((counts[2], counts[5]), (0, 0));
+		let chi_square: f64 = weights
+			.iter()
+			.filter(|(_, weight)| *weight > 0)
+			.map(|(key, weight)| {
+				let expected = SAMPLES as f64 * *weight as f64 / 100.0;
+				(counts[*key as usize] as f64 - expected).powi(2) / expected
+			})
+			.sum();
+		// p = 0.001 for 2 degrees of freedom
+		assert!(chi_square < 13.82, "chi square of {chi_square}, counts: {counts:?}");
+		Ok(())
+	}
+
+	#[test]
+	fn cumulative_ranges() -> TestingResult {
+		let _storage_lock = init()?;
+		let index = WeightedIndex::<u8>::new(NAMESPACE);
+		index.set_weight(&1, 2)?;
+		index.set_weight(&2, 0)?;
+		index.set_weight(&3, 1)?;
+		assert_eq!(index.find_by_cumulative_weight(0)?, Some(1));
+		assert_eq!(index.find_by_cumulative_weight(1)?, Some(1));
+		assert_eq!(index.find_by_cumulative_weight(2)?, Some(3));
+		assert_eq!(index.find_by_cumulative_weight(3)?, None);
+		assert!(!index.weights().has(&2));
+
+		index.set_weight(&1, 5)?;
+		assert_eq!(index.total_weight()?, 6);
+		assert!(index.set_weight(&2, u128::MAX).is_err());
+		assert_eq!(index.total_weight()?, 6);
+		Ok(())
+	}
+
+	#[test]
+	fn empty_and_removal() -> TestingResult {
+		let _storage_lock = init()?;
+		let index = WeightedIndex::<u8>::new(NAMESPACE);
+		assert_eq!(index.sample(b"entropy")?, None);
+		assert_eq!(index.sample_and_remove(b"entropy")?, None);
+
+		index.set_weight(&1, 1)?;
+		index.set_weight(&2, 1_000_000)?;
+		assert!(index.sample(b"").is_err());
+		let mut drawn = [
+			index.sample_and_remove(b"first")?.unwrap(),
+			index.sample_and_remove(b"second")?.unwrap(),
+		];
+		drawn.sort();
+		assert_eq!(drawn, [1, 2]);
+		assert_eq!(index.total_weight()?, 0);
+		assert_eq!(index.sample(b"third")?, None);
+		// Nothing is left behind
+		assert!(crate::storage::StorageKeyIterator::new(None, None).next().is_none());
+		Ok(())
+	}
+}
@@ -0,0 +1,213 @@
+use cosmwasm_std::StdResult;
+
+use super::{
+	base::{storage_remove, storage_write},
+	SerializableItem,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum WriteOp {
+	Set(Vec<u8>, Vec<u8>),
+	Remove(Vec<u8>),
+}
+
+/// A list of storage writes which are only applied once [commit](Self::commit) is called, so that a sequence of updates
+/// to multiple containers can't be left half-applied if one of them fails.
+///
+/// Containers add to the batch through their `*_batched` methods, e.g. [StoredMap::set_batched]. Values are serialized
+/// when they're added, so any serialization errors happen before anything is written. Reads aren't affected by the
+/// batch: until it's committed, containers return what's in storage rather than the pending writes.
+///
+/// Length headers of [StoredVec] and [StoredVecDeque] are only written once per container, after all other writes.
+/// Those containers keep track of their length in memory, so if a batch is dropped without being committed, any
+/// container which was pushed to through it no longer matches storage and should be discarded.
+///
+/// [StoredMap::set_batched]: super::map::StoredMap::set_batched
+/// [StoredVec]: super::vec::StoredVec
+/// [StoredVecDeque]: super::queue::StoredVecDeque
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WriteBatch {
+	ops: Vec<WriteOp>,
+	headers: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl WriteBatch {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn set_raw(&mut self, key: Vec<u8>, value: Vec<u8>) {
+		self.ops.push(WriteOp::Set(key, value));
+	}
+
+	pub fn set_item<T: SerializableItem>(&mut self, key: Vec<u8>, value: &T) -> StdResult<()> {
+		let value = match value.serialize_as_ref() {
+			Some(bytes) => bytes.to_vec(),
+			None => value.serialize_to_owned()?,
+		};
+		self.set_raw(key, value);
+		Ok(())
+	}
+
+	pub fn remove_raw(&mut self, key: Vec<u8>) {
+		self.ops.push(WriteOp::Remove(key));
+	}
+
+	/// Sets a container header, replacing any header previously set for the same key within this batch.
+	pub(crate) fn set_header(&mut self, key: &[u8], value: Vec<u8>) {
+		match self.headers.iter_mut().find(|(header_key, _)| header_key == key) {
+			Some((_, header_value)) => *header_value = value,
+			None => self.headers.push((key.to_vec(), value)),
+		}
+	}
+
+	/// Returns the length of the value `key` will have once the batch is committed, `Some(None)` meaning it'll be
+	/// removed, or `None` if the batch doesn't write to it.
+	pub(crate) fn pending_len(&self, key: &[u8]) -> Option<Option<usize>> {
+		self.ops.iter().rev().find_map(|op| match op {
+			WriteOp::Set(op_key, value) if op_key == key => Some(Some(value.len())),
+			WriteOp::Remove(op_key) if op_key == key => Some(None),
+			_ => None,
+		})
+	}
+
+	/// Returns the pending value of a container header set with [set_header](Self::set_header).
+	pub(crate) fn header(&self, key: &[u8]) -> Option<&[u8]> {
+		self.headers
+			.iter()
+			.find(|(header_key, _)| header_key == key)
+			.map(|(_, value)| value.as_slice())
+	}
+
+	/// Returns the amount of pending writes, including headers.
+	pub fn len(&self) -> usize {
+		self.ops.len() + self.headers.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Applies all writes in the order they were added, followed by the container headers.
+	pub fn commit(self) {
+		for op in self.ops {
+			match op {
+				WriteOp::Set(key, value) => storage_write(&key, &value),
+				WriteOp::Remove(key) => storage_remove(&key),
+			}
+		}
+		for (key, value) in self.headers {
+			storage_write(&key, &value);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::StdError;
+
+	use super::*;
+	use crate::storage::{
+		base::storage_read, map::StoredMap, queue::StoredVecDeque, testing_common::*, vec::StoredVec, OZeroCopy,
+		StorageKeyIterator,
+	};
+
+	/// Fails to serialize, like a value which is too large to store would.
+	struct Unserializable;
+	impl SerializableItem for Unserializable {
+		fn serialize_to_owned(&self) -> Result<Vec<u8>, StdError> {
+			Err(StdError::serialize_err("Unserializable", "nope"))
+		}
+		fn deserialize_to_owned(_: &[u8]) -> Result<Self, StdError> {
+			Err(StdError::parse_err("Unserializable", "nope"))
+		}
+	}
+
+	fn storage_is_empty() -> bool {
+		StorageKeyIterator::new(None, None).next().is_none()
+	}
+
+	#[test]
+	fn failed_op_writes_nothing() -> TestingResult {
+		let _storage_lock = init()?;
+		let balances = StoredMap::<String, u128>::new(b"balances");
+		let mut history = StoredVec::<u64>::new(b"history");
+		let failing = StoredMap::<u8, Unserializable>::new(b"failing");
+
+		let mut batch = WriteBatch::new();
+		let result = (|| -> StdResult<()> {
+			balances.set_batched(&mut batch, &"alice".to_string(), &100)?;
+			balances.remove_batched(&mut batch, &"bob".to_string())?;
+			history.push_batched(&mut batch, &1)?;
+			failing.set_batched(&mut batch, &0, &Unserializable)?;
+			batch.commit();
+			Ok(())
+		})();
+		assert!(result.is_err());
+		assert!(storage_is_empty());
+		Ok(())
+	}
+
+	#[test]
+	fn commit_preserves_order() -> TestingResult {
+		let _storage_lock = init()?;
+		let map = StoredMap::<u8, u16>::new(NAMESPACE);
+		map.set(&1, &10)?;
+
+		let mut batch = WriteBatch::new();
+		map.remove_batched(&mut batch, &1)?;
+		map.set_batched(&mut batch, &1, &11)?;
+		map.set_batched(&mut batch, &2, &20)?;
+		map.remove_batched(&mut batch, &2)?;
+		map.set_batched(&mut batch, &3, &30)?;
+		map.set_batched(&mut batch, &3, &31)?;
+		assert_eq!(batch.len(), 6);
+		// Nothing is written until the commit
+		assert_eq!(map.get(&1)?.map(OZeroCopy::into_inner), Some(10));
+		assert_eq!(map.get(&3)?, None);
+
+		batch.commit();
+		assert_eq!(map.get(&1)?.map(OZeroCopy::into_inner), Some(11));
+		assert_eq!(map.get(&2)?, None);
+		assert_eq!(map.get(&3)?.map(OZeroCopy::into_inner), Some(31));
+		Ok(())
+	}
+
+	#[test]
+	fn headers_written_once() -> TestingResult {
+		let _storage_lock = init()?;
+		let mut vec = StoredVec::<u32>::new(b"vec");
+		vec.push(&0)?;
+		let mut queue = StoredVecDeque::<u32>::new(b"queue");
+		queue.push_back(&1)?;
+
+		let mut batch = WriteBatch::new();
+		vec.push_batched(&mut batch, &1)?;
+		vec.push_batched(&mut batch, &2)?;
+		vec.set_batched(&mut batch, 0, &100)?;
+		queue.push_back_batched(&mut batch, &2)?;
+		queue.push_front_batched(&mut batch, &0)?;
+		queue.set_batched(&mut batch, 2, &200)?;
+		// 3 vec entries + 3 queue entries + 2 headers
+		assert_eq!(batch.len(), 8);
+		assert_eq!(storage_read(b"vec"), Some(1u32.to_le_bytes().to_vec()));
+
+		batch.commit();
+		let vec = StoredVec::<u32>::new(b"vec");
+		assert_eq!(
+			vec.iter()
+				.map(|value| value.map(OZeroCopy::into_inner))
+				.collect::<StdResult<Vec<_>>>()?,
+			[100, 1, 2]
+		);
+		let queue = StoredVecDeque::<u32>::new(b"queue");
+		assert_eq!(
+			queue
+				.iter()
+				.map(|value| value.map(OZeroCopy::into_inner))
+				.collect::<StdResult<Vec<_>>>()?,
+			[0, 1, 200]
+		);
+		Ok(())
+	}
+}
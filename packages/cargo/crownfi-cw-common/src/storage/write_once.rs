@@ -0,0 +1,236 @@
+use std::marker::PhantomData;
+
+use cosmwasm_std::{Env, StdError, StdResult};
+use thiserror::Error;
+
+use super::{
+	base::{storage_read, storage_remove, storage_write, with_storage_context},
+	map::StoredMap,
+	OZeroCopy, SerializableItem,
+};
+
+const HEIGHT_PREFIX_LEN: usize = 8;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum WriteOnceError {
+	#[error("Value was already set at block height {0}")]
+	AlreadySetThisBlock(u64),
+	#[error("{0}")]
+	Std(#[from] StdError),
+}
+impl From<WriteOnceError> for StdError {
+	fn from(value: WriteOnceError) -> Self {
+		match value {
+			WriteOnceError::Std(err) => err,
+			_ => StdError::generic_err(value.to_string()),
+		}
+	}
+}
+
+/// A single value which can only be written once per block, intended for oracle-style values which must not be
+/// manipulated multiple times within the same block.
+///
+/// The value is stored as `height (u64 LE) || value bytes`, so Pod values are still zero-copy readable.
+pub struct WriteOncePerBlock<T: SerializableItem> {
+	namespace: &'static [u8],
+	value_type: PhantomData<T>,
+}
+
+impl<T: SerializableItem> WriteOncePerBlock<T> {
+	pub const fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			namespace,
+			value_type: PhantomData,
+		}
+	}
+
+	/// Returns the value along with the block height it was set at.
+	pub fn get(&self) -> StdResult<Option<(u64, OZeroCopy<T>)>> {
+		storage_read(self.namespace)
+			.map(split_height)
+			.transpose()
+			.map_err(|err| with_storage_context(err, self.namespace, &[]))
+	}
+
+	/// Returns the block height the value was last set at, if any.
+	pub fn last_height(&self) -> StdResult<Option<u64>> {
+		storage_read(self.namespace)
+			.map(|bytes| read_height(&bytes))
+			.transpose()
+	}
+
+	/// Sets the value, fails if it has already been set during the current block.
+	pub fn try_set(&self, env: &Env, value: &T) -> Result<(), WriteOnceError> {
+		if let Some(last_height) = self.last_height()? {
+			if last_height >= env.block.height {
+				return Err(WriteOnceError::AlreadySetThisBlock(last_height));
+			}
+		}
+		self.force_set(env, value)?;
+		Ok(())
+	}
+
+	/// Sets the value regardless of whether or not it was already set during the current block. Intended for admin
+	/// overrides.
+	pub fn force_set(&self, env: &Env, value: &T) -> StdResult<()> {
+		storage_write(self.namespace, &join_height(env.block.height, value)?);
+		Ok(())
+	}
+
+	pub fn remove(&self) {
+		storage_remove(self.namespace)
+	}
+}
+
+/// Like [WriteOncePerBlock], but with a separate value for each key (e.g. an oracle feed ID).
+///
+/// Each key can be written once per block independently of the others.
+pub struct WriteOncePerBlockMap<K: SerializableItem, T: SerializableItem> {
+	map: StoredMap<K, T>,
+}
+
+impl<K: SerializableItem, T: SerializableItem> WriteOncePerBlockMap<K, T> {
+	pub fn new(namespace: &'static [u8]) -> Self {
+		Self {
+			map: StoredMap::new(namespace),
+		}
+	}
+
+	#[inline]
+	pub fn key(&self, key: &K) -> Vec<u8> {
+		self.map.key(key)
+	}
+
+	/// Returns the value along with the block height it was set at.
+	pub fn get(&self, key: &K) -> StdResult<Option<(u64, OZeroCopy<T>)>> {
+		self.map
+			.get_raw_bytes(key)
+			.map(split_height)
+			.transpose()
+			.map_err(|err| self.map.with_storage_context(err, key))
+	}
+
+	/// Returns the block height the value was last set at, if any.
+	pub fn last_height(&self, key: &K) -> StdResult<Option<u64>> {
+		self.map.get_raw_bytes(key).map(|bytes| read_height(&bytes)).transpose()
+	}
+
+	/// Sets the value for the key, fails if it has already been set during the current block.
+	pub fn try_set(&self, env: &Env, key: &K, value: &T) -> Result<(), WriteOnceError> {
+		if let Some(last_height) = self.last_height(key)? {
+			if last_height >= env.block.height {
+				return Err(WriteOnceError::AlreadySetThisBlock(last_height));
+			}
+		}
+		self.force_set(env, key, value)?;
+		Ok(())
+	}
+
+	/// Sets the value for the key regardless of whether or not it was already set during the current block. Intended
+	/// for admin overrides.
+	pub fn force_set(&self, env: &Env, key: &K, value: &T) -> StdResult<()> {
+		self.map.set_raw_bytes(key, &join_height(env.block.height, value)?)?;
+		Ok(())
+	}
+
+	pub fn remove(&self, key: &K) {
+		self.map.remove(key)
+	}
+}
+
+fn read_height(bytes: &[u8]) -> StdResult<u64> {
+	let Some(height) = bytes.first_chunk::<HEIGHT_PREFIX_LEN>() else {
+		return Err(StdError::parse_err(
+			"WriteOncePerBlock",
+			"value is missing its height prefix",
+		));
+	};
+	Ok(u64::from_le_bytes(*height))
+}
+
+fn split_height<T: SerializableItem>(mut bytes: Vec<u8>) -> StdResult<(u64, OZeroCopy<T>)> {
+	let height = read_height(&bytes)?;
+	bytes.drain(..HEIGHT_PREFIX_LEN);
+	Ok((height, OZeroCopy::new(bytes)?))
+}
+
+fn join_height<T: SerializableItem>(height: u64, value: &T) -> StdResult<Vec<u8>> {
+	let mut bytes;
+	if let Some(value_bytes) = value.serialize_as_ref() {
+		bytes = Vec::with_capacity(HEIGHT_PREFIX_LEN + value_bytes.len());
+		bytes.extend_from_slice(&height.to_le_bytes());
+		bytes.extend_from_slice(value_bytes);
+	} else {
+		bytes = height.to_le_bytes().to_vec();
+		bytes.extend_from_slice(&value.serialize_to_owned()?);
+	}
+	Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::testing_common::*;
+	use cosmwasm_std::testing::mock_env;
+
+	fn env_at(height: u64) -> Env {
+		let mut env = mock_env();
+		env.block.height = height;
+		env
+	}
+
+	#[test]
+	fn once_per_block() -> TestingResult {
+		let _storage_lock = init()?;
+		let price = WriteOncePerBlock::<u128>::new(NAMESPACE);
+
+		assert_eq!(price.get()?, None);
+		price.try_set(&env_at(100), &5)?;
+		assert_eq!(
+			price.try_set(&env_at(100), &6),
+			Err(WriteOnceError::AlreadySetThisBlock(100))
+		);
+		let (height, value) = price.get()?.unwrap();
+		assert_eq!(height, 100);
+		assert_eq!(*value, 5);
+
+		price.try_set(&env_at(101), &7)?;
+		let (height, value) = price.get()?.unwrap();
+		assert_eq!(height, 101);
+		assert_eq!(*value, 7);
+
+		price.force_set(&env_at(101), &8)?;
+		assert_eq!(
+			price.get()?.map(|(height, value)| (height, value.into_inner())),
+			Some((101, 8))
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn once_per_block_per_key() -> TestingResult {
+		let _storage_lock = init()?;
+		let prices = WriteOncePerBlockMap::<String, String>::new(NAMESPACE);
+		let feed_a = "sei/usd".to_string();
+		let feed_b = "eth/usd".to_string();
+
+		prices.try_set(&env_at(100), &feed_a, &"0.5".to_string())?;
+		prices.try_set(&env_at(100), &feed_b, &"3000".to_string())?;
+		assert_eq!(
+			prices.try_set(&env_at(100), &feed_a, &"0.6".to_string()),
+			Err(WriteOnceError::AlreadySetThisBlock(100))
+		);
+		assert_eq!(prices.last_height(&feed_b)?, Some(100));
+
+		prices.try_set(&env_at(101), &feed_a, &"0.6".to_string())?;
+		let (height, value) = prices.get(&feed_a)?.unwrap();
+		assert_eq!(height, 101);
+		assert_eq!(*value, "0.6");
+
+		prices.remove(&feed_a);
+		assert_eq!(prices.get(&feed_a)?, None);
+
+		Ok(())
+	}
+}
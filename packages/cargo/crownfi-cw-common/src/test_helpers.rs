@@ -0,0 +1,3 @@
+#[cfg(feature = "sei")]
+pub mod msgs;
+pub mod simulator;
@@ -0,0 +1,374 @@
+use std::fmt::{self, Debug, Display};
+
+use cosmwasm_std::{from_json, BankMsg, Binary, CosmosMsg, SubMsg, Uint128, Uint256, WasmMsg};
+use hex::ToHex;
+use sei_cosmwasm::SeiMsg;
+use serde::de::DeserializeOwned;
+
+use crate::utils::bytes_to_ethereum_address;
+
+/// An ERC20 call decoded from its calldata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Erc20Call {
+	Transfer { to: String, amount: Uint256 },
+	Approve { spender: String, amount: Uint256 },
+	TransferFrom { from: String, to: String, amount: Uint256 },
+}
+
+impl Erc20Call {
+	pub const TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+	pub const APPROVE_SELECTOR: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3];
+	pub const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x23, 0xb8, 0x72, 0xdd];
+
+	/// Decodes the call if the selector is a known ERC20 one and the args are the expected length.
+	pub fn decode(selector: [u8; 4], args: &[u8]) -> Option<Self> {
+		let words: Vec<&[u8; 32]> = args
+			.chunks(32)
+			.map(|word| word.try_into().ok())
+			.collect::<Option<_>>()?;
+		let address = |word: &[u8; 32]| bytes_to_ethereum_address(word).ok();
+		match (selector, words.as_slice()) {
+			(Self::TRANSFER_SELECTOR, [to, amount]) => Some(Self::Transfer {
+				to: address(to)?,
+				amount: Uint256::from_be_bytes(**amount),
+			}),
+			(Self::APPROVE_SELECTOR, [spender, amount]) => Some(Self::Approve {
+				spender: address(spender)?,
+				amount: Uint256::from_be_bytes(**amount),
+			}),
+			(Self::TRANSFER_FROM_SELECTOR, [from, to, amount]) => Some(Self::TransferFrom {
+				from: address(from)?,
+				to: address(to)?,
+				amount: Uint256::from_be_bytes(**amount),
+			}),
+			_ => None,
+		}
+	}
+}
+
+impl Display for Erc20Call {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Erc20Call::Transfer { to, amount } => write!(f, "transfer(to: {to}, amount: {amount})"),
+			Erc20Call::Approve { spender, amount } => write!(f, "approve(spender: {spender}, amount: {amount})"),
+			Erc20Call::TransferFrom { from, to, amount } => {
+				write!(f, "transferFrom(from: {from}, to: {to}, amount: {amount})")
+			}
+		}
+	}
+}
+
+/// A `SeiMsg::CallEvm` or `SeiMsg::DelegateCallEvm` with its base64 calldata decoded.
+#[derive(Clone, PartialEq, Eq)]
+pub struct DecodedEvmCall {
+	pub to: String,
+	/// Always zero for delegate calls
+	pub value: Uint128,
+	pub delegate: bool,
+	/// `None` if the calldata is less than 4 bytes long, i.e. a plain value transfer.
+	pub selector: Option<[u8; 4]>,
+	/// The calldata following the selector
+	pub args: Vec<u8>,
+	/// Set if the selector is a known ERC20 one
+	pub erc20: Option<Erc20Call>,
+}
+
+impl DecodedEvmCall {
+	/// Returns `None` if the message isn't an EVM call.
+	///
+	/// Panics if the calldata isn't valid base64, as no EVM call could be made with it.
+	pub fn from_msg(msg: &CosmosMsg<SeiMsg>) -> Option<Self> {
+		let (to, value, data, delegate) = match msg {
+			CosmosMsg::Custom(SeiMsg::CallEvm { value, to, data }) => (to, *value, data, false),
+			CosmosMsg::Custom(SeiMsg::DelegateCallEvm { to, data }) => (to, Uint128::zero(), data, true),
+			_ => return None,
+		};
+		let data =
+			Binary::from_base64(data).unwrap_or_else(|err| panic!("EVM call to {to} has invalid calldata: {err}"));
+		let (selector, args) = match data.split_first_chunk::<4>() {
+			Some((selector, args)) => (Some(*selector), args.to_vec()),
+			None => (None, data.to_vec()),
+		};
+		Some(Self {
+			to: to.clone(),
+			value,
+			delegate,
+			selector,
+			erc20: selector.and_then(|selector| Erc20Call::decode(selector, &args)),
+			args,
+		})
+	}
+}
+
+impl Display for DecodedEvmCall {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(if self.delegate {
+			"evm delegate call to "
+		} else {
+			"evm call to "
+		})?;
+		write!(f, "{} with value {}: ", self.to, self.value)?;
+		if let Some(erc20) = &self.erc20 {
+			return write!(f, "{erc20}");
+		}
+		match self.selector {
+			Some(selector) => write!(f, "0x{}(", selector.encode_hex::<String>())?,
+			None => f.write_str("(")?,
+		}
+		for (i, word) in self.args.chunks(32).enumerate() {
+			if i > 0 {
+				f.write_str(", ")?;
+			}
+			write!(f, "0x{}", word.encode_hex::<String>())?;
+		}
+		f.write_str(")")
+	}
+}
+
+impl Debug for DecodedEvmCall {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		Display::fmt(self, f)
+	}
+}
+
+/// Returns all EVM calls within the messages, decoded.
+pub fn find_evm_calls(msgs: &[CosmosMsg<SeiMsg>]) -> Vec<DecodedEvmCall> {
+	msgs.iter().filter_map(DecodedEvmCall::from_msg).collect()
+}
+
+fn describe_msg(msg: &CosmosMsg<SeiMsg>) -> String {
+	if let Some(evm_call) = DecodedEvmCall::from_msg(msg) {
+		return evm_call.to_string();
+	}
+	match msg {
+		CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+			format!("bank send {} to {to_address}", describe_coins(amount))
+		}
+		CosmosMsg::Wasm(WasmMsg::Execute {
+			contract_addr,
+			msg,
+			funds,
+		}) => format!(
+			"wasm execute {contract_addr} with funds [{}]: {}",
+			describe_coins(funds),
+			String::from_utf8_lossy(msg)
+		),
+		msg => format!("{msg:?}"),
+	}
+}
+
+fn describe_coins(coins: &[cosmwasm_std::Coin]) -> String {
+	coins.iter().map(|coin| coin.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// Renders one message per line, with bank sends, wasm executions and EVM calls in a readable form.
+pub struct PrettyMsgs<'a>(pub &'a [CosmosMsg<SeiMsg>]);
+
+impl Debug for PrettyMsgs<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.0.is_empty() {
+			return f.write_str("(no messages)");
+		}
+		for (i, msg) in self.0.iter().enumerate() {
+			writeln!(f, "  {i}: {}", describe_msg(msg))?;
+		}
+		Ok(())
+	}
+}
+
+/// Like [PrettyMsgs], but also shows the reply settings of each sub-message.
+pub struct PrettySubMsgs<'a>(pub &'a [SubMsg<SeiMsg>]);
+
+impl Debug for PrettySubMsgs<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.0.is_empty() {
+			return f.write_str("(no messages)");
+		}
+		for (i, sub_msg) in self.0.iter().enumerate() {
+			write!(
+				f,
+				"  {i}: {} (id: {}, reply_on: {:?}",
+				describe_msg(&sub_msg.msg),
+				sub_msg.id,
+				sub_msg.reply_on
+			)?;
+			if let Some(gas_limit) = sub_msg.gas_limit {
+				write!(f, ", gas_limit: {gas_limit}")?;
+			}
+			writeln!(f, ")")?;
+		}
+		Ok(())
+	}
+}
+
+/// Panics unless one of the messages is a bank send of exactly the specified coins to the specified address.
+///
+/// The order of the coins doesn't matter.
+pub fn assert_bank_send(msgs: &[CosmosMsg<SeiMsg>], to: &str, coins: &[(&str, u128)]) {
+	let mut expected_coins: Vec<(&str, u128)> = coins.to_vec();
+	expected_coins.sort();
+	let found = msgs.iter().any(|msg| {
+		let CosmosMsg::Bank(BankMsg::Send { to_address, amount }) = msg else {
+			return false;
+		};
+		let mut coins: Vec<(&str, u128)> = amount
+			.iter()
+			.map(|coin| (coin.denom.as_str(), coin.amount.u128()))
+			.collect();
+		coins.sort();
+		to_address == to && coins == expected_coins
+	});
+	if !found {
+		let expected_coins = coins
+			.iter()
+			.map(|(denom, amount)| format!("{amount}{denom}"))
+			.collect::<Vec<_>>()
+			.join(", ");
+		panic!(
+			"expected a bank send of {expected_coins} to {to}, found:\n{:?}",
+			PrettyMsgs(msgs)
+		);
+	}
+}
+
+/// Panics unless one of the messages executes the specified contract with a message equal to `expected_msg`.
+pub fn assert_wasm_execute<T: DeserializeOwned + PartialEq + Debug>(
+	msgs: &[CosmosMsg<SeiMsg>],
+	contract: &str,
+	expected_msg: &T,
+) {
+	let found = msgs.iter().any(|msg| {
+		let CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) = msg else {
+			return false;
+		};
+		contract_addr == contract && from_json::<T>(msg).is_ok_and(|msg| msg == *expected_msg)
+	});
+	if !found {
+		panic!(
+			"expected a wasm execution of {contract} with {expected_msg:?}, found:\n{:?}",
+			PrettyMsgs(msgs)
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::panic::catch_unwind;
+
+	use cosmwasm_std::{coin, to_json_binary, Addr, ReplyOn};
+	use cw20::{Cw20Coin, Cw20ExecuteMsg};
+
+	use super::*;
+	use crate::data_types::asset::FungibleAsset;
+
+	const TOKEN: &str = "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+	const RECIPIENT: &str = "0x000000000000000000000000000000000000beef";
+
+	fn msgs() -> Vec<CosmosMsg<SeiMsg>> {
+		vec![
+			BankMsg::Send {
+				to_address: "sei1alice".into(),
+				amount: vec![coin(100, "usei"), coin(5, "uatom")],
+			}
+			.into(),
+			WasmMsg::Execute {
+				contract_addr: "sei1token".into(),
+				msg: to_json_binary(&Cw20ExecuteMsg::Burn { amount: 7u128.into() }).unwrap(),
+				funds: vec![],
+			}
+			.into(),
+			FungibleAsset::ERC20(Cw20Coin {
+				address: TOKEN.into(),
+				amount: 42u128.into(),
+			})
+			.transfer_to_msg(&Addr::unchecked(RECIPIENT)),
+		]
+	}
+
+	fn panic_message(f: impl FnOnce() + std::panic::UnwindSafe) -> String {
+		let err = catch_unwind(f).expect_err("expected a panic");
+		err.downcast_ref::<String>().cloned().unwrap()
+	}
+
+	#[test]
+	fn bank_send() {
+		assert_bank_send(&msgs(), "sei1alice", &[("uatom", 5), ("usei", 100)]);
+		assert_eq!(
+			panic_message(|| assert_bank_send(&msgs(), "sei1alice", &[("usei", 100)])),
+			"expected a bank send of 100usei to sei1alice, found:
+  0: bank send 100usei, 5uatom to sei1alice
+  1: wasm execute sei1token with funds []: {\"burn\":{\"amount\":\"7\"}}
+  2: evm call to 0x2c7536e3605d9c16a7a3d7b1898e529396a65c23 with value 0: transfer(to: 0x000000000000000000000000000000000000beef, amount: 42)
+"
+		);
+	}
+
+	#[test]
+	fn wasm_execute() {
+		assert_wasm_execute(&msgs(), "sei1token", &Cw20ExecuteMsg::Burn { amount: 7u128.into() });
+		assert!(catch_unwind(|| assert_wasm_execute(
+			&msgs(),
+			"sei1token",
+			&Cw20ExecuteMsg::Burn { amount: 8u128.into() }
+		))
+		.is_err());
+		assert!(catch_unwind(|| assert_wasm_execute(
+			&msgs(),
+			"sei1other",
+			&Cw20ExecuteMsg::Burn { amount: 7u128.into() }
+		))
+		.is_err());
+	}
+
+	#[test]
+	fn evm_calls() {
+		let evm_calls = find_evm_calls(&msgs());
+		assert_eq!(evm_calls.len(), 1);
+		assert_eq!(evm_calls[0].to, TOKEN);
+		assert_eq!(evm_calls[0].selector, Some(Erc20Call::TRANSFER_SELECTOR));
+		assert_eq!(
+			evm_calls[0].erc20,
+			Some(Erc20Call::Transfer {
+				to: RECIPIENT.into(),
+				amount: 42u128.into()
+			})
+		);
+
+		let unknown_call = CosmosMsg::Custom(SeiMsg::DelegateCallEvm {
+			to: TOKEN.into(),
+			data: Binary::from([0x12, 0x34, 0x56, 0x78, 1, 2].as_slice()).to_base64(),
+		});
+		assert_eq!(
+			format!("{:?}", find_evm_calls(&[unknown_call])[0]),
+			format!("evm delegate call to {TOKEN} with value 0: 0x12345678(0x0102)")
+		);
+	}
+
+	#[test]
+	fn pretty_sub_msgs() {
+		let sub_msgs = vec![
+			SubMsg::new(BankMsg::Send {
+				to_address: "sei1alice".into(),
+				amount: vec![coin(1, "usei")],
+			}),
+			SubMsg::reply_on_error(
+				CosmosMsg::Custom(SeiMsg::CallEvm {
+					value: 3u128.into(),
+					to: TOKEN.into(),
+					data: String::new(),
+				}),
+				9,
+			)
+			.with_gas_limit(100_000),
+		];
+		assert_eq!(
+			format!("{:?}", PrettySubMsgs(&sub_msgs)),
+			format!(
+				"  0: bank send 1usei to sei1alice (id: 0, reply_on: {:?})\n  \
+				1: evm call to {TOKEN} with value 3: () (id: 9, reply_on: Error, gas_limit: 100000)\n",
+				ReplyOn::Never
+			)
+		);
+		assert_eq!(format!("{:?}", PrettySubMsgs(&[])), "(no messages)");
+	}
+}
@@ -0,0 +1,289 @@
+use std::{collections::BTreeMap, rc::Rc};
+
+use cosmwasm_std::{
+	testing::{mock_env, MockQuerier},
+	Addr, BankMsg, Coin, CosmosMsg, Env, MessageInfo, OverflowError, OverflowOperation, QuerierWrapper, Response,
+	StdResult, Uint128,
+};
+
+use crate::{
+	env::MinimalEnvInfo,
+	storage::base::{ThreadSafeStorage, ThreadStorageGuard},
+};
+
+/// The block time used by [Simulator::advance_blocks] unless changed with [Simulator::set_block_time].
+pub const DEFAULT_BLOCK_TIME_NANOS: u64 = 400_000_000;
+
+/// Standardizes the env, time and funds bookkeeping of tests which run several calls against the native storage.
+///
+/// The simulator makes the current thread use its own storage for as long as it exists (see
+/// [ThreadStorageGuard]), so everything done through this crate's storage types inside [call](Self::call) or
+/// elsewhere in the test sees the same state.
+///
+/// Bank balances are tracked in a ledger which is also exposed through the querier. Funds sent with a call are moved
+/// to the contract before it runs, and the `BankMsg`s it returns can be applied with
+/// [apply_response](Self::apply_response). No Wasm or submessages are executed.
+pub struct Simulator {
+	env: Env,
+	sender: Addr,
+	funds: Vec<Coin>,
+	block_time_nanos: u64,
+	balances: BTreeMap<(String, String), Uint128>,
+	querier: MockQuerier,
+	_storage: ThreadStorageGuard,
+}
+
+impl Simulator {
+	/// Starts at [mock_env], with an empty [MemoryStorage](cosmwasm_std::MemoryStorage).
+	pub fn new() -> Self {
+		Self::with_storage(Box::new(cosmwasm_std::MemoryStorage::new()))
+	}
+
+	/// Same as `new`, but uses the specified storage.
+	pub fn with_storage(storage: Box<dyn ThreadSafeStorage>) -> Self {
+		Self {
+			env: mock_env(),
+			sender: Addr::unchecked("sender"),
+			funds: Vec::new(),
+			block_time_nanos: DEFAULT_BLOCK_TIME_NANOS,
+			balances: BTreeMap::new(),
+			querier: MockQuerier::new(&[]),
+			_storage: ThreadStorageGuard::new(storage),
+		}
+	}
+
+	pub fn env(&self) -> &Env {
+		&self.env
+	}
+
+	pub fn env_mut(&mut self) -> &mut Env {
+		&mut self.env
+	}
+
+	pub fn contract_address(&self) -> &Addr {
+		&self.env.contract.address
+	}
+
+	/// Advances the block time without changing the height.
+	pub fn advance_time(&mut self, secs: u64) {
+		self.env.block.time = self.env.block.time.plus_seconds(secs);
+	}
+
+	/// Advances the height by `blocks`, and the time by `blocks` times the block time.
+	pub fn advance_blocks(&mut self, blocks: u64) {
+		self.env.block.height += blocks;
+		self.env.block.time = self.env.block.time.plus_nanos(blocks * self.block_time_nanos);
+	}
+
+	pub fn set_block_time(&mut self, nanos: u64) {
+		self.block_time_nanos = nanos;
+	}
+
+	/// Sets the sender of the following calls.
+	pub fn set_sender(&mut self, sender: impl Into<String>) {
+		self.sender = Addr::unchecked(sender);
+	}
+
+	/// Sets the funds sent with the next call only.
+	pub fn set_funds(&mut self, funds: &[Coin]) {
+		self.funds = funds.to_vec();
+	}
+
+	/// Moves the funds set with [set_funds](Self::set_funds) from the sender to the contract, then calls `f` with the
+	/// current env and the message info.
+	///
+	/// Panics if the sender can't afford the funds.
+	pub fn call<F, R>(&mut self, f: F) -> R
+	where
+		F: FnOnce(MinimalEnvInfo, MessageInfo) -> R,
+	{
+		let funds = std::mem::take(&mut self.funds);
+		let contract = self.env.contract.address.clone();
+		self.transfer(self.sender.clone().as_str(), contract.as_str(), &funds)
+			.unwrap_or_else(|err| panic!("{} can't afford the funds sent: {err}", self.sender));
+		let info = MessageInfo {
+			sender: self.sender.clone(),
+			funds,
+		};
+		let env_info = MinimalEnvInfo {
+			querier: Rc::new(QuerierWrapper::new(&self.querier)),
+			env: Rc::new(self.env.clone()),
+		};
+		f(env_info, info)
+	}
+
+	pub fn balance(&self, address: &str, denom: &str) -> Uint128 {
+		self.balances
+			.get(&(address.to_string(), denom.to_string()))
+			.copied()
+			.unwrap_or_default()
+	}
+
+	/// Adds the coins to the balance of `address`, out of thin air.
+	pub fn mint(&mut self, address: &str, coins: &[Coin]) -> StdResult<()> {
+		for coin in coins {
+			let balance = self.balance(address, &coin.denom).checked_add(coin.amount)?;
+			self.balances.insert((address.to_string(), coin.denom.clone()), balance);
+		}
+		self.sync_querier(address);
+		Ok(())
+	}
+
+	/// Removes the coins from the balance of `address`, returning an error if it can't afford them.
+	pub fn burn(&mut self, address: &str, coins: &[Coin]) -> StdResult<()> {
+		for coin in coins {
+			let balance = self.balance(address, &coin.denom);
+			let balance = balance
+				.checked_sub(coin.amount)
+				.map_err(|_| OverflowError::new(OverflowOperation::Sub, balance, coin.amount))?;
+			self.balances.insert((address.to_string(), coin.denom.clone()), balance);
+		}
+		self.sync_querier(address);
+		Ok(())
+	}
+
+	pub fn transfer(&mut self, from: &str, to: &str, coins: &[Coin]) -> StdResult<()> {
+		self.burn(from, coins)?;
+		self.mint(to, coins)
+	}
+
+	/// Applies the effects of the bank messages sent by the contract, other messages are ignored.
+	pub fn apply_msgs<'a, T: 'a>(&mut self, msgs: impl IntoIterator<Item = &'a CosmosMsg<T>>) -> StdResult<()> {
+		let contract = self.env.contract.address.to_string();
+		for msg in msgs {
+			match msg {
+				CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+					self.transfer(&contract, to_address, amount)?
+				}
+				CosmosMsg::Bank(BankMsg::Burn { amount }) => self.burn(&contract, amount)?,
+				_ => {}
+			}
+		}
+		Ok(())
+	}
+
+	/// Same as `apply_msgs` with the response's messages, regardless of their reply settings.
+	pub fn apply_response<T>(&mut self, response: &Response<T>) -> StdResult<()> {
+		self.apply_msgs(response.messages.iter().map(|sub_msg| &sub_msg.msg))
+	}
+
+	fn sync_querier(&mut self, address: &str) {
+		let coins = self
+			.balances
+			.range((address.to_string(), String::new())..)
+			.take_while(|((holder, _), _)| holder == address)
+			.filter(|(_, amount)| !amount.is_zero())
+			.map(|((_, denom), amount)| Coin::new(amount.u128(), denom))
+			.collect();
+		self.querier.update_balance(address, coins);
+	}
+}
+
+impl Default for Simulator {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::panic::{catch_unwind, AssertUnwindSafe};
+
+	use cosmwasm_std::{coin, coins, Timestamp};
+
+	use super::*;
+	use crate::storage::{base::storage_read_item, base::storage_write_item, timelock::TimelockedItem};
+
+	#[test]
+	fn timelock_promoted_after_advance_time() -> StdResult<()> {
+		let mut sim = Simulator::new();
+		let item = TimelockedItem::<u64>::new(b"fee");
+		sim.call(|env_info, _| -> StdResult<()> {
+			item.set_immediately(&1)?;
+			let now = env_info.env.block.time;
+			item.schedule(&2, now.plus_seconds(3600), now, false)?;
+			Ok(())
+		})?;
+
+		sim.advance_time(3599);
+		let current = sim.call(|env_info, _| item.current(env_info.env.block.time))?;
+		assert_eq!(current.map(|value| *value), Some(1));
+
+		sim.advance_time(1);
+		let promoted = sim.call(|env_info, _| item.tick(env_info.env.block.time))?;
+		assert!(promoted);
+		assert_eq!(item.current(Timestamp::from_nanos(0))?.map(|value| *value), Some(2));
+		Ok(())
+	}
+
+	/// Allows 2 calls per sender every 10 blocks
+	fn throttled(env_info: MinimalEnvInfo, info: MessageInfo) -> StdResult<bool> {
+		let key = [b"throttle".as_slice(), info.sender.as_bytes()].concat();
+		let window = env_info.env.block.height / 10;
+		let count = match storage_read_item::<(u64, u32)>(&key)? {
+			Some(state) if state.0 == window => state.1,
+			_ => 0,
+		};
+		if count == 2 {
+			return Ok(false);
+		}
+		storage_write_item(&key, &(window, count + 1))?;
+		Ok(true)
+	}
+
+	#[test]
+	fn throttle_window_reset() -> StdResult<()> {
+		let mut sim = Simulator::new();
+		// mock_env starts at height 12345
+		assert!(sim.call(throttled)?);
+		assert!(sim.call(throttled)?);
+		assert!(!sim.call(throttled)?);
+		sim.set_sender("other");
+		assert!(sim.call(throttled)?);
+
+		sim.set_sender("sender");
+		sim.advance_blocks(4);
+		assert!(!sim.call(throttled)?);
+		sim.advance_blocks(1);
+		assert_eq!(sim.env().block.height, 12350);
+		assert!(sim.call(throttled)?);
+		Ok(())
+	}
+
+	#[test]
+	fn bank_msgs_update_balances() -> StdResult<()> {
+		let mut sim = Simulator::new();
+		sim.mint("sender", &coins(100, "usei"))?;
+		sim.set_funds(&coins(60, "usei"));
+		let response = sim.call(|env_info, info| -> StdResult<Response> {
+			// The funds have been received already
+			let balance = env_info.querier.query_balance(&env_info.env.contract.address, "usei")?;
+			assert_eq!(balance, info.funds[0]);
+			Ok(Response::new()
+				.add_message(BankMsg::Send {
+					to_address: "alice".into(),
+					amount: coins(25, "usei"),
+				})
+				.add_message(BankMsg::Burn {
+					amount: coins(5, "usei"),
+				}))
+		})?;
+		sim.apply_response(&response)?;
+
+		let contract = sim.contract_address().to_string();
+		assert_eq!(sim.balance("sender", "usei"), Uint128::new(40));
+		assert_eq!(sim.balance(&contract, "usei"), Uint128::new(30));
+		assert_eq!(sim.balance("alice", "usei"), Uint128::new(25));
+		sim.call(|env_info, _| -> StdResult<()> {
+			assert_eq!(env_info.querier.query_all_balances("alice")?, coins(25, "usei"));
+			Ok(())
+		})?;
+
+		// Funds are only sent with the next call, and can't be spent twice
+		assert!(sim.call(|_, info| info.funds.is_empty()));
+		sim.set_funds(&[coin(41, "usei")]);
+		assert!(catch_unwind(AssertUnwindSafe(|| sim.call(|_, _| ()))).is_err());
+		assert_eq!(sim.balance("sender", "usei"), Uint128::new(40));
+		Ok(())
+	}
+}
@@ -1,7 +1,15 @@
-use cosmwasm_std::StdError;
+#[cfg(feature = "cosmwasm_1_2")]
+use cosmwasm_std::{to_json_binary, Coin, WasmMsg};
+use cosmwasm_std::{Attribute, Binary, ConversionOverflowError, StdError, StdResult, Uint128, Uint256};
 use hex::{FromHex, ToHex};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fmt;
 use tiny_keccak::Hasher;
 
+use crate::data_types::canonical_addr::SeiCanonicalAddr;
+
 pub fn lexicographic_next(bytes: &[u8]) -> Vec<u8> {
 	let mut result = Vec::from(bytes);
 	let mut add = true;
@@ -47,6 +55,28 @@ pub fn bytes_to_ethereum_address(addr_bytes: &[u8]) -> Result<String, StdError>
 	Ok(result)
 }
 
+/// Applies the EIP-55 checksum casing to the 40 lowercase hex characters of an ethereum address.
+///
+/// **This performs a keccak hash** and might use a lot of gas
+fn apply_ethereum_checksum_case(addr_hex: &mut str) {
+	debug_assert!(addr_hex.len() == 40 && addr_hex.is_ascii());
+	let mut hash = [0u8; 32];
+	let mut hasher = tiny_keccak::Keccak::v256();
+	hasher.update(addr_hex.as_bytes());
+	hasher.finalize(&mut hash);
+
+	// Each character is uppercased if the corresponding nibble of the hash is 8 or greater
+	for (hash_index, hash_byte) in hash[..20].iter().enumerate() {
+		let addr_index = hash_index * 2;
+		if hash_byte >> 4 > 0x07 {
+			addr_hex[addr_index..addr_index + 1].make_ascii_uppercase();
+		}
+		if hash_byte & 0x0f > 0x07 {
+			addr_hex[addr_index + 1..addr_index + 2].make_ascii_uppercase();
+		}
+	}
+}
+
 /// Turns the specified all-lowercase ethereum address into a checksum-case addres
 ///
 /// **This performs a keccak hash** and might use a lot of gas
@@ -70,31 +100,704 @@ pub fn checksumify_ethereum_address(addr_str: &mut str) -> Result<(), StdError>
 	}
 	// We're not actually gonna check if it's all hex characters for now
 	addr_hex.make_ascii_lowercase();
-	let mut hash = [0u8; 32];
+	apply_ethereum_checksum_case(addr_hex);
+	Ok(())
+}
+
+/// Parses an ethereum address, enforcing the EIP-55 checksum if the address is mixed-case.
+///
+/// All-lowercase and all-uppercase addresses don't carry a checksum, and are accepted like with
+/// [parse_ethereum_address]. Intended for addresses entered by users, e.g. in instantiate or config messages, where a
+/// wrong checksum means the address was mistyped or truncated.
+///
+/// **This performs a keccak hash** for mixed-case addresses
+pub fn parse_ethereum_address_checked(addr_str: &str) -> Result<[u8; 20], StdError> {
+	let addr_bytes = parse_ethereum_address(addr_str)?;
+	let addr_hex = &addr_str[2..];
+	let has_lowercase = addr_hex.bytes().any(|c| c.is_ascii_lowercase());
+	let has_uppercase = addr_hex.bytes().any(|c| c.is_ascii_uppercase());
+	if has_lowercase && has_uppercase {
+		let mut checksummed = addr_hex.to_ascii_lowercase();
+		apply_ethereum_checksum_case(&mut checksummed);
+		if checksummed != addr_hex {
+			return Err(StdError::parse_err(
+				"[u8; 20]",
+				"parse_ethereum_address_checked: address checksum is invalid",
+			));
+		}
+	}
+	Ok(addr_bytes)
+}
+
+/// Same as [parse_ethereum_address_checked], but returns the address with checksum casing, for storage or display.
+///
+/// **This performs a keccak hash** and might use a lot of gas
+pub fn validate_ethereum_address_str(addr_str: &str) -> Result<String, StdError> {
+	let mut result = bytes_to_ethereum_address(&parse_ethereum_address_checked(addr_str)?)?;
+	apply_ethereum_checksum_case(&mut result[2..]);
+	Ok(result)
+}
+
+/// Encodes the amount as a big-endian 32-byte EVM word, i.e. a `uint256` in an ABI-encoded payload.
+pub fn uint128_to_evm_word(amount: Uint128) -> [u8; 32] {
+	let mut word = [0u8; 32];
+	word[16..].copy_from_slice(&amount.to_be_bytes());
+	word
+}
+
+/// Decodes a big-endian 32-byte EVM word into a [Uint128], returning a [ConversionOverflowError] if it doesn't fit.
+pub fn evm_word_to_uint128(word: &[u8]) -> Result<Uint128, StdError> {
+	let word = evm_word(word, "Uint128")?;
+	if word[..16] != [0; 16] {
+		return Err(ConversionOverflowError::new("Uint256", "Uint128", Uint256::from_be_bytes(*word)).into());
+	}
+	Ok(Uint128::new(u128::from_be_bytes(
+		word[16..].try_into().expect("slice is 16 bytes long"),
+	)))
+}
+
+#[inline]
+pub fn uint256_to_evm_word(amount: Uint256) -> [u8; 32] {
+	amount.to_be_bytes()
+}
+
+pub fn evm_word_to_uint256(word: &[u8]) -> Result<Uint256, StdError> {
+	Ok(Uint256::from_be_bytes(*evm_word(word, "Uint256")?))
+}
+
+/// Decodes an ABI-encoded `address`, which must have its leading 12 bytes set to zero.
+pub fn evm_word_to_address(word: &[u8]) -> Result<[u8; 20], StdError> {
+	let word = evm_word(word, "[u8; 20]")?;
+	if word[..12] != [0; 12] {
+		return Err(StdError::parse_err(
+			"[u8; 20]",
+			"evm_word_to_address: the leading 12 bytes of the word aren't zero",
+		));
+	}
+	Ok(word[12..].try_into().expect("slice is 20 bytes long"))
+}
+
+fn evm_word<'a>(word: &'a [u8], target_type: &str) -> Result<&'a [u8; 32], StdError> {
+	word.try_into().map_err(|_| {
+		StdError::parse_err(
+			target_type,
+			format!("expected a 32 byte long EVM word, got {} bytes", word.len()),
+		)
+	})
+}
+
+/// Derives the address a contract instantiated by `creator` through `WasmMsg::Instantiate2` will have, without
+/// needing `&dyn Api`.
+///
+/// This is the same derivation as [cosmwasm_std::instantiate2_address], `checksum` is the SHA-256 hash of the wasm
+/// code and `salt` must be between 1 and 64 bytes long.
+pub fn instantiate2_address(checksum: &[u8], creator: &SeiCanonicalAddr, salt: &[u8]) -> StdResult<SeiCanonicalAddr> {
+	if checksum.len() != 32 {
+		return Err(StdError::generic_err(
+			"instantiate2_address: checksum must be 32 bytes long",
+		));
+	}
+	if salt.is_empty() || salt.len() > 64 {
+		return Err(StdError::generic_err(
+			"instantiate2_address: salt must be between 1 and 64 bytes long",
+		));
+	}
+	let creator = creator.as_slice();
+	// ADR-028 module address of the wasm module, the trailing 0 is the length of the (unused) init msg.
+	let address: [u8; 32] = Sha256::new()
+		.chain_update(Sha256::digest(b"module"))
+		.chain_update(b"wasm\0")
+		.chain_update((checksum.len() as u64).to_be_bytes())
+		.chain_update(checksum)
+		.chain_update((creator.len() as u64).to_be_bytes())
+		.chain_update(creator)
+		.chain_update((salt.len() as u64).to_be_bytes())
+		.chain_update(salt)
+		.chain_update(0u64.to_be_bytes())
+		.finalize()
+		.into();
+	Ok(address.into())
+}
+
+/// Builds a `WasmMsg::Instantiate2` message, returning it along with the address the new contract will have.
+///
+/// `creator` must be the address of the contract sending the message.
+#[cfg(feature = "cosmwasm_1_2")]
+#[allow(clippy::too_many_arguments)]
+pub fn build_instantiate2_msg<T: Serialize>(
+	creator: &SeiCanonicalAddr,
+	checksum: &[u8],
+	code_id: u64,
+	salt: &[u8],
+	msg: &T,
+	admin: Option<String>,
+	funds: Vec<Coin>,
+	label: impl Into<String>,
+) -> StdResult<(SeiCanonicalAddr, WasmMsg)> {
+	let address = instantiate2_address(checksum, creator, salt)?;
+	Ok((
+		address,
+		WasmMsg::Instantiate2 {
+			admin,
+			code_id,
+			label: label.into(),
+			msg: to_json_binary(msg)?,
+			funds,
+			salt: Binary::from(salt),
+		},
+	))
+}
+
+/// Formats bytes as lowercase, 0x-prefixed hex. This is the format [attr_bytes] uses.
+pub fn hex_display(bytes: &[u8]) -> impl fmt::Display + '_ {
+	HexDisplay {
+		bytes,
+		head: bytes.len(),
+		tail: 0,
+	}
+}
+
+/// Like [hex_display], but only shows the first `head` and last `tail` bytes if there are more, e.g. `0x1234…abcd`.
+/// Intended for log messages and errors, use [hex_display] for values which have to be parsed.
+pub fn short_hex(bytes: &[u8], head: usize, tail: usize) -> impl fmt::Display + '_ {
+	if bytes.len() <= head.saturating_add(tail) {
+		HexDisplay {
+			bytes,
+			head: bytes.len(),
+			tail: 0,
+		}
+	} else {
+		HexDisplay { bytes, head, tail }
+	}
+}
+
+struct HexDisplay<'a> {
+	bytes: &'a [u8],
+	head: usize,
+	tail: usize,
+}
+impl fmt::Display for HexDisplay<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("0x")?;
+		for byte in &self.bytes[..self.head] {
+			write!(f, "{byte:02x}")?;
+		}
+		if self.head + self.tail < self.bytes.len() {
+			f.write_str("…")?;
+		}
+		for byte in &self.bytes[self.bytes.len() - self.tail..] {
+			write!(f, "{byte:02x}")?;
+		}
+		Ok(())
+	}
+}
+
+/// Formats bytes as standard (padded) base64, the same as [Binary] is serialized to JSON.
+pub fn b64_display(bytes: &[u8]) -> impl fmt::Display + '_ {
+	struct Base64Display<'a>(&'a [u8]);
+	impl fmt::Display for Base64Display<'_> {
+		fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+			f.write_str(&Binary::from(self.0).to_base64())
+		}
+	}
+	Base64Display(bytes)
+}
+
+/// Creates an event attribute for a byte value, which is always encoded as lowercase 0x-prefixed hex so that indexers
+/// only have to deal with one format.
+pub fn attr_bytes(key: impl Into<String>, bytes: &[u8]) -> Attribute {
+	Attribute::new(key, hex_display(bytes).to_string())
+}
+
+/// Parses hex with or without a `0x` prefix, intended for message inputs. Both upper and lowercase are accepted.
+///
+/// Use `HexBinary::from(parse_hex_binary(value)?)` if you need a [HexBinary](cosmwasm_std::HexBinary), as its own
+/// parsing doesn't accept the prefix.
+pub fn parse_hex_binary(value: &str) -> Result<Vec<u8>, StdError> {
+	let unprefixed = value.strip_prefix("0x").unwrap_or(value);
+	Vec::from_hex(unprefixed).map_err(|err| StdError::parse_err("Vec<u8>", format!("parse_hex_binary: {err}")))
+}
+
+/// Returns whether [escape_key_bytes] leaves the byte as-is.
+#[inline]
+fn is_unescaped_key_byte(byte: u8) -> bool {
+	byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+/// Escapes a storage key (or any other bytes) into printable ASCII, for debug dumps and exports.
+///
+/// ASCII letters, digits and `-._~` are kept as-is, every other byte becomes `%XX` with uppercase hex, similar to URL
+/// percent-encoding. Each byte string has exactly one escaped form and [unescape_key_bytes] is its inverse, so keys
+/// can be compared and parsed back exactly. This format is stable, as indexers parse it.
+pub fn escape_key_bytes(bytes: &[u8]) -> String {
+	const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+	let mut result = String::with_capacity(bytes.len());
+	for &byte in bytes {
+		if is_unescaped_key_byte(byte) {
+			result.push(byte as char);
+		} else {
+			result.push('%');
+			result.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+			result.push(HEX_DIGITS[(byte & 0xf) as usize] as char);
+		}
+	}
+	result
+}
+
+/// Parses the output of [escape_key_bytes]. Anything it wouldn't have output (e.g. lowercase hex or an unescaped `/`)
+/// is rejected, so that every byte string has only one valid escaped form.
+pub fn unescape_key_bytes(escaped: &str) -> Result<Vec<u8>, StdError> {
+	let parse_err = |msg: String| StdError::parse_err("Vec<u8>", format!("unescape_key_bytes: {msg}"));
+	let hex_digit = |digit: u8| match digit {
+		b'0'..=b'9' => Some(digit - b'0'),
+		b'A'..=b'F' => Some(digit - b'A' + 10),
+		_ => None,
+	};
+	let escaped = escaped.as_bytes();
+	let mut result = Vec::with_capacity(escaped.len());
+	let mut i = 0;
+	while i < escaped.len() {
+		let byte = escaped[i];
+		if is_unescaped_key_byte(byte) {
+			result.push(byte);
+			i += 1;
+			continue;
+		}
+		if byte != b'%' {
+			return Err(parse_err(format!("unexpected character at {i}")));
+		}
+		let Some((high, low)) = escaped
+			.get(i + 1..i + 3)
+			.and_then(|digits| Some((hex_digit(digits[0])?, hex_digit(digits[1])?)))
+		else {
+			return Err(parse_err(format!("invalid escape sequence at {i}")));
+		};
+		let unescaped = high << 4 | low;
+		if is_unescaped_key_byte(unescaped) {
+			return Err(parse_err(format!("unnecessary escape sequence at {i}")));
+		}
+		result.push(unescaped);
+		i += 3;
+	}
+	Ok(result)
+}
+
+/// The largest integer which can be represented exactly by an `f64`, and therefore by JavaScript numbers.
+pub const MAX_SAFE_JSON_INTEGER: u64 = (1 << 53) - 1;
+
+/// Serializes the value as canonical JSON (RFC 8785), so that the same value always results in the same bytes no
+/// matter which language or library produced them. This is what signed JSON payloads should be hashed as.
+///
+/// Object keys are sorted (by their UTF-16 code units, as JavaScript does) and there's no whitespace. Strings only
+/// escape `"`, `\` and control characters. Numbers are formatted the same way as JavaScript's `Number.toString`.
+///
+/// Integers which aren't within ±[MAX_SAFE_JSON_INTEGER] are rejected, as JavaScript would silently round them. Use
+/// types which serialize as strings for large values instead, e.g. [Uint128] or [Uint64](cosmwasm_std::Uint64).
+pub fn to_canonical_json<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, StdError> {
+	let value = serde_json::to_value(value)
+		.map_err(|err| StdError::serialize_err(std::any::type_name::<T>(), format!("to_canonical_json: {err}")))?;
+	let mut output = Vec::new();
+	write_canonical_json(&mut output, &value)?;
+	Ok(output)
+}
+
+/// Returns the SHA-256 hash of the value's [canonical JSON](to_canonical_json).
+pub fn canonical_json_hash_sha256<T: Serialize + ?Sized>(value: &T) -> Result<[u8; 32], StdError> {
+	Ok(Sha256::digest(to_canonical_json(value)?).into())
+}
+
+/// Returns the keccak256 hash of the value's [canonical JSON](to_canonical_json).
+pub fn canonical_json_hash_keccak<T: Serialize + ?Sized>(value: &T) -> Result<[u8; 32], StdError> {
 	let mut hasher = tiny_keccak::Keccak::v256();
-	hasher.update(addr_hex.as_bytes());
-	hasher.finalize(&mut hash);
+	hasher.update(&to_canonical_json(value)?);
+	let mut result = [0u8; 32];
+	hasher.finalize(&mut result);
+	Ok(result)
+}
 
-	// If of hex hash is > "7" then print in upper case
-	for hash_index in 0..20usize {
-		let mut addr_index = hash_index * 2;
-		let mut hash_byte = hash[hash_index];
-		if hash_byte > 0x70 {
-			// SAFTY: We asserted that addr_hex.is_ascii() and is 40 bytes long
-			unsafe {
-				let addr_char_as_uppercase = addr_hex.as_bytes().get_unchecked(addr_index).to_ascii_uppercase();
-				*addr_hex.as_bytes_mut().get_unchecked_mut(addr_index) = addr_char_as_uppercase;
+fn write_canonical_json(output: &mut Vec<u8>, value: &Value) -> Result<(), StdError> {
+	match value {
+		Value::Null => output.extend_from_slice(b"null"),
+		Value::Bool(value) => output.extend_from_slice(if *value { b"true" } else { b"false" }),
+		Value::Number(number) => {
+			if let Some(int) = number.as_i64() {
+				if int.unsigned_abs() > MAX_SAFE_JSON_INTEGER {
+					return Err(unsafe_json_integer(int));
+				}
+				output.extend_from_slice(int.to_string().as_bytes());
+			} else if let Some(int) = number.as_u64() {
+				// Only integers larger than i64::MAX get here
+				return Err(unsafe_json_integer(int));
+			} else {
+				let float = number.as_f64().expect("serde_json numbers are either i64, u64 or f64");
+				write_canonical_json_float(output, float);
 			}
 		}
-		hash_byte &= 0x0f;
-		addr_index += 1;
-		if hash_byte > 0x07 {
-			// SAFTY: We asserted that addr_hex.is_ascii() and is 40 bytes long
-			unsafe {
-				let addr_char_as_uppercase = addr_hex.as_bytes().get_unchecked(addr_index).to_ascii_uppercase();
-				*addr_hex.as_bytes_mut().get_unchecked_mut(addr_index) = addr_char_as_uppercase;
+		Value::String(string) => write_canonical_json_string(output, string),
+		Value::Array(values) => {
+			output.push(b'[');
+			for (i, value) in values.iter().enumerate() {
+				if i != 0 {
+					output.push(b',');
+				}
+				write_canonical_json(output, value)?;
 			}
+			output.push(b']');
+		}
+		Value::Object(map) => {
+			let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+			entries.sort_unstable_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+			output.push(b'{');
+			for (i, (key, value)) in entries.into_iter().enumerate() {
+				if i != 0 {
+					output.push(b',');
+				}
+				write_canonical_json_string(output, key);
+				output.push(b':');
+				write_canonical_json(output, value)?;
+			}
+			output.push(b'}');
 		}
 	}
 	Ok(())
 }
+
+fn unsafe_json_integer(int: impl fmt::Display) -> StdError {
+	StdError::serialize_err(
+		"integer",
+		format!("to_canonical_json: {int} can't be represented exactly by JSON numbers, serialize it as a string"),
+	)
+}
+
+fn write_canonical_json_string(output: &mut Vec<u8>, string: &str) {
+	output.push(b'"');
+	for char in string.chars() {
+		match char {
+			'"' => output.extend_from_slice(b"\\\""),
+			'\\' => output.extend_from_slice(b"\\\\"),
+			'\u{08}' => output.extend_from_slice(b"\\b"),
+			'\t' => output.extend_from_slice(b"\\t"),
+			'\n' => output.extend_from_slice(b"\\n"),
+			'\u{0c}' => output.extend_from_slice(b"\\f"),
+			'\r' => output.extend_from_slice(b"\\r"),
+			'\u{00}'..='\u{1f}' => output.extend_from_slice(format!("\\u{:04x}", char as u32).as_bytes()),
+			char => output.extend_from_slice(char.encode_utf8(&mut [0; 4]).as_bytes()),
+		}
+	}
+	output.push(b'"');
+}
+
+/// Formats the float the same way as JavaScript's `Number.toString` (ECMA-262 section 7.1.12.1).
+fn write_canonical_json_float(output: &mut Vec<u8>, float: f64) {
+	if float == 0.0 {
+		// Includes -0
+		output.push(b'0');
+		return;
+	}
+	if float < 0.0 {
+		output.push(b'-');
+	}
+	// Rust also uses the shortest digits which round-trip, only the placement of the decimal point differs
+	let scientific = format!("{:e}", float.abs());
+	let (mantissa, exponent) = scientific.split_once('e').expect("LowerExp output has an exponent");
+	let digits = mantissa.replace('.', "");
+	let k = digits.len() as i32;
+	// The position of the decimal point relative to the start of the digits
+	let n = exponent.parse::<i32>().expect("LowerExp exponent is an integer") + 1;
+	let digits = digits.as_bytes();
+	if k <= n && n <= 21 {
+		output.extend_from_slice(digits);
+		output.resize(output.len() + (n - k) as usize, b'0');
+	} else if 0 < n && n <= 21 {
+		output.extend_from_slice(&digits[..n as usize]);
+		output.push(b'.');
+		output.extend_from_slice(&digits[n as usize..]);
+	} else if -6 < n && n <= 0 {
+		output.extend_from_slice(b"0.");
+		output.resize(output.len() + (-n) as usize, b'0');
+		output.extend_from_slice(digits);
+	} else {
+		output.push(digits[0]);
+		if k > 1 {
+			output.push(b'.');
+			output.extend_from_slice(&digits[1..]);
+		}
+		output.extend_from_slice(format!("e{}{}", if n > 0 { "+" } else { "-" }, (n - 1).abs()).as_bytes());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cosmwasm_std::CanonicalAddr;
+
+	use super::*;
+
+	const CHECKSUM: [u8; 32] = [0x13; 32];
+
+	/// From the EIP-55 test cases
+	const CHECKSUMMED_ADDRESSES: [&str; 4] = [
+		"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+		"0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+		"0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+		"0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+	];
+
+	#[test]
+	fn ethereum_address_checksums() {
+		for addr in CHECKSUMMED_ADDRESSES {
+			let mut lowercase = addr.to_ascii_lowercase();
+			checksumify_ethereum_address(&mut lowercase).unwrap();
+			assert_eq!(lowercase, addr);
+			let addr_bytes = parse_ethereum_address(addr).unwrap();
+			assert_eq!(parse_ethereum_address_checked(addr).unwrap(), addr_bytes);
+			// No checksum provided
+			assert_eq!(
+				parse_ethereum_address_checked(&addr.to_ascii_lowercase()).unwrap(),
+				addr_bytes
+			);
+			let uppercase = format!("0x{}", addr[2..].to_ascii_uppercase());
+			assert_eq!(parse_ethereum_address_checked(&uppercase).unwrap(), addr_bytes);
+			assert_eq!(validate_ethereum_address_str(&uppercase).unwrap(), addr);
+		}
+
+		// A single flipped character
+		assert!(parse_ethereum_address_checked("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD").is_err());
+		assert!(parse_ethereum_address_checked("0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_err());
+		assert!(validate_ethereum_address_str("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD").is_err());
+		// Not hex, or the wrong length
+		assert!(parse_ethereum_address_checked("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeg").is_err());
+		assert!(parse_ethereum_address_checked("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA").is_err());
+		assert!(parse_ethereum_address_checked("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_err());
+	}
+
+	#[test]
+	fn evm_words() {
+		assert_eq!(uint128_to_evm_word(Uint128::zero()), [0; 32]);
+		assert_eq!(evm_word_to_uint128(&[0; 32]).unwrap(), Uint128::zero());
+		let max_word = uint128_to_evm_word(Uint128::MAX);
+		assert_eq!(max_word, [[0; 16], [0xff; 16]].concat().as_slice());
+		assert_eq!(evm_word_to_uint128(&max_word).unwrap(), Uint128::MAX);
+		assert_eq!(uint256_to_evm_word(Uint256::from(Uint128::MAX)), max_word);
+
+		let overflowing_amount = Uint256::from(Uint128::MAX) + Uint256::one();
+		let overflowing_word = uint256_to_evm_word(overflowing_amount);
+		assert_eq!(overflowing_word[15], 1);
+		assert_eq!(
+			evm_word_to_uint128(&overflowing_word).unwrap_err(),
+			ConversionOverflowError::new("Uint256", "Uint128", overflowing_amount).into()
+		);
+		assert_eq!(evm_word_to_uint256(&overflowing_word).unwrap(), overflowing_amount);
+		assert!(evm_word_to_uint128(&max_word[1..]).is_err());
+		assert!(evm_word_to_uint256(&[0; 33]).is_err());
+	}
+
+	#[test]
+	fn evm_word_addresses() {
+		let mut word = [0u8; 32];
+		word[12..].copy_from_slice(&[0xab; 20]);
+		assert_eq!(evm_word_to_address(&word).unwrap(), [0xab; 20]);
+		word[11] = 1;
+		assert!(evm_word_to_address(&word).is_err());
+	}
+
+	fn assert_matches_cosmwasm(creator: SeiCanonicalAddr, salt: &[u8]) {
+		let expected = cosmwasm_std::instantiate2_address(&CHECKSUM, &CanonicalAddr::from(creator), salt).unwrap();
+		assert_eq!(
+			CanonicalAddr::from(instantiate2_address(&CHECKSUM, &creator, salt).unwrap()),
+			expected
+		);
+	}
+
+	#[test]
+	fn instantiate2_address_derivation() {
+		// 20 byte creator (EOA-style)
+		assert_matches_cosmwasm(SeiCanonicalAddr::from([0xab; 20]), b"salt");
+		// 32 byte creator (contract)
+		assert_matches_cosmwasm(SeiCanonicalAddr::from([0xcd; 32]), b"salt");
+		assert_matches_cosmwasm(SeiCanonicalAddr::from([0xcd; 32]), &[0xff; 64]);
+
+		let creator = SeiCanonicalAddr::from([0xab; 20]);
+		assert!(instantiate2_address(&CHECKSUM, &creator, b"").is_err());
+		assert!(instantiate2_address(&CHECKSUM, &creator, &[0; 65]).is_err());
+		assert!(instantiate2_address(&CHECKSUM[1..], &creator, b"salt").is_err());
+	}
+
+	#[cfg(feature = "cosmwasm_1_2")]
+	#[test]
+	fn instantiate2_msg() {
+		let creator = SeiCanonicalAddr::from([0xcd; 32]);
+		let (address, msg) =
+			build_instantiate2_msg(&creator, &CHECKSUM, 7, b"pair", &"hi", None, vec![], "pair").unwrap();
+		assert_eq!(address, instantiate2_address(&CHECKSUM, &creator, b"pair").unwrap());
+		let WasmMsg::Instantiate2 { code_id, salt, msg, .. } = msg else {
+			panic!("expected an Instantiate2 message");
+		};
+		assert_eq!(code_id, 7);
+		assert_eq!(salt, Binary::from(b"pair"));
+		assert_eq!(msg, Binary::from(b"\"hi\""));
+		assert!(build_instantiate2_msg(&creator, &CHECKSUM, 7, &[0; 65], &"hi", None, vec![], "pair").is_err());
+	}
+
+	#[test]
+	fn byte_displays() {
+		let bytes = [0x12, 0x34, 0x56, 0x78, 0xab, 0xcd];
+		assert_eq!(hex_display(&bytes).to_string(), "0x12345678abcd");
+		assert_eq!(hex_display(b"").to_string(), "0x");
+		assert_eq!(short_hex(&bytes, 2, 2).to_string(), "0x1234…abcd");
+		assert_eq!(short_hex(&bytes, 3, 0).to_string(), "0x123456…");
+		assert_eq!(short_hex(&bytes, 0, 1).to_string(), "0x…cd");
+		// Nothing to hide
+		assert_eq!(short_hex(&bytes, 3, 3).to_string(), "0x12345678abcd");
+		assert_eq!(short_hex(&bytes, usize::MAX, usize::MAX).to_string(), "0x12345678abcd");
+		assert_eq!(b64_display(b"hello").to_string(), "aGVsbG8=");
+		assert_eq!(attr_bytes("key", &[0xff, 0x00]), Attribute::new("key", "0xff00"));
+	}
+
+	#[test]
+	fn hex_parsing() {
+		let bytes = [0x00, 0x12, 0xab, 0xcd];
+		assert_eq!(parse_hex_binary(&hex_display(&bytes).to_string()).unwrap(), bytes);
+		assert_eq!(parse_hex_binary("0012ABcd").unwrap(), bytes);
+		assert!(parse_hex_binary("0x").unwrap().is_empty());
+		assert!(parse_hex_binary("").unwrap().is_empty());
+		assert!(parse_hex_binary("0x123").is_err());
+		assert!(parse_hex_binary("abc").is_err());
+		assert!(parse_hex_binary("0xzz").is_err());
+		assert!(parse_hex_binary("0x0x12").is_err());
+	}
+
+	#[test]
+	fn key_escaping() {
+		let all_bytes: Vec<u8> = (0..=255).collect();
+		// This is parsed by indexers, so it must never change
+		let escaped_bytes: Vec<String> = all_bytes.iter().map(|byte| escape_key_bytes(&[*byte])).collect();
+		assert_eq!(
+			escaped_bytes.concat(),
+			"%00%01%02%03%04%05%06%07%08%09%0A%0B%0C%0D%0E%0F%10%11%12%13%14%15%16%17%18%19%1A%1B%1C%1D%1E%1F\
+			%20%21%22%23%24%25%26%27%28%29%2A%2B%2C-.%2F0123456789%3A%3B%3C%3D%3E%3F%40ABCDEFGHIJKLMNOPQRSTU\
+			VWXYZ%5B%5C%5D%5E_%60abcdefghijklmnopqrstuvwxyz%7B%7C%7D~%7F%80%81%82%83%84%85%86%87%88%89%8A%8B\
+			%8C%8D%8E%8F%90%91%92%93%94%95%96%97%98%99%9A%9B%9C%9D%9E%9F%A0%A1%A2%A3%A4%A5%A6%A7%A8%A9%AA%AB\
+			%AC%AD%AE%AF%B0%B1%B2%B3%B4%B5%B6%B7%B8%B9%BA%BB%BC%BD%BE%BF%C0%C1%C2%C3%C4%C5%C6%C7%C8%C9%CA%CB\
+			%CC%CD%CE%CF%D0%D1%D2%D3%D4%D5%D6%D7%D8%D9%DA%DB%DC%DD%DE%DF%E0%E1%E2%E3%E4%E5%E6%E7%E8%E9%EA%EB\
+			%EC%ED%EE%EF%F0%F1%F2%F3%F4%F5%F6%F7%F8%F9%FA%FB%FC%FD%FE%FF"
+		);
+		assert_eq!(escape_key_bytes(&all_bytes), escaped_bytes.concat());
+		assert_eq!(escape_key_bytes(b"\x00\x04usei/a%"), "%00%04usei%2Fa%25");
+		assert_eq!(escape_key_bytes(b""), "");
+
+		// Every 1 and 2 byte string round-trips, which also means no two of them are escaped the same way
+		for first in 0..=255u8 {
+			assert_eq!(unescape_key_bytes(&escape_key_bytes(&[first])).unwrap(), [first]);
+			for second in 0..=255u8 {
+				assert_eq!(
+					unescape_key_bytes(&escape_key_bytes(&[first, second])).unwrap(),
+					[first, second]
+				);
+			}
+		}
+		assert_eq!(unescape_key_bytes(&escape_key_bytes(&all_bytes)).unwrap(), all_bytes);
+
+		for invalid in ["/", "%2f", "%41", "%", "%2", "%G0", "é", "a b"] {
+			assert!(unescape_key_bytes(invalid).is_err(), "{invalid}");
+		}
+	}
+
+	#[test]
+	// The RFC's test vector has more digits than an f64
+	#[allow(clippy::excessive_precision)]
+	fn canonical_json_rfc8785() {
+		let canonical = |json: &str| {
+			String::from_utf8(to_canonical_json(&serde_json::from_str::<Value>(json).unwrap()).unwrap()).unwrap()
+		};
+		// RFC 8785 section 3.2.2.3, serde_json's float parsing isn't exact so the numbers aren't parsed from JSON
+		let sample = serde_json::json!({
+			"numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+			"string": "\u{20ac}$\u{000F}\u{000a}A'\u{0042}\u{0022}\u{005c}\\\"/",
+			"literals": [null, true, false]
+		});
+		assert_eq!(
+			String::from_utf8(to_canonical_json(&sample).unwrap()).unwrap(),
+			r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+		);
+		// RFC 8785 section 3.2.3, keys are sorted by UTF-16 code units rather than code points
+		assert_eq!(
+			canonical(r#"{"\u20ac": 1, "\r": 2, "\ufb33": 3, "1": 4, "\ud83d\ude00": 5, "\u0080": 6, "\u00f6": 7}"#),
+			"{\"\\r\":2,\"1\":4,\"\u{80}\":6,\"ö\":7,\"€\":1,\"😀\":5,\"\u{fb33}\":3}"
+		);
+		// Number formatting, as output by JavaScript
+		for (number, expected) in [
+			(1e21, "1e+21"),
+			(1e-7, "1e-7"),
+			(123e-9, "1.23e-7"),
+			(5e-324, "5e-324"),
+			(f64::MAX, "1.7976931348623157e+308"),
+			(-0.5, "-0.5"),
+			(-0.0, "0"),
+			(100.0, "100"),
+			(1234567.125, "1234567.125"),
+		] {
+			assert_eq!(
+				String::from_utf8(to_canonical_json(&number).unwrap()).unwrap(),
+				expected
+			);
+		}
+		assert_eq!(to_canonical_json(&MAX_SAFE_JSON_INTEGER).unwrap(), b"9007199254740991");
+		assert_eq!(
+			to_canonical_json(&-(MAX_SAFE_JSON_INTEGER as i64)).unwrap(),
+			b"-9007199254740991"
+		);
+		assert!(to_canonical_json(&(MAX_SAFE_JSON_INTEGER + 1)).is_err());
+		assert!(to_canonical_json(&i64::MIN).is_err());
+		assert!(to_canonical_json(&u64::MAX).is_err());
+	}
+
+	#[test]
+	fn canonical_json_struct() {
+		use std::collections::BTreeMap;
+
+		#[derive(Serialize)]
+		struct SignedOrder {
+			owner: String,
+			amount: Uint128,
+			routes: BTreeMap<String, BTreeMap<String, u32>>,
+			ratio: f64,
+			memo: String,
+			expires: Option<u64>,
+			nonce: u64,
+		}
+		let routes = [
+			("uusdc", vec![("pool-2", 30), ("pool-10", 5)]),
+			("€", vec![("b", 1)]),
+			("😀", vec![]),
+			("\u{fb33}", vec![("a", 0)]),
+			("Z", vec![("z", 2), ("a", 1)]),
+		];
+		let order = SignedOrder {
+			owner: "sei1nduq8yy8h4nr7g9vuuglzklqatmaquq9xw6h5x".into(),
+			amount: Uint128::MAX,
+			routes: routes
+				.into_iter()
+				.map(|(denom, pools)| {
+					let pools = pools.into_iter().map(|(pool, fee)| (pool.to_string(), fee)).collect();
+					(denom.to_string(), pools)
+				})
+				.collect(),
+			ratio: 0.000001234,
+			memo: "line\nbreak \"quoted\" \u{1} \\ /".into(),
+			expires: None,
+			nonce: MAX_SAFE_JSON_INTEGER,
+		};
+		// Output of the RFC 8785 reference implementation (JSON.stringify with sorted keys) for the same object
+		let expected = r#"{"amount":"340282366920938463463374607431768211455","expires":null,"memo":"line\nbreak \"quoted\" \u0001 \\ /","nonce":9007199254740991,"owner":"sei1nduq8yy8h4nr7g9vuuglzklqatmaquq9xw6h5x","ratio":0.000001234,"routes":{"Z":{"a":1,"z":2},"uusdc":{"pool-10":5,"pool-2":30},"€":{"b":1},"😀":{},"דּ":{"a":0}}}"#;
+		assert_eq!(String::from_utf8(to_canonical_json(&order).unwrap()).unwrap(), expected);
+		assert_eq!(
+			canonical_json_hash_sha256(&order).unwrap(),
+			<[u8; 32]>::from(Sha256::digest(expected.as_bytes()))
+		);
+		let mut keccak = tiny_keccak::Keccak::v256();
+		keccak.update(expected.as_bytes());
+		let mut expected_keccak = [0u8; 32];
+		keccak.finalize(&mut expected_keccak);
+		assert_eq!(canonical_json_hash_keccak(&order).unwrap(), expected_keccak);
+	}
+}
@@ -1,4 +1,5 @@
 // Because cosmwasm_std is riddled with needless copies and pointers which point to nothing.
 pub mod addr;
+pub mod debug;
 pub mod memory;
 pub mod storage;
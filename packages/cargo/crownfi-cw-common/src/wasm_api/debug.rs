@@ -0,0 +1,14 @@
+use super::memory::ConstRegion;
+use std::ptr;
+
+extern "C" {
+	#[link_name = "debug"]
+	fn wasmvm_debug(source_ptr: usize);
+}
+
+/// Writes a message to the VM's debug output, which is only shown by nodes with debug logging enabled.
+pub fn debug(message: &str) {
+	let message_region = ConstRegion::new(message.as_bytes());
+	// SAFTY: It is assumed that the VM only reads from the message_region.
+	unsafe { wasmvm_debug(ptr::from_ref(&message_region) as usize) };
+}
@@ -0,0 +1,100 @@
+//! Uses only what's available without the `sei` feature, `check.sh` runs this with `--no-default-features` to make
+//! sure the crate stays usable on other chains.
+use cosmwasm_std::{
+	coin, coins,
+	testing::{MockQuerier, MOCK_CONTRACT_ADDR},
+	to_json_binary, Addr, BankMsg, ContractResult, CosmosMsg, Empty, MemoryStorage, QuerierWrapper, SystemResult,
+	Uint128, WasmMsg, WasmQuery,
+};
+use crownfi_cw_common::{
+	data_types::{
+		asset::{FungibleAsset, FungibleAssetKindString},
+		canonical_addr::SeiCanonicalAddr,
+	},
+	storage::{base::ThreadStorageGuard, map::StoredMap, vec::StoredVec},
+};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg};
+
+type TestingResult = Result<(), Box<dyn std::error::Error>>;
+
+#[test]
+fn storage_containers() -> TestingResult {
+	let _storage = ThreadStorageGuard::new(Box::new(MemoryStorage::new()));
+	let map = StoredMap::<SeiCanonicalAddr, u64>::new(b"map");
+	let addr = SeiCanonicalAddr::from([7u8; 32]);
+	map.set(&addr, &42)?;
+	assert_eq!(map.get(&addr)?.as_deref(), Some(&42));
+	assert_eq!(map.iter()?.count(), 1);
+
+	let mut vec = StoredVec::<u32>::new(b"vec");
+	vec.extend([1, 2, 3].into_iter())?;
+	assert_eq!(vec.len(), 3);
+	assert_eq!(vec.pop()?.as_deref(), Some(&3));
+	assert_eq!(
+		vec.iter()
+			.map(|value| value.map(|value| *value))
+			.collect::<Result<Vec<_>, _>>()?,
+		[1, 2]
+	);
+	Ok(())
+}
+
+#[test]
+fn fungible_assets() -> TestingResult {
+	let to = Addr::unchecked("recipient");
+	let native = FungibleAsset::from(coin(5, "uatom"));
+	assert_eq!(
+		native.transfer_to_msg_without_evm::<Empty>(&to)?,
+		CosmosMsg::Bank(BankMsg::Send {
+			to_address: "recipient".into(),
+			amount: coins(5, "uatom"),
+		})
+	);
+	let cw20 = FungibleAsset::CW20(Cw20Coin {
+		address: "token".into(),
+		amount: Uint128::new(7),
+	});
+	assert_eq!(
+		cw20.transfer_to_msg_without_evm::<Empty>(&to)?,
+		CosmosMsg::Wasm(WasmMsg::Execute {
+			contract_addr: "token".into(),
+			msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+				recipient: "recipient".into(),
+				amount: Uint128::new(7),
+			})?,
+			funds: vec![],
+		})
+	);
+	let erc20 = FungibleAssetKindString::ERC20("0x3894085ef7ff0f0aedf52e2a2704928d1ec074f1".into());
+	assert!(erc20
+		.clone()
+		.into_asset(1u128)
+		.transfer_to_msg_without_evm::<Empty>(&to)
+		.is_err());
+
+	let mut querier = MockQuerier::<Empty>::new(&[(MOCK_CONTRACT_ADDR, &coins(100, "uatom"))]);
+	querier.update_wasm(|query| {
+		let WasmQuery::Smart { contract_addr, .. } = query else {
+			panic!("unexpected query");
+		};
+		assert_eq!(contract_addr, "token");
+		SystemResult::Ok(ContractResult::Ok(
+			to_json_binary(&BalanceResponse {
+				balance: Uint128::new(9),
+			})
+			.unwrap(),
+		))
+	});
+	let querier = QuerierWrapper::<Empty>::new(&querier);
+	let holder = Addr::unchecked(MOCK_CONTRACT_ADDR);
+	assert_eq!(
+		FungibleAssetKindString::Native("uatom".into()).query_balance_without_evm(&querier, &holder)?,
+		Uint128::new(100)
+	);
+	assert_eq!(
+		FungibleAssetKindString::CW20("token".into()).query_balance_without_evm(&querier, &holder)?,
+		Uint128::new(9)
+	);
+	assert!(erc20.query_balance_without_evm(&querier, &holder).is_err());
+	Ok(())
+}
@@ -0,0 +1,15 @@
+use crownfi_cw_common::impl_coded_error;
+
+#[derive(Debug)]
+enum PoolError {
+	Unauthorized,
+	InsufficientFunds { requested: u128 },
+}
+impl std::fmt::Display for PoolError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{self:?}")
+	}
+}
+impl_coded_error!(PoolError { Unauthorized = 1001 });
+
+fn main() {}
@@ -0,0 +1,13 @@
+use bytemuck::{Pod, Zeroable};
+use crownfi_cw_common::assert_pod_layout;
+
+// `back` used to be declared after `front`
+#[derive(Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct QueueEnds {
+	pub back: u32,
+	pub front: u32,
+}
+assert_pod_layout!(QueueEnds, size = 8, align = 4, fields = [front: 0, back: 4]);
+
+fn main() {}
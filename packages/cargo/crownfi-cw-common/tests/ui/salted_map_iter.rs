@@ -0,0 +1,7 @@
+use cosmwasm_std::testing::mock_env;
+use crownfi_cw_common::storage::salted_map::SaltedMap;
+
+fn main() {
+	let map = SaltedMap::<String, u64>::new(b"listed", &mock_env()).unwrap();
+	for _ in map.iter() {}
+}
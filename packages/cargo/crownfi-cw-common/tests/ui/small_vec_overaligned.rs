@@ -0,0 +1,14 @@
+use bytemuck::{Pod, Zeroable};
+use crownfi_cw_common::{impl_small_vec_item, storage::small_vec::PodSmallVec};
+
+// Padding between PodSmallVec's 16 byte header and the elements would be uninitialized
+#[derive(Clone, Copy, Zeroable, Pod)]
+#[repr(C, align(32))]
+pub struct Wide {
+	pub bytes: [u8; 32],
+}
+impl_small_vec_item!(Wide);
+
+fn main() {
+	let _ = PodSmallVec::<Wide, 2>::new();
+}
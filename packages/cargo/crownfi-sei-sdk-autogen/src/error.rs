@@ -15,11 +15,6 @@ pub enum SdkMakerError {
 	#[cfg(not(target_family = "wasm"))]
 	#[error("\"json2ts\" wasn't found: {0} (Try \"npm install -g json-schema-to-typescript\")")]
 	Json2TsNotFound(which::Error),
-	#[cfg(target_family = "wasm")]
-	#[error(
-		"The \"which\" crate this depends upon doesn't know how to search for executable programs in a wasm space"
-	)]
-	Json2TsNotFound(()),
 	#[error("{0} is not an enum. (Must be made up of subschemas using one_of)")]
 	MsgTypeNotEnum(String),
 	#[error("{0} has a malformed enum variant: {1}")]
@@ -30,4 +25,10 @@ pub enum SdkMakerError {
 	UnknownEnumVariantField(String, String, String),
 	#[error("{0}::{1}.{2} is defined as an array/tuple of multiple types, which this tool currently cannot handle.")]
 	EnumVariantFieldHasMultiTypedArray(String, String, String),
+	#[error("{0} is referenced but not defined in the schema")]
+	UndefinedType(String),
+	#[error("No contract named {0} has been added")]
+	UnknownContract(String),
+	#[error("Types are nested more than {0} levels deep, an example can't be generated")]
+	FixtureTooDeep(usize),
 }
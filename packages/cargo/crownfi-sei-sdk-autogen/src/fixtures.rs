@@ -0,0 +1,525 @@
+use schemars::schema::{InstanceType, Schema, SchemaObject, SingleOrVec};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+#[cfg(not(target_family = "wasm"))]
+use serde::de::DeserializeOwned;
+#[cfg(not(target_family = "wasm"))]
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	error::SdkMakerError,
+	sdk_maker::{CrownfiSdkMaker, SdkModel},
+	struct_extentions::{SchemaStructExtentions, SingleOrVecStructExtentions},
+};
+
+/// Recursive types can't be expanded forever, a type nesting deeper than this is most likely unrepresentable.
+const MAX_EXAMPLE_DEPTH: usize = 64;
+
+/// Generates example JSON instances of the contract messages from their schemas, for use as golden files which assert
+/// that the wire format doesn't change accidentally.
+///
+/// Only required fields are filled in, with placeholder values appropriate for their types. Enums are represented by
+/// their first variant, except for the message types themselves, of which every variant gets its own fixture. The
+/// fixtures of each contract are laid out as `<contract>/<message kind>/<variant>.json`, where the message kind is one
+/// of `instantiate`, `execute`, `query`, `migrate`, `sudo`, `cw20_hook`, or `query_responses`. Messages which aren't
+/// enums are named after their type instead of a variant.
+///
+/// Output is deterministic, so the fixtures can be committed and compared against in CI.
+pub struct FixtureWriter {
+	model: SdkModel,
+}
+
+impl FixtureWriter {
+	/// Creates a fixture writer for all the contracts added to the SDK maker.
+	pub fn new(sdk_maker: &CrownfiSdkMaker) -> Self {
+		Self::from_model(sdk_maker.export_model())
+	}
+
+	pub fn from_model(model: SdkModel) -> Self {
+		Self { model }
+	}
+
+	/// Returns the fixtures, mapping their paths to their contents.
+	pub fn generate_in_memory(&self) -> Result<BTreeMap<String, String>, SdkMakerError> {
+		let mut files = BTreeMap::new();
+		for (contract_name, contract) in self.model.contracts.iter() {
+			for (kind, msg_type_name) in [
+				("instantiate", &contract.instantiate_type),
+				("execute", &contract.execute_type),
+				("query", &contract.query_type),
+				("migrate", &contract.migrate_type),
+				("sudo", &contract.sudo_type),
+				("cw20_hook", &contract.cw20_hook_type),
+			] {
+				let Some(msg_type_name) = msg_type_name else {
+					continue;
+				};
+				for (variant, example) in self.message_examples(msg_type_name)? {
+					files.insert(
+						format!("{contract_name}/{kind}/{variant}.json"),
+						fixture_contents(&example)?,
+					);
+				}
+			}
+			for (query_variant, response_type_name) in contract.query_responses.iter() {
+				let example = self.example_for_definition(response_type_name, 0)?;
+				files.insert(
+					format!("{contract_name}/query_responses/{query_variant}.json"),
+					fixture_contents(&example)?,
+				);
+			}
+		}
+		Ok(files)
+	}
+
+	/// Writes the fixtures into the specified directory, creating it and its subdirectories as needed.
+	#[cfg(not(target_family = "wasm"))]
+	pub fn write_to_dir<P: Into<PathBuf>>(&self, out_dir: P) -> Result<(), SdkMakerError> {
+		let out_dir: PathBuf = out_dir.into();
+		for (file_name, contents) in self.generate_in_memory()? {
+			let output_path = out_dir.join(file_name);
+			if let Some(parent) = output_path.parent() {
+				fs::create_dir_all(parent)?;
+			}
+			fs::write(output_path, contents)?;
+		}
+		Ok(())
+	}
+
+	fn definition(&self, type_name: &str) -> Result<&Schema, SdkMakerError> {
+		self.model
+			.root_schema
+			.definitions
+			.get(type_name)
+			.ok_or_else(|| SdkMakerError::UndefinedType(type_name.to_string()))
+	}
+
+	/// Returns an example of every variant of the message, or a single example named after the type if it isn't an
+	/// enum.
+	fn message_examples(&self, msg_type_name: &str) -> Result<Vec<(String, Value)>, SdkMakerError> {
+		let Some(msg_type_def) = self.definition(msg_type_name)?.as_object() else {
+			return Ok(Vec::new());
+		};
+		if let Some(enum_values) = msg_type_def.enum_values.as_ref() {
+			return Ok(variant_values(enum_values));
+		}
+		let Some(variant_defs) = msg_type_def
+			.subschemas
+			.as_ref()
+			.and_then(|subschemas| subschemas.one_of.as_ref())
+		else {
+			return Ok(vec![(
+				msg_type_name.to_string(),
+				self.example_for_definition(msg_type_name, 0)?,
+			)]);
+		};
+		let mut examples = Vec::new();
+		for (index, variant_def) in variant_defs.iter().enumerate() {
+			if let Some(enum_values) = variant_def.as_object().and_then(|def| def.enum_values.as_ref()) {
+				examples.extend(variant_values(enum_values));
+				continue;
+			}
+			let example = self.example_for_schema(variant_def, 1)?;
+			let variant = match example.as_object() {
+				Some(object) if object.len() == 1 => object.keys().next().expect("len is 1").clone(),
+				_ => index.to_string(),
+			};
+			examples.push((variant, example));
+		}
+		Ok(examples)
+	}
+
+	fn example_for_definition(&self, type_name: &str, depth: usize) -> Result<Value, SdkMakerError> {
+		if let Some(placeholder) = well_known_placeholder(type_name) {
+			return Ok(placeholder);
+		}
+		self.example_for_schema(self.definition(type_name)?, depth + 1)
+	}
+
+	fn example_for_schema(&self, schema: &Schema, depth: usize) -> Result<Value, SdkMakerError> {
+		if depth > MAX_EXAMPLE_DEPTH {
+			return Err(SdkMakerError::FixtureTooDeep(MAX_EXAMPLE_DEPTH));
+		}
+		let Some(schema) = schema.as_object() else {
+			return Ok(Value::Null);
+		};
+		if let Some(reference) = schema.reference.as_ref() {
+			let type_name = reference.strip_prefix("#/definitions/").unwrap_or(reference);
+			return self.example_for_definition(type_name, depth);
+		}
+		if let Some(const_value) = schema.const_value.as_ref() {
+			return Ok(const_value.clone());
+		}
+		if let Some(first_value) = schema.enum_values.as_ref().and_then(|values| values.first()) {
+			return Ok(first_value.clone());
+		}
+		if let Some(subschemas) = schema.subschemas.as_ref() {
+			let first_subschema = subschemas
+				.one_of
+				.as_ref()
+				.and_then(|one_of| one_of.first())
+				.or_else(|| {
+					// Option<T> is represented as anyOf [T, null]
+					subschemas
+						.any_of
+						.as_ref()
+						.and_then(|any_of| any_of.iter().find(|schema| !is_null_schema(schema)))
+				})
+				.or_else(|| subschemas.all_of.as_ref().and_then(|all_of| all_of.first()));
+			if let Some(first_subschema) = first_subschema {
+				return self.example_for_schema(first_subschema, depth + 1);
+			}
+		}
+		let instance_type = schema.instance_type.as_ref().and_then(|instance_type| {
+			instance_type
+				.iter()
+				.find(|instance_type| **instance_type != InstanceType::Null)
+				.or_else(|| instance_type.get_first())
+		});
+		Ok(match instance_type {
+			None | Some(InstanceType::Null) => Value::Null,
+			Some(InstanceType::Boolean) => Value::Bool(false),
+			Some(InstanceType::Integer) | Some(InstanceType::Number) => Value::from(1),
+			Some(InstanceType::String) => Value::from("string"),
+			Some(InstanceType::Array) => self.example_array(schema, depth)?,
+			Some(InstanceType::Object) => self.example_object(schema, depth)?,
+		})
+	}
+
+	fn example_array(&self, schema: &SchemaObject, depth: usize) -> Result<Value, SdkMakerError> {
+		let Some(items) = schema.array.as_ref().and_then(|array| array.items.as_ref()) else {
+			return Ok(Value::Array(Vec::new()));
+		};
+		Ok(Value::Array(match items {
+			// A single element is enough to show the element type
+			SingleOrVec::Single(item) => vec![self.example_for_schema(item, depth + 1)?],
+			// Tuples
+			SingleOrVec::Vec(items) => items
+				.iter()
+				.map(|item| self.example_for_schema(item, depth + 1))
+				.collect::<Result<_, _>>()?,
+		}))
+	}
+
+	fn example_object(&self, schema: &SchemaObject, depth: usize) -> Result<Value, SdkMakerError> {
+		let mut fields = Map::new();
+		let Some(object) = schema.object.as_ref() else {
+			return Ok(Value::Object(fields));
+		};
+		for (field_name, field_schema) in object.properties.iter() {
+			if object.required.contains(field_name) {
+				fields.insert(field_name.clone(), self.example_for_schema(field_schema, depth + 1)?);
+			}
+		}
+		Ok(Value::Object(fields))
+	}
+}
+
+/// Placeholders for cosmwasm types whose schema is just "string", but which require a specific format.
+fn well_known_placeholder(type_name: &str) -> Option<Value> {
+	match type_name {
+		"Uint64" | "Uint128" | "Uint256" | "Uint512" | "Int64" | "Int128" | "Int256" | "Int512" | "Decimal"
+		| "Decimal256" | "SignedDecimal" | "SignedDecimal256" => Some(Value::from("1")),
+		"Binary" | "HexBinary" => Some(Value::from("")),
+		_ => None,
+	}
+}
+
+fn is_null_schema(schema: &Schema) -> bool {
+	schema.as_object().is_some_and(|schema| {
+		schema
+			.instance_type
+			.as_ref()
+			.and_then(|instance_type| instance_type.as_single())
+			== Some(&InstanceType::Null)
+	})
+}
+
+fn variant_values(enum_values: &[Value]) -> Vec<(String, Value)> {
+	enum_values
+		.iter()
+		.filter_map(|value| Some((value.as_str()?.to_string(), value.clone())))
+		.collect()
+}
+
+fn fixture_contents(example: &Value) -> Result<String, SdkMakerError> {
+	let mut contents = serde_json::to_string_pretty(example)?;
+	contents.push('\n');
+	Ok(contents)
+}
+
+/// Asserts that every `.json` file in `dir` deserializes into `T`, which fails on missing fields, and also on unknown
+/// fields if `T` denies them (as `#[cw_serde]` types do).
+///
+/// Intended to be used in contract tests with the output of [FixtureWriter]. Panics with the file and the path to the
+/// offending field if any fixture doesn't deserialize, or if there are no fixtures.
+#[cfg(not(target_family = "wasm"))]
+pub fn assert_fixtures_deserialize<T: DeserializeOwned>(dir: impl AsRef<Path>) {
+	let dir = dir.as_ref();
+	let mut fixture_paths: Vec<_> = fs::read_dir(dir)
+		.unwrap_or_else(|err| panic!("couldn't read fixtures directory {}: {err}", dir.display()))
+		.map(|entry| entry.expect("couldn't read fixtures directory entry").path())
+		.filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+		.collect();
+	assert!(!fixture_paths.is_empty(), "no fixtures found in {}", dir.display());
+	fixture_paths.sort();
+	let errors: Vec<_> = fixture_paths
+		.iter()
+		.filter_map(|path| check_fixture::<T>(path).err())
+		.collect();
+	assert!(
+		errors.is_empty(),
+		"fixtures failed to deserialize:\n{}",
+		errors.join("\n")
+	);
+}
+
+/// Same as [assert_fixtures_deserialize], but for a single file, e.g. a query response.
+#[cfg(not(target_family = "wasm"))]
+pub fn assert_fixture_deserializes<T: DeserializeOwned>(path: impl AsRef<Path>) {
+	if let Err(err) = check_fixture::<T>(path.as_ref()) {
+		panic!("fixture failed to deserialize:\n{err}");
+	}
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn check_fixture<T: DeserializeOwned>(path: &Path) -> Result<(), String> {
+	let contents =
+		fs::read_to_string(path).map_err(|err| format!("{}: couldn't read fixture: {err}", path.display()))?;
+	serde_json::from_str::<T>(&contents).map(|_| ()).map_err(|err| {
+		format!(
+			"{}: {} (at {})",
+			path.display(),
+			err,
+			json_path_at(&contents, err.line(), err.column())
+		)
+	})
+}
+
+enum PathSegment {
+	Field {
+		name: Option<String>,
+		expecting_value: bool,
+	},
+	Index(usize),
+}
+
+/// Returns the path (e.g. `$.do_thing.amounts[1]`) of the value being parsed at the position serde_json reports errors
+/// at. The JSON up to that point is assumed to be well-formed.
+fn json_path_at(json: &str, line: usize, column: usize) -> String {
+	let mut segments = Vec::new();
+	let mut chars = json
+		.split_inclusive('\n')
+		.take(line)
+		.enumerate()
+		.flat_map(|(line_index, line_str)| {
+			// serde_json reports the column of the last character it has read
+			let len = if line_index + 1 == line {
+				column.min(line_str.len())
+			} else {
+				line_str.len()
+			};
+			line_str[..len].chars()
+		});
+	while let Some(char) = chars.next() {
+		match char {
+			'{' => segments.push(PathSegment::Field {
+				name: None,
+				expecting_value: false,
+			}),
+			'[' => segments.push(PathSegment::Index(0)),
+			'}' | ']' => {
+				segments.pop();
+			}
+			':' => {
+				if let Some(PathSegment::Field { expecting_value, .. }) = segments.last_mut() {
+					*expecting_value = true;
+				}
+			}
+			',' => match segments.last_mut() {
+				Some(PathSegment::Field { expecting_value, .. }) => *expecting_value = false,
+				Some(PathSegment::Index(index)) => *index += 1,
+				None => {}
+			},
+			'"' => {
+				let mut string = String::new();
+				while let Some(char) = chars.next() {
+					match char {
+						'"' => break,
+						'\\' => {
+							string.push(char);
+							string.extend(chars.next());
+						}
+						_ => string.push(char),
+					}
+				}
+				if let Some(PathSegment::Field { name, expecting_value }) = segments.last_mut() {
+					if !*expecting_value {
+						*name = Some(string);
+					}
+				}
+			}
+			_ => {}
+		}
+	}
+	let mut path = String::from("$");
+	for segment in segments {
+		match segment {
+			PathSegment::Field { name: Some(name), .. } => {
+				path.push('.');
+				path.push_str(&name);
+			}
+			PathSegment::Field { name: None, .. } => {}
+			PathSegment::Index(index) => path.push_str(&format!("[{index}]")),
+		}
+	}
+	path
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use cosmwasm_schema::{cw_serde, QueryResponses};
+	use cosmwasm_std::{Binary, Uint128};
+
+	#[cw_serde]
+	pub struct FixtureInstantiateMsg {
+		pub admin: String,
+		pub fee: Option<Uint128>,
+	}
+
+	#[cw_serde]
+	pub enum FixtureSide {
+		Bid,
+		Ask,
+	}
+
+	#[cw_serde]
+	pub struct FixtureOrder {
+		pub side: FixtureSide,
+		pub amounts: Vec<Uint128>,
+	}
+
+	#[cw_serde]
+	pub enum FixtureExecuteMsg {
+		PlaceOrders {
+			orders: Vec<FixtureOrder>,
+			memo: Option<String>,
+		},
+		Reset {},
+		Pause,
+		SetPayload {
+			payload: Binary,
+			enabled: bool,
+		},
+	}
+
+	#[cw_serde]
+	#[derive(QueryResponses)]
+	pub enum FixtureQueryMsg {
+		#[returns(FixtureOrder)]
+		Order { id: u64 },
+	}
+
+	#[cw_serde]
+	pub enum FixtureEmptyMsg {}
+
+	fn fixture_writer() -> FixtureWriter {
+		let mut sdk_maker = CrownfiSdkMaker::new();
+		sdk_maker
+			.add_contract::<
+				FixtureInstantiateMsg,
+				FixtureExecuteMsg,
+				FixtureQueryMsg,
+				FixtureEmptyMsg,
+				FixtureEmptyMsg,
+				FixtureEmptyMsg,
+			>("order_book")
+			.unwrap();
+		FixtureWriter::new(&sdk_maker)
+	}
+
+	fn temp_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("crownfi-fixtures-test-{name}-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		dir
+	}
+
+	#[test]
+	fn generation_is_deterministic() {
+		let files = fixture_writer().generate_in_memory().unwrap();
+		assert_eq!(files, fixture_writer().generate_in_memory().unwrap());
+		assert_eq!(
+			files.keys().collect::<Vec<_>>(),
+			[
+				"order_book/execute/pause.json",
+				"order_book/execute/place_orders.json",
+				"order_book/execute/reset.json",
+				"order_book/execute/set_payload.json",
+				"order_book/instantiate/FixtureInstantiateMsg.json",
+				"order_book/query/order.json",
+				"order_book/query_responses/order.json",
+			]
+		);
+		assert_eq!(
+			files["order_book/execute/place_orders.json"],
+			"{\n  \"place_orders\": {\n    \"orders\": [\n      {\n        \"amounts\": [\n          \"1\"\n        ],\n        \"side\": \"bid\"\n      }\n    ]\n  }\n}\n"
+		);
+
+		let out_dir = temp_dir("deterministic");
+		fixture_writer().write_to_dir(&out_dir).unwrap();
+		for (file_name, contents) in files.iter() {
+			assert_eq!(&fs::read_to_string(out_dir.join(file_name)).unwrap(), contents);
+		}
+		assert_fixtures_deserialize::<FixtureExecuteMsg>(out_dir.join("order_book/execute"));
+		assert_fixtures_deserialize::<FixtureQueryMsg>(out_dir.join("order_book/query"));
+		assert_fixtures_deserialize::<FixtureInstantiateMsg>(out_dir.join("order_book/instantiate"));
+		assert_fixture_deserializes::<FixtureOrder>(out_dir.join("order_book/query_responses/order.json"));
+		fs::remove_dir_all(&out_dir).unwrap();
+	}
+
+	#[test]
+	fn broken_fixtures_fail() {
+		let out_dir = temp_dir("broken");
+		fixture_writer().write_to_dir(&out_dir).unwrap();
+		let place_orders_path = out_dir.join("order_book/execute/place_orders.json");
+		let broken_fixture = fs::read_to_string(&place_orders_path)
+			.unwrap()
+			.replace("\"side\": \"bid\"", "\"sides\": \"bid\"");
+		fs::write(&place_orders_path, broken_fixture).unwrap();
+		let err = check_fixture::<FixtureExecuteMsg>(&place_orders_path).unwrap_err();
+		assert!(
+			err.starts_with(&format!("{}: unknown field `sides`", place_orders_path.display())),
+			"{err}"
+		);
+		assert!(err.ends_with("(at $.place_orders.orders[0].sides)"), "{err}");
+
+		let reset_path = out_dir.join("order_book/execute/set_payload.json");
+		fs::write(&reset_path, "{\"set_payload\": {\"enabled\": true}}").unwrap();
+		let err = check_fixture::<FixtureExecuteMsg>(&reset_path).unwrap_err();
+		assert!(err.contains("missing field `payload`"), "{err}");
+		assert!(err.ends_with("(at $.set_payload)"), "{err}");
+
+		let panic_message = std::panic::catch_unwind(|| {
+			assert_fixtures_deserialize::<FixtureExecuteMsg>(out_dir.join("order_book/execute"))
+		})
+		.unwrap_err();
+		let panic_message = panic_message.downcast_ref::<String>().unwrap();
+		assert!(panic_message.contains("place_orders.json"), "{panic_message}");
+		assert!(panic_message.contains("set_payload.json"), "{panic_message}");
+		assert!(!panic_message.contains("reset.json"), "{panic_message}");
+		fs::remove_dir_all(&out_dir).unwrap();
+	}
+
+	#[test]
+	fn json_paths() {
+		let json = "{\"a\": [1, {\"b\": \"x,y\", \"c\": [";
+		assert_eq!(json_path_at(json, 1, json.len()), "$.a[1].c[0]");
+		assert_eq!(json_path_at(json, 1, 8), "$.a[0]");
+		assert_eq!(json_path_at("{\"a\\\"\": {}", 1, 10), "$.a\\\"");
+	}
+}
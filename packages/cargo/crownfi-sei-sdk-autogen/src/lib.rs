@@ -1,6 +1,16 @@
 mod error;
+mod fixtures;
+mod manifest;
 mod sdk_maker;
 mod strings_for_code;
 mod struct_extentions;
+mod types_emitter;
+#[cfg(feature = "wasm")]
+mod wasm;
 
-pub use sdk_maker::CrownfiSdkMaker;
+pub use error::SdkMakerError;
+pub use fixtures::FixtureWriter;
+#[cfg(not(target_family = "wasm"))]
+pub use fixtures::{assert_fixture_deserializes, assert_fixtures_deserialize};
+pub use manifest::{ContractDeployment, ContractManifestEntry, DeployManifest};
+pub use sdk_maker::{CrownfiSdkMaker, GeneratedSdk, SdkContractModel, SdkModel, SDK_MODEL_FORMAT_VERSION};
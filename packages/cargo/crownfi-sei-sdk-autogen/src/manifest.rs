@@ -0,0 +1,184 @@
+use convert_case::{Case, Casing};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, io::Write};
+
+use crate::error::SdkMakerError;
+
+/// Where a contract is deployed on a network. Both fields are left out until the contract is uploaded/instantiated.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractDeployment {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub code_id: Option<u64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub address: Option<String>,
+}
+
+/// A contract's entry in the [DeployManifest].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractManifestEntry {
+	/// The snake_case name the contract was added to the SDK maker with.
+	pub name: String,
+	/// The name of the contract's generated TypeScript class.
+	pub class_name: String,
+	/// Keyed by network ID, e.g. `pacific-1`.
+	pub networks: BTreeMap<String, ContractDeployment>,
+}
+
+/// The deploy manifest as read by the generated `getContractClient`, keyed by the contracts' snake_case names.
+///
+/// The JSON representation of this is the same as the `DeployManifest` type in the generated `manifest.ts`, so deploy
+/// scripts can read and update a manifest which was started from [CrownfiSdkMaker::deploy_manifest].
+///
+/// [CrownfiSdkMaker::deploy_manifest]: crate::CrownfiSdkMaker::deploy_manifest
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(transparent)]
+pub struct DeployManifest {
+	pub contracts: BTreeMap<String, ContractManifestEntry>,
+}
+
+impl DeployManifest {
+	/// Creates a manifest with an empty deployment for each of the contracts on each of the networks.
+	pub fn placeholder<'a>(contract_names: impl IntoIterator<Item = &'a str>, networks: &[impl AsRef<str>]) -> Self {
+		Self {
+			contracts: contract_names
+				.into_iter()
+				.map(|contract_name| {
+					let entry = ContractManifestEntry {
+						name: contract_name.to_string(),
+						class_name: contract_class_name(contract_name),
+						networks: networks
+							.iter()
+							.map(|network| (network.as_ref().to_string(), ContractDeployment::default()))
+							.collect(),
+					};
+					(contract_name.to_string(), entry)
+				})
+				.collect(),
+		}
+	}
+}
+
+/// The name of the generated TypeScript class for the contract.
+pub(crate) fn contract_class_name(contract_name: &str) -> String {
+	[&contract_name.to_case(Case::Pascal), "Contract"].concat()
+}
+
+/// Writes the body of `manifest.ts`, which has the placeholder manifest and a factory for the contract classes.
+pub(crate) fn codegen_manifest(
+	output: &mut impl Write,
+	manifest: &DeployManifest,
+	networks: &[impl AsRef<str>],
+) -> Result<(), SdkMakerError> {
+	writeln!(output, "import {{QueryClient}} from \"@cosmjs/stargate\";")?;
+	writeln!(output, "import {{WasmExtension}} from \"@cosmjs/cosmwasm-stargate\";")?;
+	for (contract_name, entry) in manifest.contracts.iter() {
+		writeln!(
+			output,
+			"import {{{}}} from \"./{}.js\";",
+			entry.class_name,
+			contract_name.escape_default()
+		)?;
+	}
+	writeln!(output)?;
+	writeln!(
+		output,
+		"export type DeployNetworkId = {};",
+		networks
+			.iter()
+			.map(|network| format!("\"{}\"", network.as_ref().escape_default()))
+			.collect::<Vec<_>>()
+			.join(" | ")
+	)?;
+	writeln!(output, "export interface ContractDeployment {{")?;
+	writeln!(output, "\tcodeId?: number;")?;
+	writeln!(output, "\taddress?: string;")?;
+	writeln!(output, "}}")?;
+	writeln!(output, "export interface ContractManifestEntry {{")?;
+	writeln!(output, "\tname: string;")?;
+	writeln!(output, "\tclassName: string;")?;
+	writeln!(output, "\tnetworks: Record<DeployNetworkId, ContractDeployment>;")?;
+	writeln!(output, "}}")?;
+	writeln!(output, "export type DeployContractName = keyof typeof contractClasses;")?;
+	writeln!(
+		output,
+		"export type DeployManifest = Record<DeployContractName, ContractManifestEntry>;"
+	)?;
+	writeln!(output)?;
+
+	for (contract_name, entry) in manifest.contracts.iter() {
+		writeln!(
+			output,
+			"export const {}Manifest: ContractManifestEntry = {};",
+			contract_name.to_case(Case::Camel),
+			serde_json::to_string(entry)?
+		)?;
+	}
+	writeln!(output, "export const deployManifest: DeployManifest = {{")?;
+	for contract_name in manifest.contracts.keys() {
+		writeln!(
+			output,
+			"\t\"{}\": {}Manifest,",
+			contract_name.escape_default(),
+			contract_name.to_case(Case::Camel)
+		)?;
+	}
+	writeln!(output, "}};")?;
+	writeln!(output)?;
+
+	writeln!(output, "const contractClasses = {{")?;
+	for (contract_name, entry) in manifest.contracts.iter() {
+		writeln!(
+			output,
+			"\t\"{}\": {},",
+			contract_name.escape_default(),
+			entry.class_name
+		)?;
+	}
+	writeln!(output, "}};")?;
+	writeln!(
+		output,
+		"export interface ContractClients<Q extends QueryClient & WasmExtension> {{"
+	)?;
+	for (contract_name, entry) in manifest.contracts.iter() {
+		writeln!(
+			output,
+			"\t\"{}\": {}<Q>;",
+			contract_name.escape_default(),
+			entry.class_name
+		)?;
+	}
+	writeln!(output, "}}")?;
+	writeln!(output)?;
+
+	writeln!(output, "/**")?;
+	writeln!(
+		output,
+		" * Creates the client for the named contract, using its address on the network in the manifest."
+	)?;
+	writeln!(output, " *")?;
+	writeln!(
+		output,
+		" * @throws if the manifest doesn't have an address for the contract on the network"
+	)?;
+	writeln!(output, " */")?;
+	writeln!(
+		output,
+		"export function getContractClient<N extends DeployContractName, Q extends QueryClient & WasmExtension>(network: DeployNetworkId, name: N, client: Q, manifest: DeployManifest = deployManifest): ContractClients<Q>[N] {{"
+	)?;
+	writeln!(output, "\tconst address = manifest[name]?.networks[network]?.address;")?;
+	writeln!(output, "\tif (address == null) {{")?;
+	writeln!(
+		output,
+		"\t\tthrow new Error(\"The deploy manifest has no address for the \" + name + \" contract on \" + network);"
+	)?;
+	writeln!(output, "\t}}")?;
+	writeln!(
+		output,
+		"\treturn new contractClasses[name](client, address) as ContractClients<Q>[N];"
+	)?;
+	writeln!(output, "}}")?;
+	Ok(())
+}
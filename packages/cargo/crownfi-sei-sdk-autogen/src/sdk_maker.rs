@@ -1,33 +1,34 @@
 use convert_case::{Case, Casing};
 use cosmwasm_schema::QueryResponses;
 use itertools::Itertools;
-use lazy_regex::regex;
 use schemars::{
 	schema::{InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec},
 	schema_for, JsonSchema,
 };
+use serde::{Deserialize, Serialize};
 use std::{
 	collections::{BTreeMap, BTreeSet, HashMap},
-	fs,
 	io::Write,
-	path::PathBuf,
-	process::{Command, Stdio},
 	rc::Rc,
 	sync::{Arc, OnceLock},
 };
 
 #[cfg(not(target_family = "wasm"))]
-use which::which;
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
+};
 
-#[cfg(target_family = "wasm")]
-fn which(_: &str) -> Result<PathBuf, ()> {
-	Err(())
-}
+#[cfg(not(target_family = "wasm"))]
+use which::which;
 
 use crate::{
 	error::SdkMakerError,
+	manifest::{codegen_manifest, contract_class_name, DeployManifest},
 	strings_for_code::{make_type_name, schema_type_string, MethodArgType, MethodGenType},
 	struct_extentions::{SchemaStructExtentions, SingleOrVecStructExtentions},
+	types_emitter::{self, metadata_doc, write_doc_comment},
 };
 
 const TYPESCRIPT_OUTPUT_DISCLAIMER_COMMENT: &'static str = "/* eslint-disable */
@@ -47,6 +48,10 @@ fn type_to_module() -> &'static HashMap<Arc<str>, Arc<str>> {
 		m.insert("WasmExtension".into(), "@cosmjs/cosmwasm-stargate".into());
 		m.insert("ExecuteInstruction".into(), "@cosmjs/cosmwasm-stargate".into());
 		m.insert("Coin".into(), "@cosmjs/amino".into());
+		m.insert("toBase64".into(), "@cosmjs/encoding".into());
+		m.insert("fromBase64".into(), "@cosmjs/encoding".into());
+		m.insert("toUtf8".into(), "@cosmjs/encoding".into());
+		m.insert("fromUtf8".into(), "@cosmjs/encoding".into());
 		// put Addr and shit here
 		m
 	})
@@ -56,13 +61,46 @@ fn default_module() -> &'static Arc<str> {
 	VALUE.get_or_init(|| Arc::from("./types.js"))
 }
 
-#[derive(Debug)]
+/// The generated TypeScript SDK, mapping file names to their contents.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeneratedSdk {
+	files: BTreeMap<String, String>,
+}
+impl GeneratedSdk {
+	pub fn get(&self, file_name: &str) -> Option<&str> {
+		self.files.get(file_name).map(String::as_str)
+	}
+	pub fn files(&self) -> &BTreeMap<String, String> {
+		&self.files
+	}
+	pub fn into_files(self) -> BTreeMap<String, String> {
+		self.files
+	}
+	/// Writes all the files into the specified directory, creating it if it doesn't exist.
+	#[cfg(not(target_family = "wasm"))]
+	pub fn write_to_dir<P: Into<PathBuf>>(&self, out_dir: P) -> Result<(), SdkMakerError> {
+		let mut output_path: PathBuf = out_dir.into();
+		fs::create_dir_all(&output_path)?;
+		for (file_name, contents) in self.files.iter() {
+			output_path.push(file_name);
+			let mut out_file = fs::File::create(&output_path)?;
+			output_path.pop();
+			out_file.write_all(contents.as_bytes())?;
+			out_file.sync_all()?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CrownfiSdkMaker {
 	root_schema: RootSchema,
 	contracts: BTreeMap<Rc<str>, ContractSdkContractDefinition>,
+	#[serde(default)]
+	networks: Vec<Rc<str>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContractSdkContractDefinition {
 	pub instantiate_type: Option<Rc<str>>,
 	pub execute_type: Option<Rc<str>>,
@@ -72,6 +110,13 @@ pub struct ContractSdkContractDefinition {
 	pub sudo_type: Option<Rc<str>>,
 	pub cw20_hook_type: Option<Rc<str>>,
 	pub name_and_version: Option<(Rc<str>, Rc<str>)>,
+	/// Suggested gas limits, keyed by the (snake_case) execute or cw20 hook variant, see
+	/// [CrownfiSdkMaker::set_gas_hints].
+	#[serde(default)]
+	pub gas_hints: BTreeMap<Arc<str>, u64>,
+	/// The codes of the contract's error variants, see [CrownfiSdkMaker::add_contract_errors].
+	#[serde(default)]
+	pub error_codes: BTreeMap<Arc<str>, u32>,
 }
 impl ContractSdkContractDefinition {
 	pub fn new(dummy_schema: &RootSchema, name_and_version: Option<(Rc<str>, Rc<str>)>) -> Self {
@@ -91,6 +136,8 @@ impl ContractSdkContractDefinition {
 		};
 		ContractSdkContractDefinition {
 			name_and_version,
+			gas_hints: BTreeMap::new(),
+			error_codes: BTreeMap::new(),
 			instantiate_type: dummy_schema.schema.object.as_ref().and_then(|obj| {
 				obj.properties
 					.get("instantiate")
@@ -148,6 +195,7 @@ impl CrownfiSdkMaker {
 		let mut seyulf = Self {
 			root_schema: RootSchema::default(),
 			contracts: BTreeMap::new(),
+			networks: Vec::new(),
 		};
 
 		// Assemble the bare minimum schema
@@ -168,7 +216,7 @@ impl CrownfiSdkMaker {
 	>(
 		&mut self,
 		snake_case_name: &str,
-		name_and_version: Option<(Rc<str>, Rc<str>)>
+		name_and_version: Option<(Rc<str>, Rc<str>)>,
 	) -> Result<&mut Self, SdkMakerError> {
 		if !snake_case_name.is_case(Case::Snake) {
 			return Err(SdkMakerError::ContractNameNotSnakeCase);
@@ -220,27 +268,75 @@ impl CrownfiSdkMaker {
 		&mut self,
 		snake_case_name: &str,
 	) -> Result<&mut Self, SdkMakerError> {
-		Self::add_contract_with_version::<
-			InstantiateType,
-			ExecuteType,
-			QueryType,
-			MigrateType,
-			SudoType,
-			Cw20HookType
-		>(
+		Self::add_contract_with_version::<InstantiateType, ExecuteType, QueryType, MigrateType, SudoType, Cw20HookType>(
 			self,
 			snake_case_name,
-			None
+			None,
 		)
 	}
 
-	fn codegen_types(&self, output_path: &mut PathBuf, files_list: &mut Vec<String>) -> Result<(), SdkMakerError> {
+	/// Sets the suggested gas limits of the contract's execute and cw20 hook variants, keyed by their snake_case name.
+	///
+	/// The generated class gets a static `gasHints` record with them, and the `build*Ix` methods of the variants
+	/// return the hint as the instruction's `gasHint`, for the signing layer to use instead of simulating. Variants
+	/// without a hint have neither.
+	pub fn set_gas_hints(
+		&mut self,
+		snake_case_name: &str,
+		gas_hints: &[(&str, u64)],
+	) -> Result<&mut Self, SdkMakerError> {
+		let contract_def = self
+			.contracts
+			.get_mut(snake_case_name)
+			.ok_or_else(|| SdkMakerError::UnknownContract(snake_case_name.to_string()))?;
+		contract_def.gas_hints = gas_hints
+			.iter()
+			.map(|(variant, gas)| (Arc::from(*variant), *gas))
+			.collect();
+		Ok(self)
+	}
+
+	/// Adds the codes of the contract's error variants, as given by the `ERROR_CODES` of an error type which has
+	/// `crownfi_cw_common::impl_coded_error!` applied to it, e.g. `add_contract_errors("pool", PoolError::ERROR_CODES)`.
+	///
+	/// A `{Contract}ErrorCode` enum and a `{Contract}ErrorNames` map from code to variant name are generated with them,
+	/// and the contract class gets a static `errorCodeOf` which reads the code back out of a failed transaction's
+	/// error message.
+	pub fn add_contract_errors(
+		&mut self,
+		snake_case_name: &str,
+		error_codes: &[(&str, u32)],
+	) -> Result<&mut Self, SdkMakerError> {
+		let contract_def = self
+			.contracts
+			.get_mut(snake_case_name)
+			.ok_or_else(|| SdkMakerError::UnknownContract(snake_case_name.to_string()))?;
+		contract_def
+			.error_codes
+			.extend(error_codes.iter().map(|(variant, code)| (Arc::from(*variant), *code)));
+		Ok(self)
+	}
+
+	/// Sets the networks (e.g. `pacific-1`) the contracts are deployed to. If any are set, a `manifest.ts` is generated
+	/// as well, which has a placeholder deploy manifest and a `getContractClient` factory which uses it.
+	pub fn set_networks(&mut self, networks: &[&str]) -> &mut Self {
+		self.networks = networks.iter().map(|network| Rc::from(*network)).collect();
+		self
+	}
+
+	/// Returns the deploy manifest with an empty deployment for each contract on each network, in the same format as
+	/// the one in the generated `manifest.ts`.
+	pub fn deploy_manifest(&self) -> DeployManifest {
+		DeployManifest::placeholder(self.contracts.keys().map(|name| &**name), &self.networks)
+	}
+
+	/// Re-creates `types.ts` with json2ts, which has more complete output than the built-in types emitter
+	#[cfg(not(target_family = "wasm"))]
+	fn codegen_types_json2ts(&self, output_path: &Path) -> Result<(), SdkMakerError> {
 		let json2ts_bin_path = which("json2ts").map_err(|err| SdkMakerError::Json2TsNotFound(err))?;
-		files_list.push("types.ts".into());
-		output_path.push("types.ts");
 		let mut child = Command::new(json2ts_bin_path)
 			.arg("--output")
-			.arg(&output_path)
+			.arg(output_path.join("types.ts"))
 			.arg("--bannerComment")
 			.arg(TYPESCRIPT_OUTPUT_DISCLAIMER_COMMENT)
 			.arg("--unreachableDefinitions")
@@ -249,7 +345,6 @@ impl CrownfiSdkMaker {
 			.arg("false")
 			.stdin(Stdio::piped())
 			.spawn()?;
-		output_path.pop();
 
 		serde_json::to_writer(
 			child
@@ -262,6 +357,139 @@ impl CrownfiSdkMaker {
 		Ok(())
 	}
 
+	/// Generates the TypeScript SDK without touching the filesystem or spawning any processes.
+	///
+	/// The types are generated with the built-in types emitter rather than json2ts. This is the same as generating
+	/// the SDK from the output of [export_model](Self::export_model).
+	pub fn generate_in_memory(&self) -> Result<GeneratedSdk, SdkMakerError> {
+		self.export_model().generate_in_memory()
+	}
+
+	/// Exports the merged schema and contract definitions, for use by SDK generators for other languages.
+	pub fn export_model(&self) -> SdkModel {
+		SdkModel {
+			format_version: SDK_MODEL_FORMAT_VERSION,
+			root_schema: self.root_schema.clone(),
+			contracts: self
+				.contracts
+				.iter()
+				.map(|(contract_name, contract_def)| (contract_name.to_string(), SdkContractModel::from(contract_def)))
+				.collect(),
+			networks: self.networks.iter().map(|network| network.to_string()).collect(),
+		}
+	}
+
+	/// Writes the output of [generate_in_memory](Self::generate_in_memory) to the specified directory.
+	#[cfg(not(target_family = "wasm"))]
+	pub fn generate_code<P: Into<PathBuf>>(&self, out_dir: P) -> Result<(), SdkMakerError> {
+		self.generate_in_memory()?.write_to_dir(out_dir)
+	}
+
+	/// Same as [generate_code](Self::generate_code), but `types.ts` is generated with json2ts.
+	/// (`npm install -g json-schema-to-typescript`)
+	#[cfg(not(target_family = "wasm"))]
+	pub fn generate_code_with_json2ts<P: Into<PathBuf>>(&self, out_dir: P) -> Result<(), SdkMakerError> {
+		let output_path: PathBuf = out_dir.into();
+		self.generate_code(&output_path)?;
+		self.codegen_types_json2ts(&output_path)
+	}
+
+	/// Serializes the schema and contract definitions so they can be loaded elsewhere with
+	/// [from_schema_json](Self::from_schema_json), e.g. by the wasm build of this crate.
+	pub fn to_schema_json(&self) -> Result<String, SdkMakerError> {
+		Ok(serde_json::to_string(self)?)
+	}
+
+	pub fn from_schema_json(schema_json: &str) -> Result<Self, SdkMakerError> {
+		Ok(serde_json::from_str(schema_json)?)
+	}
+}
+
+/// The current value of [SdkModel::format_version].
+pub const SDK_MODEL_FORMAT_VERSION: u32 = 1;
+
+/// Everything the SDK generators need to know about the contracts, for generating SDKs in languages other than
+/// TypeScript. Obtained with [CrownfiSdkMaker::export_model].
+///
+/// The JSON representation of this is considered stable: Fields may be added, but existing fields won't be renamed,
+/// removed, or change their meaning without incrementing `format_version`. Map keys are always sorted, so the output
+/// is deterministic.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SdkModel {
+	pub format_version: u32,
+	/// Contains the definitions of all message and response types, keyed by type name.
+	pub root_schema: RootSchema,
+	/// The contracts, keyed by their snake_case name.
+	pub contracts: BTreeMap<String, SdkContractModel>,
+	/// The networks the contracts are deployed to, see [CrownfiSdkMaker::set_networks].
+	#[serde(default)]
+	pub networks: Vec<String>,
+}
+
+/// A contract's message types, all of which are keys of [SdkModel::root_schema]'s definitions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SdkContractModel {
+	pub instantiate_type: Option<String>,
+	pub execute_type: Option<String>,
+	pub query_type: Option<String>,
+	/// Maps the (snake_case) query variants to the type of their response.
+	pub query_responses: BTreeMap<String, String>,
+	pub migrate_type: Option<String>,
+	pub sudo_type: Option<String>,
+	pub cw20_hook_type: Option<String>,
+	/// The contract name and version, as stored by cw2.
+	pub name_and_version: Option<(String, String)>,
+	/// Suggested gas limits, keyed by the (snake_case) execute or cw20 hook variant.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub gas_hints: BTreeMap<String, u64>,
+	/// The codes of the contract's error variants, keyed by the variant name.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub error_codes: BTreeMap<String, u32>,
+}
+
+impl From<&ContractSdkContractDefinition> for SdkContractModel {
+	fn from(contract_def: &ContractSdkContractDefinition) -> Self {
+		let to_string = |type_name: &Option<Rc<str>>| type_name.as_deref().map(str::to_string);
+		Self {
+			instantiate_type: to_string(&contract_def.instantiate_type),
+			execute_type: to_string(&contract_def.execute_type),
+			query_type: to_string(&contract_def.query_type),
+			query_responses: contract_def
+				.query_enum_varient_to_return_type
+				.iter()
+				.map(|(query_variant, response_type)| (query_variant.to_string(), response_type.to_string()))
+				.collect(),
+			migrate_type: to_string(&contract_def.migrate_type),
+			sudo_type: to_string(&contract_def.sudo_type),
+			cw20_hook_type: to_string(&contract_def.cw20_hook_type),
+			name_and_version: contract_def
+				.name_and_version
+				.as_ref()
+				.map(|(name, version)| (name.to_string(), version.to_string())),
+			gas_hints: contract_def
+				.gas_hints
+				.iter()
+				.map(|(variant, gas)| (variant.to_string(), *gas))
+				.collect(),
+			error_codes: contract_def
+				.error_codes
+				.iter()
+				.map(|(variant, code)| (variant.to_string(), *code))
+				.collect(),
+		}
+	}
+}
+
+impl SdkModel {
+	pub fn to_json(&self) -> Result<String, SdkMakerError> {
+		Ok(serde_json::to_string_pretty(self)?)
+	}
+
+	pub fn from_json(model_json: &str) -> Result<Self, SdkMakerError> {
+		Ok(serde_json::from_str(model_json)?)
+	}
+
+	#[allow(clippy::too_many_arguments)]
 	fn codegen_contract_method(
 		&self,
 		output: &mut impl Write,
@@ -271,11 +499,9 @@ impl CrownfiSdkMaker {
 		msg_enum_varient_fields: MethodArgType,
 		kind: MethodGenType,
 		description: &str,
+		gas_hint: Option<u64>,
 	) -> Result<(), SdkMakerError> {
-		if description.len() > 0 {
-			writeln!(output, "\t/** {0} */", regex!(r"\*/").replace_all(description, "* /"))?;
-		}
-
+		write_doc_comment(output, "\t", description)?;
 		write!(output, "\t{}(", kind.generate_method_name(msg_enum_variant))?;
 		if kind.prepend_extra_args() {
 			output.write_all(kind.extra_func_args().as_bytes())?;
@@ -283,6 +509,15 @@ impl CrownfiSdkMaker {
 		match msg_enum_varient_fields {
 			MethodArgType::None => {}
 			MethodArgType::Object(msg_enum_varient_fields) if msg_enum_varient_fields.properties.len() == 0 => {}
+			MethodArgType::Object(_) if matches!(kind, MethodGenType::Cw20Hook) => {
+				// Typed with the hook message type itself, so the payload can't drift from what the contract parses
+				write!(
+					output,
+					", args: Extract<{0}, {{\"{1}\": unknown}}>[\"{1}\"]",
+					make_type_name(msg_type_name),
+					msg_enum_variant.escape_default()
+				)?;
+			}
 			MethodArgType::Object(msg_enum_varient_fields) => {
 				if kind.prepend_extra_args() {
 					write!(output, ", ")?;
@@ -291,11 +526,8 @@ impl CrownfiSdkMaker {
 
 				let mut fields_iter = msg_enum_varient_fields.properties.iter().peekable();
 				while let Some((key, value)) = fields_iter.next() {
-					if let Some(value_description) = value
-						.as_object()
-						.and_then(|schema| Some(schema.metadata.as_ref()?.as_ref().description.as_deref()?))
-					{
-						write!(output, "\t\t/** {0} */\n", value_description)?;
+					if let Some(value_schema) = value.as_object() {
+						write_doc_comment(output, "\t\t", &metadata_doc(value_schema.metadata.as_deref()))?;
 					}
 					write!(
 						output,
@@ -345,6 +577,8 @@ impl CrownfiSdkMaker {
 
 		if kind.is_query() {
 			writeln!(output, "): Promise<{}> {{", typescript_return_type)?;
+		} else if gas_hint.is_some() {
+			writeln!(output, "): {} & {{gasHint: bigint}} {{", typescript_return_type)?;
 		} else {
 			writeln!(output, "): {} {{", typescript_return_type)?;
 		}
@@ -360,11 +594,97 @@ impl CrownfiSdkMaker {
 			write!(output, "\"{}\"", msg_enum_variant.escape_default())?;
 		}
 		writeln!(output, " satisfies {};", msg_type_name)?;
-		writeln!(output, "\t\treturn {};", kind.parent_func_call())?;
+		if matches!(kind, MethodGenType::Cw20Hook) {
+			Self::codegen_cw20_send(output, required_types, gas_hint)?;
+		} else if let Some(gas_hint) = gas_hint {
+			writeln!(
+				output,
+				"\t\treturn Object.assign({}, {{gasHint: {}n}});",
+				kind.parent_func_call(),
+				gas_hint
+			)?;
+		} else {
+			writeln!(output, "\t\treturn {};", kind.parent_func_call())?;
+		}
 		writeln!(output, "\t}}")?;
 		Ok(())
 	}
 
+	/// Writes the construction of the `Cw20ExecuteMsg::Send` which delivers `msg` to this contract as a cw20 hook.
+	///
+	/// The hook message is encoded as base64 JSON, which must match how the contract decodes it. The Rust equivalent
+	/// is `crownfi_cw_common::data_types::asset::encode_cw20_hook`.
+	fn codegen_cw20_send(
+		output: &mut impl Write,
+		required_types: &mut BTreeSet<Arc<str>>,
+		gas_hint: Option<u64>,
+	) -> Result<(), SdkMakerError> {
+		writeln!(output, "\t\tif (tokenContractOrUnifiedDenom.startsWith(\"erc20/\")) {{")?;
+		writeln!(
+			output,
+			"\t\t\tthrow new Error(\"Cw20 hooks can't be used with ERC20 tokens, send \" + tokenContractOrUnifiedDenom + \" through its CW20 pointer contract instead\");"
+		)?;
+		writeln!(output, "\t\t}}")?;
+		writeln!(output, "\t\treturn {{")?;
+		writeln!(
+			output,
+			"\t\t\tcontractAddress: tokenContractOrUnifiedDenom.startsWith(\"cw20/\") ? tokenContractOrUnifiedDenom.substring(5) : tokenContractOrUnifiedDenom,"
+		)?;
+		write!(
+			output,
+			"\t\t\tmsg: {{\"send\": {{\"contract\": this.address, \"amount\": amount.toString(), \"msg\": toBase64(toUtf8(JSON.stringify(msg)))}}}}"
+		)?;
+		if let Some(gas_hint) = gas_hint {
+			write!(output, ",\n\t\t\tgasHint: {}n", gas_hint)?;
+		}
+		writeln!(output)?;
+		writeln!(output, "\t\t}};")?;
+		for encoding_function in ["toBase64", "toUtf8"] {
+			required_types.insert(encoding_function.into());
+		}
+		Ok(())
+	}
+
+	/// Query variants which take a `BatchQueryMsg` get a method which takes the query messages directly and decodes
+	/// the responses, rather than requiring the caller to do the base64 encoding themselves.
+	fn codegen_batch_query_method(
+		&self,
+		output: &mut impl Write,
+		required_types: &mut BTreeSet<Arc<str>>,
+		msg_type_name: &str,
+		msg_enum_variant: &str,
+		kind: MethodGenType,
+		description: &str,
+	) -> Result<(), SdkMakerError> {
+		write_doc_comment(output, "\t", description)?;
+		let msg_type_name = make_type_name(msg_type_name);
+		let return_type = make_type_name(&kind.return_type(msg_enum_variant)).into_owned();
+		writeln!(
+			output,
+			"\tasync {}(msgs: {}[]): Promise<({{\"Ok\": unknown}} | {{\"Err\": string}})[]> {{",
+			kind.generate_method_name(msg_enum_variant),
+			msg_type_name
+		)?;
+		writeln!(
+			output,
+			"\t\tconst msg = {{\"{}\": {{\"queries\": msgs.map((msg) => toBase64(toUtf8(JSON.stringify(msg))))}}}} satisfies {};",
+			msg_enum_variant.escape_default(),
+			msg_type_name
+		)?;
+		writeln!(output, "\t\tconst result: {} = await this.query(msg);", return_type)?;
+		writeln!(
+			output,
+			"\t\treturn result.responses.map((response) => \"Ok\" in response ? {{\"Ok\": JSON.parse(fromUtf8(fromBase64(response.Ok)))}} : response);"
+		)?;
+		writeln!(output, "\t}}")?;
+		required_types.insert(msg_type_name.into());
+		required_types.insert(return_type.into());
+		for encoding_function in ["toBase64", "fromBase64", "toUtf8", "fromUtf8"] {
+			required_types.insert(encoding_function.into());
+		}
+		Ok(())
+	}
+
 	fn codegen_contract_methods(
 		&self,
 		output: &mut impl Write,
@@ -372,8 +692,16 @@ impl CrownfiSdkMaker {
 		msg_type_name: &str,
 		msg_type_def: &SchemaObject,
 		kind: MethodGenType,
+		gas_hints: &BTreeMap<String, u64>,
 	) -> Result<(), SdkMakerError> {
 		required_types.insert(make_type_name(msg_type_name).into());
+		let gas_hint = |enum_variant: &str| {
+			if kind.is_query() {
+				None
+			} else {
+				gas_hints.get(enum_variant).copied()
+			}
+		};
 
 		let Some(enum_varients_def) = msg_type_def
 			.subschemas
@@ -417,11 +745,7 @@ impl CrownfiSdkMaker {
 								"string enum variant is specified with a non-string value".to_string(),
 							));
 						};
-						let description = enum_varient_def
-							.metadata
-							.as_ref()
-							.and_then(|val| val.as_ref().description.as_deref())
-							.unwrap_or_default();
+						let description = metadata_doc(enum_varient_def.metadata.as_deref());
 						self.codegen_contract_method(
 							output,
 							required_types,
@@ -429,7 +753,8 @@ impl CrownfiSdkMaker {
 							enum_variant,
 							MethodArgType::None,
 							kind,
-							description,
+							&description,
+							gas_hint(enum_variant),
 						)?;
 					}
 				}
@@ -450,11 +775,7 @@ impl CrownfiSdkMaker {
 						.next()
 						.expect("object.properties.len() == 1 should mean at least 1 item is returned");
 
-					let description = enum_varient_def
-						.metadata
-						.as_ref()
-						.and_then(|val| val.as_ref().description.as_deref())
-						.unwrap_or_default();
+					let description = metadata_doc(enum_varient_def.metadata.as_deref());
 
 					// Quick hack, allow enum varients with references to single types
 					if let Some(type_reference) = enum_variant_schema
@@ -467,6 +788,17 @@ impl CrownfiSdkMaker {
 								None
 							}
 						}) {
+						if kind.is_query() && type_reference == "BatchQueryMsg" {
+							self.codegen_batch_query_method(
+								output,
+								required_types,
+								msg_type_name,
+								enum_variant,
+								kind,
+								&description,
+							)?;
+							continue;
+						}
 						self.codegen_contract_method(
 							output,
 							required_types,
@@ -474,7 +806,8 @@ impl CrownfiSdkMaker {
 							enum_variant,
 							MethodArgType::TypeRef(type_reference),
 							kind,
-							description,
+							&description,
+							gas_hint(enum_variant),
 						)?;
 						continue;
 					}
@@ -492,11 +825,7 @@ impl CrownfiSdkMaker {
 						enum_variant_schema.as_object().and_then(|enum_variant_schema| {
 							Some((
 								enum_variant_schema.object.as_ref()?.as_ref(),
-								enum_variant_schema
-									.metadata
-									.as_ref()
-									.and_then(|metadata| metadata.description.as_deref())
-									.unwrap_or_default(),
+								metadata_doc(enum_variant_schema.metadata.as_deref()),
 							))
 						})
 					else {
@@ -513,10 +842,11 @@ impl CrownfiSdkMaker {
 						MethodArgType::Object(enum_variant_schema),
 						kind,
 						if other_description.len() > 0 {
-							other_description
+							&other_description
 						} else {
-							description
+							&description
 						},
+						gas_hint(enum_variant),
 					)?;
 				}
 				_ => {
@@ -529,22 +859,92 @@ impl CrownfiSdkMaker {
 		}
 		Ok(())
 	}
-	fn codegen_contracts(&self, output_path: &mut PathBuf, files_list: &mut Vec<String>) -> Result<(), SdkMakerError> {
+	fn codegen_contracts(
+		&self,
+		files: &mut BTreeMap<String, String>,
+		files_list: &mut Vec<String>,
+	) -> Result<(), SdkMakerError> {
 		let mut types_required = BTreeSet::<Arc<str>>::new();
 		// Creating a temp buffer as we must import the types first and we only know that as we go through the contract
 		let mut contract_body = Vec::<u8>::new();
 		for (contract_name, contract_def) in self.contracts.iter() {
-			let contract_class_name = contract_name.as_ref().to_case(Case::Pascal);
+			let contract_class_name = contract_class_name(contract_name);
+			// Prefix of the `{Contract}ErrorCode` enum and `{Contract}ErrorNames` map
+			let error_type_name = contract_name.to_case(Case::Pascal);
 			types_required.insert("QueryClient".into());
 			types_required.insert("WasmExtension".into());
 			types_required.insert("ContractBase".into());
 			types_required.insert("Coin".into());
 
+			if !contract_def.error_codes.is_empty() {
+				let mut error_codes = contract_def.error_codes.iter().collect::<Vec<_>>();
+				error_codes.sort_by_key(|(_, code)| **code);
+				writeln!(contract_body, "export enum {}ErrorCode {{", error_type_name)?;
+				for (variant, code) in error_codes.iter() {
+					writeln!(contract_body, "\t{} = {},", variant, code)?;
+				}
+				writeln!(contract_body, "}}")?;
+				writeln!(
+					contract_body,
+					"export const {}ErrorNames: ReadonlyMap<number, string> = new Map([{}]);",
+					error_type_name,
+					error_codes
+						.iter()
+						.format_with(", ", |(variant, code), f| f(&format_args!(
+							"[{}, \"{}\"]",
+							code,
+							variant.escape_default()
+						)))
+				)?;
+			}
 			writeln!(
 				contract_body,
-				"export class {}Contract<Q extends QueryClient & WasmExtension> extends ContractBase<Q> {{",
+				"export class {}<Q extends QueryClient & WasmExtension> extends ContractBase<Q> {{",
 				contract_class_name
 			)?;
+			writeln!(
+				contract_body,
+				"\tstatic gasHints: Record<string, bigint> = {{{}}};",
+				contract_def
+					.gas_hints
+					.iter()
+					.format_with(", ", |(variant, gas), f| f(&format_args!(
+						"\"{}\": {}n",
+						variant.escape_default(),
+						gas
+					)))
+			)?;
+			writeln!(
+				contract_body,
+				"\t/** The size of the message once encoded, which the fees may depend on. */"
+			)?;
+			writeln!(contract_body, "\tstatic encodedSizeOf(msg: unknown): number {{")?;
+			writeln!(
+				contract_body,
+				"\t\treturn new TextEncoder().encode(JSON.stringify(msg)).length;"
+			)?;
+			writeln!(contract_body, "\t}}")?;
+			if !contract_def.error_codes.is_empty() {
+				writeln!(
+					contract_body,
+					"\t/** Reads the `[E{{code}}]` the contract prefixes its errors with out of a failed transaction's error. */"
+				)?;
+				writeln!(
+					contract_body,
+					"\tstatic errorCodeOf(error: unknown): {}ErrorCode | undefined {{",
+					error_type_name
+				)?;
+				writeln!(
+					contract_body,
+					"\t\tconst match = /\\[E(\\d+)\\]/.exec(error instanceof Error ? error.message : String(error));"
+				)?;
+				writeln!(
+					contract_body,
+					"\t\treturn match && {}ErrorNames.has(Number(match[1])) ? Number(match[1]) : undefined;",
+					error_type_name
+				)?;
+				writeln!(contract_body, "\t}}")?;
+			}
 			if let Some((name, version)) = &contract_def.name_and_version {
 				writeln!(
 					contract_body,
@@ -559,51 +959,53 @@ impl CrownfiSdkMaker {
 				let query_def = self
 					.root_schema
 					.definitions
-					.get(query_type.as_ref())
+					.get(query_type.as_str())
 					.and_then(|s| s.as_object())
 					.expect("types referenced by contract_def should exist in root_schema.definitions");
 				self.codegen_contract_methods(
 					&mut contract_body,
 					&mut types_required,
-					query_type.as_ref(),
+					query_type,
 					query_def,
-					MethodGenType::Query(&contract_def.query_enum_varient_to_return_type),
+					MethodGenType::Query(&contract_def.query_responses),
+					&contract_def.gas_hints,
 				)?;
 			}
 			if let Some(execute_type) = &contract_def.execute_type {
 				let query_def = self
 					.root_schema
 					.definitions
-					.get(execute_type.as_ref())
+					.get(execute_type.as_str())
 					.and_then(|s| s.as_object())
 					.expect("types referenced by contract_def should exist in root_schema.definitions");
 				self.codegen_contract_methods(
 					&mut contract_body,
 					&mut types_required,
-					execute_type.as_ref(),
+					execute_type,
 					query_def,
 					MethodGenType::Execute,
+					&contract_def.gas_hints,
 				)?;
 			}
 			if let Some(cw20_hook_type) = &contract_def.cw20_hook_type {
 				let query_def = self
 					.root_schema
 					.definitions
-					.get(cw20_hook_type.as_ref())
+					.get(cw20_hook_type.as_str())
 					.and_then(|s| s.as_object())
 					.expect("types referenced by contract_def should exist in root_schema.definitions");
 				self.codegen_contract_methods(
 					&mut contract_body,
 					&mut types_required,
-					cw20_hook_type.as_ref(),
+					cw20_hook_type,
 					query_def,
 					MethodGenType::Cw20Hook,
+					&contract_def.gas_hints,
 				)?;
 			}
 
 			writeln!(contract_body, "}}")?;
-			files_list.push([contract_name, ".ts"].join(""));
-			output_path.push(files_list.last().expect("literally just pushed this"));
+			files_list.push([contract_name, ".ts"].concat());
 			let modules_to_types = {
 				let mut modules_to_types = BTreeMap::<Arc<str>, BTreeSet<Arc<str>>>::new();
 				for type_required in types_required.iter().cloned() {
@@ -621,9 +1023,7 @@ impl CrownfiSdkMaker {
 				modules_to_types
 			};
 
-			let mut out_file = fs::File::create(&output_path)?;
-			output_path.pop();
-
+			let mut out_file = Vec::<u8>::new();
 			out_file.write_all(TYPESCRIPT_OUTPUT_DISCLAIMER_COMMENT.as_bytes())?;
 			for (module, imported_types) in modules_to_types.iter() {
 				writeln!(
@@ -634,33 +1034,565 @@ impl CrownfiSdkMaker {
 				)?;
 			}
 			out_file.write_all(&contract_body)?;
-			out_file.sync_all()?;
+			files.insert(
+				files_list.last().expect("literally just pushed this").clone(),
+				String::from_utf8(out_file).expect("we only write valid UTF-8"),
+			);
 			types_required.clear();
 			contract_body.clear();
 		}
 		Ok(())
 	}
 
-	pub fn generate_code<P: Into<PathBuf>>(&self, out_dir: P) -> Result<(), SdkMakerError> {
-		let mut output_path: PathBuf = out_dir.into();
-		fs::create_dir_all(&output_path)?;
+	/// Generates the TypeScript SDK, see [CrownfiSdkMaker::generate_in_memory].
+	pub fn generate_in_memory(&self) -> Result<GeneratedSdk, SdkMakerError> {
+		let mut files = BTreeMap::new();
 		let mut files_list = Vec::new();
-		self.codegen_types(&mut output_path, &mut files_list)?;
-		self.codegen_contracts(&mut output_path, &mut files_list)?;
 
-		output_path.push("index.ts");
-		let mut out_file = fs::File::create(&output_path)?;
-		output_path.pop();
-		out_file.write_all(TYPESCRIPT_OUTPUT_DISCLAIMER_COMMENT.as_bytes())?;
+		let mut types_file = Vec::<u8>::new();
+		types_file.write_all(TYPESCRIPT_OUTPUT_DISCLAIMER_COMMENT.as_bytes())?;
+		types_emitter::codegen_types(&mut types_file, &self.root_schema)?;
+		files_list.push("types.ts".to_string());
+		files.insert(
+			"types.ts".to_string(),
+			String::from_utf8(types_file).expect("we only write valid UTF-8"),
+		);
+
+		self.codegen_contracts(&mut files, &mut files_list)?;
+
+		if !self.networks.is_empty() {
+			let manifest = DeployManifest::placeholder(self.contracts.keys().map(String::as_str), &self.networks);
+			let mut manifest_file = Vec::<u8>::new();
+			manifest_file.write_all(TYPESCRIPT_OUTPUT_DISCLAIMER_COMMENT.as_bytes())?;
+			codegen_manifest(&mut manifest_file, &manifest, &self.networks)?;
+			files_list.push("manifest.ts".to_string());
+			files.insert(
+				"manifest.ts".to_string(),
+				String::from_utf8(manifest_file).expect("we only write valid UTF-8"),
+			);
+		}
+
+		let mut index_file = Vec::<u8>::new();
+		index_file.write_all(TYPESCRIPT_OUTPUT_DISCLAIMER_COMMENT.as_bytes())?;
 		for mut file_name in files_list.into_iter() {
 			if file_name.ends_with(".ts") {
 				file_name.truncate(file_name.len() - 2);
 				file_name.push_str("js");
 			}
-			writeln!(out_file, "export * from \"./{}\";", file_name.escape_default())?;
+			writeln!(index_file, "export * from \"./{}\";", file_name.escape_default())?;
 		}
-		out_file.sync_all()?;
+		files.insert(
+			"index.ts".to_string(),
+			String::from_utf8(index_file).expect("we only write valid UTF-8"),
+		);
+		Ok(GeneratedSdk { files })
+	}
+}
 
-		Ok(())
+#[cfg(test)]
+// The derives on FixtureExecuteMsg use its deprecated variant
+#[allow(deprecated)]
+mod tests {
+	use super::*;
+	use crate::manifest::ContractDeployment;
+	use cosmwasm_schema::{cw_serde, QueryResponses};
+	use cosmwasm_std::Binary;
+
+	#[cw_serde]
+	pub struct FixtureInstantiateMsg {
+		pub admin: String,
+	}
+
+	#[cw_serde]
+	pub enum FixtureExecuteMsg {
+		/// Does the thing
+		DoThing {
+			amount: u64,
+			memo: Option<String>,
+		},
+		Reset,
+		/// Does the old thing.
+		///
+		/// Deprecated: use `do_thing` instead, this ends with */ for good measure
+		/// @since v1.0.0
+		/// @example
+		/// contract.buildOldThingIx({amount: 1})
+		OldThing {
+			/// The amount of things
+			///
+			/// Must be non-zero
+			amount: u64,
+		},
+		#[deprecated]
+		OlderThing {},
+	}
+
+	#[cw_serde]
+	#[derive(QueryResponses)]
+	pub enum FixtureQueryMsg {
+		#[returns(FixtureConfigResponse)]
+		Config {},
+	}
+
+	#[cw_serde]
+	pub struct FixtureConfigResponse {
+		pub admin: String,
+		pub things_done: Vec<u64>,
+	}
+
+	#[cw_serde]
+	pub struct BatchQueryMsg {
+		pub queries: Vec<Binary>,
+	}
+
+	#[cw_serde]
+	pub struct BatchQueryResponse {
+		pub responses: Vec<Result<Binary, String>>,
+	}
+
+	#[cw_serde]
+	#[derive(QueryResponses)]
+	pub enum BatchingQueryMsg {
+		#[returns(FixtureConfigResponse)]
+		Config {},
+		#[returns(BatchQueryResponse)]
+		Batch(BatchQueryMsg),
+	}
+
+	#[cw_serde]
+	pub enum FixtureHookMsg {
+		Deposit {},
+		Swap { min_out: u64, recipient: Option<String> },
+	}
+
+	#[cw_serde]
+	pub enum FixtureMigrateMsg {}
+
+	fn fixture_sdk_maker() -> CrownfiSdkMaker {
+		let mut sdk_maker = CrownfiSdkMaker::new();
+		sdk_maker
+			.add_contract_with_version::<
+				FixtureInstantiateMsg,
+				FixtureExecuteMsg,
+				FixtureQueryMsg,
+				FixtureMigrateMsg,
+				FixtureMigrateMsg,
+				FixtureHookMsg,
+			>("fixture_contract", Some(("fixture".into(), "1.0.0".into())))
+			.unwrap();
+		sdk_maker
+	}
+
+	#[test]
+	fn generate_code_matches_in_memory() {
+		let sdk_maker = fixture_sdk_maker();
+		let generated_sdk = sdk_maker.generate_in_memory().unwrap();
+		assert_eq!(
+			generated_sdk.files().keys().collect::<Vec<_>>(),
+			["fixture_contract.ts", "index.ts", "types.ts"]
+		);
+		assert!(generated_sdk.get("types.ts").unwrap().contains(
+			"export interface FixtureConfigResponse {\n\t\"admin\": string;\n\t\"things_done\": number[];\n}"
+		));
+		assert!(generated_sdk
+			.get("fixture_contract.ts")
+			.unwrap()
+			.contains("queryConfig(): Promise<FixtureConfigResponse> {"));
+		assert!(generated_sdk
+			.get("index.ts")
+			.unwrap()
+			.ends_with("export * from \"./types.js\";\nexport * from \"./fixture_contract.js\";\n"));
+
+		let out_dir = std::env::temp_dir().join(format!("crownfi-sdk-autogen-test-{}", std::process::id()));
+		sdk_maker.generate_code(&out_dir).unwrap();
+		for (file_name, contents) in generated_sdk.files() {
+			assert_eq!(
+				fs::read(out_dir.join(file_name)).unwrap(),
+				contents.as_bytes(),
+				"{}",
+				file_name
+			);
+		}
+		assert_eq!(fs::read_dir(&out_dir).unwrap().count(), generated_sdk.files().len());
+		fs::remove_dir_all(&out_dir).unwrap();
+	}
+
+	#[test]
+	fn schema_json_round_trip() {
+		let sdk_maker = fixture_sdk_maker();
+		let loaded_sdk_maker = CrownfiSdkMaker::from_schema_json(&sdk_maker.to_schema_json().unwrap()).unwrap();
+		assert_eq!(
+			loaded_sdk_maker.generate_in_memory().unwrap(),
+			sdk_maker.generate_in_memory().unwrap()
+		);
+	}
+
+	#[test]
+	fn batch_query_method() {
+		let mut sdk_maker = CrownfiSdkMaker::new();
+		sdk_maker
+			.add_contract::<
+				FixtureInstantiateMsg,
+				FixtureExecuteMsg,
+				BatchingQueryMsg,
+				FixtureMigrateMsg,
+				FixtureMigrateMsg,
+				FixtureHookMsg,
+			>("batching_contract")
+			.unwrap();
+		let generated_sdk = sdk_maker.generate_in_memory().unwrap();
+		let contract_file = generated_sdk.get("batching_contract.ts").unwrap();
+		assert!(contract_file.contains("import {fromBase64, fromUtf8, toBase64, toUtf8} from \"@cosmjs/encoding\";\n"));
+		assert!(contract_file.contains(
+			"\tasync queryBatch(msgs: BatchingQueryMsg[]): Promise<({\"Ok\": unknown} | {\"Err\": string})[]> {\n\
+			\t\tconst msg = {\"batch\": {\"queries\": msgs.map((msg) => toBase64(toUtf8(JSON.stringify(msg))))}} satisfies BatchingQueryMsg;\n\
+			\t\tconst result: BatchQueryResponse = await this.query(msg);\n\
+			\t\treturn result.responses.map((response) => \"Ok\" in response ? {\"Ok\": JSON.parse(fromUtf8(fromBase64(response.Ok)))} : response);\n\
+			\t}\n"
+		));
+		assert!(contract_file.contains("queryConfig(): Promise<FixtureConfigResponse> {"));
+		assert!(generated_sdk
+			.get("types.ts")
+			.unwrap()
+			.contains("export interface BatchQueryResponse {\n\t\"responses\": ResultOf_BinaryOr_String[];\n}"));
+	}
+
+	#[test]
+	fn jsdoc_tags() {
+		let generated_sdk = fixture_sdk_maker().generate_in_memory().unwrap();
+		let contract_file = generated_sdk.get("fixture_contract.ts").unwrap();
+		assert!(contract_file.contains(
+			"\t/**\n\
+			\t * Does the old thing.\n\
+			\t *\n\
+			\t * @deprecated use `do_thing` instead, this ends with * / for good measure\n\
+			\t * @since v1.0.0\n\
+			\t * @example contract.buildOldThingIx({amount: 1})\n\
+			\t */\n\
+			\tbuildOldThingIx(args: {\n\
+			\t\t/**\n\
+			\t\t * The amount of things\n\
+			\t\t *\n\
+			\t\t * Must be non-zero\n\
+			\t\t */\n\
+			\t\t\"amount\": number\n"
+		));
+		assert!(contract_file.contains("\t/** @deprecated */\n\tbuildOlderThingIx("));
+		assert!(contract_file.contains("\t/** Does the thing */\n\tbuildDoThingIx("));
+	}
+
+	#[test]
+	fn cw20_hook_method() {
+		let generated_sdk = fixture_sdk_maker().generate_in_memory().unwrap();
+		let contract_file = generated_sdk.get("fixture_contract.ts").unwrap();
+		assert!(contract_file.contains("import {toBase64, toUtf8} from \"@cosmjs/encoding\";\n"));
+		assert!(contract_file.contains(
+			"\tbuildSwapCw20Ix(tokenContractOrUnifiedDenom: string, amount: string | bigint | number, args: Extract<FixtureHookMsg, {\"swap\": unknown}>[\"swap\"]): ExecuteInstruction {\n\
+			\t\tconst msg = {\"swap\": args} satisfies FixtureHookMsg;\n\
+			\t\tif (tokenContractOrUnifiedDenom.startsWith(\"erc20/\")) {\n\
+			\t\t\tthrow new Error(\"Cw20 hooks can't be used with ERC20 tokens, send \" + tokenContractOrUnifiedDenom + \" through its CW20 pointer contract instead\");\n\
+			\t\t}\n\
+			\t\treturn {\n\
+			\t\t\tcontractAddress: tokenContractOrUnifiedDenom.startsWith(\"cw20/\") ? tokenContractOrUnifiedDenom.substring(5) : tokenContractOrUnifiedDenom,\n\
+			\t\t\tmsg: {\"send\": {\"contract\": this.address, \"amount\": amount.toString(), \"msg\": toBase64(toUtf8(JSON.stringify(msg)))}}\n\
+			\t\t};\n\
+			\t}\n"
+		));
+		assert!(contract_file.contains(
+			"\tbuildDepositCw20Ix(tokenContractOrUnifiedDenom: string, amount: string | bigint | number): ExecuteInstruction {\n\
+			\t\tconst msg = {\"deposit\": {}} satisfies FixtureHookMsg;\n"
+		));
+	}
+
+	#[test]
+	fn gas_hints() {
+		let mut sdk_maker = fixture_sdk_maker();
+		let unhinted_file = sdk_maker
+			.generate_in_memory()
+			.unwrap()
+			.get("fixture_contract.ts")
+			.unwrap()
+			.to_string();
+		assert!(unhinted_file.contains("\tstatic gasHints: Record<string, bigint> = {};\n"));
+		assert!(unhinted_file.contains(
+			"\tstatic encodedSizeOf(msg: unknown): number {\n\
+			\t\treturn new TextEncoder().encode(JSON.stringify(msg)).length;\n\
+			\t}\n"
+		));
+		assert!(matches!(
+			sdk_maker.set_gas_hints("nonexistent_contract", &[]),
+			Err(SdkMakerError::UnknownContract(_))
+		));
+
+		sdk_maker
+			.set_gas_hints("fixture_contract", &[("do_thing", 450000), ("swap", 600000)])
+			.unwrap();
+		let generated_sdk = sdk_maker.generate_in_memory().unwrap();
+		let contract_file = generated_sdk.get("fixture_contract.ts").unwrap();
+		assert!(contract_file
+			.contains("\tstatic gasHints: Record<string, bigint> = {\"do_thing\": 450000n, \"swap\": 600000n};\n"));
+		assert!(contract_file.contains(
+			"): ExecuteInstruction & {gasHint: bigint} {\n\
+			\t\tconst msg = {\"do_thing\": args} satisfies FixtureExecuteMsg;\n\
+			\t\treturn Object.assign(this.executeIx(msg, funds), {gasHint: 450000n});\n\
+			\t}\n"
+		));
+		assert!(contract_file.contains(
+			"\t\t\tmsg: {\"send\": {\"contract\": this.address, \"amount\": amount.toString(), \"msg\": toBase64(toUtf8(JSON.stringify(msg)))}},\n\
+			\t\t\tgasHint: 600000n\n\
+			\t\t};\n"
+		));
+		// Methods without a hint are unchanged
+		for method in ["buildResetIx(", "buildOldThingIx(", "buildDepositCw20Ix("] {
+			let method_start = |file: &str| {
+				file[file.find(method).unwrap()..]
+					.split("\n\t}\n")
+					.next()
+					.unwrap()
+					.to_string()
+			};
+			assert_eq!(method_start(contract_file), method_start(&unhinted_file), "{}", method);
+		}
+
+		let loaded_sdk_maker = CrownfiSdkMaker::from_schema_json(&sdk_maker.to_schema_json().unwrap()).unwrap();
+		assert_eq!(loaded_sdk_maker.generate_in_memory().unwrap(), generated_sdk);
+		let model_json = sdk_maker.export_model().to_json().unwrap();
+		let model_value: serde_json::Value = serde_json::from_str(&model_json).unwrap();
+		assert_eq!(
+			model_value["contracts"]["fixture_contract"]["gas_hints"],
+			serde_json::json!({"do_thing": 450000, "swap": 600000})
+		);
+		assert_eq!(
+			SdkModel::from_json(&model_json).unwrap().generate_in_memory().unwrap(),
+			generated_sdk
+		);
+	}
+
+	#[test]
+	fn error_codes() {
+		let mut sdk_maker = fixture_sdk_maker();
+		let uncoded_file = sdk_maker
+			.generate_in_memory()
+			.unwrap()
+			.get("fixture_contract.ts")
+			.unwrap()
+			.to_string();
+		assert!(!uncoded_file.contains("ErrorCode"));
+		assert!(matches!(
+			sdk_maker.add_contract_errors("nonexistent_contract", &[]),
+			Err(SdkMakerError::UnknownContract(_))
+		));
+
+		sdk_maker
+			.add_contract_errors(
+				"fixture_contract",
+				&[("Unauthorized", 1001), ("InsufficientFunds", 1002), ("Std", 1)],
+			)
+			.unwrap();
+		let generated_sdk = sdk_maker.generate_in_memory().unwrap();
+		let contract_file = generated_sdk.get("fixture_contract.ts").unwrap();
+		assert!(contract_file.contains(
+			"export enum FixtureContractErrorCode {\n\
+			\tStd = 1,\n\
+			\tUnauthorized = 1001,\n\
+			\tInsufficientFunds = 1002,\n\
+			}\n\
+			export const FixtureContractErrorNames: ReadonlyMap<number, string> = \
+			new Map([[1, \"Std\"], [1001, \"Unauthorized\"], [1002, \"InsufficientFunds\"]]);\n\
+			export class FixtureContractContract<"
+		));
+		assert!(contract_file.contains(
+			"\tstatic errorCodeOf(error: unknown): FixtureContractErrorCode | undefined {\n\
+			\t\tconst match = /\\[E(\\d+)\\]/.exec(error instanceof Error ? error.message : String(error));\n\
+			\t\treturn match && FixtureContractErrorNames.has(Number(match[1])) ? Number(match[1]) : undefined;\n\
+			\t}\n"
+		));
+
+		let loaded_sdk_maker = CrownfiSdkMaker::from_schema_json(&sdk_maker.to_schema_json().unwrap()).unwrap();
+		assert_eq!(loaded_sdk_maker.generate_in_memory().unwrap(), generated_sdk);
+		let model_json = sdk_maker.export_model().to_json().unwrap();
+		assert_eq!(
+			SdkModel::from_json(&model_json).unwrap().generate_in_memory().unwrap(),
+			generated_sdk
+		);
+	}
+
+	#[test]
+	fn deploy_manifest() {
+		let mut sdk_maker = fixture_sdk_maker();
+		assert!(sdk_maker.generate_in_memory().unwrap().get("manifest.ts").is_none());
+		sdk_maker
+			.add_contract::<
+				FixtureInstantiateMsg,
+				FixtureExecuteMsg,
+				BatchingQueryMsg,
+				FixtureMigrateMsg,
+				FixtureMigrateMsg,
+				FixtureHookMsg,
+			>("batching_contract")
+			.unwrap()
+			.set_networks(&["pacific-1", "atlantic-2"]);
+		let generated_sdk = sdk_maker.generate_in_memory().unwrap();
+		assert_eq!(
+			generated_sdk.get("manifest.ts").unwrap(),
+			[
+				TYPESCRIPT_OUTPUT_DISCLAIMER_COMMENT,
+				"import {QueryClient} from \"@cosmjs/stargate\";\n\
+				import {WasmExtension} from \"@cosmjs/cosmwasm-stargate\";\n\
+				import {BatchingContractContract} from \"./batching_contract.js\";\n\
+				import {FixtureContractContract} from \"./fixture_contract.js\";\n\
+				\n\
+				export type DeployNetworkId = \"pacific-1\" | \"atlantic-2\";\n\
+				export interface ContractDeployment {\n\
+				\tcodeId?: number;\n\
+				\taddress?: string;\n\
+				}\n\
+				export interface ContractManifestEntry {\n\
+				\tname: string;\n\
+				\tclassName: string;\n\
+				\tnetworks: Record<DeployNetworkId, ContractDeployment>;\n\
+				}\n\
+				export type DeployContractName = keyof typeof contractClasses;\n\
+				export type DeployManifest = Record<DeployContractName, ContractManifestEntry>;\n\
+				\n\
+				export const batchingContractManifest: ContractManifestEntry = {\"name\":\"batching_contract\",\"className\":\"BatchingContractContract\",\"networks\":{\"atlantic-2\":{},\"pacific-1\":{}}};\n\
+				export const fixtureContractManifest: ContractManifestEntry = {\"name\":\"fixture_contract\",\"className\":\"FixtureContractContract\",\"networks\":{\"atlantic-2\":{},\"pacific-1\":{}}};\n\
+				export const deployManifest: DeployManifest = {\n\
+				\t\"batching_contract\": batchingContractManifest,\n\
+				\t\"fixture_contract\": fixtureContractManifest,\n\
+				};\n\
+				\n\
+				const contractClasses = {\n\
+				\t\"batching_contract\": BatchingContractContract,\n\
+				\t\"fixture_contract\": FixtureContractContract,\n\
+				};\n\
+				export interface ContractClients<Q extends QueryClient & WasmExtension> {\n\
+				\t\"batching_contract\": BatchingContractContract<Q>;\n\
+				\t\"fixture_contract\": FixtureContractContract<Q>;\n\
+				}\n\
+				\n\
+				/**\n\
+				\x20* Creates the client for the named contract, using its address on the network in the manifest.\n\
+				\x20*\n\
+				\x20* @throws if the manifest doesn't have an address for the contract on the network\n\
+				\x20*/\n\
+				export function getContractClient<N extends DeployContractName, Q extends QueryClient & WasmExtension>(network: DeployNetworkId, name: N, client: Q, manifest: DeployManifest = deployManifest): ContractClients<Q>[N] {\n\
+				\tconst address = manifest[name]?.networks[network]?.address;\n\
+				\tif (address == null) {\n\
+				\t\tthrow new Error(\"The deploy manifest has no address for the \" + name + \" contract on \" + network);\n\
+				\t}\n\
+				\treturn new contractClasses[name](client, address) as ContractClients<Q>[N];\n\
+				}\n"
+			]
+			.concat()
+		);
+		assert!(generated_sdk
+			.get("index.ts")
+			.unwrap()
+			.ends_with("export * from \"./fixture_contract.js\";\nexport * from \"./manifest.js\";\n"));
+
+		// Deploy scripts fill in the same JSON shape
+		let mut manifest = sdk_maker.deploy_manifest();
+		manifest.contracts.get_mut("fixture_contract").unwrap().networks.insert(
+			"pacific-1".into(),
+			ContractDeployment {
+				code_id: Some(12),
+				address: Some("sei1fixture".into()),
+			},
+		);
+		let manifest_json = serde_json::to_value(&manifest).unwrap();
+		assert_eq!(
+			manifest_json["fixture_contract"]["networks"],
+			serde_json::json!({"atlantic-2": {}, "pacific-1": {"codeId": 12, "address": "sei1fixture"}})
+		);
+		assert_eq!(
+			serde_json::from_value::<DeployManifest>(manifest_json).unwrap(),
+			manifest
+		);
+		// The networks are part of the schema JSON and the exported model
+		assert_eq!(
+			CrownfiSdkMaker::from_schema_json(&sdk_maker.to_schema_json().unwrap())
+				.unwrap()
+				.generate_in_memory()
+				.unwrap(),
+			generated_sdk
+		);
+		assert_eq!(sdk_maker.export_model().generate_in_memory().unwrap(), generated_sdk);
+	}
+
+	#[test]
+	fn export_model() {
+		let mut sdk_maker = fixture_sdk_maker();
+		sdk_maker
+			.add_contract::<
+				FixtureInstantiateMsg,
+				FixtureExecuteMsg,
+				BatchingQueryMsg,
+				FixtureMigrateMsg,
+				FixtureMigrateMsg,
+				FixtureHookMsg,
+			>("batching_contract")
+			.unwrap();
+		let model_json = sdk_maker.export_model().to_json().unwrap();
+		assert_eq!(model_json, sdk_maker.export_model().to_json().unwrap());
+
+		let model_value: serde_json::Value = serde_json::from_str(&model_json).unwrap();
+		assert_eq!(model_value["format_version"], SDK_MODEL_FORMAT_VERSION);
+		assert_eq!(
+			model_value["contracts"],
+			serde_json::json!({
+				"batching_contract": {
+					"instantiate_type": "FixtureInstantiateMsg",
+					"execute_type": "FixtureExecuteMsg",
+					"query_type": "BatchingQueryMsg",
+					"query_responses": {
+						"batch": "BatchQueryResponse",
+						"config": "FixtureConfigResponse"
+					},
+					"migrate_type": "FixtureMigrateMsg",
+					"sudo_type": "FixtureMigrateMsg",
+					"cw20_hook_type": "FixtureHookMsg",
+					"name_and_version": null
+				},
+				"fixture_contract": {
+					"instantiate_type": "FixtureInstantiateMsg",
+					"execute_type": "FixtureExecuteMsg",
+					"query_type": "FixtureQueryMsg",
+					"query_responses": {
+						"config": "FixtureConfigResponse"
+					},
+					"migrate_type": "FixtureMigrateMsg",
+					"sudo_type": "FixtureMigrateMsg",
+					"cw20_hook_type": "FixtureHookMsg",
+					"name_and_version": ["fixture", "1.0.0"]
+				}
+			})
+		);
+		assert_eq!(
+			model_value["root_schema"]["definitions"]
+				.as_object()
+				.unwrap()
+				.keys()
+				.collect::<Vec<_>>(),
+			[
+				"BatchQueryMsg",
+				"BatchQueryResponse",
+				"BatchingQueryMsg",
+				"Binary",
+				"FixtureConfigResponse",
+				"FixtureExecuteMsg",
+				"FixtureHookMsg",
+				"FixtureInstantiateMsg",
+				"FixtureMigrateMsg",
+				"FixtureQueryMsg",
+				"Result_of_Binary_or_String"
+			]
+		);
+
+		// An external generator consuming the exported model sees exactly what the TypeScript generator sees
+		assert_eq!(
+			SdkModel::from_json(&model_json).unwrap().generate_in_memory().unwrap(),
+			sdk_maker.generate_in_memory().unwrap()
+		);
 	}
 }
@@ -238,7 +238,7 @@ pub(crate) fn schema_type_string(
 pub(crate) enum MethodGenType<'a> {
 	Instantiate,
 	Execute,
-	Query(&'a BTreeMap<Arc<str>, Arc<str>>),
+	Query(&'a BTreeMap<String, String>),
 	Migrate,
 	Sudo,
 	Cw20Hook,
@@ -282,7 +282,7 @@ impl MethodGenType<'_> {
 			}
 			MethodGenType::Execute => "this.executeIx(msg, funds)",
 			MethodGenType::Query(_) => "this.query(msg)",
-			MethodGenType::Cw20Hook => "this.executeIxCw20(msg, tokenContractOrUnifiedDenom, amount)",
+			MethodGenType::Cw20Hook => unreachable!("cw20 hook methods construct the Send message themselves"),
 		}
 	}
 	pub(crate) fn return_type(&self, enum_variant: &str) -> Arc<str> {
@@ -291,9 +291,10 @@ impl MethodGenType<'_> {
 				todo!("Unknown parent function for {:?}", self)
 			}
 			MethodGenType::Execute => "ExecuteInstruction".into(),
-			MethodGenType::Query(return_type_map) => {
-				return_type_map.get(enum_variant).cloned().unwrap_or("unknown".into())
-			}
+			MethodGenType::Query(return_type_map) => return_type_map
+				.get(enum_variant)
+				.map(|return_type| return_type.as_str().into())
+				.unwrap_or("unknown".into()),
 			MethodGenType::Cw20Hook => "ExecuteInstruction".into(),
 		}
 	}
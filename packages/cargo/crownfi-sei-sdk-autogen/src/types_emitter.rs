@@ -0,0 +1,325 @@
+use std::{borrow::Cow, io::Write};
+
+use lazy_regex::regex;
+use schemars::schema::{InstanceType, Metadata, RootSchema, Schema, SchemaObject, SingleOrVec};
+
+use crate::{
+	error::SdkMakerError,
+	strings_for_code::make_type_name,
+	struct_extentions::{SchemaStructExtentions, SingleOrVecStructExtentions},
+};
+
+/// Writes a JSDoc comment block, one ` * ` line per line of the description.
+///
+/// Lines starting with `Deprecated:` are turned into `@deprecated` tags so TypeScript tooling picks them up. As
+/// schemars joins the lines of each paragraph, tags such as `@since` and `@example` are moved back onto their own
+/// lines.
+pub(crate) fn write_doc_comment(output: &mut impl Write, indent: &str, description: &str) -> Result<(), SdkMakerError> {
+	let description = regex!(r"\*/").replace_all(description.trim(), "* /");
+	let description = regex!(r"(?mi)^[ \t]*deprecated:[ \t]*").replace_all(&description, "@deprecated ");
+	let description = regex!(r"[ \t]+(@(?:deprecated|since|example|see|remarks|returns|throws|default)\b)")
+		.replace_all(&description, "\n$1");
+	let description = description.trim();
+	if description.is_empty() {
+		return Ok(());
+	}
+	if !description.contains('\n') {
+		writeln!(output, "{indent}/** {description} */")?;
+		return Ok(());
+	}
+	writeln!(output, "{indent}/**")?;
+	for line in description.lines() {
+		if line.is_empty() {
+			writeln!(output, "{indent} *")?;
+		} else {
+			writeln!(output, "{indent} * {line}")?;
+		}
+	}
+	writeln!(output, "{indent} */")?;
+	Ok(())
+}
+
+/// Returns the description of the schema, with an `@deprecated` tag added if the schema is marked as deprecated (i.e.
+/// with `#[deprecated]`) but the description doesn't mention it.
+pub(crate) fn metadata_doc(metadata: Option<&Metadata>) -> Cow<'_, str> {
+	let Some(metadata) = metadata else {
+		return Cow::Borrowed("");
+	};
+	let description = metadata.description.as_deref().unwrap_or_default();
+	if metadata.deprecated && !regex!(r"(?mi)(^|\s)@deprecated\b|^\s*deprecated:").is_match(description) {
+		if description.is_empty() {
+			return Cow::Borrowed("@deprecated");
+		}
+		return Cow::Owned(format!("{description}\n@deprecated"));
+	}
+	Cow::Borrowed(description)
+}
+
+fn definition_reference(schema: &SchemaObject) -> Option<&str> {
+	schema
+		.reference
+		.as_ref()
+		.and_then(|ref_string| ref_string.strip_prefix("#/definitions/"))
+}
+
+fn is_plain_object(schema: &SchemaObject) -> bool {
+	schema.reference.is_none()
+		&& schema.subschemas.is_none()
+		&& schema.enum_values.is_none()
+		&& schema.const_value.is_none()
+		&& schema.object.is_some()
+		&& schema
+			.instance_type
+			.as_ref()
+			.and_then(|instance_type| instance_type.as_single())
+			.is_some_and(|instance_type| *instance_type == InstanceType::Object)
+}
+
+/// Wraps unions and intersections in parentheses so they can be used as array items
+fn array_item(type_string: String) -> String {
+	if type_string.contains(" | ") || type_string.contains(" & ") {
+		["(", &type_string, ")"].join("")
+	} else {
+		type_string
+	}
+}
+
+fn union(types: impl Iterator<Item = String>, separator: &str) -> String {
+	let mut types = types.collect::<Vec<_>>();
+	types.dedup();
+	match types.len() {
+		0 => "never".to_string(),
+		1 => types.pop().expect("length was just checked"),
+		_ => types.join(separator),
+	}
+}
+
+/// Converts the schema into a TypeScript type expression. `indent` is the indentation of the line the expression
+/// starts on, which is used when the expression spans multiple lines.
+fn ts_type_string(schema: &Schema, indent: &str) -> Result<String, SdkMakerError> {
+	let schema = match schema {
+		Schema::Bool(true) => return Ok("unknown".to_string()),
+		Schema::Bool(false) => return Ok("never".to_string()),
+		Schema::Object(schema) => schema,
+	};
+	if let Some(type_ref) = definition_reference(schema) {
+		return Ok(make_type_name(type_ref).into_owned());
+	}
+	if let Some(const_value) = schema.const_value.as_ref() {
+		return Ok(serde_json::to_string(const_value)?);
+	}
+	if let Some(enum_values) = schema.enum_values.as_ref() {
+		return Ok(union(
+			enum_values
+				.iter()
+				.map(serde_json::to_string)
+				.collect::<Result<Vec<_>, _>>()?
+				.into_iter(),
+			" | ",
+		));
+	}
+	if let Some(subschemas) = schema.subschemas.as_ref() {
+		if let Some(one_of) = subschemas.one_of.as_ref().or(subschemas.any_of.as_ref()) {
+			return Ok(union(
+				one_of
+					.iter()
+					.map(|sub_schema| ts_type_string(sub_schema, indent))
+					.collect::<Result<Vec<_>, _>>()?
+					.into_iter(),
+				" | ",
+			));
+		}
+		if let Some(all_of) = subschemas.all_of.as_ref() {
+			return Ok(union(
+				all_of
+					.iter()
+					.map(|sub_schema| ts_type_string(sub_schema, indent))
+					.collect::<Result<Vec<_>, _>>()?
+					.into_iter(),
+				" & ",
+			));
+		}
+	}
+	let Some(instance_types) = schema.instance_type.as_ref() else {
+		return Ok("unknown".to_string());
+	};
+	let mut result = Vec::new();
+	for instance_type in instance_types.iter() {
+		match instance_type {
+			InstanceType::Array if schema.array.is_some() => {
+				let array = schema.array.as_ref().expect("checked by the match guard");
+				match array.items.as_ref() {
+					None => result.push("unknown[]".to_string()),
+					Some(SingleOrVec::Single(item)) => {
+						let item = ts_type_string(item, indent)?;
+						if let Some(length) = array
+							.max_items
+							.filter(|max_items| *max_items == array.min_items.unwrap_or_default())
+						{
+							result.push(["[", &vec![item; length as usize].join(", "), "]"].join(""));
+						} else {
+							result.push([array_item(item).as_str(), "[]"].join(""));
+						}
+					}
+					Some(SingleOrVec::Vec(items)) => {
+						result.push(
+							[
+								"[",
+								&items
+									.iter()
+									.map(|item| ts_type_string(item, indent))
+									.collect::<Result<Vec<_>, _>>()?
+									.join(", "),
+								"]",
+							]
+							.join(""),
+						);
+					}
+				}
+			}
+			InstanceType::Object if schema.object.is_some() => {
+				let mut object_body = Vec::<u8>::new();
+				let inner_indent = [indent, "\t"].join("");
+				write_object_body(&mut object_body, schema, &inner_indent)?;
+				if object_body.is_empty() {
+					result.push("{}".to_string());
+					continue;
+				}
+				result.push(
+					[
+						"{\n",
+						&String::from_utf8(object_body).expect("we only write valid UTF-8"),
+						indent,
+						"}",
+					]
+					.join(""),
+				);
+			}
+			InstanceType::Object => {
+				result.push(["{\n", indent, "\t[k: string]: unknown;\n", indent, "}"].join(""));
+			}
+			InstanceType::Array => result.push("unknown[]".to_string()),
+			InstanceType::Null => result.push("null".to_string()),
+			InstanceType::Boolean => result.push("boolean".to_string()),
+			InstanceType::Number | InstanceType::Integer => result.push("number".to_string()),
+			InstanceType::String => result.push("string".to_string()),
+		}
+	}
+	Ok(union(result.into_iter(), " | "))
+}
+
+fn write_object_body(output: &mut impl Write, schema: &SchemaObject, indent: &str) -> Result<(), SdkMakerError> {
+	let object = schema.object.as_ref().expect("caller should have checked this");
+	for (key, value) in object.properties.iter() {
+		if let Some(value_object) = value.as_object() {
+			write_doc_comment(output, indent, &metadata_doc(value_object.metadata.as_deref()))?;
+		}
+		writeln!(
+			output,
+			"{indent}\"{}\"{}: {};",
+			key.escape_default(),
+			if object.required.contains(key) { "" } else { "?" },
+			ts_type_string(value, indent)?
+		)?;
+	}
+	match object.additional_properties.as_deref() {
+		None | Some(Schema::Bool(false)) => {}
+		Some(additional_properties) => {
+			writeln!(
+				output,
+				"{indent}[k: string]: {};",
+				ts_type_string(additional_properties, indent)?
+			)?;
+		}
+	}
+	Ok(())
+}
+
+/// Generates the TypeScript type definitions for everything in the root schema's definitions.
+///
+/// This is a much simpler emitter than json2ts, but it doesn't require spawning an external program, which means it
+/// can be used in environments without a filesystem or node.js installation.
+pub(crate) fn codegen_types(output: &mut impl Write, root_schema: &RootSchema) -> Result<(), SdkMakerError> {
+	for (definition_name, definition) in root_schema.definitions.iter() {
+		let type_name = make_type_name(definition_name);
+		writeln!(output)?;
+		if let Some(definition) = definition.as_object() {
+			write_doc_comment(output, "", &metadata_doc(definition.metadata.as_deref()))?;
+			if is_plain_object(definition) {
+				writeln!(output, "export interface {type_name} {{")?;
+				write_object_body(output, definition, "\t")?;
+				writeln!(output, "}}")?;
+				continue;
+			}
+		}
+		writeln!(output, "export type {type_name} = {};", ts_type_string(definition, "")?)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use schemars::{schema_for, JsonSchema};
+
+	/// An example struct
+	#[derive(JsonSchema)]
+	#[allow(dead_code)]
+	struct ExampleStruct {
+		/// A name
+		name: String,
+		count: Option<u32>,
+		pair: (u8, String),
+		items: Vec<ExampleEnum>,
+	}
+
+	#[derive(JsonSchema)]
+	#[serde(rename_all = "snake_case")]
+	#[allow(dead_code)]
+	enum ExampleEnum {
+		Unit,
+		Tuple(u64),
+		Named {
+			/// Multi-line
+			///
+			/// documentation */
+			value: bool,
+		},
+	}
+
+	#[test]
+	fn emits_types() {
+		let mut root_schema = schema_for!(ExampleStruct);
+		root_schema
+			.definitions
+			.insert("ExampleStruct".to_string(), Schema::Object(root_schema.schema.clone()));
+		let mut output = Vec::new();
+		codegen_types(&mut output, &root_schema).unwrap();
+		assert_eq!(
+			String::from_utf8(output).unwrap(),
+			"
+export type ExampleEnum = \"unit\" | {
+	\"tuple\": number;
+} | {
+	\"named\": {
+		/**
+		 * Multi-line
+		 *
+		 * documentation * /
+		 */
+		\"value\": boolean;
+	};
+};
+
+/** An example struct */
+export interface ExampleStruct {
+	\"count\"?: number | null;
+	\"items\": ExampleEnum[];
+	/** A name */
+	\"name\": string;
+	\"pair\": [number, string];
+}
+"
+		);
+	}
+}
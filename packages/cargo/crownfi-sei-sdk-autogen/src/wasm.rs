@@ -0,0 +1,12 @@
+use wasm_bindgen::prelude::*;
+
+use crate::sdk_maker::CrownfiSdkMaker;
+
+/// Generates the TypeScript SDK from the output of `CrownfiSdkMaker::to_schema_json`.
+///
+/// Returns a JSON object mapping file names to their contents.
+#[wasm_bindgen(js_name = generateSdk)]
+pub fn generate_sdk(schema_json: &str) -> Result<String, JsError> {
+	let generated_sdk = CrownfiSdkMaker::from_schema_json(schema_json)?.generate_in_memory()?;
+	Ok(serde_json::to_string(generated_sdk.files())?)
+}